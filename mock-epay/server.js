@@ -247,6 +247,112 @@ app.get('/api/query', (req, res) => {
   });
 });
 
+// 易支付标准 API：act=order 查单 / act=refund 退款
+app.all('/api.php', (req, res) => {
+  const params = { ...req.query, ...req.body };
+  const { act, pid, key, out_trade_no, money } = params;
+  const merchant = MERCHANT_CONFIG[pid];
+  if (!merchant || merchant.key !== key) {
+    return res.json({ code: -1, msg: '商户密钥错误' });
+  }
+
+  let order = null;
+  for (const value of paymentOrders.values()) {
+    if (value.out_trade_no === out_trade_no && value.pid === pid) {
+      order = value;
+      break;
+    }
+  }
+  if (!order) {
+    return res.json({ code: -1, msg: '订单不存在' });
+  }
+
+  if (act === 'order') {
+    return res.json({
+      code: 1,
+      msg: 'succ',
+      trade_no: order.trade_no,
+      out_trade_no: order.out_trade_no,
+      type: order.type,
+      money: order.money.toFixed(2),
+      status: order.status
+    });
+  }
+
+  if (act === 'refund') {
+    if (order.status !== 1) {
+      return res.json({ code: -1, msg: '订单未支付' });
+    }
+    const refundMoney = parseFloat(money);
+    if (!(refundMoney > 0) || refundMoney > order.money) {
+      return res.json({ code: -1, msg: '退款金额无效' });
+    }
+    order.status = 2;
+    order.refund_money = refundMoney;
+    console.log('订单已退款:', order);
+    return res.json({ code: 1, msg: '退款成功' });
+  }
+
+  res.json({ code: -1, msg: '不支持的操作' });
+});
+
+// 模拟银行转账到账：对应面板的 bank 通道（HMAC-SHA256 签名回调）
+const BANK_CONFIG = {
+  secret: process.env.BANK_WEBHOOK_SECRET || 'mock-bank-secret',
+  notifyUrl: process.env.BANK_NOTIFY_URL || 'http://localhost:18787/api/payment/callback/bank'
+};
+
+function bankSign(params, secret) {
+  const signStr = Object.keys(params)
+    .filter(k => k !== 'sign' && String(params[k]).trim() !== '')
+    .sort()
+    .map(k => `${k}=${params[k]}`)
+    .join('&');
+  return crypto.createHmac('sha256', secret).update(signStr).digest('hex');
+}
+
+// 收款说明页，面板配置 bank_pay_url=http://localhost:3001/bank/{trade_no}?amount={amount}
+app.get('/bank/:trade_no', (req, res) => {
+  const { trade_no } = req.params;
+  const amount = req.query.amount || '0.00';
+  res.send(`<!DOCTYPE html>
+<html lang="zh-CN"><head><meta charset="utf-8"><title>银行转账</title></head>
+<body>
+  <h3>请转账 ¥${amount}，备注订单号 ${trade_no}</h3>
+  <form method="post" action="/api/bank/confirm/${encodeURIComponent(trade_no)}">
+    <input type="hidden" name="amount" value="${amount}">
+    <button type="submit">模拟到账</button>
+  </form>
+</body></html>`);
+});
+
+// 模拟对账系统确认到账并发送签名回调
+app.post('/api/bank/confirm/:trade_no', async (req, res) => {
+  const params = {
+    trade_no: req.params.trade_no,
+    amount: parseFloat(req.body.amount || req.query.amount || 0).toFixed(2),
+    currency: 'CNY',
+    status: 'paid',
+    timestamp: Math.floor(Date.now() / 1000).toString()
+  };
+  params.sign = bankSign(params, BANK_CONFIG.secret);
+  console.log('发送银行到账通知:', BANK_CONFIG.notifyUrl, params);
+
+  try {
+    const response = await fetch(BANK_CONFIG.notifyUrl, {
+      method: 'POST',
+      headers: { 'Content-Type': 'application/json' },
+      body: JSON.stringify(params)
+    });
+    const result = await response.text();
+    console.log('通知响应:', response.status, result);
+    res.json({ success: result.trim() === 'success', response: result });
+  } catch (error) {
+    console.error('发送银行到账通知失败:', error);
+    res.status(502).json({ success: false, message: error.message });
+  }
+});
+
 // 健康检查接口
 app.get('/health', (req, res) => {
   res.status(200).json({
@@ -53,13 +53,83 @@ INSERT IGNORE INTO system_configs (`key`, value, description) VALUES
 ('telegram_ticket_group_id', '', 'Telegram 工单转发群组 ID（需开启论坛话题）'),
 ('rebate_rate', '0', '邀请返利比例（0-1之间，例如0.1表示10%）'),
 ('rebate_mode', 'every_order', '返利模式：first_order（首单）或 every_order（循环）'),
+('rebate_levels', '', '多级返利比例（逗号分隔，如 0.2,0.05,0.02，第 1 项为直接邀请人；为空时使用 rebate_rate 单级返利）'),
+('rebate_levels_recharge', '', '充值订单返利层级比例（为空时使用 rebate_levels）'),
+('rebate_levels_purchase', '', '套餐订单返利层级比例（为空时使用 rebate_levels；套餐单独配置优先）'),
+('rebate_invitee_cap', '0', '单个邀请人从同一被邀请人处可获得的返利上限（元，0 表示不限）'),
 ('rebate_withdraw_fee_rate', '0.05', '返利提现手续费比例（0-1之间，例如0.05=5%）'),
-('rebate_withdraw_min_amount', '200', '返利提现最低金额（元）');
+('rebate_withdraw_min_amount', '200', '返利提现最低金额（元）'),
+('subscription_auto_fallback', 'v2ray', '自动订阅未匹配 User-Agent 时的回退格式（v2ray/clash/quantumultx/singbox/shadowrocket/surge/xray）'),
+('device_limit_action', 'kick', '设备数量超限处理方式：record（仅记录）、kick（踢出多余 IP）、suspend（临时停用）'),
+('device_limit_window_minutes', '5', '设备数量统计窗口（分钟，按 online_ips.last_seen 计算）'),
+('device_limit_suspend_minutes', '30', '超限临时停用时长（分钟，仅 suspend 模式）'),
+('device_limit_notify_enabled', '1', '设备数量超限时是否通知用户（1 开启，0 关闭）'),
+('device_limit_notify_cooldown_minutes', '360', '同一用户超限通知冷却时间（分钟）'),
+('traffic_anomaly_enabled', '1', '是否检测节点流量上报异常（1 开启，0 关闭；关闭后全部直接计费）'),
+('traffic_anomaly_max_mbps', '10000', '单用户单次上报允许的最大平均速率（Mbps，按距上次上报的间隔换算字节上限）'),
+('traffic_anomaly_grace_minutes', '10', '账户或等级到期后的宽限时间（分钟），宽限期内的上报不按资格判定为异常'),
+('job_schedule_user_expiration_check', '* * * * *', '账号过期检查与消息队列调度（cron，北京时间，留空禁用；需 JOB_SCHEDULER_ENABLED=true）'),
+('job_schedule_daily_tasks', '0 0 * * *', '每日任务调度（cron，北京时间，留空禁用；需 JOB_SCHEDULER_ENABLED=true）'),
+('job_schedule_subscription_cleanup', '0 3 * * *', '订阅记录清理调度（cron，北京时间，留空禁用；需 JOB_SCHEDULER_ENABLED=true）'),
+('job_runs_retention_days', '30', '定时任务执行记录保留天数（由订阅记录清理任务一并清理，0 表示不清理）'),
+('job_schedule_device_limit_check', '*/5 * * * *', '设备数量限制检查调度（cron，北京时间，留空禁用；需 JOB_SCHEDULER_ENABLED=true）'),
+('payment_alipay', '', '支付宝通道使用的支付接口（epay/epusdt/bank，留空使用环境变量 PAYMENT_ALIPAY）'),
+('payment_wxpay', '', '微信支付通道使用的支付接口（epay/epusdt/bank，留空使用环境变量 PAYMENT_WXPAY）'),
+('payment_crypto', '', 'USDT 通道使用的支付接口（epay/epusdt/bank，留空使用环境变量 PAYMENT_CRYPTO）'),
+('payment_bank', '', '银行转账通道使用的支付接口（通常为 bank，留空禁用）'),
+('epay_api_url', '', '易支付网关地址（留空使用环境变量 EPAY_API_URL）'),
+('epay_pid', '', '易支付商户 ID（留空使用环境变量 EPAY_PID）'),
+('epay_key', '', '易支付商户密钥（留空使用环境变量 EPAY_KEY）'),
+('epay_notify_url', '', '易支付异步通知地址（留空使用环境变量 EPAY_NOTIFY_URL）'),
+('epay_return_url', '', '易支付完成跳转地址（留空使用环境变量 EPAY_RETURN_URL）'),
+('epay_payment_mode', '', '易支付下单模式：redirect 或 api（留空使用环境变量 EPAY_PAYMENT_MODE）'),
+('epusdt_api_url', '', 'epusdt 接口地址（留空使用环境变量 EPUSDT_API_URL）'),
+('epusdt_token', '', 'epusdt 签名 Token（留空使用环境变量 EPUSDT_TOKEN）'),
+('epusdt_notify_url', '', 'epusdt 异步通知地址（留空使用环境变量 EPUSDT_NOTIFY_URL）'),
+('epusdt_return_url', '', 'epusdt 完成跳转地址（留空使用环境变量 EPUSDT_RETURN_URL）'),
+('epusdt_trade_type', '', 'epusdt 收款网络（留空使用环境变量 EPUSDT_TRADE_TYPE，默认 usdt.trc20）'),
+('epusdt_timeout', '', 'epusdt 订单超时秒数（留空使用环境变量 EPUSDT_TIMEOUT，默认 600）'),
+('bank_webhook_secret', '', '银行转账到账回调的 HMAC-SHA256 签名密钥'),
+('bank_pay_url', '', '银行转账收款说明页地址，支持 {trade_no}、{amount}、{subject} 占位符'),
+('bank_instructions', '', '银行转账下单后展示给用户的说明文字'),
+('payment_reconcile_after_minutes', '10', '待支付订单创建多少分钟后开始向支付通道查单'),
+('payment_order_expire_minutes', '120', '待支付订单超过多少分钟仍未支付则标记为已过期'),
+('job_schedule_payment_reconcile', '*/10 * * * *', '待支付订单对账调度（cron，北京时间，留空禁用；需 JOB_SCHEDULER_ENABLED=true）'),
+('node_bandwidth_warn_percents', '80,95', '节点流量达到上限的百分比时通知管理员（逗号分隔，留空不告警）'),
+('subscription_abuse_enabled', '1', '是否检测订阅链接滥用（1 开启，0 关闭）'),
+('subscription_abuse_window_minutes', '1440', '订阅链接滥用统计窗口（分钟，按订阅拉取记录计算）'),
+('subscription_abuse_max_ips', '10', '窗口内单个订阅链接允许的最大不同 IP 数'),
+('subscription_abuse_max_networks', '5', '窗口内单个订阅链接允许的最大不同网段数（IPv4 /24、IPv6 /48，近似代替 ASN）'),
+('subscription_abuse_max_user_agents', '5', '窗口内单个订阅链接允许的最大不同 User-Agent 数'),
+('subscription_abuse_auto_reset', '0', '检测到滥用时是否自动重置订阅链接（1 开启，0 仅记录）'),
+('subscription_abuse_notify_enabled', '1', '检测到滥用时是否通知用户（1 开启，0 关闭）'),
+('subscription_abuse_cooldown_minutes', '1440', '同一订阅链接重复报告的冷却时间（分钟，不短于统计窗口）'),
+('job_schedule_subscription_abuse_check', '*/30 * * * *', '订阅链接滥用检测调度（cron，北京时间，留空禁用；需 JOB_SCHEDULER_ENABLED=true）');
+
+-- 插入默认订阅 User-Agent 映射
+INSERT IGNORE INTO subscription_ua_rules (pattern, format, priority, description) VALUES
+('clash-verge', 'clash', 100, 'Clash Verge'),
+('mihomo', 'clash', 100, 'mihomo 内核客户端'),
+('clash.meta', 'clash', 90, 'Clash Meta'),
+('stash', 'clash', 80, 'Stash'),
+('clash', 'clash', 50, 'Clash 系客户端'),
+('sing-box', 'singbox', 100, 'sing-box'),
+('sfa/', 'singbox', 90, 'sing-box Android'),
+('sfi/', 'singbox', 90, 'sing-box iOS'),
+('sfm/', 'singbox', 90, 'sing-box macOS'),
+('hiddify', 'singbox', 80, 'Hiddify'),
+('surge', 'surge', 100, 'Surge'),
+('quantumult', 'quantumultx', 100, 'Quantumult X'),
+('shadowrocket', 'shadowrocket', 100, 'Shadowrocket'),
+('v2rayng', 'v2ray', 100, 'v2rayNG'),
+('v2rayn', 'v2ray', 90, 'v2rayN'),
+('v2box', 'v2ray', 80, 'V2Box'),
+('xray', 'xray', 60, 'Xray 原生配置');
 
 -- 插入默认审计规则
-INSERT IGNORE INTO audit_rules (name, rule, description) VALUES
-('种子下载', 'regexp:.*\\.torrent', '种子文件'),
-('成人内容', 'regexp:.*porn.*', '成人内容');
+INSERT IGNORE INTO audit_rules (name, rule_type, rule, description) VALUES
+('种子下载', 'regex', '.*\\.torrent', '种子文件'),
+('成人内容', 'regex', '.*porn.*', '成人内容');
 
 -- 插入默认白名单
 INSERT IGNORE INTO white_list (rule, description) VALUES
@@ -0,0 +1,22 @@
+-- 内置任务调度器：执行记录表
+CREATE TABLE IF NOT EXISTS job_runs (
+  id BIGINT AUTO_INCREMENT PRIMARY KEY COMMENT '任务执行记录 ID',
+  job_name VARCHAR(64) NOT NULL COMMENT '任务名称（userExpirationCheck/dailyTasks 等）',
+  trigger_type VARCHAR(16) NOT NULL DEFAULT 'schedule' COMMENT '触发方式（schedule/manual/cli）',
+  scheduled_at DATETIME COMMENT '调度时间点（仅定时触发，用于多实例去重）',
+  instance_id VARCHAR(64) NOT NULL DEFAULT '' COMMENT '执行实例标识',
+  status VARCHAR(16) NOT NULL DEFAULT 'running' COMMENT '状态（running/success/failed）',
+  error_message TEXT COMMENT '失败原因',
+  started_at DATETIME DEFAULT CURRENT_TIMESTAMP COMMENT '开始时间',
+  finished_at DATETIME COMMENT '结束时间',
+  duration_ms BIGINT NOT NULL DEFAULT 0 COMMENT '耗时（毫秒）',
+  UNIQUE KEY uk_job_runs_slot (job_name, scheduled_at),
+  INDEX idx_job_runs_status (job_name, status),
+  INDEX idx_job_runs_started_at (started_at)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci COMMENT='定时任务执行记录';
+
+-- 调度表达式（已存在则忽略）
+INSERT IGNORE INTO system_configs (`key`, value, description) VALUES
+('job_schedule_user_expiration_check', '* * * * *', '账号过期检查与消息队列调度（cron，北京时间，留空禁用；需 JOB_SCHEDULER_ENABLED=true）'),
+('job_schedule_daily_tasks', '0 0 * * *', '每日任务调度（cron，北京时间，留空禁用；需 JOB_SCHEDULER_ENABLED=true）'),
+('job_schedule_subscription_cleanup', '0 3 * * *', '订阅记录清理调度（cron，北京时间，留空禁用；需 JOB_SCHEDULER_ENABLED=true）');
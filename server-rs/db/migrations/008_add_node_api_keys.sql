@@ -0,0 +1,14 @@
+-- 节点独立 API 密钥（支持轮换宽限期）
+ALTER TABLE nodes
+  ADD COLUMN api_key_hash VARCHAR(64) NULL COMMENT '节点独立 API 密钥哈希（SHA-256）';
+
+ALTER TABLE nodes
+  ADD COLUMN api_key_prev_hash VARCHAR(64) NULL COMMENT '轮换前的旧密钥哈希（宽限期内仍有效）';
+
+ALTER TABLE nodes
+  ADD COLUMN api_key_prev_expires_at DATETIME NULL COMMENT '旧密钥宽限期截止时间';
+
+ALTER TABLE nodes
+  ADD COLUMN api_key_rotated_at DATETIME NULL COMMENT '最近一次生成/轮换密钥时间';
+
+-- 升级说明：已有节点在生成独立密钥前需设置 NODE_API_KEY_LEGACY_FALLBACK=true 继续使用全局 NODE_API_KEY
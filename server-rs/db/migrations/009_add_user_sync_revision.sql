@@ -0,0 +1,76 @@
+-- 节点用户增量同步（get_users?since=<revision>）
+ALTER TABLE users
+  ADD COLUMN sync_revision BIGINT NOT NULL DEFAULT 0 COMMENT '节点同步版本号（影响节点用户列表的字段变化时递增）';
+
+ALTER TABLE nodes
+  ADD COLUMN users_sync_floor BIGINT NOT NULL DEFAULT 0 COMMENT '低于该版本号的增量同步需改为全量（节点等级/配置变更时更新）';
+
+CREATE INDEX idx_users_sync_revision ON users (sync_revision);
+
+CREATE TABLE IF NOT EXISTS sync_revisions (
+  name VARCHAR(64) PRIMARY KEY COMMENT '版本序列名称',
+  revision BIGINT NOT NULL DEFAULT 0 COMMENT '当前版本号'
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci COMMENT='全局单调递增版本序列';
+
+INSERT IGNORE INTO sync_revisions (name, revision) VALUES ('users', 0);
+
+CREATE TABLE IF NOT EXISTS user_sync_tombstones (
+  user_id BIGINT PRIMARY KEY COMMENT '已删除的用户 ID',
+  revision BIGINT NOT NULL COMMENT '删除时的同步版本号',
+  deleted_at DATETIME DEFAULT CURRENT_TIMESTAMP COMMENT '删除时间',
+  INDEX idx_user_sync_tombstones_revision (revision)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci COMMENT='用户删除记录（供节点增量同步）';
+
+-- 节点增量同步：影响节点用户列表的字段变化时递增 users.sync_revision
+DELIMITER $$
+
+CREATE TRIGGER IF NOT EXISTS trg_users_sync_revision_insert
+BEFORE INSERT ON users
+FOR EACH ROW
+BEGIN
+  UPDATE sync_revisions SET revision = LAST_INSERT_ID(revision + 1) WHERE name = 'users';
+  SET NEW.sync_revision = LAST_INSERT_ID();
+END$$
+
+CREATE TRIGGER IF NOT EXISTS trg_users_sync_revision_update
+BEFORE UPDATE ON users
+FOR EACH ROW
+BEGIN
+  IF NOT (NEW.uuid <=> OLD.uuid)
+    OR NOT (NEW.passwd <=> OLD.passwd)
+    OR NOT (NEW.status <=> OLD.status)
+    OR NOT (NEW.class <=> OLD.class)
+    OR NOT (NEW.expire_time <=> OLD.expire_time)
+    OR NOT (NEW.class_expire_time <=> OLD.class_expire_time)
+    OR NOT (NEW.speed_limit <=> OLD.speed_limit)
+    OR NOT (NEW.device_limit <=> OLD.device_limit)
+    OR NOT (NEW.tcp_limit <=> OLD.tcp_limit)
+    OR NOT ((NEW.transfer_enable > NEW.transfer_total) <=> (OLD.transfer_enable > OLD.transfer_total))
+  THEN
+    UPDATE sync_revisions SET revision = LAST_INSERT_ID(revision + 1) WHERE name = 'users';
+    SET NEW.sync_revision = LAST_INSERT_ID();
+  END IF;
+END$$
+
+CREATE TRIGGER IF NOT EXISTS trg_users_sync_revision_delete
+AFTER DELETE ON users
+FOR EACH ROW
+BEGIN
+  UPDATE sync_revisions SET revision = LAST_INSERT_ID(revision + 1) WHERE name = 'users';
+  REPLACE INTO user_sync_tombstones (user_id, revision) VALUES (OLD.id, LAST_INSERT_ID());
+END$$
+
+CREATE TRIGGER IF NOT EXISTS trg_nodes_users_sync_floor
+BEFORE UPDATE ON nodes
+FOR EACH ROW
+BEGIN
+  IF NOT (NEW.node_class <=> OLD.node_class)
+    OR NOT (NEW.type <=> OLD.type)
+    OR NOT (NEW.node_config <=> OLD.node_config)
+  THEN
+    UPDATE sync_revisions SET revision = LAST_INSERT_ID(revision + 1) WHERE name = 'users';
+    SET NEW.users_sync_floor = LAST_INSERT_ID();
+  END IF;
+END$$
+
+DELIMITER ;
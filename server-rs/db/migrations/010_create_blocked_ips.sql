@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS blocked_ips (
+  id BIGINT AUTO_INCREMENT PRIMARY KEY COMMENT '封禁记录 ID',
+  ip VARCHAR(64) NOT NULL COMMENT '封禁 IP 或 CIDR 网段',
+  reason VARCHAR(255) COMMENT '封禁原因',
+  expires_at DATETIME COMMENT '到期时间（为空表示永久）',
+  created_by BIGINT COMMENT '操作管理员 ID',
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP COMMENT '创建时间',
+  updated_at DATETIME DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP COMMENT '更新时间',
+  UNIQUE KEY uk_blocked_ips_ip (ip),
+  INDEX idx_blocked_ips_expires_at (expires_at)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci COMMENT='IP 封禁列表（面板认证拦截并下发节点）';
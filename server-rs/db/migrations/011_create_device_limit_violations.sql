@@ -0,0 +1,29 @@
+-- 设备数量限制：超限记录表
+CREATE TABLE IF NOT EXISTS device_limit_violations (
+  id BIGINT AUTO_INCREMENT PRIMARY KEY COMMENT '记录 ID',
+  user_id BIGINT NOT NULL COMMENT '用户 ID',
+  device_limit INT NOT NULL DEFAULT 0 COMMENT '检测时的设备数量限制',
+  ip_count INT NOT NULL DEFAULT 0 COMMENT '检测到的在线 IP 数',
+  ips TEXT COMMENT '在线 IP 列表（JSON）',
+  action VARCHAR(16) NOT NULL DEFAULT 'kick' COMMENT '处理方式（record/kick/suspend）',
+  kicked_ips TEXT COMMENT '被踢出的 IP 列表（JSON）',
+  suspended_until DATETIME COMMENT '临时停用截止时间',
+  released_at DATETIME COMMENT '停用解除时间',
+  notified TINYINT NOT NULL DEFAULT 0 COMMENT '是否已通知用户',
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP COMMENT '检测时间',
+  INDEX idx_device_limit_violations_user (user_id, created_at),
+  INDEX idx_device_limit_violations_release (action, released_at),
+  CONSTRAINT fk_device_limit_violations_user FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci COMMENT='设备数量超限记录';
+
+-- 用户通知复用消息队列，不再强制关联公告
+ALTER TABLE message_queue MODIFY COLUMN announcement_id BIGINT NULL COMMENT '关联公告 ID（用户通知为空）';
+
+-- 默认配置（已存在则忽略）
+INSERT IGNORE INTO system_configs (`key`, value, description) VALUES
+('device_limit_action', 'kick', '设备数量超限处理方式：record（仅记录）、kick（踢出多余 IP）、suspend（临时停用）'),
+('device_limit_window_minutes', '5', '设备数量统计窗口（分钟，按 online_ips.last_seen 计算）'),
+('device_limit_suspend_minutes', '30', '超限临时停用时长（分钟，仅 suspend 模式）'),
+('device_limit_notify_enabled', '1', '设备数量超限时是否通知用户（1 开启，0 关闭）'),
+('device_limit_notify_cooldown_minutes', '360', '同一用户超限通知冷却时间（分钟）'),
+('job_schedule_device_limit_check', '*/5 * * * *', '设备数量限制检查调度（cron，北京时间，留空禁用；需 JOB_SCHEDULER_ENABLED=true）');
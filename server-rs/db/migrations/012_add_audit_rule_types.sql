@@ -0,0 +1,10 @@
+-- 审计规则类型：regex / domain_suffix / domain_keyword / ip_cidr / port_range
+ALTER TABLE audit_rules
+  ADD COLUMN rule_type VARCHAR(32) NOT NULL DEFAULT 'regex' COMMENT '规则类型（regex/domain_suffix/domain_keyword/ip_cidr/port_range）' AFTER description;
+
+-- 旧规则按 soga 前缀拆分为类型与表达式
+UPDATE audit_rules SET rule_type = 'regex', rule = TRIM(SUBSTRING(rule, 8)) WHERE rule LIKE 'regexp:%';
+UPDATE audit_rules SET rule_type = 'domain_suffix', rule = TRIM(SUBSTRING(rule, 8)) WHERE rule LIKE 'domain:%';
+UPDATE audit_rules SET rule_type = 'domain_keyword', rule = TRIM(SUBSTRING(rule, 9)) WHERE rule LIKE 'keyword:%';
+UPDATE audit_rules SET rule_type = 'ip_cidr', rule = TRIM(SUBSTRING(rule, 4)) WHERE rule LIKE 'ip:%';
+UPDATE audit_rules SET rule_type = 'port_range', rule = TRIM(SUBSTRING(rule, 6)) WHERE rule LIKE 'port:%';
@@ -0,0 +1,36 @@
+-- 自动订阅：User-Agent 与订阅格式映射表
+CREATE TABLE IF NOT EXISTS subscription_ua_rules (
+  id BIGINT AUTO_INCREMENT PRIMARY KEY COMMENT '规则 ID',
+  pattern VARCHAR(128) NOT NULL COMMENT 'User-Agent 匹配关键字（不区分大小写）',
+  format VARCHAR(32) NOT NULL COMMENT '订阅格式（v2ray/clash/quantumultx/singbox/shadowrocket/surge/xray）',
+  priority INT NOT NULL DEFAULT 0 COMMENT '匹配优先级（越大越先匹配）',
+  enabled TINYINT NOT NULL DEFAULT 1 COMMENT '是否启用',
+  description VARCHAR(255) COMMENT '备注',
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP COMMENT '创建时间',
+  updated_at DATETIME DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP COMMENT '更新时间',
+  UNIQUE KEY uk_subscription_ua_rules_pattern (pattern),
+  INDEX idx_subscription_ua_rules_enabled (enabled, priority)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci COMMENT='订阅 User-Agent 与格式映射';
+
+INSERT IGNORE INTO subscription_ua_rules (pattern, format, priority, description) VALUES
+('clash-verge', 'clash', 100, 'Clash Verge'),
+('mihomo', 'clash', 100, 'mihomo 内核客户端'),
+('clash.meta', 'clash', 90, 'Clash Meta'),
+('stash', 'clash', 80, 'Stash'),
+('clash', 'clash', 50, 'Clash 系客户端'),
+('sing-box', 'singbox', 100, 'sing-box'),
+('sfa/', 'singbox', 90, 'sing-box Android'),
+('sfi/', 'singbox', 90, 'sing-box iOS'),
+('sfm/', 'singbox', 90, 'sing-box macOS'),
+('hiddify', 'singbox', 80, 'Hiddify'),
+('surge', 'surge', 100, 'Surge'),
+('quantumult', 'quantumultx', 100, 'Quantumult X'),
+('shadowrocket', 'shadowrocket', 100, 'Shadowrocket'),
+('v2rayng', 'v2ray', 100, 'v2rayNG'),
+('v2rayn', 'v2ray', 90, 'v2rayN'),
+('v2box', 'v2ray', 80, 'V2Box'),
+('xray', 'xray', 60, 'Xray 原生配置');
+
+-- 回退格式（已存在则忽略）
+INSERT IGNORE INTO system_configs (`key`, value, description) VALUES
+('subscription_auto_fallback', 'v2ray', '自动订阅未匹配 User-Agent 时的回退格式（v2ray/clash/quantumultx/singbox/shadowrocket/surge/xray）');
@@ -0,0 +1,22 @@
+-- 支付回调流水：记录所有回调及金额校验结果
+CREATE TABLE IF NOT EXISTS payment_callbacks (
+  id BIGINT AUTO_INCREMENT PRIMARY KEY COMMENT '回调记录 ID',
+  provider VARCHAR(32) NOT NULL DEFAULT 'unknown' COMMENT '支付通道（epay/epusdt 等）',
+  trade_no VARCHAR(255) COMMENT '回调携带的订单号',
+  order_type VARCHAR(16) COMMENT '订单类型（recharge/purchase）',
+  raw_payload MEDIUMTEXT COMMENT '原始回调参数（JSON）',
+  verified TINYINT NOT NULL DEFAULT 0 COMMENT '签名是否校验通过',
+  reported_amount DECIMAL(10,2) COMMENT '回调上报金额',
+  expected_amount DECIMAL(10,2) COMMENT '订单应付金额',
+  currency VARCHAR(16) COMMENT '回调上报币种',
+  status VARCHAR(32) NOT NULL COMMENT '处理结果（settled/duplicate/invalid_signature/order_not_found/amount_mismatch/ignored_closed/error）',
+  message VARCHAR(255) COMMENT '处理说明',
+  review_status VARCHAR(16) NOT NULL DEFAULT 'none' COMMENT '人工审核状态（none/pending/settled/dismissed）',
+  reviewed_by BIGINT COMMENT '审核管理员 ID',
+  reviewed_at DATETIME COMMENT '审核时间',
+  review_note VARCHAR(255) COMMENT '审核备注',
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP COMMENT '回调时间',
+  INDEX idx_payment_callbacks_trade_no (trade_no),
+  INDEX idx_payment_callbacks_status (status, created_at),
+  INDEX idx_payment_callbacks_review (review_status, created_at)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci COMMENT='支付回调流水';
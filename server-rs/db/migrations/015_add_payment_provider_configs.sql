@@ -0,0 +1,21 @@
+-- 支付通道配置：按通道选择支付接口，配置项可覆盖环境变量
+INSERT IGNORE INTO system_configs (`key`, value, description) VALUES
+('payment_alipay', '', '支付宝通道使用的支付接口（epay/epusdt/bank，留空使用环境变量 PAYMENT_ALIPAY）'),
+('payment_wxpay', '', '微信支付通道使用的支付接口（epay/epusdt/bank，留空使用环境变量 PAYMENT_WXPAY）'),
+('payment_crypto', '', 'USDT 通道使用的支付接口（epay/epusdt/bank，留空使用环境变量 PAYMENT_CRYPTO）'),
+('payment_bank', '', '银行转账通道使用的支付接口（通常为 bank，留空禁用）'),
+('epay_api_url', '', '易支付网关地址（留空使用环境变量 EPAY_API_URL）'),
+('epay_pid', '', '易支付商户 ID（留空使用环境变量 EPAY_PID）'),
+('epay_key', '', '易支付商户密钥（留空使用环境变量 EPAY_KEY）'),
+('epay_notify_url', '', '易支付异步通知地址（留空使用环境变量 EPAY_NOTIFY_URL）'),
+('epay_return_url', '', '易支付完成跳转地址（留空使用环境变量 EPAY_RETURN_URL）'),
+('epay_payment_mode', '', '易支付下单模式：redirect 或 api（留空使用环境变量 EPAY_PAYMENT_MODE）'),
+('epusdt_api_url', '', 'epusdt 接口地址（留空使用环境变量 EPUSDT_API_URL）'),
+('epusdt_token', '', 'epusdt 签名 Token（留空使用环境变量 EPUSDT_TOKEN）'),
+('epusdt_notify_url', '', 'epusdt 异步通知地址（留空使用环境变量 EPUSDT_NOTIFY_URL）'),
+('epusdt_return_url', '', 'epusdt 完成跳转地址（留空使用环境变量 EPUSDT_RETURN_URL）'),
+('epusdt_trade_type', '', 'epusdt 收款网络（留空使用环境变量 EPUSDT_TRADE_TYPE，默认 usdt.trc20）'),
+('epusdt_timeout', '', 'epusdt 订单超时秒数（留空使用环境变量 EPUSDT_TIMEOUT，默认 600）'),
+('bank_webhook_secret', '', '银行转账到账回调的 HMAC-SHA256 签名密钥'),
+('bank_pay_url', '', '银行转账收款说明页地址，支持 {trade_no}、{amount}、{subject} 占位符'),
+('bank_instructions', '', '银行转账下单后展示给用户的说明文字');
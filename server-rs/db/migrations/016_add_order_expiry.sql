@@ -0,0 +1,20 @@
+-- 待支付订单对账与超时关闭
+-- status 新增 4（已过期），close_reason/closed_at 记录关闭原因与时间
+
+ALTER TABLE recharge_records
+  ADD COLUMN close_reason VARCHAR(255) NULL COMMENT '订单关闭原因';
+
+ALTER TABLE recharge_records
+  ADD COLUMN closed_at DATETIME NULL COMMENT '订单关闭时间';
+
+ALTER TABLE package_purchase_records
+  ADD COLUMN close_reason VARCHAR(255) NULL COMMENT '订单关闭原因';
+
+ALTER TABLE package_purchase_records
+  ADD COLUMN closed_at DATETIME NULL COMMENT '订单关闭时间';
+
+-- 追加系统配置项（已存在则忽略）
+INSERT IGNORE INTO system_configs (`key`, value, description) VALUES
+('payment_reconcile_after_minutes', '10', '待支付订单创建多少分钟后开始向支付通道查单'),
+('payment_order_expire_minutes', '120', '待支付订单超过多少分钟仍未支付则标记为已过期'),
+('job_schedule_payment_reconcile', '*/10 * * * *', '待支付订单对账调度（cron，北京时间，留空禁用；需 JOB_SCHEDULER_ENABLED=true）');
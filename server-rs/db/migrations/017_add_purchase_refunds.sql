@@ -0,0 +1,8 @@
+-- 套餐订单退款
+-- status 新增 5（已退款），benefit_snapshot 保存发放前的用户状态用于回滚权益
+
+ALTER TABLE package_purchase_records
+  ADD COLUMN refund_amount DECIMAL(10,2) NULL COMMENT '退款金额';
+
+ALTER TABLE package_purchase_records
+  ADD COLUMN benefit_snapshot JSON NULL COMMENT '套餐发放前的用户等级/到期/流量快照（退款回滚用）';
@@ -0,0 +1,24 @@
+-- 节点流量按重置日自动清零：归档每个周期的用量，并记录本周期已发送的告警阈值
+
+ALTER TABLE nodes
+  ADD COLUMN bandwidth_period_start DATETIME NULL COMMENT '当前流量周期开始时间（最近一次重置）';
+
+ALTER TABLE nodes
+  ADD COLUMN bandwidth_warned_percent INT NOT NULL DEFAULT 0 COMMENT '本周期已发送的流量告警阈值（百分比）';
+
+CREATE TABLE IF NOT EXISTS node_traffic_history (
+  id BIGINT AUTO_INCREMENT PRIMARY KEY COMMENT '记录 ID',
+  node_id BIGINT NOT NULL COMMENT '节点 ID',
+  period_start DATETIME COMMENT '周期开始时间',
+  period_end DATETIME NOT NULL COMMENT '周期结束（重置）时间',
+  used_traffic BIGINT NOT NULL DEFAULT 0 COMMENT '周期内已用流量（字节）',
+  bandwidth_limit BIGINT NOT NULL DEFAULT 0 COMMENT '周期内流量上限（字节，0 表示不限）',
+  reset_type VARCHAR(16) NOT NULL DEFAULT 'auto' COMMENT '重置方式（auto 按重置日自动/manual 管理员手动）',
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP COMMENT '记录时间',
+  INDEX idx_node_traffic_history_node (node_id, period_end),
+  CONSTRAINT fk_node_traffic_history_node FOREIGN KEY (node_id) REFERENCES nodes(id) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci COMMENT='节点流量周期归档';
+
+-- 追加系统配置项（已存在则忽略）
+INSERT IGNORE INTO system_configs (`key`, value, description) VALUES
+('node_bandwidth_warn_percents', '80,95', '节点流量达到上限的百分比时通知管理员（逗号分隔，留空不告警）');
@@ -0,0 +1,17 @@
+-- 管理员操作审计：记录操作人、IP、接口、对象及变更前后差异
+
+CREATE TABLE IF NOT EXISTS admin_action_logs (
+  id BIGINT AUTO_INCREMENT PRIMARY KEY COMMENT '记录 ID',
+  admin_id BIGINT NOT NULL COMMENT '操作管理员 ID',
+  ip VARCHAR(64) COMMENT '操作 IP',
+  route VARCHAR(255) NOT NULL COMMENT '接口（方法 + 路径）',
+  action VARCHAR(64) NOT NULL COMMENT '操作类型（如 user.update、system_config.update）',
+  target_type VARCHAR(32) NOT NULL COMMENT '操作对象类型',
+  target_id VARCHAR(255) COMMENT '操作对象 ID（批量操作为逗号分隔）',
+  before_data JSON COMMENT '变更前的字段值（仅含变更字段，敏感字段已脱敏）',
+  after_data JSON COMMENT '变更后的字段值（仅含变更字段，敏感字段已脱敏）',
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP COMMENT '操作时间',
+  INDEX idx_admin_action_logs_admin (admin_id, created_at),
+  INDEX idx_admin_action_logs_target (target_type, target_id),
+  INDEX idx_admin_action_logs_action (action, created_at)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci COMMENT='管理员操作审计日志';
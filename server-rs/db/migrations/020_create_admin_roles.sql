@@ -0,0 +1,24 @@
+-- 管理员角色：按后台路由分组授予权限范围（permissions 为 JSON 数组，"*" 表示全部权限）
+
+CREATE TABLE IF NOT EXISTS admin_roles (
+  id BIGINT AUTO_INCREMENT PRIMARY KEY COMMENT '角色 ID',
+  code VARCHAR(32) NOT NULL UNIQUE COMMENT '角色标识',
+  name VARCHAR(64) NOT NULL COMMENT '角色名称',
+  description VARCHAR(255) COMMENT '角色说明',
+  permissions JSON NOT NULL COMMENT '权限范围列表（如 ["tickets","users"]，"*" 为全部）',
+  is_system TINYINT NOT NULL DEFAULT 0 COMMENT '是否内置角色（内置角色不可删除）',
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP COMMENT '创建时间',
+  updated_at DATETIME DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP COMMENT '更新时间'
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci COMMENT='管理员角色';
+
+INSERT IGNORE INTO admin_roles (code, name, description, permissions, is_system) VALUES
+  ('super_admin', '超级管理员', '拥有全部后台权限，可管理角色', '["*"]', 1),
+  ('finance', '财务', '充值、购买、退款、返利提现与支付回调', '["recharge-records","purchase-records","rebate","payment-callbacks","coupons","gift-cards","packages"]', 1),
+  ('support', '客服', '工单与公告处理', '["tickets","announcements"]', 1),
+  ('ops', '运维', '节点、规则、IP 与缓存维护', '["nodes","traffic","task","xray-rules","audit","whitelist","online-ips","blocked-ips","subscription-ua-rules","cache","maintenance"]', 1);
+
+-- 未分配角色（admin_role_id 为空）的管理员视为超级管理员，兼容升级前的账号
+ALTER TABLE users
+  ADD COLUMN admin_role_id BIGINT NULL COMMENT '管理员角色 ID（为空时视为超级管理员）' AFTER is_admin;
+
+CREATE INDEX idx_users_admin_role ON users (admin_role_id);
@@ -0,0 +1,14 @@
+-- 多级邀请返利：流水记录返利层级，套餐可单独配置各层比例
+
+ALTER TABLE rebate_transactions
+  ADD COLUMN level TINYINT NOT NULL DEFAULT 1 COMMENT '返利层级（1 为直接邀请人）' AFTER invitee_id;
+
+ALTER TABLE packages
+  ADD COLUMN rebate_levels VARCHAR(255) NULL COMMENT '套餐返利层级比例（逗号分隔，为空时使用系统配置）' AFTER sort_weight;
+
+-- 追加系统配置项（已存在则忽略）
+INSERT IGNORE INTO system_configs (`key`, value, description) VALUES
+('rebate_levels', '', '多级返利比例（逗号分隔，如 0.2,0.05,0.02，第 1 项为直接邀请人；为空时使用 rebate_rate 单级返利）'),
+('rebate_levels_recharge', '', '充值订单返利层级比例（为空时使用 rebate_levels）'),
+('rebate_levels_purchase', '', '套餐订单返利层级比例（为空时使用 rebate_levels；套餐单独配置优先）'),
+('rebate_invitee_cap', '0', '单个邀请人从同一被邀请人处可获得的返利上限（元，0 表示不限）');
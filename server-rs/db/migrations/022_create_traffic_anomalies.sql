@@ -0,0 +1,33 @@
+-- 节点流量上报异常检测：可疑流量隔离到待审核表，不直接计入用户流量
+
+ALTER TABLE nodes
+  ADD COLUMN last_traffic_report_at DATETIME NULL COMMENT '最近一次流量上报时间（用于计算上报间隔）';
+
+ALTER TABLE nodes
+  ADD COLUMN traffic_anomaly_count BIGINT NOT NULL DEFAULT 0 COMMENT '累计被隔离的可疑流量条数';
+
+CREATE TABLE IF NOT EXISTS traffic_anomalies (
+  id BIGINT AUTO_INCREMENT PRIMARY KEY COMMENT '记录 ID',
+  node_id BIGINT NOT NULL COMMENT '上报节点 ID',
+  user_id BIGINT NOT NULL COMMENT '上报的用户 ID',
+  upload_traffic BIGINT NOT NULL DEFAULT 0 COMMENT '上报的上传流量（字节）',
+  download_traffic BIGINT NOT NULL DEFAULT 0 COMMENT '上报的下载流量（字节）',
+  deduction_multiplier DECIMAL(10,4) NOT NULL DEFAULT 1 COMMENT '上报时的节点流量倍率',
+  reason VARCHAR(32) NOT NULL COMMENT '隔离原因（throughput/not_eligible/node_disabled）',
+  interval_seconds INT NOT NULL DEFAULT 0 COMMENT '判定时使用的上报间隔（秒）',
+  date DATE NOT NULL COMMENT '上报日期（北京时间）',
+  status VARCHAR(16) NOT NULL DEFAULT 'pending' COMMENT '审核状态（pending/approved/rejected）',
+  reviewed_by BIGINT NULL COMMENT '审核管理员 ID',
+  reviewed_at DATETIME NULL COMMENT '审核时间',
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP COMMENT '上报时间',
+  INDEX idx_traffic_anomalies_status (status, created_at),
+  INDEX idx_traffic_anomalies_node (node_id, status),
+  INDEX idx_traffic_anomalies_user (user_id, created_at),
+  CONSTRAINT fk_traffic_anomalies_node FOREIGN KEY (node_id) REFERENCES nodes(id) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci COMMENT='可疑流量上报（待审核）';
+
+-- 追加系统配置项（已存在则忽略）
+INSERT IGNORE INTO system_configs (`key`, value, description) VALUES
+('traffic_anomaly_enabled', '1', '是否检测节点流量上报异常（1 开启，0 关闭；关闭后全部直接计费）'),
+('traffic_anomaly_max_mbps', '10000', '单用户单次上报允许的最大平均速率（Mbps，按距上次上报的间隔换算字节上限）'),
+('traffic_anomaly_grace_minutes', '10', '账户或等级到期后的宽限时间（分钟），宽限期内的上报不按资格判定为异常');
@@ -0,0 +1,16 @@
+-- 订阅模板（Clash/sing-box/Surge/Xray 基础配置与 Clash 规则）改为后台可编辑并按版本保存
+
+CREATE TABLE IF NOT EXISTS subscription_templates (
+  id BIGINT AUTO_INCREMENT PRIMARY KEY COMMENT '模板 ID',
+  kind VARCHAR(32) NOT NULL COMMENT '模板类型（clash/clash_rules/singbox/surge/xray）',
+  version INT NOT NULL COMMENT '同类型内递增的版本号',
+  name VARCHAR(100) NOT NULL DEFAULT '' COMMENT '模板名称',
+  content MEDIUMTEXT NOT NULL COMMENT '模板内容（保存后不可修改，修改即生成新版本）',
+  note VARCHAR(255) COMMENT '版本说明',
+  is_active TINYINT NOT NULL DEFAULT 0 COMMENT '是否为该类型当前生效版本（无生效版本时使用内置模板）',
+  created_by BIGINT COMMENT '创建管理员 ID',
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP COMMENT '创建时间',
+  updated_at DATETIME DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP COMMENT '更新时间',
+  UNIQUE KEY uk_subscription_templates_version (kind, version),
+  INDEX idx_subscription_templates_active (kind, is_active)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci COMMENT='订阅模板版本';
@@ -0,0 +1,31 @@
+-- 订阅分组（地区/自定义策略组）改为后台配置，按节点标签、名称正则或节点 ID 匹配
+
+ALTER TABLE nodes
+  ADD COLUMN tags JSON NULL COMMENT '节点标签（用于订阅分组匹配）';
+
+CREATE TABLE IF NOT EXISTS proxy_groups (
+  id BIGINT AUTO_INCREMENT PRIMARY KEY COMMENT '分组 ID',
+  name VARCHAR(64) NOT NULL COMMENT '分组名称（不含 emoji）',
+  emoji VARCHAR(16) NOT NULL DEFAULT '' COMMENT '分组名称前缀 emoji',
+  match_type VARCHAR(16) NOT NULL DEFAULT 'regex' COMMENT '匹配方式（tag/regex/node_ids）',
+  match_value TEXT NOT NULL COMMENT '匹配内容（标签、节点名称正则或节点 ID 列表）',
+  group_type VARCHAR(16) NOT NULL DEFAULT 'select' COMMENT '分组类型（select/url-test/fallback/load-balance）',
+  test_url VARCHAR(255) NULL COMMENT '测速地址（非 select 类型使用）',
+  test_interval INT NOT NULL DEFAULT 300 COMMENT '测速间隔（秒）',
+  sort_order INT NOT NULL DEFAULT 0 COMMENT '排序（越小越靠前）',
+  enabled TINYINT NOT NULL DEFAULT 1 COMMENT '是否启用（1 启用）',
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP COMMENT '创建时间',
+  updated_at DATETIME DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP COMMENT '更新时间',
+  UNIQUE KEY uk_proxy_groups_name (name),
+  INDEX idx_proxy_groups_enabled (enabled, sort_order)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci COMMENT='订阅分组';
+
+-- 默认地区分组，与原内置的节点名称识别规则一致
+INSERT IGNORE INTO proxy_groups (name, emoji, match_type, match_value, group_type, sort_order) VALUES
+  ('香港节点', '🇭🇰', 'regex', '(?i)香港|🇭🇰|hong\\s*kong|(?:^|[^a-z0-9])hk(?:[^a-z0-9]|$)', 'select', 10),
+  ('台湾节点', '🇨🇳', 'regex', '(?i)台湾|台北|🇹🇼|taiwan|taipei|(?:^|[^a-z0-9])tw(?:[^a-z0-9]|$)', 'select', 20),
+  ('狮城节点', '🇸🇬', 'regex', '(?i)狮城|新加坡|🇸🇬|singapore|(?:^|[^a-z0-9])sg(?:[^a-z0-9]|$)', 'select', 30),
+  ('日本节点', '🇯🇵', 'regex', '(?i)日本|东京|大阪|🇯🇵|japan|(?:^|[^a-z0-9])jp(?:[^a-z0-9]|$)', 'select', 40),
+  ('美国节点', '🇺🇲', 'regex', '(?i)美国|洛杉矶|纽约|硅谷|🇺🇸|🇺🇲|united\\s*states|(?:^|[^a-z0-9])usa?(?:[^a-z0-9]|$)', 'select', 50),
+  ('韩国节点', '🇰🇷', 'regex', '(?i)韩国|首尔|🇰🇷|korea|(?:^|[^a-z0-9])kr(?:[^a-z0-9]|$)', 'select', 60),
+  ('奈飞节点', '🎥', 'regex', '(?i)奈飞|netflix|(?:^|[^a-z0-9])nf(?:[^a-z0-9]|$)', 'select', 70);
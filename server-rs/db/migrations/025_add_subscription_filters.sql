@@ -0,0 +1,3 @@
+-- 订阅链接支持按名称正则、协议、地区筛选节点并排序，访问日志记录所用的筛选参数
+ALTER TABLE subscriptions
+  ADD COLUMN filters VARCHAR(512) NULL COMMENT '订阅链接上的节点筛选参数（include/exclude/type/region/sort）';
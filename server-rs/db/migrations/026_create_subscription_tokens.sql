@@ -0,0 +1,22 @@
+-- 每个用户可创建多个订阅 Token（按设备命名、可设到期时间与允许格式），单独撤销；users.token 作为旧链接继续可用
+
+CREATE TABLE IF NOT EXISTS subscription_tokens (
+  id BIGINT AUTO_INCREMENT PRIMARY KEY COMMENT '订阅 Token ID',
+  user_id BIGINT NOT NULL COMMENT '用户 ID',
+  token VARCHAR(64) NOT NULL COMMENT '订阅 Token',
+  label VARCHAR(64) NOT NULL DEFAULT '' COMMENT '设备名称',
+  allowed_formats JSON NULL COMMENT '允许的订阅格式（为空不限制）',
+  expires_at DATETIME NULL COMMENT '到期时间（为空不过期）',
+  last_used_at DATETIME NULL COMMENT '最近使用时间',
+  last_ip VARCHAR(255) NULL COMMENT '最近使用 IP',
+  revoked_at DATETIME NULL COMMENT '撤销时间（撤销后保留记录用于日志归属）',
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP COMMENT '创建时间',
+  UNIQUE KEY uk_subscription_tokens_token (token),
+  INDEX idx_subscription_tokens_user (user_id, revoked_at),
+  CONSTRAINT fk_subscription_tokens_user FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci COMMENT='用户订阅 Token（多设备，可单独撤销）';
+
+ALTER TABLE subscriptions
+  ADD COLUMN token_id BIGINT NULL COMMENT '请求使用的订阅 Token ID（为空表示 users.token 旧链接）';
+
+CREATE INDEX idx_subscriptions_token ON subscriptions (token_id);
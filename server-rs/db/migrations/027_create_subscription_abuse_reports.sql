@@ -0,0 +1,32 @@
+-- 订阅链接滥用检测：统计 token 在时间窗口内的拉取 IP、网段与 UA 数量，超限时记录报告并可自动重置
+
+CREATE TABLE IF NOT EXISTS subscription_abuse_reports (
+  id BIGINT AUTO_INCREMENT PRIMARY KEY COMMENT '报告 ID',
+  user_id BIGINT NOT NULL COMMENT '用户 ID',
+  token_id BIGINT NULL COMMENT '订阅 Token ID（为空表示 users.token 旧链接）',
+  window_minutes INT NOT NULL DEFAULT 0 COMMENT '统计窗口（分钟）',
+  ip_count INT NOT NULL DEFAULT 0 COMMENT '窗口内不同 IP 数',
+  network_count INT NOT NULL DEFAULT 0 COMMENT '窗口内不同网段数（IPv4 /24、IPv6 /48）',
+  user_agent_count INT NOT NULL DEFAULT 0 COMMENT '窗口内不同 User-Agent 数',
+  ips TEXT NULL COMMENT 'IP 样本（JSON 数组）',
+  user_agents TEXT NULL COMMENT 'User-Agent 样本（JSON 数组）',
+  reasons VARCHAR(64) NOT NULL DEFAULT '' COMMENT '超出的阈值（ips/networks/user_agents，逗号分隔）',
+  action VARCHAR(16) NOT NULL DEFAULT 'record' COMMENT '处理方式（record 仅记录，reset 已重置 token）',
+  notified TINYINT NOT NULL DEFAULT 0 COMMENT '是否已通知用户',
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP COMMENT '检测时间',
+  INDEX idx_subscription_abuse_created (created_at),
+  INDEX idx_subscription_abuse_user (user_id, token_id, created_at),
+  CONSTRAINT fk_subscription_abuse_user FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci COMMENT='疑似泄露的订阅链接报告';
+
+-- 追加系统配置项（已存在则忽略）
+INSERT IGNORE INTO system_configs (`key`, value, description) VALUES
+('subscription_abuse_enabled', '1', '是否检测订阅链接滥用（1 开启，0 关闭）'),
+('subscription_abuse_window_minutes', '1440', '订阅链接滥用统计窗口（分钟，按订阅拉取记录计算）'),
+('subscription_abuse_max_ips', '10', '窗口内单个订阅链接允许的最大不同 IP 数'),
+('subscription_abuse_max_networks', '5', '窗口内单个订阅链接允许的最大不同网段数（IPv4 /24、IPv6 /48，近似代替 ASN）'),
+('subscription_abuse_max_user_agents', '5', '窗口内单个订阅链接允许的最大不同 User-Agent 数'),
+('subscription_abuse_auto_reset', '0', '检测到滥用时是否自动重置订阅链接（1 开启，0 仅记录）'),
+('subscription_abuse_notify_enabled', '1', '检测到滥用时是否通知用户（1 开启，0 关闭）'),
+('subscription_abuse_cooldown_minutes', '1440', '同一订阅链接重复报告的冷却时间（分钟，不短于统计窗口）'),
+('job_schedule_subscription_abuse_check', '*/30 * * * *', '订阅链接滥用检测调度（cron，北京时间，留空禁用；需 JOB_SCHEDULER_ENABLED=true）');
@@ -0,0 +1,4 @@
+-- 设备超限临时停用标记：到期恢复时只恢复由停用任务禁用的账号，管理员期间修改过状态的不再自动启用
+
+ALTER TABLE users
+  ADD COLUMN device_limit_suspended_at DATETIME NULL COMMENT '设备超限临时停用时间（恢复或管理员修改状态时清除）';
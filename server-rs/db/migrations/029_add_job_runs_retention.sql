@@ -0,0 +1,5 @@
+-- 定时任务执行记录保留期：subscriptionCleanup 任务按天数清理 job_runs
+
+-- 追加系统配置项（已存在则忽略）
+INSERT IGNORE INTO system_configs (`key`, value, description) VALUES
+('job_runs_retention_days', '30', '定时任务执行记录保留天数（由订阅记录清理任务一并清理，0 表示不清理）');
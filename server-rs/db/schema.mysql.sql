@@ -19,6 +19,7 @@ CREATE TABLE IF NOT EXISTS users (
   last_oauth_login_at DATETIME COMMENT '最近 OAuth 登录时间',
   github_id VARCHAR(255) COMMENT 'GitHub OAuth 唯一标识',
   is_admin TINYINT DEFAULT 0 COMMENT '是否管理员（1 为管理员）',
+  admin_role_id BIGINT NULL COMMENT '管理员角色 ID（为空时视为超级管理员）',
   speed_limit INT DEFAULT 0 COMMENT '速度限制 Mbps（0 表示不限）',
   device_limit INT DEFAULT 0 COMMENT '设备数量限制（0 表示不限）',
   tcp_limit INT DEFAULT 0 COMMENT 'TCP 连接数限制（0 表示不限）',
@@ -29,6 +30,7 @@ CREATE TABLE IF NOT EXISTS users (
   transfer_total BIGINT DEFAULT 0 COMMENT '历史已用总流量（字节）',
   transfer_enable BIGINT DEFAULT 10737418240 COMMENT '总流量额度（字节）',
   status TINYINT DEFAULT 1 COMMENT '账户状态（0 禁用，1 启用）',
+  device_limit_suspended_at DATETIME NULL COMMENT '设备超限临时停用时间（恢复或管理员修改状态时清除）',
   reg_date DATETIME DEFAULT CURRENT_TIMESTAMP COMMENT '注册时间',
   expire_time DATETIME COMMENT '账户过期时间',
   last_login_time DATETIME COMMENT '最后登录时间',
@@ -50,6 +52,7 @@ CREATE TABLE IF NOT EXISTS users (
   rebate_available DECIMAL(10,2) DEFAULT 0.00 COMMENT '可用返利余额',
   rebate_total DECIMAL(10,2) DEFAULT 0.00 COMMENT '累计返利总额',
   register_ip VARCHAR(255) COMMENT '注册 IP',
+  sync_revision BIGINT NOT NULL DEFAULT 0 COMMENT '节点同步版本号（影响节点用户列表的字段变化时递增）',
   created_at DATETIME DEFAULT CURRENT_TIMESTAMP COMMENT '创建时间',
   updated_at DATETIME DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP COMMENT '更新时间'
 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
@@ -65,9 +68,19 @@ CREATE TABLE IF NOT EXISTS nodes (
   node_bandwidth_limit BIGINT DEFAULT 0 COMMENT '节点流量上限（字节，0 表示不限）',
   traffic_multiplier DECIMAL(10,4) DEFAULT 1 COMMENT '流量倍率（扣费时的倍数）',
   bandwidthlimit_resetday INT DEFAULT 1 COMMENT '每月流量重置日（1-31）',
+  bandwidth_period_start DATETIME COMMENT '当前流量周期开始时间（最近一次重置）',
+  bandwidth_warned_percent INT NOT NULL DEFAULT 0 COMMENT '本周期已发送的流量告警阈值（百分比）',
   node_config JSON NOT NULL COMMENT '节点配置 JSON',
   xray_rule_ids JSON NULL COMMENT '绑定路由规则 ID 列表',
+  tags JSON NULL COMMENT '节点标签（用于订阅分组匹配）',
   status TINYINT DEFAULT 1 COMMENT '节点状态（0 禁用，1 启用）',
+  api_key_hash VARCHAR(64) COMMENT '节点独立 API 密钥哈希（SHA-256）',
+  api_key_prev_hash VARCHAR(64) COMMENT '轮换前的旧密钥哈希（宽限期内仍有效）',
+  api_key_prev_expires_at DATETIME COMMENT '旧密钥宽限期截止时间',
+  api_key_rotated_at DATETIME COMMENT '最近一次生成/轮换密钥时间',
+  users_sync_floor BIGINT NOT NULL DEFAULT 0 COMMENT '低于该版本号的增量同步需改为全量（节点等级/配置变更时更新）',
+  last_traffic_report_at DATETIME NULL COMMENT '最近一次流量上报时间（用于计算上报间隔）',
+  traffic_anomaly_count BIGINT NOT NULL DEFAULT 0 COMMENT '累计被隔离的可疑流量条数',
   created_at DATETIME DEFAULT CURRENT_TIMESTAMP COMMENT '创建时间',
   updated_at DATETIME DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP COMMENT '更新时间'
 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
@@ -76,6 +89,7 @@ CREATE TABLE IF NOT EXISTS audit_rules (
   id BIGINT AUTO_INCREMENT PRIMARY KEY COMMENT '审计规则 ID',
   name VARCHAR(255) NOT NULL COMMENT '规则名称',
   description TEXT COMMENT '规则描述',
+  rule_type VARCHAR(32) NOT NULL DEFAULT 'regex' COMMENT '规则类型（regex/domain_suffix/domain_keyword/ip_cidr/port_range）',
   rule TEXT NOT NULL COMMENT '规则表达式',
   enabled TINYINT DEFAULT 1 COMMENT '是否启用（1 启用）',
   created_at DATETIME DEFAULT CURRENT_TIMESTAMP COMMENT '创建时间',
@@ -108,12 +122,49 @@ CREATE TABLE IF NOT EXISTS subscriptions (
   id BIGINT AUTO_INCREMENT PRIMARY KEY COMMENT '订阅记录 ID',
   user_id BIGINT NOT NULL COMMENT '用户 ID',
   type VARCHAR(50) NOT NULL COMMENT '订阅类型（clash/v2ray 等）',
+  token_id BIGINT NULL COMMENT '请求使用的订阅 Token ID（为空表示 users.token 旧链接）',
+  filters VARCHAR(512) NULL COMMENT '订阅链接上的节点筛选参数（include/exclude/type/region/sort）',
   request_ip VARCHAR(255) COMMENT '请求 IP',
   request_time DATETIME DEFAULT CURRENT_TIMESTAMP COMMENT '请求时间',
   request_user_agent TEXT COMMENT 'User-Agent',
   CONSTRAINT fk_subscriptions_user FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
 
+CREATE TABLE IF NOT EXISTS subscription_tokens (
+  id BIGINT AUTO_INCREMENT PRIMARY KEY COMMENT '订阅 Token ID',
+  user_id BIGINT NOT NULL COMMENT '用户 ID',
+  token VARCHAR(64) NOT NULL COMMENT '订阅 Token',
+  label VARCHAR(64) NOT NULL DEFAULT '' COMMENT '设备名称',
+  allowed_formats JSON NULL COMMENT '允许的订阅格式（为空不限制）',
+  expires_at DATETIME NULL COMMENT '到期时间（为空不过期）',
+  last_used_at DATETIME NULL COMMENT '最近使用时间',
+  last_ip VARCHAR(255) NULL COMMENT '最近使用 IP',
+  revoked_at DATETIME NULL COMMENT '撤销时间（撤销后保留记录用于日志归属）',
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP COMMENT '创建时间',
+  UNIQUE KEY uk_subscription_tokens_token (token),
+  INDEX idx_subscription_tokens_user (user_id, revoked_at),
+  CONSTRAINT fk_subscription_tokens_user FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci COMMENT='用户订阅 Token（多设备，可单独撤销）';
+
+CREATE TABLE IF NOT EXISTS subscription_abuse_reports (
+  id BIGINT AUTO_INCREMENT PRIMARY KEY COMMENT '报告 ID',
+  user_id BIGINT NOT NULL COMMENT '用户 ID',
+  token_id BIGINT NULL COMMENT '订阅 Token ID（为空表示 users.token 旧链接）',
+  window_minutes INT NOT NULL DEFAULT 0 COMMENT '统计窗口（分钟）',
+  ip_count INT NOT NULL DEFAULT 0 COMMENT '窗口内不同 IP 数',
+  network_count INT NOT NULL DEFAULT 0 COMMENT '窗口内不同网段数（IPv4 /24、IPv6 /48）',
+  user_agent_count INT NOT NULL DEFAULT 0 COMMENT '窗口内不同 User-Agent 数',
+  ips TEXT NULL COMMENT 'IP 样本（JSON 数组）',
+  user_agents TEXT NULL COMMENT 'User-Agent 样本（JSON 数组）',
+  reasons VARCHAR(64) NOT NULL DEFAULT '' COMMENT '超出的阈值（ips/networks/user_agents，逗号分隔）',
+  action VARCHAR(16) NOT NULL DEFAULT 'record' COMMENT '处理方式（record 仅记录，reset 已重置 token）',
+  notified TINYINT NOT NULL DEFAULT 0 COMMENT '是否已通知用户',
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP COMMENT '检测时间',
+  INDEX idx_subscription_abuse_created (created_at),
+  INDEX idx_subscription_abuse_user (user_id, token_id, created_at),
+  CONSTRAINT fk_subscription_abuse_user FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci COMMENT='疑似泄露的订阅链接报告';
+
 CREATE TABLE IF NOT EXISTS online_ips (
   id BIGINT AUTO_INCREMENT PRIMARY KEY COMMENT '在线 IP 记录 ID',
   user_id BIGINT NOT NULL COMMENT '用户 ID',
@@ -221,7 +272,7 @@ CREATE TABLE IF NOT EXISTS announcements (
 
 CREATE TABLE IF NOT EXISTS message_queue (
   id BIGINT AUTO_INCREMENT PRIMARY KEY COMMENT '消息队列 ID',
-  announcement_id BIGINT NOT NULL COMMENT '关联公告 ID',
+  announcement_id BIGINT NULL COMMENT '关联公告 ID（用户通知为空）',
   user_id BIGINT NOT NULL COMMENT '接收用户 ID',
   channel VARCHAR(32) NOT NULL COMMENT '通知通道（email/bark/telegram/...)',
   recipient VARCHAR(512) NOT NULL COMMENT '接收地址（邮箱/Bark Key）',
@@ -363,6 +414,7 @@ CREATE TABLE IF NOT EXISTS packages (
   status TINYINT DEFAULT 1 COMMENT '状态（1 上架）',
   is_recommended TINYINT DEFAULT 0 COMMENT '是否推荐套餐',
   sort_weight INT DEFAULT 0 COMMENT '排序权重',
+  rebate_levels VARCHAR(255) NULL COMMENT '套餐返利层级比例（逗号分隔，为空时使用系统配置）',
   created_at DATETIME DEFAULT CURRENT_TIMESTAMP COMMENT '创建时间',
   updated_at DATETIME DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP COMMENT '更新时间'
 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
@@ -479,6 +531,7 @@ CREATE TABLE IF NOT EXISTS rebate_transactions (
   inviter_id BIGINT NOT NULL COMMENT '邀请人用户 ID',
   referral_id BIGINT COMMENT '关联的邀请关系 ID',
   invitee_id BIGINT COMMENT '被邀请用户 ID',
+  level TINYINT NOT NULL DEFAULT 1 COMMENT '返利层级（1 为直接邀请人）',
   source_type VARCHAR(50) NOT NULL COMMENT '来源类型（recharge/purchase/withdraw 等）',
   source_id BIGINT COMMENT '来源记录 ID',
   trade_no VARCHAR(255) COMMENT '关联订单号',
@@ -532,9 +585,11 @@ CREATE TABLE IF NOT EXISTS recharge_records (
   amount DECIMAL(10,2) NOT NULL COMMENT '充值金额',
   payment_method VARCHAR(50) NOT NULL DEFAULT 'alipay' COMMENT '支付方式',
   trade_no VARCHAR(255) NOT NULL UNIQUE COMMENT '支付订单号',
-  status TINYINT DEFAULT 0 COMMENT '支付状态（0 未支付，1 已支付）',
+  status TINYINT DEFAULT 0 COMMENT '支付状态（0 未支付，1 已支付，2 已取消，3 支付失败，4 已过期）',
   created_at DATETIME DEFAULT CURRENT_TIMESTAMP COMMENT '创建时间',
   paid_at DATETIME COMMENT '支付时间',
+  close_reason VARCHAR(255) COMMENT '订单关闭原因',
+  closed_at DATETIME COMMENT '订单关闭时间',
   CONSTRAINT fk_recharge_records_user FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE,
   INDEX idx_recharge_records_user_id (user_id),
   INDEX idx_recharge_records_trade_no (trade_no),
@@ -553,10 +608,14 @@ CREATE TABLE IF NOT EXISTS package_purchase_records (
   discount_amount DECIMAL(10,2) NOT NULL DEFAULT 0 COMMENT '优惠金额',
   purchase_type VARCHAR(50) NOT NULL DEFAULT 'balance' COMMENT '购买方式（余额/礼品卡等）',
   trade_no VARCHAR(255) NOT NULL UNIQUE COMMENT '订单号',
-  status TINYINT DEFAULT 0 COMMENT '状态（0 未支付，1 已支付）',
+  status TINYINT DEFAULT 0 COMMENT '状态（0 未支付，1 已支付，2 已取消，3 支付失败，4 已过期，5 已退款）',
   created_at DATETIME DEFAULT CURRENT_TIMESTAMP COMMENT '创建时间',
   paid_at DATETIME COMMENT '支付时间',
   expires_at DATETIME COMMENT '套餐到期时间',
+  close_reason VARCHAR(255) COMMENT '订单关闭原因',
+  closed_at DATETIME COMMENT '订单关闭时间',
+  refund_amount DECIMAL(10,2) COMMENT '退款金额',
+  benefit_snapshot JSON COMMENT '套餐发放前的用户等级/到期/流量快照（退款回滚用）',
   CONSTRAINT fk_package_purchase_records_user FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE,
   CONSTRAINT fk_package_purchase_records_package FOREIGN KEY (package_id) REFERENCES packages (id) ON DELETE CASCADE,
   CONSTRAINT fk_package_purchase_records_coupon FOREIGN KEY (coupon_id) REFERENCES coupons (id) ON DELETE SET NULL,
@@ -637,6 +696,207 @@ CREATE TABLE IF NOT EXISTS ticket_telegram_topics (
   INDEX idx_ticket_tg_topics_thread (message_thread_id)
 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
 
+CREATE TABLE IF NOT EXISTS job_runs (
+  id BIGINT AUTO_INCREMENT PRIMARY KEY COMMENT '任务执行记录 ID',
+  job_name VARCHAR(64) NOT NULL COMMENT '任务名称（userExpirationCheck/dailyTasks 等）',
+  trigger_type VARCHAR(16) NOT NULL DEFAULT 'schedule' COMMENT '触发方式（schedule/manual/cli）',
+  scheduled_at DATETIME COMMENT '调度时间点（仅定时触发，用于多实例去重）',
+  instance_id VARCHAR(64) NOT NULL DEFAULT '' COMMENT '执行实例标识',
+  status VARCHAR(16) NOT NULL DEFAULT 'running' COMMENT '状态（running/success/failed）',
+  error_message TEXT COMMENT '失败原因',
+  started_at DATETIME DEFAULT CURRENT_TIMESTAMP COMMENT '开始时间',
+  finished_at DATETIME COMMENT '结束时间',
+  duration_ms BIGINT NOT NULL DEFAULT 0 COMMENT '耗时（毫秒）',
+  UNIQUE KEY uk_job_runs_slot (job_name, scheduled_at),
+  INDEX idx_job_runs_status (job_name, status),
+  INDEX idx_job_runs_started_at (started_at)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci COMMENT='定时任务执行记录';
+
+CREATE TABLE IF NOT EXISTS sync_revisions (
+  name VARCHAR(64) PRIMARY KEY COMMENT '版本序列名称',
+  revision BIGINT NOT NULL DEFAULT 0 COMMENT '当前版本号'
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci COMMENT='全局单调递增版本序列';
+
+INSERT IGNORE INTO sync_revisions (name, revision) VALUES ('users', 0);
+
+CREATE TABLE IF NOT EXISTS user_sync_tombstones (
+  user_id BIGINT PRIMARY KEY COMMENT '已删除的用户 ID',
+  revision BIGINT NOT NULL COMMENT '删除时的同步版本号',
+  deleted_at DATETIME DEFAULT CURRENT_TIMESTAMP COMMENT '删除时间',
+  INDEX idx_user_sync_tombstones_revision (revision)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci COMMENT='用户删除记录（供节点增量同步）';
+
+CREATE TABLE IF NOT EXISTS blocked_ips (
+  id BIGINT AUTO_INCREMENT PRIMARY KEY COMMENT '封禁记录 ID',
+  ip VARCHAR(64) NOT NULL COMMENT '封禁 IP 或 CIDR 网段',
+  reason VARCHAR(255) COMMENT '封禁原因',
+  expires_at DATETIME COMMENT '到期时间（为空表示永久）',
+  created_by BIGINT COMMENT '操作管理员 ID',
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP COMMENT '创建时间',
+  updated_at DATETIME DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP COMMENT '更新时间',
+  UNIQUE KEY uk_blocked_ips_ip (ip),
+  INDEX idx_blocked_ips_expires_at (expires_at)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci COMMENT='IP 封禁列表（面板认证拦截并下发节点）';
+
+CREATE TABLE IF NOT EXISTS device_limit_violations (
+  id BIGINT AUTO_INCREMENT PRIMARY KEY COMMENT '记录 ID',
+  user_id BIGINT NOT NULL COMMENT '用户 ID',
+  device_limit INT NOT NULL DEFAULT 0 COMMENT '检测时的设备数量限制',
+  ip_count INT NOT NULL DEFAULT 0 COMMENT '检测到的在线 IP 数',
+  ips TEXT COMMENT '在线 IP 列表（JSON）',
+  action VARCHAR(16) NOT NULL DEFAULT 'kick' COMMENT '处理方式（record/kick/suspend）',
+  kicked_ips TEXT COMMENT '被踢出的 IP 列表（JSON）',
+  suspended_until DATETIME COMMENT '临时停用截止时间',
+  released_at DATETIME COMMENT '停用解除时间',
+  notified TINYINT NOT NULL DEFAULT 0 COMMENT '是否已通知用户',
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP COMMENT '检测时间',
+  INDEX idx_device_limit_violations_user (user_id, created_at),
+  INDEX idx_device_limit_violations_release (action, released_at),
+  CONSTRAINT fk_device_limit_violations_user FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci COMMENT='设备数量超限记录';
+
+CREATE TABLE IF NOT EXISTS subscription_ua_rules (
+  id BIGINT AUTO_INCREMENT PRIMARY KEY COMMENT '规则 ID',
+  pattern VARCHAR(128) NOT NULL COMMENT 'User-Agent 匹配关键字（不区分大小写）',
+  format VARCHAR(32) NOT NULL COMMENT '订阅格式（v2ray/clash/quantumultx/singbox/shadowrocket/surge/xray）',
+  priority INT NOT NULL DEFAULT 0 COMMENT '匹配优先级（越大越先匹配）',
+  enabled TINYINT NOT NULL DEFAULT 1 COMMENT '是否启用',
+  description VARCHAR(255) COMMENT '备注',
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP COMMENT '创建时间',
+  updated_at DATETIME DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP COMMENT '更新时间',
+  UNIQUE KEY uk_subscription_ua_rules_pattern (pattern),
+  INDEX idx_subscription_ua_rules_enabled (enabled, priority)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci COMMENT='订阅 User-Agent 与格式映射';
+
+CREATE TABLE IF NOT EXISTS subscription_templates (
+  id BIGINT AUTO_INCREMENT PRIMARY KEY COMMENT '模板 ID',
+  kind VARCHAR(32) NOT NULL COMMENT '模板类型（clash/clash_rules/singbox/surge/xray）',
+  version INT NOT NULL COMMENT '同类型内递增的版本号',
+  name VARCHAR(100) NOT NULL DEFAULT '' COMMENT '模板名称',
+  content MEDIUMTEXT NOT NULL COMMENT '模板内容（保存后不可修改，修改即生成新版本）',
+  note VARCHAR(255) COMMENT '版本说明',
+  is_active TINYINT NOT NULL DEFAULT 0 COMMENT '是否为该类型当前生效版本（无生效版本时使用内置模板）',
+  created_by BIGINT COMMENT '创建管理员 ID',
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP COMMENT '创建时间',
+  updated_at DATETIME DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP COMMENT '更新时间',
+  UNIQUE KEY uk_subscription_templates_version (kind, version),
+  INDEX idx_subscription_templates_active (kind, is_active)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci COMMENT='订阅模板版本';
+
+CREATE TABLE IF NOT EXISTS proxy_groups (
+  id BIGINT AUTO_INCREMENT PRIMARY KEY COMMENT '分组 ID',
+  name VARCHAR(64) NOT NULL COMMENT '分组名称（不含 emoji）',
+  emoji VARCHAR(16) NOT NULL DEFAULT '' COMMENT '分组名称前缀 emoji',
+  match_type VARCHAR(16) NOT NULL DEFAULT 'regex' COMMENT '匹配方式（tag/regex/node_ids）',
+  match_value TEXT NOT NULL COMMENT '匹配内容（标签、节点名称正则或节点 ID 列表）',
+  group_type VARCHAR(16) NOT NULL DEFAULT 'select' COMMENT '分组类型（select/url-test/fallback/load-balance）',
+  test_url VARCHAR(255) NULL COMMENT '测速地址（非 select 类型使用）',
+  test_interval INT NOT NULL DEFAULT 300 COMMENT '测速间隔（秒）',
+  sort_order INT NOT NULL DEFAULT 0 COMMENT '排序（越小越靠前）',
+  enabled TINYINT NOT NULL DEFAULT 1 COMMENT '是否启用（1 启用）',
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP COMMENT '创建时间',
+  updated_at DATETIME DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP COMMENT '更新时间',
+  UNIQUE KEY uk_proxy_groups_name (name),
+  INDEX idx_proxy_groups_enabled (enabled, sort_order)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci COMMENT='订阅分组';
+
+INSERT IGNORE INTO proxy_groups (name, emoji, match_type, match_value, group_type, sort_order) VALUES
+  ('香港节点', '🇭🇰', 'regex', '(?i)香港|🇭🇰|hong\\s*kong|(?:^|[^a-z0-9])hk(?:[^a-z0-9]|$)', 'select', 10),
+  ('台湾节点', '🇨🇳', 'regex', '(?i)台湾|台北|🇹🇼|taiwan|taipei|(?:^|[^a-z0-9])tw(?:[^a-z0-9]|$)', 'select', 20),
+  ('狮城节点', '🇸🇬', 'regex', '(?i)狮城|新加坡|🇸🇬|singapore|(?:^|[^a-z0-9])sg(?:[^a-z0-9]|$)', 'select', 30),
+  ('日本节点', '🇯🇵', 'regex', '(?i)日本|东京|大阪|🇯🇵|japan|(?:^|[^a-z0-9])jp(?:[^a-z0-9]|$)', 'select', 40),
+  ('美国节点', '🇺🇲', 'regex', '(?i)美国|洛杉矶|纽约|硅谷|🇺🇸|🇺🇲|united\\s*states|(?:^|[^a-z0-9])usa?(?:[^a-z0-9]|$)', 'select', 50),
+  ('韩国节点', '🇰🇷', 'regex', '(?i)韩国|首尔|🇰🇷|korea|(?:^|[^a-z0-9])kr(?:[^a-z0-9]|$)', 'select', 60),
+  ('奈飞节点', '🎥', 'regex', '(?i)奈飞|netflix|(?:^|[^a-z0-9])nf(?:[^a-z0-9]|$)', 'select', 70);
+
+CREATE TABLE IF NOT EXISTS payment_callbacks (
+  id BIGINT AUTO_INCREMENT PRIMARY KEY COMMENT '回调记录 ID',
+  provider VARCHAR(32) NOT NULL DEFAULT 'unknown' COMMENT '支付通道（epay/epusdt 等）',
+  trade_no VARCHAR(255) COMMENT '回调携带的订单号',
+  order_type VARCHAR(16) COMMENT '订单类型（recharge/purchase）',
+  raw_payload MEDIUMTEXT COMMENT '原始回调参数（JSON）',
+  verified TINYINT NOT NULL DEFAULT 0 COMMENT '签名是否校验通过',
+  reported_amount DECIMAL(10,2) COMMENT '回调上报金额',
+  expected_amount DECIMAL(10,2) COMMENT '订单应付金额',
+  currency VARCHAR(16) COMMENT '回调上报币种',
+  status VARCHAR(32) NOT NULL COMMENT '处理结果（settled/duplicate/invalid_signature/order_not_found/amount_mismatch/ignored_closed/error）',
+  message VARCHAR(255) COMMENT '处理说明',
+  review_status VARCHAR(16) NOT NULL DEFAULT 'none' COMMENT '人工审核状态（none/pending/settled/dismissed）',
+  reviewed_by BIGINT COMMENT '审核管理员 ID',
+  reviewed_at DATETIME COMMENT '审核时间',
+  review_note VARCHAR(255) COMMENT '审核备注',
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP COMMENT '回调时间',
+  INDEX idx_payment_callbacks_trade_no (trade_no),
+  INDEX idx_payment_callbacks_status (status, created_at),
+  INDEX idx_payment_callbacks_review (review_status, created_at)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci COMMENT='支付回调流水';
+
+CREATE TABLE IF NOT EXISTS node_traffic_history (
+  id BIGINT AUTO_INCREMENT PRIMARY KEY COMMENT '记录 ID',
+  node_id BIGINT NOT NULL COMMENT '节点 ID',
+  period_start DATETIME COMMENT '周期开始时间',
+  period_end DATETIME NOT NULL COMMENT '周期结束（重置）时间',
+  used_traffic BIGINT NOT NULL DEFAULT 0 COMMENT '周期内已用流量（字节）',
+  bandwidth_limit BIGINT NOT NULL DEFAULT 0 COMMENT '周期内流量上限（字节，0 表示不限）',
+  reset_type VARCHAR(16) NOT NULL DEFAULT 'auto' COMMENT '重置方式（auto 按重置日自动/manual 管理员手动）',
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP COMMENT '记录时间',
+  INDEX idx_node_traffic_history_node (node_id, period_end),
+  CONSTRAINT fk_node_traffic_history_node FOREIGN KEY (node_id) REFERENCES nodes(id) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci COMMENT='节点流量周期归档';
+
+CREATE TABLE IF NOT EXISTS admin_action_logs (
+  id BIGINT AUTO_INCREMENT PRIMARY KEY COMMENT '记录 ID',
+  admin_id BIGINT NOT NULL COMMENT '操作管理员 ID',
+  ip VARCHAR(64) COMMENT '操作 IP',
+  route VARCHAR(255) NOT NULL COMMENT '接口（方法 + 路径）',
+  action VARCHAR(64) NOT NULL COMMENT '操作类型（如 user.update、system_config.update）',
+  target_type VARCHAR(32) NOT NULL COMMENT '操作对象类型',
+  target_id VARCHAR(255) COMMENT '操作对象 ID（批量操作为逗号分隔）',
+  before_data JSON COMMENT '变更前的字段值（仅含变更字段，敏感字段已脱敏）',
+  after_data JSON COMMENT '变更后的字段值（仅含变更字段，敏感字段已脱敏）',
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP COMMENT '操作时间',
+  INDEX idx_admin_action_logs_admin (admin_id, created_at),
+  INDEX idx_admin_action_logs_target (target_type, target_id),
+  INDEX idx_admin_action_logs_action (action, created_at)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci COMMENT='管理员操作审计日志';
+
+CREATE TABLE IF NOT EXISTS admin_roles (
+  id BIGINT AUTO_INCREMENT PRIMARY KEY COMMENT '角色 ID',
+  code VARCHAR(32) NOT NULL UNIQUE COMMENT '角色标识',
+  name VARCHAR(64) NOT NULL COMMENT '角色名称',
+  description VARCHAR(255) COMMENT '角色说明',
+  permissions JSON NOT NULL COMMENT '权限范围列表（如 ["tickets","users"]，"*" 为全部）',
+  is_system TINYINT NOT NULL DEFAULT 0 COMMENT '是否内置角色（内置角色不可删除）',
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP COMMENT '创建时间',
+  updated_at DATETIME DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP COMMENT '更新时间'
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci COMMENT='管理员角色';
+
+INSERT IGNORE INTO admin_roles (code, name, description, permissions, is_system) VALUES
+  ('super_admin', '超级管理员', '拥有全部后台权限，可管理角色', '["*"]', 1),
+  ('finance', '财务', '充值、购买、退款、返利提现与支付回调', '["recharge-records","purchase-records","rebate","payment-callbacks","coupons","gift-cards","packages"]', 1),
+  ('support', '客服', '工单与公告处理', '["tickets","announcements"]', 1),
+  ('ops', '运维', '节点、规则、IP 与缓存维护', '["nodes","traffic","task","xray-rules","audit","whitelist","online-ips","blocked-ips","subscription-ua-rules","cache","maintenance"]', 1);
+
+CREATE TABLE IF NOT EXISTS traffic_anomalies (
+  id BIGINT AUTO_INCREMENT PRIMARY KEY COMMENT '记录 ID',
+  node_id BIGINT NOT NULL COMMENT '上报节点 ID',
+  user_id BIGINT NOT NULL COMMENT '上报的用户 ID',
+  upload_traffic BIGINT NOT NULL DEFAULT 0 COMMENT '上报的上传流量（字节）',
+  download_traffic BIGINT NOT NULL DEFAULT 0 COMMENT '上报的下载流量（字节）',
+  deduction_multiplier DECIMAL(10,4) NOT NULL DEFAULT 1 COMMENT '上报时的节点流量倍率',
+  reason VARCHAR(32) NOT NULL COMMENT '隔离原因（throughput/not_eligible/node_disabled）',
+  interval_seconds INT NOT NULL DEFAULT 0 COMMENT '判定时使用的上报间隔（秒）',
+  date DATE NOT NULL COMMENT '上报日期（北京时间）',
+  status VARCHAR(16) NOT NULL DEFAULT 'pending' COMMENT '审核状态（pending/approved/rejected）',
+  reviewed_by BIGINT NULL COMMENT '审核管理员 ID',
+  reviewed_at DATETIME NULL COMMENT '审核时间',
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP COMMENT '上报时间',
+  INDEX idx_traffic_anomalies_status (status, created_at),
+  INDEX idx_traffic_anomalies_node (node_id, status),
+  INDEX idx_traffic_anomalies_user (user_id, created_at),
+  CONSTRAINT fk_traffic_anomalies_node FOREIGN KEY (node_id) REFERENCES nodes(id) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci COMMENT='可疑流量上报（待审核）';
+
 -- 额外索引（若已在表定义中创建则可忽略重复）
 CREATE INDEX IF NOT EXISTS idx_users_uuid ON users (uuid);
 CREATE INDEX IF NOT EXISTS idx_users_token ON users (token);
@@ -649,11 +909,15 @@ CREATE INDEX IF NOT EXISTS idx_users_status ON users (status);
 CREATE INDEX IF NOT EXISTS idx_users_expire_time ON users (expire_time);
 CREATE INDEX IF NOT EXISTS idx_users_class ON users (class);
 CREATE INDEX IF NOT EXISTS idx_users_money ON users (money);
+CREATE INDEX IF NOT EXISTS idx_users_sync_revision ON users (sync_revision);
+CREATE INDEX IF NOT EXISTS idx_users_admin_role ON users (admin_role_id);
 
 CREATE INDEX IF NOT EXISTS idx_nodes_type ON nodes (type);
 CREATE INDEX IF NOT EXISTS idx_nodes_status ON nodes (status);
 CREATE INDEX IF NOT EXISTS idx_nodes_class ON nodes (node_class);
 
+CREATE INDEX IF NOT EXISTS idx_subscriptions_token ON subscriptions (token_id);
+
 CREATE INDEX IF NOT EXISTS idx_traffic_logs_user_date ON traffic_logs (user_id, date);
 CREATE INDEX IF NOT EXISTS idx_traffic_logs_node_date ON traffic_logs (node_id, date);
 
@@ -688,3 +952,57 @@ CREATE INDEX IF NOT EXISTS idx_message_queue_user ON message_queue (user_id);
 CREATE INDEX IF NOT EXISTS idx_packages_status ON packages (status);
 CREATE INDEX IF NOT EXISTS idx_packages_level ON packages (level);
 CREATE INDEX IF NOT EXISTS idx_packages_price ON packages (price);
+
+-- 节点增量同步：影响节点用户列表的字段变化时递增 users.sync_revision
+DELIMITER $$
+
+CREATE TRIGGER IF NOT EXISTS trg_users_sync_revision_insert
+BEFORE INSERT ON users
+FOR EACH ROW
+BEGIN
+  UPDATE sync_revisions SET revision = LAST_INSERT_ID(revision + 1) WHERE name = 'users';
+  SET NEW.sync_revision = LAST_INSERT_ID();
+END$$
+
+CREATE TRIGGER IF NOT EXISTS trg_users_sync_revision_update
+BEFORE UPDATE ON users
+FOR EACH ROW
+BEGIN
+  IF NOT (NEW.uuid <=> OLD.uuid)
+    OR NOT (NEW.passwd <=> OLD.passwd)
+    OR NOT (NEW.status <=> OLD.status)
+    OR NOT (NEW.class <=> OLD.class)
+    OR NOT (NEW.expire_time <=> OLD.expire_time)
+    OR NOT (NEW.class_expire_time <=> OLD.class_expire_time)
+    OR NOT (NEW.speed_limit <=> OLD.speed_limit)
+    OR NOT (NEW.device_limit <=> OLD.device_limit)
+    OR NOT (NEW.tcp_limit <=> OLD.tcp_limit)
+    OR NOT ((NEW.transfer_enable > NEW.transfer_total) <=> (OLD.transfer_enable > OLD.transfer_total))
+  THEN
+    UPDATE sync_revisions SET revision = LAST_INSERT_ID(revision + 1) WHERE name = 'users';
+    SET NEW.sync_revision = LAST_INSERT_ID();
+  END IF;
+END$$
+
+CREATE TRIGGER IF NOT EXISTS trg_users_sync_revision_delete
+AFTER DELETE ON users
+FOR EACH ROW
+BEGIN
+  UPDATE sync_revisions SET revision = LAST_INSERT_ID(revision + 1) WHERE name = 'users';
+  REPLACE INTO user_sync_tombstones (user_id, revision) VALUES (OLD.id, LAST_INSERT_ID());
+END$$
+
+CREATE TRIGGER IF NOT EXISTS trg_nodes_users_sync_floor
+BEFORE UPDATE ON nodes
+FOR EACH ROW
+BEGIN
+  IF NOT (NEW.node_class <=> OLD.node_class)
+    OR NOT (NEW.type <=> OLD.type)
+    OR NOT (NEW.node_config <=> OLD.node_config)
+  THEN
+    UPDATE sync_revisions SET revision = LAST_INSERT_ID(revision + 1) WHERE name = 'users';
+    SET NEW.users_sync_floor = LAST_INSERT_ID();
+  END IF;
+END$$
+
+DELIMITER ;
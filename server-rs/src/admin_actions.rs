@@ -0,0 +1,148 @@
+use axum::http::HeaderMap;
+use serde_json::{Map, Value};
+
+use crate::client_ip::client_ip;
+use crate::state::AppState;
+
+const MASKED_VALUE: &str = "******";
+const MAX_TARGET_ID_LEN: usize = 255;
+const SENSITIVE_KEYWORDS: [&str; 5] = ["password", "passwd", "secret", "token", "key"];
+
+/// 一次管理员操作：`before`/`after` 为变更前后的快照，入库时只保留有差异的字段
+pub struct AdminAction<'a> {
+    pub action: &'a str,
+    pub route: &'a str,
+    pub target_type: &'a str,
+    pub target_id: Option<String>,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+/// 写入管理员操作日志；失败只记录警告，不影响业务请求
+pub async fn record_admin_action(
+    state: &AppState,
+    headers: &HeaderMap,
+    admin_id: i64,
+    action: AdminAction<'_>,
+) {
+    let (before, after) = diff_snapshots(action.before, action.after);
+    let result = sqlx::query(
+        r#"
+    INSERT INTO admin_action_logs
+      (admin_id, ip, route, action, target_type, target_id, before_data, after_data, created_at)
+    VALUES (?, ?, ?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP)
+    "#,
+    )
+    .bind(admin_id)
+    .bind(client_ip(headers))
+    .bind(action.route)
+    .bind(action.action)
+    .bind(action.target_type)
+    .bind(
+        action
+            .target_id
+            .map(|value| value.chars().take(MAX_TARGET_ID_LEN).collect::<String>()),
+    )
+    .bind(before.map(|value| value.to_string()))
+    .bind(after.map(|value| value.to_string()))
+    .execute(&state.db)
+    .await;
+    if let Err(err) = result {
+        tracing::warn!(
+            "[admin_actions] record {} by admin {admin_id} failed: {err}",
+            action.action
+        );
+    }
+}
+
+/// 对象快照只保留前后不同的键；敏感字段（密码、密钥、令牌等）统一脱敏
+fn diff_snapshots(before: Option<Value>, after: Option<Value>) -> (Option<Value>, Option<Value>) {
+    let (before, after) = match (before, after) {
+        (Some(Value::Object(before)), Some(Value::Object(after))) => {
+            let mut changed_before = Map::new();
+            let mut changed_after = Map::new();
+            for key in before.keys().chain(after.keys()) {
+                let old = before.get(key).cloned().unwrap_or(Value::Null);
+                let new = after.get(key).cloned().unwrap_or(Value::Null);
+                if old != new && !changed_after.contains_key(key) {
+                    changed_before.insert(key.clone(), old);
+                    changed_after.insert(key.clone(), new);
+                }
+            }
+            (
+                Some(Value::Object(changed_before)),
+                Some(Value::Object(changed_after)),
+            )
+        }
+        other => other,
+    };
+    (before.map(mask_sensitive), after.map(mask_sensitive))
+}
+
+fn mask_sensitive(value: Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(key, value)| {
+                    let value = if is_sensitive_key(&key) && !is_blank(&value) {
+                        Value::String(MASKED_VALUE.to_string())
+                    } else {
+                        mask_sensitive(value)
+                    };
+                    (key, value)
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.into_iter().map(mask_sensitive).collect()),
+        other => other,
+    }
+}
+
+fn is_sensitive_key(key: &str) -> bool {
+    let key = key.to_lowercase();
+    SENSITIVE_KEYWORDS
+        .iter()
+        .any(|keyword| key.contains(keyword))
+}
+
+fn is_blank(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::String(text) => text.is_empty(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn diff_keeps_changed_fields_and_masks_secrets() {
+        let (before, after) = diff_snapshots(
+            Some(json!({ "email": "a@example.com", "money": 10.0, "bark_key": "", "status": 1 })),
+            Some(
+                json!({ "email": "a@example.com", "money": 25.5, "bark_key": "abc", "status": 0 }),
+            ),
+        );
+        assert_eq!(
+            before,
+            Some(json!({ "money": 10.0, "bark_key": "", "status": 1 }))
+        );
+        assert_eq!(
+            after,
+            Some(json!({ "money": 25.5, "bark_key": "******", "status": 0 }))
+        );
+
+        let (before, after) = diff_snapshots(
+            None,
+            Some(json!({ "epay_key": "k", "config": { "api_token": "t", "port": 443 } })),
+        );
+        assert_eq!(before, None);
+        assert_eq!(
+            after,
+            Some(json!({ "epay_key": "******", "config": { "api_token": "******", "port": 443 } }))
+        );
+    }
+}
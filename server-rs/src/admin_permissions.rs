@@ -0,0 +1,173 @@
+use serde_json::Value;
+use sqlx::Row;
+
+use crate::state::AppState;
+
+/// 通配权限（超级管理员），同时也是角色管理接口所需的权限
+pub const ALL_PERMISSIONS: &str = "*";
+
+/// 可分配给角色的权限范围，与后台路由分组一一对应
+pub const PERMISSION_SCOPES: [(&str, &str); 28] = [
+    ("users", "用户管理"),
+    ("nodes", "节点管理"),
+    ("system-configs", "系统配置"),
+    ("tickets", "工单"),
+    ("announcements", "公告"),
+    ("coupons", "优惠券"),
+    ("gift-cards", "礼品卡"),
+    ("traffic", "流量统计"),
+    ("task", "定时任务"),
+    ("packages", "套餐"),
+    ("shared-ids", "共享账号"),
+    ("rebate", "返利与提现"),
+    ("login-logs", "登录日志"),
+    ("subscription-logs", "订阅日志"),
+    ("audit", "审计规则与日志"),
+    ("xray-rules", "路由规则"),
+    ("whitelist", "白名单"),
+    ("online-ips", "在线 IP"),
+    ("blocked-ips", "IP 封禁"),
+    ("subscription-ua-rules", "订阅 UA 规则"),
+    ("subscription-templates", "订阅模板"),
+    ("proxy-groups", "订阅分组"),
+    ("cache", "缓存管理"),
+    ("maintenance", "维护工具"),
+    ("recharge-records", "充值记录"),
+    ("purchase-records", "购买记录与退款"),
+    ("payment-callbacks", "支付回调"),
+    ("action-logs", "操作日志"),
+];
+
+pub struct AdminAccess {
+    pub role_code: Option<String>,
+    pub permissions: Vec<String>,
+}
+
+impl AdminAccess {
+    pub fn allows(&self, scope: &str) -> bool {
+        self.permissions
+            .iter()
+            .any(|permission| permission == ALL_PERMISSIONS || permission == scope)
+    }
+}
+
+/// 读取管理员的角色与权限；非管理员返回 None。
+/// 未分配角色的管理员（升级前的旧账号）视为超级管理员；角色已不存在时不授予任何权限
+pub async fn load_admin_access(
+    state: &AppState,
+    user_id: i64,
+) -> Result<Option<AdminAccess>, String> {
+    let row = sqlx::query(
+        r#"
+    SELECT u.is_admin, u.admin_role_id, r.id AS role_id, r.code, CAST(r.permissions AS CHAR) AS permissions
+    FROM users u
+    LEFT JOIN admin_roles r ON r.id = u.admin_role_id
+    WHERE u.id = ?
+    "#,
+    )
+    .bind(user_id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|err| err.to_string())?;
+    let Some(row) = row else {
+        return Ok(None);
+    };
+    let read_i64 = |key: &str| row.try_get::<Option<i64>, _>(key).ok().flatten();
+    if read_i64("is_admin").unwrap_or(0) != 1 {
+        return Ok(None);
+    }
+
+    if read_i64("admin_role_id").is_none() {
+        return Ok(Some(AdminAccess {
+            role_code: None,
+            permissions: vec![ALL_PERMISSIONS.to_string()],
+        }));
+    }
+    let permissions = if read_i64("role_id").is_some() {
+        parse_permissions(
+            &row.try_get::<Option<String>, _>("permissions")
+                .ok()
+                .flatten()
+                .unwrap_or_default(),
+        )
+    } else {
+        Vec::new()
+    };
+    Ok(Some(AdminAccess {
+        role_code: row.try_get::<Option<String>, _>("code").ok().flatten(),
+        permissions,
+    }))
+}
+
+/// 解析数据库中的权限 JSON 数组，忽略未知的权限范围
+pub fn parse_permissions(raw: &str) -> Vec<String> {
+    let values = match serde_json::from_str::<Value>(raw) {
+        Ok(Value::Array(items)) => items
+            .into_iter()
+            .filter_map(|item| item.as_str().map(str::to_string))
+            .filter(|item| item == ALL_PERMISSIONS || is_known_scope(item))
+            .collect::<Vec<String>>(),
+        _ => Vec::new(),
+    };
+    normalize_permissions(&values).unwrap_or_default()
+}
+
+/// 校验并去重权限列表；包含 `*` 时只保留 `*`
+pub fn normalize_permissions(values: &[String]) -> Result<Vec<String>, String> {
+    let mut permissions: Vec<String> = Vec::new();
+    for value in values {
+        let value = value.trim();
+        if value.is_empty() {
+            continue;
+        }
+        if value == ALL_PERMISSIONS {
+            return Ok(vec![ALL_PERMISSIONS.to_string()]);
+        }
+        if !is_known_scope(value) {
+            return Err(format!("未知的权限范围: {value}"));
+        }
+        if !permissions.iter().any(|item| item == value) {
+            permissions.push(value.to_string());
+        }
+    }
+    Ok(permissions)
+}
+
+fn is_known_scope(value: &str) -> bool {
+    PERMISSION_SCOPES.iter().any(|(scope, _)| *scope == value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn permissions_are_validated_and_wildcard_wins() {
+        let input = vec![
+            "tickets".to_string(),
+            " users ".to_string(),
+            "tickets".to_string(),
+        ];
+        assert_eq!(
+            normalize_permissions(&input),
+            Ok(vec!["tickets".to_string(), "users".to_string()])
+        );
+        assert!(normalize_permissions(&["roles".to_string()]).is_err());
+        assert_eq!(
+            parse_permissions(r#"["nodes", "*"]"#),
+            vec![ALL_PERMISSIONS.to_string()]
+        );
+        assert_eq!(
+            parse_permissions(r#"["nodes", "unknown"]"#),
+            vec!["nodes".to_string()]
+        );
+
+        let support = AdminAccess {
+            role_code: Some("support".to_string()),
+            permissions: parse_permissions(r#"["tickets"]"#),
+        };
+        assert!(support.allows("tickets"));
+        assert!(!support.allows("rebate"));
+        assert!(!support.allows(ALL_PERMISSIONS));
+    }
+}
@@ -0,0 +1,317 @@
+use std::net::IpAddr;
+
+use ipnet::IpNet;
+use regex::{Regex, RegexBuilder};
+
+/// 正则编译体积上限，避免管理员误填超大表达式
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+/// 审计规则类型
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuditRuleType {
+    Regex,
+    DomainSuffix,
+    DomainKeyword,
+    IpCidr,
+    PortRange,
+}
+
+impl AuditRuleType {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "regex" | "regexp" => Some(Self::Regex),
+            "domain_suffix" | "domain" => Some(Self::DomainSuffix),
+            "domain_keyword" | "keyword" => Some(Self::DomainKeyword),
+            "ip_cidr" | "ip" => Some(Self::IpCidr),
+            "port_range" | "port" => Some(Self::PortRange),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Regex => "regex",
+            Self::DomainSuffix => "domain_suffix",
+            Self::DomainKeyword => "domain_keyword",
+            Self::IpCidr => "ip_cidr",
+            Self::PortRange => "port_range",
+        }
+    }
+
+    /// soga 规则字符串前缀
+    fn soga_prefix(&self) -> &'static str {
+        match self {
+            Self::Regex => "regexp:",
+            Self::DomainSuffix => "domain:",
+            Self::DomainKeyword => "keyword:",
+            Self::IpCidr => "ip:",
+            Self::PortRange => "port:",
+        }
+    }
+}
+
+/// 编译后的审计规则
+pub enum CompiledAuditRule {
+    Regex(Regex),
+    DomainSuffix(String),
+    DomainKeyword(String),
+    IpCidr(IpNet),
+    PortRange(Vec<(u16, u16)>),
+}
+
+/// 待检测的目标地址（域名/IP + 端口）
+#[derive(Debug, Default)]
+pub struct AuditTarget {
+    pub raw: String,
+    pub host: Option<String>,
+    pub ip: Option<IpAddr>,
+    pub port: Option<u16>,
+}
+
+/// 兼容旧数据：`regexp:xxx` 等带前缀的规则拆分为类型与表达式
+pub fn split_legacy_rule(raw: &str) -> (Option<AuditRuleType>, &str) {
+    let trimmed = raw.trim();
+    if let Some((prefix, rest)) = trimmed.split_once(':') {
+        if !prefix.contains(['.', '/', '\\', '*']) {
+            if let Some(rule_type) = AuditRuleType::parse(prefix) {
+                return (Some(rule_type), rest.trim());
+            }
+        }
+    }
+    (None, trimmed)
+}
+
+/// 校验并规范化规则表达式
+pub fn normalize_audit_rule(rule_type: AuditRuleType, pattern: &str) -> Result<String, String> {
+    let trimmed = pattern.trim();
+    if trimmed.is_empty() {
+        return Err("规则内容不能为空".to_string());
+    }
+    let normalized = match rule_type {
+        AuditRuleType::Regex => trimmed.to_string(),
+        AuditRuleType::DomainSuffix | AuditRuleType::DomainKeyword => {
+            let value = trimmed.trim_start_matches('.').trim_end_matches('.');
+            let value = value.to_lowercase();
+            if value.is_empty() || value.contains(|ch: char| ch.is_whitespace() || ch == '/') {
+                return Err("域名格式无效".to_string());
+            }
+            value
+        }
+        AuditRuleType::IpCidr => {
+            if trimmed.contains('/') {
+                trimmed
+                    .parse::<IpNet>()
+                    .map(|net| net.trunc().to_string())
+                    .map_err(|_| "IP 或 CIDR 格式无效".to_string())?
+            } else {
+                trimmed
+                    .parse::<IpAddr>()
+                    .map(|ip| ip.to_string())
+                    .map_err(|_| "IP 或 CIDR 格式无效".to_string())?
+            }
+        }
+        AuditRuleType::PortRange => parse_port_ranges(trimmed)?
+            .into_iter()
+            .map(|(start, end)| {
+                if start == end {
+                    start.to_string()
+                } else {
+                    format!("{start}-{end}")
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(","),
+    };
+    compile_audit_rule(rule_type, &normalized)?;
+    Ok(normalized)
+}
+
+pub fn compile_audit_rule(
+    rule_type: AuditRuleType,
+    pattern: &str,
+) -> Result<CompiledAuditRule, String> {
+    match rule_type {
+        AuditRuleType::Regex => RegexBuilder::new(pattern)
+            .size_limit(REGEX_SIZE_LIMIT)
+            .build()
+            .map(CompiledAuditRule::Regex)
+            .map_err(|err| format!("正则表达式无效: {err}")),
+        AuditRuleType::DomainSuffix => Ok(CompiledAuditRule::DomainSuffix(pattern.to_lowercase())),
+        AuditRuleType::DomainKeyword => {
+            Ok(CompiledAuditRule::DomainKeyword(pattern.to_lowercase()))
+        }
+        AuditRuleType::IpCidr => {
+            let net = if pattern.contains('/') {
+                pattern.parse::<IpNet>().ok()
+            } else {
+                pattern.parse::<IpAddr>().ok().map(IpNet::from)
+            };
+            net.map(CompiledAuditRule::IpCidr)
+                .ok_or_else(|| "IP 或 CIDR 格式无效".to_string())
+        }
+        AuditRuleType::PortRange => parse_port_ranges(pattern).map(CompiledAuditRule::PortRange),
+    }
+}
+
+fn parse_port_ranges(raw: &str) -> Result<Vec<(u16, u16)>, String> {
+    let mut ranges = Vec::new();
+    for part in raw
+        .split(',')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+    {
+        let (start, end) = match part.split_once('-') {
+            Some((start, end)) => (start.trim(), end.trim()),
+            None => (part, part),
+        };
+        let start = start.parse::<u16>().ok().filter(|value| *value > 0);
+        let end = end.parse::<u16>().ok().filter(|value| *value > 0);
+        match (start, end) {
+            (Some(start), Some(end)) if start <= end => ranges.push((start, end)),
+            _ => return Err(format!("端口范围无效: {part}")),
+        }
+    }
+    if ranges.is_empty() {
+        return Err("端口范围不能为空".to_string());
+    }
+    Ok(ranges)
+}
+
+/// 转换为节点（soga）使用的规则字符串
+pub fn to_soga_rule(rule_type: AuditRuleType, pattern: &str) -> String {
+    format!("{}{}", rule_type.soga_prefix(), pattern)
+}
+
+/// 解析目标地址，支持 `host:port`、`[ipv6]:port`、URL 与纯 IP/域名
+pub fn parse_audit_target(raw: &str) -> AuditTarget {
+    let raw = raw.trim().to_string();
+    let mut rest = raw.as_str();
+    if let Some((_, value)) = rest.split_once("://") {
+        rest = value;
+    }
+    rest = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    if let Some((_, value)) = rest.rsplit_once('@') {
+        rest = value;
+    }
+
+    let (host, port) = if let Some(value) = rest.strip_prefix('[') {
+        match value.split_once(']') {
+            Some((host, tail)) => (host, tail.strip_prefix(':')),
+            None => (value, None),
+        }
+    } else if rest.matches(':').count() == 1 {
+        let (host, port) = rest.split_once(':').unwrap_or((rest, ""));
+        (host, Some(port))
+    } else {
+        (rest, None)
+    };
+
+    let host = host.trim().trim_end_matches('.').to_lowercase();
+    let port = port.and_then(|value| value.trim().parse::<u16>().ok());
+    let ip = host.parse::<IpAddr>().ok();
+    AuditTarget {
+        raw,
+        host: if host.is_empty() { None } else { Some(host) },
+        ip,
+        port,
+    }
+}
+
+impl CompiledAuditRule {
+    pub fn matches(&self, target: &AuditTarget) -> bool {
+        let domain = target.host.as_deref().filter(|_| target.ip.is_none());
+        match self {
+            Self::Regex(re) => {
+                re.is_match(&target.raw)
+                    || target.host.as_deref().is_some_and(|host| re.is_match(host))
+            }
+            Self::DomainSuffix(suffix) => domain.is_some_and(|host| {
+                host == suffix
+                    || host
+                        .strip_suffix(suffix.as_str())
+                        .is_some_and(|head| head.ends_with('.'))
+            }),
+            Self::DomainKeyword(keyword) => domain.is_some_and(|host| host.contains(keyword)),
+            Self::IpCidr(net) => target.ip.is_some_and(|ip| net.contains(&ip)),
+            Self::PortRange(ranges) => target.port.is_some_and(|port| {
+                ranges
+                    .iter()
+                    .any(|(start, end)| *start <= port && port <= *end)
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(rule_type: AuditRuleType, pattern: &str, target: &str) -> bool {
+        let pattern = normalize_audit_rule(rule_type, pattern).expect("valid rule");
+        compile_audit_rule(rule_type, &pattern)
+            .expect("compile")
+            .matches(&parse_audit_target(target))
+    }
+
+    #[test]
+    fn typed_rules_match_destinations() {
+        assert!(check(
+            AuditRuleType::Regex,
+            r".*\.torrent",
+            "tracker.example.com/a.torrent"
+        ));
+        assert!(check(
+            AuditRuleType::DomainSuffix,
+            ".Example.com",
+            "cdn.example.com:443"
+        ));
+        assert!(check(
+            AuditRuleType::DomainSuffix,
+            "example.com",
+            "example.com"
+        ));
+        assert!(!check(
+            AuditRuleType::DomainSuffix,
+            "example.com",
+            "badexample.com:443"
+        ));
+        assert!(check(
+            AuditRuleType::DomainKeyword,
+            "porn",
+            "https://www.pornsite.net/x"
+        ));
+        assert!(check(
+            AuditRuleType::IpCidr,
+            "10.1.2.3/16",
+            "10.1.200.9:8080"
+        ));
+        assert!(check(
+            AuditRuleType::IpCidr,
+            "2001:db8::/32",
+            "[2001:db8::1]:443"
+        ));
+        assert!(!check(
+            AuditRuleType::IpCidr,
+            "10.0.0.0/8",
+            "example.com:443"
+        ));
+        assert!(check(
+            AuditRuleType::PortRange,
+            "25, 6881-6889",
+            "1.2.3.4:6885"
+        ));
+        assert!(!check(AuditRuleType::PortRange, "25", "1.2.3.4"));
+    }
+
+    #[test]
+    fn invalid_rules_are_rejected() {
+        assert!(normalize_audit_rule(AuditRuleType::Regex, "(unclosed").is_err());
+        assert!(normalize_audit_rule(AuditRuleType::IpCidr, "10.0.0.0/33").is_err());
+        assert!(normalize_audit_rule(AuditRuleType::PortRange, "9000-80").is_err());
+        assert_eq!(
+            split_legacy_rule("regexp:.*\\.torrent"),
+            (Some(AuditRuleType::Regex), ".*\\.torrent")
+        );
+        assert_eq!(split_legacy_rule("a.b:c"), (None, "a.b:c"));
+    }
+}
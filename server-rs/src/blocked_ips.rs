@@ -0,0 +1,193 @@
+use std::net::IpAddr;
+
+use axum::body::Body;
+use axum::extract::State;
+use axum::http::{Request, StatusCode};
+use axum::middleware::Next;
+use axum::response::Response;
+use chrono::NaiveDateTime;
+use ipnet::IpNet;
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+
+use crate::cache::{cache_delete, cache_get, cache_set};
+use crate::client_ip::request_client_ip;
+use crate::node_hub::{notify_nodes, NodeEvent};
+use crate::response::error;
+use crate::state::AppState;
+
+pub const BLOCKED_IPS_CACHE_KEY: &str = "blocked_ips";
+/// 缓存时间较短，保证到期的封禁能及时失效
+const BLOCKED_IPS_CACHE_TTL: u64 = 60;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BlockedIpEntry {
+    pub id: i64,
+    pub ip: String,
+    pub expires_at: Option<String>,
+}
+
+/// 规范化封禁目标：单个 IP 或 CIDR 网段，返回统一格式的字符串
+pub fn normalize_block_target(raw: &str) -> Option<String> {
+    let trimmed = raw.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+    if trimmed.contains('/') {
+        return trimmed
+            .parse::<IpNet>()
+            .ok()
+            .map(|net| net.trunc().to_string());
+    }
+    trimmed.parse::<IpAddr>().ok().map(|ip| ip.to_string())
+}
+
+fn entry_matches(target: &str, ip: &IpAddr) -> bool {
+    if target.contains('/') {
+        target
+            .parse::<IpNet>()
+            .map(|net| net.contains(ip))
+            .unwrap_or(false)
+    } else {
+        target
+            .parse::<IpAddr>()
+            .map(|value| value == *ip)
+            .unwrap_or(false)
+    }
+}
+
+/// 读取当前生效（未过期）的封禁列表
+pub async fn load_active_blocked_ips(state: &AppState) -> Result<Vec<BlockedIpEntry>, String> {
+    if let Some(cached) = cache_get(state, BLOCKED_IPS_CACHE_KEY).await {
+        if let Ok(entries) = serde_json::from_str::<Vec<BlockedIpEntry>>(&cached) {
+            return Ok(entries);
+        }
+    }
+
+    let rows = sqlx::query(
+        r#"
+    SELECT id, ip, DATE_FORMAT(expires_at, '%Y-%m-%d %H:%i:%s') AS expires_at
+    FROM blocked_ips
+    WHERE expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP
+    ORDER BY id ASC
+    "#,
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|err| err.to_string())?;
+
+    let entries = rows
+        .into_iter()
+        .map(|row| BlockedIpEntry {
+            id: row.try_get::<i64, _>("id").unwrap_or(0),
+            ip: row
+                .try_get::<Option<String>, _>("ip")
+                .ok()
+                .flatten()
+                .unwrap_or_default(),
+            expires_at: row
+                .try_get::<Option<String>, _>("expires_at")
+                .ok()
+                .flatten(),
+        })
+        .filter(|entry| !entry.ip.is_empty())
+        .collect::<Vec<BlockedIpEntry>>();
+
+    cache_set(
+        state,
+        BLOCKED_IPS_CACHE_KEY,
+        &serde_json::to_string(&entries).unwrap_or_default(),
+        BLOCKED_IPS_CACHE_TTL,
+    )
+    .await;
+    Ok(entries)
+}
+
+/// 新增或更新封禁记录（同一 IP/网段重复封禁时覆盖原因与到期时间）
+pub async fn upsert_blocked_ip(
+    state: &AppState,
+    target: &str,
+    reason: Option<&str>,
+    expires_at: Option<NaiveDateTime>,
+    created_by: i64,
+) -> Result<i64, String> {
+    let result = sqlx::query(
+        r#"
+    INSERT INTO blocked_ips (ip, reason, expires_at, created_by, created_at, updated_at)
+    VALUES (?, ?, ?, ?, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
+    ON DUPLICATE KEY UPDATE
+      id = LAST_INSERT_ID(id),
+      reason = VALUES(reason),
+      expires_at = VALUES(expires_at),
+      created_by = VALUES(created_by),
+      updated_at = CURRENT_TIMESTAMP
+    "#,
+    )
+    .bind(target)
+    .bind(reason)
+    .bind(expires_at)
+    .bind(created_by)
+    .execute(&state.db)
+    .await
+    .map_err(|err| err.to_string())?;
+    invalidate_blocked_ips(state).await;
+    Ok(result.last_insert_id() as i64)
+}
+
+/// 封禁列表变更后清理缓存并通知节点
+pub async fn invalidate_blocked_ips(state: &AppState) {
+    cache_delete(state, BLOCKED_IPS_CACHE_KEY).await;
+    notify_nodes(state, NodeEvent::BlockListChanged).await;
+}
+
+pub async fn is_ip_blocked(state: &AppState, ip: &str) -> bool {
+    let Ok(ip) = ip.trim().parse::<IpAddr>() else {
+        return false;
+    };
+    match load_active_blocked_ips(state).await {
+        Ok(entries) => entries.iter().any(|entry| entry_matches(&entry.ip, &ip)),
+        Err(err) => {
+            tracing::warn!("[blocked_ips] load failed: {err}");
+            false
+        }
+    }
+}
+
+/// 拦截被封禁 IP 的登录/注册等认证请求
+pub async fn blocked_ip_middleware(
+    State(state): State<AppState>,
+    req: Request<Body>,
+    next: Next,
+) -> Response {
+    if req.uri().path().starts_with("/api/auth/") {
+        if let Some(ip) = request_client_ip(&req, &state.env.trusted_proxies) {
+            if is_ip_blocked(&state, &ip.to_string()).await {
+                return error(StatusCode::FORBIDDEN, "当前 IP 已被封禁", None);
+            }
+        }
+    }
+    next.run(req).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn block_targets_normalize_and_match() {
+        assert_eq!(
+            normalize_block_target(" 203.0.113.9/24 ").as_deref(),
+            Some("203.0.113.0/24")
+        );
+        assert_eq!(
+            normalize_block_target("2001:db8::1").as_deref(),
+            Some("2001:db8::1")
+        );
+        assert_eq!(normalize_block_target("not-an-ip"), None);
+
+        let ip = "203.0.113.77".parse::<IpAddr>().unwrap();
+        assert!(entry_matches("203.0.113.0/24", &ip));
+        assert!(!entry_matches("203.0.114.0/24", &ip));
+        assert!(entry_matches("203.0.113.77", &ip));
+    }
+}
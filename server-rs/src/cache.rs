@@ -84,6 +84,41 @@ pub async fn cache_get_redis_only(state: &AppState, key: &str) -> Option<String>
     None
 }
 
+/// 基于 Redis `SET NX EX` 的分布式锁；未配置 Redis 或 Redis 异常时返回 None
+pub async fn redis_try_lock(
+    state: &AppState,
+    key: &str,
+    owner: &str,
+    ttl_seconds: u64,
+) -> Option<bool> {
+    let mut conn = state.redis.clone()?;
+    let result: redis::RedisResult<Option<String>> = redis::cmd("SET")
+        .arg(redis_key(state, key))
+        .arg(owner)
+        .arg("NX")
+        .arg("EX")
+        .arg(ttl_seconds)
+        .query_async(&mut conn)
+        .await;
+    result.ok().map(|value| value.is_some())
+}
+
+/// 仅当锁仍由 owner 持有时释放，避免误删其他实例在锁过期后重新获取的锁
+pub async fn redis_release_lock(state: &AppState, key: &str, owner: &str) {
+    let mut conn = match state.redis.clone() {
+        Some(conn) => conn,
+        None => return,
+    };
+    let script = redis::Script::new(
+        r#"if redis.call("GET", KEYS[1]) == ARGV[1] then return redis.call("DEL", KEYS[1]) else return 0 end"#,
+    );
+    let _: redis::RedisResult<i32> = script
+        .key(redis_key(state, key))
+        .arg(owner)
+        .invoke_async(&mut conn)
+        .await;
+}
+
 pub async fn cache_delete_by_prefix(state: &AppState, prefix: &str) {
     let redis = match state.redis.clone() {
         Some(conn) => conn,
@@ -0,0 +1,125 @@
+use std::net::{IpAddr, SocketAddr};
+
+use axum::body::Body;
+use axum::extract::connect_info::ConnectInfo;
+use axum::http::{HeaderMap, HeaderName, HeaderValue, Request};
+use ipnet::IpNet;
+
+/// app_middleware 写入解析结果的请求头；客户端自带的同名头会被覆盖，不可伪造
+pub const CLIENT_IP_HEADER: &str = "x-real-ip";
+
+/// 解析真实客户端 IP：仅当 socket 对端是受信任代理时才采用转发头，否则直接使用对端地址
+pub fn resolve_client_ip(peer: IpAddr, headers: &HeaderMap, trusted: &[IpNet]) -> IpAddr {
+    let peer = peer.to_canonical();
+    if !is_trusted(&peer, trusted) {
+        return peer;
+    }
+
+    // X-Forwarded-For 由各级代理依次追加，从右往左取第一个非受信任代理的地址
+    let forwarded = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(parse_ip)
+        .collect::<Vec<IpAddr>>();
+    if let Some(ip) = forwarded.iter().rev().find(|ip| !is_trusted(ip, trusted)) {
+        return *ip;
+    }
+    if let Some(ip) = forwarded.first() {
+        return *ip;
+    }
+
+    for key in [CLIENT_IP_HEADER, "cf-connecting-ip"] {
+        if let Some(ip) = headers
+            .get(key)
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_ip)
+        {
+            return ip;
+        }
+    }
+    peer
+}
+
+/// 按连接信息解析请求的客户端 IP；缺少 ConnectInfo（非 TCP 调用）时返回 None
+pub fn request_client_ip(req: &Request<Body>, trusted: &[IpNet]) -> Option<IpAddr> {
+    req.extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|info| resolve_client_ip(info.0.ip(), req.headers(), trusted))
+}
+
+/// 用解析后的客户端 IP 覆盖 `CLIENT_IP_HEADER`，无法解析时移除该头
+pub fn apply_client_ip_header(req: &mut Request<Body>, trusted: &[IpNet]) {
+    let name = HeaderName::from_static(CLIENT_IP_HEADER);
+    match request_client_ip(req, trusted).and_then(|ip| HeaderValue::from_str(&ip.to_string()).ok())
+    {
+        Some(value) => {
+            req.headers_mut().insert(name, value);
+        }
+        None => {
+            req.headers_mut().remove(name);
+        }
+    }
+}
+
+/// 读取 app_middleware 解析后的客户端 IP
+pub fn client_ip(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(CLIENT_IP_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+}
+
+fn is_trusted(ip: &IpAddr, trusted: &[IpNet]) -> bool {
+    trusted.iter().any(|net| net.contains(ip))
+}
+
+fn parse_ip(value: &str) -> Option<IpAddr> {
+    value
+        .trim()
+        .parse::<IpAddr>()
+        .ok()
+        .map(|ip| ip.to_canonical())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (key, value) in pairs {
+            map.append(*key, HeaderValue::from_str(value).unwrap());
+        }
+        map
+    }
+
+    #[test]
+    fn forwarding_headers_require_trusted_peer() {
+        let trusted = vec!["10.0.0.0/8".parse::<IpNet>().unwrap()];
+        let forged = headers(&[
+            ("x-client-ip", "1.1.1.1"),
+            ("x-forwarded-for", "1.1.1.1"),
+            ("x-real-ip", "1.1.1.1"),
+        ]);
+        let peer = "203.0.113.9".parse::<IpAddr>().unwrap();
+        assert_eq!(resolve_client_ip(peer, &forged, &trusted), peer);
+
+        let proxy = "10.0.0.2".parse::<IpAddr>().unwrap();
+        let chained = headers(&[("x-forwarded-for", "1.1.1.1, 198.51.100.7, 10.0.0.3")]);
+        assert_eq!(
+            resolve_client_ip(proxy, &chained, &trusted).to_string(),
+            "198.51.100.7"
+        );
+        let real_ip = headers(&[("x-real-ip", "198.51.100.8")]);
+        assert_eq!(
+            resolve_client_ip(proxy, &real_ip, &trusted).to_string(),
+            "198.51.100.8"
+        );
+        let mapped = "::ffff:203.0.113.9".parse::<IpAddr>().unwrap();
+        assert_eq!(resolve_client_ip(mapped, &HeaderMap::new(), &[]), peer);
+    }
+}
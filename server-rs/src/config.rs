@@ -1,6 +1,8 @@
 use std::env;
 use std::net::IpAddr;
 
+use ipnet::IpNet;
+
 #[derive(Clone, Debug)]
 #[allow(dead_code)]
 pub struct AppEnv {
@@ -48,6 +50,7 @@ pub struct AppEnv {
     pub two_factor_secret_key: Option<String>,
     pub turnstile_secret_key: Option<String>,
     pub node_api_key: Option<String>,
+    pub node_api_key_legacy_fallback: bool,
     pub epay_key: Option<String>,
     pub epay_pid: Option<String>,
     pub epay_api_url: Option<String>,
@@ -72,6 +75,8 @@ pub struct AppEnv {
     pub mail_verification_attempt_limit: Option<String>,
     pub passkey_rp_id: Option<String>,
     pub passkey_origin: Option<String>,
+    pub job_scheduler_enabled: bool,
+    pub trusted_proxies: Vec<IpNet>,
 }
 
 pub fn apply_dotenv(path: Option<&str>) -> Result<(), String> {
@@ -132,6 +137,13 @@ fn parse_u64_opt(key: &str) -> Result<Option<u64>, String> {
     }
 }
 
+fn parse_bool(key: &str, default: bool) -> bool {
+    match get_env(key) {
+        Some(value) => matches!(value.to_lowercase().as_str(), "1" | "true" | "yes" | "on"),
+        None => default,
+    }
+}
+
 fn parse_ip(key: &str, default: &str) -> Result<IpAddr, String> {
     match get_env(key) {
         Some(value) => value
@@ -143,6 +155,21 @@ fn parse_ip(key: &str, default: &str) -> Result<IpAddr, String> {
     }
 }
 
+/// 逗号分隔的 IP 或 CIDR 列表，单个 IP 视为 /32（IPv6 为 /128）
+fn parse_ip_nets(key: &str, default: &str) -> Result<Vec<IpNet>, String> {
+    get_env(key)
+        .unwrap_or_else(|| default.to_string())
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(|item| {
+            item.parse::<IpNet>()
+                .or_else(|_| item.parse::<IpAddr>().map(IpNet::from))
+                .map_err(|_| format!("{key} contains invalid IP or CIDR: {item}"))
+        })
+        .collect()
+}
+
 pub fn load_env() -> Result<AppEnv, String> {
     let listen = parse_ip("LISTEN", "127.0.0.1")?;
     let port = parse_u16("PORT", 18787)?;
@@ -207,6 +234,7 @@ pub fn load_env() -> Result<AppEnv, String> {
         two_factor_secret_key: get_env("TWO_FACTOR_SECRET_KEY"),
         turnstile_secret_key: get_env("TURNSTILE_SECRET_KEY"),
         node_api_key: get_env("NODE_API_KEY"),
+        node_api_key_legacy_fallback: parse_bool("NODE_API_KEY_LEGACY_FALLBACK", false),
         epay_key: get_env("EPAY_KEY"),
         epay_pid: get_env("EPAY_PID"),
         epay_api_url: get_env("EPAY_API_URL"),
@@ -231,5 +259,7 @@ pub fn load_env() -> Result<AppEnv, String> {
         mail_verification_attempt_limit: get_env("MAIL_VERIFICATION_ATTEMPT_LIMIT"),
         passkey_rp_id: get_env("PASSKEY_RP_ID"),
         passkey_origin: get_env("PASSKEY_ORIGIN"),
+        job_scheduler_enabled: parse_bool("JOB_SCHEDULER_ENABLED", false),
+        trusted_proxies: parse_ip_nets("TRUSTED_PROXIES", "127.0.0.1,::1")?,
     })
 }
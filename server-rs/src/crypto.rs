@@ -1,3 +1,5 @@
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
 use base64::engine::general_purpose::URL_SAFE_NO_PAD;
 use base64::Engine;
 use rand::RngCore;
@@ -11,12 +13,48 @@ pub fn sha256_hex(input: &str) -> String {
     hex::encode(result)
 }
 
+/// 生成 Argon2id 密码哈希（PHC 字符串格式，每次随机盐）
 pub fn hash_password(password: &str) -> String {
-    sha256_hex(password)
+    let mut salt = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut salt);
+    let salt = SaltString::encode_b64(&salt).expect("16-byte salt is always valid");
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .expect("argon2 default params are always valid")
+        .to_string()
 }
 
+/// 校验密码，同时兼容 Argon2id PHC 字符串与旧版无盐 SHA-256 十六进制哈希
 pub fn verify_password(password: &str, hash: &str) -> bool {
-    hash_password(password) == hash
+    if is_legacy_password_hash(hash) {
+        return timing_safe_eq(&sha256_hex(password), &hash.to_ascii_lowercase());
+    }
+    match PasswordHash::new(hash) {
+        Ok(parsed) => Argon2::default()
+            .verify_password(password.as_bytes(), &parsed)
+            .is_ok(),
+        Err(_) => false,
+    }
+}
+
+/// 旧格式（SHA-256）或非 Argon2id 的哈希需要在下次验证成功后重新生成
+pub fn password_needs_rehash(hash: &str) -> bool {
+    !hash.starts_with("$argon2id$")
+}
+
+fn is_legacy_password_hash(hash: &str) -> bool {
+    hash.len() == 64 && hash.bytes().all(|byte| byte.is_ascii_hexdigit())
+}
+
+pub fn timing_safe_eq(left: &str, right: &str) -> bool {
+    if left.len() != right.len() {
+        return false;
+    }
+    let mut diff: u8 = 0;
+    for (a, b) in left.bytes().zip(right.bytes()) {
+        diff |= a ^ b;
+    }
+    diff == 0
 }
 
 pub fn random_string(len: usize) -> String {
@@ -54,3 +92,29 @@ pub fn random_numeric_code(len: usize) -> String {
 pub fn generate_uuid() -> String {
     Uuid::new_v4().to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn argon2_hash_round_trips_with_unique_salt() {
+        let first = hash_password("admin123");
+        let second = hash_password("admin123");
+
+        assert!(first.starts_with("$argon2id$"));
+        assert_ne!(first, second);
+        assert!(verify_password("admin123", &first));
+        assert!(!verify_password("admin124", &first));
+        assert!(!password_needs_rehash(&first));
+    }
+
+    #[test]
+    fn legacy_sha256_hash_still_verifies_and_needs_rehash() {
+        let legacy = "240be518fabd2724ddb6f04eeb1da5967448d7e831c08c8fa822809f74c720a9";
+
+        assert!(verify_password("admin123", legacy));
+        assert!(!verify_password("wrong", legacy));
+        assert!(password_needs_rehash(legacy));
+    }
+}
@@ -0,0 +1,417 @@
+use std::collections::HashMap;
+
+use serde_json::json;
+use sqlx::Row;
+
+use crate::cache::cache_delete_by_prefix;
+use crate::message_queue::enqueue_user_notice;
+use crate::node_hub::{notify_node, notify_nodes, NodeEvent};
+use crate::state::AppState;
+
+const DEFAULT_WINDOW_MINUTES: i64 = 5;
+const DEFAULT_SUSPEND_MINUTES: i64 = 30;
+const DEFAULT_NOTIFY_COOLDOWN_MINUTES: i64 = 360;
+
+/// 超出设备数时的处理方式
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum DeviceLimitAction {
+    /// 仅记录
+    Record,
+    /// 踢出最早活跃的多余 IP
+    Kick,
+    /// 临时停用账号
+    Suspend,
+}
+
+impl DeviceLimitAction {
+    fn from_config(value: &str) -> Self {
+        match value.trim().to_lowercase().as_str() {
+            "record" => Self::Record,
+            "suspend" => Self::Suspend,
+            _ => Self::Kick,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Record => "record",
+            Self::Kick => "kick",
+            Self::Suspend => "suspend",
+        }
+    }
+}
+
+struct DeviceLimitConfig {
+    action: DeviceLimitAction,
+    window_minutes: i64,
+    suspend_minutes: i64,
+    notify_enabled: bool,
+    notify_cooldown_minutes: i64,
+}
+
+struct OnlineIp {
+    ip: String,
+    node_ids: Vec<i64>,
+}
+
+pub struct DeviceLimitResult {
+    pub violations: usize,
+    pub kicked_ips: usize,
+    pub suspended_users: usize,
+    pub released_users: usize,
+    pub notified_users: usize,
+}
+
+/// 统计各用户在所有节点上的在线 IP 数，超出 device_limit 时按配置处理
+pub async fn run_device_limit_check(state: &AppState) -> Result<DeviceLimitResult, String> {
+    let config = load_config(state).await?;
+    let mut result = DeviceLimitResult {
+        violations: 0,
+        kicked_ips: 0,
+        suspended_users: 0,
+        released_users: 0,
+        notified_users: 0,
+    };
+
+    result.released_users = release_expired_suspensions(state).await?;
+
+    let rows = sqlx::query(
+        r#"
+    SELECT u.id AS user_id, u.device_limit, oi.ip, oi.node_id, oi.last_seen
+    FROM online_ips oi
+    JOIN users u ON u.id = oi.user_id
+    WHERE u.status = 1
+      AND u.device_limit > 0
+      AND oi.last_seen >= DATE_SUB(CURRENT_TIMESTAMP, INTERVAL ? MINUTE)
+    ORDER BY oi.user_id ASC, oi.last_seen DESC
+    "#,
+    )
+    .bind(config.window_minutes)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|err| err.to_string())?;
+
+    // 按用户聚合去重后的 IP，顺序为最近活跃在前
+    let mut users: Vec<(i64, i64, Vec<OnlineIp>)> = Vec::new();
+    for row in rows {
+        let user_id = row.try_get::<i64, _>("user_id").unwrap_or(0);
+        let device_limit = row
+            .try_get::<Option<i64>, _>("device_limit")
+            .ok()
+            .flatten()
+            .unwrap_or(0);
+        let ip = row
+            .try_get::<Option<String>, _>("ip")
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+        let node_id = row.try_get::<i64, _>("node_id").unwrap_or(0);
+        if user_id <= 0 || ip.is_empty() {
+            continue;
+        }
+        match users.last_mut() {
+            Some((id, _, ips)) if *id == user_id => {
+                match ips.iter_mut().find(|item| item.ip == ip) {
+                    Some(item) => item.node_ids.push(node_id),
+                    None => ips.push(OnlineIp {
+                        ip,
+                        node_ids: vec![node_id],
+                    }),
+                }
+            }
+            _ => users.push((
+                user_id,
+                device_limit,
+                vec![OnlineIp {
+                    ip,
+                    node_ids: vec![node_id],
+                }],
+            )),
+        }
+    }
+
+    for (user_id, device_limit, ips) in users {
+        if (ips.len() as i64) <= device_limit {
+            continue;
+        }
+        result.violations += 1;
+        let ip_list = ips.iter().map(|item| item.ip.clone()).collect::<Vec<_>>();
+        let excess = ips.split_at(device_limit as usize).1;
+
+        let mut kicked: Vec<String> = Vec::new();
+        let mut suspended_until = None;
+        match config.action {
+            DeviceLimitAction::Record => {}
+            DeviceLimitAction::Kick => {
+                for item in excess {
+                    kick_ip(state, user_id, item).await?;
+                    kicked.push(item.ip.clone());
+                }
+                result.kicked_ips += kicked.len();
+            }
+            DeviceLimitAction::Suspend => {
+                if suspend_user(state, user_id).await? {
+                    suspended_until = Some(config.suspend_minutes);
+                    result.suspended_users += 1;
+                }
+            }
+        }
+
+        let notify = config.notify_enabled
+            && !notified_recently(state, user_id, config.notify_cooldown_minutes).await?;
+        sqlx::query(
+            r#"
+      INSERT INTO device_limit_violations
+        (user_id, device_limit, ip_count, ips, action, kicked_ips, suspended_until, notified, created_at)
+      VALUES
+        (?, ?, ?, ?, ?, ?, IF(? IS NULL, NULL, DATE_ADD(CURRENT_TIMESTAMP, INTERVAL ? MINUTE)), ?, CURRENT_TIMESTAMP)
+      "#,
+        )
+        .bind(user_id)
+        .bind(device_limit)
+        .bind(ip_list.len() as i64)
+        .bind(json!(ip_list).to_string())
+        .bind(config.action.as_str())
+        .bind(json!(kicked).to_string())
+        .bind(suspended_until)
+        .bind(suspended_until)
+        .bind(if notify { 1 } else { 0 })
+        .execute(&state.db)
+        .await
+        .map_err(|err| err.to_string())?;
+
+        if notify {
+            let content = build_notice(
+                device_limit,
+                &ip_list,
+                config.action,
+                config.suspend_minutes,
+            );
+            match enqueue_user_notice(state, user_id, "设备数量超限提醒", &content).await {
+                Ok(queued) if queued > 0 => result.notified_users += 1,
+                Ok(_) => {}
+                Err(err) => tracing::warn!("[device_limit] notify user {user_id} failed: {err}"),
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+async fn load_config(state: &AppState) -> Result<DeviceLimitConfig, String> {
+    let rows = sqlx::query(
+        r#"
+    SELECT `key`, `value` FROM system_configs
+    WHERE `key` IN (
+      'device_limit_action',
+      'device_limit_window_minutes',
+      'device_limit_suspend_minutes',
+      'device_limit_notify_enabled',
+      'device_limit_notify_cooldown_minutes'
+    )
+    "#,
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|err| err.to_string())?;
+
+    let mut map: HashMap<String, String> = HashMap::new();
+    for row in rows {
+        let key = row
+            .try_get::<Option<String>, _>("key")
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+        let value = row
+            .try_get::<Option<String>, _>("value")
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+        map.insert(key, value.trim().to_string());
+    }
+    let read_minutes = |key: &str, default: i64| {
+        map.get(key)
+            .and_then(|value| value.parse::<i64>().ok())
+            .filter(|value| *value > 0)
+            .unwrap_or(default)
+    };
+
+    Ok(DeviceLimitConfig {
+        action: DeviceLimitAction::from_config(
+            map.get("device_limit_action")
+                .map(String::as_str)
+                .unwrap_or("kick"),
+        ),
+        window_minutes: read_minutes("device_limit_window_minutes", DEFAULT_WINDOW_MINUTES),
+        suspend_minutes: read_minutes("device_limit_suspend_minutes", DEFAULT_SUSPEND_MINUTES),
+        notify_enabled: map
+            .get("device_limit_notify_enabled")
+            .map(|value| value != "0" && value != "false")
+            .unwrap_or(true),
+        notify_cooldown_minutes: read_minutes(
+            "device_limit_notify_cooldown_minutes",
+            DEFAULT_NOTIFY_COOLDOWN_MINUTES,
+        ),
+    })
+}
+
+async fn kick_ip(state: &AppState, user_id: i64, item: &OnlineIp) -> Result<(), String> {
+    sqlx::query("DELETE FROM online_ips WHERE user_id = ? AND ip = ?")
+        .bind(user_id)
+        .bind(&item.ip)
+        .execute(&state.db)
+        .await
+        .map_err(|err| err.to_string())?;
+    for node_id in &item.node_ids {
+        notify_node(
+            state,
+            *node_id,
+            NodeEvent::KickUser {
+                user_id,
+                ip: Some(item.ip.clone()),
+            },
+        )
+        .await;
+    }
+    Ok(())
+}
+
+async fn suspend_user(state: &AppState, user_id: i64) -> Result<bool, String> {
+    let result = sqlx::query(
+        r#"
+    UPDATE users
+    SET status = 0, device_limit_suspended_at = CURRENT_TIMESTAMP, updated_at = CURRENT_TIMESTAMP
+    WHERE id = ? AND status = 1
+    "#,
+    )
+    .bind(user_id)
+    .execute(&state.db)
+    .await
+    .map_err(|err| err.to_string())?;
+    if result.rows_affected() == 0 {
+        return Ok(false);
+    }
+    sqlx::query("DELETE FROM online_ips WHERE user_id = ?")
+        .bind(user_id)
+        .execute(&state.db)
+        .await
+        .map_err(|err| err.to_string())?;
+    cache_delete_by_prefix(state, &format!("user_{user_id}_")).await;
+    notify_nodes(
+        state,
+        NodeEvent::UsersChanged {
+            user_ids: vec![user_id],
+        },
+    )
+    .await;
+    notify_nodes(state, NodeEvent::KickUser { user_id, ip: None }).await;
+    Ok(true)
+}
+
+/// 恢复到期的临时停用；只恢复仍带停用标记的账号（管理员期间修改过状态的会清除标记），账号已过期的不恢复
+async fn release_expired_suspensions(state: &AppState) -> Result<usize, String> {
+    let rows = sqlx::query(
+        r#"
+    SELECT id, user_id
+    FROM device_limit_violations
+    WHERE action = 'suspend'
+      AND suspended_until IS NOT NULL
+      AND suspended_until <= CURRENT_TIMESTAMP
+      AND released_at IS NULL
+    "#,
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|err| err.to_string())?;
+
+    let mut released = 0;
+    for row in rows {
+        let id = row.try_get::<i64, _>("id").unwrap_or(0);
+        let user_id = row.try_get::<i64, _>("user_id").unwrap_or(0);
+        let result = sqlx::query(
+            r#"
+      UPDATE users
+      SET status = 1, device_limit_suspended_at = NULL, updated_at = CURRENT_TIMESTAMP
+      WHERE id = ?
+        AND status = 0
+        AND device_limit_suspended_at IS NOT NULL
+        AND (expire_time IS NULL OR expire_time > CURRENT_TIMESTAMP)
+      "#,
+        )
+        .bind(user_id)
+        .execute(&state.db)
+        .await
+        .map_err(|err| err.to_string())?;
+        if result.rows_affected() == 0 {
+            // 账号已过期时不恢复，但标记随本次停用一并结束
+            sqlx::query("UPDATE users SET device_limit_suspended_at = NULL WHERE id = ?")
+                .bind(user_id)
+                .execute(&state.db)
+                .await
+                .map_err(|err| err.to_string())?;
+        }
+        sqlx::query(
+            "UPDATE device_limit_violations SET released_at = CURRENT_TIMESTAMP WHERE id = ?",
+        )
+        .bind(id)
+        .execute(&state.db)
+        .await
+        .map_err(|err| err.to_string())?;
+        if result.rows_affected() > 0 {
+            released += 1;
+            cache_delete_by_prefix(state, &format!("user_{user_id}_")).await;
+            notify_nodes(
+                state,
+                NodeEvent::UsersChanged {
+                    user_ids: vec![user_id],
+                },
+            )
+            .await;
+        }
+    }
+    Ok(released)
+}
+
+async fn notified_recently(
+    state: &AppState,
+    user_id: i64,
+    cooldown_minutes: i64,
+) -> Result<bool, String> {
+    let row = sqlx::query(
+        r#"
+    SELECT id FROM device_limit_violations
+    WHERE user_id = ?
+      AND notified = 1
+      AND created_at >= DATE_SUB(CURRENT_TIMESTAMP, INTERVAL ? MINUTE)
+    LIMIT 1
+    "#,
+    )
+    .bind(user_id)
+    .bind(cooldown_minutes)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|err| err.to_string())?;
+    Ok(row.is_some())
+}
+
+fn build_notice(
+    device_limit: i64,
+    ips: &[String],
+    action: DeviceLimitAction,
+    suspend_minutes: i64,
+) -> String {
+    let action_text = match action {
+        DeviceLimitAction::Record => "请尽快断开不再使用的设备。".to_string(),
+        DeviceLimitAction::Kick => "系统已自动断开最早活跃的多余设备。".to_string(),
+        DeviceLimitAction::Suspend => {
+            format!("账号已被临时停用 {suspend_minutes} 分钟，到期后自动恢复。")
+        }
+    };
+    format!(
+        "您的账号当前在 {} 个 IP 上同时在线，超过了套餐允许的 {} 台设备。\n在线 IP：{}\n{}",
+        ips.len(),
+        device_limit,
+        ips.join("、"),
+        action_text
+    )
+}
@@ -4,22 +4,82 @@ use serde_json::{json, Value};
 use sqlx::Row;
 
 use crate::cache::cache_delete_by_prefix;
+use crate::device_limit::run_device_limit_check;
 use crate::message_queue::process_pending_messages;
+use crate::node_bandwidth::run_node_bandwidth_tasks;
+use crate::payment_reconcile::run_payment_reconcile;
 use crate::state::AppState;
+use crate::subscription_abuse::run_subscription_abuse_check;
+
+/// 未配置 `job_runs_retention_days` 时任务执行记录的保留天数
+const DEFAULT_JOB_RUNS_RETENTION_DAYS: i64 = 30;
 
 #[derive(Clone, Copy)]
 pub enum JobKind {
     UserExpirationCheck,
     DailyTasks,
     SubscriptionCleanup,
+    DeviceLimitCheck,
+    PaymentReconcile,
+    SubscriptionAbuseCheck,
 }
 
 impl JobKind {
+    pub fn all() -> [JobKind; 6] {
+        [
+            Self::UserExpirationCheck,
+            Self::DailyTasks,
+            Self::SubscriptionCleanup,
+            Self::DeviceLimitCheck,
+            Self::PaymentReconcile,
+            Self::SubscriptionAbuseCheck,
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::UserExpirationCheck => "userExpirationCheck",
+            Self::DailyTasks => "dailyTasks",
+            Self::SubscriptionCleanup => "subscriptionCleanup",
+            Self::DeviceLimitCheck => "deviceLimitCheck",
+            Self::PaymentReconcile => "paymentReconcile",
+            Self::SubscriptionAbuseCheck => "subscriptionAbuseCheck",
+        }
+    }
+
+    /// 内置调度器读取的 system_configs 键（值为 5 段 cron 表达式，留空表示不调度）
+    pub fn schedule_config_key(&self) -> &'static str {
+        match self {
+            Self::UserExpirationCheck => "job_schedule_user_expiration_check",
+            Self::DailyTasks => "job_schedule_daily_tasks",
+            Self::SubscriptionCleanup => "job_schedule_subscription_cleanup",
+            Self::DeviceLimitCheck => "job_schedule_device_limit_check",
+            Self::PaymentReconcile => "job_schedule_payment_reconcile",
+            Self::SubscriptionAbuseCheck => "job_schedule_subscription_abuse_check",
+        }
+    }
+
+    pub fn default_schedule(&self) -> &'static str {
+        match self {
+            Self::UserExpirationCheck => "* * * * *",
+            Self::DailyTasks => "0 0 * * *",
+            Self::SubscriptionCleanup => "0 3 * * *",
+            Self::DeviceLimitCheck => "*/5 * * * *",
+            Self::PaymentReconcile => "*/10 * * * *",
+            Self::SubscriptionAbuseCheck => "*/30 * * * *",
+        }
+    }
+
     pub fn from_name(name: &str) -> Option<Self> {
         match name.trim() {
             "userExpirationCheck" | "user-expiration-check" => Some(Self::UserExpirationCheck),
             "dailyTasks" | "daily-tasks" | "daily" => Some(Self::DailyTasks),
             "subscriptionCleanup" | "subscription-cleanup" => Some(Self::SubscriptionCleanup),
+            "deviceLimitCheck" | "device-limit-check" => Some(Self::DeviceLimitCheck),
+            "paymentReconcile" | "payment-reconcile" => Some(Self::PaymentReconcile),
+            "subscriptionAbuseCheck" | "subscription-abuse-check" => {
+                Some(Self::SubscriptionAbuseCheck)
+            }
             _ => None,
         }
     }
@@ -30,9 +90,24 @@ pub fn job_descriptions() -> Vec<(&'static str, &'static str)> {
         ("userExpirationCheck", "检查账号/等级过期并重置"),
         (
             "dailyTasks",
-            "每日流量汇总、Bark/Telegram 通知、日/月重置、节点状态清理",
+            "每日流量汇总、Bark/Telegram 通知、日/月重置、节点流量重置与告警、节点状态清理",
+        ),
+        (
+            "subscriptionCleanup",
+            "清理 7 天前订阅记录并刷新订阅缓存，按保留天数清理任务执行记录",
+        ),
+        (
+            "deviceLimitCheck",
+            "统计跨节点在线 IP，处理超出设备数限制的用户",
+        ),
+        (
+            "paymentReconcile",
+            "向支付通道查询待支付订单，补入账已支付订单并关闭超时订单",
+        ),
+        (
+            "subscriptionAbuseCheck",
+            "统计订阅链接的拉取 IP/网段/UA，记录疑似泄露的 token 并按配置重置、通知用户",
         ),
-        ("subscriptionCleanup", "清理 7 天前订阅记录并刷新订阅缓存"),
     ]
 }
 
@@ -41,6 +116,37 @@ pub async fn run_job(state: &AppState, job: JobKind) -> Result<(), String> {
         JobKind::UserExpirationCheck => run_user_expiration_check(state).await,
         JobKind::DailyTasks => run_daily_tasks(state).await,
         JobKind::SubscriptionCleanup => run_subscription_cleanup(state).await,
+        JobKind::DeviceLimitCheck => {
+            let result = run_device_limit_check(state).await?;
+            println!(
+                "[job] deviceLimitCheck done: violations={}, kicked_ips={}, suspended={}, released={}, notified={}",
+                result.violations,
+                result.kicked_ips,
+                result.suspended_users,
+                result.released_users,
+                result.notified_users
+            );
+            Ok(())
+        }
+        JobKind::PaymentReconcile => {
+            let result = run_payment_reconcile(state).await?;
+            println!(
+                "[job] paymentReconcile done: checked={}, settled={}, expired={}, failed={}",
+                result.checked, result.settled, result.expired, result.failed
+            );
+            Ok(())
+        }
+        JobKind::SubscriptionAbuseCheck => {
+            let result = run_subscription_abuse_check(state).await?;
+            println!(
+                "[job] subscriptionAbuseCheck done: checked={}, flagged={}, reset={}, notified={}",
+                result.checked_tokens,
+                result.flagged_tokens,
+                result.reset_tokens,
+                result.notified_users
+            );
+            Ok(())
+        }
     }
 }
 
@@ -73,7 +179,7 @@ async fn run_user_expiration_check(state: &AppState) -> Result<(), String> {
             .collect::<Vec<&str>>()
             .join(",");
         let sql = format!(
-      "UPDATE users SET status = 0, updated_at = CURRENT_TIMESTAMP WHERE id IN ({placeholders})"
+      "UPDATE users SET status = 0, device_limit_suspended_at = NULL, updated_at = CURRENT_TIMESTAMP WHERE id IN ({placeholders})"
     );
         let mut query = sqlx::query(&sql);
         for id in &expired_account_ids {
@@ -211,6 +317,14 @@ async fn run_daily_tasks(state: &AppState) -> Result<(), String> {
 
     try_monthly_reset(state).await;
 
+    match run_node_bandwidth_tasks(state).await {
+        Ok(result) => println!(
+            "[job] node bandwidth tasks done: reset={}, warned={}",
+            result.reset_nodes, result.warned_nodes
+        ),
+        Err(err) => println!("[job] node bandwidth tasks failed: {err}"),
+    }
+
     let _ = sqlx::query("DELETE FROM node_status")
         .execute(&state.db)
         .await;
@@ -231,13 +345,45 @@ async fn run_subscription_cleanup(state: &AppState) -> Result<(), String> {
         .map_err(|err| err.to_string())?;
     cache_delete_by_prefix(state, "sub_token_").await;
 
+    let job_runs_deleted = cleanup_job_runs(state).await?;
+
     println!(
-        "[job] subscriptionCleanup done: deleted_rows={}",
-        result.rows_affected()
+        "[job] subscriptionCleanup done: deleted_rows={}, job_runs_deleted={}",
+        result.rows_affected(),
+        job_runs_deleted
     );
     Ok(())
 }
 
+/// 按 `job_runs_retention_days` 清理任务执行记录（0 表示不清理），运行中的记录保留
+async fn cleanup_job_runs(state: &AppState) -> Result<u64, String> {
+    let row =
+        sqlx::query("SELECT `value` FROM system_configs WHERE `key` = 'job_runs_retention_days'")
+            .fetch_optional(&state.db)
+            .await
+            .map_err(|err| err.to_string())?;
+    let retention_days = row
+        .and_then(|row| row.try_get::<Option<String>, _>("value").ok().flatten())
+        .and_then(|value| value.trim().parse::<i64>().ok())
+        .unwrap_or(DEFAULT_JOB_RUNS_RETENTION_DAYS);
+    if retention_days <= 0 {
+        return Ok(0);
+    }
+
+    let result = sqlx::query(
+        r#"
+    DELETE FROM job_runs
+    WHERE started_at < DATE_SUB(CURRENT_TIMESTAMP, INTERVAL ? DAY)
+      AND status <> 'running'
+    "#,
+    )
+    .bind(retention_days)
+    .execute(&state.db)
+    .await
+    .map_err(|err| err.to_string())?;
+    Ok(result.rows_affected())
+}
+
 async fn aggregate_traffic_for_date(
     state: &AppState,
     record_date: &str,
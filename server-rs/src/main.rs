@@ -1,23 +1,42 @@
+mod admin_actions;
+mod admin_permissions;
+mod audit_rules;
+mod blocked_ips;
 mod cache;
+mod client_ip;
 mod config;
 mod crypto;
+mod device_limit;
 mod etag;
 mod jobs;
 mod mail;
 mod message_queue;
+mod money;
+mod node_bandwidth;
+mod node_hub;
 mod passkey;
 mod payment;
+mod payment_reconcile;
+mod proxy_groups;
+mod purchase_refund;
 mod referral;
 mod response;
 mod routes;
+mod scheduler;
 mod shared_ids;
 mod state;
 mod subscription;
+mod subscription_abuse;
+mod subscription_filter;
+mod subscription_templates;
+mod subscription_tokens;
+mod subscription_ua;
 mod templates;
 mod totp;
+mod traffic_anomaly;
 
 use axum::body::Body;
-use axum::extract::connect_info::ConnectInfo;
+use axum::extract::State;
 use axum::http::{header, HeaderName, HeaderValue, Method, Request, Uri};
 use axum::middleware::Next;
 use axum::response::Response;
@@ -32,8 +51,12 @@ use std::time::Instant;
 use tokio::sync::RwLock;
 use tower_http::cors::{Any, CorsLayer};
 
+use crate::blocked_ips::blocked_ip_middleware;
+use crate::client_ip::apply_client_ip_header;
 use crate::config::{apply_dotenv, load_env};
-use crate::jobs::{job_descriptions, run_job, JobKind};
+use crate::jobs::{job_descriptions, JobKind};
+use crate::node_hub::NodeHub;
+use crate::scheduler::{run_tracked_job, spawn_scheduler};
 use crate::state::{AppState, RedisStatus};
 
 #[tokio::main]
@@ -95,13 +118,17 @@ async fn main() {
                 std::process::exit(1);
             }
         };
-        if let Err(err) = run_job(&state, job).await {
+        if let Err(err) = run_tracked_job(&state, job, "cli").await {
             eprintln!("[job] failed: {err}");
             std::process::exit(1);
         }
         return;
     }
 
+    if env.job_scheduler_enabled {
+        spawn_scheduler(state.clone());
+    }
+
     let cors = CorsLayer::new()
         .allow_origin(Any)
         .allow_methods([
@@ -124,9 +151,13 @@ async fn main() {
         ])
         .max_age(std::time::Duration::from_secs(86400));
 
-    let app = routes::create_router(state)
+    let app = routes::create_router(state.clone())
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            blocked_ip_middleware,
+        ))
         .layer(cors)
-        .layer(axum::middleware::from_fn(app_middleware));
+        .layer(axum::middleware::from_fn_with_state(state, app_middleware));
 
     let addr = SocketAddr::new(env.listen, env.port);
     println!("[server] listening on http://{}", addr);
@@ -195,6 +226,7 @@ async fn build_state(env: config::AppEnv) -> Result<AppState, String> {
         redis_status,
         oauth_pending: Arc::new(RwLock::new(HashMap::new())),
         passkey_challenges: Arc::new(RwLock::new(HashMap::new())),
+        node_hub: NodeHub::default(),
     })
 }
 
@@ -339,20 +371,16 @@ async fn build_redis_manager(
         .map_err(|err| err.to_string())
 }
 
-async fn app_middleware(mut req: Request<Body>, next: Next) -> Response {
+async fn app_middleware(
+    State(state): State<AppState>,
+    mut req: Request<Body>,
+    next: Next,
+) -> Response {
     let start = Instant::now();
     let method = req.method().clone();
     let original_uri = req.uri().to_string();
 
-    if let Some(connect_info) = req.extensions().get::<ConnectInfo<SocketAddr>>() {
-        if !req.headers().contains_key("x-real-ip") {
-            let ip = connect_info.0.ip().to_string();
-            if let Ok(value) = HeaderValue::from_str(&ip) {
-                req.headers_mut()
-                    .insert(HeaderName::from_static("x-real-ip"), value);
-            }
-        }
-    }
+    apply_client_ip_header(&mut req, &state.env.trusted_proxies);
 
     let headers_snapshot = req.headers().clone();
 
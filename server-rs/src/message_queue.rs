@@ -214,6 +214,93 @@ pub async fn enqueue_announcement_notifications(
     })
 }
 
+/// 向单个用户的已启用通道投递通知（邮件始终投递，Bark 与 Telegram 按用户开关）
+pub async fn enqueue_user_notice(
+    state: &AppState,
+    user_id: i64,
+    title: &str,
+    content: &str,
+) -> Result<i64, String> {
+    let row = sqlx::query(
+        r#"
+    SELECT email, bark_key, bark_enabled, telegram_id, telegram_enabled
+    FROM users
+    WHERE id = ?
+    "#,
+    )
+    .bind(user_id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|err| err.to_string())?;
+    let Some(row) = row else {
+        return Ok(0);
+    };
+    let read_string = |key: &str| {
+        row.try_get::<Option<String>, _>(key)
+            .ok()
+            .flatten()
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+    let read_flag = |key: &str| row.try_get::<Option<i64>, _>(key).ok().flatten() == Some(1);
+
+    let mut recipients: Vec<(MessageChannel, String)> = Vec::new();
+    if let Some(email) = read_string("email") {
+        recipients.push((MessageChannel::Email, email));
+    }
+    let telegram = read_string("telegram_id").filter(|_| read_flag("telegram_enabled"));
+    if let Some(telegram_id) = telegram.clone() {
+        recipients.push((MessageChannel::Telegram, telegram_id));
+    }
+    // 与公告一致：已启用 Telegram 时不再重复推送 Bark
+    if telegram.is_none() {
+        if let Some(bark_key) = read_string("bark_key").filter(|_| read_flag("bark_enabled")) {
+            recipients.push((MessageChannel::Bark, bark_key));
+        }
+    }
+    if recipients.is_empty() {
+        return Ok(0);
+    }
+
+    let (site_name, site_url) = load_site_configs(state).await?;
+    let payload = QueuePayload {
+        payload_type: "user_notice".to_string(),
+        site_name: Some(site_name),
+        site_url: Some(site_url),
+        announcement: QueueAnnouncement {
+            id: 0,
+            title: title.to_string(),
+            content: content.to_string(),
+            content_html: String::new(),
+            announcement_type: "warning".to_string(),
+        },
+    };
+    let payload_json = serde_json::to_string(&payload).map_err(|err| err.to_string())?;
+
+    let mut queued_count: i64 = 0;
+    for (channel, recipient) in recipients {
+        sqlx::query(
+            r#"
+      INSERT INTO message_queue (
+        announcement_id, user_id, channel, recipient, payload,
+        status, attempt_count, max_attempts, scheduled_at, created_at, updated_at
+      ) VALUES (NULL, ?, ?, ?, ?, ?, 0, ?, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
+      "#,
+        )
+        .bind(user_id)
+        .bind(channel.as_str())
+        .bind(recipient)
+        .bind(&payload_json)
+        .bind(STATUS_PENDING)
+        .bind(DEFAULT_MAX_ATTEMPTS)
+        .execute(&state.db)
+        .await
+        .map_err(|err| err.to_string())?;
+        queued_count += 1;
+    }
+    Ok(queued_count)
+}
+
 pub async fn process_pending_messages(state: &AppState) -> Result<QueueDispatchResult, String> {
     release_stale_processing_messages(state).await?;
 
@@ -878,7 +965,7 @@ async fn mark_failed_or_retry(
 fn parse_payload(raw: &str) -> Result<QueuePayload, String> {
     let payload = serde_json::from_str::<QueuePayload>(raw)
         .map_err(|err| format!("消息内容解析失败: {}", err))?;
-    if payload.payload_type != "announcement" {
+    if payload.payload_type != "announcement" && payload.payload_type != "user_notice" {
         return Err("消息内容解析失败: payload type mismatch".to_string());
     }
     Ok(payload)
@@ -0,0 +1,194 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Neg, Sub};
+use std::str::FromStr;
+
+use bigdecimal::{BigDecimal, RoundingMode, ToPrimitive, Zero};
+use serde::{Serialize, Serializer};
+use sqlx::encode::IsNull;
+use sqlx::error::BoxDynError;
+use sqlx::mysql::{MySql, MySqlRow, MySqlTypeInfo, MySqlValueRef};
+use sqlx::{Decode, Encode, Row, Type};
+
+/// 金额（元），与数据库 `DECIMAL(10,2)` 对应，始终保留两位小数（四舍五入）
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Money(BigDecimal);
+
+impl Money {
+    pub fn zero() -> Self {
+        Self(BigDecimal::zero().with_scale(2))
+    }
+
+    pub fn new(value: BigDecimal) -> Self {
+        Self(value.with_scale_round(2, RoundingMode::HalfUp))
+    }
+
+    pub fn parse(text: &str) -> Option<Self> {
+        BigDecimal::from_str(text.trim()).ok().map(Self::new)
+    }
+
+    /// 请求体中的 JSON 数字按其十进制字面值转换，避免二进制误差
+    pub fn from_f64(value: f64) -> Option<Self> {
+        if !value.is_finite() {
+            return None;
+        }
+        Self::parse(&value.to_string())
+    }
+
+    /// 读取金额列，兼容 DECIMAL、DOUBLE、字符串与整数
+    pub fn from_row(row: &MySqlRow, column: &str) -> Option<Self> {
+        if let Ok(value) = row.try_get::<Option<BigDecimal>, _>(column) {
+            return value.map(Self::new);
+        }
+        if let Ok(value) = row.try_get::<Option<f64>, _>(column) {
+            return value.and_then(Self::from_f64);
+        }
+        if let Ok(value) = row.try_get::<Option<i64>, _>(column) {
+            return value.map(|value| Self::new(BigDecimal::from(value)));
+        }
+        if let Ok(value) = row.try_get::<Option<String>, _>(column) {
+            return value.and_then(|value| Self::parse(&value));
+        }
+        None
+    }
+
+    pub fn from_row_or_zero(row: &MySqlRow, column: &str) -> Self {
+        Self::from_row(row, column).unwrap_or_default()
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.0.to_f64().unwrap_or(0.0)
+    }
+
+    pub fn is_positive(&self) -> bool {
+        self.0 > BigDecimal::zero()
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0.is_zero()
+    }
+
+    /// 小于 0 时取 0
+    pub fn non_negative(self) -> Self {
+        if self.0 < BigDecimal::zero() {
+            Self::zero()
+        } else {
+            self
+        }
+    }
+
+    /// 按比例计算（如返利比例、手续费率），结果四舍五入到分
+    pub fn mul_rate(&self, rate: &BigDecimal) -> Self {
+        Self::new(&self.0 * rate)
+    }
+
+    /// 按百分比计算（`percent` 为 0-100）
+    pub fn percent(&self, percent: &Money) -> Self {
+        Self::new(&self.0 * &percent.0 / BigDecimal::from(100))
+    }
+
+    pub fn min(self, other: Self) -> Self {
+        match self.cmp(&other) {
+            Ordering::Greater => other,
+            _ => self,
+        }
+    }
+}
+
+/// 解析比例配置（如 `0.1`），限制在 0-1 之间
+pub fn parse_rate(text: &str) -> Option<BigDecimal> {
+    let value = BigDecimal::from_str(text.trim()).ok()?;
+    Some(value.clamp(BigDecimal::zero(), BigDecimal::from(1)))
+}
+
+impl From<i64> for Money {
+    fn from(value: i64) -> Self {
+        Self::new(BigDecimal::from(value))
+    }
+}
+
+impl Add for Money {
+    type Output = Money;
+
+    fn add(self, other: Money) -> Money {
+        Money::new(self.0 + other.0)
+    }
+}
+
+impl Sub for Money {
+    type Output = Money;
+
+    fn sub(self, other: Money) -> Money {
+        Money::new(self.0 - other.0)
+    }
+}
+
+impl Neg for Money {
+    type Output = Money;
+
+    fn neg(self) -> Money {
+        Money::new(-self.0)
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.with_scale_round(2, RoundingMode::HalfUp))
+    }
+}
+
+/// 接口中仍以 JSON 数字返回，保持与前端的兼容
+impl Serialize for Money {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(self.to_f64())
+    }
+}
+
+impl Type<MySql> for Money {
+    fn type_info() -> MySqlTypeInfo {
+        <BigDecimal as Type<MySql>>::type_info()
+    }
+
+    fn compatible(ty: &MySqlTypeInfo) -> bool {
+        <BigDecimal as Type<MySql>>::compatible(ty)
+    }
+}
+
+impl Encode<'_, MySql> for Money {
+    fn encode_by_ref(&self, buf: &mut Vec<u8>) -> Result<IsNull, BoxDynError> {
+        <BigDecimal as Encode<MySql>>::encode_by_ref(&self.0, buf)
+    }
+}
+
+impl Decode<'_, MySql> for Money {
+    fn decode(value: MySqlValueRef<'_>) -> Result<Self, BoxDynError> {
+        <BigDecimal as Decode<MySql>>::decode(value).map(Money::new)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn money(text: &str) -> Money {
+        Money::parse(text).unwrap_or_default()
+    }
+
+    #[test]
+    fn money_rounds_half_up_to_cents() {
+        assert_eq!(money("9.995").to_string(), "10.00");
+        assert_eq!(money("0.1") + money("0.2"), money("0.3"));
+        assert_eq!(Money::from_f64(0.1 + 0.2), Some(money("0.3")));
+        assert_eq!((money("5") - money("7.5")).non_negative(), Money::zero());
+        assert_eq!(-money("1.2"), money("-1.20"));
+    }
+
+    #[test]
+    fn money_rates_and_percentages() {
+        let rate = parse_rate("0.15").unwrap_or_default();
+        assert_eq!(money("33.33").mul_rate(&rate), money("5.00"));
+        assert_eq!(money("19.90").percent(&money("15")), money("2.99"));
+        assert_eq!(parse_rate("1.5"), Some(BigDecimal::from(1)));
+        assert!(parse_rate("abc").is_none());
+    }
+}
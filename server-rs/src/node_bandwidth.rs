@@ -0,0 +1,373 @@
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Utc};
+use sqlx::Row;
+
+use crate::cache::cache_delete_by_prefix;
+use crate::message_queue::enqueue_user_notice;
+use crate::state::AppState;
+
+const DEFAULT_WARN_PERCENTS: [i64; 2] = [80, 95];
+
+struct NodeBandwidthRow {
+    id: i64,
+    name: String,
+    used: i64,
+    limit: i64,
+    reset_day: i64,
+    period_start: Option<NaiveDateTime>,
+    warned_percent: i64,
+}
+
+pub struct NodeBandwidthResult {
+    pub reset_nodes: usize,
+    pub warned_nodes: usize,
+}
+
+/// 按各节点的 bandwidthlimit_resetday 重置已用流量并归档上一周期，超过告警阈值时通知管理员。
+/// 当前周期开始早于最近一次重置日即补做重置，任务漏跑（停机、失败）后下次运行仍会重置
+pub async fn run_node_bandwidth_tasks(state: &AppState) -> Result<NodeBandwidthResult, String> {
+    let today = beijing_now().date();
+    let warn_percents = load_warn_percents(state).await?;
+    let mut result = NodeBandwidthResult {
+        reset_nodes: 0,
+        warned_nodes: 0,
+    };
+
+    let rows = sqlx::query(
+        r#"
+    SELECT id, name, node_bandwidth, node_bandwidth_limit, bandwidthlimit_resetday,
+           bandwidth_period_start, bandwidth_warned_percent
+    FROM nodes
+    "#,
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|err| err.to_string())?;
+
+    for row in rows {
+        let read_i64 = |key: &str| row.try_get::<Option<i64>, _>(key).ok().flatten();
+        let node = NodeBandwidthRow {
+            id: read_i64("id").unwrap_or(0),
+            name: row
+                .try_get::<Option<String>, _>("name")
+                .ok()
+                .flatten()
+                .unwrap_or_default(),
+            used: read_i64("node_bandwidth").unwrap_or(0),
+            limit: read_i64("node_bandwidth_limit").unwrap_or(0),
+            reset_day: read_i64("bandwidthlimit_resetday").unwrap_or(1),
+            period_start: row
+                .try_get::<Option<NaiveDateTime>, _>("bandwidth_period_start")
+                .ok()
+                .flatten(),
+            warned_percent: read_i64("bandwidth_warned_percent").unwrap_or(0),
+        };
+        if node.id <= 0 {
+            continue;
+        }
+
+        if let Some(boundary) =
+            last_reset_date(node.reset_day, today).and_then(|date| date.and_hms_opt(0, 0, 0))
+        {
+            match node.period_start {
+                Some(start) if start < boundary => {
+                    match reset_node_bandwidth(state, node.id, "auto", Some(boundary)).await {
+                        Ok(true) => result.reset_nodes += 1,
+                        Ok(false) => {}
+                        Err(err) => {
+                            tracing::warn!("[node_bandwidth] reset node {} failed: {err}", node.id)
+                        }
+                    }
+                    continue;
+                }
+                Some(_) => {}
+                None => {
+                    // 尚未记录周期起点的节点（升级前创建）视为本周期从最近的重置日开始，不清零已用流量
+                    sqlx::query(
+                        "UPDATE nodes SET bandwidth_period_start = ? WHERE id = ? AND bandwidth_period_start IS NULL",
+                    )
+                    .bind(boundary)
+                    .bind(node.id)
+                    .execute(&state.db)
+                    .await
+                    .map_err(|err| err.to_string())?;
+                }
+            }
+        }
+
+        let Some(threshold) = crossed_threshold(node.used, node.limit, &warn_percents) else {
+            continue;
+        };
+        if threshold <= node.warned_percent {
+            continue;
+        }
+        let claimed = sqlx::query(
+            "UPDATE nodes SET bandwidth_warned_percent = ? WHERE id = ? AND bandwidth_warned_percent < ?",
+        )
+        .bind(threshold)
+        .bind(node.id)
+        .bind(threshold)
+        .execute(&state.db)
+        .await
+        .map_err(|err| err.to_string())?;
+        if claimed.rows_affected() == 0 {
+            continue;
+        }
+        notify_admins(state, &node, threshold).await;
+        result.warned_nodes += 1;
+    }
+
+    Ok(result)
+}
+
+/// 归档当前周期用量并清零节点流量（auto 为自动重置，manual 为管理员手动重置）。
+/// `due_before` 为自动重置的周期边界：加锁后周期起点已不早于该时间（其他实例或重跑已重置）则跳过
+pub async fn reset_node_bandwidth(
+    state: &AppState,
+    node_id: i64,
+    reset_type: &str,
+    due_before: Option<NaiveDateTime>,
+) -> Result<bool, String> {
+    let now = beijing_now();
+    let mut tx = state.db.begin().await.map_err(|err| err.to_string())?;
+    let row = sqlx::query(
+        r#"
+    SELECT node_bandwidth, node_bandwidth_limit, bandwidth_period_start, created_at
+    FROM nodes
+    WHERE id = ?
+    FOR UPDATE
+    "#,
+    )
+    .bind(node_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|err| err.to_string())?;
+    let Some(row) = row else {
+        return Ok(false);
+    };
+    let read_time = |key: &str| row.try_get::<Option<NaiveDateTime>, _>(key).ok().flatten();
+    let period_start = read_time("bandwidth_period_start");
+    if let (Some(due), Some(start)) = (due_before, period_start) {
+        if start >= due {
+            return Ok(false);
+        }
+    }
+
+    sqlx::query(
+        r#"
+    INSERT INTO node_traffic_history
+      (node_id, period_start, period_end, used_traffic, bandwidth_limit, reset_type, created_at)
+    VALUES (?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP)
+    "#,
+    )
+    .bind(node_id)
+    .bind(period_start.or_else(|| read_time("created_at")))
+    .bind(now)
+    .bind(
+        row.try_get::<Option<i64>, _>("node_bandwidth")
+            .ok()
+            .flatten()
+            .unwrap_or(0),
+    )
+    .bind(
+        row.try_get::<Option<i64>, _>("node_bandwidth_limit")
+            .ok()
+            .flatten()
+            .unwrap_or(0),
+    )
+    .bind(reset_type)
+    .execute(&mut *tx)
+    .await
+    .map_err(|err| err.to_string())?;
+
+    sqlx::query(
+        r#"
+    UPDATE nodes
+    SET node_bandwidth = 0,
+        bandwidth_period_start = ?,
+        bandwidth_warned_percent = 0,
+        updated_at = CURRENT_TIMESTAMP
+    WHERE id = ?
+    "#,
+    )
+    .bind(now)
+    .bind(node_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|err| err.to_string())?;
+    tx.commit().await.map_err(|err| err.to_string())?;
+
+    cache_delete_by_prefix(state, &format!("node_config_{node_id}")).await;
+    Ok(true)
+}
+
+/// 周期起点与重置判断统一使用北京时间，不依赖数据库的 CURRENT_TIMESTAMP 时区
+fn beijing_now() -> NaiveDateTime {
+    (Utc::now() + Duration::hours(8)).naive_utc()
+}
+
+/// 不晚于 `today` 的最近一个重置日；重置日超过当月天数（如 31 日遇到 2 月）时取当月最后一天
+fn last_reset_date(reset_day: i64, today: NaiveDate) -> Option<NaiveDate> {
+    if !(1..=31).contains(&reset_day) {
+        return None;
+    }
+    let this_month = reset_date_in_month(reset_day, today.year(), today.month())?;
+    if this_month <= today {
+        return Some(this_month);
+    }
+    let (year, month) = if today.month() == 1 {
+        (today.year() - 1, 12)
+    } else {
+        (today.year(), today.month() - 1)
+    };
+    reset_date_in_month(reset_day, year, month)
+}
+
+fn reset_date_in_month(reset_day: i64, year: i32, month: u32) -> Option<NaiveDate> {
+    let first = NaiveDate::from_ymd_opt(year, month, 1)?;
+    first.with_day((reset_day as u32).min(last_day_of_month(first)))
+}
+
+fn last_day_of_month(date: NaiveDate) -> u32 {
+    let (year, month) = if date.month() == 12 {
+        (date.year() + 1, 1)
+    } else {
+        (date.year(), date.month() + 1)
+    };
+    NaiveDate::from_ymd_opt(year, month, 1)
+        .and_then(|first| first.pred_opt())
+        .map(|last| last.day())
+        .unwrap_or(28)
+}
+
+/// 返回已达到的最高告警百分比（未设上限或未达到任何阈值时为 None）
+fn crossed_threshold(used: i64, limit: i64, percents: &[i64]) -> Option<i64> {
+    if limit <= 0 {
+        return None;
+    }
+    let used_percent = (used as i128) * 100 / (limit as i128);
+    percents
+        .iter()
+        .copied()
+        .filter(|percent| used_percent >= *percent as i128)
+        .max()
+}
+
+async fn notify_admins(state: &AppState, node: &NodeBandwidthRow, threshold: i64) {
+    let admins = match sqlx::query("SELECT id FROM users WHERE is_admin = 1 AND status = 1")
+        .fetch_all(&state.db)
+        .await
+    {
+        Ok(rows) => rows,
+        Err(err) => {
+            tracing::warn!("[node_bandwidth] load admins failed: {err}");
+            return;
+        }
+    };
+    let content = format!(
+        "节点「{}」（ID {}）本周期已用流量 {} / {}，已超过 {}%。节点将在超出上限后停止服务，请及时处理。",
+        node.name,
+        node.id,
+        format_bytes(node.used),
+        format_bytes(node.limit),
+        threshold
+    );
+    for admin in admins {
+        let admin_id = admin.try_get::<i64, _>("id").unwrap_or(0);
+        if let Err(err) = enqueue_user_notice(state, admin_id, "节点流量告警", &content).await
+        {
+            tracing::warn!("[node_bandwidth] notify admin {admin_id} failed: {err}");
+        }
+    }
+}
+
+async fn load_warn_percents(state: &AppState) -> Result<Vec<i64>, String> {
+    let value = sqlx::query(
+        "SELECT `value` FROM system_configs WHERE `key` = 'node_bandwidth_warn_percents'",
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|err| err.to_string())?
+    .and_then(|row| row.try_get::<Option<String>, _>("value").ok().flatten());
+    Ok(match value {
+        Some(value) => parse_warn_percents(&value),
+        None => DEFAULT_WARN_PERCENTS.to_vec(),
+    })
+}
+
+/// 解析逗号分隔的百分比（如 `80,95`），留空表示不告警
+fn parse_warn_percents(value: &str) -> Vec<i64> {
+    let mut percents = value
+        .split(',')
+        .filter_map(|item| item.trim().parse::<i64>().ok())
+        .filter(|percent| (1..=100).contains(percent))
+        .collect::<Vec<i64>>();
+    percents.sort_unstable();
+    percents.dedup();
+    percents
+}
+
+fn format_bytes(bytes: i64) -> String {
+    if bytes <= 0 {
+        return "0 B".to_string();
+    }
+    let sizes = ["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut idx = 0;
+    while value >= 1024.0 && idx < sizes.len() - 1 {
+        value /= 1024.0;
+        idx += 1;
+    }
+    format!("{:.2} {}", value, sizes[idx])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(text: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap_or_default()
+    }
+
+    #[test]
+    fn last_reset_date_falls_back_to_month_end() {
+        assert_eq!(
+            last_reset_date(15, date("2026-03-15")),
+            Some(date("2026-03-15"))
+        );
+        assert_eq!(
+            last_reset_date(15, date("2026-03-16")),
+            Some(date("2026-03-15"))
+        );
+        assert_eq!(
+            last_reset_date(15, date("2026-03-14")),
+            Some(date("2026-02-15"))
+        );
+        assert_eq!(
+            last_reset_date(31, date("2026-02-28")),
+            Some(date("2026-02-28"))
+        );
+        assert_eq!(
+            last_reset_date(30, date("2028-02-29")),
+            Some(date("2028-02-29"))
+        );
+        assert_eq!(
+            last_reset_date(31, date("2026-03-30")),
+            Some(date("2026-02-28"))
+        );
+        assert_eq!(
+            last_reset_date(20, date("2026-01-05")),
+            Some(date("2025-12-20"))
+        );
+        assert_eq!(last_reset_date(0, date("2026-03-01")), None);
+    }
+
+    #[test]
+    fn warn_threshold_uses_highest_crossed_percent() {
+        let percents = parse_warn_percents("95, 80,abc,150");
+        assert_eq!(percents, vec![80, 95]);
+        assert_eq!(crossed_threshold(79, 100, &percents), None);
+        assert_eq!(crossed_threshold(85, 100, &percents), Some(80));
+        assert_eq!(crossed_threshold(120, 100, &percents), Some(95));
+        assert_eq!(crossed_threshold(120, 0, &percents), None);
+    }
+}
@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use serde_json::{json, Value};
+use tokio::sync::{mpsc, RwLock};
+
+use crate::state::AppState;
+
+/// 支持服务端推送的 WebSocket 协议版本
+pub const WS_PUSH_PROTOCOL_VERSION: u8 = 2;
+/// 单连接待发送推送上限，超出后丢弃（节点仍会按周期拉取）
+const PUSH_CHANNEL_CAPACITY: usize = 64;
+
+/// 推送给节点的变更事件
+#[derive(Clone, Debug)]
+pub enum NodeEvent {
+    UsersChanged { user_ids: Vec<i64> },
+    AuditRulesChanged,
+    XrayRulesChanged,
+    WhiteListChanged,
+    BlockListChanged,
+    KickUser { user_id: i64, ip: Option<String> },
+}
+
+impl NodeEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::UsersChanged { .. } => "users_changed",
+            Self::AuditRulesChanged => "audit_rules_changed",
+            Self::XrayRulesChanged => "xray_rules_changed",
+            Self::WhiteListChanged => "white_list_changed",
+            Self::BlockListChanged => "block_list_changed",
+            Self::KickUser { .. } => "kick_user",
+        }
+    }
+
+    fn data(&self) -> Value {
+        match self {
+            Self::UsersChanged { user_ids } => json!({ "user_ids": user_ids }),
+            Self::KickUser { user_id, ip } => json!({ "user_id": user_id, "ip": ip }),
+            _ => json!({}),
+        }
+    }
+
+    fn to_message(&self) -> String {
+        json!({
+            "v": WS_PUSH_PROTOCOL_VERSION,
+            "type": "event",
+            "event": self.name(),
+            "data": self.data(),
+        })
+        .to_string()
+    }
+}
+
+struct NodeConnection {
+    id: u64,
+    sender: mpsc::Sender<String>,
+}
+
+/// 当前实例上已连接节点的注册表
+#[derive(Clone, Default)]
+pub struct NodeHub {
+    connections: Arc<RwLock<HashMap<i64, Vec<NodeConnection>>>>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl NodeHub {
+    pub async fn register(&self, node_id: i64) -> (u64, mpsc::Receiver<String>) {
+        let (sender, receiver) = mpsc::channel(PUSH_CHANNEL_CAPACITY);
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        self.connections
+            .write()
+            .await
+            .entry(node_id)
+            .or_default()
+            .push(NodeConnection { id, sender });
+        (id, receiver)
+    }
+
+    pub async fn unregister(&self, node_id: i64, connection_id: u64) {
+        let mut connections = self.connections.write().await;
+        if let Some(list) = connections.get_mut(&node_id) {
+            list.retain(|item| item.id != connection_id);
+            if list.is_empty() {
+                connections.remove(&node_id);
+            }
+        }
+    }
+
+    pub async fn broadcast(&self, event: &NodeEvent) -> usize {
+        let message = event.to_message();
+        let connections = self.connections.read().await;
+        connections
+            .values()
+            .flatten()
+            .filter(|item| item.sender.try_send(message.clone()).is_ok())
+            .count()
+    }
+
+    pub async fn send_to_node(&self, node_id: i64, event: &NodeEvent) -> usize {
+        let message = event.to_message();
+        let connections = self.connections.read().await;
+        connections
+            .get(&node_id)
+            .map(|list| {
+                list.iter()
+                    .filter(|item| item.sender.try_send(message.clone()).is_ok())
+                    .count()
+            })
+            .unwrap_or(0)
+    }
+}
+
+/// 向所有已连接节点推送事件
+pub async fn notify_nodes(state: &AppState, event: NodeEvent) {
+    let delivered = state.node_hub.broadcast(&event).await;
+    tracing::debug!(event = event.name(), delivered, "node push broadcast");
+}
+
+/// 向指定节点推送事件
+pub async fn notify_node(state: &AppState, node_id: i64, event: NodeEvent) {
+    let delivered = state.node_hub.send_to_node(node_id, &event).await;
+    tracing::debug!(event = event.name(), node_id, delivered, "node push sent");
+}
@@ -1,14 +1,28 @@
-use crate::config::AppEnv;
+mod bank;
+mod epay;
+mod epusdt;
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+
 use md5::compute as md5_compute;
-use serde::{Deserialize, Serialize};
+use serde::Serialize;
 use serde_json::Value;
-use std::time::Duration;
-use urlencoding::encode;
+use sqlx::Row;
+
+use crate::config::AppEnv;
+use crate::money::Money;
+use crate::state::AppState;
+
+use bank::BankTransferProvider;
+use epay::EpayProvider;
+use epusdt::EpusdtProvider;
 
 #[derive(Clone, Debug)]
 pub struct PaymentOrder {
     pub trade_no: String,
-    pub amount: f64,
+    pub amount: Money,
     pub subject: String,
     pub notify_url: String,
     pub return_url: String,
@@ -32,6 +46,22 @@ pub struct PaymentCallbackResult {
     pub method: Option<String>,
 }
 
+/// 通道侧订单状态
+#[derive(Clone, Debug, Serialize)]
+pub struct PaymentQueryResult {
+    pub paid: bool,
+    pub amount: Option<Money>,
+    pub status: String,
+}
+
+/// 通道退款结果，`manual` 表示需线下处理
+#[derive(Clone, Debug, Serialize)]
+pub struct PaymentRefundResult {
+    pub success: bool,
+    pub manual: bool,
+    pub message: Option<String>,
+}
+
 #[derive(Clone, Debug, Serialize)]
 pub struct PaymentMethodInfo {
     pub value: String,
@@ -39,7 +69,141 @@ pub struct PaymentMethodInfo {
     pub provider: Option<String>,
 }
 
-const CHANNELS: [&str; 3] = ["alipay", "wxpay", "crypto"];
+pub type ProviderFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, String>> + Send + 'a>>;
+
+/// 支付通道：下单、回调验签、查单与退款
+pub trait PaymentProvider: Send + Sync {
+    fn is_configured(&self) -> bool;
+
+    fn create_order<'a>(
+        &'a self,
+        order: &'a PaymentOrder,
+        channel: &'a str,
+    ) -> ProviderFuture<'a, PaymentCreateResult>;
+
+    fn verify_callback(&self, payload: &serde_json::Map<String, Value>) -> PaymentCallbackResult;
+
+    /// 回调处理成功时返回给通道的应答内容
+    fn callback_ack(&self) -> &'static str {
+        "success"
+    }
+
+    fn query_order<'a>(&'a self, trade_no: &'a str) -> ProviderFuture<'a, PaymentQueryResult>;
+
+    fn refund<'a>(
+        &'a self,
+        trade_no: &'a str,
+        amount: &'a Money,
+    ) -> ProviderFuture<'a, PaymentRefundResult>;
+}
+
+const CHANNELS: [&str; 4] = ["alipay", "wxpay", "crypto", "bank"];
+const PROVIDERS: [&str; 3] = ["epay", "epusdt", "bank"];
+
+/// 可在 system_configs 中覆盖的支付配置项（未配置时回退到环境变量）
+const PAYMENT_CONFIG_KEYS: [&str; 20] = [
+    "site_name",
+    "payment_alipay",
+    "payment_wxpay",
+    "payment_crypto",
+    "payment_bank",
+    "epay_api_url",
+    "epay_pid",
+    "epay_key",
+    "epay_notify_url",
+    "epay_return_url",
+    "epay_payment_mode",
+    "epusdt_api_url",
+    "epusdt_token",
+    "epusdt_notify_url",
+    "epusdt_return_url",
+    "epusdt_trade_type",
+    "epusdt_timeout",
+    "bank_webhook_secret",
+    "bank_pay_url",
+    "bank_instructions",
+];
+
+/// 支付配置快照
+#[derive(Clone, Debug, Default)]
+pub struct PaymentSettings {
+    values: HashMap<String, String>,
+}
+
+impl PaymentSettings {
+    pub fn from_env(env: &AppEnv) -> Self {
+        let pairs = [
+            ("site_name", env.site_name.clone()),
+            ("payment_alipay", env.payment_alipay.clone()),
+            ("payment_wxpay", env.payment_wxpay.clone()),
+            ("payment_crypto", env.payment_crypto.clone()),
+            ("epay_api_url", env.epay_api_url.clone()),
+            ("epay_pid", env.epay_pid.clone()),
+            ("epay_key", env.epay_key.clone()),
+            ("epay_notify_url", env.epay_notify_url.clone()),
+            ("epay_return_url", env.epay_return_url.clone()),
+            ("epay_payment_mode", env.epay_payment_mode.clone()),
+            ("epusdt_api_url", env.epusdt_api_url.clone()),
+            ("epusdt_token", env.epusdt_token.clone()),
+            ("epusdt_notify_url", env.epusdt_notify_url.clone()),
+            ("epusdt_return_url", env.epusdt_return_url.clone()),
+            ("epusdt_trade_type", env.epusdt_trade_type.clone()),
+            (
+                "epusdt_timeout",
+                env.epusdt_timeout.map(|value| value.to_string()),
+            ),
+        ];
+        let mut settings = Self::default();
+        for (key, value) in pairs {
+            if let Some(value) = value {
+                settings.set(key, &value);
+            }
+        }
+        settings
+    }
+
+    /// 读取 system_configs 中的支付配置，非空值覆盖环境变量
+    pub async fn load(state: &AppState) -> Self {
+        let mut settings = Self::from_env(&state.env);
+        let placeholders = vec!["?"; PAYMENT_CONFIG_KEYS.len()].join(", ");
+        let sql =
+            format!("SELECT `key`, `value` FROM system_configs WHERE `key` IN ({placeholders})");
+        let mut query = sqlx::query(&sql);
+        for key in PAYMENT_CONFIG_KEYS {
+            query = query.bind(key);
+        }
+        match query.fetch_all(&state.db).await {
+            Ok(rows) => {
+                for row in rows {
+                    let key = row
+                        .try_get::<Option<String>, _>("key")
+                        .ok()
+                        .flatten()
+                        .unwrap_or_default();
+                    let value = row
+                        .try_get::<Option<String>, _>("value")
+                        .ok()
+                        .flatten()
+                        .unwrap_or_default();
+                    settings.set(&key, &value);
+                }
+            }
+            Err(err) => tracing::warn!("[payment] load settings failed: {err}"),
+        }
+        settings
+    }
+
+    fn set(&mut self, key: &str, value: &str) {
+        let value = value.trim();
+        if !value.is_empty() {
+            self.values.insert(key.to_string(), value.to_string());
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
+}
 
 pub fn normalize_channel(input: Option<&str>) -> Option<&'static str> {
     let value = input.unwrap_or("").trim().to_lowercase();
@@ -60,37 +224,46 @@ pub fn normalize_channel(input: Option<&str>) -> Option<&'static str> {
     {
         return Some("crypto");
     }
+    if value == "bank" || value == "transfer" || value == "bank_transfer" {
+        return Some("bank");
+    }
     None
 }
 
-pub fn get_channel_provider_type(env: &AppEnv, channel: &str) -> Option<&'static str> {
-    let raw = match channel {
-        "alipay" => env.payment_alipay.as_deref(),
-        "wxpay" => env.payment_wxpay.as_deref(),
-        "crypto" => env.payment_crypto.as_deref(),
-        _ => None,
-    }?;
+pub fn get_channel_provider_type(
+    settings: &PaymentSettings,
+    channel: &str,
+) -> Option<&'static str> {
+    let raw = settings.get(&format!("payment_{channel}"))?;
     let normalized = raw.trim().to_lowercase();
-    if normalized.is_empty() || normalized == "none" {
-        return None;
-    }
-    if normalized == "epay" {
-        return Some("epay");
-    }
-    if normalized == "epusdt" {
-        return Some("epusdt");
+    PROVIDERS
+        .iter()
+        .copied()
+        .find(|provider| *provider == normalized)
+}
+
+pub fn build_provider(settings: &PaymentSettings, name: &str) -> Option<Box<dyn PaymentProvider>> {
+    match name {
+        "epay" => Some(Box::new(EpayProvider::from_settings(settings))),
+        "epusdt" => Some(Box::new(EpusdtProvider::from_settings(settings))),
+        "bank" => Some(Box::new(BankTransferProvider::from_settings(settings))),
+        _ => None,
     }
-    None
 }
 
-pub fn get_payment_methods(env: &AppEnv) -> Vec<PaymentMethodInfo> {
-    active_channels(env)
+fn channel_provider(settings: &PaymentSettings, channel: &str) -> Option<Box<dyn PaymentProvider>> {
+    build_provider(settings, get_channel_provider_type(settings, channel)?)
+}
+
+pub fn get_payment_methods(settings: &PaymentSettings) -> Vec<PaymentMethodInfo> {
+    active_channels(settings)
         .into_iter()
         .map(|ch| {
-            let provider = get_channel_provider_type(env, ch).map(|value| value.to_string());
+            let provider = get_channel_provider_type(settings, ch).map(|value| value.to_string());
             let (value, label) = match ch {
                 "crypto" => ("usdt".to_string(), "USDT".to_string()),
                 "wxpay" => ("wechat".to_string(), "微信支付".to_string()),
+                "bank" => ("bank".to_string(), "银行转账".to_string()),
                 _ => ("alipay".to_string(), "支付宝".to_string()),
             };
             PaymentMethodInfo {
@@ -102,538 +275,132 @@ pub fn get_payment_methods(env: &AppEnv) -> Vec<PaymentMethodInfo> {
         .collect()
 }
 
-pub fn verify_callback(
-    env: &AppEnv,
-    payload: &serde_json::Map<String, Value>,
-) -> PaymentCallbackResult {
-    if payload.contains_key("token")
-        || payload.get("payType").and_then(Value::as_str) == Some("epusdt")
-    {
-        let mut result = verify_epusdt_callback(env, payload);
-        result.method = Some("epusdt".to_string());
-        return result;
-    }
-
-    let mut epay = verify_epay_callback(env, payload);
-    if epay.ok {
-        epay.method = Some("epay".to_string());
-        return epay;
-    }
-
-    let mut epusdt = verify_epusdt_callback(env, payload);
-    if epusdt.ok {
-        epusdt.method = Some("epusdt".to_string());
-        return epusdt;
-    }
+pub fn active_channels(settings: &PaymentSettings) -> Vec<&'static str> {
+    CHANNELS
+        .iter()
+        .copied()
+        .filter(|ch| {
+            channel_provider(settings, ch).is_some_and(|provider| provider.is_configured())
+        })
+        .collect()
+}
 
-    PaymentCallbackResult {
-        ok: false,
-        trade_no: None,
-        method: None,
-    }
+pub async fn create_payment(
+    settings: &PaymentSettings,
+    order: &PaymentOrder,
+    prefer: Option<&str>,
+) -> Result<PaymentCreateResult, String> {
+    let channel = normalize_channel(prefer)
+        .or_else(|| active_channels(settings).first().copied())
+        .ok_or_else(|| "支付方式不可用".to_string())?;
+    let provider =
+        channel_provider(settings, channel).ok_or_else(|| "支付方式未配置".to_string())?;
+    provider.create_order(order, channel).await
 }
 
-pub fn verify_epay_callback(
-    env: &AppEnv,
+/// 校验回调签名；未指定通道时依次尝试已配置的通道
+pub fn verify_callback(
+    settings: &PaymentSettings,
+    provider: Option<&str>,
     payload: &serde_json::Map<String, Value>,
 ) -> PaymentCallbackResult {
-    if !is_epay_configured(env) {
-        return PaymentCallbackResult {
-            ok: false,
-            trade_no: None,
-            method: None,
+    if let Some(name) = provider {
+        return match build_provider(settings, name) {
+            Some(provider) => provider.verify_callback(payload),
+            None => PaymentCallbackResult {
+                ok: false,
+                trade_no: get_trade_no(payload),
+                method: None,
+            },
         };
     }
 
-    let sign = payload
-        .get("sign")
-        .map(value_to_string)
-        .unwrap_or_default()
-        .to_lowercase();
-    if sign.is_empty() {
-        return PaymentCallbackResult {
-            ok: false,
-            trade_no: get_trade_no(payload),
-            method: None,
-        };
+    let prefer_epusdt = payload.contains_key("token")
+        || payload.get("payType").and_then(Value::as_str) == Some("epusdt");
+    let mut candidates = PROVIDERS.to_vec();
+    if prefer_epusdt {
+        candidates.retain(|name| *name == "epusdt");
     }
-
-    let sign_key = env.epay_key.clone().unwrap_or_default();
-    let mut params = std::collections::BTreeMap::<String, String>::new();
-    for (key, value) in payload.iter() {
-        if key == "sign" || key == "sign_type" {
+    for name in candidates {
+        let Some(provider) = build_provider(settings, name) else {
             continue;
-        }
-        let val = value_to_string(value);
-        if val.is_empty() {
-            continue;
-        }
-        params.insert(key.clone(), val);
-    }
-    let base = params
-        .iter()
-        .map(|(k, v)| format!("{k}={v}"))
-        .collect::<Vec<_>>()
-        .join("&");
-    let expected = md5_hex(&(base + &sign_key)).to_lowercase();
-
-    PaymentCallbackResult {
-        ok: expected == sign,
-        trade_no: get_trade_no(payload),
-        method: None,
-    }
-}
-
-pub fn verify_epusdt_callback(
-    env: &AppEnv,
-    payload: &serde_json::Map<String, Value>,
-) -> PaymentCallbackResult {
-    if !is_epusdt_configured(env) {
-        return PaymentCallbackResult {
-            ok: false,
-            trade_no: None,
-            method: None,
-        };
-    }
-
-    let signature = payload
-        .get("signature")
-        .or_else(|| payload.get("sign"))
-        .map(value_to_string)
-        .unwrap_or_default()
-        .to_lowercase();
-    if signature.is_empty() {
-        return PaymentCallbackResult {
-            ok: false,
-            trade_no: get_trade_no(payload),
-            method: None,
         };
-    }
-
-    let mut params = std::collections::BTreeMap::<String, String>::new();
-    for (key, value) in payload.iter() {
-        if key == "signature" || key == "sign" {
+        if !provider.is_configured() {
             continue;
         }
-        let value_str = value_to_string(value);
-        if value_str.trim().is_empty() {
-            continue;
+        let result = provider.verify_callback(payload);
+        if result.ok || prefer_epusdt {
+            return result;
         }
-        params.insert(key.clone(), value_str);
     }
-    let base = params
-        .iter()
-        .map(|(k, v)| format!("{k}={v}"))
-        .collect::<Vec<_>>()
-        .join("&");
-    let token = env.epusdt_token.clone().unwrap_or_default();
-    let expected = md5_hex(&(base + &token)).to_lowercase();
-
-    let status_ok = payload
-        .get("status")
-        .and_then(value_to_f64)
-        .map(|value| (value - 2.0).abs() < f64::EPSILON)
-        .unwrap_or(true);
 
     PaymentCallbackResult {
-        ok: expected == signature && status_ok,
+        ok: false,
         trade_no: get_trade_no(payload),
         method: None,
     }
 }
 
-pub fn active_channels(env: &AppEnv) -> Vec<&'static str> {
-    CHANNELS
-        .iter()
-        .copied()
-        .filter(|ch| {
-            let provider = match get_channel_provider_type(env, ch) {
-                Some(value) => value,
-                None => return false,
-            };
-            match provider {
-                "epay" => is_epay_configured(env),
-                "epusdt" => is_epusdt_configured(env),
-                _ => false,
-            }
-        })
-        .collect()
+pub fn callback_ack(settings: &PaymentSettings, provider: Option<&str>) -> &'static str {
+    provider
+        .and_then(|name| build_provider(settings, name))
+        .map(|provider| provider.callback_ack())
+        .unwrap_or("success")
 }
 
-pub async fn create_payment(
-    env: &AppEnv,
-    order: &PaymentOrder,
-    prefer: Option<&str>,
-) -> Result<PaymentCreateResult, String> {
-    let channel = normalize_channel(prefer)
-        .or_else(|| active_channels(env).first().copied())
-        .ok_or_else(|| "支付方式不可用".to_string())?;
+/// 查询订单在指定通道的支付状态
+pub async fn query_payment(
+    settings: &PaymentSettings,
+    provider: &str,
+    trade_no: &str,
+) -> Result<PaymentQueryResult, String> {
     let provider =
-        get_channel_provider_type(env, channel).ok_or_else(|| "支付方式未配置".to_string())?;
-
-    match provider {
-        "epay" => create_epay_payment(env, order, channel).await,
-        "epusdt" => create_epusdt_payment(env, order).await,
-        _ => Err("支付方式未配置".to_string()),
-    }
+        build_provider(settings, provider).ok_or_else(|| "支付方式未配置".to_string())?;
+    provider.query_order(trade_no).await
 }
 
-fn is_epay_configured(env: &AppEnv) -> bool {
-    env.epay_api_url
-        .as_ref()
-        .is_some_and(|v| !v.trim().is_empty())
-        && env.epay_pid.as_ref().is_some_and(|v| !v.trim().is_empty())
-        && env.epay_key.as_ref().is_some_and(|v| !v.trim().is_empty())
+/// 根据订单记录的支付方式解析当前对应的通道（余额补差订单取补差通道）
+pub async fn find_order_provider(
+    state: &AppState,
+    settings: &PaymentSettings,
+    trade_no: &str,
+) -> Result<Option<&'static str>, String> {
+    let row = sqlx::query(
+        r#"
+    SELECT payment_method AS method FROM recharge_records WHERE trade_no = ?
+    UNION ALL
+    SELECT purchase_type AS method FROM package_purchase_records WHERE trade_no = ?
+    LIMIT 1
+    "#,
+    )
+    .bind(trade_no)
+    .bind(trade_no)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|err| err.to_string())?;
+    let method = row
+        .and_then(|row| row.try_get::<Option<String>, _>("method").ok().flatten())
+        .unwrap_or_default();
+    Ok(order_method_provider(settings, &method))
 }
 
-fn is_epusdt_configured(env: &AppEnv) -> bool {
-    env.epusdt_api_url
-        .as_ref()
-        .is_some_and(|v| !v.trim().is_empty())
-        && env
-            .epusdt_token
-            .as_ref()
-            .is_some_and(|v| !v.trim().is_empty())
-        && env
-            .epusdt_notify_url
-            .as_ref()
-            .is_some_and(|v| !v.trim().is_empty())
+/// 订单记录中的支付方式（如 `alipay`、`balance_wxpay`）对应的支付接口
+pub fn order_method_provider(settings: &PaymentSettings, method: &str) -> Option<&'static str> {
+    let method = method.trim().to_lowercase();
+    let channel = normalize_channel(Some(method.trim_start_matches("balance_")))?;
+    get_channel_provider_type(settings, channel)
 }
 
-async fn create_epay_payment(
-    env: &AppEnv,
-    order: &PaymentOrder,
-    channel: &str,
-) -> Result<PaymentCreateResult, String> {
-    if !is_epay_configured(env) {
-        return Ok(PaymentCreateResult {
-            method: "epay".to_string(),
-            pay_url: None,
-            success: false,
-            message: Some("支付方式未配置".to_string()),
-            pay_type: None,
-        });
-    }
-
-    let api_url = env
-        .epay_api_url
-        .as_ref()
-        .map(|value| value.trim().trim_end_matches('/'))
-        .unwrap_or("https://pay.example.com");
-
-    let payment_mode = env
-        .epay_payment_mode
-        .as_deref()
-        .unwrap_or("redirect")
-        .to_lowercase();
-
-    let pay_type = if channel == "wxpay" {
-        "wxpay"
-    } else {
-        "alipay"
-    };
-    let return_url = if !order.return_url.trim().is_empty() {
-        order.return_url.trim().to_string()
-    } else {
-        env.epay_return_url.clone().unwrap_or_default()
-    };
-    let notify_url = if !order.notify_url.trim().is_empty() {
-        order.notify_url.trim().to_string()
-    } else {
-        env.epay_notify_url.clone().unwrap_or_default()
-    };
-
-    let mut params = std::collections::BTreeMap::<String, String>::new();
-    params.insert("pid".to_string(), env.epay_pid.clone().unwrap_or_default());
-    params.insert("type".to_string(), pay_type.to_string());
-    params.insert("out_trade_no".to_string(), order.trade_no.clone());
-    params.insert("notify_url".to_string(), notify_url);
-    params.insert("return_url".to_string(), return_url);
-    params.insert("name".to_string(), order.subject.clone());
-    params.insert("money".to_string(), order.amount.to_string());
-    params.insert(
-        "clientip".to_string(),
-        order
-            .clientip
-            .clone()
-            .unwrap_or_else(|| "127.0.0.1".to_string()),
-    );
-    params.insert(
-        "sitename".to_string(),
-        env.site_name
-            .clone()
-            .unwrap_or_else(|| "Soga Panel".to_string()),
-    );
-
-    let sign_base = params
-        .iter()
-        .map(|(k, v)| format!("{k}={v}"))
-        .collect::<Vec<_>>()
-        .join("&");
-    let sign_key = env.epay_key.clone().unwrap_or_default();
-    let sign = md5_hex(&(sign_base + &sign_key));
-    params.insert("sign".to_string(), sign);
-    params.insert("sign_type".to_string(), "MD5".to_string());
-
-    if payment_mode == "api" {
-        let mapi_url = format!("{api_url}/mapi.php");
-        let client = reqwest::Client::builder()
-            .timeout(Duration::from_secs(15))
-            .build()
-            .map_err(|err| {
-                eprintln!("Failed to build reqwest client: {}", err);
-                err.to_string()
-            })?;
-
-        let response = client
-            .post(mapi_url)
-            .form(&params)
-            .send()
-            .await
-            .map_err(|err| {
-                eprintln!("易支付接口请求失败: {}", err);
-                format!("易支付接口请求失败: {err}")
-            })?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let text = response.text().await.unwrap_or_default();
-            eprintln!("易支付接口错误: {} - {}", status, text);
-            return Ok(PaymentCreateResult {
-                method: "epay".to_string(),
-                pay_url: None,
-                success: false,
-                message: Some(format!("易支付接口错误: {}", status)),
-                pay_type: None,
-            });
-        }
-
-        #[derive(Deserialize)]
-        struct EpayApiResponse {
-            code: i64,
-            msg: Option<String>,
-            payurl: Option<String>,
-            qrcode: Option<String>,
-            urlscheme: Option<String>,
-        }
-
-        let text = response.text().await.map_err(|err| {
-            eprintln!("Failed to get response text: {}", err);
-            err.to_string()
-        })?;
-
-        let data: EpayApiResponse = match serde_json::from_str(&text) {
-            Ok(d) => d,
-            Err(err) => {
-                eprintln!("易支付响应解析失败: {} - Response: {}", err, text);
-                return Ok(PaymentCreateResult {
-                    method: "epay".to_string(),
-                    pay_url: None,
-                    success: false,
-                    message: Some("支付接口响应格式错误".to_string()),
-                    pay_type: None,
-                });
-            }
-        };
-
-        if data.code == 1 {
-            let (pay_url, p_type) = if let Some(url) = data.payurl {
-                (Some(url), Some("url".to_string()))
-            } else if let Some(qr) = data.qrcode {
-                (Some(qr), Some("qrcode".to_string()))
-            } else if let Some(scheme) = data.urlscheme {
-                (Some(scheme), Some("scheme".to_string()))
-            } else {
-                (None, None)
-            };
-
-            Ok(PaymentCreateResult {
-                method: "epay".to_string(),
-                pay_url,
-                success: true,
-                message: None,
-                pay_type: p_type,
-            })
-        } else {
-            Ok(PaymentCreateResult {
-                method: "epay".to_string(),
-                pay_url: None,
-                success: false,
-                message: data.msg.or(Some("易支付下单失败".to_string())),
-                pay_type: None,
-            })
-        }
-    } else {
-        let query = params
-            .iter()
-            .map(|(k, v)| format!("{k}={}", encode(v)))
-            .collect::<Vec<_>>()
-            .join("&");
-        let pay_url = format!("{api_url}/submit.php?{query}");
-
-        Ok(PaymentCreateResult {
-            method: "epay".to_string(),
-            pay_url: Some(pay_url),
-            success: true,
-            message: None,
-            pay_type: Some("url".to_string()),
-        })
-    }
-}
-
-async fn create_epusdt_payment(
-    env: &AppEnv,
-    order: &PaymentOrder,
-) -> Result<PaymentCreateResult, String> {
-    if !is_epusdt_configured(env) {
-        return Ok(PaymentCreateResult {
-            method: "epusdt".to_string(),
-            pay_url: None,
-            success: false,
-            message: Some("USDT 支付未配置".to_string()),
-            pay_type: None,
-        });
-    }
-
-    if order.trade_no.trim().is_empty() {
-        return Ok(PaymentCreateResult {
-            method: "epusdt".to_string(),
-            pay_url: None,
-            success: false,
-            message: Some("缺少订单编号".to_string()),
-            pay_type: None,
-        });
-    }
-    if !order.amount.is_finite() || order.amount <= 0.0 {
-        return Ok(PaymentCreateResult {
-            method: "epusdt".to_string(),
-            pay_url: None,
-            success: false,
-            message: Some("金额异常".to_string()),
-            pay_type: None,
-        });
-    }
-
-    let notify_url = if !order.notify_url.trim().is_empty() {
-        order.notify_url.trim().to_string()
-    } else {
-        env.epusdt_notify_url.clone().unwrap_or_default()
-    };
-    let return_url = if !order.return_url.trim().is_empty() {
-        order.return_url.trim().to_string()
-    } else {
-        env.epusdt_return_url.clone().unwrap_or_default()
-    };
-    let trade_type = env
-        .epusdt_trade_type
-        .clone()
-        .filter(|value| !value.trim().is_empty())
-        .unwrap_or_else(|| "usdt.trc20".to_string());
-    let timeout = env.epusdt_timeout.unwrap_or(600).max(60);
-
-    let mut payload_map = std::collections::BTreeMap::<String, String>::new();
-    payload_map.insert("order_id".to_string(), order.trade_no.clone());
-    payload_map.insert("amount".to_string(), order.amount.to_string());
-    payload_map.insert("trade_type".to_string(), trade_type);
-    payload_map.insert("notify_url".to_string(), notify_url);
-    payload_map.insert("redirect_url".to_string(), return_url);
-    payload_map.insert("timeout".to_string(), timeout.to_string());
-
-    let signature =
-        generate_epusdt_sign(&payload_map, env.epusdt_token.clone().unwrap_or_default());
-    payload_map.insert("signature".to_string(), signature);
-
-    let api_base = env.epusdt_api_url.clone().unwrap_or_default();
-    let url = format!(
-        "{}/api/v1/order/create-transaction",
-        api_base.trim_end_matches('/')
-    );
-    let client = reqwest::Client::builder()
-        .timeout(Duration::from_secs(15))
-        .build()
-        .map_err(|err| err.to_string())?;
-
-    let payload_json: Value = payload_map
-        .iter()
-        .map(|(k, v)| (k.clone(), Value::String(v.clone())))
-        .collect::<serde_json::Map<String, Value>>()
-        .into();
-
-    let response = client
-        .post(url)
-        .header("Content-Type", "application/json")
-        .json(&payload_json)
-        .send()
-        .await
-        .map_err(|err| format!("USDT 支付请求失败: {err}"))?;
-
-    if !response.status().is_success() {
-        let status = response.status();
-        let text = response.text().await.unwrap_or_default();
-        return Ok(PaymentCreateResult {
-            method: "epusdt".to_string(),
-            pay_url: None,
-            success: false,
-            message: Some(format!("USDT 支付接口错误: {} {}", status.as_u16(), text)),
-            pay_type: None,
-        });
-    }
-
-    let data: EpusdtCreateResponse = response
-        .json()
-        .await
-        .map_err(|_| "USDT 支付解析响应失败".to_string())?;
-
-    if data.status_code != 200 {
-        return Ok(PaymentCreateResult {
-            method: "epusdt".to_string(),
-            pay_url: None,
-            success: false,
-            message: Some(
-                data.message
-                    .unwrap_or_else(|| "创建 USDT 支付订单失败".to_string()),
-            ),
-            pay_type: None,
-        });
-    }
-
-    let pay_url = data
-        .data
-        .and_then(|value| value.payment_url)
-        .filter(|value| !value.trim().is_empty());
-    if pay_url.is_none() {
-        return Ok(PaymentCreateResult {
-            method: "epusdt".to_string(),
-            pay_url: None,
-            success: false,
-            message: Some(
-                data.message
-                    .unwrap_or_else(|| "创建 USDT 支付订单失败".to_string()),
-            ),
-            pay_type: None,
-        });
-    }
-
-    Ok(PaymentCreateResult {
-        method: "epusdt".to_string(),
-        pay_url,
-        success: true,
-        message: None,
-        pay_type: Some("url".to_string()),
-    })
-}
-
-fn generate_epusdt_sign(
-    params: &std::collections::BTreeMap<String, String>,
-    token: String,
-) -> String {
-    let base = params
-        .iter()
-        .filter(|(k, v)| !k.is_empty() && !v.trim().is_empty() && *k != "signature" && *k != "sign")
-        .map(|(k, v)| format!("{k}={v}"))
-        .collect::<Vec<_>>()
-        .join("&");
-    md5_hex(&(base + &token))
+/// 通过订单所属通道发起退款
+pub async fn refund_payment(
+    settings: &PaymentSettings,
+    provider: &str,
+    trade_no: &str,
+    amount: &Money,
+) -> Result<PaymentRefundResult, String> {
+    let provider =
+        build_provider(settings, provider).ok_or_else(|| "支付方式未配置".to_string())?;
+    provider.refund(trade_no, amount).await
 }
 
 pub fn md5_hex(input: &str) -> String {
@@ -649,6 +416,24 @@ fn get_trade_no(payload: &serde_json::Map<String, Value>) -> Option<String> {
         .filter(|value| !value.trim().is_empty())
 }
 
+/// 回调上报的订单金额（ePay 为 `money`，epusdt 为人民币 `amount`）
+pub fn get_callback_amount(payload: &serde_json::Map<String, Value>) -> Option<Money> {
+    payload
+        .get("money")
+        .or_else(|| payload.get("amount"))
+        .and_then(value_to_money)
+}
+
+/// 回调上报的币种，未携带时视为人民币
+pub fn get_callback_currency(payload: &serde_json::Map<String, Value>) -> String {
+    payload
+        .get("currency")
+        .map(value_to_string)
+        .map(|value| value.trim().to_uppercase())
+        .filter(|value| !value.is_empty())
+        .unwrap_or_else(|| "CNY".to_string())
+}
+
 pub fn value_to_string(value: &Value) -> String {
     if let Some(text) = value.as_str() {
         return text.to_string();
@@ -669,6 +454,13 @@ pub fn value_to_string(value: &Value) -> String {
     String::new()
 }
 
+pub fn value_to_money(value: &Value) -> Option<Money> {
+    match value.as_str() {
+        Some(text) => Money::parse(text),
+        None => value.as_f64().and_then(Money::from_f64),
+    }
+}
+
 pub fn value_to_f64(value: &Value) -> Option<f64> {
     if let Some(number) = value.as_f64() {
         return Some(number);
@@ -681,15 +473,3 @@ pub fn value_to_f64(value: &Value) -> Option<f64> {
     }
     None
 }
-
-#[derive(Deserialize)]
-struct EpusdtCreateResponse {
-    status_code: i64,
-    message: Option<String>,
-    data: Option<EpusdtCreateData>,
-}
-
-#[derive(Deserialize)]
-struct EpusdtCreateData {
-    payment_url: Option<String>,
-}
@@ -0,0 +1,176 @@
+use std::collections::BTreeMap;
+
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde_json::Value;
+use sha2::Sha256;
+use urlencoding::encode;
+
+use crate::money::Money;
+
+use super::{
+    get_trade_no, value_to_f64, value_to_string, PaymentCallbackResult, PaymentCreateResult,
+    PaymentOrder, PaymentProvider, PaymentQueryResult, PaymentRefundResult, PaymentSettings,
+    ProviderFuture,
+};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// 回调时间戳允许的偏差（秒），超出视为重放
+const WEBHOOK_TOLERANCE_SECS: i64 = 300;
+
+/// 人工转账/银行到账：下单跳转到收款说明页，到账后由对账系统发送 HMAC-SHA256 签名回调
+pub struct BankTransferProvider {
+    secret: String,
+    pay_url: String,
+    instructions: String,
+}
+
+impl BankTransferProvider {
+    pub fn from_settings(settings: &PaymentSettings) -> Self {
+        let read = |key: &str| settings.get(key).unwrap_or_default().to_string();
+        Self {
+            secret: read("bank_webhook_secret"),
+            pay_url: read("bank_pay_url"),
+            instructions: read("bank_instructions"),
+        }
+    }
+
+    /// 按参数名排序拼接 `k=v&...`（忽略 sign 与空值）后计算 HMAC-SHA256
+    fn mac(secret: &str, params: &BTreeMap<String, String>) -> Option<HmacSha256> {
+        let base = params
+            .iter()
+            .filter(|(k, v)| *k != "sign" && !v.trim().is_empty())
+            .map(|(k, v)| format!("{k}={v}"))
+            .collect::<Vec<_>>()
+            .join("&");
+        let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).ok()?;
+        mac.update(base.as_bytes());
+        Some(mac)
+    }
+
+    fn verify(&self, payload: &serde_json::Map<String, Value>, now: i64) -> bool {
+        let sign = payload.get("sign").map(value_to_string).unwrap_or_default();
+        if self.secret.is_empty() || sign.is_empty() {
+            return false;
+        }
+        let Ok(sign) = hex::decode(sign.trim()) else {
+            return false;
+        };
+        let fresh = payload
+            .get("timestamp")
+            .and_then(value_to_f64)
+            .is_some_and(|ts| (now - ts as i64).abs() <= WEBHOOK_TOLERANCE_SECS);
+        let status_ok = payload
+            .get("status")
+            .map(value_to_string)
+            .map(|value| matches!(value.trim().to_lowercase().as_str(), "paid" | "success"))
+            .unwrap_or(true);
+        let params = payload
+            .iter()
+            .map(|(key, value)| (key.clone(), value_to_string(value)))
+            .collect::<BTreeMap<String, String>>();
+        // 解码后用 verify_slice 做常量时间比较，避免逐字符比较泄露签名
+        let signed =
+            Self::mac(&self.secret, &params).is_some_and(|mac| mac.verify_slice(&sign).is_ok());
+        fresh && status_ok && signed
+    }
+}
+
+impl PaymentProvider for BankTransferProvider {
+    fn is_configured(&self) -> bool {
+        !self.secret.is_empty() && !self.pay_url.is_empty()
+    }
+
+    fn create_order<'a>(
+        &'a self,
+        order: &'a PaymentOrder,
+        _channel: &'a str,
+    ) -> ProviderFuture<'a, PaymentCreateResult> {
+        Box::pin(async move {
+            if !self.is_configured() {
+                return Ok(PaymentCreateResult {
+                    method: "bank".to_string(),
+                    pay_url: None,
+                    success: false,
+                    message: Some("银行转账未配置".to_string()),
+                    pay_type: None,
+                });
+            }
+            let amount = order.amount.to_string();
+            let pay_url = self
+                .pay_url
+                .replace("{trade_no}", &encode(&order.trade_no))
+                .replace("{amount}", &amount)
+                .replace("{subject}", &encode(&order.subject));
+            Ok(PaymentCreateResult {
+                method: "bank".to_string(),
+                pay_url: Some(pay_url),
+                success: true,
+                message: Some(self.instructions.clone()).filter(|value| !value.is_empty()),
+                pay_type: Some("url".to_string()),
+            })
+        })
+    }
+
+    fn verify_callback(&self, payload: &serde_json::Map<String, Value>) -> PaymentCallbackResult {
+        PaymentCallbackResult {
+            ok: self.verify(payload, Utc::now().timestamp()),
+            trade_no: get_trade_no(payload),
+            method: Some("bank".to_string()),
+        }
+    }
+
+    fn query_order<'a>(&'a self, _trade_no: &'a str) -> ProviderFuture<'a, PaymentQueryResult> {
+        Box::pin(async { Err("银行转账需人工核对到账记录".to_string()) })
+    }
+
+    fn refund<'a>(
+        &'a self,
+        _trade_no: &'a str,
+        _amount: &'a Money,
+    ) -> ProviderFuture<'a, PaymentRefundResult> {
+        Box::pin(async {
+            Ok(PaymentRefundResult {
+                success: true,
+                manual: true,
+                message: Some("请通过银行转账线下原路退回".to_string()),
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bank_webhook_signature_and_freshness() {
+        let provider = BankTransferProvider {
+            secret: "s3cret".to_string(),
+            pay_url: "http://localhost:3001/bank/{trade_no}".to_string(),
+            instructions: String::new(),
+        };
+        let now = 1_760_000_000;
+        let mut params = BTreeMap::new();
+        params.insert("trade_no".to_string(), "R123".to_string());
+        params.insert("amount".to_string(), "10.00".to_string());
+        params.insert("timestamp".to_string(), now.to_string());
+        let sign = BankTransferProvider::mac("s3cret", &params)
+            .map(|mac| hex::encode(mac.finalize().into_bytes()))
+            .unwrap_or_default();
+
+        let mut payload = params
+            .into_iter()
+            .map(|(k, v)| (k, Value::String(v)))
+            .collect::<serde_json::Map<String, Value>>();
+        payload.insert("sign".to_string(), Value::String(sign.to_uppercase()));
+        assert!(provider.verify(&payload, now + 10));
+        assert!(!provider.verify(&payload, now + WEBHOOK_TOLERANCE_SECS + 1));
+
+        payload.insert("amount".to_string(), Value::String("1.00".to_string()));
+        assert!(!provider.verify(&payload, now));
+        payload.insert("sign".to_string(), Value::String("not-hex".to_string()));
+        assert!(!provider.verify(&payload, now));
+    }
+}
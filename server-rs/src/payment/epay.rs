@@ -0,0 +1,329 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use serde::Deserialize;
+use serde_json::Value;
+use urlencoding::encode;
+
+use crate::money::Money;
+
+use super::{
+    get_trade_no, md5_hex, value_to_f64, value_to_money, value_to_string, PaymentCallbackResult,
+    PaymentCreateResult, PaymentOrder, PaymentProvider, PaymentQueryResult, PaymentRefundResult,
+    PaymentSettings, ProviderFuture,
+};
+
+/// 易支付（彩虹易支付协议，MD5 签名）
+pub struct EpayProvider {
+    api_url: String,
+    pid: String,
+    key: String,
+    notify_url: String,
+    return_url: String,
+    payment_mode: String,
+    site_name: String,
+}
+
+#[derive(Deserialize)]
+struct EpayApiResponse {
+    code: i64,
+    msg: Option<String>,
+    payurl: Option<String>,
+    qrcode: Option<String>,
+    urlscheme: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct EpayOrderResponse {
+    code: i64,
+    msg: Option<String>,
+    status: Option<Value>,
+    money: Option<Value>,
+}
+
+impl EpayProvider {
+    pub fn from_settings(settings: &PaymentSettings) -> Self {
+        let read = |key: &str| settings.get(key).unwrap_or_default().to_string();
+        Self {
+            api_url: read("epay_api_url").trim_end_matches('/').to_string(),
+            pid: read("epay_pid"),
+            key: read("epay_key"),
+            notify_url: read("epay_notify_url"),
+            return_url: read("epay_return_url"),
+            payment_mode: settings
+                .get("epay_payment_mode")
+                .unwrap_or("redirect")
+                .to_lowercase(),
+            site_name: settings
+                .get("site_name")
+                .unwrap_or("Soga Panel")
+                .to_string(),
+        }
+    }
+
+    fn failed(message: &str) -> PaymentCreateResult {
+        PaymentCreateResult {
+            method: "epay".to_string(),
+            pay_url: None,
+            success: false,
+            message: Some(message.to_string()),
+            pay_type: None,
+        }
+    }
+
+    fn sign(&self, params: &BTreeMap<String, String>) -> String {
+        let base = params
+            .iter()
+            .map(|(k, v)| format!("{k}={v}"))
+            .collect::<Vec<_>>()
+            .join("&");
+        md5_hex(&(base + &self.key))
+    }
+
+    fn client() -> Result<reqwest::Client, String> {
+        reqwest::Client::builder()
+            .timeout(Duration::from_secs(15))
+            .build()
+            .map_err(|err| err.to_string())
+    }
+
+    async fn create(
+        &self,
+        order: &PaymentOrder,
+        channel: &str,
+    ) -> Result<PaymentCreateResult, String> {
+        if !self.is_configured() {
+            return Ok(Self::failed("支付方式未配置"));
+        }
+
+        let pay_type = if channel == "wxpay" {
+            "wxpay"
+        } else {
+            "alipay"
+        };
+        let return_url = if !order.return_url.trim().is_empty() {
+            order.return_url.trim().to_string()
+        } else {
+            self.return_url.clone()
+        };
+        let notify_url = if !order.notify_url.trim().is_empty() {
+            order.notify_url.trim().to_string()
+        } else {
+            self.notify_url.clone()
+        };
+
+        let mut params = BTreeMap::<String, String>::new();
+        params.insert("pid".to_string(), self.pid.clone());
+        params.insert("type".to_string(), pay_type.to_string());
+        params.insert("out_trade_no".to_string(), order.trade_no.clone());
+        params.insert("notify_url".to_string(), notify_url);
+        params.insert("return_url".to_string(), return_url);
+        params.insert("name".to_string(), order.subject.clone());
+        params.insert("money".to_string(), order.amount.to_string());
+        params.insert(
+            "clientip".to_string(),
+            order
+                .clientip
+                .clone()
+                .unwrap_or_else(|| "127.0.0.1".to_string()),
+        );
+        params.insert("sitename".to_string(), self.site_name.clone());
+
+        let sign = self.sign(&params);
+        params.insert("sign".to_string(), sign);
+        params.insert("sign_type".to_string(), "MD5".to_string());
+
+        if self.payment_mode != "api" {
+            let query = params
+                .iter()
+                .map(|(k, v)| format!("{k}={}", encode(v)))
+                .collect::<Vec<_>>()
+                .join("&");
+            return Ok(PaymentCreateResult {
+                method: "epay".to_string(),
+                pay_url: Some(format!("{}/submit.php?{query}", self.api_url)),
+                success: true,
+                message: None,
+                pay_type: Some("url".to_string()),
+            });
+        }
+
+        let response = Self::client()?
+            .post(format!("{}/mapi.php", self.api_url))
+            .form(&params)
+            .send()
+            .await
+            .map_err(|err| {
+                eprintln!("易支付接口请求失败: {}", err);
+                format!("易支付接口请求失败: {err}")
+            })?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            eprintln!("易支付接口错误: {} - {}", status, text);
+            return Ok(Self::failed(&format!("易支付接口错误: {}", status)));
+        }
+
+        let text = response.text().await.map_err(|err| {
+            eprintln!("Failed to get response text: {}", err);
+            err.to_string()
+        })?;
+        let data: EpayApiResponse = match serde_json::from_str(&text) {
+            Ok(d) => d,
+            Err(err) => {
+                eprintln!("易支付响应解析失败: {} - Response: {}", err, text);
+                return Ok(Self::failed("支付接口响应格式错误"));
+            }
+        };
+
+        if data.code != 1 {
+            return Ok(Self::failed(
+                data.msg.as_deref().unwrap_or("易支付下单失败"),
+            ));
+        }
+
+        let (pay_url, p_type) = if let Some(url) = data.payurl {
+            (Some(url), Some("url".to_string()))
+        } else if let Some(qr) = data.qrcode {
+            (Some(qr), Some("qrcode".to_string()))
+        } else if let Some(scheme) = data.urlscheme {
+            (Some(scheme), Some("scheme".to_string()))
+        } else {
+            (None, None)
+        };
+        Ok(PaymentCreateResult {
+            method: "epay".to_string(),
+            pay_url,
+            success: true,
+            message: None,
+            pay_type: p_type,
+        })
+    }
+
+    async fn query(&self, trade_no: &str) -> Result<PaymentQueryResult, String> {
+        if !self.is_configured() {
+            return Err("支付方式未配置".to_string());
+        }
+        let response = Self::client()?
+            .get(format!("{}/api.php", self.api_url))
+            .query(&[
+                ("act", "order"),
+                ("pid", self.pid.as_str()),
+                ("key", self.key.as_str()),
+                ("out_trade_no", trade_no),
+            ])
+            .send()
+            .await
+            .map_err(|err| format!("易支付查询失败: {err}"))?;
+        let data: EpayOrderResponse = response
+            .json()
+            .await
+            .map_err(|_| "易支付查询响应格式错误".to_string())?;
+        if data.code != 1 {
+            return Err(data.msg.unwrap_or_else(|| "易支付查询失败".to_string()));
+        }
+
+        let paid = data
+            .status
+            .as_ref()
+            .and_then(value_to_f64)
+            .is_some_and(|value| value == 1.0);
+        Ok(PaymentQueryResult {
+            paid,
+            amount: data.money.as_ref().and_then(value_to_money),
+            status: if paid { "paid" } else { "pending" }.to_string(),
+        })
+    }
+
+    async fn refund_order(
+        &self,
+        trade_no: &str,
+        amount: &Money,
+    ) -> Result<PaymentRefundResult, String> {
+        if !self.is_configured() {
+            return Err("支付方式未配置".to_string());
+        }
+        let amount = amount.to_string();
+        let response = Self::client()?
+            .post(format!("{}/api.php?act=refund", self.api_url))
+            .form(&[
+                ("pid", self.pid.as_str()),
+                ("key", self.key.as_str()),
+                ("out_trade_no", trade_no),
+                ("money", amount.as_str()),
+            ])
+            .send()
+            .await
+            .map_err(|err| format!("易支付退款请求失败: {err}"))?;
+        let data: EpayOrderResponse = response
+            .json()
+            .await
+            .map_err(|_| "易支付退款响应格式错误".to_string())?;
+        Ok(PaymentRefundResult {
+            success: data.code == 1,
+            manual: false,
+            message: data.msg,
+        })
+    }
+}
+
+impl PaymentProvider for EpayProvider {
+    fn is_configured(&self) -> bool {
+        !self.api_url.is_empty() && !self.pid.is_empty() && !self.key.is_empty()
+    }
+
+    fn create_order<'a>(
+        &'a self,
+        order: &'a PaymentOrder,
+        channel: &'a str,
+    ) -> ProviderFuture<'a, PaymentCreateResult> {
+        Box::pin(self.create(order, channel))
+    }
+
+    fn verify_callback(&self, payload: &serde_json::Map<String, Value>) -> PaymentCallbackResult {
+        let mut result = PaymentCallbackResult {
+            ok: false,
+            trade_no: get_trade_no(payload),
+            method: Some("epay".to_string()),
+        };
+        if !self.is_configured() {
+            return result;
+        }
+
+        let sign = payload
+            .get("sign")
+            .map(value_to_string)
+            .unwrap_or_default()
+            .to_lowercase();
+        if sign.is_empty() {
+            return result;
+        }
+
+        let mut params = BTreeMap::<String, String>::new();
+        for (key, value) in payload.iter() {
+            if key == "sign" || key == "sign_type" {
+                continue;
+            }
+            let val = value_to_string(value);
+            if val.is_empty() {
+                continue;
+            }
+            params.insert(key.clone(), val);
+        }
+        result.ok = self.sign(&params).to_lowercase() == sign;
+        result
+    }
+
+    fn query_order<'a>(&'a self, trade_no: &'a str) -> ProviderFuture<'a, PaymentQueryResult> {
+        Box::pin(self.query(trade_no))
+    }
+
+    fn refund<'a>(
+        &'a self,
+        trade_no: &'a str,
+        amount: &'a Money,
+    ) -> ProviderFuture<'a, PaymentRefundResult> {
+        Box::pin(self.refund_order(trade_no, amount))
+    }
+}
@@ -0,0 +1,226 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::money::Money;
+
+use super::{
+    get_trade_no, md5_hex, value_to_f64, value_to_string, PaymentCallbackResult,
+    PaymentCreateResult, PaymentOrder, PaymentProvider, PaymentQueryResult, PaymentRefundResult,
+    PaymentSettings, ProviderFuture,
+};
+
+/// epusdt（USDT 收款，MD5 签名）
+pub struct EpusdtProvider {
+    api_url: String,
+    token: String,
+    notify_url: String,
+    return_url: String,
+    trade_type: String,
+    timeout: u64,
+}
+
+#[derive(Deserialize)]
+struct EpusdtCreateResponse {
+    status_code: i64,
+    message: Option<String>,
+    data: Option<EpusdtCreateData>,
+}
+
+#[derive(Deserialize)]
+struct EpusdtCreateData {
+    payment_url: Option<String>,
+}
+
+impl EpusdtProvider {
+    pub fn from_settings(settings: &PaymentSettings) -> Self {
+        let read = |key: &str| settings.get(key).unwrap_or_default().to_string();
+        Self {
+            api_url: read("epusdt_api_url").trim_end_matches('/').to_string(),
+            token: read("epusdt_token"),
+            notify_url: read("epusdt_notify_url"),
+            return_url: read("epusdt_return_url"),
+            trade_type: settings
+                .get("epusdt_trade_type")
+                .unwrap_or("usdt.trc20")
+                .to_string(),
+            timeout: settings
+                .get("epusdt_timeout")
+                .and_then(|value| value.parse::<u64>().ok())
+                .unwrap_or(600)
+                .max(60),
+        }
+    }
+
+    fn failed(message: String) -> PaymentCreateResult {
+        PaymentCreateResult {
+            method: "epusdt".to_string(),
+            pay_url: None,
+            success: false,
+            message: Some(message),
+            pay_type: None,
+        }
+    }
+
+    fn sign(&self, params: &BTreeMap<String, String>) -> String {
+        let base = params
+            .iter()
+            .filter(|(k, v)| {
+                !k.is_empty() && !v.trim().is_empty() && *k != "signature" && *k != "sign"
+            })
+            .map(|(k, v)| format!("{k}={v}"))
+            .collect::<Vec<_>>()
+            .join("&");
+        md5_hex(&(base + &self.token))
+    }
+
+    async fn create(&self, order: &PaymentOrder) -> Result<PaymentCreateResult, String> {
+        if !self.is_configured() {
+            return Ok(Self::failed("USDT 支付未配置".to_string()));
+        }
+        if order.trade_no.trim().is_empty() {
+            return Ok(Self::failed("缺少订单编号".to_string()));
+        }
+        if !order.amount.is_positive() {
+            return Ok(Self::failed("金额异常".to_string()));
+        }
+
+        let notify_url = if !order.notify_url.trim().is_empty() {
+            order.notify_url.trim().to_string()
+        } else {
+            self.notify_url.clone()
+        };
+        let return_url = if !order.return_url.trim().is_empty() {
+            order.return_url.trim().to_string()
+        } else {
+            self.return_url.clone()
+        };
+
+        let mut payload_map = BTreeMap::<String, String>::new();
+        payload_map.insert("order_id".to_string(), order.trade_no.clone());
+        payload_map.insert("amount".to_string(), order.amount.to_string());
+        payload_map.insert("trade_type".to_string(), self.trade_type.clone());
+        payload_map.insert("notify_url".to_string(), notify_url);
+        payload_map.insert("redirect_url".to_string(), return_url);
+        payload_map.insert("timeout".to_string(), self.timeout.to_string());
+        let signature = self.sign(&payload_map);
+        payload_map.insert("signature".to_string(), signature);
+
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(15))
+            .build()
+            .map_err(|err| err.to_string())?;
+        let payload_json: Value = payload_map
+            .iter()
+            .map(|(k, v)| (k.clone(), Value::String(v.clone())))
+            .collect::<serde_json::Map<String, Value>>()
+            .into();
+
+        let response = client
+            .post(format!("{}/api/v1/order/create-transaction", self.api_url))
+            .header("Content-Type", "application/json")
+            .json(&payload_json)
+            .send()
+            .await
+            .map_err(|err| format!("USDT 支付请求失败: {err}"))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            return Ok(Self::failed(format!(
+                "USDT 支付接口错误: {} {}",
+                status.as_u16(),
+                text
+            )));
+        }
+
+        let data: EpusdtCreateResponse = response
+            .json()
+            .await
+            .map_err(|_| "USDT 支付解析响应失败".to_string())?;
+        let pay_url = data
+            .data
+            .and_then(|value| value.payment_url)
+            .filter(|value| !value.trim().is_empty());
+        if data.status_code != 200 || pay_url.is_none() {
+            return Ok(Self::failed(
+                data.message
+                    .unwrap_or_else(|| "创建 USDT 支付订单失败".to_string()),
+            ));
+        }
+
+        Ok(PaymentCreateResult {
+            method: "epusdt".to_string(),
+            pay_url,
+            success: true,
+            message: None,
+            pay_type: Some("url".to_string()),
+        })
+    }
+}
+
+impl PaymentProvider for EpusdtProvider {
+    fn is_configured(&self) -> bool {
+        !self.api_url.is_empty() && !self.token.is_empty() && !self.notify_url.is_empty()
+    }
+
+    fn create_order<'a>(
+        &'a self,
+        order: &'a PaymentOrder,
+        _channel: &'a str,
+    ) -> ProviderFuture<'a, PaymentCreateResult> {
+        Box::pin(self.create(order))
+    }
+
+    fn verify_callback(&self, payload: &serde_json::Map<String, Value>) -> PaymentCallbackResult {
+        let mut result = PaymentCallbackResult {
+            ok: false,
+            trade_no: get_trade_no(payload),
+            method: Some("epusdt".to_string()),
+        };
+        if !self.is_configured() {
+            return result;
+        }
+
+        let signature = payload
+            .get("signature")
+            .or_else(|| payload.get("sign"))
+            .map(value_to_string)
+            .unwrap_or_default()
+            .to_lowercase();
+        if signature.is_empty() {
+            return result;
+        }
+
+        let params = payload
+            .iter()
+            .map(|(key, value)| (key.clone(), value_to_string(value)))
+            .collect::<BTreeMap<String, String>>();
+        let status_ok = payload
+            .get("status")
+            .and_then(value_to_f64)
+            .map(|value| (value - 2.0).abs() < f64::EPSILON)
+            .unwrap_or(true);
+        result.ok = self.sign(&params).to_lowercase() == signature && status_ok;
+        result
+    }
+
+    /// epusdt 仅以 `ok` 作为回调成功应答
+    fn callback_ack(&self) -> &'static str {
+        "ok"
+    }
+
+    fn query_order<'a>(&'a self, _trade_no: &'a str) -> ProviderFuture<'a, PaymentQueryResult> {
+        Box::pin(async { Err("USDT 通道不支持订单查询".to_string()) })
+    }
+
+    fn refund<'a>(
+        &'a self,
+        _trade_no: &'a str,
+        _amount: &'a Money,
+    ) -> ProviderFuture<'a, PaymentRefundResult> {
+        Box::pin(async { Err("USDT 通道不支持自动退款".to_string()) })
+    }
+}
@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDateTime;
+use sqlx::Row;
+
+use crate::payment::{order_method_provider, query_payment, PaymentSettings};
+use crate::routes::payment_callback::settle_queried_trade;
+use crate::state::AppState;
+
+const DEFAULT_RECONCILE_AFTER_MINUTES: i64 = 10;
+const DEFAULT_EXPIRE_MINUTES: i64 = 120;
+/// 单次对账最多处理的订单数，避免集中请求支付网关
+const RECONCILE_BATCH_SIZE: i64 = 100;
+
+/// 订单过期状态（0 待支付，1 已支付，2 已取消，3 支付失败，4 已过期）
+pub const ORDER_STATUS_EXPIRED: i64 = 4;
+
+struct PendingOrder {
+    table: &'static str,
+    trade_no: String,
+    method: String,
+    created_at: Option<NaiveDateTime>,
+}
+
+pub struct PaymentReconcileResult {
+    pub checked: usize,
+    pub settled: usize,
+    pub expired: usize,
+    pub failed: usize,
+}
+
+/// 查询超过 N 分钟仍待支付的在线订单，已支付的补入账，超时未支付的标记为已过期
+pub async fn run_payment_reconcile(state: &AppState) -> Result<PaymentReconcileResult, String> {
+    let (reconcile_after, expire_after) = load_config(state).await?;
+    let settings = PaymentSettings::load(state).await;
+    let mut result = PaymentReconcileResult {
+        checked: 0,
+        settled: 0,
+        expired: 0,
+        failed: 0,
+    };
+
+    let rows = sqlx::query(
+        r#"
+    SELECT 'recharge_records' AS source, trade_no, payment_method AS method, created_at
+    FROM recharge_records
+    WHERE status = 0
+      AND payment_method <> 'gift_card'
+      AND created_at <= DATE_SUB(CURRENT_TIMESTAMP, INTERVAL ? MINUTE)
+    UNION ALL
+    SELECT 'package_purchase_records' AS source, trade_no, purchase_type AS method, created_at
+    FROM package_purchase_records
+    WHERE status = 0
+      AND purchase_type <> 'balance'
+      AND created_at <= DATE_SUB(CURRENT_TIMESTAMP, INTERVAL ? MINUTE)
+    ORDER BY created_at ASC
+    LIMIT ?
+    "#,
+    )
+    .bind(reconcile_after)
+    .bind(reconcile_after)
+    .bind(RECONCILE_BATCH_SIZE)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|err| err.to_string())?;
+
+    let orders = rows
+        .into_iter()
+        .map(|row| PendingOrder {
+            table: match row
+                .try_get::<Option<String>, _>("source")
+                .ok()
+                .flatten()
+                .as_deref()
+            {
+                Some("recharge_records") => "recharge_records",
+                _ => "package_purchase_records",
+            },
+            trade_no: row
+                .try_get::<Option<String>, _>("trade_no")
+                .ok()
+                .flatten()
+                .unwrap_or_default(),
+            method: row
+                .try_get::<Option<String>, _>("method")
+                .ok()
+                .flatten()
+                .unwrap_or_default(),
+            created_at: row
+                .try_get::<Option<NaiveDateTime>, _>("created_at")
+                .ok()
+                .flatten(),
+        })
+        .filter(|order| !order.trade_no.is_empty())
+        .collect::<Vec<PendingOrder>>();
+
+    let now = sqlx::query("SELECT CURRENT_TIMESTAMP AS now")
+        .fetch_one(&state.db)
+        .await
+        .map_err(|err| err.to_string())?
+        .try_get::<NaiveDateTime, _>("now")
+        .map_err(|err| err.to_string())?;
+
+    for order in orders {
+        result.checked += 1;
+        let provider = order_method_provider(&settings, &order.method);
+        let reason = match provider {
+            Some(provider) => match query_payment(&settings, provider, &order.trade_no).await {
+                Ok(query) if query.paid => {
+                    if settle_queried_trade(state, provider, &order.trade_no, &query).await {
+                        result.settled += 1;
+                    } else {
+                        result.failed += 1;
+                    }
+                    continue;
+                }
+                Ok(_) => "超时未支付".to_string(),
+                Err(message) => format!("超时未支付（通道查询：{message}）"),
+            },
+            None => "超时未支付（支付方式已停用）".to_string(),
+        };
+
+        let stale = order
+            .created_at
+            .is_some_and(|created_at| (now - created_at).num_minutes() >= expire_after);
+        if !stale {
+            continue;
+        }
+        match expire_order(state, order.table, &order.trade_no, &reason).await {
+            Ok(true) => result.expired += 1,
+            Ok(false) => {}
+            Err(message) => {
+                tracing::warn!(
+                    "[payment] expire order {} failed: {message}",
+                    order.trade_no
+                );
+                result.failed += 1;
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+async fn expire_order(
+    state: &AppState,
+    table: &'static str,
+    trade_no: &str,
+    reason: &str,
+) -> Result<bool, String> {
+    let sql = format!(
+        "UPDATE {table} SET status = ?, close_reason = ?, closed_at = CURRENT_TIMESTAMP WHERE trade_no = ? AND status = 0"
+    );
+    let outcome = sqlx::query(&sql)
+        .bind(ORDER_STATUS_EXPIRED)
+        .bind(reason)
+        .bind(trade_no)
+        .execute(&state.db)
+        .await
+        .map_err(|err| err.to_string())?;
+    Ok(outcome.rows_affected() > 0)
+}
+
+async fn load_config(state: &AppState) -> Result<(i64, i64), String> {
+    let rows = sqlx::query(
+        r#"
+    SELECT `key`, `value` FROM system_configs
+    WHERE `key` IN ('payment_reconcile_after_minutes', 'payment_order_expire_minutes')
+    "#,
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|err| err.to_string())?;
+
+    let mut map: HashMap<String, i64> = HashMap::new();
+    for row in rows {
+        let key = row
+            .try_get::<Option<String>, _>("key")
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+        let value = row
+            .try_get::<Option<String>, _>("value")
+            .ok()
+            .flatten()
+            .and_then(|value| value.trim().parse::<i64>().ok())
+            .filter(|value| *value > 0);
+        if let Some(value) = value {
+            map.insert(key, value);
+        }
+    }
+    let reconcile_after = map
+        .get("payment_reconcile_after_minutes")
+        .copied()
+        .unwrap_or(DEFAULT_RECONCILE_AFTER_MINUTES);
+    let expire_after = map
+        .get("payment_order_expire_minutes")
+        .copied()
+        .unwrap_or(DEFAULT_EXPIRE_MINUTES)
+        .max(reconcile_after);
+    Ok((reconcile_after, expire_after))
+}
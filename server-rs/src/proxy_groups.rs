@@ -0,0 +1,452 @@
+use std::collections::HashSet;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+
+use crate::state::AppState;
+
+/// 分组类型，对应 Clash/Surge 的策略组类型；sing-box 中非 select 类型统一生成 urltest
+pub const GROUP_TYPES: [&str; 4] = ["select", "url-test", "fallback", "load-balance"];
+/// 节点匹配方式：节点标签、节点名称正则、指定节点 ID
+pub const MATCH_TYPES: [&str; 3] = ["tag", "regex", "node_ids"];
+
+pub const DEFAULT_TEST_URL: &str = "http://www.gstatic.com/generate_204";
+pub const DEFAULT_TEST_INTERVAL: i64 = 300;
+
+/// 内置地区分组（与迁移写入的默认数据一致），读取分组配置失败时使用
+const DEFAULT_GROUPS: [(&str, &str, &str); 7] = [
+    (
+        "🇭🇰",
+        "香港节点",
+        r"(?i)香港|🇭🇰|hong\s*kong|(?:^|[^a-z0-9])hk(?:[^a-z0-9]|$)",
+    ),
+    (
+        "🇨🇳",
+        "台湾节点",
+        r"(?i)台湾|台北|🇹🇼|taiwan|taipei|(?:^|[^a-z0-9])tw(?:[^a-z0-9]|$)",
+    ),
+    (
+        "🇸🇬",
+        "狮城节点",
+        r"(?i)狮城|新加坡|🇸🇬|singapore|(?:^|[^a-z0-9])sg(?:[^a-z0-9]|$)",
+    ),
+    (
+        "🇯🇵",
+        "日本节点",
+        r"(?i)日本|东京|大阪|🇯🇵|japan|(?:^|[^a-z0-9])jp(?:[^a-z0-9]|$)",
+    ),
+    (
+        "🇺🇲",
+        "美国节点",
+        r"(?i)美国|洛杉矶|纽约|硅谷|🇺🇸|🇺🇲|united\s*states|(?:^|[^a-z0-9])usa?(?:[^a-z0-9]|$)",
+    ),
+    (
+        "🇰🇷",
+        "韩国节点",
+        r"(?i)韩国|首尔|🇰🇷|korea|(?:^|[^a-z0-9])kr(?:[^a-z0-9]|$)",
+    ),
+    (
+        "🎥",
+        "奈飞节点",
+        r"(?i)奈飞|netflix|(?:^|[^a-z0-9])nf(?:[^a-z0-9]|$)",
+    ),
+];
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum GroupMatch {
+    Tag(String),
+    Regex(String),
+    NodeIds(Vec<i64>),
+}
+
+/// 订阅中的地区/自定义分组；`name` 为带 emoji 的完整显示名
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProxyGroup {
+    pub name: String,
+    pub matcher: GroupMatch,
+    pub group_type: String,
+    pub test_url: String,
+    pub interval: i64,
+}
+
+impl ProxyGroup {
+    pub fn is_select(&self) -> bool {
+        self.group_type == "select"
+    }
+}
+
+/// 参与分组匹配的节点；`proxy_name` 为该节点在生成配置中的名称
+pub struct GroupMember<'a> {
+    pub node_id: i64,
+    pub node_name: String,
+    pub tags: &'a [String],
+    pub proxy_name: String,
+}
+
+pub fn display_name(emoji: &str, name: &str) -> String {
+    let emoji = emoji.trim();
+    if emoji.is_empty() {
+        name.trim().to_string()
+    } else {
+        format!("{emoji} {}", name.trim())
+    }
+}
+
+pub fn default_proxy_groups() -> Vec<ProxyGroup> {
+    DEFAULT_GROUPS
+        .iter()
+        .map(|(emoji, name, pattern)| ProxyGroup {
+            name: display_name(emoji, name),
+            matcher: GroupMatch::Regex((*pattern).to_string()),
+            group_type: "select".to_string(),
+            test_url: DEFAULT_TEST_URL.to_string(),
+            interval: DEFAULT_TEST_INTERVAL,
+        })
+        .collect()
+}
+
+/// 校验后台提交的分组字段并转换为分组配置
+pub fn build_group(
+    emoji: &str,
+    name: &str,
+    match_type: &str,
+    match_value: &str,
+    group_type: &str,
+    test_url: Option<&str>,
+    interval: Option<i64>,
+) -> Result<ProxyGroup, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("分组名称不能为空".to_string());
+    }
+    if name.contains(',') || name.contains('=') {
+        return Err("分组名称不能包含逗号或等号".to_string());
+    }
+    if !GROUP_TYPES.contains(&group_type) {
+        return Err("不支持的分组类型".to_string());
+    }
+    let match_value = match_value.trim();
+    let matcher = match match_type {
+        "tag" => {
+            if match_value.is_empty() {
+                return Err("节点标签不能为空".to_string());
+            }
+            GroupMatch::Tag(match_value.to_string())
+        }
+        "regex" => {
+            if match_value.is_empty() {
+                return Err("正则表达式不能为空".to_string());
+            }
+            Regex::new(match_value).map_err(|err| format!("正则表达式无效：{err}"))?;
+            GroupMatch::Regex(match_value.to_string())
+        }
+        "node_ids" => {
+            let ids = parse_node_ids(match_value)?;
+            if ids.is_empty() {
+                return Err("节点 ID 不能为空".to_string());
+            }
+            GroupMatch::NodeIds(ids)
+        }
+        _ => return Err("不支持的匹配方式".to_string()),
+    };
+    let test_url = test_url
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .unwrap_or(DEFAULT_TEST_URL);
+    if !test_url.starts_with("http://") && !test_url.starts_with("https://") {
+        return Err("测速地址必须以 http:// 或 https:// 开头".to_string());
+    }
+    let interval = interval.unwrap_or(DEFAULT_TEST_INTERVAL);
+    if !(10..=86_400).contains(&interval) {
+        return Err("测速间隔需在 10-86400 秒之间".to_string());
+    }
+
+    Ok(ProxyGroup {
+        name: display_name(emoji, name),
+        matcher,
+        group_type: group_type.to_string(),
+        test_url: test_url.to_string(),
+        interval,
+    })
+}
+
+/// 节点 ID 列表支持 JSON 数组或逗号分隔
+pub fn parse_node_ids(value: &str) -> Result<Vec<i64>, String> {
+    let value = value.trim();
+    let raw: Vec<String> = if value.starts_with('[') {
+        serde_json::from_str::<Vec<serde_json::Value>>(value)
+            .map_err(|_| "节点 ID 列表格式无效".to_string())?
+            .into_iter()
+            .map(|item| match item {
+                serde_json::Value::String(text) => text,
+                other => other.to_string(),
+            })
+            .collect()
+    } else {
+        value.split(',').map(|item| item.to_string()).collect()
+    };
+    let mut ids: Vec<i64> = Vec::new();
+    for item in raw {
+        let item = item.trim();
+        if item.is_empty() {
+            continue;
+        }
+        let id = item
+            .parse::<i64>()
+            .ok()
+            .filter(|id| *id > 0)
+            .ok_or_else(|| format!("节点 ID 无效：{item}"))?;
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+    Ok(ids)
+}
+
+const MAX_NODE_TAGS: usize = 20;
+const MAX_NODE_TAG_LENGTH: usize = 32;
+
+/// 读取节点 `tags` 列（JSON 字符串数组），格式异常时视为无标签
+pub fn parse_node_tags(raw: Option<&str>) -> Vec<String> {
+    raw.and_then(|value| serde_json::from_str::<Vec<String>>(value).ok())
+        .unwrap_or_default()
+}
+
+/// 规范化后台提交的节点标签：支持数组或逗号分隔字符串，去除空白与重复项
+pub fn normalize_node_tags(value: &serde_json::Value) -> Result<Vec<String>, String> {
+    let raw: Vec<String> = match value {
+        serde_json::Value::Null => Vec::new(),
+        serde_json::Value::String(text) => text.split(',').map(str::to_string).collect(),
+        serde_json::Value::Array(items) => items
+            .iter()
+            .map(|item| match item {
+                serde_json::Value::String(text) => Ok(text.clone()),
+                _ => Err("节点标签必须是字符串".to_string()),
+            })
+            .collect::<Result<Vec<String>, String>>()?,
+        _ => return Err("节点标签格式无效".to_string()),
+    };
+    let mut tags: Vec<String> = Vec::new();
+    for tag in raw {
+        let tag = tag.trim();
+        if tag.is_empty() || tags.iter().any(|item| item.eq_ignore_ascii_case(tag)) {
+            continue;
+        }
+        if tag.chars().count() > MAX_NODE_TAG_LENGTH {
+            return Err(format!("节点标签不能超过 {MAX_NODE_TAG_LENGTH} 个字符"));
+        }
+        tags.push(tag.to_string());
+    }
+    if tags.len() > MAX_NODE_TAGS {
+        return Err(format!("节点标签最多 {MAX_NODE_TAGS} 个"));
+    }
+    Ok(tags)
+}
+
+/// 读取启用的分组（按排序值）；配置无效的分组跳过
+pub async fn fetch_proxy_groups(state: &AppState) -> Result<Vec<ProxyGroup>, String> {
+    let rows = sqlx::query(
+        r#"
+    SELECT id, name, emoji, match_type, match_value, group_type, test_url, test_interval
+    FROM proxy_groups
+    WHERE enabled = 1
+    ORDER BY sort_order ASC, id ASC
+    "#,
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|err| err.to_string())?;
+
+    let mut groups = Vec::new();
+    for row in rows {
+        let read = |key: &str| {
+            row.try_get::<Option<String>, _>(key)
+                .ok()
+                .flatten()
+                .unwrap_or_default()
+        };
+        let id = row.try_get::<i64, _>("id").unwrap_or(0);
+        match build_group(
+            &read("emoji"),
+            &read("name"),
+            &read("match_type"),
+            &read("match_value"),
+            &read("group_type"),
+            Some(&read("test_url")),
+            row.try_get::<Option<i64>, _>("test_interval")
+                .ok()
+                .flatten(),
+        ) {
+            Ok(group) => groups.push(group),
+            Err(message) => tracing::warn!("[subscription] proxy group #{id} ignored: {message}"),
+        }
+    }
+    Ok(groups)
+}
+
+/// 一次渲染中各分组的匹配结果
+pub struct GroupPlan {
+    known: HashSet<String>,
+    available: Vec<(ProxyGroup, Vec<String>)>,
+}
+
+impl GroupPlan {
+    pub fn new(groups: &[ProxyGroup], members: &[GroupMember<'_>]) -> Self {
+        // 内置分组名也视为分组引用，改名或停用后模板中的旧名称会被移除
+        let mut known: HashSet<String> = default_proxy_groups()
+            .into_iter()
+            .map(|group| group.name)
+            .collect();
+        let mut available = Vec::new();
+        let mut seen: HashSet<String> = HashSet::new();
+        for group in groups {
+            known.insert(group.name.clone());
+            if !seen.insert(group.name.clone()) {
+                continue;
+            }
+            let regex = match &group.matcher {
+                GroupMatch::Regex(pattern) => match Regex::new(pattern) {
+                    Ok(value) => Some(value),
+                    Err(_) => continue,
+                },
+                _ => None,
+            };
+            let mut matched: Vec<String> = Vec::new();
+            for member in members {
+                let hit = match &group.matcher {
+                    GroupMatch::Tag(tag) => member
+                        .tags
+                        .iter()
+                        .any(|item| item.eq_ignore_ascii_case(tag)),
+                    GroupMatch::Regex(_) => regex
+                        .as_ref()
+                        .map(|value| value.is_match(&member.node_name))
+                        .unwrap_or(false),
+                    GroupMatch::NodeIds(ids) => ids.contains(&member.node_id),
+                };
+                if hit && !matched.contains(&member.proxy_name) {
+                    matched.push(member.proxy_name.clone());
+                }
+            }
+            if !matched.is_empty() {
+                available.push((group.clone(), matched));
+            }
+        }
+        Self { known, available }
+    }
+
+    /// 匹配到节点的分组（按配置顺序）
+    pub fn available(&self) -> &[(ProxyGroup, Vec<String>)] {
+        &self.available
+    }
+
+    pub fn available_names(&self) -> Vec<String> {
+        self.available
+            .iter()
+            .map(|(group, _)| group.name.clone())
+            .collect()
+    }
+
+    pub fn is_group_name(&self, name: &str) -> bool {
+        self.known.contains(name)
+    }
+
+    /// 展开策略组成员：去掉没有节点的分组，未在列表中出现的分组追加到最后一个分组引用之后
+    pub fn expand<S: AsRef<str>>(&self, list: &[S]) -> Vec<String> {
+        let mut result: Vec<String> = Vec::new();
+        let mut insert_at: Option<usize> = None;
+        let available: HashSet<&str> = self
+            .available
+            .iter()
+            .map(|(group, _)| group.name.as_str())
+            .collect();
+        for item in list {
+            let item = item.as_ref();
+            if self.known.contains(item) {
+                if available.contains(item) && !result.iter().any(|value| value == item) {
+                    result.push(item.to_string());
+                }
+                insert_at = Some(result.len());
+                continue;
+            }
+            result.push(item.to_string());
+        }
+        if let Some(position) = insert_at {
+            let missing = self
+                .available
+                .iter()
+                .map(|(group, _)| group.name.clone())
+                .filter(|name| !result.contains(name))
+                .collect::<Vec<String>>();
+            result.splice(position..position, missing);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn member<'a>(id: i64, name: &'a str, tags: &'a [String]) -> GroupMember<'a> {
+        GroupMember {
+            node_id: id,
+            node_name: name.to_string(),
+            tags,
+            proxy_name: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn default_groups_match_region_names() {
+        let no_tags: Vec<String> = Vec::new();
+        let members = [
+            member(1, "香港 01", &no_tags),
+            member(2, "HK-IPLC", &no_tags),
+            member(3, "HK01 Premium", &no_tags),
+            member(4, "Los Angeles US", &no_tags),
+            member(5, "🇯🇵 Tokyo", &no_tags),
+        ];
+        let plan = GroupPlan::new(&default_proxy_groups(), &members);
+        assert_eq!(
+            plan.available_names(),
+            vec!["🇭🇰 香港节点", "🇯🇵 日本节点", "🇺🇲 美国节点"]
+        );
+        assert_eq!(plan.available()[0].1, vec!["香港 01", "HK-IPLC"]);
+    }
+
+    #[test]
+    fn custom_groups_match_and_expand_into_lists() {
+        let tags = vec!["streaming".to_string()];
+        let no_tags: Vec<String> = Vec::new();
+        let members = [member(1, "香港 01", &tags), member(2, "Node B", &no_tags)];
+        let groups = vec![
+            build_group("🇭🇰", "香港节点", "regex", "香港", "select", None, None).unwrap(),
+            build_group(
+                "📺",
+                "流媒体",
+                "tag",
+                "Streaming",
+                "url-test",
+                None,
+                Some(600),
+            )
+            .unwrap(),
+            build_group("", "精选", "node_ids", "[2]", "fallback", None, None).unwrap(),
+        ];
+        let plan = GroupPlan::new(&groups, &members);
+        assert_eq!(plan.available().len(), 3);
+        assert_eq!(plan.available()[2].1, vec!["Node B"]);
+        assert_eq!(
+            plan.expand(&["🚀 节点选择", "🇸🇬 狮城节点", "🇭🇰 香港节点", "DIRECT"]),
+            vec!["🚀 节点选择", "🇭🇰 香港节点", "📺 流媒体", "精选", "DIRECT"]
+        );
+        assert_eq!(plan.expand(&["DIRECT", "REJECT"]), vec!["DIRECT", "REJECT"]);
+
+        assert!(build_group("", "坏", "regex", "(", "select", None, None).is_err());
+        assert!(build_group("", "坏", "tag", "x", "balance", None, None).is_err());
+        assert!(build_group("", "坏", "node_ids", "1,a", "select", None, None).is_err());
+    }
+}
@@ -0,0 +1,490 @@
+use chrono::{Duration, Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use sqlx::{MySqlConnection, Row};
+
+use crate::cache::{redis_release_lock, redis_try_lock};
+use crate::crypto::generate_uuid;
+use crate::money::Money;
+use crate::payment::{order_method_provider, refund_payment, PaymentSettings};
+use crate::referral::{insert_user_transaction, revoke_rebate};
+use crate::state::AppState;
+
+/// 订单已退款状态（0 待支付，1 已支付，2 已取消，3 支付失败，4 已过期，5 已退款）
+pub const ORDER_STATUS_REFUNDED: i64 = 5;
+const REFUND_LOCK_TTL_SECONDS: u64 = 120;
+
+/// 套餐发放前的用户状态及本次发放内容，保存在 `package_purchase_records.benefit_snapshot`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BenefitSnapshot {
+    pub class: i64,
+    pub class_expire_time: Option<String>,
+    pub transfer_enable: i64,
+    pub speed_limit: i64,
+    pub device_limit: i64,
+    pub granted_traffic: i64,
+    pub granted_days: i64,
+    /// 发放时等级变更，已用流量被清零
+    pub reset: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct UserBenefits {
+    class: i64,
+    class_expire_time: Option<NaiveDateTime>,
+    transfer_enable: i64,
+    speed_limit: i64,
+    device_limit: i64,
+}
+
+pub struct RefundOptions {
+    pub rollback_benefits: bool,
+    /// 在线支付部分原路退回；否则退回到用户余额
+    pub to_original: bool,
+    pub reason: Option<String>,
+}
+
+pub struct RefundResult {
+    pub user_id: i64,
+    pub refund_amount: Money,
+    pub balance_refunded: Money,
+    pub original_refunded: Money,
+    pub manual: bool,
+    pub rebate_revoked: Money,
+    pub benefits_rolled_back: bool,
+    pub message: Option<String>,
+}
+
+/// 退款已支付的套餐订单：退回余额/原路退款、扣回返利、释放优惠券，并按需回滚套餐权益
+pub async fn refund_purchase(
+    state: &AppState,
+    trade_no: &str,
+    options: &RefundOptions,
+) -> Result<Option<RefundResult>, String> {
+    // 原路退款在订单标记为已退款之前调用支付通道，用锁避免同一订单并发重复退款；
+    // Redis 不可用时无法加锁，原路退款直接拒绝，仅退回余额的退款由事务保证不重复
+    let owner = generate_uuid();
+    let lock_key = format!("purchase_refund_{trade_no}");
+    match redis_try_lock(state, &lock_key, &owner, REFUND_LOCK_TTL_SECONDS).await {
+        Some(true) => {}
+        Some(false) => return Err("订单正在退款处理中，请稍后再试".to_string()),
+        None if options.to_original => {
+            return Err("退款锁不可用，暂无法原路退款，请稍后再试".to_string())
+        }
+        None => {}
+    }
+    let result = process_refund(state, trade_no, options).await;
+    redis_release_lock(state, &lock_key, &owner).await;
+    result
+}
+
+async fn process_refund(
+    state: &AppState,
+    trade_no: &str,
+    options: &RefundOptions,
+) -> Result<Option<RefundResult>, String> {
+    let record = sqlx::query(
+        r#"
+    SELECT id, user_id, package_id, status, price, package_price, discount_amount,
+           purchase_type, benefit_snapshot
+    FROM package_purchase_records
+    WHERE trade_no = ?
+    "#,
+    )
+    .bind(trade_no)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|err| err.to_string())?;
+    let record = match record {
+        Some(value) => value,
+        None => return Ok(None),
+    };
+
+    let record_id = record.try_get::<i64, _>("id").unwrap_or(0);
+    let user_id = record.try_get::<i64, _>("user_id").unwrap_or(0);
+    let status = record
+        .try_get::<Option<i64>, _>("status")
+        .ok()
+        .flatten()
+        .unwrap_or(0);
+    if status != 1 {
+        return Err("仅已支付的订单可以退款".to_string());
+    }
+
+    let purchase_type = record
+        .try_get::<Option<String>, _>("purchase_type")
+        .ok()
+        .flatten()
+        .unwrap_or_default()
+        .to_lowercase();
+    let (balance_paid, online_paid) = split_paid_amount(
+        &purchase_type,
+        Money::from_row_or_zero(&record, "price"),
+        Money::from_row_or_zero(&record, "package_price"),
+        Money::from_row_or_zero(&record, "discount_amount"),
+    );
+
+    if options.rollback_benefits {
+        let later = sqlx::query(
+            "SELECT id FROM package_purchase_records WHERE user_id = ? AND status = 1 AND id > ? LIMIT 1",
+        )
+        .bind(user_id)
+        .bind(record_id)
+        .fetch_optional(&state.db)
+        .await
+        .map_err(|err| err.to_string())?;
+        if later.is_some() {
+            return Err("该用户之后还有已支付的套餐订单，无法自动回滚套餐权益".to_string());
+        }
+    }
+
+    let reason = options
+        .reason
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .unwrap_or("管理员退款")
+        .to_string();
+    let refund_amount = balance_paid.clone() + online_paid.clone();
+
+    // 旧订单没有快照时按套餐内容扣减，只读查询放在事务之外
+    let snapshot = if options.rollback_benefits {
+        let snapshot = record
+            .try_get::<Option<String>, _>("benefit_snapshot")
+            .ok()
+            .flatten()
+            .and_then(|value| serde_json::from_str::<BenefitSnapshot>(&value).ok());
+        match snapshot {
+            Some(value) => Some(value),
+            None => {
+                package_snapshot(state, record.try_get::<i64, _>("package_id").unwrap_or(0)).await?
+            }
+        }
+    } else {
+        None
+    };
+
+    let mut result = RefundResult {
+        user_id,
+        refund_amount,
+        balance_refunded: balance_paid.clone(),
+        original_refunded: Money::zero(),
+        manual: false,
+        rebate_revoked: Money::zero(),
+        benefits_rolled_back: false,
+        message: None,
+    };
+
+    // 原路退款调用支付通道，不放在数据库事务内；失败时订单保持已支付，可重试
+    if online_paid.is_positive() && options.to_original {
+        let settings = PaymentSettings::load(state).await;
+        let outcome = match order_method_provider(&settings, &purchase_type) {
+            Some(provider) => refund_payment(&settings, provider, trade_no, &online_paid).await,
+            None => Err("订单支付方式已停用，无法原路退款".to_string()),
+        };
+        match outcome {
+            Ok(refund) if refund.success => {
+                result.original_refunded = online_paid;
+                result.manual = refund.manual;
+                result.message = refund.message;
+            }
+            Ok(refund) => {
+                return Err(refund
+                    .message
+                    .unwrap_or_else(|| "支付通道退款失败".to_string()))
+            }
+            Err(message) => return Err(message),
+        }
+    } else {
+        result.balance_refunded = balance_paid + online_paid;
+    }
+
+    let settled = settle_refund(
+        state,
+        &mut result,
+        record_id,
+        trade_no,
+        &reason,
+        snapshot.as_ref(),
+    )
+    .await;
+    if let Err(message) = settled {
+        if result.original_refunded.is_positive() {
+            return Err(format!(
+                "支付通道已原路退款，但退款入账失败：{message}。请勿重复退款，需人工核对"
+            ));
+        }
+        return Err(message);
+    }
+
+    Ok(Some(result))
+}
+
+/// 在同一事务中标记订单已退款、退回余额、释放优惠券、扣回返利、回滚权益并记录流水，全部成功才提交
+async fn settle_refund(
+    state: &AppState,
+    result: &mut RefundResult,
+    record_id: i64,
+    trade_no: &str,
+    reason: &str,
+    snapshot: Option<&BenefitSnapshot>,
+) -> Result<(), String> {
+    let user_id = result.user_id;
+    let mut tx = state.db.begin().await.map_err(|err| err.to_string())?;
+    let claimed = sqlx::query(
+        r#"
+    UPDATE package_purchase_records
+    SET status = ?, refund_amount = ?, close_reason = ?, closed_at = CURRENT_TIMESTAMP
+    WHERE id = ? AND status = 1
+    "#,
+    )
+    .bind(ORDER_STATUS_REFUNDED)
+    .bind(&result.refund_amount)
+    .bind(reason)
+    .bind(record_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|err| err.to_string())?;
+    if claimed.rows_affected() == 0 {
+        return Err("订单状态已变化，请刷新后重试".to_string());
+    }
+
+    if result.balance_refunded.is_positive() {
+        sqlx::query(
+            "UPDATE users SET money = money + ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+        )
+        .bind(&result.balance_refunded)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|err| err.to_string())?;
+    }
+
+    sqlx::query("DELETE FROM coupon_usages WHERE order_trade_no = ?")
+        .bind(trade_no)
+        .execute(&mut *tx)
+        .await
+        .map_err(|err| err.to_string())?;
+
+    result.rebate_revoked = revoke_rebate(&mut tx, "purchase", record_id, Some(trade_no)).await?;
+
+    if let Some(snapshot) = snapshot {
+        result.benefits_rolled_back = rollback_user_benefits(&mut tx, user_id, snapshot).await?;
+    }
+
+    insert_user_transaction(
+        &mut *tx,
+        user_id,
+        &result.refund_amount,
+        "purchase_refund",
+        "purchase_refund",
+        Some(record_id),
+        Some(trade_no),
+        Some(reason),
+    )
+    .await?;
+
+    tx.commit().await.map_err(|err| err.to_string())
+}
+
+/// 按购买方式拆分余额支付与在线支付的金额，返回 `(余额部分, 在线部分)`
+pub fn split_paid_amount(
+    purchase_type: &str,
+    price: Money,
+    package_price: Money,
+    discount: Money,
+) -> (Money, Money) {
+    if purchase_type == "balance" {
+        return (price, Money::zero());
+    }
+    if purchase_type == "gift_card" {
+        return (Money::zero(), Money::zero());
+    }
+    if purchase_type.starts_with("balance_") {
+        let base = if package_price.is_positive() {
+            package_price
+        } else {
+            price.clone()
+        };
+        let balance = (base - discount - price.clone()).non_negative();
+        return (balance, price);
+    }
+    (Money::zero(), price)
+}
+
+/// 旧订单没有快照时按套餐内容扣减（不恢复等级）
+async fn package_snapshot(
+    state: &AppState,
+    package_id: i64,
+) -> Result<Option<BenefitSnapshot>, String> {
+    let row = sqlx::query("SELECT traffic_quota, validity_days FROM packages WHERE id = ?")
+        .bind(package_id)
+        .fetch_optional(&state.db)
+        .await
+        .map_err(|err| err.to_string())?;
+    Ok(row.map(|row| {
+        let traffic_gb = row
+            .try_get::<Option<i64>, _>("traffic_quota")
+            .ok()
+            .flatten()
+            .unwrap_or(0);
+        BenefitSnapshot {
+            class: 0,
+            class_expire_time: None,
+            transfer_enable: 0,
+            speed_limit: 0,
+            device_limit: 0,
+            granted_traffic: traffic_gb * 1024 * 1024 * 1024,
+            granted_days: row
+                .try_get::<Option<i64>, _>("validity_days")
+                .ok()
+                .flatten()
+                .unwrap_or(30)
+                .max(1),
+            reset: false,
+        }
+    }))
+}
+
+async fn rollback_user_benefits(
+    conn: &mut MySqlConnection,
+    user_id: i64,
+    snapshot: &BenefitSnapshot,
+) -> Result<bool, String> {
+    let row = sqlx::query(
+        r#"
+    SELECT class, class_expire_time, transfer_enable, speed_limit, device_limit
+    FROM users
+    WHERE id = ?
+    "#,
+    )
+    .bind(user_id)
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(|err| err.to_string())?;
+    let row = match row {
+        Some(value) => value,
+        None => return Ok(false),
+    };
+    let read = |column: &str| {
+        row.try_get::<Option<i64>, _>(column)
+            .ok()
+            .flatten()
+            .unwrap_or(0)
+    };
+    let current = UserBenefits {
+        class: read("class"),
+        class_expire_time: row
+            .try_get::<Option<NaiveDateTime>, _>("class_expire_time")
+            .ok()
+            .flatten(),
+        transfer_enable: read("transfer_enable"),
+        speed_limit: read("speed_limit"),
+        device_limit: read("device_limit"),
+    };
+
+    let restored = rollback_benefits(&current, snapshot);
+    if restored == current {
+        return Ok(false);
+    }
+    sqlx::query(
+        r#"
+    UPDATE users
+    SET class = ?, class_expire_time = ?, transfer_enable = ?,
+        speed_limit = ?, device_limit = ?, updated_at = CURRENT_TIMESTAMP
+    WHERE id = ?
+    "#,
+    )
+    .bind(restored.class)
+    .bind(restored.class_expire_time)
+    .bind(restored.transfer_enable)
+    .bind(restored.speed_limit)
+    .bind(restored.device_limit)
+    .bind(user_id)
+    .execute(&mut *conn)
+    .await
+    .map_err(|err| err.to_string())?;
+    Ok(true)
+}
+
+/// 计算撤销一次套餐发放后的用户状态：等级变更的订单恢复到发放前，同等级续费的订单扣回时长与流量
+fn rollback_benefits(current: &UserBenefits, snapshot: &BenefitSnapshot) -> UserBenefits {
+    if snapshot.reset {
+        return UserBenefits {
+            class: snapshot.class,
+            class_expire_time: snapshot
+                .class_expire_time
+                .as_deref()
+                .and_then(|value| NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S").ok()),
+            transfer_enable: snapshot.transfer_enable,
+            speed_limit: snapshot.speed_limit,
+            device_limit: snapshot.device_limit,
+        };
+    }
+
+    let now = Local::now().naive_local();
+    UserBenefits {
+        class: current.class,
+        class_expire_time: current
+            .class_expire_time
+            .map(|expire| (expire - Duration::days(snapshot.granted_days)).max(now)),
+        transfer_enable: (current.transfer_enable - snapshot.granted_traffic).max(0),
+        speed_limit: current.speed_limit,
+        device_limit: current.device_limit,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(reset: bool) -> BenefitSnapshot {
+        BenefitSnapshot {
+            class: 1,
+            class_expire_time: Some("2030-01-01 00:00:00".to_string()),
+            transfer_enable: 10,
+            speed_limit: 100,
+            device_limit: 2,
+            granted_traffic: 50,
+            granted_days: 30,
+            reset,
+        }
+    }
+
+    #[test]
+    fn rollback_restores_or_subtracts_benefits() {
+        let expire = NaiveDateTime::parse_from_str("2031-03-01 00:00:00", "%Y-%m-%d %H:%M:%S")
+            .unwrap_or_default();
+        let current = UserBenefits {
+            class: 2,
+            class_expire_time: Some(expire),
+            transfer_enable: 80,
+            speed_limit: 0,
+            device_limit: 5,
+        };
+
+        let restored = rollback_benefits(&current, &snapshot(true));
+        assert_eq!(restored.class, 1);
+        assert_eq!(restored.transfer_enable, 10);
+        assert_eq!(restored.device_limit, 2);
+
+        let renewed = rollback_benefits(&current, &snapshot(false));
+        assert_eq!(renewed.class, 2);
+        assert_eq!(renewed.transfer_enable, 30);
+        assert_eq!(renewed.class_expire_time, Some(expire - Duration::days(30)));
+    }
+
+    #[test]
+    fn split_paid_amount_by_purchase_type() {
+        let m = |text: &str| Money::parse(text).unwrap_or_default();
+        let split = |kind: &str, price: &str, package_price: &str, discount: &str| {
+            split_paid_amount(kind, m(price), m(package_price), m(discount))
+        };
+        assert_eq!(split("balance", "20", "20", "0"), (m("20"), m("0")));
+        assert_eq!(split("alipay", "18", "20", "2"), (m("0"), m("18")));
+        assert_eq!(
+            split("balance_wxpay", "5.1", "20", "2.3"),
+            (m("12.6"), m("5.1"))
+        );
+        assert_eq!(split("gift_card", "0", "20", "0"), (m("0"), m("0")));
+    }
+}
@@ -1,6 +1,9 @@
+use bigdecimal::{BigDecimal, Zero};
 use rand::Rng;
-use sqlx::Row;
+use sqlx::{Executor, MySql, MySqlConnection, Row};
+use std::str::FromStr;
 
+use crate::money::{parse_rate, Money};
 use crate::state::AppState;
 
 const INVITE_CHARS: &str = "abcdefghjkmnpqrstuvwxyz23456789";
@@ -175,18 +178,21 @@ pub struct InviterInfo {
     pub invite_used: i64,
 }
 
-pub async fn insert_user_transaction(
-    state: &AppState,
+/// 写入用户资金流水；`executor` 可传连接池或事务，便于与余额变动一起提交
+pub async fn insert_user_transaction<'c, E>(
+    executor: E,
     user_id: i64,
-    amount: f64,
+    amount: &Money,
     event_type: &str,
     source_type: &str,
     source_id: Option<i64>,
     trade_no: Option<&str>,
     remark: Option<&str>,
-) -> Result<(), String> {
-    let fixed_amount = fix_money_precision(amount);
-    if user_id == 0 || fixed_amount == 0.0 {
+) -> Result<(), String>
+where
+    E: Executor<'c, Database = MySql>,
+{
+    if user_id == 0 || amount.is_zero() {
         return Ok(());
     }
     sqlx::query(
@@ -201,63 +207,44 @@ pub async fn insert_user_transaction(
   .bind(source_id)
   .bind(trade_no)
   .bind(event_type)
-  .bind(fixed_amount)
+  .bind(amount)
   .bind(remark)
-  .execute(&state.db)
+  .execute(executor)
   .await
   .map_err(|err| err.to_string())?;
     Ok(())
 }
 
+/// 按返利层级发放佣金：从付款用户的邀请人开始沿邀请关系逐级向上，每级按对应比例计算。
+/// 不满足条件（账号停用、无有效等级）的上级跳过该层，不影响更上层
 pub async fn award_rebate(
     state: &AppState,
     invitee_id: i64,
-    amount: f64,
+    amount: &Money,
     source_type: &str,
     source_id: Option<i64>,
     trade_no: Option<&str>,
     event_type: Option<&str>,
 ) -> Result<bool, String> {
-    if invitee_id == 0 || amount <= 0.0 {
+    if invitee_id == 0 || !amount.is_positive() {
         return Ok(false);
     }
 
     let settings = fetch_rebate_settings(state).await?;
-    if settings.rate <= 0.0 {
+    let package_levels = match (source_type, source_id) {
+        ("purchase", Some(id)) => fetch_package_rebate_levels(state, id).await?,
+        _ => None,
+    };
+    let levels = settings.levels_for(source_type, package_levels);
+    if levels.iter().all(|rate| *rate <= BigDecimal::zero()) {
         return Ok(false);
     }
 
-    let inviter_id = sqlx::query("SELECT invited_by FROM users WHERE id = ?")
-        .bind(invitee_id)
-        .fetch_optional(&state.db)
-        .await
-        .map_err(|err| err.to_string())?
-        .and_then(|row| row.try_get::<Option<i64>, _>("invited_by").ok().flatten())
-        .unwrap_or(0);
+    let inviter_id = find_inviter_id(state, invitee_id).await?;
     if inviter_id <= 0 {
         return Ok(false);
     }
 
-    let inviter_ok = sqlx::query(
-        r#"
-    SELECT id
-    FROM users
-    WHERE id = ?
-      AND status = 1
-      AND class > 0
-      AND (class_expire_time IS NULL OR class_expire_time > CURRENT_TIMESTAMP)
-    LIMIT 1
-    "#,
-    )
-    .bind(inviter_id)
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|err| err.to_string())?
-    .is_some();
-    if !inviter_ok {
-        return Ok(false);
-    }
-
     if let Some(source_id) = source_id {
         let exists = sqlx::query(
       "SELECT id FROM rebate_transactions WHERE source_type = ? AND source_id = ? AND amount > 0 LIMIT 1"
@@ -287,26 +274,114 @@ pub async fn award_rebate(
         return Ok(false);
     }
 
-    let rebate_amount = fix_money_precision(amount * settings.rate);
-    if rebate_amount <= 0.0 {
+    let mut awarded = false;
+    let mut visited = vec![invitee_id];
+    let mut current_inviter = inviter_id;
+    for (index, rate) in levels.iter().enumerate() {
+        if current_inviter <= 0 || visited.contains(&current_inviter) {
+            break;
+        }
+        visited.push(current_inviter);
+        let level = index as i64 + 1;
+        if *rate > BigDecimal::zero() && is_rebate_eligible(state, current_inviter).await? {
+            let mut rebate_amount = amount.mul_rate(rate);
+            if settings.invitee_cap.is_positive() {
+                let earned = fetch_earned_from_invitee(state, current_inviter, invitee_id).await?;
+                rebate_amount =
+                    rebate_amount.min((settings.invitee_cap.clone() - earned).non_negative());
+            }
+            if rebate_amount.is_positive() {
+                insert_rebate(
+                    state,
+                    RebateEntry {
+                        inviter_id: current_inviter,
+                        referral_id: relation.id,
+                        invitee_id,
+                        level,
+                        source_type,
+                        source_id,
+                        trade_no,
+                        event_type: event_type.unwrap_or(source_type),
+                        amount: &rebate_amount,
+                    },
+                )
+                .await?;
+                awarded = true;
+            }
+        }
+        if index + 1 < levels.len() {
+            current_inviter = find_inviter_id(state, current_inviter).await?;
+        }
+    }
+    if !awarded {
         return Ok(false);
     }
 
+    if relation.first_payment_id.is_none() {
+        sqlx::query(
+            r#"
+      UPDATE referral_relations
+      SET first_payment_type = ?,
+          first_payment_id = ?,
+          first_paid_at = CURRENT_TIMESTAMP,
+          status = 'active',
+          updated_at = CURRENT_TIMESTAMP
+      WHERE invitee_id = ?
+      "#,
+        )
+        .bind(source_type)
+        .bind(source_id)
+        .bind(invitee_id)
+        .execute(&state.db)
+        .await
+        .map_err(|err| err.to_string())?;
+    } else if relation.status != "active" {
+        sqlx::query(
+            r#"
+      UPDATE referral_relations
+      SET status = 'active',
+          updated_at = CURRENT_TIMESTAMP
+      WHERE invitee_id = ?
+      "#,
+        )
+        .bind(invitee_id)
+        .execute(&state.db)
+        .await
+        .map_err(|err| err.to_string())?;
+    }
+
+    Ok(true)
+}
+
+struct RebateEntry<'a> {
+    inviter_id: i64,
+    referral_id: i64,
+    invitee_id: i64,
+    level: i64,
+    source_type: &'a str,
+    source_id: Option<i64>,
+    trade_no: Option<&'a str>,
+    event_type: &'a str,
+    amount: &'a Money,
+}
+
+async fn insert_rebate(state: &AppState, entry: RebateEntry<'_>) -> Result<(), String> {
     sqlx::query(
     r#"
     INSERT INTO rebate_transactions (
-      inviter_id, referral_id, invitee_id, source_type, source_id, trade_no, event_type, amount, status, created_at
-    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, 'confirmed', CURRENT_TIMESTAMP)
+      inviter_id, referral_id, invitee_id, level, source_type, source_id, trade_no, event_type, amount, status, created_at
+    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, 'confirmed', CURRENT_TIMESTAMP)
     "#
   )
-  .bind(inviter_id)
-  .bind(relation.id)
-  .bind(invitee_id)
-  .bind(source_type)
-  .bind(source_id)
-  .bind(trade_no)
-  .bind(event_type.unwrap_or(source_type))
-  .bind(rebate_amount)
+  .bind(entry.inviter_id)
+  .bind(entry.referral_id)
+  .bind(entry.invitee_id)
+  .bind(entry.level)
+  .bind(entry.source_type)
+  .bind(entry.source_id)
+  .bind(entry.trade_no)
+  .bind(entry.event_type)
+  .bind(entry.amount)
   .execute(&state.db)
   .await
   .map_err(|err| err.to_string())?;
@@ -318,63 +393,244 @@ pub async fn award_rebate(
     WHERE id = ?
     "#
   )
-  .bind(rebate_amount)
-  .bind(rebate_amount)
-  .bind(inviter_id)
+  .bind(entry.amount)
+  .bind(entry.amount)
+  .bind(entry.inviter_id)
   .execute(&state.db)
   .await
   .map_err(|err| err.to_string())?;
+    Ok(())
+}
+
+/// 上级邀请人：优先读取邀请关系，缺失时回退到 `users.invited_by`
+async fn find_inviter_id(state: &AppState, user_id: i64) -> Result<i64, String> {
+    let row = sqlx::query(
+        r#"
+    SELECT COALESCE(
+      (SELECT inviter_id FROM referral_relations WHERE invitee_id = u.id LIMIT 1),
+      u.invited_by
+    ) AS inviter_id
+    FROM users u
+    WHERE u.id = ?
+    "#,
+    )
+    .bind(user_id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|err| err.to_string())?;
+    Ok(row
+        .and_then(|row| row.try_get::<Option<i64>, _>("inviter_id").ok().flatten())
+        .unwrap_or(0))
+}
+
+async fn is_rebate_eligible(state: &AppState, inviter_id: i64) -> Result<bool, String> {
+    let row = sqlx::query(
+        r#"
+    SELECT id
+    FROM users
+    WHERE id = ?
+      AND status = 1
+      AND class > 0
+      AND (class_expire_time IS NULL OR class_expire_time > CURRENT_TIMESTAMP)
+    LIMIT 1
+    "#,
+    )
+    .bind(inviter_id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|err| err.to_string())?;
+    Ok(row.is_some())
+}
+
+/// 邀请人已从该被邀请人处获得的返利净额（已扣除退款扣回）
+async fn fetch_earned_from_invitee(
+    state: &AppState,
+    inviter_id: i64,
+    invitee_id: i64,
+) -> Result<Money, String> {
+    let row = sqlx::query(
+        r#"
+    SELECT COALESCE(SUM(amount), 0) AS total
+    FROM rebate_transactions
+    WHERE inviter_id = ? AND invitee_id = ?
+    "#,
+    )
+    .bind(inviter_id)
+    .bind(invitee_id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|err| err.to_string())?;
+    Ok(row
+        .map(|row| Money::from_row_or_zero(&row, "total"))
+        .unwrap_or_default())
+}
+
+async fn fetch_package_rebate_levels(
+    state: &AppState,
+    purchase_id: i64,
+) -> Result<Option<Vec<BigDecimal>>, String> {
+    let row = sqlx::query(
+        r#"
+    SELECT p.rebate_levels
+    FROM package_purchase_records pr
+    JOIN packages p ON p.id = pr.package_id
+    WHERE pr.id = ?
+    "#,
+    )
+    .bind(purchase_id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|err| err.to_string())?;
+    Ok(row
+        .and_then(|row| {
+            row.try_get::<Option<String>, _>("rebate_levels")
+                .ok()
+                .flatten()
+        })
+        .and_then(|value| parse_rebate_levels(&value).ok().flatten()))
+}
+
+/// 订单退款时扣回该订单产生的返利，返回扣回总额（已扣回过则返回 0）；在调用方的事务内执行
+pub async fn revoke_rebate(
+    conn: &mut MySqlConnection,
+    source_type: &str,
+    source_id: i64,
+    trade_no: Option<&str>,
+) -> Result<Money, String> {
+    let revoked = sqlx::query(
+        "SELECT id FROM rebate_transactions WHERE source_type = ? AND source_id = ? AND amount < 0 LIMIT 1",
+    )
+    .bind(source_type)
+    .bind(source_id)
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(|err| err.to_string())?;
+    if revoked.is_some() {
+        return Ok(Money::zero());
+    }
+
+    let rows = sqlx::query(
+        r#"
+    SELECT inviter_id, referral_id, invitee_id, level, amount
+    FROM rebate_transactions
+    WHERE source_type = ? AND source_id = ? AND amount > 0
+    "#,
+    )
+    .bind(source_type)
+    .bind(source_id)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|err| err.to_string())?;
+
+    let mut total = Money::zero();
+    for row in rows {
+        let inviter_id = row.try_get::<i64, _>("inviter_id").unwrap_or(0);
+        let amount = Money::from_row_or_zero(&row, "amount");
+        if inviter_id <= 0 || !amount.is_positive() {
+            continue;
+        }
 
-    if relation.first_payment_id.is_none() {
         sqlx::query(
             r#"
-      UPDATE referral_relations
-      SET first_payment_type = ?,
-          first_payment_id = ?,
-          first_paid_at = CURRENT_TIMESTAMP,
-          status = 'active',
-          updated_at = CURRENT_TIMESTAMP
-      WHERE invitee_id = ?
+      INSERT INTO rebate_transactions (
+        inviter_id, referral_id, invitee_id, level, source_type, source_id, trade_no, event_type, amount, status, remark, created_at
+      ) VALUES (?, ?, ?, ?, ?, ?, ?, 'rebate_revoke', ?, 'confirmed', '订单退款，扣回返利', CURRENT_TIMESTAMP)
       "#,
         )
+        .bind(inviter_id)
+        .bind(row.try_get::<Option<i64>, _>("referral_id").ok().flatten())
+        .bind(row.try_get::<Option<i64>, _>("invitee_id").ok().flatten())
+        .bind(row.try_get::<Option<i64>, _>("level").ok().flatten().unwrap_or(1))
         .bind(source_type)
         .bind(source_id)
-        .bind(invitee_id)
-        .execute(&state.db)
+        .bind(trade_no)
+        .bind(-amount.clone())
+        .execute(&mut *conn)
         .await
         .map_err(|err| err.to_string())?;
-    } else if relation.status != "active" {
+
+        // 返利可能已被划转或提现，允许扣为负数，后续返利先抵扣欠额
         sqlx::query(
             r#"
-      UPDATE referral_relations
-      SET status = 'active',
-          updated_at = CURRENT_TIMESTAMP
-      WHERE invitee_id = ?
+      UPDATE users
+      SET rebate_available = rebate_available - ?, rebate_total = rebate_total - ?, updated_at = CURRENT_TIMESTAMP
+      WHERE id = ?
       "#,
         )
-        .bind(invitee_id)
-        .execute(&state.db)
+        .bind(&amount)
+        .bind(&amount)
+        .bind(inviter_id)
+        .execute(&mut *conn)
         .await
         .map_err(|err| err.to_string())?;
+        total = total + amount;
     }
 
-    Ok(true)
+    Ok(total)
 }
 
+/// 返利最多支持的层级数
+const MAX_REBATE_LEVELS: usize = 10;
+
 struct RebateSettings {
-    rate: f64,
+    levels: Vec<BigDecimal>,
+    source_levels: Vec<(String, Vec<BigDecimal>)>,
     mode: String,
+    invitee_cap: Money,
+}
+
+impl RebateSettings {
+    /// 各层返利比例，优先级：套餐配置 > 来源类型配置（`rebate_levels_<source_type>`）> 全局配置
+    fn levels_for(
+        &self,
+        source_type: &str,
+        package_levels: Option<Vec<BigDecimal>>,
+    ) -> Vec<BigDecimal> {
+        if let Some(levels) = package_levels {
+            return levels;
+        }
+        self.source_levels
+            .iter()
+            .find(|(source, _)| source == source_type)
+            .map(|(_, levels)| levels.clone())
+            .unwrap_or_else(|| self.levels.clone())
+    }
+}
+
+/// 解析逗号分隔的层级比例（如 `0.2,0.05,0.02`，第 1 项为直接邀请人）。
+/// 空字符串返回 `Ok(None)` 表示未配置
+pub fn parse_rebate_levels(text: &str) -> Result<Option<Vec<BigDecimal>>, String> {
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        return Ok(None);
+    }
+    let mut levels = Vec::new();
+    for part in trimmed.split([',', '，']) {
+        let part = part.trim();
+        let value = BigDecimal::from_str(part).map_err(|_| format!("返利比例无效: {part}"))?;
+        if value < BigDecimal::zero() || value > 1 {
+            return Err(format!("返利比例需在 0-1 之间: {part}"));
+        }
+        levels.push(value);
+    }
+    if levels.len() > MAX_REBATE_LEVELS {
+        return Err(format!("返利层级最多 {MAX_REBATE_LEVELS} 级"));
+    }
+    Ok(Some(levels))
 }
 
 async fn fetch_rebate_settings(state: &AppState) -> Result<RebateSettings, String> {
     let rows = sqlx::query(
-        "SELECT `key`, `value` FROM system_configs WHERE `key` IN ('rebate_rate','rebate_mode')",
+        "SELECT `key`, `value` FROM system_configs WHERE `key` IN ('rebate_rate','rebate_mode','rebate_invitee_cap') OR `key` LIKE 'rebate\\_levels%'",
     )
     .fetch_all(&state.db)
     .await
     .map_err(|err| err.to_string())?;
-    let mut rate = 0.0;
+    let mut rate = BigDecimal::zero();
+    let mut levels: Option<Vec<BigDecimal>> = None;
+    let mut source_levels: Vec<(String, Vec<BigDecimal>)> = Vec::new();
     let mut mode = "every_order".to_string();
+    let mut invitee_cap = Money::zero();
     for row in rows {
         let key: String = row.try_get("key").unwrap_or_default();
         let value: String = row
@@ -383,18 +639,37 @@ async fn fetch_rebate_settings(state: &AppState) -> Result<RebateSettings, Strin
             .unwrap_or_default();
         match key.as_str() {
             "rebate_rate" => {
-                let parsed = value.parse::<f64>().unwrap_or(0.0);
-                rate = parsed.clamp(0.0, 1.0);
+                rate = parse_rate(&value).unwrap_or_default();
             }
             "rebate_mode" => {
                 if value.trim() == "first_order" {
                     mode = "first_order".to_string();
                 }
             }
-            _ => {}
+            "rebate_invitee_cap" => {
+                invitee_cap = Money::parse(&value).unwrap_or_default().non_negative();
+            }
+            "rebate_levels" => {
+                levels = parse_rebate_levels(&value).ok().flatten();
+            }
+            _ => {
+                if let (Some(source), Ok(Some(parsed))) = (
+                    key.strip_prefix("rebate_levels_"),
+                    parse_rebate_levels(&value),
+                ) {
+                    source_levels.push((source.to_string(), parsed));
+                }
+            }
         }
     }
-    Ok(RebateSettings { rate, mode })
+    // 未配置层级时沿用单级 rebate_rate
+    let levels = levels.unwrap_or_else(|| vec![rate]);
+    Ok(RebateSettings {
+        levels,
+        source_levels,
+        mode,
+        invitee_cap,
+    })
 }
 
 #[derive(Clone)]
@@ -434,6 +709,42 @@ async fn get_referral_relation(
     }))
 }
 
-fn fix_money_precision(amount: f64) -> f64 {
-    (amount * 100.0).round() / 100.0
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rates(values: &[&str]) -> Vec<BigDecimal> {
+        values
+            .iter()
+            .map(|value| BigDecimal::from_str(value).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn rebate_levels_parse_and_override_order() {
+        assert_eq!(parse_rebate_levels("  "), Ok(None));
+        assert_eq!(
+            parse_rebate_levels("0.2, 0.05，0.02"),
+            Ok(Some(rates(&["0.2", "0.05", "0.02"])))
+        );
+        assert!(parse_rebate_levels("0.2,abc").is_err());
+        assert!(parse_rebate_levels("1.5").is_err());
+        assert!(parse_rebate_levels(&["0.01"; MAX_REBATE_LEVELS + 1].join(",")).is_err());
+
+        let settings = RebateSettings {
+            levels: rates(&["0.2", "0.05"]),
+            source_levels: vec![("recharge".to_string(), rates(&["0.1"]))],
+            mode: "every_order".to_string(),
+            invitee_cap: Money::zero(),
+        };
+        assert_eq!(
+            settings.levels_for("purchase", None),
+            rates(&["0.2", "0.05"])
+        );
+        assert_eq!(settings.levels_for("recharge", None), rates(&["0.1"]));
+        assert_eq!(
+            settings.levels_for("recharge", Some(rates(&["0.3", "0.1", "0.05"]))),
+            rates(&["0.3", "0.1", "0.05"])
+        );
+    }
 }
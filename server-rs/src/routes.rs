@@ -14,7 +14,7 @@ mod admin;
 mod announcement;
 mod auth;
 mod node;
-mod payment_callback;
+pub(crate) mod payment_callback;
 mod payment_config;
 mod rebate;
 mod store;
@@ -0,0 +1,336 @@
+use axum::extract::{Query, State};
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Extension, Router};
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sqlx::Row;
+
+use crate::response::{error, success};
+use crate::state::AppState;
+
+use super::super::auth::require_admin_permission;
+
+const EXPORT_LIMIT: i64 = 5000;
+
+#[derive(Deserialize)]
+struct ActionLogsQuery {
+    page: Option<i64>,
+    limit: Option<i64>,
+    #[serde(rename = "pageSize")]
+    page_size: Option<i64>,
+    #[serde(rename = "admin_id")]
+    admin_search: Option<String>,
+    action: Option<String>,
+    target_type: Option<String>,
+    target_id: Option<String>,
+    ip: Option<String>,
+    start_date: Option<String>,
+    end_date: Option<String>,
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(get_action_logs))
+        .route("/export", get(export_action_logs))
+}
+
+async fn get_action_logs(
+    State(state): State<AppState>,
+    Extension(headers): Extension<HeaderMap>,
+    Query(query): Query<ActionLogsQuery>,
+) -> Response {
+    if let Err(resp) = require_admin_permission(&state, &headers, "action-logs").await {
+        return resp;
+    }
+
+    let page = query.page.unwrap_or(1).max(1);
+    let limit_raw = query.limit.or(query.page_size).unwrap_or(50);
+    let limit = limit_raw.clamp(1, 200);
+    let offset = (page - 1) * limit;
+    let (where_clause, params) = build_filters(&query);
+
+    let list_sql = format!(
+        r#"
+    SELECT l.id, l.admin_id, l.ip, l.route, l.action, l.target_type, l.target_id,
+           l.before_data, l.after_data, l.created_at, u.email AS admin_email, u.username AS admin_username
+    FROM admin_action_logs l
+    LEFT JOIN users u ON l.admin_id = u.id
+    {where_clause}
+    ORDER BY l.id DESC
+    LIMIT ? OFFSET ?
+    "#
+    );
+    let mut list_query = sqlx::query(&list_sql);
+    list_query = bind_params(list_query, &params);
+    let rows = match list_query
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&state.db)
+        .await
+    {
+        Ok(value) => value,
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    };
+
+    let total_sql = format!(
+        r#"
+    SELECT COUNT(*) as total
+    FROM admin_action_logs l
+    LEFT JOIN users u ON l.admin_id = u.id
+    {where_clause}
+    "#
+    );
+    let mut total_query = sqlx::query(&total_sql);
+    total_query = bind_params(total_query, &params);
+    let total_row = match total_query.fetch_optional(&state.db).await {
+        Ok(value) => value,
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    };
+    let total = total_row
+        .and_then(|row| row.try_get::<Option<i64>, _>("total").ok().flatten())
+        .unwrap_or(0);
+
+    let logs = rows
+        .into_iter()
+        .map(|row| {
+            let read_string = |key: &str| {
+                row.try_get::<Option<String>, _>(key)
+                    .ok()
+                    .flatten()
+                    .unwrap_or_default()
+            };
+            json!({
+              "id": row.try_get::<i64, _>("id").unwrap_or(0),
+              "admin_id": row.try_get::<Option<i64>, _>("admin_id").ok().flatten().unwrap_or(0),
+              "admin_email": read_string("admin_email"),
+              "admin_username": read_string("admin_username"),
+              "ip": read_string("ip"),
+              "route": read_string("route"),
+              "action": read_string("action"),
+              "target_type": read_string("target_type"),
+              "target_id": read_string("target_id"),
+              "before": parse_json_column(&row, "before_data"),
+              "after": parse_json_column(&row, "after_data"),
+              "created_at": format_datetime(row.try_get::<Option<NaiveDateTime>, _>("created_at").ok().flatten())
+            })
+        })
+        .collect::<Vec<Value>>();
+
+    success(
+        json!({
+          "data": logs,
+          "total": total,
+          "pagination": {
+            "total": total,
+            "page": page,
+            "limit": limit,
+            "pages": if total > 0 { ((total as f64) / (limit as f64)).ceil() as i64 } else { 0 }
+          }
+        }),
+        "Success",
+    )
+    .into_response()
+}
+
+async fn export_action_logs(
+    State(state): State<AppState>,
+    Extension(headers): Extension<HeaderMap>,
+    Query(query): Query<ActionLogsQuery>,
+) -> Response {
+    if let Err(resp) = require_admin_permission(&state, &headers, "action-logs").await {
+        return resp;
+    }
+
+    let (where_clause, params) = build_filters(&query);
+    let sql = format!(
+        r#"
+    SELECT l.admin_id, l.ip, l.route, l.action, l.target_type, l.target_id,
+           l.before_data, l.after_data, l.created_at, u.email AS admin_email
+    FROM admin_action_logs l
+    LEFT JOIN users u ON l.admin_id = u.id
+    {where_clause}
+    ORDER BY l.id DESC
+    LIMIT {EXPORT_LIMIT}
+    "#
+    );
+    let mut query_builder = sqlx::query(&sql);
+    query_builder = bind_params(query_builder, &params);
+    let rows = match query_builder.fetch_all(&state.db).await {
+        Ok(value) => value,
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    };
+
+    let headers = [
+        "时间",
+        "管理员ID",
+        "管理员邮箱",
+        "IP",
+        "接口",
+        "操作",
+        "对象类型",
+        "对象ID",
+        "变更前",
+        "变更后",
+    ];
+    let mut csv = format!("{}\n", headers.join(","));
+    for row in rows {
+        let read_string = |key: &str| {
+            row.try_get::<Option<String>, _>(key)
+                .ok()
+                .flatten()
+                .unwrap_or_default()
+        };
+        let line = [
+            escape_csv(
+                format_datetime(
+                    row.try_get::<Option<NaiveDateTime>, _>("created_at")
+                        .ok()
+                        .flatten(),
+                )
+                .unwrap_or_default(),
+            ),
+            escape_csv(
+                row.try_get::<Option<i64>, _>("admin_id")
+                    .ok()
+                    .flatten()
+                    .unwrap_or(0),
+            ),
+            escape_csv(read_string("admin_email")),
+            escape_csv(read_string("ip")),
+            escape_csv(read_string("route")),
+            escape_csv(read_string("action")),
+            escape_csv(read_string("target_type")),
+            escape_csv(read_string("target_id")),
+            escape_csv(read_string("before_data")),
+            escape_csv(read_string("after_data")),
+        ];
+        csv.push_str(&format!("{}\n", line.join(",")));
+    }
+
+    build_csv_response("admin_action_logs.csv", csv)
+}
+
+fn build_filters(query: &ActionLogsQuery) -> (String, Vec<SqlParam>) {
+    let mut conditions: Vec<String> = Vec::new();
+    let mut params: Vec<SqlParam> = Vec::new();
+
+    let admin_search = trimmed(query.admin_search.as_deref());
+    if !admin_search.is_empty() {
+        let parsed_id = admin_search.parse::<i64>().unwrap_or(-1);
+        let pattern = format!("%{admin_search}%");
+        conditions.push("(l.admin_id = ? OR u.email LIKE ? OR u.username LIKE ?)".to_string());
+        params.push(SqlParam::I64(parsed_id));
+        params.push(SqlParam::String(pattern.clone()));
+        params.push(SqlParam::String(pattern));
+    }
+    // 支持按前缀筛选，如 `user.` 匹配所有用户相关操作
+    let action = trimmed(query.action.as_deref());
+    if !action.is_empty() {
+        conditions.push("l.action LIKE ?".to_string());
+        params.push(SqlParam::String(format!("{action}%")));
+    }
+    let target_type = trimmed(query.target_type.as_deref());
+    if !target_type.is_empty() {
+        conditions.push("l.target_type = ?".to_string());
+        params.push(SqlParam::String(target_type));
+    }
+    let target_id = trimmed(query.target_id.as_deref());
+    if !target_id.is_empty() {
+        conditions.push("l.target_id = ?".to_string());
+        params.push(SqlParam::String(target_id));
+    }
+    let ip = trimmed(query.ip.as_deref());
+    if !ip.is_empty() {
+        conditions.push("l.ip LIKE ?".to_string());
+        params.push(SqlParam::String(format!("%{ip}%")));
+    }
+    if let Some(value) = parse_datetime_input(query.start_date.as_deref()) {
+        conditions.push("l.created_at >= ?".to_string());
+        params.push(SqlParam::String(value));
+    }
+    if let Some(value) = parse_datetime_input(query.end_date.as_deref()) {
+        conditions.push("l.created_at <= ?".to_string());
+        params.push(SqlParam::String(value));
+    }
+
+    let where_clause = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    };
+    (where_clause, params)
+}
+
+fn trimmed(value: Option<&str>) -> String {
+    value.unwrap_or_default().trim().to_string()
+}
+
+fn parse_json_column(row: &sqlx::mysql::MySqlRow, column: &str) -> Value {
+    row.try_get::<Option<String>, _>(column)
+        .ok()
+        .flatten()
+        .and_then(|raw| serde_json::from_str::<Value>(&raw).ok())
+        .unwrap_or(Value::Null)
+}
+
+fn parse_datetime_input(value: Option<&str>) -> Option<String> {
+    let raw = value?.trim();
+    if raw.is_empty() {
+        return None;
+    }
+    if let Ok(dt) = NaiveDateTime::parse_from_str(raw, "%Y-%m-%d %H:%M:%S") {
+        return Some(dt.format("%Y-%m-%d %H:%M:%S").to_string());
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(raw, "%Y-%m-%d") {
+        return date
+            .and_hms_opt(0, 0, 0)
+            .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string());
+    }
+    if let Ok(dt) = DateTime::parse_from_rfc3339(raw) {
+        return Some(dt.naive_local().format("%Y-%m-%d %H:%M:%S").to_string());
+    }
+    None
+}
+
+fn format_datetime(value: Option<NaiveDateTime>) -> Option<String> {
+    value.map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
+}
+
+fn escape_csv(value: impl ToString) -> String {
+    format!("\"{}\"", value.to_string().replace('"', "\"\""))
+}
+
+fn build_csv_response(filename: &str, csv: String) -> Response {
+    let mut response = Response::new(csv.into());
+    *response.status_mut() = StatusCode::OK;
+    response.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("text/csv; charset=utf-8"),
+    );
+    if let Ok(value) = HeaderValue::from_str(&format!("attachment; filename={filename}")) {
+        response
+            .headers_mut()
+            .insert(header::CONTENT_DISPOSITION, value);
+    }
+    response
+}
+
+type SqlxQuery<'a> = sqlx::query::Query<'a, sqlx::MySql, sqlx::mysql::MySqlArguments>;
+
+enum SqlParam {
+    I64(i64),
+    String(String),
+}
+
+fn bind_params<'a>(mut query: SqlxQuery<'a>, params: &'a [SqlParam]) -> SqlxQuery<'a> {
+    for param in params {
+        query = match param {
+            SqlParam::I64(value) => query.bind(*value),
+            SqlParam::String(value) => query.bind(value),
+        };
+    }
+    query
+}
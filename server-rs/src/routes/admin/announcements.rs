@@ -15,7 +15,7 @@ use crate::message_queue::{
 use crate::response::{error, success};
 use crate::state::AppState;
 
-use super::super::auth::require_admin_user_id;
+use super::super::auth::require_admin_permission;
 
 #[derive(Deserialize)]
 struct AnnouncementsQuery {
@@ -51,7 +51,7 @@ async fn get_announcements(
     Extension(headers): Extension<HeaderMap>,
     Query(query): Query<AnnouncementsQuery>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "announcements").await {
         return resp;
     }
 
@@ -110,7 +110,7 @@ async fn post_announcement(
     Extension(headers): Extension<HeaderMap>,
     Json(body): Json<AnnouncementRequest>,
 ) -> Response {
-    let admin_id = match require_admin_user_id(&state, &headers, None).await {
+    let admin_id = match require_admin_permission(&state, &headers, "announcements").await {
         Ok(value) => value,
         Err(resp) => return resp,
     };
@@ -253,7 +253,7 @@ async fn put_announcement(
     Path(id): Path<i64>,
     Json(body): Json<AnnouncementRequest>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "announcements").await {
         return resp;
     }
     if id <= 0 {
@@ -343,7 +343,7 @@ async fn delete_announcement(
     Extension(headers): Extension<HeaderMap>,
     Path(id): Path<i64>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "announcements").await {
         return resp;
     }
     if id <= 0 {
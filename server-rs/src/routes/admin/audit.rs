@@ -8,11 +8,16 @@ use serde::Deserialize;
 use serde_json::{json, Value};
 use sqlx::Row;
 
+use crate::audit_rules::{
+    compile_audit_rule, normalize_audit_rule, parse_audit_target, split_legacy_rule, AuditRuleType,
+    AuditTarget,
+};
 use crate::cache::cache_delete_by_prefix;
+use crate::node_hub::{notify_nodes, NodeEvent};
 use crate::response::{error, success};
 use crate::state::AppState;
 
-use super::super::auth::require_admin_user_id;
+use super::super::auth::require_admin_permission;
 
 #[derive(Deserialize)]
 struct AuditRulesQuery {
@@ -22,6 +27,7 @@ struct AuditRulesQuery {
     page_size: Option<i64>,
     search: Option<String>,
     enabled: Option<String>,
+    rule_type: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -29,12 +35,14 @@ struct AuditRuleRequest {
     name: Option<String>,
     description: Option<String>,
     rule: Option<String>,
+    rule_type: Option<String>,
     enabled: Option<i64>,
 }
 
 #[derive(Deserialize)]
 struct AuditRuleTestRequest {
     content: Option<String>,
+    target: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -62,6 +70,7 @@ pub fn router() -> Router<AppState> {
     Router::new()
         .route("/audit-rules", get(get_audit_rules))
         .route("/audit-rules", post(post_audit_rule))
+        .route("/audit-rules/test", post(post_audit_rules_test))
         .route("/audit-rules/{id}", put(put_audit_rule))
         .route("/audit-rules/{id}", delete(delete_audit_rule))
         .route("/audit-rules/{id}/test", post(post_audit_rule_test))
@@ -78,7 +87,7 @@ async fn get_audit_rules(
     Extension(headers): Extension<HeaderMap>,
     Query(query): Query<AuditRulesQuery>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "audit").await {
         return resp;
     }
 
@@ -103,6 +112,10 @@ async fn get_audit_rules(
         conditions.push("enabled = ?".to_string());
         params.push(SqlParam::I64(value));
     }
+    if let Some(rule_type) = query.rule_type.as_deref().and_then(AuditRuleType::parse) {
+        conditions.push("rule_type = ?".to_string());
+        params.push(SqlParam::String(rule_type.as_str().to_string()));
+    }
 
     let where_clause = if conditions.is_empty() {
         String::new()
@@ -123,7 +136,7 @@ async fn get_audit_rules(
 
     let list_sql = format!(
         r#"
-    SELECT id, name, description, rule, enabled, created_at, updated_at
+    SELECT id, name, description, rule_type, rule, enabled, created_at, updated_at
     FROM audit_rules
     {where_clause}
     ORDER BY id ASC
@@ -143,19 +156,9 @@ async fn get_audit_rules(
     };
 
     let items = rows
-    .into_iter()
-    .map(|row| {
-      json!({
-        "id": row.try_get::<i64, _>("id").unwrap_or(0),
-        "name": row.try_get::<Option<String>, _>("name").ok().flatten().unwrap_or_default(),
-        "description": row.try_get::<Option<String>, _>("description").ok().flatten().unwrap_or_default(),
-        "rule": row.try_get::<Option<String>, _>("rule").ok().flatten().unwrap_or_default(),
-        "enabled": row.try_get::<Option<i64>, _>("enabled").unwrap_or(Some(1)).unwrap_or(1),
-        "created_at": format_datetime(row.try_get::<Option<NaiveDateTime>, _>("created_at").ok().flatten()),
-        "updated_at": format_datetime(row.try_get::<Option<NaiveDateTime>, _>("updated_at").ok().flatten())
-      })
-    })
-    .collect::<Vec<Value>>();
+        .into_iter()
+        .map(map_audit_rule_row)
+        .collect::<Vec<Value>>();
 
     success(
         json!({
@@ -178,7 +181,7 @@ async fn post_audit_rule(
     Extension(headers): Extension<HeaderMap>,
     Json(body): Json<AuditRuleRequest>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "audit").await {
         return resp;
     }
 
@@ -187,17 +190,22 @@ async fn post_audit_rule(
     if name.is_empty() || rule.is_empty() {
         return error(StatusCode::BAD_REQUEST, "缺少必要字段", None);
     }
+    let (rule_type, rule) = match resolve_rule(body.rule_type.as_deref(), &rule) {
+        Ok(value) => value,
+        Err(message) => return error(StatusCode::BAD_REQUEST, &message, None),
+    };
     let description = body.description.unwrap_or_default();
     let enabled = body.enabled.unwrap_or(1);
 
     let result = sqlx::query(
         r#"
-    INSERT INTO audit_rules (name, description, rule, enabled, created_at, updated_at)
-    VALUES (?, ?, ?, ?, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
+    INSERT INTO audit_rules (name, description, rule_type, rule, enabled, created_at, updated_at)
+    VALUES (?, ?, ?, ?, ?, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
     "#,
     )
     .bind(&name)
     .bind(description)
+    .bind(rule_type.as_str())
     .bind(&rule)
     .bind(enabled)
     .execute(&state.db)
@@ -219,6 +227,7 @@ async fn post_audit_rule(
     };
 
     cache_delete_by_prefix(&state, "audit_rules").await;
+    notify_nodes(&state, NodeEvent::AuditRulesChanged).await;
     let payload = row.map(map_audit_rule_row).unwrap_or(Value::Null);
     success(payload, "创建成功").into_response()
 }
@@ -229,7 +238,7 @@ async fn put_audit_rule(
     Path(id): Path<i64>,
     Json(body): Json<AuditRuleRequest>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "audit").await {
         return resp;
     }
     if id <= 0 {
@@ -250,12 +259,46 @@ async fn put_audit_rule(
         fields.push("description = ?".to_string());
         params.push(SqlParam::String(value));
     }
-    if let Some(value) = body.rule {
-        let trimmed = value.trim().to_string();
-        if !trimmed.is_empty() {
-            fields.push("rule = ?".to_string());
-            params.push(SqlParam::String(trimmed));
-        }
+    let new_rule = body
+        .rule
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty());
+    if new_rule.is_some() || body.rule_type.is_some() {
+        // 类型与内容需一起校验，缺少的一项沿用已有值
+        let current = match sqlx::query("SELECT rule_type, rule FROM audit_rules WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&state.db)
+            .await
+        {
+            Ok(Some(row)) => row,
+            Ok(None) => return error(StatusCode::NOT_FOUND, "审计规则不存在", None),
+            Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+        };
+        let rule_type = body.rule_type.or_else(|| {
+            // 仅修改内容时允许通过前缀切换类型
+            match new_rule.as_deref().map(split_legacy_rule) {
+                Some((Some(_), _)) => None,
+                _ => current
+                    .try_get::<Option<String>, _>("rule_type")
+                    .ok()
+                    .flatten(),
+            }
+        });
+        let rule = new_rule.unwrap_or_else(|| {
+            current
+                .try_get::<Option<String>, _>("rule")
+                .ok()
+                .flatten()
+                .unwrap_or_default()
+        });
+        let (rule_type, rule) = match resolve_rule(rule_type.as_deref(), &rule) {
+            Ok(value) => value,
+            Err(message) => return error(StatusCode::BAD_REQUEST, &message, None),
+        };
+        fields.push("rule_type = ?".to_string());
+        params.push(SqlParam::String(rule_type.as_str().to_string()));
+        fields.push("rule = ?".to_string());
+        params.push(SqlParam::String(rule));
     }
     if let Some(value) = body.enabled {
         fields.push("enabled = ?".to_string());
@@ -292,6 +335,7 @@ async fn put_audit_rule(
     };
 
     cache_delete_by_prefix(&state, "audit_rules").await;
+    notify_nodes(&state, NodeEvent::AuditRulesChanged).await;
     let payload = row.map(map_audit_rule_row).unwrap_or(Value::Null);
     success(payload, "更新成功").into_response()
 }
@@ -301,7 +345,7 @@ async fn delete_audit_rule(
     Extension(headers): Extension<HeaderMap>,
     Path(id): Path<i64>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "audit").await {
         return resp;
     }
     if id <= 0 {
@@ -317,6 +361,7 @@ async fn delete_audit_rule(
     }
 
     cache_delete_by_prefix(&state, "audit_rules").await;
+    notify_nodes(&state, NodeEvent::AuditRulesChanged).await;
     success(Value::Null, "删除成功").into_response()
 }
 
@@ -326,15 +371,18 @@ async fn post_audit_rule_test(
     Path(id): Path<i64>,
     Json(body): Json<AuditRuleTestRequest>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "audit").await {
         return resp;
     }
     if id <= 0 {
         return error(StatusCode::BAD_REQUEST, "ID 无效", None);
     }
 
-    let content = body.content.unwrap_or_default();
-    let row = sqlx::query("SELECT name, rule FROM audit_rules WHERE id = ?")
+    let target = parse_audit_target(&body.target.or(body.content).unwrap_or_default());
+    if target.raw.is_empty() {
+        return error(StatusCode::BAD_REQUEST, "请输入需要测试的目标地址", None);
+    }
+    let row = sqlx::query("SELECT name, rule_type, rule FROM audit_rules WHERE id = ?")
         .bind(id)
         .fetch_optional(&state.db)
         .await;
@@ -347,22 +395,24 @@ async fn post_audit_rule_test(
         None => return error(StatusCode::NOT_FOUND, "审计规则不存在", None),
     };
 
-    let rule = row
-        .try_get::<Option<String>, _>("rule")
-        .ok()
-        .flatten()
-        .unwrap_or_default();
     let name = row
         .try_get::<Option<String>, _>("name")
         .ok()
         .flatten()
         .unwrap_or_default();
-    let matched = !rule.is_empty() && content.contains(&rule);
+    let (rule_type, rule) = read_rule(&row);
+    let compiled = match compile_audit_rule(rule_type, &rule) {
+        Ok(value) => value,
+        Err(message) => return error(StatusCode::BAD_REQUEST, &message, None),
+    };
+    let matched = compiled.matches(&target);
 
     success(
         json!({
           "matched": matched,
           "rule_name": name,
+          "rule_type": rule_type.as_str(),
+          "target": target_json(&target),
           "matched_content": if matched { Value::String(rule) } else { Value::Null }
         }),
         "Success",
@@ -370,12 +420,78 @@ async fn post_audit_rule_test(
     .into_response()
 }
 
+async fn post_audit_rules_test(
+    State(state): State<AppState>,
+    Extension(headers): Extension<HeaderMap>,
+    Json(body): Json<AuditRuleTestRequest>,
+) -> Response {
+    if let Err(resp) = require_admin_permission(&state, &headers, "audit").await {
+        return resp;
+    }
+
+    let target = parse_audit_target(&body.target.or(body.content).unwrap_or_default());
+    if target.raw.is_empty() {
+        return error(StatusCode::BAD_REQUEST, "请输入需要测试的目标地址", None);
+    }
+
+    let rows = match sqlx::query(
+        "SELECT id, name, rule_type, rule FROM audit_rules WHERE enabled = 1 ORDER BY id ASC",
+    )
+    .fetch_all(&state.db)
+    .await
+    {
+        Ok(value) => value,
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    };
+
+    let total = rows.len();
+    let mut matches: Vec<Value> = Vec::new();
+    let mut invalid: Vec<Value> = Vec::new();
+    for row in rows {
+        let id = row.try_get::<i64, _>("id").unwrap_or(0);
+        let name = row
+            .try_get::<Option<String>, _>("name")
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+        let (rule_type, rule) = read_rule(&row);
+        match compile_audit_rule(rule_type, &rule) {
+            Ok(compiled) if compiled.matches(&target) => matches.push(json!({
+              "id": id,
+              "name": name,
+              "rule_type": rule_type.as_str(),
+              "rule": rule
+            })),
+            Ok(_) => {}
+            Err(message) => invalid.push(json!({
+              "id": id,
+              "name": name,
+              "rule_type": rule_type.as_str(),
+              "rule": rule,
+              "error": message
+            })),
+        }
+    }
+
+    success(
+        json!({
+          "target": target_json(&target),
+          "matched": !matches.is_empty(),
+          "total_rules": total,
+          "matches": matches,
+          "invalid_rules": invalid
+        }),
+        "Success",
+    )
+    .into_response()
+}
+
 async fn get_audit_logs(
     State(state): State<AppState>,
     Extension(headers): Extension<HeaderMap>,
     Query(query): Query<AuditLogsQuery>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "audit").await {
         return resp;
     }
 
@@ -517,7 +633,7 @@ async fn delete_audit_log(
     Extension(headers): Extension<HeaderMap>,
     Path(id): Path<i64>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "audit").await {
         return resp;
     }
     if id <= 0 {
@@ -538,7 +654,7 @@ async fn post_audit_log_batch_delete(
     Extension(headers): Extension<HeaderMap>,
     Json(body): Json<BatchIdsRequest>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "audit").await {
         return resp;
     }
 
@@ -597,6 +713,7 @@ fn map_audit_rule_row(row: sqlx::mysql::MySqlRow) -> Value {
       "id": row.try_get::<i64, _>("id").unwrap_or(0),
       "name": row.try_get::<Option<String>, _>("name").ok().flatten().unwrap_or_default(),
       "description": row.try_get::<Option<String>, _>("description").ok().flatten().unwrap_or_default(),
+      "rule_type": row.try_get::<Option<String>, _>("rule_type").ok().flatten().unwrap_or_else(|| "regex".to_string()),
       "rule": row.try_get::<Option<String>, _>("rule").ok().flatten().unwrap_or_default(),
       "enabled": row.try_get::<Option<i64>, _>("enabled").unwrap_or(Some(1)).unwrap_or(1),
       "created_at": format_datetime(row.try_get::<Option<NaiveDateTime>, _>("created_at").ok().flatten()),
@@ -604,6 +721,45 @@ fn map_audit_rule_row(row: sqlx::mysql::MySqlRow) -> Value {
     })
 }
 
+/// 未指定类型时兼容旧的 `regexp:` 等前缀写法，默认按正则处理
+fn resolve_rule(rule_type: Option<&str>, rule: &str) -> Result<(AuditRuleType, String), String> {
+    let (rule_type, pattern) = match rule_type.map(str::trim).filter(|value| !value.is_empty()) {
+        Some(raw) => match AuditRuleType::parse(raw) {
+            Some(value) => (value, rule),
+            None => return Err(format!("不支持的规则类型: {raw}")),
+        },
+        None => match split_legacy_rule(rule) {
+            (Some(value), pattern) => (value, pattern),
+            (None, pattern) => (AuditRuleType::Regex, pattern),
+        },
+    };
+    normalize_audit_rule(rule_type, pattern).map(|pattern| (rule_type, pattern))
+}
+
+fn read_rule(row: &sqlx::mysql::MySqlRow) -> (AuditRuleType, String) {
+    let rule_type = row
+        .try_get::<Option<String>, _>("rule_type")
+        .ok()
+        .flatten()
+        .and_then(|value| AuditRuleType::parse(&value))
+        .unwrap_or(AuditRuleType::Regex);
+    let rule = row
+        .try_get::<Option<String>, _>("rule")
+        .ok()
+        .flatten()
+        .unwrap_or_default();
+    (rule_type, rule)
+}
+
+fn target_json(target: &AuditTarget) -> Value {
+    json!({
+      "raw": target.raw,
+      "host": target.host,
+      "ip": target.ip.map(|ip| ip.to_string()),
+      "port": target.port
+    })
+}
+
 type SqlxQuery<'a> = sqlx::query::Query<'a, sqlx::MySql, sqlx::mysql::MySqlArguments>;
 
 enum SqlParam {
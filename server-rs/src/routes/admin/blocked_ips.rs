@@ -0,0 +1,335 @@
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{delete, get, post, put};
+use axum::{Extension, Json, Router};
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sqlx::Row;
+
+use crate::blocked_ips::{invalidate_blocked_ips, normalize_block_target, upsert_blocked_ip};
+use crate::response::{error, success};
+use crate::state::AppState;
+
+use super::super::auth::require_admin_permission;
+
+#[derive(Deserialize)]
+struct BlockedIpsQuery {
+    page: Option<i64>,
+    limit: Option<i64>,
+    #[serde(rename = "pageSize")]
+    page_size: Option<i64>,
+    search: Option<String>,
+    status: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct BlockedIpRequest {
+    ip: Option<String>,
+    reason: Option<String>,
+    expires_at: Option<String>,
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/blocked-ips", get(get_blocked_ips))
+        .route("/blocked-ips", post(post_blocked_ip))
+        .route("/blocked-ips/{id}", put(put_blocked_ip))
+        .route("/blocked-ips/{id}", delete(delete_blocked_ip))
+}
+
+async fn get_blocked_ips(
+    State(state): State<AppState>,
+    Extension(headers): Extension<HeaderMap>,
+    Query(query): Query<BlockedIpsQuery>,
+) -> Response {
+    if let Err(resp) = require_admin_permission(&state, &headers, "blocked-ips").await {
+        return resp;
+    }
+
+    let page = query.page.unwrap_or(1).max(1);
+    let limit_raw = query.limit.or(query.page_size).unwrap_or(20);
+    let limit = limit_raw.clamp(1, 200);
+    let offset = (page - 1) * limit;
+    let search = query.search.unwrap_or_default().trim().to_string();
+    let status = query.status.unwrap_or_default().trim().to_string();
+
+    let mut conditions: Vec<String> = Vec::new();
+    let mut params: Vec<String> = Vec::new();
+    if !search.is_empty() {
+        let pattern = format!("%{search}%");
+        conditions.push("(b.ip LIKE ? OR b.reason LIKE ?)".to_string());
+        params.push(pattern.clone());
+        params.push(pattern);
+    }
+    match status.as_str() {
+        "active" => conditions
+            .push("(b.expires_at IS NULL OR b.expires_at > CURRENT_TIMESTAMP)".to_string()),
+        "expired" => conditions
+            .push("(b.expires_at IS NOT NULL AND b.expires_at <= CURRENT_TIMESTAMP)".to_string()),
+        _ => {}
+    }
+
+    let where_clause = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    };
+
+    let total_sql = format!("SELECT COUNT(*) as total FROM blocked_ips b {where_clause}");
+    let mut total_query = sqlx::query(&total_sql);
+    for param in &params {
+        total_query = total_query.bind(param);
+    }
+    let total = match total_query.fetch_optional(&state.db).await {
+        Ok(row) => row
+            .and_then(|row| row.try_get::<Option<i64>, _>("total").ok().flatten())
+            .unwrap_or(0),
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    };
+
+    let list_sql = format!(
+        r#"
+    SELECT b.id, b.ip, b.reason, b.expires_at, b.created_by, b.created_at, b.updated_at,
+           u.username AS created_by_username,
+           (b.expires_at IS NULL OR b.expires_at > CURRENT_TIMESTAMP) AS is_active
+    FROM blocked_ips b
+    LEFT JOIN users u ON u.id = b.created_by
+    {where_clause}
+    ORDER BY b.id DESC
+    LIMIT ? OFFSET ?
+    "#
+    );
+    let mut list_query = sqlx::query(&list_sql);
+    for param in &params {
+        list_query = list_query.bind(param);
+    }
+    let rows = match list_query
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&state.db)
+        .await
+    {
+        Ok(value) => value,
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    };
+
+    let items = rows
+        .into_iter()
+        .map(map_blocked_ip_row)
+        .collect::<Vec<Value>>();
+
+    success(
+        json!({
+          "data": items,
+          "total": total,
+          "pagination": {
+            "total": total,
+            "page": page,
+            "limit": limit,
+            "pages": if total > 0 { ((total as f64) / (limit as f64)).ceil() as i64 } else { 0 }
+          }
+        }),
+        "Success",
+    )
+    .into_response()
+}
+
+async fn post_blocked_ip(
+    State(state): State<AppState>,
+    Extension(headers): Extension<HeaderMap>,
+    Json(body): Json<BlockedIpRequest>,
+) -> Response {
+    let admin_id = match require_admin_permission(&state, &headers, "blocked-ips").await {
+        Ok(value) => value,
+        Err(resp) => return resp,
+    };
+
+    let Some(target) = normalize_block_target(body.ip.as_deref().unwrap_or_default()) else {
+        return error(StatusCode::BAD_REQUEST, "IP 或 CIDR 格式无效", None);
+    };
+    let expires_at = match parse_expires_at(body.expires_at.as_deref()) {
+        Ok(value) => value,
+        Err(message) => return error(StatusCode::BAD_REQUEST, message, None),
+    };
+    let reason = body
+        .reason
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty());
+
+    let id = match upsert_blocked_ip(&state, &target, reason, expires_at, admin_id).await {
+        Ok(value) => value,
+        Err(message) => return error(StatusCode::INTERNAL_SERVER_ERROR, &message, None),
+    };
+
+    match fetch_blocked_ip(&state, id).await {
+        Ok(payload) => success(payload, "封禁成功").into_response(),
+        Err(resp) => resp,
+    }
+}
+
+async fn put_blocked_ip(
+    State(state): State<AppState>,
+    Extension(headers): Extension<HeaderMap>,
+    Path(id): Path<i64>,
+    Json(body): Json<BlockedIpRequest>,
+) -> Response {
+    if let Err(resp) = require_admin_permission(&state, &headers, "blocked-ips").await {
+        return resp;
+    }
+    if id <= 0 {
+        return error(StatusCode::BAD_REQUEST, "ID 无效", None);
+    }
+
+    let mut fields: Vec<&str> = Vec::new();
+    let mut target: Option<String> = None;
+    if let Some(raw) = body.ip.as_deref() {
+        match normalize_block_target(raw) {
+            Some(value) => {
+                fields.push("ip = ?");
+                target = Some(value);
+            }
+            None => return error(StatusCode::BAD_REQUEST, "IP 或 CIDR 格式无效", None),
+        }
+    }
+    let reason = body.reason.as_ref().map(|value| value.trim().to_string());
+    if reason.is_some() {
+        fields.push("reason = ?");
+    }
+    // expires_at 传空字符串表示改为永久封禁
+    let expires_at = match body.expires_at.as_deref() {
+        Some(raw) => match parse_expires_at(Some(raw)) {
+            Ok(value) => {
+                fields.push("expires_at = ?");
+                Some(value)
+            }
+            Err(message) => return error(StatusCode::BAD_REQUEST, message, None),
+        },
+        None => None,
+    };
+
+    if fields.is_empty() {
+        return error(StatusCode::BAD_REQUEST, "没有需要更新的字段", None);
+    }
+
+    let sql = format!(
+        "UPDATE blocked_ips SET {}, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+        fields.join(", ")
+    );
+    let mut query_builder = sqlx::query(&sql);
+    if let Some(value) = target {
+        query_builder = query_builder.bind(value);
+    }
+    if let Some(value) = reason {
+        query_builder = query_builder.bind(value);
+    }
+    if let Some(value) = expires_at {
+        query_builder = query_builder.bind(value);
+    }
+    match query_builder.bind(id).execute(&state.db).await {
+        Ok(outcome) if outcome.rows_affected() == 0 => {
+            return error(StatusCode::NOT_FOUND, "封禁记录不存在", None);
+        }
+        Ok(_) => {}
+        Err(err) => {
+            let message = err.to_string();
+            if message.contains("Duplicate") {
+                return error(StatusCode::BAD_REQUEST, "该 IP 或网段已存在封禁记录", None);
+            }
+            return error(StatusCode::INTERNAL_SERVER_ERROR, &message, None);
+        }
+    }
+
+    invalidate_blocked_ips(&state).await;
+    match fetch_blocked_ip(&state, id).await {
+        Ok(payload) => success(payload, "更新成功").into_response(),
+        Err(resp) => resp,
+    }
+}
+
+async fn delete_blocked_ip(
+    State(state): State<AppState>,
+    Extension(headers): Extension<HeaderMap>,
+    Path(id): Path<i64>,
+) -> Response {
+    if let Err(resp) = require_admin_permission(&state, &headers, "blocked-ips").await {
+        return resp;
+    }
+    if id <= 0 {
+        return error(StatusCode::BAD_REQUEST, "ID 无效", None);
+    }
+
+    match sqlx::query("DELETE FROM blocked_ips WHERE id = ?")
+        .bind(id)
+        .execute(&state.db)
+        .await
+    {
+        Ok(outcome) if outcome.rows_affected() == 0 => {
+            return error(StatusCode::NOT_FOUND, "封禁记录不存在", None);
+        }
+        Ok(_) => {}
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    }
+
+    invalidate_blocked_ips(&state).await;
+    success(Value::Null, "已解除封禁").into_response()
+}
+
+async fn fetch_blocked_ip(state: &AppState, id: i64) -> Result<Value, Response> {
+    let row = sqlx::query(
+        r#"
+    SELECT b.id, b.ip, b.reason, b.expires_at, b.created_by, b.created_at, b.updated_at,
+           u.username AS created_by_username,
+           (b.expires_at IS NULL OR b.expires_at > CURRENT_TIMESTAMP) AS is_active
+    FROM blocked_ips b
+    LEFT JOIN users u ON u.id = b.created_by
+    WHERE b.id = ?
+    "#,
+    )
+    .bind(id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|err| error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None))?;
+    Ok(row.map(map_blocked_ip_row).unwrap_or(Value::Null))
+}
+
+fn parse_expires_at(value: Option<&str>) -> Result<Option<NaiveDateTime>, &'static str> {
+    let raw = value.unwrap_or_default().trim();
+    if raw.is_empty() {
+        return Ok(None);
+    }
+    parse_datetime(raw).map(Some).ok_or("到期时间格式无效")
+}
+
+fn parse_datetime(raw: &str) -> Option<NaiveDateTime> {
+    if let Ok(dt) = NaiveDateTime::parse_from_str(raw, "%Y-%m-%d %H:%M:%S") {
+        return Some(dt);
+    }
+    if let Ok(dt) = DateTime::parse_from_rfc3339(raw) {
+        return Some(dt.naive_local());
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(raw, "%Y-%m-%d") {
+        return date.and_hms_opt(0, 0, 0);
+    }
+    None
+}
+
+fn format_datetime(value: Option<NaiveDateTime>) -> Option<String> {
+    value.map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
+}
+
+fn map_blocked_ip_row(row: sqlx::mysql::MySqlRow) -> Value {
+    json!({
+      "id": row.try_get::<i64, _>("id").unwrap_or(0),
+      "ip": row.try_get::<Option<String>, _>("ip").ok().flatten().unwrap_or_default(),
+      "reason": row.try_get::<Option<String>, _>("reason").ok().flatten(),
+      "expires_at": format_datetime(row.try_get::<Option<NaiveDateTime>, _>("expires_at").ok().flatten()),
+      "is_active": row.try_get::<Option<i64>, _>("is_active").ok().flatten().unwrap_or(0) == 1,
+      "created_by": row.try_get::<Option<i64>, _>("created_by").ok().flatten(),
+      "created_by_username": row.try_get::<Option<String>, _>("created_by_username").ok().flatten(),
+      "created_at": format_datetime(row.try_get::<Option<NaiveDateTime>, _>("created_at").ok().flatten()),
+      "updated_at": format_datetime(row.try_get::<Option<NaiveDateTime>, _>("updated_at").ok().flatten())
+    })
+}
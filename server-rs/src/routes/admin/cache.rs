@@ -10,7 +10,7 @@ use crate::cache::cache_delete_by_prefix;
 use crate::response::success;
 use crate::state::AppState;
 
-use super::super::auth::require_admin_user_id;
+use super::super::auth::require_admin_permission;
 
 pub fn router() -> Router<AppState> {
     Router::new()
@@ -25,7 +25,7 @@ async fn get_cache_status(
     State(state): State<AppState>,
     Extension(headers): Extension<HeaderMap>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "cache").await {
         return resp;
     }
 
@@ -65,7 +65,7 @@ async fn post_clear_audit_rules(
     State(state): State<AppState>,
     Extension(headers): Extension<HeaderMap>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "cache").await {
         return resp;
     }
     cache_delete_by_prefix(&state, "audit_rules").await;
@@ -80,7 +80,7 @@ async fn post_clear_whitelist(
     State(state): State<AppState>,
     Extension(headers): Extension<HeaderMap>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "cache").await {
         return resp;
     }
     cache_delete_by_prefix(&state, "white_list").await;
@@ -96,7 +96,7 @@ async fn post_clear_all(
     State(state): State<AppState>,
     Extension(headers): Extension<HeaderMap>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "cache").await {
         return resp;
     }
 
@@ -127,7 +127,7 @@ async fn post_clear_nodes(
     State(state): State<AppState>,
     Extension(headers): Extension<HeaderMap>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "cache").await {
         return resp;
     }
 
@@ -8,10 +8,11 @@ use serde_json::{json, Value};
 use sqlx::Row;
 
 use crate::crypto::random_string;
+use crate::money::Money;
 use crate::response::{error, success};
 use crate::state::AppState;
 
-use super::super::auth::require_admin_user_id;
+use super::super::auth::require_admin_permission;
 
 #[derive(Deserialize)]
 struct CouponQuery {
@@ -54,7 +55,7 @@ async fn get_coupons(
     Extension(headers): Extension<HeaderMap>,
     Query(query): Query<CouponQuery>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "coupons").await {
         return resp;
     }
 
@@ -159,7 +160,7 @@ async fn get_coupon_detail(
     Extension(headers): Extension<HeaderMap>,
     Path(coupon_id): Path<i64>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "coupons").await {
         return resp;
     }
     if coupon_id <= 0 {
@@ -204,7 +205,7 @@ async fn get_coupon_stats(
     State(state): State<AppState>,
     Extension(headers): Extension<HeaderMap>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "coupons").await {
         return resp;
     }
 
@@ -319,7 +320,7 @@ async fn post_coupon(
     Extension(headers): Extension<HeaderMap>,
     Json(body): Json<CouponPayload>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "coupons").await {
         return resp;
     }
 
@@ -332,13 +333,14 @@ async fn post_coupon(
         Some("percentage") => "percentage",
         _ => "amount",
     };
-    let mut discount_value = body.discount_value.unwrap_or(0.0);
-    if discount_value <= 0.0 {
+    let discount_value = body
+        .discount_value
+        .and_then(Money::from_f64)
+        .unwrap_or_default();
+    if !discount_value.is_positive() {
         return error(StatusCode::BAD_REQUEST, "优惠值必须大于0", None);
     }
-    if discount_type == "amount" {
-        discount_value = fix_money_precision(discount_value);
-    } else if discount_value > 100.0 {
+    if discount_type == "percentage" && discount_value > Money::from(100) {
         return error(StatusCode::BAD_REQUEST, "折扣比例不能大于100%", None);
     }
 
@@ -394,7 +396,7 @@ async fn post_coupon(
   .bind(&name)
   .bind(&code_raw)
   .bind(discount_type)
-  .bind(discount_value)
+  .bind(&discount_value)
   .bind(start_at)
   .bind(end_at)
   .bind(max_usage)
@@ -444,7 +446,7 @@ async fn put_coupon(
     Path(coupon_id): Path<i64>,
     Json(body): Json<CouponPayload>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "coupons").await {
         return resp;
     }
     if coupon_id <= 0 {
@@ -481,7 +483,7 @@ async fn put_coupon(
         .ok()
         .flatten()
         .unwrap_or_else(|| "amount".to_string());
-    let mut discount_value = parse_decimal(&existing, "discount_value", 0.0);
+    let mut discount_value = Money::from_row_or_zero(&existing, "discount_value");
 
     let discount_type_input = body.discount_type.clone();
     if let Some(value) = discount_type_input.as_deref() {
@@ -492,21 +494,19 @@ async fn put_coupon(
         };
     }
     if let Some(value) = body.discount_value {
-        discount_value = value;
+        discount_value = Money::from_f64(value).unwrap_or_default();
     }
     if discount_type_input.is_some() || body.discount_value.is_some() {
-        if discount_value <= 0.0 {
+        if !discount_value.is_positive() {
             return error(StatusCode::BAD_REQUEST, "优惠值必须大于0", None);
         }
-        if discount_type == "amount" {
-            discount_value = fix_money_precision(discount_value);
-        } else if discount_value > 100.0 {
+        if discount_type == "percentage" && discount_value > Money::from(100) {
             return error(StatusCode::BAD_REQUEST, "折扣比例不能大于100%", None);
         }
         update_fields.push("discount_type = ?".to_string());
         params.push(SqlParam::String(discount_type.clone()));
         update_fields.push("discount_value = ?".to_string());
-        params.push(SqlParam::F64(discount_value));
+        params.push(SqlParam::Money(discount_value));
     }
 
     let mut start_at = existing
@@ -630,7 +630,7 @@ async fn delete_coupon(
     Extension(headers): Extension<HeaderMap>,
     Path(coupon_id): Path<i64>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "coupons").await {
         return resp;
     }
     if coupon_id <= 0 {
@@ -647,7 +647,7 @@ async fn delete_coupon(
 }
 
 fn map_coupon_row(row: &sqlx::mysql::MySqlRow) -> Value {
-    let discount_value = parse_decimal(row, "discount_value", 0.0);
+    let discount_value = Money::from_row_or_zero(row, "discount_value");
     let max_usage = row.try_get::<Option<i64>, _>("max_usage").ok().flatten();
     let per_user_limit = row
         .try_get::<Option<i64>, _>("per_user_limit")
@@ -788,32 +788,11 @@ fn normalize_limit(value: Option<i64>, field: &str) -> Result<Option<i64>, Strin
     }
 }
 
-fn fix_money_precision(amount: f64) -> f64 {
-    (amount * 100.0).round() / 100.0
-}
-
-fn parse_decimal(row: &sqlx::mysql::MySqlRow, column: &str, fallback: f64) -> f64 {
-    if let Ok(value) = row.try_get::<Option<f64>, _>(column) {
-        return value.unwrap_or(fallback);
-    }
-    if let Ok(value) = row.try_get::<Option<sqlx::types::BigDecimal>, _>(column) {
-        return value
-            .map(|val| val.to_string().parse::<f64>().unwrap_or(fallback))
-            .unwrap_or(fallback);
-    }
-    if let Ok(value) = row.try_get::<Option<String>, _>(column) {
-        return value
-            .and_then(|val| val.parse::<f64>().ok())
-            .unwrap_or(fallback);
-    }
-    fallback
-}
-
 type SqlxQuery<'a> = sqlx::query::Query<'a, sqlx::MySql, sqlx::mysql::MySqlArguments>;
 
 enum SqlParam {
     I64(i64),
-    F64(f64),
+    Money(Money),
     String(String),
     NullableI64(Option<i64>),
     NullableString(Option<String>),
@@ -823,7 +802,7 @@ fn bind_params<'a>(mut query: SqlxQuery<'a>, params: &'a [SqlParam]) -> SqlxQuer
     for param in params {
         query = match param {
             SqlParam::I64(value) => query.bind(*value),
-            SqlParam::F64(value) => query.bind(*value),
+            SqlParam::Money(value) => query.bind(value),
             SqlParam::String(value) => query.bind(value),
             SqlParam::NullableI64(value) => query.bind(value),
             SqlParam::NullableString(value) => query.bind(value),
@@ -11,7 +11,7 @@ use sqlx::Row;
 use crate::response::{error, success};
 use crate::state::AppState;
 
-use super::super::auth::require_admin_user_id;
+use super::super::auth::require_admin_permission;
 
 #[derive(Deserialize)]
 struct GiftCardBatchQuery {
@@ -35,7 +35,7 @@ async fn get_gift_card_batches(
     Extension(headers): Extension<HeaderMap>,
     Query(query): Query<GiftCardBatchQuery>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "gift-cards").await {
         return resp;
     }
 
@@ -140,7 +140,7 @@ async fn export_batch_csv(
     Extension(headers): Extension<HeaderMap>,
     Path(batch_id): Path<i64>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "gift-cards").await {
         return resp;
     }
     if batch_id <= 0 {
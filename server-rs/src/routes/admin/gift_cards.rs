@@ -8,10 +8,11 @@ use serde::Deserialize;
 use serde_json::{json, Value};
 use sqlx::Row;
 
+use crate::admin_actions::{record_admin_action, AdminAction};
 use crate::response::{error, success};
 use crate::state::AppState;
 
-use super::super::auth::require_admin_user_id;
+use super::super::auth::require_admin_permission;
 
 #[derive(Deserialize)]
 struct GiftCardQuery {
@@ -66,7 +67,7 @@ async fn get_gift_cards(
     Extension(headers): Extension<HeaderMap>,
     Query(query): Query<GiftCardQuery>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "gift-cards").await {
         return resp;
     }
 
@@ -169,7 +170,7 @@ async fn post_gift_cards(
     Extension(headers): Extension<HeaderMap>,
     Json(body): Json<GiftCardPayload>,
 ) -> Response {
-    let admin_id = match require_admin_user_id(&state, &headers, None).await {
+    let admin_id = match require_admin_permission(&state, &headers, "gift-cards").await {
         Ok(value) => value,
         Err(resp) => return resp,
     };
@@ -359,6 +360,33 @@ async fn post_gift_cards(
     }));
     }
 
+    // 卡密本身不写入审计日志
+    record_admin_action(
+        &state,
+        &headers,
+        admin_id,
+        AdminAction {
+            action: "gift_card.generate",
+            route: "POST /api/admin/gift-cards",
+            target_type: "gift_card_batch",
+            target_id: Some(batch_id.to_string()),
+            before: None,
+            after: Some(json!({
+              "name": name,
+              "card_type": card_type,
+              "quantity": cards.len(),
+              "balance_amount": balance_amount,
+              "duration_days": duration_days,
+              "traffic_value_gb": traffic_value_gb,
+              "package_id": body.package_id,
+              "max_usage": max_usage,
+              "per_user_limit": per_user_limit,
+              "start_at": start_at,
+              "end_at": end_at
+            })),
+        },
+    )
+    .await;
     success(json!({ "batch_id": batch_id, "cards": cards }), "Success").into_response()
 }
 
@@ -368,7 +396,7 @@ async fn put_gift_card(
     Path(card_id): Path<i64>,
     Json(body): Json<GiftCardPayload>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "gift-cards").await {
         return resp;
     }
     if card_id <= 0 {
@@ -496,7 +524,7 @@ async fn post_gift_card_status(
     Path(card_id): Path<i64>,
     Json(body): Json<StatusRequest>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "gift-cards").await {
         return resp;
     }
     if card_id <= 0 {
@@ -531,7 +559,7 @@ async fn delete_gift_card(
     Extension(headers): Extension<HeaderMap>,
     Path(card_id): Path<i64>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "gift-cards").await {
         return resp;
     }
     if card_id <= 0 {
@@ -575,7 +603,7 @@ async fn get_redemptions(
     Path(card_id): Path<i64>,
     Query(query): Query<PaginationQuery>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "gift-cards").await {
         return resp;
     }
     if card_id <= 0 {
@@ -11,7 +11,7 @@ use sqlx::Row;
 use crate::response::{error, success};
 use crate::state::AppState;
 
-use super::super::auth::require_admin_user_id;
+use super::super::auth::require_admin_permission;
 
 #[derive(Deserialize)]
 struct LoginLogsQuery {
@@ -45,7 +45,7 @@ async fn get_login_logs(
     Extension(headers): Extension<HeaderMap>,
     Query(query): Query<LoginLogsQuery>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "login-logs").await {
         return resp;
     }
 
@@ -177,7 +177,7 @@ async fn delete_login_log(
     Extension(headers): Extension<HeaderMap>,
     Path(id): Path<i64>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "login-logs").await {
         return resp;
     }
     if id <= 0 {
@@ -198,7 +198,7 @@ async fn post_batch_delete(
     Extension(headers): Extension<HeaderMap>,
     Json(body): Json<BatchIdsRequest>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "login-logs").await {
         return resp;
     }
 
@@ -230,7 +230,7 @@ async fn post_export_csv(
     Extension(headers): Extension<HeaderMap>,
     Json(body): Json<BatchIdsRequest>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "login-logs").await {
         return resp;
     }
 
@@ -12,7 +12,7 @@ use crate::crypto::{generate_uuid, random_base64, random_string};
 use crate::response::{error, success};
 use crate::state::AppState;
 
-use super::super::auth::require_admin_user_id;
+use super::super::auth::require_admin_permission;
 
 pub fn router() -> Router<AppState> {
     Router::new()
@@ -35,7 +35,7 @@ async fn post_reset_daily_traffic(
     State(state): State<AppState>,
     Extension(headers): Extension<HeaderMap>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "maintenance").await {
         return resp;
     }
 
@@ -67,7 +67,7 @@ async fn post_trigger_traffic_reset(
     State(state): State<AppState>,
     Extension(headers): Extension<HeaderMap>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "maintenance").await {
         return resp;
     }
 
@@ -89,7 +89,7 @@ async fn post_reset_all_passwords(
     State(state): State<AppState>,
     Extension(headers): Extension<HeaderMap>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "maintenance").await {
         return resp;
     }
 
@@ -134,7 +134,7 @@ async fn post_reset_all_subscriptions(
     State(state): State<AppState>,
     Extension(headers): Extension<HeaderMap>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "maintenance").await {
         return resp;
     }
 
@@ -163,6 +163,15 @@ async fn post_reset_all_subscriptions(
             count += 1;
         }
     }
+    // 用户自建的多设备 token 一并撤销
+    if let Err(err) = sqlx::query(
+        "UPDATE subscription_tokens SET revoked_at = CURRENT_TIMESTAMP WHERE revoked_at IS NULL",
+    )
+    .execute(&state.db)
+    .await
+    {
+        return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None);
+    }
 
     success(
         json!({ "count": count, "message": format!("已重置 {} 个用户的订阅链接", count) }),
@@ -175,7 +184,7 @@ async fn post_reset_invite_codes(
     State(state): State<AppState>,
     Extension(headers): Extension<HeaderMap>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "maintenance").await {
         return resp;
     }
 
@@ -218,7 +227,7 @@ async fn delete_pending_records(
     State(state): State<AppState>,
     Extension(headers): Extension<HeaderMap>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "maintenance").await {
         return resp;
     }
 
@@ -243,7 +252,7 @@ async fn post_generate_traffic_test_data(
     Extension(headers): Extension<HeaderMap>,
     Json(body): Json<TrafficTestRequest>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "maintenance").await {
         return resp;
     }
 
@@ -1,5 +1,7 @@
+mod action_logs;
 mod announcements;
 mod audit;
+mod blocked_ips;
 mod cache;
 mod coupons;
 mod gift_card_batches;
@@ -9,15 +11,21 @@ mod maintenance;
 mod nodes;
 mod online_ips;
 mod packages;
+mod payment_callbacks;
+mod proxy_groups;
 mod purchase_records;
 mod rebate;
 mod recharge_records;
+mod roles;
 mod shared_ids;
 mod subscription_logs;
+mod subscription_templates;
+mod subscription_ua_rules;
 mod system_configs;
 mod task;
 mod tickets;
 mod traffic;
+mod traffic_anomalies;
 mod users;
 mod whitelist;
 mod xray_rules;
@@ -35,7 +43,7 @@ use sqlx::Row;
 use crate::response::{error, success};
 use crate::state::AppState;
 
-use super::auth::require_admin_user_id;
+use super::auth::{require_admin_permission, require_admin_user_id};
 
 pub fn router() -> Router<AppState> {
     Router::new()
@@ -58,15 +66,23 @@ pub fn router() -> Router<AppState> {
         .nest("/rebate", rebate::router())
         .nest("/login-logs", login_logs::router())
         .nest("/subscription-logs", subscription_logs::router())
+        .nest("/action-logs", action_logs::router())
+        .nest("/roles", roles::router())
         .merge(audit::router())
         .merge(xray_rules::router())
         .merge(whitelist::router())
         .merge(online_ips::router())
+        .merge(blocked_ips::router())
+        .merge(subscription_ua_rules::router())
+        .merge(subscription_templates::router())
+        .merge(proxy_groups::router())
         .merge(cache::router())
         .merge(maintenance::router())
         .merge(packages::stats_router())
         .merge(recharge_records::router())
         .merge(purchase_records::router())
+        .merge(payment_callbacks::router())
+        .merge(traffic_anomalies::router())
 }
 
 async fn get_system_stats(
@@ -242,7 +258,7 @@ async fn get_node_status(
     Extension(headers): Extension<axum::http::HeaderMap>,
     Query(query): Query<NodeStatusQuery>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "nodes").await {
         return resp;
     }
 
@@ -323,6 +339,12 @@ async fn get_node_status(
       n.node_config,
       n.created_at,
       n.updated_at,
+      n.traffic_anomaly_count,
+      (
+        SELECT COUNT(*)
+        FROM traffic_anomalies ta
+        WHERE ta.node_id = n.id AND ta.status = 'pending'
+      ) AS pending_anomalies,
       CAST(ns.cpu_usage AS DOUBLE) AS cpu_usage,
       ns.memory_total,
       ns.memory_used,
@@ -399,7 +421,9 @@ async fn get_node_status(
         "disk_used": row.try_get::<Option<i64>, _>("disk_used").unwrap_or(Some(0)).unwrap_or(0),
         "uptime": row.try_get::<Option<i64>, _>("uptime").unwrap_or(Some(0)).unwrap_or(0),
         "last_reported": last_reported_text,
-        "is_online": is_online
+        "is_online": is_online,
+        "anomaly_count": row.try_get::<Option<i64>, _>("traffic_anomaly_count").unwrap_or(Some(0)).unwrap_or(0),
+        "pending_anomalies": row.try_get::<Option<i64>, _>("pending_anomalies").unwrap_or(Some(0)).unwrap_or(0)
       })
     })
     .collect::<Vec<Value>>();
@@ -8,11 +8,15 @@ use serde::Deserialize;
 use serde_json::{json, Value};
 use sqlx::Row;
 
+use crate::admin_actions::{record_admin_action, AdminAction};
 use crate::cache::cache_delete_by_prefix;
+use crate::crypto::{random_string, sha256_hex};
+use crate::node_bandwidth::reset_node_bandwidth;
+use crate::proxy_groups::{normalize_node_tags, parse_node_tags};
 use crate::response::{error, success};
 use crate::state::AppState;
 
-use super::super::auth::require_admin_user_id;
+use super::super::auth::require_admin_permission;
 
 #[derive(Deserialize)]
 struct NodesQuery {
@@ -41,6 +45,7 @@ struct CreateNodeRequest {
     ech_key: Option<String>,
     ech_config: Option<String>,
     xray_rule_ids: Option<Value>,
+    tags: Option<Value>,
 }
 
 #[derive(Deserialize)]
@@ -55,6 +60,7 @@ struct UpdateNodeRequest {
     node_config: Option<Value>,
     status: Option<i64>,
     xray_rule_ids: Option<Value>,
+    tags: Option<Value>,
 }
 
 #[derive(Deserialize)]
@@ -68,6 +74,15 @@ struct StatusRequest {
     status: Option<i64>,
 }
 
+#[derive(Deserialize)]
+struct RotateApiKeyRequest {
+    grace_minutes: Option<i64>,
+}
+
+const NODE_API_KEY_LENGTH: usize = 48;
+const DEFAULT_API_KEY_GRACE_MINUTES: i64 = 24 * 60;
+const MAX_API_KEY_GRACE_MINUTES: i64 = 30 * 24 * 60;
+
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/", get(get_nodes))
@@ -77,7 +92,10 @@ pub fn router() -> Router<AppState> {
         .route("/{id}", put(put_node))
         .route("/{id}", delete(delete_node))
         .route("/{id}/traffic", post(post_node_traffic))
+        .route("/{id}/traffic-history", get(get_node_traffic_history))
         .route("/{id}/status", post(post_node_status))
+        .route("/{id}/api-key", post(post_rotate_api_key))
+        .route("/{id}/api-key", delete(delete_api_key))
 }
 
 async fn get_nodes(
@@ -85,7 +103,7 @@ async fn get_nodes(
     Extension(headers): Extension<HeaderMap>,
     Query(query): Query<NodesQuery>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "nodes").await {
         return resp;
     }
 
@@ -129,9 +147,14 @@ async fn get_nodes(
       node_bandwidth_limit,
       CAST(traffic_multiplier AS DOUBLE) AS traffic_multiplier,
       bandwidthlimit_resetday,
+      bandwidth_period_start,
       CAST(node_config AS CHAR) AS node_config,
       CAST(xray_rule_ids AS CHAR) AS xray_rule_ids,
+      CAST(tags AS CHAR) AS tags,
       status,
+      api_key_hash,
+      api_key_rotated_at,
+      api_key_prev_expires_at,
       created_at,
       updated_at
     FROM nodes
@@ -183,9 +206,10 @@ async fn post_node(
     Extension(headers): Extension<HeaderMap>,
     Json(body): Json<CreateNodeRequest>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
-        return resp;
-    }
+    let admin_id = match require_admin_permission(&state, &headers, "nodes").await {
+        Ok(value) => value,
+        Err(resp) => return resp,
+    };
 
     if body.name.trim().is_empty() || body.node_type.trim().is_empty() {
         return error(StatusCode::BAD_REQUEST, "Name and type are required", None);
@@ -213,13 +237,19 @@ async fn post_node(
     }
     let xray_rule_ids_json =
         serde_json::to_string(&xray_rule_ids).unwrap_or_else(|_| "[]".to_string());
+    let tags = match normalize_node_tags(body.tags.as_ref().unwrap_or(&Value::Null)) {
+        Ok(value) => value,
+        Err(message) => return error(StatusCode::BAD_REQUEST, &message, None),
+    };
+
+    let api_key = random_string(NODE_API_KEY_LENGTH);
 
     let result = sqlx::query(
     r#"
     INSERT INTO nodes
-      (name, type, node_class, node_bandwidth_limit, traffic_multiplier, bandwidthlimit_resetday, node_config, xray_rule_ids, status)
+      (name, type, node_class, node_bandwidth_limit, traffic_multiplier, bandwidthlimit_resetday, node_config, xray_rule_ids, tags, status, api_key_hash, api_key_rotated_at)
     VALUES
-      (?, ?, ?, ?, ?, ?, ?, ?, ?)
+      (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP)
     "#
   )
   .bind(body.name.trim())
@@ -230,18 +260,35 @@ async fn post_node(
   .bind(reset_day)
   .bind(node_config)
   .bind(xray_rule_ids_json)
+  .bind(serde_json::to_string(&tags).unwrap_or_else(|_| "[]".to_string()))
   .bind(status)
+  .bind(sha256_hex(&api_key))
   .execute(&state.db)
   .await;
 
-    if let Err(err) = result {
-        return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None);
-    }
+    let node_id = match result {
+        Ok(outcome) => outcome.last_insert_id() as i64,
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    };
 
     cache_delete_by_prefix(&state, "node_config_").await;
     cache_delete_by_prefix(&state, "xray_rules_").await;
 
-    success(Value::Null, "节点已创建").into_response()
+    record_admin_action(
+        &state,
+        &headers,
+        admin_id,
+        AdminAction {
+            action: "node.create",
+            route: "POST /api/admin/nodes",
+            target_type: "node",
+            target_id: Some(node_id.to_string()),
+            before: None,
+            after: load_node_snapshot(&state, node_id).await,
+        },
+    )
+    .await;
+    success(json!({ "id": node_id, "api_key": api_key }), "节点已创建").into_response()
 }
 
 async fn put_node(
@@ -250,9 +297,10 @@ async fn put_node(
     Path(node_id): Path<i64>,
     Json(body): Json<UpdateNodeRequest>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
-        return resp;
-    }
+    let admin_id = match require_admin_permission(&state, &headers, "nodes").await {
+        Ok(value) => value,
+        Err(resp) => return resp,
+    };
     if node_id <= 0 {
         return error(StatusCode::BAD_REQUEST, "ID 无效", None);
     }
@@ -305,11 +353,22 @@ async fn put_node(
             serde_json::to_string(&xray_rule_ids).unwrap_or_else(|_| "[]".to_string()),
         ));
     }
+    if let Some(value) = body.tags {
+        let tags = match normalize_node_tags(&value) {
+            Ok(value) => value,
+            Err(message) => return error(StatusCode::BAD_REQUEST, &message, None),
+        };
+        updates.push("tags = ?".to_string());
+        params.push(SqlParam::String(
+            serde_json::to_string(&tags).unwrap_or_else(|_| "[]".to_string()),
+        ));
+    }
 
     if updates.is_empty() {
         return error(StatusCode::BAD_REQUEST, "没有需要更新的字段", None);
     }
 
+    let before = load_node_snapshot(&state, node_id).await;
     let sql = format!(
         "UPDATE nodes SET {}, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
         updates.join(", ")
@@ -329,6 +388,20 @@ async fn put_node(
     cache_delete_by_prefix(&state, "node_config_").await;
     cache_delete_by_prefix(&state, "xray_rules_").await;
 
+    record_admin_action(
+        &state,
+        &headers,
+        admin_id,
+        AdminAction {
+            action: "node.update",
+            route: "PUT /api/admin/nodes/{id}",
+            target_type: "node",
+            target_id: Some(node_id.to_string()),
+            before,
+            after: load_node_snapshot(&state, node_id).await,
+        },
+    )
+    .await;
     success(Value::Null, "节点已更新").into_response()
 }
 
@@ -337,24 +410,34 @@ async fn post_node_traffic(
     Extension(headers): Extension<HeaderMap>,
     Path(node_id): Path<i64>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
-        return resp;
-    }
+    let admin_id = match require_admin_permission(&state, &headers, "nodes").await {
+        Ok(value) => value,
+        Err(resp) => return resp,
+    };
     if node_id <= 0 {
         return error(StatusCode::BAD_REQUEST, "ID 无效", None);
     }
 
-    let result = sqlx::query(
-        "UPDATE nodes SET node_bandwidth = 0, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+    match reset_node_bandwidth(&state, node_id, "manual", None).await {
+        Ok(true) => {}
+        Ok(false) => return error(StatusCode::NOT_FOUND, "节点不存在", None),
+        Err(message) => return error(StatusCode::INTERNAL_SERVER_ERROR, &message, None),
+    }
+    record_admin_action(
+        &state,
+        &headers,
+        admin_id,
+        AdminAction {
+            action: "node.traffic_reset",
+            route: "POST /api/admin/nodes/{id}/traffic",
+            target_type: "node",
+            target_id: Some(node_id.to_string()),
+            before: None,
+            after: None,
+        },
     )
-    .bind(node_id)
-    .execute(&state.db)
     .await;
-    if let Err(err) = result {
-        return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None);
-    }
 
-    cache_delete_by_prefix(&state, &format!("node_config_{node_id}")).await;
     success(
         json!({ "message": "Node traffic reset successfully" }),
         "Success",
@@ -362,11 +445,71 @@ async fn post_node_traffic(
     .into_response()
 }
 
+#[derive(Deserialize)]
+struct TrafficHistoryQuery {
+    limit: Option<i64>,
+}
+
+async fn get_node_traffic_history(
+    State(state): State<AppState>,
+    Extension(headers): Extension<HeaderMap>,
+    Path(node_id): Path<i64>,
+    Query(query): Query<TrafficHistoryQuery>,
+) -> Response {
+    if let Err(resp) = require_admin_permission(&state, &headers, "nodes").await {
+        return resp;
+    }
+    if node_id <= 0 {
+        return error(StatusCode::BAD_REQUEST, "ID 无效", None);
+    }
+    let limit = query.limit.unwrap_or(12).clamp(1, 120);
+
+    let rows = sqlx::query(
+        r#"
+    SELECT id, period_start, period_end, used_traffic, bandwidth_limit, reset_type
+    FROM node_traffic_history
+    WHERE node_id = ?
+    ORDER BY period_end DESC, id DESC
+    LIMIT ?
+    "#,
+    )
+    .bind(node_id)
+    .bind(limit)
+    .fetch_all(&state.db)
+    .await;
+    let rows = match rows {
+        Ok(value) => value,
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    };
+
+    let records = rows
+        .into_iter()
+        .map(|row| {
+            let read_time = |key: &str| {
+                row.try_get::<Option<chrono::NaiveDateTime>, _>(key)
+                    .ok()
+                    .flatten()
+                    .map(format_datetime)
+            };
+            json!({
+              "id": row.try_get::<i64, _>("id").unwrap_or(0),
+              "period_start": read_time("period_start"),
+              "period_end": read_time("period_end"),
+              "used_traffic": row.try_get::<Option<i64>, _>("used_traffic").ok().flatten().unwrap_or(0),
+              "bandwidth_limit": row.try_get::<Option<i64>, _>("bandwidth_limit").ok().flatten().unwrap_or(0),
+              "reset_type": row.try_get::<Option<String>, _>("reset_type").ok().flatten().unwrap_or_default()
+            })
+        })
+        .collect::<Vec<Value>>();
+
+    success(json!({ "node_id": node_id, "records": records }), "Success").into_response()
+}
+
 async fn export_nodes(
     State(state): State<AppState>,
     Extension(headers): Extension<HeaderMap>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "nodes").await {
         return resp;
     }
 
@@ -503,13 +646,15 @@ async fn delete_node(
     Extension(headers): Extension<HeaderMap>,
     Path(node_id): Path<i64>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
-        return resp;
-    }
+    let admin_id = match require_admin_permission(&state, &headers, "nodes").await {
+        Ok(value) => value,
+        Err(resp) => return resp,
+    };
     if node_id <= 0 {
         return error(StatusCode::BAD_REQUEST, "ID 无效", None);
     }
 
+    let before = load_node_snapshot(&state, node_id).await;
     let result = sqlx::query("DELETE FROM nodes WHERE id = ?")
         .bind(node_id)
         .execute(&state.db)
@@ -526,6 +671,20 @@ async fn delete_node(
     cache_delete_by_prefix(&state, "node_config_").await;
     cache_delete_by_prefix(&state, "xray_rules_").await;
 
+    record_admin_action(
+        &state,
+        &headers,
+        admin_id,
+        AdminAction {
+            action: "node.delete",
+            route: "DELETE /api/admin/nodes/{id}",
+            target_type: "node",
+            target_id: Some(node_id.to_string()),
+            before,
+            after: None,
+        },
+    )
+    .await;
     success(Value::Null, "节点已删除").into_response()
 }
 
@@ -534,9 +693,10 @@ async fn post_batch(
     Extension(headers): Extension<HeaderMap>,
     Json(body): Json<BatchRequest>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
-        return resp;
-    }
+    let admin_id = match require_admin_permission(&state, &headers, "nodes").await {
+        Ok(value) => value,
+        Err(resp) => return resp,
+    };
 
     if body.action.trim().is_empty() || body.node_ids.is_empty() {
         return error(StatusCode::BAD_REQUEST, "action 和 node_ids 必填", None);
@@ -577,6 +737,25 @@ async fn post_batch(
         Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
     };
 
+    record_admin_action(
+        &state,
+        &headers,
+        admin_id,
+        AdminAction {
+            action: "node.batch",
+            route: "POST /api/admin/nodes/batch",
+            target_type: "node",
+            target_id: Some(
+                ids.iter()
+                    .map(|id| id.to_string())
+                    .collect::<Vec<String>>()
+                    .join(","),
+            ),
+            before: None,
+            after: Some(json!({ "action": body.action, "node_ids": ids })),
+        },
+    )
+    .await;
     success(
         json!({
           "message": message,
@@ -594,9 +773,10 @@ async fn post_node_status(
     Path(node_id): Path<i64>,
     Json(body): Json<StatusRequest>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
-        return resp;
-    }
+    let admin_id = match require_admin_permission(&state, &headers, "nodes").await {
+        Ok(value) => value,
+        Err(resp) => return resp,
+    };
     if node_id <= 0 {
         return error(StatusCode::BAD_REQUEST, "ID 无效", None);
     }
@@ -605,6 +785,7 @@ async fn post_node_status(
         return error(StatusCode::BAD_REQUEST, "状态无效", None);
     }
 
+    let before = load_node_snapshot(&state, node_id).await;
     if let Err(err) =
         sqlx::query("UPDATE nodes SET status = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
             .bind(status)
@@ -614,10 +795,213 @@ async fn post_node_status(
     {
         return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None);
     }
+    record_admin_action(
+        &state,
+        &headers,
+        admin_id,
+        AdminAction {
+            action: "node.status",
+            route: "POST /api/admin/nodes/{id}/status",
+            target_type: "node",
+            target_id: Some(node_id.to_string()),
+            before,
+            after: load_node_snapshot(&state, node_id).await,
+        },
+    )
+    .await;
 
     success(Value::Null, "状态已更新").into_response()
 }
 
+async fn post_rotate_api_key(
+    State(state): State<AppState>,
+    Extension(headers): Extension<HeaderMap>,
+    Path(node_id): Path<i64>,
+    body: Option<Json<RotateApiKeyRequest>>,
+) -> Response {
+    let admin_id = match require_admin_permission(&state, &headers, "nodes").await {
+        Ok(value) => value,
+        Err(resp) => return resp,
+    };
+    if node_id <= 0 {
+        return error(StatusCode::BAD_REQUEST, "ID 无效", None);
+    }
+    let grace_minutes = body
+        .and_then(|Json(value)| value.grace_minutes)
+        .unwrap_or(DEFAULT_API_KEY_GRACE_MINUTES)
+        .clamp(0, MAX_API_KEY_GRACE_MINUTES);
+
+    let row = match sqlx::query("SELECT api_key_hash FROM nodes WHERE id = ?")
+        .bind(node_id)
+        .fetch_optional(&state.db)
+        .await
+    {
+        Ok(Some(value)) => value,
+        Ok(None) => return error(StatusCode::NOT_FOUND, "节点不存在", None),
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    };
+    let previous_hash = row
+        .try_get::<Option<String>, _>("api_key_hash")
+        .ok()
+        .flatten()
+        .filter(|value| !value.is_empty() && grace_minutes > 0);
+
+    // 旧密钥在宽限期内继续有效，便于节点端无中断切换
+    let api_key = random_string(NODE_API_KEY_LENGTH);
+    let result = sqlx::query(
+        r#"
+    UPDATE nodes
+    SET api_key_hash = ?,
+        api_key_prev_hash = ?,
+        api_key_prev_expires_at = IF(? IS NULL, NULL, DATE_ADD(CURRENT_TIMESTAMP, INTERVAL ? MINUTE)),
+        api_key_rotated_at = CURRENT_TIMESTAMP,
+        updated_at = CURRENT_TIMESTAMP
+    WHERE id = ?
+    "#,
+    )
+    .bind(sha256_hex(&api_key))
+    .bind(previous_hash.as_deref())
+    .bind(previous_hash.as_deref())
+    .bind(grace_minutes)
+    .bind(node_id)
+    .execute(&state.db)
+    .await;
+    if let Err(err) = result {
+        return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None);
+    }
+
+    record_admin_action(
+        &state,
+        &headers,
+        admin_id,
+        AdminAction {
+            action: "node.api_key_rotate",
+            route: "POST /api/admin/nodes/{id}/api-key",
+            target_type: "node",
+            target_id: Some(node_id.to_string()),
+            before: None,
+            after: Some(json!({ "grace_minutes": grace_minutes })),
+        },
+    )
+    .await;
+
+    let previous_key_expires_at = if previous_hash.is_some() {
+        sqlx::query("SELECT api_key_prev_expires_at FROM nodes WHERE id = ?")
+            .bind(node_id)
+            .fetch_optional(&state.db)
+            .await
+            .ok()
+            .flatten()
+            .and_then(|row| {
+                row.try_get::<Option<chrono::NaiveDateTime>, _>("api_key_prev_expires_at")
+                    .ok()
+                    .flatten()
+            })
+            .map(format_datetime)
+    } else {
+        None
+    };
+
+    success(
+        json!({
+          "id": node_id,
+          "api_key": api_key,
+          "grace_minutes": grace_minutes,
+          "previous_key_expires_at": previous_key_expires_at
+        }),
+        "节点密钥已生成，请妥善保存（仅显示一次）",
+    )
+    .into_response()
+}
+
+async fn delete_api_key(
+    State(state): State<AppState>,
+    Extension(headers): Extension<HeaderMap>,
+    Path(node_id): Path<i64>,
+) -> Response {
+    let admin_id = match require_admin_permission(&state, &headers, "nodes").await {
+        Ok(value) => value,
+        Err(resp) => return resp,
+    };
+    if node_id <= 0 {
+        return error(StatusCode::BAD_REQUEST, "ID 无效", None);
+    }
+
+    let result = sqlx::query(
+        r#"
+    UPDATE nodes
+    SET api_key_hash = NULL,
+        api_key_prev_hash = NULL,
+        api_key_prev_expires_at = NULL,
+        api_key_rotated_at = NULL,
+        updated_at = CURRENT_TIMESTAMP
+    WHERE id = ?
+    "#,
+    )
+    .bind(node_id)
+    .execute(&state.db)
+    .await;
+    match result {
+        Ok(outcome) if outcome.rows_affected() == 0 => {
+            error(StatusCode::NOT_FOUND, "节点不存在", None)
+        }
+        Ok(_) => {
+            record_admin_action(
+                &state,
+                &headers,
+                admin_id,
+                AdminAction {
+                    action: "node.api_key_revoke",
+                    route: "DELETE /api/admin/nodes/{id}/api-key",
+                    target_type: "node",
+                    target_id: Some(node_id.to_string()),
+                    before: None,
+                    after: None,
+                },
+            )
+            .await;
+            success(Value::Null, "节点密钥已撤销").into_response()
+        }
+        Err(err) => error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    }
+}
+
+/// 审计日志用的节点快照（节点配置中的密钥字段在入库时脱敏）
+async fn load_node_snapshot(state: &AppState, node_id: i64) -> Option<Value> {
+    let row = sqlx::query(
+        r#"
+    SELECT name, type, node_class, node_bandwidth_limit,
+           CAST(traffic_multiplier AS DOUBLE) AS traffic_multiplier, bandwidthlimit_resetday,
+           node_config, xray_rule_ids, tags, status
+    FROM nodes
+    WHERE id = ?
+    "#,
+    )
+    .bind(node_id)
+    .fetch_optional(&state.db)
+    .await
+    .ok()??;
+    let read_i64 = |key: &str| row.try_get::<Option<i64>, _>(key).ok().flatten();
+    let read_string = |key: &str| row.try_get::<Option<String>, _>(key).ok().flatten();
+    let read_json = |key: &str| {
+        read_string(key)
+            .and_then(|raw| serde_json::from_str::<Value>(&raw).ok())
+            .unwrap_or(Value::Null)
+    };
+    Some(json!({
+      "name": read_string("name"),
+      "type": read_string("type"),
+      "node_class": read_i64("node_class"),
+      "node_bandwidth_limit": read_i64("node_bandwidth_limit"),
+      "traffic_multiplier": row.try_get::<Option<f64>, _>("traffic_multiplier").ok().flatten(),
+      "bandwidthlimit_resetday": read_i64("bandwidthlimit_resetday"),
+      "node_config": read_json("node_config"),
+      "xray_rule_ids": read_json("xray_rule_ids"),
+      "tags": read_json("tags"),
+      "status": read_i64("status")
+    }))
+}
+
 fn parse_optional_i64(value: Option<&str>) -> Option<i64> {
     value
         .map(|value| value.trim())
@@ -913,6 +1297,7 @@ fn map_node_row(row: sqlx::mysql::MySqlRow) -> Value {
         .try_get::<Option<String>, _>("xray_rule_ids")
         .ok()
         .flatten();
+    let raw_tags = row.try_get::<Option<String>, _>("tags").ok().flatten();
     let (client, config) = parse_node_config(raw_config.as_deref());
     let mut server = read_string(&client, "server");
     if server.is_empty() {
@@ -980,9 +1365,14 @@ fn map_node_row(row: sqlx::mysql::MySqlRow) -> Value {
       "node_bandwidth_limit": row.try_get::<Option<i64>, _>("node_bandwidth_limit").unwrap_or(Some(0)).unwrap_or(0),
       "traffic_multiplier": row.try_get::<Option<f64>, _>("traffic_multiplier").unwrap_or(Some(1.0)).unwrap_or(1.0),
       "bandwidthlimit_resetday": row.try_get::<Option<i64>, _>("bandwidthlimit_resetday").unwrap_or(Some(1)).unwrap_or(1),
+      "bandwidth_period_start": row.try_get::<Option<chrono::NaiveDateTime>, _>("bandwidth_period_start").ok().flatten().map(format_datetime),
       "node_config": normalized_config,
       "xray_rule_ids": parse_rule_ids(raw_rule_ids.as_deref()),
+      "tags": parse_node_tags(raw_tags.as_deref()),
       "status": row.try_get::<Option<i64>, _>("status").unwrap_or(Some(0)).unwrap_or(0),
+      "api_key_configured": row.try_get::<Option<String>, _>("api_key_hash").ok().flatten().is_some_and(|value| !value.is_empty()),
+      "api_key_rotated_at": row.try_get::<Option<chrono::NaiveDateTime>, _>("api_key_rotated_at").ok().flatten().map(format_datetime),
+      "api_key_previous_expires_at": row.try_get::<Option<chrono::NaiveDateTime>, _>("api_key_prev_expires_at").ok().flatten().map(format_datetime),
       "created_at": row.try_get::<Option<chrono::NaiveDateTime>, _>("created_at").ok().flatten().map(format_datetime),
       "updated_at": row.try_get::<Option<chrono::NaiveDateTime>, _>("updated_at").ok().flatten().map(format_datetime)
     })
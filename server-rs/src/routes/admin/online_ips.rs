@@ -3,15 +3,17 @@ use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
 use axum::response::{IntoResponse, Response};
 use axum::routing::{delete, get, post};
 use axum::{Extension, Json, Router};
-use chrono::NaiveDateTime;
+use chrono::{Duration, NaiveDateTime, Utc};
 use serde::Deserialize;
 use serde_json::{json, Value};
 use sqlx::Row;
 
+use crate::blocked_ips::{normalize_block_target, upsert_blocked_ip};
+use crate::node_hub::{notify_node, NodeEvent};
 use crate::response::{error, success};
 use crate::state::AppState;
 
-use super::super::auth::require_admin_user_id;
+use super::super::auth::require_admin_permission;
 
 #[derive(Deserialize)]
 struct OnlineIpsQuery {
@@ -33,6 +35,16 @@ struct OnlineIpsQuery {
     sort_by: Option<String>,
 }
 
+#[derive(Deserialize)]
+struct DeviceLimitViolationsQuery {
+    page: Option<i64>,
+    limit: Option<i64>,
+    #[serde(rename = "pageSize")]
+    page_size: Option<i64>,
+    user_id: Option<String>,
+    action: Option<String>,
+}
+
 #[derive(Deserialize)]
 struct BatchIdsRequest {
     ids: Option<Vec<i64>>,
@@ -52,6 +64,7 @@ pub fn router() -> Router<AppState> {
         .route("/online-ips/batch-delete", post(post_batch_delete))
         .route("/kick-ip", post(post_kick_compat))
         .route("/block-ip", post(post_block_ip))
+        .route("/device-limit-violations", get(get_device_limit_violations))
 }
 
 async fn get_online_ips(
@@ -59,7 +72,7 @@ async fn get_online_ips(
     Extension(headers): Extension<HeaderMap>,
     Query(query): Query<OnlineIpsQuery>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "online-ips").await {
         return resp;
     }
 
@@ -212,7 +225,7 @@ async fn post_export_csv(
     Extension(headers): Extension<HeaderMap>,
     Json(body): Json<BatchIdsRequest>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "online-ips").await {
         return resp;
     }
 
@@ -290,18 +303,14 @@ async fn post_kick(
     Extension(headers): Extension<HeaderMap>,
     Path(id): Path<i64>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "online-ips").await {
         return resp;
     }
     if id <= 0 {
         return error(StatusCode::BAD_REQUEST, "ID 无效", None);
     }
-    if let Err(err) = sqlx::query("DELETE FROM online_ips WHERE id = ?")
-        .bind(id)
-        .execute(&state.db)
-        .await
-    {
-        return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None);
+    if let Err(message) = kick_online_ip(&state, id).await {
+        return error(StatusCode::INTERNAL_SERVER_ERROR, &message, None);
     }
     success(Value::Null, "已踢出该 IP").into_response()
 }
@@ -311,21 +320,49 @@ async fn post_kick_compat(
     Extension(headers): Extension<HeaderMap>,
     Json(body): Json<KickRequest>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "online-ips").await {
         return resp;
     }
     let ip_id = body.ip_id.unwrap_or(0);
     if ip_id <= 0 {
         return error(StatusCode::BAD_REQUEST, "ip_id 必填", None);
     }
-    if let Err(err) = sqlx::query("DELETE FROM online_ips WHERE id = ?")
-        .bind(ip_id)
+    if let Err(message) = kick_online_ip(&state, ip_id).await {
+        return error(StatusCode::INTERNAL_SERVER_ERROR, &message, None);
+    }
+    success(json!({ "message": "IP踢出成功" }), "Success").into_response()
+}
+
+/// 删除在线 IP 记录，并通知所在节点断开该用户在此 IP 上的连接
+async fn kick_online_ip(state: &AppState, id: i64) -> Result<(), String> {
+    let row = sqlx::query("SELECT user_id, node_id, ip FROM online_ips WHERE id = ?")
+        .bind(id)
+        .fetch_optional(&state.db)
+        .await
+        .map_err(|err| err.to_string())?;
+    sqlx::query("DELETE FROM online_ips WHERE id = ?")
+        .bind(id)
         .execute(&state.db)
         .await
-    {
-        return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None);
+        .map_err(|err| err.to_string())?;
+
+    if let Some(row) = row {
+        let user_id = row
+            .try_get::<Option<i64>, _>("user_id")
+            .ok()
+            .flatten()
+            .unwrap_or(0);
+        let node_id = row
+            .try_get::<Option<i64>, _>("node_id")
+            .ok()
+            .flatten()
+            .unwrap_or(0);
+        let ip = row.try_get::<Option<String>, _>("ip").ok().flatten();
+        if user_id > 0 && node_id > 0 {
+            notify_node(state, node_id, NodeEvent::KickUser { user_id, ip }).await;
+        }
     }
-    success(json!({ "message": "IP踢出成功" }), "Success").into_response()
+    Ok(())
 }
 
 async fn delete_online_ip(
@@ -333,7 +370,7 @@ async fn delete_online_ip(
     Extension(headers): Extension<HeaderMap>,
     Path(id): Path<i64>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "online-ips").await {
         return resp;
     }
     if id <= 0 {
@@ -354,7 +391,7 @@ async fn post_batch_delete(
     Extension(headers): Extension<HeaderMap>,
     Json(body): Json<BatchIdsRequest>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "online-ips").await {
         return resp;
     }
     let ids = body.ids.unwrap_or_default();
@@ -383,20 +420,144 @@ async fn post_block_ip(
     Extension(headers): Extension<HeaderMap>,
     Json(body): Json<Value>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    let admin_id = match require_admin_permission(&state, &headers, "online-ips").await {
+        Ok(value) => value,
+        Err(resp) => return resp,
+    };
+    let Some(ip) =
+        normalize_block_target(body.get("ip").and_then(Value::as_str).unwrap_or_default())
+    else {
+        return error(StatusCode::BAD_REQUEST, "IP 地址无效", None);
+    };
+    let reason = body
+        .get("reason")
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|value| !value.is_empty());
+    // duration_minutes 缺省或为 0 表示永久封禁
+    let expires_at = body
+        .get("duration_minutes")
+        .and_then(Value::as_i64)
+        .filter(|value| *value > 0)
+        .map(|minutes| (Utc::now() + Duration::hours(8) + Duration::minutes(minutes)).naive_utc());
+
+    let id = match upsert_blocked_ip(&state, &ip, reason, expires_at, admin_id).await {
+        Ok(value) => value,
+        Err(message) => return error(StatusCode::INTERNAL_SERVER_ERROR, &message, None),
+    };
+    success(
+        json!({ "message": "IP 已封禁", "id": id, "ip": ip }),
+        "Success",
+    )
+    .into_response()
+}
+
+async fn get_device_limit_violations(
+    State(state): State<AppState>,
+    Extension(headers): Extension<HeaderMap>,
+    Query(query): Query<DeviceLimitViolationsQuery>,
+) -> Response {
+    if let Err(resp) = require_admin_permission(&state, &headers, "online-ips").await {
         return resp;
     }
-    let ip = body
-        .get("ip")
-        .and_then(Value::as_str)
-        .unwrap_or_default()
-        .trim()
-        .to_string();
-    if ip.is_empty() {
-        return error(StatusCode::BAD_REQUEST, "IP 地址无效", None);
+
+    let page = query.page.unwrap_or(1).max(1);
+    let limit_raw = query.limit.or(query.page_size).unwrap_or(20);
+    let limit = limit_raw.clamp(1, 200);
+    let offset = (page - 1) * limit;
+
+    let mut conditions: Vec<&str> = Vec::new();
+    let mut params: Vec<String> = Vec::new();
+    if let Some(user_id) = parse_optional_i64(query.user_id.as_deref()) {
+        conditions.push("v.user_id = ?");
+        params.push(user_id.to_string());
+    }
+    let action = query.action.unwrap_or_default().trim().to_string();
+    if !action.is_empty() {
+        conditions.push("v.action = ?");
+        params.push(action);
+    }
+    let where_clause = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    };
+
+    let total_sql =
+        format!("SELECT COUNT(*) as total FROM device_limit_violations v {where_clause}");
+    let mut total_query = sqlx::query(&total_sql);
+    for param in &params {
+        total_query = total_query.bind(param);
+    }
+    let total = match total_query.fetch_optional(&state.db).await {
+        Ok(row) => row
+            .and_then(|row| row.try_get::<Option<i64>, _>("total").ok().flatten())
+            .unwrap_or(0),
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    };
+
+    let list_sql = format!(
+        r#"
+    SELECT v.id, v.user_id, v.device_limit, v.ip_count, v.ips, v.action, v.kicked_ips,
+           v.suspended_until, v.released_at, v.notified, v.created_at,
+           u.username, u.email
+    FROM device_limit_violations v
+    LEFT JOIN users u ON u.id = v.user_id
+    {where_clause}
+    ORDER BY v.id DESC
+    LIMIT ? OFFSET ?
+    "#
+    );
+    let mut list_query = sqlx::query(&list_sql);
+    for param in &params {
+        list_query = list_query.bind(param);
     }
+    let rows = match list_query
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&state.db)
+        .await
+    {
+        Ok(value) => value,
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    };
+
+    let parse_list = |raw: Option<String>| {
+        raw.and_then(|value| serde_json::from_str::<Value>(&value).ok())
+            .unwrap_or_else(|| json!([]))
+    };
+    let items = rows
+        .into_iter()
+        .map(|row| {
+            json!({
+              "id": row.try_get::<i64, _>("id").unwrap_or(0),
+              "user_id": row.try_get::<i64, _>("user_id").unwrap_or(0),
+              "username": row.try_get::<Option<String>, _>("username").ok().flatten(),
+              "email": row.try_get::<Option<String>, _>("email").ok().flatten(),
+              "device_limit": row.try_get::<Option<i64>, _>("device_limit").ok().flatten().unwrap_or(0),
+              "ip_count": row.try_get::<Option<i64>, _>("ip_count").ok().flatten().unwrap_or(0),
+              "ips": parse_list(row.try_get::<Option<String>, _>("ips").ok().flatten()),
+              "action": row.try_get::<Option<String>, _>("action").ok().flatten().unwrap_or_default(),
+              "kicked_ips": parse_list(row.try_get::<Option<String>, _>("kicked_ips").ok().flatten()),
+              "suspended_until": format_datetime(row.try_get::<Option<NaiveDateTime>, _>("suspended_until").ok().flatten()),
+              "released_at": format_datetime(row.try_get::<Option<NaiveDateTime>, _>("released_at").ok().flatten()),
+              "notified": row.try_get::<Option<i64>, _>("notified").ok().flatten().unwrap_or(0) == 1,
+              "created_at": format_datetime(row.try_get::<Option<NaiveDateTime>, _>("created_at").ok().flatten())
+            })
+        })
+        .collect::<Vec<Value>>();
+
     success(
-        json!({ "message": "IP 封禁功能待实现", "ip": ip }),
+        json!({
+          "data": items,
+          "total": total,
+          "pagination": {
+            "total": total,
+            "page": page,
+            "limit": limit,
+            "pages": if total > 0 { ((total as f64) / (limit as f64)).ceil() as i64 } else { 0 }
+          }
+        }),
         "Success",
     )
     .into_response()
@@ -7,10 +7,12 @@ use serde::Deserialize;
 use serde_json::{json, Value};
 use sqlx::Row;
 
+use crate::money::Money;
+use crate::referral::parse_rebate_levels;
 use crate::response::{error, success};
 use crate::state::AppState;
 
-use super::super::auth::require_admin_user_id;
+use super::super::auth::require_admin_permission;
 
 #[derive(Deserialize)]
 struct PackageQuery {
@@ -34,6 +36,7 @@ struct PackagePayload {
     status: Option<i64>,
     is_recommended: Option<i64>,
     sort_weight: Option<i64>,
+    rebate_levels: Option<String>,
 }
 
 pub fn router() -> Router<AppState> {
@@ -53,7 +56,7 @@ async fn get_packages(
     Extension(headers): Extension<HeaderMap>,
     Query(query): Query<PackageQuery>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "packages").await {
         return resp;
     }
 
@@ -119,6 +122,7 @@ async fn get_packages(
       p.status,
       p.is_recommended,
       p.sort_weight,
+      p.rebate_levels,
       p.created_at,
       p.updated_at,
       (
@@ -169,15 +173,19 @@ async fn post_package(
     Extension(headers): Extension<HeaderMap>,
     Json(body): Json<PackagePayload>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "packages").await {
         return resp;
     }
 
     let name = body.name.unwrap_or_default().trim().to_string();
-    let price = body.price.unwrap_or(-1.0);
+    let price = body.price.and_then(Money::from_f64);
     let traffic_quota = body.traffic_quota.unwrap_or(-1);
     let validity_days = body.validity_days.unwrap_or(-1);
-    if name.is_empty() || price < 0.0 || traffic_quota < 0 || validity_days < 0 {
+    let price = match price {
+        Some(value) if value >= Money::zero() => value,
+        _ => return error(StatusCode::BAD_REQUEST, "必填字段缺失", None),
+    };
+    if name.is_empty() || traffic_quota < 0 || validity_days < 0 {
         return error(StatusCode::BAD_REQUEST, "必填字段缺失", None);
     }
 
@@ -187,16 +195,20 @@ async fn post_package(
     let status = body.status.unwrap_or(1);
     let is_recommended = body.is_recommended.unwrap_or(0);
     let sort_weight = body.sort_weight.unwrap_or(0);
+    let rebate_levels = match normalize_rebate_levels(body.rebate_levels.as_deref()) {
+        Ok(value) => value,
+        Err(message) => return error(StatusCode::BAD_REQUEST, &message, None),
+    };
 
     if let Err(err) = sqlx::query(
     r#"
     INSERT INTO packages
-      (name, price, traffic_quota, validity_days, speed_limit, device_limit, level, status, is_recommended, sort_weight)
-    VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+      (name, price, traffic_quota, validity_days, speed_limit, device_limit, level, status, is_recommended, sort_weight, rebate_levels)
+    VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
     "#
   )
   .bind(&name)
-  .bind(price)
+  .bind(&price)
   .bind(traffic_quota)
   .bind(validity_days)
   .bind(speed_limit)
@@ -205,6 +217,7 @@ async fn post_package(
   .bind(status)
   .bind(is_recommended)
   .bind(sort_weight)
+  .bind(rebate_levels)
   .execute(&state.db)
   .await
   {
@@ -220,7 +233,7 @@ async fn put_package(
     Path(package_id): Path<i64>,
     Json(body): Json<PackagePayload>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "packages").await {
         return resp;
     }
     if package_id <= 0 {
@@ -238,8 +251,11 @@ async fn put_package(
         }
     }
     if let Some(value) = body.price {
+        if value < 0.0 {
+            return error(StatusCode::BAD_REQUEST, "价格不能为负数", None);
+        }
         updates.push("price = ?".to_string());
-        params.push(SqlParam::F64(value));
+        params.push(SqlParam::Money(Money::from_f64(value).unwrap_or_default()));
     }
     if let Some(value) = body.traffic_quota {
         updates.push("traffic_quota = ?".to_string());
@@ -273,6 +289,15 @@ async fn put_package(
         updates.push("sort_weight = ?".to_string());
         params.push(SqlParam::I64(value));
     }
+    if body.rebate_levels.is_some() {
+        match normalize_rebate_levels(body.rebate_levels.as_deref()) {
+            Ok(value) => {
+                updates.push("rebate_levels = ?".to_string());
+                params.push(SqlParam::OptionalString(value));
+            }
+            Err(message) => return error(StatusCode::BAD_REQUEST, &message, None),
+        }
+    }
 
     if updates.is_empty() {
         return error(StatusCode::BAD_REQUEST, "没有需要更新的字段", None);
@@ -296,7 +321,7 @@ async fn delete_package(
     Extension(headers): Extension<HeaderMap>,
     Path(package_id): Path<i64>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "packages").await {
         return resp;
     }
     if package_id <= 0 {
@@ -318,7 +343,7 @@ async fn get_package_stats(
     State(state): State<AppState>,
     Extension(headers): Extension<HeaderMap>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "packages").await {
         return resp;
     }
 
@@ -482,6 +507,7 @@ fn map_package_row(row: &sqlx::mysql::MySqlRow) -> Value {
       "status": status,
       "is_recommended": is_recommended,
       "sort_weight": sort_weight,
+      "rebate_levels": row.try_get::<Option<String>, _>("rebate_levels").ok().flatten(),
       "sales_count": sales_count,
       "created_at": row.try_get::<Option<chrono::NaiveDateTime>, _>("created_at").ok().flatten().map(format_datetime),
       "updated_at": row.try_get::<Option<chrono::NaiveDateTime>, _>("updated_at").ok().flatten().map(format_datetime),
@@ -493,6 +519,18 @@ fn map_package_row(row: &sqlx::mysql::MySqlRow) -> Value {
     })
 }
 
+/// 校验套餐返利层级比例，空值表示使用系统配置
+fn normalize_rebate_levels(value: Option<&str>) -> Result<Option<String>, String> {
+    let raw = value.unwrap_or_default();
+    Ok(parse_rebate_levels(raw)?.map(|levels| {
+        levels
+            .iter()
+            .map(|rate| rate.normalized().to_string())
+            .collect::<Vec<String>>()
+            .join(",")
+    }))
+}
+
 fn format_datetime(value: chrono::NaiveDateTime) -> String {
     value.format("%Y-%m-%d %H:%M:%S").to_string()
 }
@@ -518,16 +556,18 @@ type SqlxQuery<'a> = sqlx::query::Query<'a, sqlx::MySql, sqlx::mysql::MySqlArgum
 
 enum SqlParam {
     I64(i64),
-    F64(f64),
+    Money(Money),
     String(String),
+    OptionalString(Option<String>),
 }
 
 fn bind_params<'a>(mut query: SqlxQuery<'a>, params: &'a [SqlParam]) -> SqlxQuery<'a> {
     for param in params {
         query = match param {
             SqlParam::I64(value) => query.bind(*value),
-            SqlParam::F64(value) => query.bind(*value),
+            SqlParam::Money(value) => query.bind(value),
             SqlParam::String(value) => query.bind(value),
+            SqlParam::OptionalString(value) => query.bind(value),
         };
     }
     query
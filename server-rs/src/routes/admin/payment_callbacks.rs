@@ -0,0 +1,311 @@
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Extension, Json, Router};
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sqlx::Row;
+
+use crate::admin_actions::{record_admin_action, AdminAction};
+use crate::money::Money;
+use crate::payment::{find_order_provider, query_payment, PaymentSettings};
+use crate::response::{error, success};
+use crate::state::AppState;
+
+use super::super::auth::require_admin_permission;
+use super::super::payment_callback::{settle_trade, SettleOutcome};
+
+#[derive(Deserialize)]
+struct PaymentCallbacksQuery {
+    page: Option<i64>,
+    limit: Option<i64>,
+    #[serde(rename = "pageSize")]
+    page_size: Option<i64>,
+    status: Option<String>,
+    provider: Option<String>,
+    review_status: Option<String>,
+    trade_no: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ReviewRequest {
+    action: Option<String>,
+    note: Option<String>,
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/payment-callbacks", get(get_payment_callbacks))
+        .route("/payment-callbacks/{id}/review", post(post_review_callback))
+        .route("/payment-orders/{tradeNo}/query", get(get_provider_order))
+}
+
+async fn get_payment_callbacks(
+    State(state): State<AppState>,
+    Extension(headers): Extension<HeaderMap>,
+    Query(query): Query<PaymentCallbacksQuery>,
+) -> Response {
+    if let Err(resp) = require_admin_permission(&state, &headers, "payment-callbacks").await {
+        return resp;
+    }
+
+    let page = query.page.unwrap_or(1).max(1);
+    let limit_raw = query.limit.or(query.page_size).unwrap_or(20);
+    let limit = limit_raw.clamp(1, 200);
+    let offset = (page - 1) * limit;
+
+    let mut conditions: Vec<&str> = Vec::new();
+    let mut params: Vec<String> = Vec::new();
+    for (column, value) in [
+        ("status = ?", query.status),
+        ("provider = ?", query.provider),
+        ("review_status = ?", query.review_status),
+        ("trade_no = ?", query.trade_no),
+    ] {
+        let value = value.unwrap_or_default().trim().to_string();
+        if !value.is_empty() {
+            conditions.push(column);
+            params.push(value);
+        }
+    }
+    let where_clause = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    };
+
+    let total_sql = format!("SELECT COUNT(*) as total FROM payment_callbacks {where_clause}");
+    let mut total_query = sqlx::query(&total_sql);
+    for param in &params {
+        total_query = total_query.bind(param);
+    }
+    let total = match total_query.fetch_optional(&state.db).await {
+        Ok(row) => row
+            .and_then(|row| row.try_get::<Option<i64>, _>("total").ok().flatten())
+            .unwrap_or(0),
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    };
+
+    let list_sql = format!(
+        r#"
+    SELECT id, provider, trade_no, order_type, raw_payload, verified,
+           reported_amount, expected_amount, currency, status, message, review_status, reviewed_by, reviewed_at, review_note, created_at
+    FROM payment_callbacks
+    {where_clause}
+    ORDER BY id DESC
+    LIMIT ? OFFSET ?
+    "#
+    );
+    let mut list_query = sqlx::query(&list_sql);
+    for param in &params {
+        list_query = list_query.bind(param);
+    }
+    let rows = match list_query
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&state.db)
+        .await
+    {
+        Ok(value) => value,
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    };
+
+    let items = rows
+        .into_iter()
+        .map(map_callback_row)
+        .collect::<Vec<Value>>();
+    success(
+        json!({
+          "data": items,
+          "total": total,
+          "pagination": {
+            "total": total,
+            "page": page,
+            "limit": limit,
+            "pages": if total > 0 { ((total as f64) / (limit as f64)).ceil() as i64 } else { 0 }
+          }
+        }),
+        "Success",
+    )
+    .into_response()
+}
+
+/// 审核待处理回调：`settle` 人工确认入账，`dismiss` 驳回
+async fn post_review_callback(
+    State(state): State<AppState>,
+    Extension(headers): Extension<HeaderMap>,
+    Path(id): Path<i64>,
+    Json(body): Json<ReviewRequest>,
+) -> Response {
+    let admin_id = match require_admin_permission(&state, &headers, "payment-callbacks").await {
+        Ok(value) => value,
+        Err(resp) => return resp,
+    };
+    if id <= 0 {
+        return error(StatusCode::BAD_REQUEST, "ID 无效", None);
+    }
+    let action = body.action.unwrap_or_default().trim().to_lowercase();
+    if action != "settle" && action != "dismiss" {
+        return error(StatusCode::BAD_REQUEST, "审核操作无效", None);
+    }
+
+    let row = match sqlx::query(
+        r#"
+    SELECT trade_no, order_type, status, review_status, reported_amount, expected_amount
+    FROM payment_callbacks
+    WHERE id = ?
+    "#,
+    )
+    .bind(id)
+    .fetch_optional(&state.db)
+    .await
+    {
+        Ok(Some(value)) => value,
+        Ok(None) => return error(StatusCode::NOT_FOUND, "回调记录不存在", None),
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    };
+    let review_status = row
+        .try_get::<Option<String>, _>("review_status")
+        .ok()
+        .flatten()
+        .unwrap_or_default();
+    if review_status != "pending" {
+        return error(StatusCode::BAD_REQUEST, "该回调无需审核", None);
+    }
+
+    let mut applied = false;
+    if action == "settle" {
+        let trade_no = row
+            .try_get::<Option<String>, _>("trade_no")
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+        match settle_trade(&state, &trade_no).await {
+            Ok(Some(SettleOutcome::Applied)) => applied = true,
+            Ok(Some(SettleOutcome::AlreadyPaid)) => {}
+            Ok(Some(SettleOutcome::Closed)) => {
+                return error(
+                    StatusCode::BAD_REQUEST,
+                    "订单已退款或已关闭，无法入账",
+                    None,
+                )
+            }
+            Ok(None) => return error(StatusCode::NOT_FOUND, "订单不存在", None),
+            Err(message) => return error(StatusCode::INTERNAL_SERVER_ERROR, &message, None),
+        }
+    }
+
+    let next_status = if action == "settle" {
+        "settled"
+    } else {
+        "dismissed"
+    };
+    if let Err(err) = sqlx::query(
+        r#"
+    UPDATE payment_callbacks
+    SET review_status = ?, reviewed_by = ?, reviewed_at = CURRENT_TIMESTAMP, review_note = ?
+    WHERE id = ?
+    "#,
+    )
+    .bind(next_status)
+    .bind(admin_id)
+    .bind(body.note.unwrap_or_default())
+    .bind(id)
+    .execute(&state.db)
+    .await
+    {
+        return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None);
+    }
+
+    record_admin_action(
+        &state,
+        &headers,
+        admin_id,
+        AdminAction {
+            action: "payment_callback.review",
+            route: "POST /api/admin/payment-callbacks/{id}/review",
+            target_type: "payment_callback",
+            target_id: Some(id.to_string()),
+            before: Some(json!({
+                "trade_no": row.try_get::<Option<String>, _>("trade_no").ok().flatten(),
+                "order_type": row.try_get::<Option<String>, _>("order_type").ok().flatten(),
+                "status": row.try_get::<Option<String>, _>("status").ok().flatten(),
+                "review_status": review_status,
+                "reported_amount": Money::from_row(&row, "reported_amount"),
+                "expected_amount": Money::from_row(&row, "expected_amount"),
+            })),
+            after: Some(json!({
+                "action": action,
+                "review_status": next_status,
+                "applied": applied,
+                "amount": if applied { Money::from_row(&row, "expected_amount") } else { None },
+            })),
+        },
+    )
+    .await;
+
+    let message = if action == "settle" {
+        "已确认入账"
+    } else {
+        "已驳回"
+    };
+    success(json!({ "id": id, "review_status": next_status }), message).into_response()
+}
+
+/// 向订单所属支付通道查询实际支付状态
+async fn get_provider_order(
+    State(state): State<AppState>,
+    Extension(headers): Extension<HeaderMap>,
+    Path(trade_no): Path<String>,
+) -> Response {
+    if let Err(resp) = require_admin_permission(&state, &headers, "payment-callbacks").await {
+        return resp;
+    }
+
+    let settings = PaymentSettings::load(&state).await;
+    let provider = match find_order_provider(&state, &settings, &trade_no).await {
+        Ok(Some(value)) => value,
+        Ok(None) => return error(StatusCode::NOT_FOUND, "订单不存在或未使用在线支付", None),
+        Err(message) => return error(StatusCode::INTERNAL_SERVER_ERROR, &message, None),
+    };
+    match query_payment(&settings, provider, &trade_no).await {
+        Ok(result) => success(
+            json!({ "trade_no": trade_no, "provider": provider, "result": result }),
+            "Success",
+        )
+        .into_response(),
+        Err(message) => error(StatusCode::BAD_GATEWAY, &message, None),
+    }
+}
+
+fn format_datetime(value: Option<NaiveDateTime>) -> Option<String> {
+    value.map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
+}
+
+fn map_callback_row(row: sqlx::mysql::MySqlRow) -> Value {
+    let raw_payload = row
+        .try_get::<Option<String>, _>("raw_payload")
+        .ok()
+        .flatten()
+        .unwrap_or_default();
+    json!({
+      "id": row.try_get::<i64, _>("id").unwrap_or(0),
+      "provider": row.try_get::<Option<String>, _>("provider").ok().flatten().unwrap_or_default(),
+      "trade_no": row.try_get::<Option<String>, _>("trade_no").ok().flatten(),
+      "order_type": row.try_get::<Option<String>, _>("order_type").ok().flatten(),
+      "payload": serde_json::from_str::<Value>(&raw_payload).unwrap_or(Value::String(raw_payload)),
+      "verified": row.try_get::<Option<i64>, _>("verified").ok().flatten().unwrap_or(0) == 1,
+      "reported_amount": Money::from_row(&row, "reported_amount"),
+      "expected_amount": Money::from_row(&row, "expected_amount"),
+      "currency": row.try_get::<Option<String>, _>("currency").ok().flatten(),
+      "status": row.try_get::<Option<String>, _>("status").ok().flatten().unwrap_or_default(),
+      "message": row.try_get::<Option<String>, _>("message").ok().flatten(),
+      "review_status": row.try_get::<Option<String>, _>("review_status").ok().flatten().unwrap_or_default(),
+      "reviewed_by": row.try_get::<Option<i64>, _>("reviewed_by").ok().flatten(),
+      "reviewed_at": format_datetime(row.try_get::<Option<NaiveDateTime>, _>("reviewed_at").ok().flatten()),
+      "review_note": row.try_get::<Option<String>, _>("review_note").ok().flatten(),
+      "created_at": format_datetime(row.try_get::<Option<NaiveDateTime>, _>("created_at").ok().flatten())
+    })
+}
@@ -0,0 +1,472 @@
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, put};
+use axum::{Extension, Json, Router};
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sqlx::Row;
+
+use crate::admin_actions::{record_admin_action, AdminAction};
+use crate::proxy_groups::{
+    build_group, fetch_proxy_groups, parse_node_tags, GroupMatch, GroupMember, GroupPlan,
+    DEFAULT_TEST_INTERVAL, GROUP_TYPES, MATCH_TYPES,
+};
+use crate::response::{error, success};
+use crate::state::AppState;
+use crate::subscription_templates::invalidate_templates;
+
+use super::super::auth::require_admin_permission;
+
+#[derive(Deserialize)]
+struct ProxyGroupRequest {
+    name: Option<String>,
+    emoji: Option<String>,
+    match_type: Option<String>,
+    match_value: Option<Value>,
+    group_type: Option<String>,
+    test_url: Option<String>,
+    test_interval: Option<i64>,
+    sort_order: Option<i64>,
+    enabled: Option<i64>,
+}
+
+/// 校验通过、待写入的分组字段
+struct GroupFields {
+    name: String,
+    emoji: String,
+    match_type: String,
+    match_value: String,
+    group_type: String,
+    test_url: Option<String>,
+    test_interval: i64,
+    sort_order: i64,
+    enabled: i64,
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/proxy-groups",
+            get(get_proxy_groups).post(post_proxy_group),
+        )
+        .route("/proxy-groups/matches", get(get_proxy_group_matches))
+        .route(
+            "/proxy-groups/{id}",
+            put(put_proxy_group).delete(delete_proxy_group),
+        )
+}
+
+async fn get_proxy_groups(
+    State(state): State<AppState>,
+    Extension(headers): Extension<HeaderMap>,
+) -> Response {
+    if let Err(resp) = require_admin_permission(&state, &headers, "proxy-groups").await {
+        return resp;
+    }
+
+    let rows = match sqlx::query(
+        r#"
+    SELECT id, name, emoji, match_type, match_value, group_type, test_url, test_interval,
+           sort_order, enabled, created_at, updated_at
+    FROM proxy_groups
+    ORDER BY sort_order ASC, id ASC
+    "#,
+    )
+    .fetch_all(&state.db)
+    .await
+    {
+        Ok(value) => value,
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    };
+
+    let items = rows
+        .into_iter()
+        .map(map_proxy_group_row)
+        .collect::<Vec<Value>>();
+    success(
+        json!({ "data": items, "group_types": GROUP_TYPES, "match_types": MATCH_TYPES }),
+        "Success",
+    )
+    .into_response()
+}
+
+async fn post_proxy_group(
+    State(state): State<AppState>,
+    Extension(headers): Extension<HeaderMap>,
+    Json(body): Json<ProxyGroupRequest>,
+) -> Response {
+    let admin_id = match require_admin_permission(&state, &headers, "proxy-groups").await {
+        Ok(value) => value,
+        Err(resp) => return resp,
+    };
+
+    let fields = match merge_fields(&Value::Null, body) {
+        Ok(value) => value,
+        Err(message) => return error(StatusCode::BAD_REQUEST, &message, None),
+    };
+    if let Err(resp) = ensure_unique_name(&state, &fields.name, None).await {
+        return resp;
+    }
+
+    let result = sqlx::query(
+        r#"
+    INSERT INTO proxy_groups
+      (name, emoji, match_type, match_value, group_type, test_url, test_interval, sort_order, enabled, created_at, updated_at)
+    VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
+    "#,
+    )
+    .bind(&fields.name)
+    .bind(&fields.emoji)
+    .bind(&fields.match_type)
+    .bind(&fields.match_value)
+    .bind(&fields.group_type)
+    .bind(fields.test_url.as_deref())
+    .bind(fields.test_interval)
+    .bind(fields.sort_order)
+    .bind(fields.enabled)
+    .execute(&state.db)
+    .await;
+    let id = match result {
+        Ok(value) => value.last_insert_id() as i64,
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    };
+
+    invalidate_templates(&state).await;
+    let payload = fetch_proxy_group(&state, id).await.unwrap_or(Value::Null);
+    record_admin_action(
+        &state,
+        &headers,
+        admin_id,
+        AdminAction {
+            action: "proxy_group.create",
+            route: "POST /api/admin/proxy-groups",
+            target_type: "proxy_group",
+            target_id: Some(id.to_string()),
+            before: None,
+            after: Some(payload.clone()),
+        },
+    )
+    .await;
+    success(payload, "创建成功").into_response()
+}
+
+async fn put_proxy_group(
+    State(state): State<AppState>,
+    Extension(headers): Extension<HeaderMap>,
+    Path(id): Path<i64>,
+    Json(body): Json<ProxyGroupRequest>,
+) -> Response {
+    let admin_id = match require_admin_permission(&state, &headers, "proxy-groups").await {
+        Ok(value) => value,
+        Err(resp) => return resp,
+    };
+    if id <= 0 {
+        return error(StatusCode::BAD_REQUEST, "ID 无效", None);
+    }
+
+    let before = match fetch_proxy_group(&state, id).await {
+        Ok(Value::Null) => return error(StatusCode::NOT_FOUND, "分组不存在", None),
+        Ok(value) => value,
+        Err(message) => return error(StatusCode::INTERNAL_SERVER_ERROR, &message, None),
+    };
+    // 未提交的字段沿用原值，合并后整体校验
+    let fields = match merge_fields(&before, body) {
+        Ok(value) => value,
+        Err(message) => return error(StatusCode::BAD_REQUEST, &message, None),
+    };
+    if let Err(resp) = ensure_unique_name(&state, &fields.name, Some(id)).await {
+        return resp;
+    }
+
+    let result = sqlx::query(
+        r#"
+    UPDATE proxy_groups
+    SET name = ?, emoji = ?, match_type = ?, match_value = ?, group_type = ?, test_url = ?,
+        test_interval = ?, sort_order = ?, enabled = ?, updated_at = CURRENT_TIMESTAMP
+    WHERE id = ?
+    "#,
+    )
+    .bind(&fields.name)
+    .bind(&fields.emoji)
+    .bind(&fields.match_type)
+    .bind(&fields.match_value)
+    .bind(&fields.group_type)
+    .bind(fields.test_url.as_deref())
+    .bind(fields.test_interval)
+    .bind(fields.sort_order)
+    .bind(fields.enabled)
+    .bind(id)
+    .execute(&state.db)
+    .await;
+    if let Err(err) = result {
+        return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None);
+    }
+
+    invalidate_templates(&state).await;
+    let payload = fetch_proxy_group(&state, id).await.unwrap_or(Value::Null);
+    record_admin_action(
+        &state,
+        &headers,
+        admin_id,
+        AdminAction {
+            action: "proxy_group.update",
+            route: "PUT /api/admin/proxy-groups/{id}",
+            target_type: "proxy_group",
+            target_id: Some(id.to_string()),
+            before: Some(before),
+            after: Some(payload.clone()),
+        },
+    )
+    .await;
+    success(payload, "更新成功").into_response()
+}
+
+async fn delete_proxy_group(
+    State(state): State<AppState>,
+    Extension(headers): Extension<HeaderMap>,
+    Path(id): Path<i64>,
+) -> Response {
+    let admin_id = match require_admin_permission(&state, &headers, "proxy-groups").await {
+        Ok(value) => value,
+        Err(resp) => return resp,
+    };
+    if id <= 0 {
+        return error(StatusCode::BAD_REQUEST, "ID 无效", None);
+    }
+
+    let before = match fetch_proxy_group(&state, id).await {
+        Ok(Value::Null) => return error(StatusCode::NOT_FOUND, "分组不存在", None),
+        Ok(value) => value,
+        Err(message) => return error(StatusCode::INTERNAL_SERVER_ERROR, &message, None),
+    };
+    if let Err(err) = sqlx::query("DELETE FROM proxy_groups WHERE id = ?")
+        .bind(id)
+        .execute(&state.db)
+        .await
+    {
+        return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None);
+    }
+
+    invalidate_templates(&state).await;
+    record_admin_action(
+        &state,
+        &headers,
+        admin_id,
+        AdminAction {
+            action: "proxy_group.delete",
+            route: "DELETE /api/admin/proxy-groups/{id}",
+            target_type: "proxy_group",
+            target_id: Some(id.to_string()),
+            before: Some(before),
+            after: None,
+        },
+    )
+    .await;
+    success(Value::Null, "删除成功").into_response()
+}
+
+/// 各启用分组当前匹配到的启用节点，便于核对标签与正则
+async fn get_proxy_group_matches(
+    State(state): State<AppState>,
+    Extension(headers): Extension<HeaderMap>,
+) -> Response {
+    if let Err(resp) = require_admin_permission(&state, &headers, "proxy-groups").await {
+        return resp;
+    }
+
+    let groups = match fetch_proxy_groups(&state).await {
+        Ok(value) => value,
+        Err(message) => return error(StatusCode::INTERNAL_SERVER_ERROR, &message, None),
+    };
+    let rows = match sqlx::query(
+        "SELECT id, name, CAST(tags AS CHAR) AS tags FROM nodes WHERE status = 1 ORDER BY id ASC",
+    )
+    .fetch_all(&state.db)
+    .await
+    {
+        Ok(value) => value,
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    };
+
+    let nodes = rows
+        .into_iter()
+        .map(|row| {
+            let id = row.try_get::<i64, _>("id").unwrap_or(0);
+            let name = row
+                .try_get::<Option<String>, _>("name")
+                .ok()
+                .flatten()
+                .unwrap_or_default();
+            let tags = parse_node_tags(
+                row.try_get::<Option<String>, _>("tags")
+                    .ok()
+                    .flatten()
+                    .as_deref(),
+            );
+            (id, name, tags)
+        })
+        .collect::<Vec<(i64, String, Vec<String>)>>();
+    // 以节点 ID 作为成员名，避免同名节点被合并
+    let members = nodes
+        .iter()
+        .map(|(id, name, tags)| GroupMember {
+            node_id: *id,
+            node_name: name.clone(),
+            tags,
+            proxy_name: id.to_string(),
+        })
+        .collect::<Vec<GroupMember<'_>>>();
+    let plan = GroupPlan::new(&groups, &members);
+
+    let items = groups
+        .iter()
+        .map(|group| {
+            let matched = plan
+                .available()
+                .iter()
+                .find(|(item, _)| item.name == group.name)
+                .map(|(_, ids)| {
+                    nodes
+                        .iter()
+                        .filter(|(id, _, _)| ids.contains(&id.to_string()))
+                        .map(|(id, name, _)| json!({ "id": id, "name": name }))
+                        .collect::<Vec<Value>>()
+                })
+                .unwrap_or_default();
+            json!({
+              "name": group.name,
+              "group_type": group.group_type,
+              "nodes": matched
+            })
+        })
+        .collect::<Vec<Value>>();
+    success(json!({ "data": items }), "Success").into_response()
+}
+
+/// 以现有分组（创建时为 null）为基础合并提交的字段并校验
+fn merge_fields(current: &Value, body: ProxyGroupRequest) -> Result<GroupFields, String> {
+    let read = |key: &str| current.get(key).and_then(Value::as_str).map(str::to_string);
+    let read_i64 = |key: &str| current.get(key).and_then(Value::as_i64);
+
+    let name = body.name.or_else(|| read("name")).unwrap_or_default();
+    let emoji = body.emoji.or_else(|| read("emoji")).unwrap_or_default();
+    let match_type = body
+        .match_type
+        .or_else(|| read("match_type"))
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase();
+    let match_value = match body.match_value {
+        Some(Value::String(text)) => text,
+        Some(Value::Array(items)) => Value::Array(items).to_string(),
+        Some(Value::Null) | None => read("match_value").unwrap_or_default(),
+        Some(_) => return Err("匹配内容格式无效".to_string()),
+    };
+    let group_type = body
+        .group_type
+        .or_else(|| read("group_type"))
+        .unwrap_or_else(|| "select".to_string())
+        .trim()
+        .to_lowercase();
+    let test_url = body.test_url.or_else(|| read("test_url"));
+    let test_interval = body
+        .test_interval
+        .or_else(|| read_i64("test_interval"))
+        .unwrap_or(DEFAULT_TEST_INTERVAL);
+
+    let group = build_group(
+        &emoji,
+        &name,
+        &match_type,
+        &match_value,
+        &group_type,
+        test_url.as_deref(),
+        Some(test_interval),
+    )?;
+    if name.trim().chars().count() > 64 || emoji.trim().chars().count() > 16 {
+        return Err("分组名称或 emoji 过长".to_string());
+    }
+    // 节点 ID 统一存为 JSON 数组
+    let match_value = match &group.matcher {
+        GroupMatch::NodeIds(ids) => serde_json::to_string(ids).unwrap_or_default(),
+        _ => match_value.trim().to_string(),
+    };
+
+    Ok(GroupFields {
+        name: name.trim().to_string(),
+        emoji: emoji.trim().to_string(),
+        match_type,
+        match_value,
+        group_type,
+        test_url: test_url
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty()),
+        test_interval,
+        sort_order: body
+            .sort_order
+            .or_else(|| read_i64("sort_order"))
+            .unwrap_or(0),
+        enabled: match body.enabled.or_else(|| read_i64("enabled")) {
+            Some(0) => 0,
+            _ => 1,
+        },
+    })
+}
+
+async fn ensure_unique_name(
+    state: &AppState,
+    name: &str,
+    exclude_id: Option<i64>,
+) -> Result<(), Response> {
+    let exists =
+        sqlx::query("SELECT id FROM proxy_groups WHERE name = ? AND (? IS NULL OR id <> ?)")
+            .bind(name)
+            .bind(exclude_id)
+            .bind(exclude_id)
+            .fetch_optional(&state.db)
+            .await
+            .map_err(|err| error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None))?;
+    if exists.is_some() {
+        return Err(error(StatusCode::CONFLICT, "分组名称已存在", None));
+    }
+    Ok(())
+}
+
+async fn fetch_proxy_group(state: &AppState, id: i64) -> Result<Value, String> {
+    let row = sqlx::query(
+        r#"
+    SELECT id, name, emoji, match_type, match_value, group_type, test_url, test_interval,
+           sort_order, enabled, created_at, updated_at
+    FROM proxy_groups
+    WHERE id = ?
+    "#,
+    )
+    .bind(id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|err| err.to_string())?;
+    Ok(row.map(map_proxy_group_row).unwrap_or(Value::Null))
+}
+
+fn format_datetime(value: Option<NaiveDateTime>) -> Option<String> {
+    value.map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
+}
+
+fn map_proxy_group_row(row: sqlx::mysql::MySqlRow) -> Value {
+    json!({
+      "id": row.try_get::<i64, _>("id").unwrap_or(0),
+      "name": row.try_get::<Option<String>, _>("name").ok().flatten().unwrap_or_default(),
+      "emoji": row.try_get::<Option<String>, _>("emoji").ok().flatten().unwrap_or_default(),
+      "match_type": row.try_get::<Option<String>, _>("match_type").ok().flatten().unwrap_or_default(),
+      "match_value": row.try_get::<Option<String>, _>("match_value").ok().flatten().unwrap_or_default(),
+      "group_type": row.try_get::<Option<String>, _>("group_type").ok().flatten().unwrap_or_default(),
+      "test_url": row.try_get::<Option<String>, _>("test_url").ok().flatten(),
+      "test_interval": row.try_get::<Option<i64>, _>("test_interval").ok().flatten().unwrap_or(DEFAULT_TEST_INTERVAL),
+      "sort_order": row.try_get::<Option<i64>, _>("sort_order").ok().flatten().unwrap_or(0),
+      "enabled": row.try_get::<Option<i64>, _>("enabled").ok().flatten().unwrap_or(1),
+      "created_at": format_datetime(row.try_get::<Option<NaiveDateTime>, _>("created_at").ok().flatten()),
+      "updated_at": format_datetime(row.try_get::<Option<NaiveDateTime>, _>("updated_at").ok().flatten())
+    })
+}
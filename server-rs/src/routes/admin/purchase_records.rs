@@ -2,16 +2,19 @@ use axum::extract::{Path, Query, State};
 use axum::http::{HeaderMap, StatusCode};
 use axum::response::{IntoResponse, Response};
 use axum::routing::{get, post};
-use axum::{Extension, Router};
+use axum::{Extension, Json, Router};
 use serde::Deserialize;
 use serde_json::{json, Value};
 use sqlx::Row;
 
+use crate::admin_actions::{record_admin_action, AdminAction};
+use crate::money::Money;
+use crate::purchase_refund::{refund_purchase, RefundOptions};
 use crate::referral::award_rebate;
 use crate::response::{error, success};
 use crate::state::AppState;
 
-use super::super::auth::require_admin_user_id;
+use super::super::auth::require_admin_permission;
 use super::super::payment_callback::mark_purchase_paid;
 
 #[derive(Deserialize)]
@@ -27,6 +30,15 @@ struct PurchaseQuery {
     package_id: Option<String>,
 }
 
+#[derive(Deserialize)]
+struct RefundRequest {
+    #[serde(alias = "rollbackBenefits")]
+    rollback_benefits: Option<bool>,
+    #[serde(alias = "refundTo")]
+    refund_to: Option<String>,
+    reason: Option<String>,
+}
+
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/purchase-records", get(get_purchase_records))
@@ -34,6 +46,7 @@ pub fn router() -> Router<AppState> {
             "/purchase-records/{tradeNo}/mark-paid",
             post(post_mark_paid),
         )
+        .route("/purchase-records/{tradeNo}/refund", post(post_refund))
 }
 
 async fn get_purchase_records(
@@ -41,7 +54,7 @@ async fn get_purchase_records(
     Extension(headers): Extension<HeaderMap>,
     Query(query): Query<PurchaseQuery>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "purchase-records").await {
         return resp;
     }
 
@@ -111,9 +124,9 @@ async fn get_purchase_records(
       ppr.id,
       ppr.user_id,
       ppr.package_id,
-      CAST(ppr.price AS DOUBLE) as price,
-      CAST(ppr.package_price AS DOUBLE) as package_price,
-      CAST(ppr.discount_amount AS DOUBLE) as discount_amount,
+      ppr.price,
+      ppr.package_price,
+      ppr.discount_amount,
       ppr.coupon_code,
       ppr.purchase_type,
       ppr.trade_no,
@@ -121,6 +134,9 @@ async fn get_purchase_records(
       ppr.created_at,
       ppr.paid_at,
       ppr.expires_at,
+      ppr.close_reason,
+      ppr.closed_at,
+      ppr.refund_amount,
       u.email,
       u.username,
       p.name AS package_name,
@@ -148,23 +164,26 @@ async fn get_purchase_records(
         Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
     };
 
-    let status_map = [(0, "待支付"), (1, "已支付"), (2, "已取消"), (3, "支付失败")];
+    let status_map = [
+        (0, "待支付"),
+        (1, "已支付"),
+        (2, "已取消"),
+        (3, "支付失败"),
+        (4, "已过期"),
+        (5, "已退款"),
+    ];
 
     let records = rows
     .into_iter()
     .map(|row| {
       let status = row.try_get::<Option<i64>, _>("status").unwrap_or(Some(0)).unwrap_or(0);
-      let price = parse_decimal(&row, "price", 0.0);
-      let package_price = row
-        .try_get::<Option<String>, _>("package_price")
-        .ok()
-        .and_then(|value| value.and_then(|val| val.parse::<f64>().ok()));
-      let discount_amount = parse_decimal(&row, "discount_amount", 0.0);
-      let final_price = fix_money_precision(
-        package_price
-          .map(|value| (value - discount_amount).max(0.0))
-          .unwrap_or(price)
-      );
+      let price = Money::from_row_or_zero(&row, "price");
+      let package_price = Money::from_row(&row, "package_price");
+      let discount_amount = Money::from_row_or_zero(&row, "discount_amount");
+      let final_price = package_price
+        .clone()
+        .map(|value| (value - discount_amount.clone()).non_negative())
+        .unwrap_or_else(|| price.clone());
 
       let purchase_type = row.try_get::<Option<String>, _>("purchase_type").ok().flatten().unwrap_or_default();
       let purchase_type_text = normalize_purchase_type_text(&purchase_type);
@@ -186,8 +205,8 @@ async fn get_purchase_records(
         "username": row.try_get::<Option<String>, _>("username").ok().flatten().unwrap_or_default(),
         "package_id": row.try_get::<Option<i64>, _>("package_id").unwrap_or(Some(0)).unwrap_or(0),
         "package_name": row.try_get::<Option<String>, _>("package_name").ok().flatten().unwrap_or_default(),
-        "price": fix_money_precision(price),
-        "package_price": package_price.map(fix_money_precision),
+        "price": price,
+        "package_price": package_price,
         "discount_amount": discount_amount,
         "coupon_code": row.try_get::<Option<String>, _>("coupon_code").ok().flatten(),
         "purchase_type": purchase_type.clone(),
@@ -200,6 +219,9 @@ async fn get_purchase_records(
         "created_at": row.try_get::<Option<chrono::NaiveDateTime>, _>("created_at").ok().flatten().map(format_datetime),
         "paid_at": row.try_get::<Option<chrono::NaiveDateTime>, _>("paid_at").ok().flatten().map(format_datetime),
         "expires_at": row.try_get::<Option<chrono::NaiveDateTime>, _>("expires_at").ok().flatten().map(format_datetime),
+        "close_reason": row.try_get::<Option<String>, _>("close_reason").ok().flatten(),
+        "closed_at": row.try_get::<Option<chrono::NaiveDateTime>, _>("closed_at").ok().flatten().map(format_datetime),
+        "refund_amount": Money::from_row(&row, "refund_amount"),
         "final_price": final_price
       })
     })
@@ -225,9 +247,10 @@ async fn post_mark_paid(
     Extension(headers): Extension<HeaderMap>,
     Path(trade_no): Path<String>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
-        return resp;
-    }
+    let admin_id = match require_admin_permission(&state, &headers, "purchase-records").await {
+        Ok(value) => value,
+        Err(resp) => return resp,
+    };
 
     let result = match mark_purchase_paid(&state, &trade_no).await {
         Ok(value) => value,
@@ -242,19 +265,116 @@ async fn post_mark_paid(
         let _ = award_rebate(
             &state,
             result.user_id,
-            result.amount,
+            &result.amount,
             "purchase",
             Some(result.record_id),
             Some(&trade_no),
             Some("purchase_rebate"),
         )
         .await;
+        record_admin_action(
+            &state,
+            &headers,
+            admin_id,
+            AdminAction {
+                action: "purchase.mark_paid",
+                route: "POST /api/admin/purchase-records/{tradeNo}/mark-paid",
+                target_type: "purchase",
+                target_id: Some(trade_no.clone()),
+                before: Some(json!({ "status": result.previous_status })),
+                after: Some(json!({ "status": 1, "amount": result.amount })),
+            },
+        )
+        .await;
         return success(json!({ "trade_no": trade_no }), "已标记支付并激活套餐").into_response();
     }
 
     success(json!({ "trade_no": trade_no }), "订单已是已支付").into_response()
 }
 
+async fn post_refund(
+    State(state): State<AppState>,
+    Extension(headers): Extension<HeaderMap>,
+    Path(trade_no): Path<String>,
+    Json(body): Json<RefundRequest>,
+) -> Response {
+    let admin_id = match require_admin_permission(&state, &headers, "purchase-records").await {
+        Ok(value) => value,
+        Err(resp) => return resp,
+    };
+
+    let to_original = match body
+        .refund_to
+        .as_deref()
+        .map(str::trim)
+        .unwrap_or("balance")
+    {
+        "" | "balance" => false,
+        "original" => true,
+        _ => {
+            return error(
+                StatusCode::BAD_REQUEST,
+                "refund_to 仅支持 balance 或 original",
+                None,
+            )
+        }
+    };
+    let options = RefundOptions {
+        rollback_benefits: body.rollback_benefits.unwrap_or(false),
+        to_original,
+        reason: body.reason,
+    };
+
+    let result = match refund_purchase(&state, &trade_no, &options).await {
+        Ok(Some(value)) => value,
+        Ok(None) => return error(StatusCode::NOT_FOUND, "订单不存在", None),
+        Err(message) => return error(StatusCode::BAD_REQUEST, &message, None),
+    };
+
+    record_admin_action(
+        &state,
+        &headers,
+        admin_id,
+        AdminAction {
+            action: "purchase.refund",
+            route: "POST /api/admin/purchase-records/{tradeNo}/refund",
+            target_type: "purchase",
+            target_id: Some(trade_no.clone()),
+            before: None,
+            after: Some(json!({
+              "user_id": result.user_id,
+              "refund_amount": result.refund_amount,
+              "balance_refunded": result.balance_refunded,
+              "original_refunded": result.original_refunded,
+              "rollback_benefits": options.rollback_benefits,
+              "reason": options.reason
+            })),
+        },
+    )
+    .await;
+
+    let message = if result.manual {
+        "已退款，在线支付部分需线下退回"
+    } else {
+        "退款成功"
+    };
+    success(
+        json!({
+          "trade_no": trade_no,
+          "user_id": result.user_id,
+          "refund_amount": result.refund_amount,
+          "balance_refunded": result.balance_refunded,
+          "original_refunded": result.original_refunded,
+          "manual": result.manual,
+          "provider_message": result.message,
+          "rebate_revoked": result.rebate_revoked,
+          "benefits_rolled_back": result.benefits_rolled_back
+        }),
+        message,
+    )
+    .into_response()
+}
+
 fn normalize_purchase_type_text(value: &str) -> String {
     if value.is_empty() {
         return "未知".to_string();
@@ -288,27 +408,6 @@ fn format_datetime(value: chrono::NaiveDateTime) -> String {
     value.format("%Y-%m-%d %H:%M:%S").to_string()
 }
 
-fn fix_money_precision(amount: f64) -> f64 {
-    (amount * 100.0).round() / 100.0
-}
-
-fn parse_decimal(row: &sqlx::mysql::MySqlRow, column: &str, fallback: f64) -> f64 {
-    if let Ok(value) = row.try_get::<Option<f64>, _>(column) {
-        return value.unwrap_or(fallback);
-    }
-    if let Ok(value) = row.try_get::<Option<sqlx::types::BigDecimal>, _>(column) {
-        return value
-            .map(|val| val.to_string().parse::<f64>().unwrap_or(fallback))
-            .unwrap_or(fallback);
-    }
-    if let Ok(value) = row.try_get::<Option<String>, _>(column) {
-        return value
-            .and_then(|val| val.parse::<f64>().ok())
-            .unwrap_or(fallback);
-    }
-    fallback
-}
-
 type SqlxQuery<'a> = sqlx::query::Query<'a, sqlx::MySql, sqlx::mysql::MySqlArguments>;
 
 enum SqlParam {
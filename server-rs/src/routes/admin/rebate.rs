@@ -3,16 +3,19 @@ use axum::http::{HeaderMap, StatusCode};
 use axum::response::{IntoResponse, Response};
 use axum::routing::{get, post};
 use axum::{Extension, Json, Router};
+use bigdecimal::{BigDecimal, ToPrimitive};
 use chrono::NaiveDateTime;
 use serde::Deserialize;
 use serde_json::{json, Value};
 use sqlx::Row;
 
+use crate::admin_actions::{record_admin_action, AdminAction};
+use crate::money::Money;
 use crate::referral::insert_user_transaction;
 use crate::response::{error, success};
 use crate::state::AppState;
 
-use super::super::auth::require_admin_user_id;
+use super::super::auth::require_admin_permission;
 
 #[derive(Deserialize)]
 struct PaginationQuery {
@@ -74,7 +77,7 @@ async fn get_users(
     Extension(headers): Extension<HeaderMap>,
     Query(query): Query<PaginationQuery>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "rebate").await {
         return resp;
     }
 
@@ -118,9 +121,9 @@ async fn get_users(
         "id": row.try_get::<i64, _>("id").unwrap_or(0),
         "email": row.try_get::<Option<String>, _>("email").ok().flatten().unwrap_or_default(),
         "username": row.try_get::<Option<String>, _>("username").ok().flatten().unwrap_or_default(),
-        "money": parse_decimal(&row, "money", 0.0),
-        "rebate_available": parse_decimal(&row, "rebate_available", 0.0),
-        "rebate_total": parse_decimal(&row, "rebate_total", 0.0),
+        "money": Money::from_row_or_zero(&row, "money"),
+        "rebate_available": Money::from_row_or_zero(&row, "rebate_available"),
+        "rebate_total": Money::from_row_or_zero(&row, "rebate_total"),
         "created_at": format_datetime(row.try_get::<Option<NaiveDateTime>, _>("created_at").ok().flatten())
       })
     })
@@ -134,7 +137,7 @@ async fn get_transactions(
     Extension(headers): Extension<HeaderMap>,
     Query(query): Query<TransactionsQuery>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "rebate").await {
         return resp;
     }
 
@@ -198,11 +201,12 @@ async fn get_transactions(
         "inviter_id": row.try_get::<Option<i64>, _>("inviter_id").ok().flatten(),
         "referral_id": row.try_get::<Option<i64>, _>("referral_id").ok().flatten(),
         "invitee_id": row.try_get::<Option<i64>, _>("invitee_id").ok().flatten(),
+        "level": row.try_get::<Option<i64>, _>("level").ok().flatten().unwrap_or(1),
         "source_type": row.try_get::<Option<String>, _>("source_type").ok().flatten().unwrap_or_default(),
         "source_id": row.try_get::<Option<i64>, _>("source_id").ok().flatten(),
         "trade_no": row.try_get::<Option<String>, _>("trade_no").ok().flatten(),
         "event_type": row.try_get::<Option<String>, _>("event_type").ok().flatten().unwrap_or_default(),
-        "amount": parse_decimal(&row, "amount", 0.0),
+        "amount": Money::from_row_or_zero(&row, "amount"),
         "status": row.try_get::<Option<String>, _>("status").ok().flatten().unwrap_or_default(),
         "remark": row.try_get::<Option<String>, _>("remark").ok().flatten(),
         "created_at": format_datetime(row.try_get::<Option<NaiveDateTime>, _>("created_at").ok().flatten()),
@@ -222,13 +226,14 @@ async fn post_transfer(
     Extension(headers): Extension<HeaderMap>,
     Json(body): Json<TransferRequest>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
-        return resp;
-    }
+    let admin_id = match require_admin_permission(&state, &headers, "rebate").await {
+        Ok(value) => value,
+        Err(resp) => return resp,
+    };
 
     let user_id = body.user_id.unwrap_or(0);
-    let amount = fix_money_precision(body.amount.unwrap_or(0.0));
-    if user_id <= 0 || amount <= 0.0 {
+    let amount = body.amount.and_then(Money::from_f64).unwrap_or_default();
+    if user_id <= 0 || !amount.is_positive() {
         return error(StatusCode::BAD_REQUEST, "参数无效", None);
     }
 
@@ -237,7 +242,7 @@ async fn post_transfer(
         Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
     };
 
-    let row = sqlx::query("SELECT money, rebate_available FROM users WHERE id = ?")
+    let row = sqlx::query("SELECT money, rebate_available FROM users WHERE id = ? FOR UPDATE")
         .bind(user_id)
         .fetch_optional(&mut *tx)
         .await;
@@ -250,29 +255,34 @@ async fn post_transfer(
         None => return error(StatusCode::NOT_FOUND, "用户不存在", None),
     };
 
-    let balance_before = parse_decimal(&row, "money", 0.0);
-    let rebate_before = parse_decimal(&row, "rebate_available", 0.0);
-    if rebate_before + 1e-6 < amount {
+    let balance_before = Money::from_row_or_zero(&row, "money");
+    let rebate_before = Money::from_row_or_zero(&row, "rebate_available");
+    if rebate_before < amount {
         return error(StatusCode::BAD_REQUEST, "返利余额不足", None);
     }
 
-    let balance_after = fix_money_precision(balance_before + amount);
-    let rebate_after = fix_money_precision(rebate_before - amount);
+    let balance_after = balance_before.clone() + amount.clone();
+    let rebate_after = rebate_before.clone() - amount.clone();
 
-    if let Err(err) = sqlx::query(
+    let updated = sqlx::query(
         r#"
     UPDATE users
     SET rebate_available = rebate_available - ?, money = money + ?, updated_at = CURRENT_TIMESTAMP
-    WHERE id = ?
+    WHERE id = ? AND rebate_available >= ?
     "#,
     )
-    .bind(amount)
-    .bind(amount)
+    .bind(&amount)
+    .bind(&amount)
     .bind(user_id)
+    .bind(&amount)
     .execute(&mut *tx)
-    .await
-    {
-        return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None);
+    .await;
+    match updated {
+        Ok(result) if result.rows_affected() == 0 => {
+            return error(StatusCode::BAD_REQUEST, "返利余额不足", None)
+        }
+        Ok(_) => {}
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
     }
 
     if let Err(err) = sqlx::query(
@@ -283,11 +293,11 @@ async fn post_transfer(
     "#,
     )
     .bind(user_id)
-    .bind(amount)
-    .bind(balance_before)
-    .bind(balance_after)
-    .bind(rebate_before)
-    .bind(rebate_after)
+    .bind(&amount)
+    .bind(&balance_before)
+    .bind(&balance_after)
+    .bind(&rebate_before)
+    .bind(&rebate_after)
     .execute(&mut *tx)
     .await
     {
@@ -298,6 +308,20 @@ async fn post_transfer(
         return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None);
     }
 
+    record_admin_action(
+        &state,
+        &headers,
+        admin_id,
+        AdminAction {
+            action: "rebate.transfer",
+            route: "POST /api/admin/rebate/transfer",
+            target_type: "user",
+            target_id: Some(user_id.to_string()),
+            before: Some(json!({ "money": balance_before, "rebate_available": rebate_before })),
+            after: Some(json!({ "money": balance_after, "rebate_available": rebate_after })),
+        },
+    )
+    .await;
     success(Value::Null, "划转成功").into_response()
 }
 
@@ -306,7 +330,7 @@ async fn get_transfers(
     Extension(headers): Extension<HeaderMap>,
     Query(query): Query<TransfersQuery>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "rebate").await {
         return resp;
     }
 
@@ -345,11 +369,11 @@ async fn get_transfers(
     .into_iter()
     .map(|row| {
       json!({
-        "amount": parse_decimal(&row, "amount", 0.0),
-        "balance_before": parse_decimal(&row, "balance_before", 0.0),
-        "balance_after": parse_decimal(&row, "balance_after", 0.0),
-        "rebate_before": parse_decimal(&row, "rebate_before", 0.0),
-        "rebate_after": parse_decimal(&row, "rebate_after", 0.0),
+        "amount": Money::from_row_or_zero(&row, "amount"),
+        "balance_before": Money::from_row_or_zero(&row, "balance_before"),
+        "balance_after": Money::from_row_or_zero(&row, "balance_after"),
+        "rebate_before": Money::from_row_or_zero(&row, "rebate_before"),
+        "rebate_after": Money::from_row_or_zero(&row, "rebate_after"),
         "created_at": format_datetime(row.try_get::<Option<NaiveDateTime>, _>("created_at").ok().flatten())
       })
     })
@@ -363,7 +387,7 @@ async fn get_withdrawals(
     Extension(headers): Extension<HeaderMap>,
     Query(query): Query<WithdrawalsQuery>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "rebate").await {
         return resp;
     }
 
@@ -434,13 +458,18 @@ async fn get_withdrawals(
         "userId": row.try_get::<Option<i64>, _>("user_id").ok().flatten().unwrap_or(0),
         "email": row.try_get::<Option<String>, _>("email").ok().flatten().unwrap_or_default(),
         "username": row.try_get::<Option<String>, _>("username").ok().flatten().unwrap_or_default(),
-        "amount": parse_decimal(&row, "amount", 0.0),
+        "amount": Money::from_row_or_zero(&row, "amount"),
         "method": row.try_get::<Option<String>, _>("method").ok().flatten().unwrap_or_default(),
         "status": row.try_get::<Option<String>, _>("status").ok().flatten().unwrap_or_default(),
         "accountPayload": payload,
         "reviewNote": row.try_get::<Option<String>, _>("review_note").ok().flatten().unwrap_or_default(),
-        "feeRate": parse_decimal(&row, "fee_rate", 0.0),
-        "feeAmount": parse_decimal(&row, "fee_amount", 0.0),
+        "feeRate": row
+            .try_get::<Option<BigDecimal>, _>("fee_rate")
+            .ok()
+            .flatten()
+            .and_then(|value| value.to_f64())
+            .unwrap_or(0.0),
+        "feeAmount": Money::from_row_or_zero(&row, "fee_amount"),
         "createdAt": format_datetime(row.try_get::<Option<NaiveDateTime>, _>("created_at").ok().flatten()),
         "updatedAt": format_datetime(row.try_get::<Option<NaiveDateTime>, _>("updated_at").ok().flatten()),
         "processedAt": format_datetime(row.try_get::<Option<NaiveDateTime>, _>("processed_at").ok().flatten())
@@ -473,7 +502,7 @@ async fn post_withdrawal_review(
     Extension(headers): Extension<HeaderMap>,
     Json(body): Json<ReviewRequest>,
 ) -> Response {
-    let admin_id = match require_admin_user_id(&state, &headers, None).await {
+    let admin_id = match require_admin_permission(&state, &headers, "rebate").await {
         Ok(value) => value,
         Err(resp) => return resp,
     };
@@ -488,6 +517,21 @@ async fn post_withdrawal_review(
         return error(StatusCode::BAD_REQUEST, "状态无效", None);
     }
 
+    let before =
+        match sqlx::query("SELECT status, review_note FROM rebate_withdrawals WHERE id = ?")
+            .bind(withdrawal_id)
+            .fetch_optional(&state.db)
+            .await
+        {
+            Ok(value) => value.map(|row| {
+                json!({
+                  "status": row.try_get::<Option<String>, _>("status").ok().flatten(),
+                  "review_note": row.try_get::<Option<String>, _>("review_note").ok().flatten()
+                })
+            }),
+            Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+        };
+
     if let Err(err) = sqlx::query(
     r#"
     UPDATE rebate_withdrawals
@@ -496,7 +540,7 @@ async fn post_withdrawal_review(
     "#
   )
   .bind(&status)
-  .bind(body.note)
+  .bind(body.note.as_deref())
   .bind(admin_id)
   .bind(withdrawal_id)
   .execute(&state.db)
@@ -520,8 +564,8 @@ async fn post_withdrawal_review(
                 .ok()
                 .flatten()
                 .unwrap_or(0);
-            let amount = parse_decimal(&row, "amount", 0.0);
-            if user_id > 0 && amount > 0.0 {
+            let amount = Money::from_row_or_zero(&row, "amount");
+            if user_id > 0 && amount.is_positive() {
                 let _ = sqlx::query(
                     r#"
           UPDATE users
@@ -529,15 +573,15 @@ async fn post_withdrawal_review(
           WHERE id = ?
           "#,
                 )
-                .bind(amount)
+                .bind(&amount)
                 .bind(user_id)
                 .execute(&state.db)
                 .await;
 
                 let _ = insert_user_transaction(
-                    &state,
+                    &state.db,
                     user_id,
-                    amount,
+                    &amount,
                     "withdraw_revert",
                     "withdraw",
                     Some(withdrawal_id),
@@ -549,6 +593,20 @@ async fn post_withdrawal_review(
         }
     }
 
+    record_admin_action(
+        &state,
+        &headers,
+        admin_id,
+        AdminAction {
+            action: "rebate.withdrawal_review",
+            route: "POST /api/admin/rebate/withdrawals/review",
+            target_type: "rebate_withdrawal",
+            target_id: Some(withdrawal_id.to_string()),
+            before,
+            after: Some(json!({ "status": status, "review_note": body.note })),
+        },
+    )
+    .await;
     success(
         json!({ "id": withdrawal_id, "status": status }),
         "已更新状态",
@@ -556,23 +614,6 @@ async fn post_withdrawal_review(
     .into_response()
 }
 
-fn fix_money_precision(amount: f64) -> f64 {
-    (amount * 100.0).round() / 100.0
-}
-
-fn parse_decimal(row: &sqlx::mysql::MySqlRow, column: &str, fallback: f64) -> f64 {
-    if let Ok(Some(value)) = row.try_get::<Option<f64>, _>(column) {
-        return value;
-    }
-    if let Ok(Some(value)) = row.try_get::<Option<sqlx::types::BigDecimal>, _>(column) {
-        return value.to_string().parse::<f64>().unwrap_or(fallback);
-    }
-    if let Ok(Some(value)) = row.try_get::<Option<String>, _>(column) {
-        return value.parse::<f64>().unwrap_or(fallback);
-    }
-    fallback
-}
-
 fn format_datetime(value: Option<NaiveDateTime>) -> Option<String> {
     value.map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
 }
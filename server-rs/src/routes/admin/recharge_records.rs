@@ -7,11 +7,13 @@ use serde::Deserialize;
 use serde_json::{json, Value};
 use sqlx::Row;
 
+use crate::admin_actions::{record_admin_action, AdminAction};
+use crate::money::Money;
 use crate::referral::award_rebate;
 use crate::response::{error, success};
 use crate::state::AppState;
 
-use super::super::auth::require_admin_user_id;
+use super::super::auth::require_admin_permission;
 use super::super::payment_callback::mark_recharge_paid;
 
 #[derive(Deserialize)]
@@ -39,7 +41,7 @@ async fn get_recharge_records(
     Extension(headers): Extension<HeaderMap>,
     Query(query): Query<RechargeQuery>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "recharge-records").await {
         return resp;
     }
 
@@ -96,12 +98,14 @@ async fn get_recharge_records(
     SELECT
       rr.id,
       rr.user_id,
-      CAST(rr.amount AS DOUBLE) as amount,
+      rr.amount,
       rr.payment_method,
       rr.trade_no,
       rr.status,
       rr.created_at,
       rr.paid_at,
+      rr.close_reason,
+      rr.closed_at,
       u.email,
       u.username,
       gcr.code AS gift_card_code
@@ -125,7 +129,13 @@ async fn get_recharge_records(
         Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
     };
 
-    let status_map = [(0, "待支付"), (1, "已支付"), (2, "已取消"), (3, "支付失败")];
+    let status_map = [
+        (0, "待支付"),
+        (1, "已支付"),
+        (2, "已取消"),
+        (3, "支付失败"),
+        (4, "已过期"),
+    ];
 
     let records = rows
     .into_iter()
@@ -147,13 +157,15 @@ async fn get_recharge_records(
         "user_id": row.try_get::<Option<i64>, _>("user_id").unwrap_or(Some(0)).unwrap_or(0),
         "email": row.try_get::<Option<String>, _>("email").ok().flatten().unwrap_or_default(),
         "username": row.try_get::<Option<String>, _>("username").ok().flatten().unwrap_or_default(),
-        "amount": fix_money_precision(parse_decimal(&row, "amount", 0.0)),
+        "amount": Money::from_row_or_zero(&row, "amount"),
         "payment_method": payment_method,
         "trade_no": trade_no,
         "status": status,
         "status_text": status_map.iter().find(|(code, _)| *code == status).map(|(_, text)| *text).unwrap_or("未知状态"),
         "created_at": row.try_get::<Option<chrono::NaiveDateTime>, _>("created_at").ok().flatten().map(format_datetime),
-        "paid_at": row.try_get::<Option<chrono::NaiveDateTime>, _>("paid_at").ok().flatten().map(format_datetime)
+        "paid_at": row.try_get::<Option<chrono::NaiveDateTime>, _>("paid_at").ok().flatten().map(format_datetime),
+        "close_reason": row.try_get::<Option<String>, _>("close_reason").ok().flatten(),
+        "closed_at": row.try_get::<Option<chrono::NaiveDateTime>, _>("closed_at").ok().flatten().map(format_datetime)
       })
     })
     .collect::<Vec<Value>>();
@@ -178,9 +190,10 @@ async fn post_mark_paid(
     Extension(headers): Extension<HeaderMap>,
     Path(trade_no): Path<String>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
-        return resp;
-    }
+    let admin_id = match require_admin_permission(&state, &headers, "recharge-records").await {
+        Ok(value) => value,
+        Err(resp) => return resp,
+    };
 
     let result = match mark_recharge_paid(&state, &trade_no).await {
         Ok(value) => value,
@@ -195,13 +208,27 @@ async fn post_mark_paid(
         let _ = award_rebate(
             &state,
             result.user_id,
-            result.amount,
+            &result.amount,
             "recharge",
             Some(result.record_id),
             Some(&trade_no),
             Some("recharge_rebate"),
         )
         .await;
+        record_admin_action(
+            &state,
+            &headers,
+            admin_id,
+            AdminAction {
+                action: "recharge.mark_paid",
+                route: "POST /api/admin/recharge-records/{tradeNo}/mark-paid",
+                target_type: "recharge",
+                target_id: Some(trade_no.clone()),
+                before: Some(json!({ "status": result.previous_status })),
+                after: Some(json!({ "status": 1, "amount": result.amount })),
+            },
+        )
+        .await;
         return success(json!({ "trade_no": trade_no }), "已入账").into_response();
     }
 
@@ -212,27 +239,6 @@ fn format_datetime(value: chrono::NaiveDateTime) -> String {
     value.format("%Y-%m-%d %H:%M:%S").to_string()
 }
 
-fn fix_money_precision(amount: f64) -> f64 {
-    (amount * 100.0).round() / 100.0
-}
-
-fn parse_decimal(row: &sqlx::mysql::MySqlRow, column: &str, fallback: f64) -> f64 {
-    if let Ok(value) = row.try_get::<Option<f64>, _>(column) {
-        return value.unwrap_or(fallback);
-    }
-    if let Ok(value) = row.try_get::<Option<sqlx::types::BigDecimal>, _>(column) {
-        return value
-            .map(|val| val.to_string().parse::<f64>().unwrap_or(fallback))
-            .unwrap_or(fallback);
-    }
-    if let Ok(value) = row.try_get::<Option<String>, _>(column) {
-        return value
-            .and_then(|val| val.parse::<f64>().ok())
-            .unwrap_or(fallback);
-    }
-    fallback
-}
-
 type SqlxQuery<'a> = sqlx::query::Query<'a, sqlx::MySql, sqlx::mysql::MySqlArguments>;
 
 enum SqlParam {
@@ -0,0 +1,516 @@
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{delete, get, post, put};
+use axum::{Extension, Json, Router};
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sqlx::Row;
+
+use crate::admin_actions::{record_admin_action, AdminAction};
+use crate::admin_permissions::{
+    normalize_permissions, parse_permissions, ALL_PERMISSIONS, PERMISSION_SCOPES,
+};
+use crate::response::{error, success};
+use crate::state::AppState;
+
+use super::super::auth::require_admin_permission;
+
+const SUPER_ADMIN_ROLE: &str = "super_admin";
+
+#[derive(Deserialize)]
+struct RoleRequest {
+    code: Option<String>,
+    name: Option<String>,
+    description: Option<String>,
+    permissions: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+struct AssignRoleRequest {
+    role_id: Option<i64>,
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(get_roles))
+        .route("/", post(post_role))
+        .route("/permissions", get(get_permission_scopes))
+        .route("/admins", get(get_admins))
+        .route("/admins/{user_id}", put(put_admin_role))
+        .route("/{id}", put(put_role))
+        .route("/{id}", delete(delete_role))
+}
+
+async fn get_roles(
+    State(state): State<AppState>,
+    Extension(headers): Extension<HeaderMap>,
+) -> Response {
+    if let Err(resp) = require_admin_permission(&state, &headers, ALL_PERMISSIONS).await {
+        return resp;
+    }
+
+    let rows = match sqlx::query(
+        r#"
+    SELECT r.id, r.code, r.name, r.description, CAST(r.permissions AS CHAR) AS permissions,
+           r.is_system, r.created_at, r.updated_at,
+           (SELECT COUNT(*) FROM users u WHERE u.is_admin = 1 AND u.admin_role_id = r.id) AS admin_count
+    FROM admin_roles r
+    ORDER BY r.id ASC
+    "#,
+    )
+    .fetch_all(&state.db)
+    .await
+    {
+        Ok(value) => value,
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    };
+
+    let roles = rows.into_iter().map(map_role_row).collect::<Vec<Value>>();
+    success(json!({ "data": roles, "total": roles.len() }), "Success").into_response()
+}
+
+async fn get_permission_scopes(
+    State(state): State<AppState>,
+    Extension(headers): Extension<HeaderMap>,
+) -> Response {
+    if let Err(resp) = require_admin_permission(&state, &headers, ALL_PERMISSIONS).await {
+        return resp;
+    }
+
+    let scopes = PERMISSION_SCOPES
+        .iter()
+        .map(|(scope, label)| json!({ "scope": scope, "label": label }))
+        .collect::<Vec<Value>>();
+    success(json!({ "data": scopes }), "Success").into_response()
+}
+
+async fn post_role(
+    State(state): State<AppState>,
+    Extension(headers): Extension<HeaderMap>,
+    Json(body): Json<RoleRequest>,
+) -> Response {
+    let admin_id = match require_admin_permission(&state, &headers, ALL_PERMISSIONS).await {
+        Ok(value) => value,
+        Err(resp) => return resp,
+    };
+
+    let code = body.code.unwrap_or_default().trim().to_lowercase();
+    if !is_valid_code(&code) {
+        return error(
+            StatusCode::BAD_REQUEST,
+            "角色标识只能包含小写字母、数字、下划线和连字符，长度 1-32",
+            None,
+        );
+    }
+    let name = body.name.unwrap_or_default().trim().to_string();
+    if name.is_empty() {
+        return error(StatusCode::BAD_REQUEST, "角色名称不能为空", None);
+    }
+    let permissions = match normalize_permissions(&body.permissions.unwrap_or_default()) {
+        Ok(value) => value,
+        Err(message) => return error(StatusCode::BAD_REQUEST, &message, None),
+    };
+    let description = body
+        .description
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty());
+
+    let result = sqlx::query(
+        r#"
+    INSERT INTO admin_roles (code, name, description, permissions, is_system, created_at, updated_at)
+    VALUES (?, ?, ?, ?, 0, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
+    "#,
+    )
+    .bind(&code)
+    .bind(&name)
+    .bind(&description)
+    .bind(json!(permissions).to_string())
+    .execute(&state.db)
+    .await;
+    let id = match result {
+        Ok(outcome) => outcome.last_insert_id() as i64,
+        Err(err) => {
+            let message = err.to_string();
+            if message.contains("Duplicate") {
+                return error(StatusCode::BAD_REQUEST, "角色标识已存在", None);
+            }
+            return error(StatusCode::INTERNAL_SERVER_ERROR, &message, None);
+        }
+    };
+
+    let role = match fetch_role(&state, id).await {
+        Ok(value) => value,
+        Err(resp) => return resp,
+    };
+    record_admin_action(
+        &state,
+        &headers,
+        admin_id,
+        AdminAction {
+            action: "admin_role.create",
+            route: "POST /api/admin/roles",
+            target_type: "admin_role",
+            target_id: Some(id.to_string()),
+            before: None,
+            after: role.clone(),
+        },
+    )
+    .await;
+    success(role.unwrap_or(Value::Null), "角色已创建").into_response()
+}
+
+async fn put_role(
+    State(state): State<AppState>,
+    Extension(headers): Extension<HeaderMap>,
+    Path(id): Path<i64>,
+    Json(body): Json<RoleRequest>,
+) -> Response {
+    let admin_id = match require_admin_permission(&state, &headers, ALL_PERMISSIONS).await {
+        Ok(value) => value,
+        Err(resp) => return resp,
+    };
+    if id <= 0 {
+        return error(StatusCode::BAD_REQUEST, "ID 无效", None);
+    }
+
+    let before = match fetch_role(&state, id).await {
+        Ok(Some(value)) => value,
+        Ok(None) => return error(StatusCode::NOT_FOUND, "角色不存在", None),
+        Err(resp) => return resp,
+    };
+    let current_code = before
+        .get("code")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string();
+    let is_system = before
+        .get("is_system")
+        .and_then(Value::as_bool)
+        .unwrap_or(false);
+
+    let mut fields: Vec<&str> = Vec::new();
+    let mut values: Vec<Option<String>> = Vec::new();
+    if let Some(raw) = body.code.as_deref() {
+        let code = raw.trim().to_lowercase();
+        if code != current_code {
+            if is_system {
+                return error(StatusCode::BAD_REQUEST, "内置角色的标识不可修改", None);
+            }
+            if !is_valid_code(&code) {
+                return error(
+                    StatusCode::BAD_REQUEST,
+                    "角色标识只能包含小写字母、数字、下划线和连字符，长度 1-32",
+                    None,
+                );
+            }
+            fields.push("code = ?");
+            values.push(Some(code));
+        }
+    }
+    if let Some(raw) = body.name.as_deref() {
+        let name = raw.trim();
+        if name.is_empty() {
+            return error(StatusCode::BAD_REQUEST, "角色名称不能为空", None);
+        }
+        fields.push("name = ?");
+        values.push(Some(name.to_string()));
+    }
+    if let Some(raw) = body.description.as_deref() {
+        fields.push("description = ?");
+        values.push(Some(raw.trim().to_string()).filter(|value| !value.is_empty()));
+    }
+    if let Some(raw) = body.permissions.as_ref() {
+        let permissions = match normalize_permissions(raw) {
+            Ok(value) => value,
+            Err(message) => return error(StatusCode::BAD_REQUEST, &message, None),
+        };
+        // 超级管理员角色必须保留全部权限，避免角色管理入口被锁死
+        if current_code == SUPER_ADMIN_ROLE && permissions != [ALL_PERMISSIONS] {
+            return error(
+                StatusCode::BAD_REQUEST,
+                "超级管理员角色的权限不可修改",
+                None,
+            );
+        }
+        fields.push("permissions = ?");
+        values.push(Some(json!(permissions).to_string()));
+    }
+
+    if fields.is_empty() {
+        return error(StatusCode::BAD_REQUEST, "没有需要更新的字段", None);
+    }
+
+    let sql = format!(
+        "UPDATE admin_roles SET {}, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+        fields.join(", ")
+    );
+    let mut query_builder = sqlx::query(&sql);
+    for value in values {
+        query_builder = query_builder.bind(value);
+    }
+    if let Err(err) = query_builder.bind(id).execute(&state.db).await {
+        let message = err.to_string();
+        if message.contains("Duplicate") {
+            return error(StatusCode::BAD_REQUEST, "角色标识已存在", None);
+        }
+        return error(StatusCode::INTERNAL_SERVER_ERROR, &message, None);
+    }
+
+    let after = match fetch_role(&state, id).await {
+        Ok(value) => value,
+        Err(resp) => return resp,
+    };
+    record_admin_action(
+        &state,
+        &headers,
+        admin_id,
+        AdminAction {
+            action: "admin_role.update",
+            route: "PUT /api/admin/roles/{id}",
+            target_type: "admin_role",
+            target_id: Some(id.to_string()),
+            before: Some(before),
+            after: after.clone(),
+        },
+    )
+    .await;
+    success(after.unwrap_or(Value::Null), "角色已更新").into_response()
+}
+
+async fn delete_role(
+    State(state): State<AppState>,
+    Extension(headers): Extension<HeaderMap>,
+    Path(id): Path<i64>,
+) -> Response {
+    let admin_id = match require_admin_permission(&state, &headers, ALL_PERMISSIONS).await {
+        Ok(value) => value,
+        Err(resp) => return resp,
+    };
+    if id <= 0 {
+        return error(StatusCode::BAD_REQUEST, "ID 无效", None);
+    }
+
+    let before = match fetch_role(&state, id).await {
+        Ok(Some(value)) => value,
+        Ok(None) => return error(StatusCode::NOT_FOUND, "角色不存在", None),
+        Err(resp) => return resp,
+    };
+    if before
+        .get("is_system")
+        .and_then(Value::as_bool)
+        .unwrap_or(false)
+    {
+        return error(StatusCode::BAD_REQUEST, "内置角色不可删除", None);
+    }
+    if before
+        .get("admin_count")
+        .and_then(Value::as_i64)
+        .unwrap_or(0)
+        > 0
+    {
+        return error(
+            StatusCode::BAD_REQUEST,
+            "仍有管理员使用该角色，请先调整其角色",
+            None,
+        );
+    }
+
+    if let Err(err) = sqlx::query("DELETE FROM admin_roles WHERE id = ? AND is_system = 0")
+        .bind(id)
+        .execute(&state.db)
+        .await
+    {
+        return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None);
+    }
+
+    record_admin_action(
+        &state,
+        &headers,
+        admin_id,
+        AdminAction {
+            action: "admin_role.delete",
+            route: "DELETE /api/admin/roles/{id}",
+            target_type: "admin_role",
+            target_id: Some(id.to_string()),
+            before: Some(before),
+            after: None,
+        },
+    )
+    .await;
+    success(Value::Null, "角色已删除").into_response()
+}
+
+async fn get_admins(
+    State(state): State<AppState>,
+    Extension(headers): Extension<HeaderMap>,
+) -> Response {
+    if let Err(resp) = require_admin_permission(&state, &headers, ALL_PERMISSIONS).await {
+        return resp;
+    }
+
+    let rows = match sqlx::query(
+        r#"
+    SELECT u.id, u.email, u.username, u.status, u.admin_role_id,
+           r.code AS role_code, r.name AS role_name
+    FROM users u
+    LEFT JOIN admin_roles r ON r.id = u.admin_role_id
+    WHERE u.is_admin = 1
+    ORDER BY u.id ASC
+    "#,
+    )
+    .fetch_all(&state.db)
+    .await
+    {
+        Ok(value) => value,
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    };
+
+    let admins = rows
+        .into_iter()
+        .map(|row| {
+            json!({
+              "id": row.try_get::<i64, _>("id").unwrap_or(0),
+              "email": row.try_get::<Option<String>, _>("email").ok().flatten().unwrap_or_default(),
+              "username": row.try_get::<Option<String>, _>("username").ok().flatten().unwrap_or_default(),
+              "status": row.try_get::<Option<i64>, _>("status").ok().flatten().unwrap_or(0),
+              "role_id": row.try_get::<Option<i64>, _>("admin_role_id").ok().flatten(),
+              "role_code": row.try_get::<Option<String>, _>("role_code").ok().flatten(),
+              "role_name": row.try_get::<Option<String>, _>("role_name").ok().flatten()
+            })
+        })
+        .collect::<Vec<Value>>();
+    success(json!({ "data": admins, "total": admins.len() }), "Success").into_response()
+}
+
+async fn put_admin_role(
+    State(state): State<AppState>,
+    Extension(headers): Extension<HeaderMap>,
+    Path(user_id): Path<i64>,
+    Json(body): Json<AssignRoleRequest>,
+) -> Response {
+    let admin_id = match require_admin_permission(&state, &headers, ALL_PERMISSIONS).await {
+        Ok(value) => value,
+        Err(resp) => return resp,
+    };
+    if user_id <= 0 {
+        return error(StatusCode::BAD_REQUEST, "ID 无效", None);
+    }
+    if user_id == admin_id {
+        return error(StatusCode::BAD_REQUEST, "不能修改自己的角色", None);
+    }
+
+    let row = match sqlx::query("SELECT is_admin, admin_role_id FROM users WHERE id = ?")
+        .bind(user_id)
+        .fetch_optional(&state.db)
+        .await
+    {
+        Ok(Some(value)) => value,
+        Ok(None) => return error(StatusCode::NOT_FOUND, "用户不存在", None),
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    };
+    if row
+        .try_get::<Option<i64>, _>("is_admin")
+        .ok()
+        .flatten()
+        .unwrap_or(0)
+        != 1
+    {
+        return error(StatusCode::BAD_REQUEST, "该用户不是管理员", None);
+    }
+    let previous_role = row
+        .try_get::<Option<i64>, _>("admin_role_id")
+        .ok()
+        .flatten();
+
+    // role_id 为空表示恢复为未分配角色（视为超级管理员）
+    let role_id = body.role_id.filter(|value| *value > 0);
+    if let Some(value) = role_id {
+        match fetch_role(&state, value).await {
+            Ok(Some(_)) => {}
+            Ok(None) => return error(StatusCode::BAD_REQUEST, "角色不存在", None),
+            Err(resp) => return resp,
+        }
+    }
+
+    if let Err(err) = sqlx::query(
+        "UPDATE users SET admin_role_id = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+    )
+    .bind(role_id)
+    .bind(user_id)
+    .execute(&state.db)
+    .await
+    {
+        return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None);
+    }
+
+    record_admin_action(
+        &state,
+        &headers,
+        admin_id,
+        AdminAction {
+            action: "admin_role.assign",
+            route: "PUT /api/admin/roles/admins/{user_id}",
+            target_type: "user",
+            target_id: Some(user_id.to_string()),
+            before: Some(json!({ "admin_role_id": previous_role })),
+            after: Some(json!({ "admin_role_id": role_id })),
+        },
+    )
+    .await;
+    success(
+        json!({ "user_id": user_id, "role_id": role_id }),
+        "管理员角色已更新",
+    )
+    .into_response()
+}
+
+async fn fetch_role(state: &AppState, id: i64) -> Result<Option<Value>, Response> {
+    let row = sqlx::query(
+        r#"
+    SELECT r.id, r.code, r.name, r.description, CAST(r.permissions AS CHAR) AS permissions,
+           r.is_system, r.created_at, r.updated_at,
+           (SELECT COUNT(*) FROM users u WHERE u.is_admin = 1 AND u.admin_role_id = r.id) AS admin_count
+    FROM admin_roles r
+    WHERE r.id = ?
+    "#,
+    )
+    .bind(id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|err| error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None))?;
+    Ok(row.map(map_role_row))
+}
+
+fn is_valid_code(code: &str) -> bool {
+    !code.is_empty()
+        && code.len() <= 32
+        && code
+            .chars()
+            .all(|ch| ch.is_ascii_lowercase() || ch.is_ascii_digit() || ch == '_' || ch == '-')
+}
+
+fn format_datetime(value: Option<NaiveDateTime>) -> Option<String> {
+    value.map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
+}
+
+fn map_role_row(row: sqlx::mysql::MySqlRow) -> Value {
+    let permissions = parse_permissions(
+        &row.try_get::<Option<String>, _>("permissions")
+            .ok()
+            .flatten()
+            .unwrap_or_default(),
+    );
+    json!({
+      "id": row.try_get::<i64, _>("id").unwrap_or(0),
+      "code": row.try_get::<Option<String>, _>("code").ok().flatten().unwrap_or_default(),
+      "name": row.try_get::<Option<String>, _>("name").ok().flatten().unwrap_or_default(),
+      "description": row.try_get::<Option<String>, _>("description").ok().flatten(),
+      "permissions": permissions,
+      "is_system": row.try_get::<Option<i64>, _>("is_system").ok().flatten().unwrap_or(0) == 1,
+      "admin_count": row.try_get::<Option<i64>, _>("admin_count").ok().flatten().unwrap_or(0),
+      "created_at": format_datetime(row.try_get::<Option<NaiveDateTime>, _>("created_at").ok().flatten()),
+      "updated_at": format_datetime(row.try_get::<Option<NaiveDateTime>, _>("updated_at").ok().flatten())
+    })
+}
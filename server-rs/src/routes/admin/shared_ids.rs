@@ -13,7 +13,7 @@ use crate::shared_ids::{
 };
 use crate::state::AppState;
 
-use super::super::auth::require_admin_user_id;
+use super::super::auth::require_admin_permission;
 
 #[derive(Deserialize)]
 struct SharedIdQuery {
@@ -46,7 +46,7 @@ async fn get_shared_ids(
     Extension(headers): Extension<HeaderMap>,
     Query(query): Query<SharedIdQuery>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "shared-ids").await {
         return resp;
     }
 
@@ -160,7 +160,7 @@ async fn post_shared_id(
     Extension(headers): Extension<HeaderMap>,
     Json(body): Json<SharedIdPayload>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "shared-ids").await {
         return resp;
     }
     let name = body.name.unwrap_or_default().trim().to_string();
@@ -200,7 +200,7 @@ async fn put_shared_id(
     Path(shared_id): Path<i64>,
     Json(body): Json<SharedIdPayload>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "shared-ids").await {
         return resp;
     }
     if shared_id <= 0 {
@@ -259,7 +259,7 @@ async fn delete_shared_id(
     Extension(headers): Extension<HeaderMap>,
     Path(shared_id): Path<i64>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "shared-ids").await {
         return resp;
     }
     if shared_id <= 0 {
@@ -11,7 +11,7 @@ use sqlx::Row;
 use crate::response::{error, success};
 use crate::state::AppState;
 
-use super::super::auth::require_admin_user_id;
+use super::super::auth::require_admin_permission;
 
 #[derive(Deserialize)]
 struct SubscriptionLogsQuery {
@@ -30,6 +30,16 @@ struct SubscriptionLogsQuery {
     end_date: Option<String>,
 }
 
+#[derive(Deserialize)]
+struct AbuseReportsQuery {
+    page: Option<i64>,
+    limit: Option<i64>,
+    #[serde(rename = "pageSize")]
+    page_size: Option<i64>,
+    user_id: Option<String>,
+    action: Option<String>,
+}
+
 #[derive(Deserialize)]
 struct BatchIdsRequest {
     ids: Option<Vec<i64>>,
@@ -41,6 +51,7 @@ pub fn router() -> Router<AppState> {
         .route("/{id}", delete(delete_subscription_log))
         .route("/batch-delete", post(post_batch_delete))
         .route("/export-csv", post(post_export_csv))
+        .route("/abuse-reports", get(get_abuse_reports))
 }
 
 async fn get_subscription_logs(
@@ -48,7 +59,7 @@ async fn get_subscription_logs(
     Extension(headers): Extension<HeaderMap>,
     Query(query): Query<SubscriptionLogsQuery>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "subscription-logs").await {
         return resp;
     }
 
@@ -104,10 +115,11 @@ async fn get_subscription_logs(
 
     let list_sql = format!(
         r#"
-    SELECT s.id, s.user_id, s.type, s.request_ip, s.request_time, s.request_user_agent,
-           u.email AS user_email, u.username
+    SELECT s.id, s.user_id, s.type, s.filters, s.token_id, t.label AS token_label,
+           s.request_ip, s.request_time, s.request_user_agent, u.email AS user_email, u.username
     FROM subscriptions s
     LEFT JOIN users u ON s.user_id = u.id
+    LEFT JOIN subscription_tokens t ON t.id = s.token_id
     {where_clause}
     ORDER BY s.request_time DESC
     LIMIT ? OFFSET ?
@@ -153,6 +165,9 @@ async fn get_subscription_logs(
         "username": row.try_get::<Option<String>, _>("username").ok().flatten().unwrap_or_default(),
         "user_email": row.try_get::<Option<String>, _>("user_email").ok().flatten().unwrap_or_default(),
         "type": row.try_get::<Option<String>, _>("type").ok().flatten().unwrap_or_default(),
+        "filters": row.try_get::<Option<String>, _>("filters").ok().flatten(),
+        "token_id": row.try_get::<Option<i64>, _>("token_id").ok().flatten(),
+        "token_label": row.try_get::<Option<String>, _>("token_label").ok().flatten(),
         "request_ip": row.try_get::<Option<String>, _>("request_ip").ok().flatten().unwrap_or_default(),
         "request_time": format_datetime(row.try_get::<Option<NaiveDateTime>, _>("request_time").ok().flatten()),
         "request_user_agent": row.try_get::<Option<String>, _>("request_user_agent").ok().flatten().unwrap_or_default()
@@ -176,12 +191,134 @@ async fn get_subscription_logs(
     .into_response()
 }
 
+/// 订阅链接滥用检测报告（疑似泄露的 token）
+async fn get_abuse_reports(
+    State(state): State<AppState>,
+    Extension(headers): Extension<HeaderMap>,
+    Query(query): Query<AbuseReportsQuery>,
+) -> Response {
+    if let Err(resp) = require_admin_permission(&state, &headers, "subscription-logs").await {
+        return resp;
+    }
+
+    let page = query.page.unwrap_or(1).max(1);
+    let limit_raw = query.limit.or(query.page_size).unwrap_or(20);
+    let limit = limit_raw.clamp(1, 200);
+    let offset = (page - 1) * limit;
+
+    let mut conditions: Vec<&str> = Vec::new();
+    let mut params: Vec<SqlParam> = Vec::new();
+    if let Some(user_id) = query
+        .user_id
+        .as_deref()
+        .and_then(|value| value.trim().parse::<i64>().ok())
+    {
+        conditions.push("r.user_id = ?");
+        params.push(SqlParam::I64(user_id));
+    }
+    let action = query.action.unwrap_or_default().trim().to_string();
+    if !action.is_empty() {
+        conditions.push("r.action = ?");
+        params.push(SqlParam::String(action));
+    }
+    let where_clause = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    };
+
+    let total_sql =
+        format!("SELECT COUNT(*) as total FROM subscription_abuse_reports r {where_clause}");
+    let mut total_query = sqlx::query(&total_sql);
+    total_query = bind_params(total_query, &params);
+    let total = match total_query.fetch_optional(&state.db).await {
+        Ok(row) => row
+            .and_then(|row| row.try_get::<Option<i64>, _>("total").ok().flatten())
+            .unwrap_or(0),
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    };
+
+    let list_sql = format!(
+        r#"
+    SELECT r.id, r.user_id, r.token_id, r.window_minutes, r.ip_count, r.network_count,
+           r.user_agent_count, r.ips, r.user_agents, r.reasons, r.action, r.notified, r.created_at,
+           t.label AS token_label, t.revoked_at AS token_revoked_at, u.username, u.email
+    FROM subscription_abuse_reports r
+    LEFT JOIN users u ON u.id = r.user_id
+    LEFT JOIN subscription_tokens t ON t.id = r.token_id
+    {where_clause}
+    ORDER BY r.id DESC
+    LIMIT ? OFFSET ?
+    "#
+    );
+    let mut list_query = sqlx::query(&list_sql);
+    list_query = bind_params(list_query, &params);
+    let rows = match list_query
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&state.db)
+        .await
+    {
+        Ok(value) => value,
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    };
+
+    let parse_list = |raw: Option<String>| {
+        raw.and_then(|value| serde_json::from_str::<Value>(&value).ok())
+            .unwrap_or_else(|| json!([]))
+    };
+    let items = rows
+        .into_iter()
+        .map(|row| {
+            let reasons = row
+                .try_get::<Option<String>, _>("reasons")
+                .ok()
+                .flatten()
+                .unwrap_or_default();
+            json!({
+              "id": row.try_get::<i64, _>("id").unwrap_or(0),
+              "user_id": row.try_get::<i64, _>("user_id").unwrap_or(0),
+              "username": row.try_get::<Option<String>, _>("username").ok().flatten(),
+              "email": row.try_get::<Option<String>, _>("email").ok().flatten(),
+              "token_id": row.try_get::<Option<i64>, _>("token_id").ok().flatten(),
+              "token_label": row.try_get::<Option<String>, _>("token_label").ok().flatten(),
+              "token_revoked_at": format_datetime(row.try_get::<Option<NaiveDateTime>, _>("token_revoked_at").ok().flatten()),
+              "window_minutes": row.try_get::<Option<i64>, _>("window_minutes").ok().flatten().unwrap_or(0),
+              "ip_count": row.try_get::<Option<i64>, _>("ip_count").ok().flatten().unwrap_or(0),
+              "network_count": row.try_get::<Option<i64>, _>("network_count").ok().flatten().unwrap_or(0),
+              "user_agent_count": row.try_get::<Option<i64>, _>("user_agent_count").ok().flatten().unwrap_or(0),
+              "ips": parse_list(row.try_get::<Option<String>, _>("ips").ok().flatten()),
+              "user_agents": parse_list(row.try_get::<Option<String>, _>("user_agents").ok().flatten()),
+              "reasons": reasons.split(',').filter(|item| !item.is_empty()).collect::<Vec<&str>>(),
+              "action": row.try_get::<Option<String>, _>("action").ok().flatten().unwrap_or_default(),
+              "notified": row.try_get::<Option<i64>, _>("notified").ok().flatten().unwrap_or(0) == 1,
+              "created_at": format_datetime(row.try_get::<Option<NaiveDateTime>, _>("created_at").ok().flatten())
+            })
+        })
+        .collect::<Vec<Value>>();
+
+    success(
+        json!({
+          "data": items,
+          "total": total,
+          "pagination": {
+            "total": total,
+            "page": page,
+            "limit": limit,
+            "pages": if total > 0 { ((total as f64) / (limit as f64)).ceil() as i64 } else { 0 }
+          }
+        }),
+        "Success",
+    )
+    .into_response()
+}
+
 async fn delete_subscription_log(
     State(state): State<AppState>,
     Extension(headers): Extension<HeaderMap>,
     Path(id): Path<i64>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "subscription-logs").await {
         return resp;
     }
     if id <= 0 {
@@ -202,7 +339,7 @@ async fn post_batch_delete(
     Extension(headers): Extension<HeaderMap>,
     Json(body): Json<BatchIdsRequest>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "subscription-logs").await {
         return resp;
     }
 
@@ -234,7 +371,7 @@ async fn post_export_csv(
     Extension(headers): Extension<HeaderMap>,
     Json(body): Json<BatchIdsRequest>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "subscription-logs").await {
         return resp;
     }
 
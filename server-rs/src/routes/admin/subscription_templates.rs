@@ -0,0 +1,787 @@
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Extension, Json, Router};
+use chrono::{Duration, Local, NaiveDateTime};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sqlx::Row;
+
+use crate::admin_actions::{record_admin_action, AdminAction};
+use crate::proxy_groups::parse_node_tags;
+use crate::response::{error, success};
+use crate::state::AppState;
+use crate::subscription::{
+    generate_clash_config, generate_singbox_config, generate_surge_config, generate_xray_config,
+    SubscriptionNode, SubscriptionUser,
+};
+use crate::subscription_templates::{
+    apply_template, builtin_template, invalidate_templates, is_template_kind,
+    load_active_templates, template_format, TEMPLATE_KINDS,
+};
+
+use super::super::auth::require_admin_permission;
+
+/// 预览时使用的示例用户凭据
+const SAMPLE_UUID: &str = "00000000-0000-4000-8000-000000000000";
+const SAMPLE_PASSWORD: &str = "preview-password";
+
+#[derive(Deserialize)]
+struct TemplatesQuery {
+    kind: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CreateTemplateRequest {
+    kind: Option<String>,
+    name: Option<String>,
+    content: Option<String>,
+    note: Option<String>,
+    activate: Option<bool>,
+}
+
+#[derive(Deserialize)]
+struct UpdateTemplateRequest {
+    name: Option<String>,
+    note: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct PreviewRequest {
+    kind: Option<String>,
+    content: Option<String>,
+    template_id: Option<i64>,
+    user_id: Option<i64>,
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/subscription-templates",
+            get(get_templates).post(post_template),
+        )
+        .route("/subscription-templates/preview", post(post_preview))
+        .route(
+            "/subscription-templates/defaults/{kind}",
+            get(get_default_template),
+        )
+        .route(
+            "/subscription-templates/{id}",
+            get(get_template).put(put_template).delete(delete_template),
+        )
+        .route(
+            "/subscription-templates/{id}/activate",
+            post(post_activate_template),
+        )
+        .route(
+            "/subscription-templates/{id}/deactivate",
+            post(post_deactivate_template),
+        )
+}
+
+async fn get_templates(
+    State(state): State<AppState>,
+    Extension(headers): Extension<HeaderMap>,
+    Query(query): Query<TemplatesQuery>,
+) -> Response {
+    if let Err(resp) = require_admin_permission(&state, &headers, "subscription-templates").await {
+        return resp;
+    }
+
+    let kind = query
+        .kind
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty());
+    if let Some(kind) = kind {
+        if !is_template_kind(kind) {
+            return error(StatusCode::BAD_REQUEST, "不支持的模板类型", None);
+        }
+    }
+
+    let rows = match sqlx::query(
+        r#"
+    SELECT id, kind, version, name, note, is_active, CHAR_LENGTH(content) AS content_length,
+           created_by, created_at, updated_at
+    FROM subscription_templates
+    WHERE (? IS NULL OR kind = ?)
+    ORDER BY kind ASC, version DESC
+    "#,
+    )
+    .bind(kind)
+    .bind(kind)
+    .fetch_all(&state.db)
+    .await
+    {
+        Ok(value) => value,
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    };
+
+    let items = rows
+        .into_iter()
+        .map(|row| map_template_row(row, false))
+        .collect::<Vec<Value>>();
+    // 各类型当前生效的版本，未启用后台模板时为 null（使用内置模板）
+    let active = TEMPLATE_KINDS
+        .iter()
+        .map(|kind| {
+            let version = items
+                .iter()
+                .find(|item| item["kind"] == *kind && item["is_active"] == 1)
+                .map(|item| item["version"].clone())
+                .unwrap_or(Value::Null);
+            (kind.to_string(), version)
+        })
+        .collect::<serde_json::Map<String, Value>>();
+
+    success(
+        json!({ "data": items, "kinds": TEMPLATE_KINDS, "active": active }),
+        "Success",
+    )
+    .into_response()
+}
+
+async fn get_default_template(
+    State(state): State<AppState>,
+    Extension(headers): Extension<HeaderMap>,
+    Path(kind): Path<String>,
+) -> Response {
+    if let Err(resp) = require_admin_permission(&state, &headers, "subscription-templates").await {
+        return resp;
+    }
+    match builtin_template(&kind) {
+        Some(content) => {
+            success(json!({ "kind": kind, "content": content }), "Success").into_response()
+        }
+        None => error(StatusCode::BAD_REQUEST, "不支持的模板类型", None),
+    }
+}
+
+async fn get_template(
+    State(state): State<AppState>,
+    Extension(headers): Extension<HeaderMap>,
+    Path(id): Path<i64>,
+) -> Response {
+    if let Err(resp) = require_admin_permission(&state, &headers, "subscription-templates").await {
+        return resp;
+    }
+    match fetch_template(&state, id).await {
+        Ok(Some(payload)) => success(payload, "Success").into_response(),
+        Ok(None) => error(StatusCode::NOT_FOUND, "模板不存在", None),
+        Err(message) => error(StatusCode::INTERNAL_SERVER_ERROR, &message, None),
+    }
+}
+
+/// 保存新版本；模板内容不可原地修改，每次保存都生成新的版本号
+async fn post_template(
+    State(state): State<AppState>,
+    Extension(headers): Extension<HeaderMap>,
+    Json(body): Json<CreateTemplateRequest>,
+) -> Response {
+    let admin_id = match require_admin_permission(&state, &headers, "subscription-templates").await
+    {
+        Ok(value) => value,
+        Err(resp) => return resp,
+    };
+
+    let kind = body.kind.unwrap_or_default().trim().to_lowercase();
+    if !is_template_kind(&kind) {
+        return error(StatusCode::BAD_REQUEST, "不支持的模板类型", None);
+    }
+    let content = body.content.unwrap_or_default();
+    if let Err(message) = apply_template(&mut Default::default(), &kind, &content) {
+        return error(StatusCode::BAD_REQUEST, &message, None);
+    }
+    let name = body.name.unwrap_or_default().trim().to_string();
+    if name.chars().count() > 100 {
+        return error(StatusCode::BAD_REQUEST, "模板名称不能超过 100 个字符", None);
+    }
+    let activate = body.activate.unwrap_or(false);
+
+    let mut tx = match state.db.begin().await {
+        Ok(tx) => tx,
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    };
+    let version = match sqlx::query(
+        "SELECT COALESCE(MAX(version), 0) + 1 AS next_version FROM subscription_templates WHERE kind = ? FOR UPDATE",
+    )
+    .bind(&kind)
+    .fetch_one(&mut *tx)
+    .await
+    {
+        Ok(row) => row
+            .try_get::<Option<i64>, _>("next_version")
+            .ok()
+            .flatten()
+            .unwrap_or(1),
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    };
+    if activate {
+        if let Err(err) =
+            sqlx::query("UPDATE subscription_templates SET is_active = 0 WHERE kind = ?")
+                .bind(&kind)
+                .execute(&mut *tx)
+                .await
+        {
+            return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None);
+        }
+    }
+    let id = match sqlx::query(
+        r#"
+    INSERT INTO subscription_templates
+      (kind, version, name, content, note, is_active, created_by, created_at, updated_at)
+    VALUES (?, ?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
+    "#,
+    )
+    .bind(&kind)
+    .bind(version)
+    .bind(&name)
+    .bind(&content)
+    .bind(body.note.map(|value| value.trim().to_string()))
+    .bind(if activate { 1 } else { 0 })
+    .bind(admin_id)
+    .execute(&mut *tx)
+    .await
+    {
+        Ok(value) => value.last_insert_id() as i64,
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    };
+    if let Err(err) = tx.commit().await {
+        return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None);
+    }
+
+    if activate {
+        invalidate_templates(&state).await;
+    }
+    record_admin_action(
+        &state,
+        &headers,
+        admin_id,
+        AdminAction {
+            action: "subscription_template.create",
+            route: "POST /api/admin/subscription-templates",
+            target_type: "subscription_template",
+            target_id: Some(id.to_string()),
+            before: None,
+            after: Some(json!({
+              "kind": kind,
+              "version": version,
+              "name": name,
+              "is_active": activate
+            })),
+        },
+    )
+    .await;
+    match fetch_template(&state, id).await {
+        Ok(payload) => success(payload.unwrap_or(Value::Null), "保存成功").into_response(),
+        Err(message) => error(StatusCode::INTERNAL_SERVER_ERROR, &message, None),
+    }
+}
+
+async fn put_template(
+    State(state): State<AppState>,
+    Extension(headers): Extension<HeaderMap>,
+    Path(id): Path<i64>,
+    Json(body): Json<UpdateTemplateRequest>,
+) -> Response {
+    let admin_id = match require_admin_permission(&state, &headers, "subscription-templates").await
+    {
+        Ok(value) => value,
+        Err(resp) => return resp,
+    };
+    if id <= 0 {
+        return error(StatusCode::BAD_REQUEST, "ID 无效", None);
+    }
+    let name = body.name.map(|value| value.trim().to_string());
+    if name
+        .as_ref()
+        .map(|value| value.chars().count() > 100)
+        .unwrap_or(false)
+    {
+        return error(StatusCode::BAD_REQUEST, "模板名称不能超过 100 个字符", None);
+    }
+    let note = body.note.map(|value| value.trim().to_string());
+    if name.is_none() && note.is_none() {
+        return error(StatusCode::BAD_REQUEST, "没有需要更新的字段", None);
+    }
+
+    let before = match fetch_template_summary(&state, id).await {
+        Ok(Some(value)) => value,
+        Ok(None) => return error(StatusCode::NOT_FOUND, "模板不存在", None),
+        Err(message) => return error(StatusCode::INTERNAL_SERVER_ERROR, &message, None),
+    };
+    if let Err(err) = sqlx::query(
+        r#"
+    UPDATE subscription_templates
+    SET name = COALESCE(?, name), note = COALESCE(?, note), updated_at = CURRENT_TIMESTAMP
+    WHERE id = ?
+    "#,
+    )
+    .bind(&name)
+    .bind(&note)
+    .bind(id)
+    .execute(&state.db)
+    .await
+    {
+        return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None);
+    }
+
+    let after = fetch_template_summary(&state, id).await.ok().flatten();
+    record_admin_action(
+        &state,
+        &headers,
+        admin_id,
+        AdminAction {
+            action: "subscription_template.update",
+            route: "PUT /api/admin/subscription-templates/{id}",
+            target_type: "subscription_template",
+            target_id: Some(id.to_string()),
+            before: Some(before),
+            after,
+        },
+    )
+    .await;
+    match fetch_template(&state, id).await {
+        Ok(payload) => success(payload.unwrap_or(Value::Null), "更新成功").into_response(),
+        Err(message) => error(StatusCode::INTERNAL_SERVER_ERROR, &message, None),
+    }
+}
+
+async fn delete_template(
+    State(state): State<AppState>,
+    Extension(headers): Extension<HeaderMap>,
+    Path(id): Path<i64>,
+) -> Response {
+    let admin_id = match require_admin_permission(&state, &headers, "subscription-templates").await
+    {
+        Ok(value) => value,
+        Err(resp) => return resp,
+    };
+    if id <= 0 {
+        return error(StatusCode::BAD_REQUEST, "ID 无效", None);
+    }
+
+    let before = match fetch_template_summary(&state, id).await {
+        Ok(Some(value)) => value,
+        Ok(None) => return error(StatusCode::NOT_FOUND, "模板不存在", None),
+        Err(message) => return error(StatusCode::INTERNAL_SERVER_ERROR, &message, None),
+    };
+    match sqlx::query("DELETE FROM subscription_templates WHERE id = ? AND is_active = 0")
+        .bind(id)
+        .execute(&state.db)
+        .await
+    {
+        Ok(outcome) if outcome.rows_affected() == 0 => {
+            return error(
+                StatusCode::BAD_REQUEST,
+                "生效中的模板不能删除，请先停用或启用其他版本",
+                None,
+            );
+        }
+        Ok(_) => {}
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    }
+
+    record_admin_action(
+        &state,
+        &headers,
+        admin_id,
+        AdminAction {
+            action: "subscription_template.delete",
+            route: "DELETE /api/admin/subscription-templates/{id}",
+            target_type: "subscription_template",
+            target_id: Some(id.to_string()),
+            before: Some(before),
+            after: None,
+        },
+    )
+    .await;
+    success(Value::Null, "删除成功").into_response()
+}
+
+/// 启用指定版本，同类型的其他版本自动停用
+async fn post_activate_template(
+    State(state): State<AppState>,
+    Extension(headers): Extension<HeaderMap>,
+    Path(id): Path<i64>,
+) -> Response {
+    let admin_id = match require_admin_permission(&state, &headers, "subscription-templates").await
+    {
+        Ok(value) => value,
+        Err(resp) => return resp,
+    };
+    if id <= 0 {
+        return error(StatusCode::BAD_REQUEST, "ID 无效", None);
+    }
+
+    let mut tx = match state.db.begin().await {
+        Ok(tx) => tx,
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    };
+    let row = match sqlx::query(
+        "SELECT kind, version, content FROM subscription_templates WHERE id = ? FOR UPDATE",
+    )
+    .bind(id)
+    .fetch_optional(&mut *tx)
+    .await
+    {
+        Ok(Some(row)) => row,
+        Ok(None) => return error(StatusCode::NOT_FOUND, "模板不存在", None),
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    };
+    let kind = row
+        .try_get::<Option<String>, _>("kind")
+        .ok()
+        .flatten()
+        .unwrap_or_default();
+    let version = row.try_get::<Option<i64>, _>("version").ok().flatten();
+    let content = row
+        .try_get::<Option<String>, _>("content")
+        .ok()
+        .flatten()
+        .unwrap_or_default();
+    // 内置模板或校验规则可能已升级，启用前重新校验
+    if let Err(message) = apply_template(&mut Default::default(), &kind, &content) {
+        return error(StatusCode::BAD_REQUEST, &message, None);
+    }
+    let previous = match sqlx::query(
+        "SELECT version FROM subscription_templates WHERE kind = ? AND is_active = 1 LIMIT 1",
+    )
+    .bind(&kind)
+    .fetch_optional(&mut *tx)
+    .await
+    {
+        Ok(row) => row.and_then(|row| row.try_get::<Option<i64>, _>("version").ok().flatten()),
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    };
+    if let Err(err) = sqlx::query(
+        r#"
+    UPDATE subscription_templates
+    SET is_active = IF(id = ?, 1, 0), updated_at = CURRENT_TIMESTAMP
+    WHERE kind = ? AND (id = ? OR is_active = 1)
+    "#,
+    )
+    .bind(id)
+    .bind(&kind)
+    .bind(id)
+    .execute(&mut *tx)
+    .await
+    {
+        return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None);
+    }
+    if let Err(err) = tx.commit().await {
+        return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None);
+    }
+
+    invalidate_templates(&state).await;
+    record_admin_action(
+        &state,
+        &headers,
+        admin_id,
+        AdminAction {
+            action: "subscription_template.activate",
+            route: "POST /api/admin/subscription-templates/{id}/activate",
+            target_type: "subscription_template",
+            target_id: Some(id.to_string()),
+            before: Some(json!({ "kind": kind, "active_version": previous })),
+            after: Some(json!({ "kind": kind, "active_version": version })),
+        },
+    )
+    .await;
+    success(
+        json!({ "id": id, "kind": kind, "version": version }),
+        "已启用",
+    )
+    .into_response()
+}
+
+/// 停用指定版本，该类型回退到内置模板
+async fn post_deactivate_template(
+    State(state): State<AppState>,
+    Extension(headers): Extension<HeaderMap>,
+    Path(id): Path<i64>,
+) -> Response {
+    let admin_id = match require_admin_permission(&state, &headers, "subscription-templates").await
+    {
+        Ok(value) => value,
+        Err(resp) => return resp,
+    };
+    if id <= 0 {
+        return error(StatusCode::BAD_REQUEST, "ID 无效", None);
+    }
+
+    match sqlx::query(
+        "UPDATE subscription_templates SET is_active = 0, updated_at = CURRENT_TIMESTAMP WHERE id = ? AND is_active = 1",
+    )
+    .bind(id)
+    .execute(&state.db)
+    .await
+    {
+        Ok(outcome) if outcome.rows_affected() == 0 => {
+            return error(StatusCode::BAD_REQUEST, "模板不存在或未启用", None);
+        }
+        Ok(_) => {}
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    }
+
+    invalidate_templates(&state).await;
+    record_admin_action(
+        &state,
+        &headers,
+        admin_id,
+        AdminAction {
+            action: "subscription_template.deactivate",
+            route: "POST /api/admin/subscription-templates/{id}/deactivate",
+            target_type: "subscription_template",
+            target_id: Some(id.to_string()),
+            before: Some(json!({ "is_active": 1 })),
+            after: Some(json!({ "is_active": 0 })),
+        },
+    )
+    .await;
+    success(Value::Null, "已停用，将使用内置模板").into_response()
+}
+
+/// 用示例用户（或指定用户）与启用节点渲染模板，不写入订阅记录
+async fn post_preview(
+    State(state): State<AppState>,
+    Extension(headers): Extension<HeaderMap>,
+    Json(body): Json<PreviewRequest>,
+) -> Response {
+    if let Err(resp) = require_admin_permission(&state, &headers, "subscription-templates").await {
+        return resp;
+    }
+
+    let (kind, content) = match (body.content, body.template_id) {
+        (Some(content), _) => (body.kind.unwrap_or_default().trim().to_lowercase(), content),
+        (None, Some(id)) => {
+            let row =
+                match sqlx::query("SELECT kind, content FROM subscription_templates WHERE id = ?")
+                    .bind(id)
+                    .fetch_optional(&state.db)
+                    .await
+                {
+                    Ok(Some(row)) => row,
+                    Ok(None) => return error(StatusCode::NOT_FOUND, "模板不存在", None),
+                    Err(err) => {
+                        return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None)
+                    }
+                };
+            let read = |key: &str| {
+                row.try_get::<Option<String>, _>(key)
+                    .ok()
+                    .flatten()
+                    .unwrap_or_default()
+            };
+            (read("kind"), read("content"))
+        }
+        (None, None) => return error(StatusCode::BAD_REQUEST, "缺少模板内容", None),
+    };
+    if !is_template_kind(&kind) {
+        return error(StatusCode::BAD_REQUEST, "不支持的模板类型", None);
+    }
+
+    // 其余类型沿用当前生效的模板，只替换正在预览的这一项
+    let mut templates = load_active_templates(&state).await;
+    if let Err(message) = apply_template(&mut templates, &kind, &content) {
+        return error(StatusCode::BAD_REQUEST, &message, None);
+    }
+
+    let (user, user_class) = match body.user_id.filter(|value| *value > 0) {
+        Some(user_id) => match fetch_preview_user(&state, user_id).await {
+            Ok(Some(value)) => value,
+            Ok(None) => return error(StatusCode::NOT_FOUND, "用户不存在", None),
+            Err(message) => return error(StatusCode::INTERNAL_SERVER_ERROR, &message, None),
+        },
+        None => (sample_user(), None),
+    };
+    let nodes = match fetch_preview_nodes(&state, user_class).await {
+        Ok(value) => value,
+        Err(message) => return error(StatusCode::INTERNAL_SERVER_ERROR, &message, None),
+    };
+
+    let format = template_format(&kind);
+    let rendered = match format {
+        "singbox" => generate_singbox_config(&nodes, &user, &templates),
+        "surge" => generate_surge_config(&nodes, &user, &templates),
+        "xray" => generate_xray_config(&nodes, &user, &templates),
+        _ => generate_clash_config(&nodes, &user, &templates),
+    };
+
+    success(
+        json!({
+          "kind": kind,
+          "format": format,
+          "user_id": body.user_id.filter(|value| *value > 0),
+          "node_count": nodes.len(),
+          "content": rendered
+        }),
+        "Success",
+    )
+    .into_response()
+}
+
+fn sample_user() -> SubscriptionUser {
+    let expire_time = (Local::now() + Duration::days(30)).naive_local();
+    SubscriptionUser {
+        id: 0,
+        uuid: Some(SAMPLE_UUID.to_string()),
+        passwd: Some(SAMPLE_PASSWORD.to_string()),
+        transfer_enable: 100 * 1024 * 1024 * 1024,
+        transfer_total: 0,
+        upload_traffic: 0,
+        download_traffic: 0,
+        class_expire_time: Some(expire_time),
+        expire_time: Some(expire_time),
+    }
+}
+
+async fn fetch_preview_user(
+    state: &AppState,
+    user_id: i64,
+) -> Result<Option<(SubscriptionUser, Option<i64>)>, String> {
+    let row = sqlx::query(
+        r#"
+    SELECT id, uuid, passwd, class, transfer_enable, transfer_total, upload_traffic,
+           download_traffic, class_expire_time, expire_time
+    FROM users
+    WHERE id = ?
+    "#,
+    )
+    .bind(user_id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|err| err.to_string())?;
+
+    Ok(row.map(|row| {
+        let read_i64 = |key: &str| row.try_get::<Option<i64>, _>(key).ok().flatten();
+        let read_time = |key: &str| row.try_get::<Option<NaiveDateTime>, _>(key).ok().flatten();
+        let user = SubscriptionUser {
+            id: read_i64("id").unwrap_or(0),
+            uuid: row.try_get::<Option<String>, _>("uuid").ok().flatten(),
+            passwd: row.try_get::<Option<String>, _>("passwd").ok().flatten(),
+            transfer_enable: read_i64("transfer_enable").unwrap_or(0),
+            transfer_total: read_i64("transfer_total").unwrap_or(0),
+            upload_traffic: read_i64("upload_traffic").unwrap_or(0),
+            download_traffic: read_i64("download_traffic").unwrap_or(0),
+            class_expire_time: read_time("class_expire_time"),
+            expire_time: read_time("expire_time"),
+        };
+        (user, Some(read_i64("class").unwrap_or(0)))
+    }))
+}
+
+/// 启用的节点；指定用户时只取其等级可用的节点
+async fn fetch_preview_nodes(
+    state: &AppState,
+    user_class: Option<i64>,
+) -> Result<Vec<SubscriptionNode>, String> {
+    let rows = sqlx::query(
+        r#"
+    SELECT id, name, type, CAST(node_config AS CHAR) AS node_config, CAST(tags AS CHAR) AS tags,
+           node_class, CAST(traffic_multiplier AS DOUBLE) AS traffic_multiplier
+    FROM nodes
+    WHERE status = 1 AND (? IS NULL OR node_class <= ?)
+    ORDER BY node_class ASC, id ASC
+    "#,
+    )
+    .bind(user_class)
+    .bind(user_class)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|err| err.to_string())?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let read_string = |key: &str| {
+                row.try_get::<Option<String>, _>(key)
+                    .ok()
+                    .flatten()
+                    .unwrap_or_default()
+            };
+            SubscriptionNode {
+                id: row.try_get::<i64, _>("id").unwrap_or(0),
+                name: read_string("name"),
+                node_type: read_string("type"),
+                node_config: serde_json::from_str::<Value>(&read_string("node_config"))
+                    .unwrap_or_else(|_| json!({})),
+                tags: parse_node_tags(Some(&read_string("tags"))),
+                node_class: row
+                    .try_get::<Option<i64>, _>("node_class")
+                    .ok()
+                    .flatten()
+                    .unwrap_or(0),
+                traffic_multiplier: row
+                    .try_get::<Option<f64>, _>("traffic_multiplier")
+                    .ok()
+                    .flatten()
+                    .unwrap_or(1.0),
+            }
+        })
+        .collect())
+}
+
+async fn fetch_template(state: &AppState, id: i64) -> Result<Option<Value>, String> {
+    let row = sqlx::query(
+        r#"
+    SELECT id, kind, version, name, note, is_active, content, CHAR_LENGTH(content) AS content_length,
+           created_by, created_at, updated_at
+    FROM subscription_templates
+    WHERE id = ?
+    "#,
+    )
+    .bind(id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|err| err.to_string())?;
+    Ok(row.map(|row| map_template_row(row, true)))
+}
+
+/// 操作日志使用的快照（不含模板正文）
+async fn fetch_template_summary(state: &AppState, id: i64) -> Result<Option<Value>, String> {
+    let row = sqlx::query(
+        "SELECT kind, version, name, note, is_active FROM subscription_templates WHERE id = ?",
+    )
+    .bind(id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|err| err.to_string())?;
+    Ok(row.map(|row| {
+        json!({
+          "kind": row.try_get::<Option<String>, _>("kind").ok().flatten(),
+          "version": row.try_get::<Option<i64>, _>("version").ok().flatten(),
+          "name": row.try_get::<Option<String>, _>("name").ok().flatten(),
+          "note": row.try_get::<Option<String>, _>("note").ok().flatten(),
+          "is_active": row.try_get::<Option<i64>, _>("is_active").ok().flatten()
+        })
+    }))
+}
+
+fn format_datetime(value: Option<NaiveDateTime>) -> Option<String> {
+    value.map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
+}
+
+fn map_template_row(row: sqlx::mysql::MySqlRow, with_content: bool) -> Value {
+    let mut value = json!({
+      "id": row.try_get::<i64, _>("id").unwrap_or(0),
+      "kind": row.try_get::<Option<String>, _>("kind").ok().flatten().unwrap_or_default(),
+      "version": row.try_get::<Option<i64>, _>("version").ok().flatten().unwrap_or(0),
+      "name": row.try_get::<Option<String>, _>("name").ok().flatten().unwrap_or_default(),
+      "note": row.try_get::<Option<String>, _>("note").ok().flatten(),
+      "is_active": row.try_get::<Option<i64>, _>("is_active").ok().flatten().unwrap_or(0),
+      "content_length": row.try_get::<Option<i64>, _>("content_length").ok().flatten().unwrap_or(0),
+      "created_by": row.try_get::<Option<i64>, _>("created_by").ok().flatten(),
+      "created_at": format_datetime(row.try_get::<Option<NaiveDateTime>, _>("created_at").ok().flatten()),
+      "updated_at": format_datetime(row.try_get::<Option<NaiveDateTime>, _>("updated_at").ok().flatten())
+    });
+    if with_content {
+        value["content"] = json!(row
+            .try_get::<Option<String>, _>("content")
+            .ok()
+            .flatten()
+            .unwrap_or_default());
+    }
+    value
+}
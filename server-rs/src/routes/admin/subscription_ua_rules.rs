@@ -0,0 +1,288 @@
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{delete, get, post, put};
+use axum::{Extension, Json, Router};
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sqlx::Row;
+
+use crate::response::{error, success};
+use crate::state::AppState;
+use crate::subscription_ua::{
+    invalidate_ua_rules, is_supported_format, load_fallback_format, load_ua_rules, match_ua_rule,
+    SUBSCRIPTION_FORMATS,
+};
+
+use super::super::auth::require_admin_permission;
+
+#[derive(Deserialize)]
+struct UaRuleRequest {
+    pattern: Option<String>,
+    format: Option<String>,
+    priority: Option<i64>,
+    enabled: Option<i64>,
+    description: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct UaRuleTestRequest {
+    user_agent: Option<String>,
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/subscription-ua-rules", get(get_ua_rules))
+        .route("/subscription-ua-rules", post(post_ua_rule))
+        .route("/subscription-ua-rules/test", post(post_ua_rule_test))
+        .route("/subscription-ua-rules/{id}", put(put_ua_rule))
+        .route("/subscription-ua-rules/{id}", delete(delete_ua_rule))
+}
+
+async fn get_ua_rules(
+    State(state): State<AppState>,
+    Extension(headers): Extension<HeaderMap>,
+) -> Response {
+    if let Err(resp) = require_admin_permission(&state, &headers, "subscription-ua-rules").await {
+        return resp;
+    }
+
+    let rows = match sqlx::query(
+        r#"
+    SELECT id, pattern, format, priority, enabled, description, created_at, updated_at
+    FROM subscription_ua_rules
+    ORDER BY priority DESC, id ASC
+    "#,
+    )
+    .fetch_all(&state.db)
+    .await
+    {
+        Ok(value) => value,
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    };
+
+    let items = rows
+        .into_iter()
+        .map(map_ua_rule_row)
+        .collect::<Vec<Value>>();
+    success(
+        json!({
+          "data": items,
+          "formats": SUBSCRIPTION_FORMATS,
+          "fallback_format": load_fallback_format(&state).await
+        }),
+        "Success",
+    )
+    .into_response()
+}
+
+async fn post_ua_rule(
+    State(state): State<AppState>,
+    Extension(headers): Extension<HeaderMap>,
+    Json(body): Json<UaRuleRequest>,
+) -> Response {
+    if let Err(resp) = require_admin_permission(&state, &headers, "subscription-ua-rules").await {
+        return resp;
+    }
+
+    let pattern = body.pattern.unwrap_or_default().trim().to_lowercase();
+    let format = body.format.unwrap_or_default().trim().to_lowercase();
+    if pattern.is_empty() || format.is_empty() {
+        return error(StatusCode::BAD_REQUEST, "缺少必要字段", None);
+    }
+    if !is_supported_format(&format) {
+        return error(StatusCode::BAD_REQUEST, "不支持的订阅格式", None);
+    }
+
+    let result = sqlx::query(
+        r#"
+    INSERT INTO subscription_ua_rules (pattern, format, priority, enabled, description, created_at, updated_at)
+    VALUES (?, ?, ?, ?, ?, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
+    "#,
+    )
+    .bind(&pattern)
+    .bind(&format)
+    .bind(body.priority.unwrap_or(0))
+    .bind(body.enabled.unwrap_or(1))
+    .bind(body.description.unwrap_or_default())
+    .execute(&state.db)
+    .await;
+    let id = match result {
+        Ok(value) => value.last_insert_id() as i64,
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    };
+
+    invalidate_ua_rules(&state).await;
+    match fetch_ua_rule(&state, id).await {
+        Ok(payload) => success(payload, "创建成功").into_response(),
+        Err(message) => error(StatusCode::INTERNAL_SERVER_ERROR, &message, None),
+    }
+}
+
+async fn put_ua_rule(
+    State(state): State<AppState>,
+    Extension(headers): Extension<HeaderMap>,
+    Path(id): Path<i64>,
+    Json(body): Json<UaRuleRequest>,
+) -> Response {
+    if let Err(resp) = require_admin_permission(&state, &headers, "subscription-ua-rules").await {
+        return resp;
+    }
+    if id <= 0 {
+        return error(StatusCode::BAD_REQUEST, "ID 无效", None);
+    }
+
+    let mut fields: Vec<&str> = Vec::new();
+    let mut strings: Vec<String> = Vec::new();
+    if let Some(value) = body.pattern.as_deref() {
+        let pattern = value.trim().to_lowercase();
+        if pattern.is_empty() {
+            return error(StatusCode::BAD_REQUEST, "匹配关键字不能为空", None);
+        }
+        fields.push("pattern = ?");
+        strings.push(pattern);
+    }
+    if let Some(value) = body.format.as_deref() {
+        let format = value.trim().to_lowercase();
+        if !is_supported_format(&format) {
+            return error(StatusCode::BAD_REQUEST, "不支持的订阅格式", None);
+        }
+        fields.push("format = ?");
+        strings.push(format);
+    }
+    if let Some(value) = body.description {
+        fields.push("description = ?");
+        strings.push(value);
+    }
+    let mut numbers: Vec<i64> = Vec::new();
+    if let Some(value) = body.priority {
+        fields.push("priority = ?");
+        numbers.push(value);
+    }
+    if let Some(value) = body.enabled {
+        fields.push("enabled = ?");
+        numbers.push(value);
+    }
+    if fields.is_empty() {
+        return error(StatusCode::BAD_REQUEST, "没有需要更新的字段", None);
+    }
+
+    // 字符串字段在前、数值字段在后，与 fields 的追加顺序一致
+    let sql = format!(
+        "UPDATE subscription_ua_rules SET {}, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+        fields.join(", ")
+    );
+    let mut query_builder = sqlx::query(&sql);
+    for value in &strings {
+        query_builder = query_builder.bind(value);
+    }
+    for value in &numbers {
+        query_builder = query_builder.bind(value);
+    }
+    match query_builder.bind(id).execute(&state.db).await {
+        Ok(outcome) if outcome.rows_affected() == 0 => {
+            return error(StatusCode::NOT_FOUND, "规则不存在", None);
+        }
+        Ok(_) => {}
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    }
+
+    invalidate_ua_rules(&state).await;
+    match fetch_ua_rule(&state, id).await {
+        Ok(payload) => success(payload, "更新成功").into_response(),
+        Err(message) => error(StatusCode::INTERNAL_SERVER_ERROR, &message, None),
+    }
+}
+
+async fn delete_ua_rule(
+    State(state): State<AppState>,
+    Extension(headers): Extension<HeaderMap>,
+    Path(id): Path<i64>,
+) -> Response {
+    if let Err(resp) = require_admin_permission(&state, &headers, "subscription-ua-rules").await {
+        return resp;
+    }
+    if id <= 0 {
+        return error(StatusCode::BAD_REQUEST, "ID 无效", None);
+    }
+
+    match sqlx::query("DELETE FROM subscription_ua_rules WHERE id = ?")
+        .bind(id)
+        .execute(&state.db)
+        .await
+    {
+        Ok(outcome) if outcome.rows_affected() == 0 => {
+            return error(StatusCode::NOT_FOUND, "规则不存在", None);
+        }
+        Ok(_) => {}
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    }
+
+    invalidate_ua_rules(&state).await;
+    success(Value::Null, "删除成功").into_response()
+}
+
+async fn post_ua_rule_test(
+    State(state): State<AppState>,
+    Extension(headers): Extension<HeaderMap>,
+    Json(body): Json<UaRuleTestRequest>,
+) -> Response {
+    if let Err(resp) = require_admin_permission(&state, &headers, "subscription-ua-rules").await {
+        return resp;
+    }
+
+    let user_agent = body.user_agent.unwrap_or_default();
+    let rules = match load_ua_rules(&state).await {
+        Ok(value) => value,
+        Err(message) => return error(StatusCode::INTERNAL_SERVER_ERROR, &message, None),
+    };
+    let matched = match_ua_rule(&user_agent, &rules);
+    let format = match matched {
+        Some(rule) => rule.format.clone(),
+        None => load_fallback_format(&state).await,
+    };
+
+    success(
+        json!({
+          "user_agent": user_agent,
+          "format": format,
+          "fallback": matched.is_none(),
+          "rule": matched.map(|rule| json!({ "id": rule.id, "pattern": rule.pattern }))
+        }),
+        "Success",
+    )
+    .into_response()
+}
+
+async fn fetch_ua_rule(state: &AppState, id: i64) -> Result<Value, String> {
+    let row = sqlx::query(
+        r#"
+    SELECT id, pattern, format, priority, enabled, description, created_at, updated_at
+    FROM subscription_ua_rules
+    WHERE id = ?
+    "#,
+    )
+    .bind(id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|err| err.to_string())?;
+    Ok(row.map(map_ua_rule_row).unwrap_or(Value::Null))
+}
+
+fn format_datetime(value: Option<NaiveDateTime>) -> Option<String> {
+    value.map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
+}
+
+fn map_ua_rule_row(row: sqlx::mysql::MySqlRow) -> Value {
+    json!({
+      "id": row.try_get::<i64, _>("id").unwrap_or(0),
+      "pattern": row.try_get::<Option<String>, _>("pattern").ok().flatten().unwrap_or_default(),
+      "format": row.try_get::<Option<String>, _>("format").ok().flatten().unwrap_or_default(),
+      "priority": row.try_get::<Option<i64>, _>("priority").ok().flatten().unwrap_or(0),
+      "enabled": row.try_get::<Option<i64>, _>("enabled").ok().flatten().unwrap_or(1),
+      "description": row.try_get::<Option<String>, _>("description").ok().flatten().unwrap_or_default(),
+      "created_at": format_datetime(row.try_get::<Option<NaiveDateTime>, _>("created_at").ok().flatten()),
+      "updated_at": format_datetime(row.try_get::<Option<NaiveDateTime>, _>("updated_at").ok().flatten())
+    })
+}
@@ -4,14 +4,15 @@ use axum::response::{IntoResponse, Response};
 use axum::routing::{delete, get, post, put};
 use axum::{Extension, Json, Router};
 use serde::Deserialize;
-use serde_json::{json, Value};
+use serde_json::{json, Map, Value};
 use sqlx::Row;
 
+use crate::admin_actions::{record_admin_action, AdminAction};
 use crate::cache::cache_delete_by_prefix;
 use crate::response::{error, success};
 use crate::state::AppState;
 
-use super::super::auth::require_admin_user_id;
+use super::super::auth::require_admin_permission;
 
 #[derive(Deserialize)]
 struct ConfigRequest {
@@ -39,7 +40,7 @@ async fn get_configs(
     State(state): State<AppState>,
     Extension(headers): Extension<HeaderMap>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "system-configs").await {
         return resp;
     }
 
@@ -83,9 +84,10 @@ async fn post_config(
     Extension(headers): Extension<HeaderMap>,
     Json(body): Json<ConfigRequest>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
-        return resp;
-    }
+    let admin_id = match require_admin_permission(&state, &headers, "system-configs").await {
+        Ok(value) => value,
+        Err(resp) => return resp,
+    };
 
     let key = body.key.unwrap_or_default().trim().to_string();
     if key.is_empty() {
@@ -103,6 +105,7 @@ async fn post_config(
     if existing.is_some() {
         return error(StatusCode::BAD_REQUEST, "配置项已存在", None);
     }
+    let value = body.value.unwrap_or_default();
 
     if let Err(err) = sqlx::query(
         r#"
@@ -111,7 +114,7 @@ async fn post_config(
     "#,
     )
     .bind(&key)
-    .bind(body.value.unwrap_or_default())
+    .bind(&value)
     .bind(body.description.unwrap_or_default())
     .execute(&state.db)
     .await
@@ -120,6 +123,16 @@ async fn post_config(
     }
 
     clear_config_cache(&state).await;
+    record_config_change(
+        &state,
+        &headers,
+        admin_id,
+        "system_config.create",
+        "POST /api/admin/system-configs",
+        Map::new(),
+        Map::from_iter([(key, Value::String(value))]),
+    )
+    .await;
     success(Value::Null, "配置添加成功").into_response()
 }
 
@@ -128,13 +141,16 @@ async fn put_config(
     Extension(headers): Extension<HeaderMap>,
     Json(body): Json<ConfigRequest>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
-        return resp;
-    }
+    let admin_id = match require_admin_permission(&state, &headers, "system-configs").await {
+        Ok(value) => value,
+        Err(resp) => return resp,
+    };
     let key = body.key.unwrap_or_default().trim().to_string();
     if key.is_empty() {
         return error(StatusCode::BAD_REQUEST, "key 必填", None);
     }
+    let value = body.value.unwrap_or_default();
+    let before = load_config_values(&state, std::slice::from_ref(&key)).await;
     if let Err(err) = sqlx::query(
         r#"
     INSERT INTO system_configs (`key`, value, updated_at)
@@ -143,7 +159,7 @@ async fn put_config(
     "#,
     )
     .bind(&key)
-    .bind(body.value.unwrap_or_default())
+    .bind(&value)
     .execute(&state.db)
     .await
     {
@@ -151,6 +167,16 @@ async fn put_config(
     }
 
     clear_config_cache(&state).await;
+    record_config_change(
+        &state,
+        &headers,
+        admin_id,
+        "system_config.update",
+        "PUT /api/admin/system-configs",
+        before,
+        Map::from_iter([(key, Value::String(value))]),
+    )
+    .await;
     success(Value::Null, "已保存").into_response()
 }
 
@@ -159,17 +185,27 @@ async fn put_batch(
     Extension(headers): Extension<HeaderMap>,
     Json(body): Json<BatchConfigRequest>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
-        return resp;
-    }
+    let admin_id = match require_admin_permission(&state, &headers, "system-configs").await {
+        Ok(value) => value,
+        Err(resp) => return resp,
+    };
     let configs = match body.configs {
         Some(value) => value,
         None => return error(StatusCode::BAD_REQUEST, "configs 格式错误", None),
     };
 
+    let keys = configs
+        .iter()
+        .filter_map(|config| config.key.as_deref())
+        .map(|key| key.trim().to_string())
+        .filter(|key| !key.is_empty())
+        .collect::<Vec<String>>();
+    let before = load_config_values(&state, &keys).await;
+
     let mut success_count = 0;
     let mut failed_count = 0;
     let mut results: Vec<Value> = Vec::new();
+    let mut after = Map::new();
 
     for config in configs {
         let key = config.key.unwrap_or_default().trim().to_string();
@@ -186,13 +222,14 @@ async fn put_batch(
       "#,
         )
         .bind(&key)
-        .bind(config.value.unwrap_or_default())
+        .bind(config.value.as_deref().unwrap_or_default())
         .execute(&state.db)
         .await;
         match result {
             Ok(_) => {
                 success_count += 1;
                 results.push(json!({ "key": key, "success": true }));
+                after.insert(key, Value::String(config.value.unwrap_or_default()));
             }
             Err(err) => {
                 failed_count += 1;
@@ -202,6 +239,20 @@ async fn put_batch(
     }
 
     clear_config_cache(&state).await;
+    let before = before
+        .into_iter()
+        .filter(|(key, _)| after.contains_key(key))
+        .collect::<Map<String, Value>>();
+    record_config_change(
+        &state,
+        &headers,
+        admin_id,
+        "system_config.batch_update",
+        "PUT /api/admin/system-configs/batch",
+        before,
+        after,
+    )
+    .await;
     success(
         json!({
           "message": "批量更新完成",
@@ -222,13 +273,15 @@ async fn delete_config(
     Extension(headers): Extension<HeaderMap>,
     Json(body): Json<ConfigRequest>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
-        return resp;
-    }
+    let admin_id = match require_admin_permission(&state, &headers, "system-configs").await {
+        Ok(value) => value,
+        Err(resp) => return resp,
+    };
     let key = body.key.unwrap_or_default().trim().to_string();
     if key.is_empty() {
         return error(StatusCode::BAD_REQUEST, "key 必填", None);
     }
+    let before = load_config_values(&state, std::slice::from_ref(&key)).await;
     if let Err(err) = sqlx::query("DELETE FROM system_configs WHERE `key` = ?")
         .bind(&key)
         .execute(&state.db)
@@ -237,6 +290,16 @@ async fn delete_config(
         return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None);
     }
     clear_config_cache(&state).await;
+    record_config_change(
+        &state,
+        &headers,
+        admin_id,
+        "system_config.delete",
+        "DELETE /api/admin/system-configs",
+        before,
+        Map::new(),
+    )
+    .await;
     success(Value::Null, "已删除").into_response()
 }
 
@@ -245,13 +308,15 @@ async fn delete_config_by_key(
     Extension(headers): Extension<HeaderMap>,
     Path(key): Path<String>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
-        return resp;
-    }
+    let admin_id = match require_admin_permission(&state, &headers, "system-configs").await {
+        Ok(value) => value,
+        Err(resp) => return resp,
+    };
     let key = key.trim().to_string();
     if key.is_empty() {
         return error(StatusCode::BAD_REQUEST, "key 必填", None);
     }
+    let before = load_config_values(&state, std::slice::from_ref(&key)).await;
     if let Err(err) = sqlx::query("DELETE FROM system_configs WHERE `key` = ?")
         .bind(&key)
         .execute(&state.db)
@@ -260,9 +325,71 @@ async fn delete_config_by_key(
         return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None);
     }
     clear_config_cache(&state).await;
+    record_config_change(
+        &state,
+        &headers,
+        admin_id,
+        "system_config.delete",
+        "DELETE /api/admin/system-configs/{key}",
+        before,
+        Map::new(),
+    )
+    .await;
     success(Value::Null, "已删除").into_response()
 }
 
+async fn load_config_values(state: &AppState, keys: &[String]) -> Map<String, Value> {
+    if keys.is_empty() {
+        return Map::new();
+    }
+    let placeholders = keys.iter().map(|_| "?").collect::<Vec<&str>>().join(",");
+    let sql = format!("SELECT `key`, value FROM system_configs WHERE `key` IN ({placeholders})");
+    let mut query = sqlx::query(&sql);
+    for key in keys {
+        query = query.bind(key);
+    }
+    let rows = query.fetch_all(&state.db).await.unwrap_or_default();
+    rows.into_iter()
+        .filter_map(|row| {
+            let key = row.try_get::<Option<String>, _>("key").ok().flatten()?;
+            let value = row.try_get::<Option<String>, _>("value").ok().flatten();
+            Some((key, value.map(Value::String).unwrap_or(Value::Null)))
+        })
+        .collect()
+}
+
+/// 以配置键为字段记录变更前后的值
+async fn record_config_change(
+    state: &AppState,
+    headers: &HeaderMap,
+    admin_id: i64,
+    action: &str,
+    route: &str,
+    before: Map<String, Value>,
+    after: Map<String, Value>,
+) {
+    let target_id = before
+        .keys()
+        .chain(after.keys().filter(|key| !before.contains_key(*key)))
+        .cloned()
+        .collect::<Vec<String>>()
+        .join(",");
+    record_admin_action(
+        state,
+        headers,
+        admin_id,
+        AdminAction {
+            action,
+            route,
+            target_type: "system_config",
+            target_id: Some(target_id),
+            before: Some(Value::Object(before)),
+            after: Some(Value::Object(after)),
+        },
+    )
+    .await;
+}
+
 async fn clear_config_cache(state: &AppState) {
     cache_delete_by_prefix(state, "system_config").await;
     cache_delete_by_prefix(state, "site_config").await;
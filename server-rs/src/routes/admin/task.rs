@@ -1,17 +1,19 @@
-use axum::extract::State;
+use axum::extract::{Path, Query, State};
 use axum::http::{HeaderMap, StatusCode};
 use axum::response::{IntoResponse, Response};
-use axum::routing::{get, post};
+use axum::routing::{get, post, put};
 use axum::{Extension, Json, Router};
-use chrono::{Duration, Utc};
+use chrono::{Duration, NaiveDateTime, Utc};
 use serde::Deserialize;
 use serde_json::{json, Value};
 use sqlx::Row;
 
+use crate::jobs::{job_descriptions, JobKind};
 use crate::response::{error, success};
+use crate::scheduler::{load_job_schedules, start_manual_job, CronSchedule};
 use crate::state::AppState;
 
-use super::super::auth::require_admin_user_id;
+use super::super::auth::require_admin_permission;
 
 #[derive(Deserialize)]
 struct TrafficResetRequest {
@@ -24,18 +26,273 @@ struct TrafficAggregateRequest {
     date: Option<String>,
 }
 
+#[derive(Deserialize)]
+struct JobScheduleRequest {
+    schedule: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct JobRunsQuery {
+    page: Option<i64>,
+    limit: Option<i64>,
+    job_name: Option<String>,
+    status: Option<String>,
+}
+
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/traffic-reset-preview", get(get_traffic_reset_preview))
         .route("/traffic-reset", post(post_traffic_reset))
         .route("/traffic-aggregate", post(post_traffic_aggregate))
+        .route("/jobs", get(get_jobs))
+        .route("/jobs/{name}/schedule", put(put_job_schedule))
+        .route("/jobs/{name}/run", post(post_job_run))
+        .route("/job-runs", get(get_job_runs))
+}
+
+async fn get_jobs(
+    State(state): State<AppState>,
+    Extension(headers): Extension<HeaderMap>,
+) -> Response {
+    if let Err(resp) = require_admin_permission(&state, &headers, "task").await {
+        return resp;
+    }
+
+    let descriptions = job_descriptions();
+    let now = (Utc::now() + Duration::hours(8)).naive_utc();
+    let mut jobs: Vec<Value> = Vec::new();
+    for (job, schedule) in load_job_schedules(&state).await {
+        let parsed = if schedule.is_empty() {
+            None
+        } else {
+            CronSchedule::parse(&schedule).ok()
+        };
+        let last_run = sqlx::query(
+            r#"
+      SELECT id, trigger_type, status, error_message, started_at, finished_at, duration_ms
+      FROM job_runs
+      WHERE job_name = ?
+      ORDER BY id DESC
+      LIMIT 1
+      "#,
+        )
+        .bind(job.name())
+        .fetch_optional(&state.db)
+        .await
+        .ok()
+        .flatten()
+        .map(|row| map_job_run(&row));
+
+        jobs.push(json!({
+          "name": job.name(),
+          "description": descriptions
+            .iter()
+            .find(|(name, _)| *name == job.name())
+            .map(|(_, desc)| *desc)
+            .unwrap_or_default(),
+          "config_key": job.schedule_config_key(),
+          "schedule": schedule,
+          "enabled": !schedule.is_empty(),
+          "schedule_valid": schedule.is_empty() || parsed.is_some(),
+          "next_run_at": parsed
+            .and_then(|value| value.next_after(&now))
+            .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string()),
+          "last_run": last_run
+        }));
+    }
+
+    success(
+        json!({
+          "scheduler_enabled": state.env.job_scheduler_enabled,
+          "jobs": jobs
+        }),
+        "Success",
+    )
+    .into_response()
+}
+
+async fn put_job_schedule(
+    State(state): State<AppState>,
+    Extension(headers): Extension<HeaderMap>,
+    Path(name): Path<String>,
+    Json(body): Json<JobScheduleRequest>,
+) -> Response {
+    if let Err(resp) = require_admin_permission(&state, &headers, "task").await {
+        return resp;
+    }
+
+    let job = match JobKind::from_name(&name) {
+        Some(value) => value,
+        None => return error(StatusCode::NOT_FOUND, "任务不存在", None),
+    };
+    let schedule = body
+        .schedule
+        .unwrap_or_default()
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ");
+    if !schedule.is_empty() {
+        if let Err(message) = CronSchedule::parse(&schedule) {
+            return error(StatusCode::BAD_REQUEST, &message, None);
+        }
+    }
+
+    if let Err(err) = sqlx::query(
+        r#"
+    INSERT INTO system_configs (`key`, value, description, created_at, updated_at)
+    VALUES (?, ?, ?, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
+    ON DUPLICATE KEY UPDATE value = VALUES(value), updated_at = CURRENT_TIMESTAMP
+    "#,
+    )
+    .bind(job.schedule_config_key())
+    .bind(&schedule)
+    .bind(format!("{} 调度表达式（cron，留空禁用）", job.name()))
+    .execute(&state.db)
+    .await
+    {
+        return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None);
+    }
+
+    success(
+        json!({ "name": job.name(), "schedule": schedule }),
+        "调度已更新",
+    )
+    .into_response()
+}
+
+async fn post_job_run(
+    State(state): State<AppState>,
+    Extension(headers): Extension<HeaderMap>,
+    Path(name): Path<String>,
+) -> Response {
+    if let Err(resp) = require_admin_permission(&state, &headers, "task").await {
+        return resp;
+    }
+
+    let job = match JobKind::from_name(&name) {
+        Some(value) => value,
+        None => return error(StatusCode::NOT_FOUND, "任务不存在", None),
+    };
+
+    match start_manual_job(&state, job).await {
+        Ok(run_id) => success(
+            json!({ "name": job.name(), "run_id": run_id }),
+            "任务已开始执行",
+        )
+        .into_response(),
+        Err(message) => error(StatusCode::CONFLICT, &message, None),
+    }
+}
+
+async fn get_job_runs(
+    State(state): State<AppState>,
+    Extension(headers): Extension<HeaderMap>,
+    Query(query): Query<JobRunsQuery>,
+) -> Response {
+    if let Err(resp) = require_admin_permission(&state, &headers, "task").await {
+        return resp;
+    }
+
+    let page = query.page.unwrap_or(1).max(1);
+    let limit = query.limit.unwrap_or(20).clamp(1, 200);
+    let offset = (page - 1) * limit;
+
+    let mut conditions: Vec<&str> = Vec::new();
+    let mut params: Vec<String> = Vec::new();
+    let job_name = query.job_name.unwrap_or_default().trim().to_string();
+    if !job_name.is_empty() {
+        conditions.push("job_name = ?");
+        params.push(job_name);
+    }
+    let status = query.status.unwrap_or_default().trim().to_string();
+    if !status.is_empty() {
+        conditions.push("status = ?");
+        params.push(status);
+    }
+    let where_clause = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    };
+
+    let list_sql = format!(
+        r#"
+    SELECT id, job_name, trigger_type, scheduled_at, instance_id, status, error_message,
+           started_at, finished_at, duration_ms
+    FROM job_runs
+    {where_clause}
+    ORDER BY id DESC
+    LIMIT ? OFFSET ?
+    "#
+    );
+    let mut list_query = sqlx::query(&list_sql);
+    for param in &params {
+        list_query = list_query.bind(param);
+    }
+    let rows = match list_query
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&state.db)
+        .await
+    {
+        Ok(value) => value,
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    };
+
+    let total_sql = format!("SELECT COUNT(*) as total FROM job_runs {where_clause}");
+    let mut total_query = sqlx::query(&total_sql);
+    for param in &params {
+        total_query = total_query.bind(param);
+    }
+    let total = match total_query.fetch_optional(&state.db).await {
+        Ok(value) => value
+            .and_then(|row| row.try_get::<Option<i64>, _>("total").ok().flatten())
+            .unwrap_or(0),
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    };
+
+    let records = rows.iter().map(map_job_run).collect::<Vec<Value>>();
+
+    success(
+        json!({
+          "data": records,
+          "total": total,
+          "pagination": {
+            "total": total,
+            "page": page,
+            "limit": limit,
+            "pages": if total > 0 { ((total as f64) / (limit as f64)).ceil() as i64 } else { 0 }
+          }
+        }),
+        "Success",
+    )
+    .into_response()
+}
+
+fn map_job_run(row: &sqlx::mysql::MySqlRow) -> Value {
+    json!({
+      "id": row.try_get::<i64, _>("id").unwrap_or(0),
+      "job_name": row.try_get::<Option<String>, _>("job_name").ok().flatten(),
+      "trigger_type": row.try_get::<Option<String>, _>("trigger_type").ok().flatten().unwrap_or_default(),
+      "scheduled_at": format_datetime(row.try_get::<Option<NaiveDateTime>, _>("scheduled_at").ok().flatten()),
+      "instance_id": row.try_get::<Option<String>, _>("instance_id").ok().flatten(),
+      "status": row.try_get::<Option<String>, _>("status").ok().flatten().unwrap_or_default(),
+      "error_message": row.try_get::<Option<String>, _>("error_message").ok().flatten(),
+      "started_at": format_datetime(row.try_get::<Option<NaiveDateTime>, _>("started_at").ok().flatten()),
+      "finished_at": format_datetime(row.try_get::<Option<NaiveDateTime>, _>("finished_at").ok().flatten()),
+      "duration_ms": row.try_get::<Option<i64>, _>("duration_ms").ok().flatten().unwrap_or(0)
+    })
+}
+
+fn format_datetime(value: Option<NaiveDateTime>) -> Option<String> {
+    value.map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
 }
 
 async fn get_traffic_reset_preview(
     State(state): State<AppState>,
     Extension(headers): Extension<HeaderMap>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "task").await {
         return resp;
     }
 
@@ -79,7 +336,7 @@ async fn post_traffic_reset(
     Extension(headers): Extension<HeaderMap>,
     Json(body): Json<TrafficResetRequest>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "task").await {
         return resp;
     }
 
@@ -118,7 +375,7 @@ async fn post_traffic_aggregate(
     Extension(headers): Extension<HeaderMap>,
     Json(body): Json<TrafficAggregateRequest>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "task").await {
         return resp;
     }
 
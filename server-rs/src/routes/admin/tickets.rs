@@ -11,7 +11,7 @@ use sqlx::Row;
 use crate::response::{error, success};
 use crate::state::AppState;
 
-use super::super::auth::require_admin_user_id;
+use super::super::auth::require_admin_permission;
 
 #[derive(Deserialize)]
 struct TicketQuery {
@@ -46,7 +46,7 @@ async fn get_tickets(
     Extension(headers): Extension<HeaderMap>,
     Query(query): Query<TicketQuery>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "tickets").await {
         return resp;
     }
 
@@ -129,7 +129,7 @@ async fn get_pending_count(
     State(state): State<AppState>,
     Extension(headers): Extension<HeaderMap>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "tickets").await {
         return resp;
     }
 
@@ -152,7 +152,7 @@ async fn get_ticket_detail(
     Extension(headers): Extension<HeaderMap>,
     Path(ticket_id): Path<i64>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "tickets").await {
         return resp;
     }
     if ticket_id <= 0 {
@@ -201,7 +201,7 @@ async fn post_reply(
     Path(ticket_id): Path<i64>,
     Json(body): Json<ReplyRequest>,
 ) -> Response {
-    let admin_id = match require_admin_user_id(&state, &headers, None).await {
+    let admin_id = match require_admin_permission(&state, &headers, "tickets").await {
         Ok(value) => value,
         Err(resp) => return resp,
     };
@@ -286,7 +286,7 @@ async fn post_status(
     Path(ticket_id): Path<i64>,
     Json(body): Json<StatusRequest>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "tickets").await {
         return resp;
     }
     if ticket_id <= 0 {
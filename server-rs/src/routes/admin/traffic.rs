@@ -11,7 +11,7 @@ use sqlx::Row;
 use crate::response::{error, success};
 use crate::state::AppState;
 
-use super::super::auth::require_admin_user_id;
+use super::super::auth::require_admin_permission;
 
 #[derive(Deserialize)]
 struct OverviewQuery {
@@ -51,7 +51,7 @@ async fn get_overview(
     Extension(headers): Extension<HeaderMap>,
     Query(query): Query<OverviewQuery>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "traffic").await {
         return resp;
     }
 
@@ -99,7 +99,7 @@ async fn post_daily_reset(
     State(state): State<AppState>,
     Extension(headers): Extension<HeaderMap>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "traffic").await {
         return resp;
     }
 
@@ -118,7 +118,7 @@ async fn get_daily(
     Extension(headers): Extension<HeaderMap>,
     Query(query): Query<DailyQuery>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "traffic").await {
         return resp;
     }
 
@@ -209,7 +209,7 @@ async fn get_system_summary(
     Extension(headers): Extension<HeaderMap>,
     Query(query): Query<SystemSummaryQuery>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "traffic").await {
         return resp;
     }
 
@@ -283,7 +283,7 @@ async fn post_reset_today(
     State(state): State<AppState>,
     Extension(headers): Extension<HeaderMap>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "traffic").await {
         return resp;
     }
 
@@ -0,0 +1,367 @@
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Extension, Router};
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sqlx::Row;
+
+use crate::admin_actions::{record_admin_action, AdminAction};
+use crate::response::{error, success};
+use crate::state::AppState;
+use crate::traffic_anomaly::{charge_user_traffic, TrafficCharge};
+
+use super::super::auth::require_admin_permission;
+
+#[derive(Deserialize)]
+struct AnomaliesQuery {
+    page: Option<i64>,
+    limit: Option<i64>,
+    #[serde(rename = "pageSize")]
+    page_size: Option<i64>,
+    status: Option<String>,
+    reason: Option<String>,
+    node_id: Option<i64>,
+    user_id: Option<i64>,
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/traffic-anomalies", get(get_traffic_anomalies))
+        .route(
+            "/traffic-anomalies/{id}/approve",
+            post(post_approve_anomaly),
+        )
+        .route("/traffic-anomalies/{id}/reject", post(post_reject_anomaly))
+}
+
+async fn get_traffic_anomalies(
+    State(state): State<AppState>,
+    Extension(headers): Extension<HeaderMap>,
+    Query(query): Query<AnomaliesQuery>,
+) -> Response {
+    if let Err(resp) = require_admin_permission(&state, &headers, "traffic").await {
+        return resp;
+    }
+
+    let page = query.page.unwrap_or(1).max(1);
+    let limit_raw = query.limit.or(query.page_size).unwrap_or(20);
+    let limit = limit_raw.clamp(1, 200);
+    let offset = (page - 1) * limit;
+
+    let mut conditions: Vec<&str> = Vec::new();
+    let mut params: Vec<SqlParam> = Vec::new();
+    if let Some(status) = query
+        .status
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+    {
+        conditions.push("a.status = ?");
+        params.push(SqlParam::String(status.to_string()));
+    }
+    if let Some(reason) = query
+        .reason
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+    {
+        conditions.push("a.reason = ?");
+        params.push(SqlParam::String(reason.to_string()));
+    }
+    if let Some(node_id) = query.node_id.filter(|value| *value > 0) {
+        conditions.push("a.node_id = ?");
+        params.push(SqlParam::I64(node_id));
+    }
+    if let Some(user_id) = query.user_id.filter(|value| *value > 0) {
+        conditions.push("a.user_id = ?");
+        params.push(SqlParam::I64(user_id));
+    }
+    let where_clause = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    };
+
+    let total_sql = format!("SELECT COUNT(*) as total FROM traffic_anomalies a {where_clause}");
+    let mut total_query = sqlx::query(&total_sql);
+    total_query = bind_params(total_query, &params);
+    let total = match total_query.fetch_optional(&state.db).await {
+        Ok(row) => row
+            .and_then(|row| row.try_get::<Option<i64>, _>("total").ok().flatten())
+            .unwrap_or(0),
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    };
+
+    let list_sql = format!(
+        r#"
+    SELECT a.id, a.node_id, a.user_id, a.upload_traffic, a.download_traffic,
+           CAST(a.deduction_multiplier AS DOUBLE) AS deduction_multiplier, a.reason,
+           a.interval_seconds, a.date, a.status, a.reviewed_by, a.reviewed_at, a.created_at,
+           n.name AS node_name, u.email AS user_email, u.username AS user_username
+    FROM traffic_anomalies a
+    LEFT JOIN nodes n ON n.id = a.node_id
+    LEFT JOIN users u ON u.id = a.user_id
+    {where_clause}
+    ORDER BY a.id DESC
+    LIMIT ? OFFSET ?
+    "#
+    );
+    let mut list_query = sqlx::query(&list_sql);
+    list_query = bind_params(list_query, &params);
+    let rows = match list_query
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&state.db)
+        .await
+    {
+        Ok(value) => value,
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    };
+
+    let items = rows
+        .into_iter()
+        .map(|row| {
+            let read_i64 = |key: &str| row.try_get::<Option<i64>, _>(key).ok().flatten();
+            let read_string = |key: &str| row.try_get::<Option<String>, _>(key).ok().flatten();
+            let read_time = |key: &str| row.try_get::<Option<NaiveDateTime>, _>(key).ok().flatten();
+            let upload = read_i64("upload_traffic").unwrap_or(0);
+            let download = read_i64("download_traffic").unwrap_or(0);
+            json!({
+              "id": read_i64("id").unwrap_or(0),
+              "node_id": read_i64("node_id"),
+              "node_name": read_string("node_name"),
+              "user_id": read_i64("user_id"),
+              "user_email": read_string("user_email"),
+              "user_username": read_string("user_username"),
+              "upload_traffic": upload,
+              "download_traffic": download,
+              "total_traffic": upload.saturating_add(download),
+              "deduction_multiplier": row.try_get::<Option<f64>, _>("deduction_multiplier").ok().flatten().unwrap_or(1.0),
+              "reason": read_string("reason").unwrap_or_default(),
+              "interval_seconds": read_i64("interval_seconds").unwrap_or(0),
+              "date": row.try_get::<Option<NaiveDate>, _>("date").ok().flatten().map(|value| value.to_string()),
+              "status": read_string("status").unwrap_or_default(),
+              "reviewed_by": read_i64("reviewed_by"),
+              "reviewed_at": format_datetime(read_time("reviewed_at")),
+              "created_at": format_datetime(read_time("created_at"))
+            })
+        })
+        .collect::<Vec<Value>>();
+
+    success(
+        json!({
+          "data": items,
+          "total": total,
+          "pagination": {
+            "total": total,
+            "page": page,
+            "limit": limit,
+            "pages": if total > 0 { ((total as f64) / (limit as f64)).ceil() as i64 } else { 0 }
+          }
+        }),
+        "Success",
+    )
+    .into_response()
+}
+
+/// 审核通过：按上报时的倍率补记用户流量与节点流量
+async fn post_approve_anomaly(
+    State(state): State<AppState>,
+    Extension(headers): Extension<HeaderMap>,
+    Path(id): Path<i64>,
+) -> Response {
+    let admin_id = match require_admin_permission(&state, &headers, "traffic").await {
+        Ok(value) => value,
+        Err(resp) => return resp,
+    };
+    if id <= 0 {
+        return error(StatusCode::BAD_REQUEST, "ID 无效", None);
+    }
+
+    let mut tx = match state.db.begin().await {
+        Ok(tx) => tx,
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    };
+    let row = match sqlx::query(
+        r#"
+    SELECT node_id, user_id, upload_traffic, download_traffic,
+           CAST(deduction_multiplier AS DOUBLE) AS deduction_multiplier, date, status
+    FROM traffic_anomalies
+    WHERE id = ?
+    FOR UPDATE
+    "#,
+    )
+    .bind(id)
+    .fetch_optional(&mut *tx)
+    .await
+    {
+        Ok(Some(row)) => row,
+        Ok(None) => return error(StatusCode::NOT_FOUND, "记录不存在", None),
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    };
+    let status = row
+        .try_get::<Option<String>, _>("status")
+        .ok()
+        .flatten()
+        .unwrap_or_default();
+    if status != "pending" {
+        return error(StatusCode::BAD_REQUEST, "该记录已审核", None);
+    }
+
+    let read_i64 = |key: &str| row.try_get::<Option<i64>, _>(key).ok().flatten();
+    let charge = TrafficCharge {
+        user_id: read_i64("user_id").unwrap_or(0),
+        node_id: read_i64("node_id").unwrap_or(0),
+        upload: read_i64("upload_traffic").unwrap_or(0),
+        download: read_i64("download_traffic").unwrap_or(0),
+        multiplier: row
+            .try_get::<Option<f64>, _>("deduction_multiplier")
+            .ok()
+            .flatten()
+            .filter(|value| *value > 0.0)
+            .unwrap_or(1.0),
+        date: row
+            .try_get::<Option<NaiveDate>, _>("date")
+            .ok()
+            .flatten()
+            .unwrap_or_default(),
+    };
+    let deducted = match charge_user_traffic(&mut tx, &charge).await {
+        Ok(value) => value,
+        Err(message) => return error(StatusCode::INTERNAL_SERVER_ERROR, &message, None),
+    };
+    let total = charge.upload.saturating_add(charge.download);
+    if let Err(err) = sqlx::query(
+        "UPDATE nodes SET node_bandwidth = node_bandwidth + ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+    )
+    .bind(total)
+    .bind(charge.node_id)
+    .execute(&mut *tx)
+    .await
+    {
+        return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None);
+    }
+    if let Err(err) = mark_reviewed(&mut tx, id, "approved", admin_id).await {
+        return error(StatusCode::INTERNAL_SERVER_ERROR, &err, None);
+    }
+    if let Err(err) = tx.commit().await {
+        return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None);
+    }
+
+    record_admin_action(
+        &state,
+        &headers,
+        admin_id,
+        AdminAction {
+            action: "traffic_anomaly.approve",
+            route: "POST /api/admin/traffic-anomalies/{id}/approve",
+            target_type: "traffic_anomaly",
+            target_id: Some(id.to_string()),
+            before: Some(json!({ "status": "pending" })),
+            after: Some(json!({ "status": "approved", "charged_traffic": deducted })),
+        },
+    )
+    .await;
+    success(
+        json!({ "id": id, "charged_traffic": deducted }),
+        "已计入用户流量",
+    )
+    .into_response()
+}
+
+/// 审核拒绝：流量不计费，仅标记为已处理
+async fn post_reject_anomaly(
+    State(state): State<AppState>,
+    Extension(headers): Extension<HeaderMap>,
+    Path(id): Path<i64>,
+) -> Response {
+    let admin_id = match require_admin_permission(&state, &headers, "traffic").await {
+        Ok(value) => value,
+        Err(resp) => return resp,
+    };
+    if id <= 0 {
+        return error(StatusCode::BAD_REQUEST, "ID 无效", None);
+    }
+
+    let result = sqlx::query(
+        r#"
+    UPDATE traffic_anomalies
+    SET status = 'rejected', reviewed_by = ?, reviewed_at = CURRENT_TIMESTAMP
+    WHERE id = ? AND status = 'pending'
+    "#,
+    )
+    .bind(admin_id)
+    .bind(id)
+    .execute(&state.db)
+    .await;
+    match result {
+        Ok(outcome) if outcome.rows_affected() == 0 => {
+            return error(StatusCode::BAD_REQUEST, "记录不存在或已审核", None);
+        }
+        Ok(_) => {}
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    }
+
+    record_admin_action(
+        &state,
+        &headers,
+        admin_id,
+        AdminAction {
+            action: "traffic_anomaly.reject",
+            route: "POST /api/admin/traffic-anomalies/{id}/reject",
+            target_type: "traffic_anomaly",
+            target_id: Some(id.to_string()),
+            before: Some(json!({ "status": "pending" })),
+            after: Some(json!({ "status": "rejected" })),
+        },
+    )
+    .await;
+    success(Value::Null, "已拒绝").into_response()
+}
+
+async fn mark_reviewed(
+    conn: &mut sqlx::MySqlConnection,
+    id: i64,
+    status: &str,
+    admin_id: i64,
+) -> Result<(), String> {
+    sqlx::query(
+        r#"
+    UPDATE traffic_anomalies
+    SET status = ?, reviewed_by = ?, reviewed_at = CURRENT_TIMESTAMP
+    WHERE id = ?
+    "#,
+    )
+    .bind(status)
+    .bind(admin_id)
+    .bind(id)
+    .execute(conn)
+    .await
+    .map_err(|err| err.to_string())?;
+    Ok(())
+}
+
+fn format_datetime(value: Option<NaiveDateTime>) -> Option<String> {
+    value.map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
+}
+
+enum SqlParam {
+    I64(i64),
+    String(String),
+}
+
+fn bind_params<'a>(
+    mut query: sqlx::query::Query<'a, sqlx::MySql, sqlx::mysql::MySqlArguments>,
+    params: &'a [SqlParam],
+) -> sqlx::query::Query<'a, sqlx::MySql, sqlx::mysql::MySqlArguments> {
+    for param in params {
+        query = match param {
+            SqlParam::I64(value) => query.bind(*value),
+            SqlParam::String(value) => query.bind(value),
+        };
+    }
+    query
+}
@@ -8,12 +8,16 @@ use serde::Deserialize;
 use serde_json::{json, Value};
 use sqlx::Row;
 
+use crate::admin_actions::{record_admin_action, AdminAction};
+use crate::admin_permissions::{load_admin_access, AdminAccess, ALL_PERMISSIONS};
 use crate::cache::cache_delete_by_prefix;
 use crate::crypto::{generate_uuid, hash_password, random_base64, random_string};
+use crate::money::Money;
+use crate::node_hub::{notify_nodes, NodeEvent};
 use crate::response::{error, success};
 use crate::state::AppState;
 
-use super::super::auth::require_admin_user_id;
+use super::super::auth::require_admin_permission;
 
 #[derive(Deserialize)]
 struct UsersQuery {
@@ -92,7 +96,7 @@ async fn get_users(
     Extension(headers): Extension<HeaderMap>,
     Query(query): Query<UsersQuery>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "users").await {
         return resp;
     }
 
@@ -220,9 +224,10 @@ async fn post_user(
     Extension(headers): Extension<HeaderMap>,
     Json(body): Json<CreateUserRequest>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
-        return resp;
-    }
+    let admin_id = match require_admin_permission(&state, &headers, "users").await {
+        Ok(value) => value,
+        Err(resp) => return resp,
+    };
 
     if body.email.trim().is_empty()
         || body.username.trim().is_empty()
@@ -253,14 +258,37 @@ async fn post_user(
   .execute(&state.db)
   .await;
 
-    if let Err(err) = insert {
-        return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None);
-    }
+    let new_user_id = match insert {
+        Ok(outcome) => outcome.last_insert_id() as i64,
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    };
 
-    if let Err(message) = apply_user_update(&state, None, &body).await {
+    if let Err(message) = apply_user_update(&state, new_user_id, &body).await {
         return error(StatusCode::INTERNAL_SERVER_ERROR, &message, None);
     }
 
+    record_admin_action(
+        &state,
+        &headers,
+        admin_id,
+        AdminAction {
+            action: "user.create",
+            route: "POST /api/admin/users",
+            target_type: "user",
+            target_id: Some(new_user_id.to_string()),
+            before: None,
+            after: load_user_snapshot(&state, new_user_id).await,
+        },
+    )
+    .await;
+
+    notify_nodes(
+        &state,
+        NodeEvent::UsersChanged {
+            user_ids: Vec::new(),
+        },
+    )
+    .await;
     success(Value::Null, "用户已创建").into_response()
 }
 
@@ -270,17 +298,43 @@ async fn put_user(
     Path(user_id): Path<i64>,
     Json(body): Json<UpdateUserRequest>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
-        return resp;
-    }
+    let admin_id = match require_admin_permission(&state, &headers, "users").await {
+        Ok(value) => value,
+        Err(resp) => return resp,
+    };
     if user_id <= 0 {
         return error(StatusCode::BAD_REQUEST, "id 无效", None);
     }
+    if let Err(resp) = require_manageable_user(&state, admin_id, user_id).await {
+        return resp;
+    }
 
-    if let Err(message) = apply_user_update(&state, Some(user_id), &body).await {
+    let before = load_user_snapshot(&state, user_id).await;
+    if let Err(message) = apply_user_update(&state, user_id, &body).await {
         return error(StatusCode::INTERNAL_SERVER_ERROR, &message, None);
     }
+    let mut after = load_user_snapshot(&state, user_id).await;
+    if let (Some(Value::Object(map)), Some(password)) = (after.as_mut(), body.password.as_deref()) {
+        if !password.trim().is_empty() {
+            map.insert("password_changed".to_string(), Value::Bool(true));
+        }
+    }
 
+    record_admin_action(
+        &state,
+        &headers,
+        admin_id,
+        AdminAction {
+            action: "user.update",
+            route: "PUT /api/admin/users/{id}",
+            target_type: "user",
+            target_id: Some(user_id.to_string()),
+            before,
+            after,
+        },
+    )
+    .await;
+    notify_user_changed(&state, user_id, body.status == Some(0)).await;
     success(Value::Null, "用户已更新").into_response()
 }
 
@@ -289,13 +343,18 @@ async fn delete_user(
     Extension(headers): Extension<HeaderMap>,
     Path(user_id): Path<i64>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
-        return resp;
-    }
+    let admin_id = match require_admin_permission(&state, &headers, "users").await {
+        Ok(value) => value,
+        Err(resp) => return resp,
+    };
     if user_id <= 0 {
         return error(StatusCode::BAD_REQUEST, "id 无效", None);
     }
+    if let Err(resp) = require_manageable_user(&state, admin_id, user_id).await {
+        return resp;
+    }
 
+    let before = load_user_snapshot(&state, user_id).await;
     let result = sqlx::query("DELETE FROM users WHERE id = ?")
         .bind(user_id)
         .execute(&state.db)
@@ -309,6 +368,22 @@ async fn delete_user(
         Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
     };
 
+    record_admin_action(
+        &state,
+        &headers,
+        admin_id,
+        AdminAction {
+            action: "user.delete",
+            route: "DELETE /api/admin/users/{id}",
+            target_type: "user",
+            target_id: Some(user_id.to_string()),
+            before,
+            after: None,
+        },
+    )
+    .await;
+
+    notify_user_changed(&state, user_id, true).await;
     success(Value::Null, "用户已删除").into_response()
 }
 
@@ -318,27 +393,53 @@ async fn post_user_status(
     Path(user_id): Path<i64>,
     Json(body): Json<StatusRequest>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
-        return resp;
-    }
+    let admin_id = match require_admin_permission(&state, &headers, "users").await {
+        Ok(value) => value,
+        Err(resp) => return resp,
+    };
     if user_id <= 0 {
         return error(StatusCode::BAD_REQUEST, "id 无效", None);
     }
+    if let Err(resp) = require_manageable_user(&state, admin_id, user_id).await {
+        return resp;
+    }
     let status = body.status.unwrap_or(-1);
     if status != 0 && status != 1 {
         return error(StatusCode::BAD_REQUEST, "状态无效", None);
     }
 
-    let result =
-        sqlx::query("UPDATE users SET status = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
-            .bind(status)
-            .bind(user_id)
-            .execute(&state.db)
-            .await;
+    let before = load_user_snapshot(&state, user_id).await;
+    let result = sqlx::query(
+        r#"
+        UPDATE users
+        SET status = ?, device_limit_suspended_at = NULL, updated_at = CURRENT_TIMESTAMP
+        WHERE id = ?
+        "#,
+    )
+    .bind(status)
+    .bind(user_id)
+    .execute(&state.db)
+    .await;
     if let Err(err) = result {
         return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None);
     }
 
+    record_admin_action(
+        &state,
+        &headers,
+        admin_id,
+        AdminAction {
+            action: "user.status",
+            route: "POST /api/admin/users/{id}/status",
+            target_type: "user",
+            target_id: Some(user_id.to_string()),
+            before,
+            after: load_user_snapshot(&state, user_id).await,
+        },
+    )
+    .await;
+
+    notify_user_changed(&state, user_id, status == 0).await;
     success(Value::Null, "状态已更新").into_response()
 }
 
@@ -347,12 +448,16 @@ async fn post_user_traffic(
     Extension(headers): Extension<HeaderMap>,
     Path(user_id): Path<i64>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
-        return resp;
-    }
+    let admin_id = match require_admin_permission(&state, &headers, "users").await {
+        Ok(value) => value,
+        Err(resp) => return resp,
+    };
     if user_id <= 0 {
         return error(StatusCode::BAD_REQUEST, "ID 无效", None);
     }
+    if let Err(resp) = require_manageable_user(&state, admin_id, user_id).await {
+        return resp;
+    }
 
     if let Err(err) = sqlx::query(
         r#"
@@ -382,6 +487,21 @@ async fn post_user_traffic(
         .execute(&state.db)
         .await;
     cache_delete_by_prefix(&state, &format!("user_{user_id}")).await;
+    notify_user_changed(&state, user_id, false).await;
+    record_admin_action(
+        &state,
+        &headers,
+        admin_id,
+        AdminAction {
+            action: "user.traffic_reset",
+            route: "POST /api/admin/users/{id}/traffic",
+            target_type: "user",
+            target_id: Some(user_id.to_string()),
+            before: None,
+            after: None,
+        },
+    )
+    .await;
 
     success(
         json!({ "message": "User traffic reset successfully" }),
@@ -395,7 +515,7 @@ async fn export_users(
     Extension(headers): Extension<HeaderMap>,
     Query(query): Query<ExportQuery>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "users").await {
         return resp;
     }
 
@@ -589,23 +709,97 @@ fn map_user_row(row: sqlx::mysql::MySqlRow) -> Value {
     })
 }
 
+/// 审计日志用的用户快照（仅管理员可修改的字段）
+async fn load_user_snapshot(state: &AppState, user_id: i64) -> Option<Value> {
+    let row = sqlx::query(
+        r#"
+    SELECT email, username, status, class, expire_time, class_expire_time, transfer_enable,
+           speed_limit, device_limit, bark_key, bark_enabled, money, invite_code, invite_limit
+    FROM users
+    WHERE id = ?
+    "#,
+    )
+    .bind(user_id)
+    .fetch_optional(&state.db)
+    .await
+    .ok()??;
+    let read_i64 = |key: &str| row.try_get::<Option<i64>, _>(key).ok().flatten();
+    let read_string = |key: &str| row.try_get::<Option<String>, _>(key).ok().flatten();
+    let read_time = |key: &str| row.try_get::<Option<NaiveDateTime>, _>(key).ok().flatten();
+    Some(json!({
+      "email": read_string("email"),
+      "username": read_string("username"),
+      "status": read_i64("status"),
+      "class": read_i64("class"),
+      "expire_time": format_datetime(read_time("expire_time")),
+      "class_expire_time": format_datetime(read_time("class_expire_time")),
+      "transfer_enable": read_i64("transfer_enable"),
+      "speed_limit": read_i64("speed_limit"),
+      "device_limit": read_i64("device_limit"),
+      "bark_key": read_string("bark_key"),
+      "bark_enabled": read_i64("bark_enabled"),
+      "money": Money::from_row(&row, "money"),
+      "invite_code": read_string("invite_code"),
+      "invite_limit": read_i64("invite_limit")
+    }))
+}
+
+/// 通知节点刷新用户列表；禁用或删除时同时要求节点断开该用户现有连接
+async fn notify_user_changed(state: &AppState, user_id: i64, kick: bool) {
+    notify_nodes(
+        state,
+        NodeEvent::UsersChanged {
+            user_ids: vec![user_id],
+        },
+    )
+    .await;
+    if kick {
+        notify_nodes(state, NodeEvent::KickUser { user_id, ip: None }).await;
+    }
+}
+
+/// 管理员账号只允许超级管理员修改或删除，防止普通角色借改密登录超级管理员提权
+async fn require_manageable_user(
+    state: &AppState,
+    admin_id: i64,
+    user_id: i64,
+) -> Result<(), Response> {
+    let target_is_admin = sqlx::query("SELECT is_admin FROM users WHERE id = ?")
+        .bind(user_id)
+        .fetch_optional(&state.db)
+        .await
+        .map_err(|err| error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None))?
+        .and_then(|row| row.try_get::<Option<i64>, _>("is_admin").ok().flatten())
+        .unwrap_or(0)
+        == 1;
+    if !target_is_admin {
+        return Ok(());
+    }
+    let access = load_admin_access(state, admin_id)
+        .await
+        .map_err(|message| error(StatusCode::INTERNAL_SERVER_ERROR, &message, None))?;
+    match admin_target_rejection(access.as_ref(), target_is_admin) {
+        Some(resp) => Err(resp),
+        None => Ok(()),
+    }
+}
+
+fn admin_target_rejection(access: Option<&AdminAccess>, target_is_admin: bool) -> Option<Response> {
+    if !target_is_admin || access.is_some_and(|access| access.allows(ALL_PERMISSIONS)) {
+        return None;
+    }
+    Some(error(
+        StatusCode::FORBIDDEN,
+        "仅超级管理员可修改或删除管理员账号",
+        None,
+    ))
+}
+
 async fn apply_user_update<T: UserUpdatePayload>(
     state: &AppState,
-    user_id: Option<i64>,
+    target_id: i64,
     payload: &T,
 ) -> Result<(), String> {
-    let target_id = match user_id {
-        Some(value) => value,
-        None => {
-            let row = sqlx::query("SELECT id FROM users WHERE email = ?")
-                .bind(payload.email().unwrap_or_default())
-                .fetch_optional(&state.db)
-                .await
-                .map_err(|err| err.to_string())?;
-            row.and_then(|row| row.try_get::<Option<i64>, _>("id").ok().flatten())
-                .unwrap_or(0)
-        }
-    };
     if target_id <= 0 {
         return Ok(());
     }
@@ -642,6 +836,12 @@ async fn apply_user_update<T: UserUpdatePayload>(
         }
     }
     if let Some(value) = payload.status() {
+        // 管理员改动状态后，设备超限停用到期不再自动恢复（须在 status 赋值前比较原值）
+        fields.push(
+            "device_limit_suspended_at = IF(status <=> ?, device_limit_suspended_at, NULL)"
+                .to_string(),
+        );
+        params.push(SqlParam::I64(value));
         fields.push("status = ?".to_string());
         params.push(SqlParam::I64(value));
     }
@@ -679,7 +879,7 @@ async fn apply_user_update<T: UserUpdatePayload>(
     }
     if let Some(value) = payload.money() {
         fields.push("money = ?".to_string());
-        params.push(SqlParam::F64(value));
+        params.push(SqlParam::Money(Money::from_f64(value).unwrap_or_default()));
     }
     if let Some(value) = payload.invite_code() {
         fields.push("invite_code = ?".to_string());
@@ -874,7 +1074,7 @@ fn format_datetime(value: Option<NaiveDateTime>) -> Option<String> {
 
 enum SqlParam {
     I64(i64),
-    F64(f64),
+    Money(Money),
     String(String),
     DateTime(NaiveDateTime),
 }
@@ -885,10 +1085,34 @@ fn bind_params<'a>(mut query: SqlxQuery<'a>, params: &'a [SqlParam]) -> SqlxQuer
     for param in params {
         query = match param {
             SqlParam::I64(value) => query.bind(*value),
-            SqlParam::F64(value) => query.bind(*value),
+            SqlParam::Money(value) => query.bind(value),
             SqlParam::String(value) => query.bind(value),
             SqlParam::DateTime(value) => query.bind(*value),
         };
     }
     query
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::admin_permissions::parse_permissions;
+
+    #[test]
+    fn only_super_admin_can_manage_admin_accounts() {
+        let users_only = AdminAccess {
+            role_code: Some("operator".to_string()),
+            permissions: parse_permissions(r#"["users"]"#),
+        };
+        let resp = admin_target_rejection(Some(&users_only), true).expect("should reject");
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        assert!(admin_target_rejection(Some(&users_only), false).is_none());
+
+        let super_admin = AdminAccess {
+            role_code: None,
+            permissions: vec![ALL_PERMISSIONS.to_string()],
+        };
+        assert!(admin_target_rejection(Some(&super_admin), true).is_none());
+        assert!(admin_target_rejection(None, true).is_some());
+    }
+}
@@ -9,10 +9,11 @@ use serde_json::{json, Value};
 use sqlx::Row;
 
 use crate::cache::cache_delete_by_prefix;
+use crate::node_hub::{notify_nodes, NodeEvent};
 use crate::response::{error, success};
 use crate::state::AppState;
 
-use super::super::auth::require_admin_user_id;
+use super::super::auth::require_admin_permission;
 
 #[derive(Deserialize)]
 struct WhitelistQuery {
@@ -51,7 +52,7 @@ async fn get_whitelist(
     Extension(headers): Extension<HeaderMap>,
     Query(query): Query<WhitelistQuery>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "whitelist").await {
         return resp;
     }
 
@@ -149,7 +150,7 @@ async fn post_whitelist(
     Extension(headers): Extension<HeaderMap>,
     Json(body): Json<WhitelistRequest>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "whitelist").await {
         return resp;
     }
 
@@ -189,6 +190,7 @@ async fn post_whitelist(
 
     cache_delete_by_prefix(&state, "white_list").await;
     cache_delete_by_prefix(&state, "whitelist").await;
+    notify_nodes(&state, NodeEvent::WhiteListChanged).await;
     let payload = row.map(map_whitelist_row).unwrap_or(Value::Null);
     success(payload, "创建成功").into_response()
 }
@@ -199,7 +201,7 @@ async fn put_whitelist(
     Path(id): Path<i64>,
     Json(body): Json<WhitelistRequest>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "whitelist").await {
         return resp;
     }
     if id <= 0 {
@@ -253,6 +255,7 @@ async fn put_whitelist(
 
     cache_delete_by_prefix(&state, "white_list").await;
     cache_delete_by_prefix(&state, "whitelist").await;
+    notify_nodes(&state, NodeEvent::WhiteListChanged).await;
     let payload = row.map(map_whitelist_row).unwrap_or(Value::Null);
     success(payload, "更新成功").into_response()
 }
@@ -262,7 +265,7 @@ async fn delete_whitelist(
     Extension(headers): Extension<HeaderMap>,
     Path(id): Path<i64>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "whitelist").await {
         return resp;
     }
     if id <= 0 {
@@ -278,6 +281,7 @@ async fn delete_whitelist(
 
     cache_delete_by_prefix(&state, "white_list").await;
     cache_delete_by_prefix(&state, "whitelist").await;
+    notify_nodes(&state, NodeEvent::WhiteListChanged).await;
     success(Value::Null, "删除成功").into_response()
 }
 
@@ -286,7 +290,7 @@ async fn post_whitelist_batch(
     Extension(headers): Extension<HeaderMap>,
     Json(body): Json<BatchRequest>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "whitelist").await {
         return resp;
     }
 
@@ -325,6 +329,7 @@ async fn post_whitelist_batch(
 
     cache_delete_by_prefix(&state, "white_list").await;
     cache_delete_by_prefix(&state, "whitelist").await;
+    notify_nodes(&state, NodeEvent::WhiteListChanged).await;
     success(
         json!({
           "message": message,
@@ -9,10 +9,11 @@ use serde_json::{json, Value};
 use sqlx::Row;
 
 use crate::cache::cache_delete_by_prefix;
+use crate::node_hub::{notify_nodes, NodeEvent};
 use crate::response::{error, success};
 use crate::state::AppState;
 
-use super::super::auth::require_admin_user_id;
+use super::super::auth::require_admin_permission;
 
 #[derive(Deserialize, Clone)]
 struct XrayRulesQuery {
@@ -69,7 +70,7 @@ async fn get_xray_rules(
 }
 
 async fn list_xray_rules(state: AppState, headers: HeaderMap, query: XrayRulesQuery) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "xray-rules").await {
         return resp;
     }
 
@@ -194,7 +195,7 @@ async fn post_xray_rule(
     Extension(headers): Extension<HeaderMap>,
     Json(body): Json<XrayRuleRequest>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "xray-rules").await {
         return resp;
     }
 
@@ -262,6 +263,7 @@ async fn post_xray_rule(
     }
 
     cache_delete_by_prefix(&state, "xray_rules_").await;
+    notify_nodes(&state, NodeEvent::XrayRulesChanged).await;
 
     let row = sqlx::query(
         r#"
@@ -293,7 +295,7 @@ async fn put_xray_rule(
     Path(id): Path<i64>,
     Json(body): Json<XrayRuleRequest>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "xray-rules").await {
         return resp;
     }
     if id <= 0 {
@@ -367,6 +369,7 @@ async fn put_xray_rule(
     }
 
     cache_delete_by_prefix(&state, "xray_rules_").await;
+    notify_nodes(&state, NodeEvent::XrayRulesChanged).await;
 
     let row = sqlx::query(
         r#"
@@ -401,7 +404,7 @@ async fn delete_xray_rule(
     Extension(headers): Extension<HeaderMap>,
     Path(id): Path<i64>,
 ) -> Response {
-    if let Err(resp) = require_admin_user_id(&state, &headers, None).await {
+    if let Err(resp) = require_admin_permission(&state, &headers, "xray-rules").await {
         return resp;
     }
     if id <= 0 {
@@ -421,6 +424,7 @@ async fn delete_xray_rule(
     }
 
     cache_delete_by_prefix(&state, "xray_rules_").await;
+    notify_nodes(&state, NodeEvent::XrayRulesChanged).await;
     success(Value::Null, "删除成功").into_response()
 }
 
@@ -15,12 +15,14 @@ use sha2::Sha256;
 use sqlx::Row;
 use std::collections::HashSet;
 
+use crate::admin_permissions::load_admin_access;
 use crate::cache::{
     cache_delete, cache_get, cache_get_redis_only, cache_set, cache_set_redis_only,
 };
+use crate::client_ip::client_ip;
 use crate::crypto::{
-    generate_uuid, hash_password, random_base64, random_numeric_code, random_string, sha256_hex,
-    verify_password,
+    generate_uuid, hash_password, password_needs_rehash, random_base64, random_numeric_code,
+    random_string, sha256_hex, verify_password,
 };
 use crate::mail::EmailService;
 use crate::passkey::{
@@ -113,6 +115,10 @@ struct SessionPayload {
     email: String,
     username: String,
     is_admin: i64,
+    #[serde(default)]
+    admin_role: Option<String>,
+    #[serde(default)]
+    permissions: Vec<String>,
 }
 
 struct VerificationFlags {
@@ -320,7 +326,7 @@ async fn post_register(
         &email,
         &username,
         &password,
-        get_client_ip(&headers),
+        client_ip(&headers),
         inviter_id,
     )
     .await;
@@ -335,7 +341,7 @@ async fn post_register(
             inviter_id,
             user_id,
             &invite_code,
-            get_client_ip(&headers),
+            client_ip(&headers),
         )
         .await;
         increment_invite_usage(&state, inviter_id).await;
@@ -357,7 +363,9 @@ async fn post_register(
                   "id": payload.id,
                   "email": payload.email,
                   "username": payload.username,
-                  "is_admin": payload.is_admin
+                  "is_admin": payload.is_admin,
+                  "admin_role": payload.admin_role,
+                  "permissions": payload.permissions
                 });
                 success(json!({ "token": token, "user": user_payload }), "注册成功").into_response()
             }
@@ -449,7 +457,7 @@ async fn post_oauth_login(
     let provider_key = provider.to_lowercase();
     let normalized_email = email.trim().to_lowercase();
     let username = body.username.unwrap_or_default();
-    let client_ip = get_client_ip(&headers);
+    let client_ip = client_ip(&headers);
 
     let user = match get_user_by_email(&state, &normalized_email).await {
         Ok(Some(user)) => Some(user),
@@ -507,7 +515,9 @@ async fn post_oauth_login(
               "id": payload.id,
               "email": payload.email,
               "username": payload.username,
-              "is_admin": payload.is_admin
+              "is_admin": payload.is_admin,
+              "admin_role": payload.admin_role,
+              "permissions": payload.permissions
             });
             success(json!({ "token": token, "user": user_payload }), "登录成功").into_response()
         }
@@ -846,7 +856,7 @@ async fn post_google_oauth(
                     tail_suffix(&google_sub, 6)
                 },
                 remember,
-                client_ip: get_client_ip(&headers),
+                client_ip: client_ip(&headers),
                 user_agent: headers
                     .get("user-agent")
                     .and_then(|v| v.to_str().ok())
@@ -1040,7 +1050,7 @@ async fn post_github_oauth(
                     tail_suffix(&github_id, 6)
                 },
                 remember,
-                client_ip: get_client_ip(&headers),
+                client_ip: client_ip(&headers),
                 user_agent: headers
                     .get("user-agent")
                     .and_then(|v| v.to_str().ok())
@@ -1478,7 +1488,9 @@ async fn post_passkey_login_verify(
               "id": payload.id,
               "email": payload.email,
               "username": payload.username,
-              "is_admin": payload.is_admin == 1
+              "is_admin": payload.is_admin == 1,
+              "admin_role": payload.admin_role,
+              "permissions": payload.permissions
             });
             success(json!({ "token": token, "user": user_payload }), "登录成功").into_response()
         }
@@ -1513,7 +1525,7 @@ async fn post_login(
         if token.is_empty() {
             return error(StatusCode::BAD_REQUEST, "请完成人机验证后再登录", None);
         }
-        match verify_turnstile(secret, &token, get_client_ip(&headers)).await {
+        match verify_turnstile(secret, &token, client_ip(&headers)).await {
             Ok(true) => {}
             Ok(false) => return error(StatusCode::BAD_REQUEST, "人机验证未通过，请重试", None),
             Err(TurnstileError::RequestFailed) => {
@@ -1537,6 +1549,7 @@ async fn post_login(
     if !verify_password(&password, &user.password_hash) {
         return error(StatusCode::UNAUTHORIZED, "密码错误", None);
     }
+    rehash_legacy_password(&state, user.id, &password, &user.password_hash).await;
 
     if user.two_factor_enabled == 1 {
         let trust_token = body.two_factor_trust_token.clone().unwrap_or_default();
@@ -1777,7 +1790,9 @@ async fn post_verify_2fa(
                   "id": payload.id,
                   "email": payload.email,
                   "username": payload.username,
-                  "is_admin": payload.is_admin == 1
+                  "is_admin": payload.is_admin == 1,
+                  "admin_role": payload.admin_role,
+                  "permissions": payload.permissions
                 }),
             );
             if let Some(value) = trust_token {
@@ -1826,7 +1841,9 @@ async fn finalize_login(
                   "id": payload.id,
                   "email": payload.email,
                   "username": payload.username,
-                  "is_admin": payload.is_admin == 1
+                  "is_admin": payload.is_admin == 1,
+                  "admin_role": payload.admin_role,
+                  "permissions": payload.permissions
                 }),
             );
             success(Value::Object(data), "登录成功").into_response()
@@ -1841,11 +1858,14 @@ async fn issue_session(
     login_method: Option<String>,
     headers: &axum::http::HeaderMap,
 ) -> Result<(String, SessionPayload), String> {
+    let access = load_admin_access(state, user.id).await?;
     let payload = SessionPayload {
         id: user.id,
         email: user.email.clone(),
         username: user.username.clone(),
         is_admin: user.is_admin,
+        admin_role: access.as_ref().and_then(|value| value.role_code.clone()),
+        permissions: access.map(|value| value.permissions).unwrap_or_default(),
     };
     let session_token = random_string(48);
     let session_payload = json!({
@@ -1853,6 +1873,8 @@ async fn issue_session(
       "email": payload.email,
       "username": payload.username,
       "is_admin": payload.is_admin,
+      "admin_role": payload.admin_role,
+      "permissions": payload.permissions,
       "login_time": Utc::now().to_rfc3339()
     });
     cache_set(
@@ -1863,11 +1885,11 @@ async fn issue_session(
     )
     .await;
 
-    update_login_info(state, payload.id, get_client_ip(headers)).await;
+    update_login_info(state, payload.id, client_ip(headers)).await;
     insert_login_log(
         state,
         payload.id,
-        get_client_ip(headers),
+        client_ip(headers),
         headers
             .get("user-agent")
             .and_then(|value| value.to_str().ok())
@@ -1887,11 +1909,14 @@ async fn issue_session_with_extra(
     headers: &axum::http::HeaderMap,
     extra: serde_json::Map<String, Value>,
 ) -> Result<(String, SessionPayload), String> {
+    let access = load_admin_access(state, user.id).await?;
     let payload = SessionPayload {
         id: user.id,
         email: user.email.clone(),
         username: user.username.clone(),
         is_admin: user.is_admin,
+        admin_role: access.as_ref().and_then(|value| value.role_code.clone()),
+        permissions: access.map(|value| value.permissions).unwrap_or_default(),
     };
     let session_token = random_string(48);
     let mut session_payload = serde_json::Map::new();
@@ -1899,6 +1924,8 @@ async fn issue_session_with_extra(
     session_payload.insert("email".to_string(), json!(payload.email));
     session_payload.insert("username".to_string(), json!(payload.username));
     session_payload.insert("is_admin".to_string(), json!(payload.is_admin));
+    session_payload.insert("admin_role".to_string(), json!(payload.admin_role));
+    session_payload.insert("permissions".to_string(), json!(payload.permissions));
     session_payload.insert("login_time".to_string(), json!(Utc::now().to_rfc3339()));
     for (key, value) in extra {
         session_payload.insert(key, value);
@@ -1911,11 +1938,11 @@ async fn issue_session_with_extra(
     )
     .await;
 
-    update_login_info(state, payload.id, get_client_ip(headers)).await;
+    update_login_info(state, payload.id, client_ip(headers)).await;
     insert_login_log(
         state,
         payload.id,
-        get_client_ip(headers),
+        client_ip(headers),
         headers
             .get("user-agent")
             .and_then(|value| value.to_str().ok())
@@ -1944,7 +1971,9 @@ async fn finalize_oauth_login(
           "id": payload.id,
           "email": payload.email,
           "username": payload.username,
-          "is_admin": payload.is_admin == 1
+          "is_admin": payload.is_admin == 1,
+          "admin_role": payload.admin_role,
+          "permissions": payload.permissions
         }),
     );
     if let Value::Object(extra_map) = extra {
@@ -2518,6 +2547,30 @@ async fn update_login_info(state: &AppState, user_id: i64, ip: Option<String>) {
     .await;
 }
 
+/// 旧版 SHA-256 密码在验证成功后透明升级为 Argon2id
+pub(super) async fn rehash_legacy_password(
+    state: &AppState,
+    user_id: i64,
+    password: &str,
+    stored_hash: &str,
+) {
+    if !password_needs_rehash(stored_hash) {
+        return;
+    }
+    let hash = hash_password(password);
+    if let Err(err) = sqlx::query(
+        "UPDATE users SET password_hash = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ? AND password_hash = ?",
+    )
+    .bind(hash)
+    .bind(user_id)
+    .bind(stored_hash)
+    .execute(&state.db)
+    .await
+    {
+        tracing::warn!("[login] rehash legacy password failed: {err}");
+    }
+}
+
 async fn insert_login_log(
     state: &AppState,
     user_id: i64,
@@ -2748,7 +2801,7 @@ async fn handle_verification_code_request(
     .execute(&state.db)
     .await;
 
-    let client_ip = get_client_ip(headers).unwrap_or_else(|| "unknown".to_string());
+    let client_ip = client_ip(headers).unwrap_or_else(|| "unknown".to_string());
     let user_agent = headers
         .get("user-agent")
         .and_then(|value| value.to_str().ok())
@@ -3826,23 +3879,21 @@ pub(super) async fn require_admin_user_id(
     Ok(user.id)
 }
 
-fn get_client_ip(headers: &axum::http::HeaderMap) -> Option<String> {
-    let candidates = [
-        "x-client-ip",
-        "x-forwarded-for",
-        "cf-connecting-ip",
-        "true-client-ip",
-        "x-real-ip",
-    ];
-    for key in candidates {
-        if let Some(raw) = headers.get(key).and_then(|value| value.to_str().ok()) {
-            let first = raw.split(',').next().unwrap_or("").trim();
-            if !first.is_empty() {
-                return Some(first.to_string());
-            }
-        }
+/// 校验管理员身份，并检查其角色是否拥有指定的权限范围
+pub(super) async fn require_admin_permission(
+    state: &AppState,
+    headers: &axum::http::HeaderMap,
+    scope: &str,
+) -> Result<i64, Response> {
+    let user_id = require_admin_user_id(state, headers, None).await?;
+    let access = match load_admin_access(state, user_id).await {
+        Ok(value) => value,
+        Err(message) => return Err(error(StatusCode::INTERNAL_SERVER_ERROR, &message, None)),
+    };
+    if !access.is_some_and(|access| access.allows(scope)) {
+        return Err(error(StatusCode::FORBIDDEN, "当前角色无权访问该功能", None));
     }
-    None
+    Ok(user_id)
 }
 
 struct UserRow {
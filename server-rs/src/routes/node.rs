@@ -1,6 +1,6 @@
 use axum::body::to_bytes;
 use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
-use axum::extract::State;
+use axum::extract::{Query, State};
 use axum::http::StatusCode;
 use axum::http::{HeaderMap, HeaderValue};
 use axum::response::{IntoResponse, Response};
@@ -10,13 +10,23 @@ use base64::engine::general_purpose::STANDARD;
 use base64::Engine;
 use chrono::{Duration, Utc};
 use serde_json::{json, Value};
+use sqlx::mysql::MySqlRow;
 use sqlx::Row;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use tokio::sync::mpsc;
 
+use crate::audit_rules::{compile_audit_rule, to_soga_rule, AuditRuleType};
+use crate::blocked_ips::load_active_blocked_ips;
 use crate::cache::{cache_get, cache_set};
+use crate::crypto::{sha256_hex, timing_safe_eq};
 use crate::etag::{generate_etag, is_etag_match, json_with_etag, not_modified};
+use crate::node_hub::WS_PUSH_PROTOCOL_VERSION;
 use crate::response::error;
 use crate::state::AppState;
+use crate::traffic_anomaly::{
+    charge_user_traffic, classify_report, load_config as load_anomaly_config, quarantine_traffic,
+    report_interval_seconds, ReportContext, ReportedUser, TrafficCharge,
+};
 
 pub fn router() -> Router<AppState> {
     Router::new()
@@ -26,6 +36,7 @@ pub fn router() -> Router<AppState> {
         .route("/audit_rules", get(get_audit_rules))
         .route("/xray_rules", get(get_xray_rules))
         .route("/white_list", get(get_white_list))
+        .route("/block_list", get(get_block_list))
         .route("/report", post(post_report))
         .route("/traffic", post(post_traffic))
         .route("/alive_ip", post(post_alive_ip))
@@ -109,11 +120,17 @@ async fn websocket(
     State(state): State<AppState>,
     headers: HeaderMap,
 ) -> Response {
-    if let Err(response) = validate_soga_auth(&headers, state.env.node_api_key.as_deref()) {
-        return response;
-    }
-    ws.on_upgrade(move |socket| handle_websocket(socket, state, headers))
-        .into_response()
+    let auth = match validate_soga_auth(&state, &headers).await {
+        Ok(auth) => auth,
+        Err(response) => return response,
+    };
+    let Some(protocol_version) = parse_protocol_version(&headers) else {
+        return error(StatusCode::BAD_REQUEST, "不支持的协议版本", None);
+    };
+    ws.on_upgrade(move |socket| {
+        handle_websocket(socket, state, headers, auth.node_id, protocol_version)
+    })
+    .into_response()
 }
 
 async fn response_json(response: Response) -> Result<(StatusCode, Value), Response> {
@@ -135,7 +152,12 @@ async fn get_sync(State(state): State<AppState>, headers: HeaderMap) -> Response
     if !node.status().is_success() {
         return node;
     }
-    let users = get_users(State(state.clone()), headers.clone()).await;
+    let users = get_users(
+        State(state.clone()),
+        headers.clone(),
+        Query(UsersSyncQuery::default()),
+    )
+    .await;
     if !users.status().is_success() {
         return users;
     }
@@ -214,13 +236,39 @@ async fn post_report(
     Json(json!({ "code": 0, "message": "ok", "data": { "operations": completed } })).into_response()
 }
 
-async fn handle_websocket(mut socket: WebSocket, state: AppState, headers: HeaderMap) {
-    while let Some(result) = socket.recv().await {
+async fn handle_websocket(
+    mut socket: WebSocket,
+    state: AppState,
+    headers: HeaderMap,
+    node_id: i64,
+    protocol_version: u8,
+) {
+    // v2 及以上的连接注册到推送表，v1 客户端保持纯请求/响应
+    let mut push = if protocol_version >= WS_PUSH_PROTOCOL_VERSION {
+        Some(state.node_hub.register(node_id).await)
+    } else {
+        None
+    };
+    let connection_id = push.as_ref().map(|(id, _)| *id);
+
+    loop {
+        let result = tokio::select! {
+            received = socket.recv() => match received {
+                Some(result) => result,
+                None => break,
+            },
+            Some(event) = recv_push_event(&mut push) => {
+                if socket.send(Message::Text(event.into())).await.is_err() {
+                    break;
+                }
+                continue;
+            }
+        };
         let message = match result {
             Ok(message) => message,
             Err(error) => {
                 tracing::warn!("node WebSocket receive failed: {error}");
-                return;
+                break;
             }
         };
 
@@ -233,23 +281,24 @@ async fn handle_websocket(mut socket: WebSocket, state: AppState, headers: Heade
                             reason: "Message too large".into(),
                         })))
                         .await;
-                    return;
+                    break;
                 }
                 let response =
                     dispatch_websocket_message(&mut socket, &state, &headers, text.as_ref()).await;
                 if response.is_err() {
-                    return;
+                    break;
                 }
             }
             Message::Ping(payload) => {
                 if socket.send(Message::Pong(payload)).await.is_err() {
-                    return;
+                    break;
                 }
             }
             Message::Pong(_) => {}
             Message::Binary(_) => {
                 if send_ws_error(
                     &mut socket,
+                    1,
                     Value::Null,
                     400,
                     "WebSocket requires text messages",
@@ -257,12 +306,34 @@ async fn handle_websocket(mut socket: WebSocket, state: AppState, headers: Heade
                 .await
                 .is_err()
                 {
-                    return;
+                    break;
                 }
             }
-            Message::Close(_) => return,
+            Message::Close(_) => break,
         }
     }
+
+    if let Some(connection_id) = connection_id {
+        state.node_hub.unregister(node_id, connection_id).await;
+    }
+}
+
+async fn recv_push_event(push: &mut Option<(u64, mpsc::Receiver<String>)>) -> Option<String> {
+    match push {
+        Some((_, receiver)) => receiver.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+fn parse_protocol_version(headers: &HeaderMap) -> Option<u8> {
+    let Some(value) = headers.get("WS-PROTOCOL-VERSION") else {
+        return Some(1);
+    };
+    value
+        .to_str()
+        .ok()
+        .and_then(|value| value.trim().parse::<u8>().ok())
+        .filter(|value| (1..=WS_PUSH_PROTOCOL_VERSION).contains(value))
 }
 
 async fn dispatch_websocket_message(
@@ -273,12 +344,15 @@ async fn dispatch_websocket_message(
 ) -> Result<(), ()> {
     let message: WsRequest = match serde_json::from_str(text) {
         Ok(value) => value,
-        Err(_) => return send_ws_error(socket, Value::Null, 400, "Invalid JSON").await,
+        Err(_) => return send_ws_error(socket, 1, Value::Null, 400, "Invalid JSON").await,
     };
     let id = message.id.unwrap_or(Value::Null);
-    if message.v != Some(1) || message.op.is_none() {
-        return send_ws_error(socket, id, 400, "Unsupported protocol message").await;
-    }
+    let version = match message.v {
+        Some(value) if (1..=WS_PUSH_PROTOCOL_VERSION).contains(&value) && message.op.is_some() => {
+            value
+        }
+        _ => return send_ws_error(socket, 1, id, 400, "Unsupported protocol message").await,
+    };
 
     let op = message.op.as_deref().unwrap_or_default();
     let mut operation_headers = headers.clone();
@@ -290,10 +364,20 @@ async fn dispatch_websocket_message(
     let response = match op {
         "sync" => get_sync(State(state.clone()), operation_headers.clone()).await,
         "get_node" => get_node(State(state.clone()), operation_headers.clone()).await,
-        "get_users" => get_users(State(state.clone()), operation_headers.clone()).await,
+        "get_users" => {
+            let query =
+                serde_json::from_value::<UsersSyncQuery>(payload.0.clone()).unwrap_or_default();
+            get_users(
+                State(state.clone()),
+                operation_headers.clone(),
+                Query(query),
+            )
+            .await
+        }
         "get_audit_rules" => get_audit_rules(State(state.clone()), operation_headers.clone()).await,
         "get_xray_rules" => get_xray_rules(State(state.clone()), operation_headers.clone()).await,
         "get_white_list" => get_white_list(State(state.clone()), operation_headers.clone()).await,
+        "get_block_list" => get_block_list(State(state.clone()), operation_headers.clone()).await,
         "submit_traffic" => {
             post_traffic(State(state.clone()), operation_headers.clone(), payload).await
         }
@@ -305,13 +389,13 @@ async fn dispatch_websocket_message(
         }
         "submit_status" => post_status(State(state.clone()), operation_headers, payload).await,
         "report" => post_report(State(state.clone()), operation_headers, payload).await,
-        _ => return send_ws_error(socket, id, 404, "Unknown operation").await,
+        _ => return send_ws_error(socket, version, id, 404, "Unknown operation").await,
     };
 
     let status = response.status();
     let body = match to_bytes(response.into_body(), MAX_WS_MESSAGE_BYTES).await {
         Ok(body) => body,
-        Err(_) => return send_ws_error(socket, id, 502, "Response too large").await,
+        Err(_) => return send_ws_error(socket, version, id, 502, "Response too large").await,
     };
     let data = if body.is_empty() {
         Value::Null
@@ -334,7 +418,7 @@ async fn dispatch_websocket_message(
     send_ws_json(
         socket,
         json!({
-            "v": 1,
+            "v": version,
             "id": id,
             "ok": ok,
             "status": status.as_u16(),
@@ -347,6 +431,7 @@ async fn dispatch_websocket_message(
 
 async fn send_ws_error(
     socket: &mut WebSocket,
+    version: u8,
     id: Value,
     status: u16,
     message: &str,
@@ -354,7 +439,7 @@ async fn send_ws_error(
     send_ws_json(
         socket,
         json!({
-            "v": 1,
+            "v": version,
             "id": id,
             "ok": false,
             "status": status,
@@ -384,7 +469,7 @@ fn extract_ws_message(data: &Value) -> String {
 }
 
 async fn get_node(State(state): State<AppState>, headers: HeaderMap) -> Response {
-    let auth = match validate_soga_auth(&headers, state.env.node_api_key.as_deref()) {
+    let auth = match validate_soga_auth(&state, &headers).await {
         Ok(auth) => auth,
         Err(resp) => return resp,
     };
@@ -441,21 +526,48 @@ async fn get_node(State(state): State<AppState>, headers: HeaderMap) -> Response
     json_with_etag(&payload, &etag)
 }
 
-async fn get_users(State(state): State<AppState>, headers: HeaderMap) -> Response {
-    let auth = match validate_soga_auth(&headers, state.env.node_api_key.as_deref()) {
+#[derive(Debug, Default, serde::Deserialize)]
+struct UsersSyncQuery {
+    since: Option<i64>,
+}
+
+/// 节点可用用户的判定条件（全量与增量同步共用）
+const NODE_USER_ELIGIBLE_SQL: &str = r#"
+      u.status = 1
+      AND (u.expire_time IS NULL OR u.expire_time > CURRENT_TIMESTAMP)
+      AND (u.class_expire_time IS NULL OR u.class_expire_time > CURRENT_TIMESTAMP)
+      AND u.transfer_enable > u.transfer_total
+      AND u.class >= n.node_class
+"#;
+
+async fn get_users(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<UsersSyncQuery>,
+) -> Response {
+    let auth = match validate_soga_auth(&state, &headers).await {
         Ok(auth) => auth,
         Err(resp) => return resp,
     };
 
-    let node_row =
-        sqlx::query("SELECT CAST(node_config AS CHAR) AS node_config FROM nodes WHERE id = ?")
-            .bind(auth.node_id)
-            .fetch_optional(&state.db)
-            .await;
+    let node_row = sqlx::query(
+        "SELECT CAST(node_config AS CHAR) AS node_config, users_sync_floor FROM nodes WHERE id = ?",
+    )
+    .bind(auth.node_id)
+    .fetch_optional(&state.db)
+    .await;
     let node_row = match node_row {
         Ok(row) => row,
         Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
     };
+    let sync_floor = node_row
+        .as_ref()
+        .and_then(|row| {
+            row.try_get::<Option<i64>, _>("users_sync_floor")
+                .ok()
+                .flatten()
+        })
+        .unwrap_or(0);
     let config_raw = node_row
         .and_then(|row| {
             row.try_get::<Option<String>, _>("node_config")
@@ -469,78 +581,195 @@ async fn get_users(State(state): State<AppState>, headers: HeaderMap) -> Respons
         .cloned()
         .unwrap_or_else(|| config_value.clone());
 
-    let users_rows = sqlx::query(
+    // 先读取当前版本号再查询用户，保证之后的变更不会被遗漏（最多重复下发）
+    let revision = match sqlx::query("SELECT revision FROM sync_revisions WHERE name = 'users'")
+        .fetch_optional(&state.db)
+        .await
+    {
+        Ok(row) => row
+            .and_then(|row| row.try_get::<Option<i64>, _>("revision").ok().flatten())
+            .unwrap_or(0),
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    };
+
+    let since = query.since.filter(|value| *value >= 0);
+    if let Some(since) = since {
+        // 版本号为 0、晚于当前版本（数据库回滚）或早于节点配置变更时，改为全量下发
+        let full = since == 0 || since > revision || since < sync_floor;
+        let (users, removed) = if full {
+            match load_node_users(&state, &auth, &ss_config).await {
+                Ok(users) => (users, Vec::new()),
+                Err(resp) => return resp,
+            }
+        } else {
+            match load_node_user_changes(&state, &auth, &ss_config, since, revision).await {
+                Ok(changes) => changes,
+                Err(resp) => return resp,
+            }
+        };
+        let payload = json!({
+          "revision": revision,
+          "full": full,
+          "users": users,
+          "removed": removed
+        });
+        return users_response(&headers, &payload, revision);
+    }
+
+    let users = match load_node_users(&state, &auth, &ss_config).await {
+        Ok(users) => users,
+        Err(resp) => return resp,
+    };
+    users_response(&headers, &Value::Array(users), revision)
+}
+
+fn users_response(headers: &HeaderMap, payload: &Value, revision: i64) -> Response {
+    let etag = generate_etag(payload);
+    let mut response = if is_etag_match(headers, &etag) {
+        not_modified(&etag)
+    } else {
+        json_with_etag(payload, &etag)
+    };
+    if let Ok(value) = HeaderValue::from_str(&revision.to_string()) {
+        response.headers_mut().insert("x-users-revision", value);
+    }
+    response
+}
+
+async fn load_node_users(
+    state: &AppState,
+    auth: &NodeAuth,
+    ss_config: &Value,
+) -> Result<Vec<Value>, Response> {
+    let sql = format!(
         r#"
     SELECT u.id, u.uuid, u.passwd AS password,
            u.speed_limit, u.device_limit, u.tcp_limit
     FROM users u, nodes n
     WHERE n.id = ?
-      AND u.status = 1
-      AND (u.expire_time IS NULL OR u.expire_time > CURRENT_TIMESTAMP)
-      AND (u.class_expire_time IS NULL OR u.class_expire_time > CURRENT_TIMESTAMP)
-      AND u.transfer_enable > u.transfer_total
-      AND u.class >= n.node_class
-    "#,
-    )
-    .bind(auth.node_id)
-    .fetch_all(&state.db)
-    .await;
-
-    let users_rows = match users_rows {
-        Ok(rows) => rows,
-        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
-    };
+      AND {NODE_USER_ELIGIBLE_SQL}
+    "#
+    );
+    let rows = sqlx::query(&sql)
+        .bind(auth.node_id)
+        .fetch_all(&state.db)
+        .await
+        .map_err(|err| error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None))?;
 
-    let mut users = Vec::with_capacity(users_rows.len());
-    for row in users_rows {
-        let user_id: i64 = row.try_get::<i64, _>("id").unwrap_or(0);
-        let speed_limit: i64 = row
-            .try_get::<Option<i64>, _>("speed_limit")
-            .unwrap_or(Some(0))
-            .unwrap_or(0);
-        let device_limit: i64 = row
-            .try_get::<Option<i64>, _>("device_limit")
-            .unwrap_or(Some(0))
-            .unwrap_or(0);
-        let tcp_limit: i64 = row
-            .try_get::<Option<i64>, _>("tcp_limit")
-            .unwrap_or(Some(0))
-            .unwrap_or(0);
-        let uuid: String = row
-            .try_get::<Option<String>, _>("uuid")
-            .unwrap_or(Some("".to_string()))
-            .unwrap_or_default();
-        let password: String = row
-            .try_get::<Option<String>, _>("password")
-            .unwrap_or(Some("".to_string()))
-            .unwrap_or_default();
+    Ok(rows
+        .iter()
+        .map(|row| build_node_user_entry(row, auth, ss_config))
+        .collect())
+}
 
-        let mut entry = json!({
-          "id": user_id,
-          "speed_limit": speed_limit,
-          "device_limit": device_limit,
-          "tcp_limit": tcp_limit
-        });
+/// 返回 (since, revision] 区间内新增/变更的可用用户，以及被删除或失去资格的用户 ID
+async fn load_node_user_changes(
+    state: &AppState,
+    auth: &NodeAuth,
+    ss_config: &Value,
+    since: i64,
+    revision: i64,
+) -> Result<(Vec<Value>, Vec<i64>), Response> {
+    let sql = format!(
+        r#"
+    SELECT u.id, u.uuid, u.passwd AS password,
+           u.speed_limit, u.device_limit, u.tcp_limit,
+           ({NODE_USER_ELIGIBLE_SQL}) AS eligible
+    FROM users u, nodes n
+    WHERE n.id = ?
+      AND u.sync_revision > ?
+      AND u.sync_revision <= ?
+    "#
+    );
+    let rows = sqlx::query(&sql)
+        .bind(auth.node_id)
+        .bind(since)
+        .bind(revision)
+        .fetch_all(&state.db)
+        .await
+        .map_err(|err| error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None))?;
 
-        if auth.node_type == "v2ray" || auth.node_type == "vmess" || auth.node_type == "vless" {
-            entry["uuid"] = json!(uuid);
-        } else if is_shadowsocks_node_type(&auth.node_type) {
-            let resolved = build_ss_password(&ss_config, &password);
-            entry["password"] = json!(resolved);
-        } else if is_ssr_node_type(&auth.node_type) {
-            entry["password"] = json!(password);
+    let mut users = Vec::new();
+    let mut removed = Vec::new();
+    for row in &rows {
+        let eligible = row
+            .try_get::<Option<i64>, _>("eligible")
+            .ok()
+            .flatten()
+            .unwrap_or(0)
+            == 1;
+        if eligible {
+            users.push(build_node_user_entry(row, auth, ss_config));
         } else {
-            entry["password"] = json!(resolve_uuid_password(&uuid, &password));
+            removed.push(row.try_get::<i64, _>("id").unwrap_or(0));
         }
+    }
+
+    let tombstones = sqlx::query(
+        "SELECT user_id FROM user_sync_tombstones WHERE revision > ? AND revision <= ?",
+    )
+    .bind(since)
+    .bind(revision)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|err| error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None))?;
+    removed.extend(
+        tombstones
+            .iter()
+            .filter_map(|row| row.try_get::<Option<i64>, _>("user_id").ok().flatten()),
+    );
+    removed.sort_unstable();
+    removed.dedup();
+
+    Ok((users, removed))
+}
 
-        users.push(entry);
+fn build_node_user_entry(row: &MySqlRow, auth: &NodeAuth, ss_config: &Value) -> Value {
+    let user_id: i64 = row.try_get::<i64, _>("id").unwrap_or(0);
+    let speed_limit: i64 = row
+        .try_get::<Option<i64>, _>("speed_limit")
+        .unwrap_or(Some(0))
+        .unwrap_or(0);
+    let device_limit: i64 = row
+        .try_get::<Option<i64>, _>("device_limit")
+        .unwrap_or(Some(0))
+        .unwrap_or(0);
+    let tcp_limit: i64 = row
+        .try_get::<Option<i64>, _>("tcp_limit")
+        .unwrap_or(Some(0))
+        .unwrap_or(0);
+    let uuid: String = row
+        .try_get::<Option<String>, _>("uuid")
+        .unwrap_or(Some("".to_string()))
+        .unwrap_or_default();
+    let password: String = row
+        .try_get::<Option<String>, _>("password")
+        .unwrap_or(Some("".to_string()))
+        .unwrap_or_default();
+
+    let mut entry = json!({
+      "id": user_id,
+      "speed_limit": speed_limit,
+      "device_limit": device_limit,
+      "tcp_limit": tcp_limit
+    });
+
+    if auth.node_type == "v2ray" || auth.node_type == "vmess" || auth.node_type == "vless" {
+        entry["uuid"] = json!(uuid);
+    } else if is_shadowsocks_node_type(&auth.node_type) {
+        let resolved = build_ss_password(ss_config, &password);
+        entry["password"] = json!(resolved);
+    } else if is_ssr_node_type(&auth.node_type) {
+        entry["password"] = json!(password);
+    } else {
+        entry["password"] = json!(resolve_uuid_password(&uuid, &password));
     }
 
-    Json(Value::Array(users)).into_response()
+    entry
 }
 
 async fn get_audit_rules(State(state): State<AppState>, headers: HeaderMap) -> Response {
-    let auth = match validate_soga_auth(&headers, state.env.node_api_key.as_deref()) {
+    let auth = match validate_soga_auth(&state, &headers).await {
         Ok(auth) => auth,
         Err(resp) => return resp,
     };
@@ -557,18 +786,39 @@ async fn get_audit_rules(State(state): State<AppState>, headers: HeaderMap) -> R
     }
 
     if rules.is_empty() {
-        let rows =
-            sqlx::query("SELECT id, rule FROM audit_rules WHERE enabled = 1 ORDER BY id ASC")
-                .fetch_all(&state.db)
-                .await;
+        let rows = sqlx::query(
+            "SELECT id, rule_type, rule FROM audit_rules WHERE enabled = 1 ORDER BY id ASC",
+        )
+        .fetch_all(&state.db)
+        .await;
         match rows {
             Ok(records) => {
                 rules = records
                     .into_iter()
-                    .map(|row| {
+                    .filter_map(|row| {
                         let id: i64 = row.try_get("id").unwrap_or(0);
-                        let rule: Option<String> = row.try_get("rule").ok();
-                        json!({ "id": id, "rule": rule })
+                        let rule_type = row
+                            .try_get::<Option<String>, _>("rule_type")
+                            .ok()
+                            .flatten()
+                            .and_then(|value| AuditRuleType::parse(&value))
+                            .unwrap_or(AuditRuleType::Regex);
+                        let pattern = row
+                            .try_get::<Option<String>, _>("rule")
+                            .ok()
+                            .flatten()
+                            .unwrap_or_default();
+                        // 无法编译的旧规则不下发，避免节点加载失败
+                        if let Err(err) = compile_audit_rule(rule_type, &pattern) {
+                            tracing::warn!("[audit_rules] skip rule {id}: {err}");
+                            return None;
+                        }
+                        Some(json!({
+                            "id": id,
+                            "rule": to_soga_rule(rule_type, &pattern),
+                            "type": rule_type.as_str(),
+                            "pattern": pattern
+                        }))
                     })
                     .collect();
                 let _ = cache_set(
@@ -757,7 +1007,7 @@ async fn load_xray_rules_payload(state: &AppState, node_id: i64) -> Result<Value
 }
 
 async fn get_xray_rules(State(state): State<AppState>, headers: HeaderMap) -> Response {
-    let auth = match validate_soga_auth(&headers, state.env.node_api_key.as_deref()) {
+    let auth = match validate_soga_auth(&state, &headers).await {
         Ok(auth) => auth,
         Err(resp) => return resp,
     };
@@ -775,7 +1025,7 @@ async fn get_xray_rules(State(state): State<AppState>, headers: HeaderMap) -> Re
 }
 
 async fn get_white_list(State(state): State<AppState>, headers: HeaderMap) -> Response {
-    let auth = match validate_soga_auth(&headers, state.env.node_api_key.as_deref()) {
+    let auth = match validate_soga_auth(&state, &headers).await {
         Ok(auth) => auth,
         Err(resp) => return resp,
     };
@@ -833,12 +1083,34 @@ async fn get_white_list(State(state): State<AppState>, headers: HeaderMap) -> Re
     json_with_etag(&payload, &etag)
 }
 
+async fn get_block_list(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    if let Err(resp) = validate_soga_auth(&state, &headers).await {
+        return resp;
+    }
+
+    let entries = match load_active_blocked_ips(&state).await {
+        Ok(entries) => entries,
+        Err(message) => return error(StatusCode::INTERNAL_SERVER_ERROR, &message, None),
+    };
+    let payload = Value::Array(
+        entries
+            .into_iter()
+            .map(|entry| json!({ "ip": entry.ip, "expires_at": entry.expires_at }))
+            .collect(),
+    );
+    let etag = generate_etag(&payload);
+    if is_etag_match(&headers, &etag) {
+        return not_modified(&etag);
+    }
+    json_with_etag(&payload, &etag)
+}
+
 async fn post_traffic(
     State(state): State<AppState>,
     headers: HeaderMap,
     Json(body): Json<Value>,
 ) -> Response {
-    let auth = match validate_soga_auth(&headers, state.env.node_api_key.as_deref()) {
+    let auth = match validate_soga_auth(&state, &headers).await {
         Ok(auth) => auth,
         Err(resp) => return resp,
     };
@@ -866,20 +1138,19 @@ async fn post_traffic(
         _ => Vec::new(),
     };
 
-    let multiplier_row = sqlx::query(
-        "SELECT CAST(traffic_multiplier AS DOUBLE) AS traffic_multiplier FROM nodes WHERE id = ?",
+    let node_row = sqlx::query(
+        r#"
+    SELECT CAST(traffic_multiplier AS DOUBLE) AS traffic_multiplier, status, node_class,
+           TIMESTAMPDIFF(SECOND, last_traffic_report_at, CURRENT_TIMESTAMP) AS seconds_since_last
+    FROM nodes
+    WHERE id = ?
+    "#,
     )
     .bind(auth.node_id)
     .fetch_optional(&state.db)
     .await;
-    let raw_multiplier = match multiplier_row {
-        Ok(row) => row
-            .and_then(|r| {
-                r.try_get::<Option<f64>, _>("traffic_multiplier")
-                    .ok()
-                    .flatten()
-            })
-            .unwrap_or(1.0),
+    let node_row = match node_row {
+        Ok(row) => row,
         Err(err) => {
             release_report_event(
                 &state,
@@ -891,17 +1162,33 @@ async fn post_traffic(
             return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None);
         }
     };
+    let read_node_i64 = |key: &str| {
+        node_row
+            .as_ref()
+            .and_then(|row| row.try_get::<Option<i64>, _>(key).ok().flatten())
+    };
+    let raw_multiplier = node_row
+        .as_ref()
+        .and_then(|r| {
+            r.try_get::<Option<f64>, _>("traffic_multiplier")
+                .ok()
+                .flatten()
+        })
+        .unwrap_or(1.0);
     let multiplier = if raw_multiplier > 0.0 {
         raw_multiplier
     } else {
         1.0
     };
+    let context = ReportContext {
+        node_enabled: read_node_i64("status").unwrap_or(1) == 1,
+        node_class: read_node_i64("node_class").unwrap_or(0),
+        interval_seconds: report_interval_seconds(read_node_i64("seconds_since_last")),
+    };
 
-    let date = (Utc::now() + Duration::hours(8)).date_naive();
-    let mut total_traffic: i64 = 0;
-    let mut tx = match state.db.begin().await {
-        Ok(tx) => tx,
-        Err(err) => {
+    let anomaly_config = match load_anomaly_config(&state).await {
+        Ok(value) => value,
+        Err(message) => {
             release_report_event(
                 &state,
                 report_event_id.as_deref(),
@@ -909,10 +1196,11 @@ async fn post_traffic(
                 "submit_traffic",
             )
             .await;
-            return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None);
+            return error(StatusCode::INTERNAL_SERVER_ERROR, &message, None);
         }
     };
 
+    let mut items: Vec<(i64, i64, i64)> = Vec::new();
     for item in data {
         let user_id = value_to_i64(item.get("id"))
             .or_else(|| value_to_i64(item.get("user_id")))
@@ -923,32 +1211,21 @@ async fn post_traffic(
         }
         let upload = value_to_i64(item.get("u")).unwrap_or(0).max(0);
         let download = value_to_i64(item.get("d")).unwrap_or(0).max(0);
-        let total = upload.saturating_add(download);
-        let actual_upload = ((upload as f64) * multiplier).round().max(0.0) as i64;
-        let actual_download = ((download as f64) * multiplier).round().max(0.0) as i64;
-        let deducted_total = actual_upload.saturating_add(actual_download);
+        items.push((user_id, upload, download));
+    }
 
-        if let Err(err) = sqlx::query(
-            r#"
-      UPDATE users
-      SET upload_traffic = upload_traffic + ?,
-          download_traffic = download_traffic + ?,
-          upload_today = upload_today + ?,
-          download_today = download_today + ?,
-          transfer_total = transfer_total + ?,
-          updated_at = CURRENT_TIMESTAMP
-      WHERE id = ?
-      "#,
-        )
-        .bind(upload)
-        .bind(download)
-        .bind(upload)
-        .bind(download)
-        .bind(deducted_total)
-        .bind(user_id)
-        .execute(&mut *tx)
-        .await
-        {
+    let reported_users = match load_reported_users(
+        &state,
+        &items
+            .iter()
+            .map(|(user_id, _, _)| *user_id)
+            .collect::<Vec<i64>>(),
+        anomaly_config.grace_minutes,
+    )
+    .await
+    {
+        Ok(value) => value,
+        Err(message) => {
             release_report_event(
                 &state,
                 report_event_id.as_deref(),
@@ -956,44 +1233,76 @@ async fn post_traffic(
                 "submit_traffic",
             )
             .await;
-            return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None);
+            return error(StatusCode::INTERNAL_SERVER_ERROR, &message, None);
         }
+    };
 
-        if let Err(err) = sqlx::query(
-      r#"
-      INSERT INTO traffic_logs
-      (user_id, node_id, upload_traffic, download_traffic, actual_upload_traffic, actual_download_traffic, actual_traffic, deduction_multiplier, date, created_at)
-      VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP)
-      "#
-    )
-    .bind(user_id)
-    .bind(auth.node_id)
-    .bind(upload)
-    .bind(download)
-    .bind(actual_upload)
-    .bind(actual_download)
-    .bind(deducted_total)
-    .bind(multiplier)
-    .bind(date)
-    .execute(&mut *tx)
-    .await
-    {
-        release_report_event(&state, report_event_id.as_deref(), auth.node_id, "submit_traffic").await;
-        return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None);
-    }
+    let date = (Utc::now() + Duration::hours(8)).date_naive();
+    let mut total_traffic: i64 = 0;
+    let mut anomalies: i64 = 0;
+    let mut tx = match state.db.begin().await {
+        Ok(tx) => tx,
+        Err(err) => {
+            release_report_event(
+                &state,
+                report_event_id.as_deref(),
+                auth.node_id,
+                "submit_traffic",
+            )
+            .await;
+            return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None);
+        }
+    };
 
-        total_traffic = total_traffic.saturating_add(total);
+    for (user_id, upload, download) in items {
+        let total = upload.saturating_add(download);
+        let charge = TrafficCharge {
+            user_id,
+            node_id: auth.node_id,
+            upload,
+            download,
+            multiplier,
+            date,
+        };
+        let reason = classify_report(
+            &anomaly_config,
+            &context,
+            reported_users.get(&user_id),
+            total,
+        );
+        let result = match reason {
+            Some(reason) => {
+                anomalies += 1;
+                quarantine_traffic(&mut tx, &charge, reason, context.interval_seconds).await
+            }
+            None => charge_user_traffic(&mut tx, &charge).await.map(|_| {
+                total_traffic = total_traffic.saturating_add(total);
+            }),
+        };
+        if let Err(message) = result {
+            release_report_event(
+                &state,
+                report_event_id.as_deref(),
+                auth.node_id,
+                "submit_traffic",
+            )
+            .await;
+            return error(StatusCode::INTERNAL_SERVER_ERROR, &message, None);
+        }
     }
 
     if let Err(err) = sqlx::query(
         r#"
     UPDATE nodes
     SET node_bandwidth = node_bandwidth + ?,
+        traffic_anomaly_count = traffic_anomaly_count + ?,
+        last_traffic_report_at = CURRENT_TIMESTAMP,
         updated_at = CURRENT_TIMESTAMP
     WHERE id = ?
     "#,
     )
     .bind(total_traffic)
+    .bind(anomalies)
     .bind(auth.node_id)
     .execute(&mut *tx)
     .await
@@ -1019,15 +1328,66 @@ async fn post_traffic(
         return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None);
     }
 
+    if anomalies > 0 {
+        tracing::warn!(
+            "[traffic] node {} report quarantined {anomalies} suspicious item(s)",
+            auth.node_id
+        );
+    }
     Json(json!({ "code": 0, "message": "ok" })).into_response()
 }
 
+/// 读取本次上报涉及用户的状态，供可疑流量判断
+async fn load_reported_users(
+    state: &AppState,
+    user_ids: &[i64],
+    grace_minutes: i64,
+) -> Result<HashMap<i64, ReportedUser>, String> {
+    if user_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let placeholders = vec!["?"; user_ids.len()].join(",");
+    let sql = format!(
+        r#"
+    SELECT id, status, class,
+           (
+             expire_time BETWEEN DATE_SUB(CURRENT_TIMESTAMP, INTERVAL ? MINUTE) AND CURRENT_TIMESTAMP
+             OR class_expire_time BETWEEN DATE_SUB(CURRENT_TIMESTAMP, INTERVAL ? MINUTE) AND CURRENT_TIMESTAMP
+           ) AS recently_changed
+    FROM users
+    WHERE id IN ({placeholders})
+    "#
+    );
+    let mut query = sqlx::query(&sql).bind(grace_minutes).bind(grace_minutes);
+    for user_id in user_ids {
+        query = query.bind(user_id);
+    }
+    let rows = query
+        .fetch_all(&state.db)
+        .await
+        .map_err(|err| err.to_string())?;
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let read_i64 = |key: &str| row.try_get::<Option<i64>, _>(key).ok().flatten();
+            (
+                read_i64("id").unwrap_or(0),
+                ReportedUser {
+                    status: read_i64("status").unwrap_or(0),
+                    class_level: read_i64("class").unwrap_or(0),
+                    recently_changed: read_i64("recently_changed").unwrap_or(0) == 1,
+                },
+            )
+        })
+        .collect())
+}
+
 async fn post_alive_ip(
     State(state): State<AppState>,
     headers: HeaderMap,
     Json(body): Json<Value>,
 ) -> Response {
-    let auth = match validate_soga_auth(&headers, state.env.node_api_key.as_deref()) {
+    let auth = match validate_soga_auth(&state, &headers).await {
         Ok(auth) => auth,
         Err(resp) => return resp,
     };
@@ -1119,7 +1479,7 @@ async fn post_audit_log(
     headers: HeaderMap,
     Json(body): Json<Value>,
 ) -> Response {
-    let auth = match validate_soga_auth(&headers, state.env.node_api_key.as_deref()) {
+    let auth = match validate_soga_auth(&state, &headers).await {
         Ok(auth) => auth,
         Err(resp) => return resp,
     };
@@ -1197,7 +1557,7 @@ async fn post_status(
     headers: HeaderMap,
     Json(body): Json<Value>,
 ) -> Response {
-    let auth = match validate_soga_auth(&headers, state.env.node_api_key.as_deref()) {
+    let auth = match validate_soga_auth(&state, &headers).await {
         Ok(auth) => auth,
         Err(resp) => return resp,
     };
@@ -1250,10 +1610,7 @@ async fn post_status(
     Json(json!({ "code": 0, "message": "ok" })).into_response()
 }
 
-fn validate_soga_auth(
-    headers: &HeaderMap,
-    expected_key: Option<&str>,
-) -> Result<NodeAuth, Response> {
+async fn validate_soga_auth(state: &AppState, headers: &HeaderMap) -> Result<NodeAuth, Response> {
     let api_key = headers
         .get("api-key")
         .or_else(|| headers.get("x-api-key"))
@@ -1276,19 +1633,63 @@ fn validate_soga_auth(
         return Err(error(StatusCode::UNAUTHORIZED, "缺少认证信息", None));
     }
 
-    if let Some(expected) = expected_key {
-        if api_key.unwrap_or_default() != expected {
-            return Err(error(StatusCode::UNAUTHORIZED, "认证失败", None));
-        }
-    }
-
+    let api_key = api_key.unwrap_or_default();
     let node_id = node_id.unwrap_or_default().parse::<i64>().unwrap_or(0);
     let node_type = node_type.unwrap_or_default().to_lowercase();
     if node_id <= 0 {
         return Err(error(StatusCode::UNAUTHORIZED, "节点信息无效", None));
     }
 
-    Ok(NodeAuth { node_id, node_type })
+    let row = sqlx::query(
+        r#"
+    SELECT api_key_hash, api_key_prev_hash,
+           (api_key_prev_expires_at IS NOT NULL AND api_key_prev_expires_at > CURRENT_TIMESTAMP) AS prev_active
+    FROM nodes
+    WHERE id = ?
+    "#,
+    )
+    .bind(node_id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|err| error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None))?;
+    let Some(row) = row else {
+        return Err(error(StatusCode::UNAUTHORIZED, "节点信息无效", None));
+    };
+
+    let current_hash = row
+        .try_get::<Option<String>, _>("api_key_hash")
+        .ok()
+        .flatten()
+        .unwrap_or_default();
+    if !current_hash.is_empty() {
+        // 节点已配置独立密钥：仅接受当前密钥或宽限期内的旧密钥，不再接受全局密钥
+        let presented = sha256_hex(api_key);
+        let prev_active = row
+            .try_get::<Option<i64>, _>("prev_active")
+            .ok()
+            .flatten()
+            .unwrap_or(0)
+            == 1;
+        let prev_hash = row
+            .try_get::<Option<String>, _>("api_key_prev_hash")
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+        let matched = timing_safe_eq(&presented, &current_hash)
+            || (prev_active && !prev_hash.is_empty() && timing_safe_eq(&presented, &prev_hash));
+        if !matched {
+            return Err(error(StatusCode::UNAUTHORIZED, "认证失败", None));
+        }
+        return Ok(NodeAuth { node_id, node_type });
+    }
+
+    if !state.env.node_api_key_legacy_fallback {
+        return Err(error(StatusCode::UNAUTHORIZED, "节点未配置 API 密钥", None));
+    }
+    match state.env.node_api_key.as_deref() {
+        Some(expected) if timing_safe_eq(api_key, expected) => Ok(NodeAuth { node_id, node_type }),
+        _ => Err(error(StatusCode::UNAUTHORIZED, "认证失败", None)),
+    }
 }
 
 fn decode_base64_safe(value: &str) -> Option<Vec<u8>> {
@@ -1466,4 +1867,14 @@ mod tests {
             "bad request"
         );
     }
+
+    #[test]
+    fn protocol_version_header_defaults_to_v1() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(parse_protocol_version(&headers), Some(1));
+        headers.insert("WS-PROTOCOL-VERSION", HeaderValue::from_static("2"));
+        assert_eq!(parse_protocol_version(&headers), Some(2));
+        headers.insert("WS-PROTOCOL-VERSION", HeaderValue::from_static("9"));
+        assert_eq!(parse_protocol_version(&headers), None);
+    }
 }
@@ -5,12 +5,15 @@ use axum::response::{IntoResponse, Response};
 use axum::routing::{any, post};
 use axum::Router;
 use chrono::{Duration, Local, NaiveDateTime};
-use serde_json::Value;
-use sqlx::Row;
+use serde_json::{json, Value};
+use sqlx::{MySqlConnection, Row};
 
+use crate::money::Money;
 use crate::payment::{
-    verify_callback, verify_epay_callback, verify_epusdt_callback, PaymentCallbackResult,
+    callback_ack, get_callback_amount, get_callback_currency, verify_callback,
+    PaymentCallbackResult, PaymentQueryResult, PaymentSettings,
 };
+use crate::purchase_refund::{split_paid_amount, BenefitSnapshot};
 use crate::referral::award_rebate;
 use crate::state::AppState;
 
@@ -19,6 +22,7 @@ pub fn router() -> Router<AppState> {
         .route("/", post(post_callback))
         .route("/epay", post(post_epay_callback))
         .route("/epusdt", post(post_epusdt_callback))
+        .route("/bank", post(post_bank_callback))
 }
 
 pub fn notify_router() -> Router<AppState> {
@@ -26,77 +30,279 @@ pub fn notify_router() -> Router<AppState> {
 }
 
 async fn post_callback(State(state): State<AppState>, req: Request<Body>) -> Response {
-    let payload = match parse_request_payload(req).await {
-        Ok(value) => value,
-        Err(message) => return text_response(StatusCode::BAD_REQUEST, &message),
-    };
-    let result = verify_callback(&state.env, &payload);
-    handle_callback(&state, result, payload).await
+    receive_callback(&state, req, None).await
 }
 
 async fn post_epay_callback(State(state): State<AppState>, req: Request<Body>) -> Response {
-    let payload = match parse_request_payload(req).await {
-        Ok(value) => value,
-        Err(message) => return text_response(StatusCode::BAD_REQUEST, &message),
-    };
-    let mut result = verify_epay_callback(&state.env, &payload);
-    result.method = Some("epay".to_string());
-    handle_callback(&state, result, payload).await
+    receive_callback(&state, req, Some("epay")).await
 }
 
 async fn post_epusdt_callback(State(state): State<AppState>, req: Request<Body>) -> Response {
-    let payload = match parse_request_payload(req).await {
-        Ok(value) => value,
-        Err(message) => return text_response(StatusCode::BAD_REQUEST, &message),
-    };
-    let mut result = verify_epusdt_callback(&state.env, &payload);
-    result.method = Some("epusdt".to_string());
-    handle_callback(&state, result, payload).await
+    receive_callback(&state, req, Some("epusdt")).await
+}
+
+async fn post_bank_callback(State(state): State<AppState>, req: Request<Body>) -> Response {
+    receive_callback(&state, req, Some("bank")).await
 }
 
 async fn payment_notify(State(state): State<AppState>, req: Request<Body>) -> Response {
+    receive_callback(&state, req, None).await
+}
+
+async fn receive_callback(
+    state: &AppState,
+    req: Request<Body>,
+    provider: Option<&str>,
+) -> Response {
     let payload = match parse_request_payload(req).await {
         Ok(value) => value,
         Err(message) => return text_response(StatusCode::BAD_REQUEST, &message),
     };
-    let epay = verify_epay_callback(&state.env, &payload);
-    if epay.ok {
-        return handle_callback(&state, epay, payload).await;
-    }
-    let mut epusdt = verify_epusdt_callback(&state.env, &payload);
-    epusdt.method = Some("epusdt".to_string());
-    handle_callback(&state, epusdt, payload).await
+    let settings = PaymentSettings::load(state).await;
+    let result = verify_callback(&settings, provider, &payload);
+    handle_callback(state, &settings, result, payload).await
 }
 
 async fn handle_callback(
     state: &AppState,
+    settings: &PaymentSettings,
     result: PaymentCallbackResult,
-    _payload: serde_json::Map<String, Value>,
+    payload: serde_json::Map<String, Value>,
 ) -> Response {
-    if !result.ok {
-        return text_response(StatusCode::OK, "fail");
-    }
-    let trade_no = match result.trade_no {
-        Some(value) if !value.trim().is_empty() => value,
-        _ => return text_response(StatusCode::OK, "fail"),
-    };
-
-    let settled = match settle_trade(state, &trade_no).await {
-        Ok(value) => value,
-        Err(_) => return text_response(StatusCode::OK, "fail"),
+    let mut entry = CallbackLedgerEntry {
+        provider: result
+            .method
+            .clone()
+            .unwrap_or_else(|| "unknown".to_string()),
+        trade_no: result
+            .trade_no
+            .clone()
+            .filter(|value| !value.trim().is_empty()),
+        order_type: None,
+        raw_payload: Value::Object(payload.clone()).to_string(),
+        verified: result.ok,
+        reported_amount: get_callback_amount(&payload),
+        expected_amount: None,
+        currency: get_callback_currency(&payload),
+        status: "invalid_signature",
+        message: None,
+        needs_review: false,
     };
+    let settled = process_callback(state, &mut entry).await;
+    record_callback(state, &entry).await;
 
     if settled {
-        let body = if result.method.as_deref() == Some("epusdt") {
-            "ok"
-        } else {
-            "success"
-        };
+        let body = callback_ack(settings, result.method.as_deref());
         return text_response(StatusCode::OK, body);
     }
     text_response(StatusCode::OK, "fail")
 }
 
+/// 对账任务查单确认已支付后入账，与回调共用金额校验与流水记录
+pub(crate) async fn settle_queried_trade(
+    state: &AppState,
+    provider: &str,
+    trade_no: &str,
+    result: &PaymentQueryResult,
+) -> bool {
+    let mut entry = CallbackLedgerEntry {
+        provider: provider.to_string(),
+        trade_no: Some(trade_no.to_string()),
+        order_type: None,
+        raw_payload: json!({ "source": "reconcile", "result": result }).to_string(),
+        verified: result.paid,
+        reported_amount: result.amount.clone(),
+        expected_amount: None,
+        currency: "CNY".to_string(),
+        status: "invalid_signature",
+        message: None,
+        needs_review: false,
+    };
+    let settled = process_callback(state, &mut entry).await;
+    record_callback(state, &entry).await;
+    settled
+}
+
+/// 支付回调流水（写入 payment_callbacks）
+struct CallbackLedgerEntry {
+    provider: String,
+    trade_no: Option<String>,
+    order_type: Option<&'static str>,
+    raw_payload: String,
+    verified: bool,
+    reported_amount: Option<Money>,
+    expected_amount: Option<Money>,
+    currency: String,
+    status: &'static str,
+    message: Option<String>,
+    needs_review: bool,
+}
+
+/// 校验签名、订单与金额后入账，返回是否应答成功
+async fn process_callback(state: &AppState, entry: &mut CallbackLedgerEntry) -> bool {
+    if !entry.verified {
+        entry.status = "invalid_signature";
+        return false;
+    }
+    let trade_no = match entry.trade_no.clone() {
+        Some(value) => value,
+        None => {
+            entry.status = "order_not_found";
+            entry.message = Some("回调缺少订单号".to_string());
+            return false;
+        }
+    };
+
+    let order = match find_order_amount(state, &trade_no).await {
+        Ok(Some(value)) => value,
+        Ok(None) => {
+            entry.status = "order_not_found";
+            return false;
+        }
+        Err(message) => {
+            entry.status = "error";
+            entry.message = Some(message);
+            return false;
+        }
+    };
+    entry.order_type = Some(order.order_type);
+    entry.expected_amount = Some(order.amount.clone());
+
+    if let Some(message) = check_callback_amount(
+        entry.reported_amount.as_ref(),
+        &entry.currency,
+        &order.amount,
+    ) {
+        entry.status = "amount_mismatch";
+        entry.message = Some(message);
+        entry.needs_review = true;
+        return false;
+    }
+
+    match settle_trade(state, &trade_no).await {
+        Ok(Some(SettleOutcome::Applied)) => {
+            entry.status = "settled";
+            true
+        }
+        Ok(Some(SettleOutcome::AlreadyPaid)) => {
+            entry.status = "duplicate";
+            true
+        }
+        Ok(Some(SettleOutcome::Closed)) => {
+            // 订单已退款/过期等，通道却回调已支付，须人工核对后再决定是否入账或原路退回
+            entry.status = "ignored_closed";
+            entry.message = Some("订单已关闭，未入账".to_string());
+            entry.needs_review = true;
+            false
+        }
+        Ok(None) => {
+            entry.status = "order_not_found";
+            false
+        }
+        Err(message) => {
+            entry.status = "error";
+            entry.message = Some(message);
+            false
+        }
+    }
+}
+
+/// 回调金额按分四舍五入后须与订单金额一致
+fn check_callback_amount(
+    reported: Option<&Money>,
+    currency: &str,
+    expected: &Money,
+) -> Option<String> {
+    if currency != "CNY" {
+        return Some(format!("币种不一致：{currency}"));
+    }
+    match reported {
+        None => Some("回调缺少金额".to_string()),
+        Some(amount) if amount != expected => {
+            Some(format!("金额不一致：回调 {amount}，订单 {expected}"))
+        }
+        Some(_) => None,
+    }
+}
+
+async fn record_callback(state: &AppState, entry: &CallbackLedgerEntry) {
+    let result = sqlx::query(
+        r#"
+    INSERT INTO payment_callbacks
+      (provider, trade_no, order_type, raw_payload, verified, reported_amount, expected_amount,
+       currency, status, message, review_status, created_at)
+    VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP)
+    "#,
+    )
+    .bind(&entry.provider)
+    .bind(&entry.trade_no)
+    .bind(entry.order_type)
+    .bind(&entry.raw_payload)
+    .bind(if entry.verified { 1 } else { 0 })
+    .bind(&entry.reported_amount)
+    .bind(&entry.expected_amount)
+    .bind(&entry.currency)
+    .bind(entry.status)
+    .bind(&entry.message)
+    .bind(if entry.needs_review {
+        "pending"
+    } else {
+        "none"
+    })
+    .execute(&state.db)
+    .await;
+    if let Err(err) = result {
+        tracing::warn!("[payment] record callback failed: {err}");
+    }
+}
+
+struct OrderAmount {
+    order_type: &'static str,
+    amount: Money,
+    paid: bool,
+}
+
+/// 查找订单应付金额（充值为 amount，套餐为在线支付的 price）
+async fn find_order_amount(
+    state: &AppState,
+    trade_no: &str,
+) -> Result<Option<OrderAmount>, String> {
+    let recharge = sqlx::query("SELECT amount, status FROM recharge_records WHERE trade_no = ?")
+        .bind(trade_no)
+        .fetch_optional(&state.db)
+        .await
+        .map_err(|err| err.to_string())?;
+    if let Some(row) = recharge {
+        return Ok(Some(OrderAmount {
+            order_type: "recharge",
+            amount: Money::from_row_or_zero(&row, "amount"),
+            paid: row
+                .try_get::<Option<i64>, _>("status")
+                .ok()
+                .flatten()
+                .unwrap_or(0)
+                == 1,
+        }));
+    }
+
+    let purchase =
+        sqlx::query("SELECT price, status FROM package_purchase_records WHERE trade_no = ?")
+            .bind(trade_no)
+            .fetch_optional(&state.db)
+            .await
+            .map_err(|err| err.to_string())?;
+    Ok(purchase.map(|row| OrderAmount {
+        order_type: "purchase",
+        amount: Money::from_row_or_zero(&row, "price"),
+        paid: row
+            .try_get::<Option<i64>, _>("status")
+            .ok()
+            .flatten()
+            .unwrap_or(0)
+            == 1,
+    }))
+}
+
 async fn parse_request_payload(
     req: Request<Body>,
 ) -> Result<serde_json::Map<String, Value>, String> {
@@ -168,13 +374,24 @@ fn text_response(status: StatusCode, body: &str) -> Response {
         .into_response()
 }
 
-async fn settle_trade(state: &AppState, trade_no: &str) -> Result<bool, String> {
-    if let Some(result) = mark_recharge_paid(state, trade_no).await? {
+/// 入账结果：本次入账、此前已入账、订单已关闭（退款/过期）未入账
+pub(crate) enum SettleOutcome {
+    Applied,
+    AlreadyPaid,
+    Closed,
+}
+
+/// 按订单号入账；订单不存在时返回 None
+pub(crate) async fn settle_trade(
+    state: &AppState,
+    trade_no: &str,
+) -> Result<Option<SettleOutcome>, String> {
+    let applied = if let Some(result) = mark_recharge_paid(state, trade_no).await? {
         if result.applied {
             let _ = award_rebate(
                 state,
                 result.user_id,
-                result.amount,
+                &result.amount,
                 "recharge",
                 Some(result.record_id),
                 Some(trade_no),
@@ -182,16 +399,13 @@ async fn settle_trade(state: &AppState, trade_no: &str) -> Result<bool, String>
             )
             .await;
         }
-        return Ok(true);
-    }
-
-    if let Some(result) = mark_purchase_paid(state, trade_no).await? {
+        result.applied
+    } else if let Some(result) = mark_purchase_paid(state, trade_no).await? {
         if result.applied {
-            let amount = result.amount;
             let _ = award_rebate(
                 state,
                 result.user_id,
-                amount,
+                &result.amount,
                 "purchase",
                 Some(result.record_id),
                 Some(trade_no),
@@ -199,28 +413,44 @@ async fn settle_trade(state: &AppState, trade_no: &str) -> Result<bool, String>
             )
             .await;
         }
-        return Ok(true);
+        result.applied
+    } else {
+        return Ok(None);
+    };
+    if applied {
+        return Ok(Some(SettleOutcome::Applied));
     }
 
-    Ok(false)
+    let paid = find_order_amount(state, trade_no)
+        .await?
+        .is_some_and(|order| order.paid);
+    Ok(Some(if paid {
+        SettleOutcome::AlreadyPaid
+    } else {
+        SettleOutcome::Closed
+    }))
 }
 
 pub(crate) struct RechargePaidResult {
     pub(crate) record_id: i64,
+    /// 入账前的订单状态
+    pub(crate) previous_status: i64,
     pub(crate) user_id: i64,
-    pub(crate) amount: f64,
+    pub(crate) amount: Money,
     pub(crate) applied: bool,
 }
 
+/// 在同一事务中锁定充值订单、标记已支付并增加余额
 pub(crate) async fn mark_recharge_paid(
     state: &AppState,
     trade_no: &str,
 ) -> Result<Option<RechargePaidResult>, String> {
+    let mut tx = state.db.begin().await.map_err(|err| err.to_string())?;
     let record = sqlx::query(
-    "SELECT id, user_id, CAST(amount AS DOUBLE) as amount, status FROM recharge_records WHERE trade_no = ?"
-  )
+        "SELECT id, user_id, amount, status FROM recharge_records WHERE trade_no = ? FOR UPDATE",
+    )
     .bind(trade_no)
-    .fetch_optional(&state.db)
+    .fetch_optional(&mut *tx)
     .await
     .map_err(|err| err.to_string())?;
     let record = match record {
@@ -228,29 +458,27 @@ pub(crate) async fn mark_recharge_paid(
         None => return Ok(None),
     };
 
-    let update = sqlx::query(
-        r#"
-    UPDATE recharge_records
-    SET status = 1, paid_at = CURRENT_TIMESTAMP
-    WHERE trade_no = ? AND status = 0
-    "#,
-    )
-    .bind(trade_no)
-    .execute(&state.db)
-    .await
-    .map_err(|err| err.to_string())?;
-
-    if update.rows_affected() == 0 {
-        return Ok(Some(RechargePaidResult {
-            record_id: record.try_get::<i64, _>("id").unwrap_or(0),
-            user_id: record.try_get::<i64, _>("user_id").unwrap_or(0),
-            amount: parse_decimal(&record, "amount", 0.0),
-            applied: false,
-        }));
+    let status = record
+        .try_get::<Option<i64>, _>("status")
+        .ok()
+        .flatten()
+        .unwrap_or(0);
+    let mut result = RechargePaidResult {
+        record_id: record.try_get::<i64, _>("id").unwrap_or(0),
+        previous_status: status,
+        user_id: record.try_get::<i64, _>("user_id").unwrap_or(0),
+        amount: Money::from_row_or_zero(&record, "amount"),
+        applied: false,
+    };
+    if status != 0 && status != 4 {
+        return Ok(Some(result));
     }
 
-    let user_id = record.try_get::<i64, _>("user_id").unwrap_or(0);
-    let amount = parse_decimal(&record, "amount", 0.0);
+    sqlx::query("UPDATE recharge_records SET status = 1, paid_at = CURRENT_TIMESTAMP WHERE id = ?")
+        .bind(result.record_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|err| err.to_string())?;
     sqlx::query(
         r#"
     UPDATE users
@@ -258,44 +486,43 @@ pub(crate) async fn mark_recharge_paid(
     WHERE id = ?
     "#,
     )
-    .bind(amount)
-    .bind(user_id)
-    .execute(&state.db)
+    .bind(&result.amount)
+    .bind(result.user_id)
+    .execute(&mut *tx)
     .await
     .map_err(|err| err.to_string())?;
 
-    Ok(Some(RechargePaidResult {
-        record_id: record.try_get::<i64, _>("id").unwrap_or(0),
-        user_id,
-        amount,
-        applied: true,
-    }))
+    tx.commit().await.map_err(|err| err.to_string())?;
+    result.applied = true;
+    Ok(Some(result))
 }
 
 pub(crate) struct PurchasePaidResult {
     pub(crate) record_id: i64,
+    /// 入账前的订单状态
+    pub(crate) previous_status: i64,
     pub(crate) user_id: i64,
-    pub(crate) amount: f64,
+    pub(crate) amount: Money,
     pub(crate) applied: bool,
 }
 
+/// 在同一事务中锁定套餐订单、标记已支付、扣除余额、记录优惠券使用并发放套餐权益，全部成功才提交
 pub(crate) async fn mark_purchase_paid(
     state: &AppState,
     trade_no: &str,
 ) -> Result<Option<PurchasePaidResult>, String> {
+    let mut tx = state.db.begin().await.map_err(|err| err.to_string())?;
     let record = sqlx::query(
         r#"
-    SELECT id, user_id, package_id, status,
-           CAST(price AS DOUBLE) as price,
-           CAST(package_price AS DOUBLE) as package_price,
-           CAST(discount_amount AS DOUBLE) as discount_amount,
+    SELECT id, user_id, package_id, status, price, package_price, discount_amount,
            coupon_id, coupon_code, purchase_type
     FROM package_purchase_records
     WHERE trade_no = ?
+    FOR UPDATE
     "#,
     )
     .bind(trade_no)
-    .fetch_optional(&state.db)
+    .fetch_optional(&mut *tx)
     .await
     .map_err(|err| err.to_string())?;
     let record = match record {
@@ -303,103 +530,89 @@ pub(crate) async fn mark_purchase_paid(
         None => return Ok(None),
     };
 
+    let status = record
+        .try_get::<Option<i64>, _>("status")
+        .ok()
+        .flatten()
+        .unwrap_or(0);
+    let mut result = PurchasePaidResult {
+        record_id: record.try_get::<i64, _>("id").unwrap_or(0),
+        previous_status: status,
+        user_id: record.try_get::<i64, _>("user_id").unwrap_or(0),
+        amount: Money::from_row_or_zero(&record, "price"),
+        applied: false,
+    };
+    if status != 0 && status != 4 {
+        return Ok(Some(result));
+    }
+
     let package_id = record.try_get::<i64, _>("package_id").unwrap_or(0);
-    let package = match get_package_by_id_any(state, package_id).await? {
+    let package = match get_package_by_id_any(&mut tx, package_id).await? {
         Some(value) => value,
         None => return Err("套餐不存在或已下架".to_string()),
     };
 
-    let update = sqlx::query(
-        r#"
-    UPDATE package_purchase_records
-    SET status = 1, paid_at = CURRENT_TIMESTAMP
-    WHERE trade_no = ? AND status = 0
-    "#,
+    sqlx::query(
+        "UPDATE package_purchase_records SET status = 1, paid_at = CURRENT_TIMESTAMP WHERE id = ?",
     )
-    .bind(trade_no)
-    .execute(&state.db)
+    .bind(result.record_id)
+    .execute(&mut *tx)
     .await
     .map_err(|err| err.to_string())?;
 
-    if update.rows_affected() == 0 {
-        return Ok(Some(PurchasePaidResult {
-            record_id: record.try_get::<i64, _>("id").unwrap_or(0),
-            user_id: record.try_get::<i64, _>("user_id").unwrap_or(0),
-            amount: parse_decimal(&record, "price", 0.0),
-            applied: false,
-        }));
-    }
-
     let purchase_type = record
         .try_get::<Option<String>, _>("purchase_type")
         .ok()
         .flatten()
         .unwrap_or_default()
         .to_lowercase();
-    if purchase_type.starts_with("balance_") {
-        let base_price = parse_decimal(
-            &record,
-            "package_price",
-            parse_decimal(&record, "price", 0.0),
+    if purchase_type == "balance" || purchase_type.starts_with("balance_") {
+        let (balance_need, _) = split_paid_amount(
+            &purchase_type,
+            Money::from_row_or_zero(&record, "price"),
+            Money::from_row_or_zero(&record, "package_price"),
+            Money::from_row_or_zero(&record, "discount_amount"),
         );
-        let discount = parse_decimal(&record, "discount_amount", 0.0);
-        let online_paid = parse_decimal(&record, "price", 0.0);
-        let balance_need = (base_price - discount - online_paid).max(0.0);
-        if balance_need > 0.0 {
-            let ok = deduct_user_balance(
-                state,
-                record.try_get::<i64, _>("user_id").unwrap_or(0),
-                balance_need,
-            )
-            .await?;
-            if !ok {
-                sqlx::query(
-                    r#"
-          UPDATE package_purchase_records
-          SET status = 0, paid_at = NULL
-          WHERE trade_no = ? AND status = 1
-          "#,
-                )
-                .bind(trade_no)
-                .execute(&state.db)
-                .await
-                .map_err(|err| err.to_string())?;
-                return Err("余额扣除失败，请联系管理员".to_string());
-            }
+        if balance_need.is_positive()
+            && !deduct_user_balance(&mut tx, result.user_id, &balance_need).await?
+        {
+            return Err("余额不足，扣款失败".to_string());
         }
     }
 
-    let coupon_id = record.try_get::<Option<i64>, _>("coupon_id").ok().flatten();
-    if let Some(coupon_id) = coupon_id {
-        let order_id = record.try_get::<i64, _>("id").unwrap_or(0);
-        let user_id = record.try_get::<i64, _>("user_id").unwrap_or(0);
-        record_coupon_usage(state, coupon_id, user_id, order_id, trade_no).await?;
+    if let Some(coupon_id) = record.try_get::<Option<i64>, _>("coupon_id").ok().flatten() {
+        record_coupon_usage(
+            &mut tx,
+            coupon_id,
+            result.user_id,
+            result.record_id,
+            trade_no,
+        )
+        .await?;
     }
 
-    let apply = update_user_after_package_purchase(
-        state,
-        record.try_get::<i64, _>("user_id").unwrap_or(0),
-        &package,
+    let apply = update_user_after_package_purchase(&mut tx, result.user_id, &package).await?;
+    let snapshot = serde_json::to_string(&apply.snapshot).map_err(|err| err.to_string())?;
+    sqlx::query(
+        "UPDATE package_purchase_records SET expires_at = ?, benefit_snapshot = ? WHERE id = ?",
     )
-    .await?;
-    if let Some(expire) = apply.new_expire_time.as_ref() {
-        sqlx::query("UPDATE package_purchase_records SET expires_at = ? WHERE trade_no = ?")
-            .bind(expire)
-            .bind(trade_no)
-            .execute(&state.db)
-            .await
-            .map_err(|err| err.to_string())?;
-    }
+    .bind(apply.new_expire_time.as_ref())
+    .bind(snapshot)
+    .bind(result.record_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|err| err.to_string())?;
 
-    Ok(Some(PurchasePaidResult {
-        record_id: record.try_get::<i64, _>("id").unwrap_or(0),
-        user_id: record.try_get::<i64, _>("user_id").unwrap_or(0),
-        amount: parse_decimal(&record, "price", 0.0),
-        applied: true,
-    }))
+    tx.commit().await.map_err(|err| err.to_string())?;
+    result.applied = true;
+    Ok(Some(result))
 }
 
-async fn deduct_user_balance(state: &AppState, user_id: i64, amount: f64) -> Result<bool, String> {
+async fn deduct_user_balance(
+    conn: &mut MySqlConnection,
+    user_id: i64,
+    amount: &Money,
+) -> Result<bool, String> {
     let result = sqlx::query(
         r#"
     UPDATE users
@@ -410,14 +623,14 @@ async fn deduct_user_balance(state: &AppState, user_id: i64, amount: f64) -> Res
     .bind(amount)
     .bind(user_id)
     .bind(amount)
-    .execute(&state.db)
+    .execute(&mut *conn)
     .await
     .map_err(|err| err.to_string())?;
     Ok(result.rows_affected() > 0)
 }
 
 async fn record_coupon_usage(
-    state: &AppState,
+    conn: &mut MySqlConnection,
     coupon_id: i64,
     user_id: i64,
     order_id: i64,
@@ -433,7 +646,7 @@ async fn record_coupon_usage(
     .bind(user_id)
     .bind(order_id)
     .bind(trade_no)
-    .execute(&state.db)
+    .execute(&mut *conn)
     .await
     .map_err(|err| err.to_string())?;
     Ok(())
@@ -449,7 +662,7 @@ struct PackageRow {
 }
 
 async fn get_package_by_id_any(
-    state: &AppState,
+    conn: &mut MySqlConnection,
     package_id: i64,
 ) -> Result<Option<PackageRow>, String> {
     let row = sqlx::query(
@@ -460,7 +673,7 @@ async fn get_package_by_id_any(
     "#,
     )
     .bind(package_id)
-    .fetch_optional(&state.db)
+    .fetch_optional(&mut *conn)
     .await
     .map_err(|err| err.to_string())?;
 
@@ -494,10 +707,11 @@ async fn get_package_by_id_any(
 
 struct ApplyPackageResult {
     new_expire_time: Option<String>,
+    snapshot: BenefitSnapshot,
 }
 
 async fn update_user_after_package_purchase(
-    state: &AppState,
+    conn: &mut MySqlConnection,
     user_id: i64,
     package: &PackageRow,
 ) -> Result<ApplyPackageResult, String> {
@@ -506,10 +720,11 @@ async fn update_user_after_package_purchase(
     SELECT class, class_expire_time, transfer_enable, transfer_total, speed_limit, device_limit
     FROM users
     WHERE id = ?
+    FOR UPDATE
     "#,
     )
     .bind(user_id)
-    .fetch_optional(&state.db)
+    .fetch_optional(&mut *conn)
     .await
     .map_err(|err| err.to_string())?;
     let user_row = match user_row {
@@ -586,7 +801,7 @@ async fn update_user_after_package_purchase(
         .bind(package.speed_limit)
         .bind(package.device_limit)
         .bind(user_id)
-        .execute(&state.db)
+        .execute(&mut *conn)
         .await
         .map_err(|err| err.to_string())?;
     } else {
@@ -604,25 +819,47 @@ async fn update_user_after_package_purchase(
         .bind(package.speed_limit)
         .bind(package.device_limit)
         .bind(user_id)
-        .execute(&state.db)
+        .execute(&mut *conn)
         .await
         .map_err(|err| err.to_string())?;
     }
 
     Ok(ApplyPackageResult {
         new_expire_time: Some(new_expire.format("%Y-%m-%d %H:%M:%S").to_string()),
+        snapshot: BenefitSnapshot {
+            class: current_level,
+            class_expire_time: class_expire
+                .map(|value| value.format("%Y-%m-%d %H:%M:%S").to_string()),
+            transfer_enable: current_transfer_enable,
+            speed_limit: user_row
+                .try_get::<Option<i64>, _>("speed_limit")
+                .ok()
+                .flatten()
+                .unwrap_or(0),
+            device_limit: user_row
+                .try_get::<Option<i64>, _>("device_limit")
+                .ok()
+                .flatten()
+                .unwrap_or(0),
+            granted_traffic: package_traffic_bytes,
+            granted_days: validity_days,
+            reset: reset_used,
+        },
     })
 }
 
-fn parse_decimal(row: &sqlx::mysql::MySqlRow, column: &str, fallback: f64) -> f64 {
-    if let Ok(Some(value)) = row.try_get::<Option<f64>, _>(column) {
-        return value;
-    }
-    if let Ok(Some(value)) = row.try_get::<Option<sqlx::types::BigDecimal>, _>(column) {
-        return value.to_string().parse::<f64>().unwrap_or(fallback);
-    }
-    if let Ok(Some(value)) = row.try_get::<Option<String>, _>(column) {
-        return value.parse::<f64>().unwrap_or(fallback);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn callback_amount_must_match_order() {
+        let money = |text: &str| Money::parse(text).unwrap_or_default();
+        let expected = money("10");
+        assert!(check_callback_amount(Some(&money("10.00")), "CNY", &expected).is_none());
+        assert!(check_callback_amount(Some(&money("9.995")), "CNY", &expected).is_none());
+        assert!(check_callback_amount(Some(&money("9.99")), "CNY", &expected).is_some());
+        assert!(check_callback_amount(None, "CNY", &expected).is_some());
+        assert!(check_callback_amount(Some(&money("10")), "USD", &expected).is_some());
     }
-    fallback
 }
@@ -4,7 +4,7 @@ use axum::routing::get;
 use axum::Router;
 use serde_json::json;
 
-use crate::payment::get_payment_methods;
+use crate::payment::{get_payment_methods, PaymentSettings};
 use crate::response::success;
 use crate::state::AppState;
 
@@ -13,7 +13,8 @@ pub fn router() -> Router<AppState> {
 }
 
 async fn get_payment_config(State(state): State<AppState>) -> Response {
-    let methods = get_payment_methods(&state.env);
+    let settings = PaymentSettings::load(&state).await;
+    let methods = get_payment_methods(&settings);
     let values: Vec<String> = methods.iter().map(|item| item.value.clone()).collect();
     success(
         json!({
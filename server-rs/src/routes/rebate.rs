@@ -3,11 +3,13 @@ use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
 use axum::routing::{get, post};
 use axum::{Extension, Json, Router};
+use bigdecimal::{BigDecimal, ToPrimitive};
 use chrono::NaiveDateTime;
 use serde::Deserialize;
 use serde_json::{json, Value};
 use sqlx::Row;
 
+use crate::money::{parse_rate, Money};
 use crate::referral::insert_user_transaction;
 use crate::response::{error, success};
 use crate::state::AppState;
@@ -47,9 +49,9 @@ async fn get_balance(
         None => return error(StatusCode::NOT_FOUND, "用户不存在", None),
     };
 
-    let rebate_available = parse_decimal(&row, "rebate_available", 0.0);
-    let rebate_total = parse_decimal(&row, "rebate_total", 0.0);
-    let balance = parse_decimal(&row, "money", 0.0);
+    let rebate_available = Money::from_row_or_zero(&row, "rebate_available");
+    let rebate_total = Money::from_row_or_zero(&row, "rebate_total");
+    let balance = Money::from_row_or_zero(&row, "money");
 
     success(
         json!({
@@ -81,28 +83,26 @@ async fn post_withdraw(
         Err(resp) => return resp,
     };
 
-    let amount_raw = body.amount.unwrap_or(0.0);
-    let amount = fix_money_precision(amount_raw);
-    if amount <= 0.0 {
+    let amount = body.amount.and_then(Money::from_f64).unwrap_or_default();
+    if !amount.is_positive() {
         return error(StatusCode::BAD_REQUEST, "金额无效", None);
     }
 
     let configs = list_system_configs(&state).await.unwrap_or_default();
     let min_amount = configs
         .get("rebate_withdraw_min_amount")
-        .and_then(|value| value.parse::<f64>().ok())
-        .filter(|value| *value > 0.0)
-        .unwrap_or(200.0);
+        .and_then(|value| Money::parse(value))
+        .filter(Money::is_positive)
+        .unwrap_or_else(|| Money::from(200));
     let fee_rate = configs
         .get("rebate_withdraw_fee_rate")
-        .and_then(|value| value.parse::<f64>().ok())
-        .unwrap_or(0.0)
-        .clamp(0.0, 1.0);
+        .and_then(|value| parse_rate(value))
+        .unwrap_or_default();
 
-    if amount + 1e-6 < min_amount {
+    if amount < min_amount {
         return error(
             StatusCode::BAD_REQUEST,
-            &format!("单次提现金额需不少于 {:.2} 元", min_amount),
+            &format!("单次提现金额需不少于 {} 元", min_amount),
             None,
         );
     }
@@ -119,19 +119,19 @@ async fn post_withdraw(
         Some(value) => value,
         None => return error(StatusCode::NOT_FOUND, "用户不存在", None),
     };
-    let available = parse_decimal(&user_row, "rebate_available", 0.0);
-    if available + 1e-6 < min_amount {
+    let available = Money::from_row_or_zero(&user_row, "rebate_available");
+    if available < min_amount {
         return error(
             StatusCode::BAD_REQUEST,
-            &format!("返利余额满 {:.2} 元才允许提现", min_amount),
+            &format!("返利余额满 {} 元才允许提现", min_amount),
             None,
         );
     }
-    if available + 1e-6 < amount {
+    if available < amount {
         return error(StatusCode::BAD_REQUEST, "返利余额不足", None);
     }
 
-    let fee_amount = fix_money_precision(amount * fee_rate);
+    let fee_amount = amount.mul_rate(&fee_rate);
     let account_payload = body
         .account_payload
         .or(body.account)
@@ -139,19 +139,29 @@ async fn post_withdraw(
         .filter(|value| !value.trim().is_empty());
     let method = body.method.unwrap_or_else(|| "manual".to_string());
 
+    // 扣减返利与提现申请在同一事务中提交，避免扣了余额却没有申请记录
+    let mut tx = match state.db.begin().await {
+        Ok(value) => value,
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    };
     let update = sqlx::query(
         r#"
     UPDATE users
     SET rebate_available = rebate_available - ?, updated_at = CURRENT_TIMESTAMP
-    WHERE id = ?
+    WHERE id = ? AND rebate_available >= ?
     "#,
     )
-    .bind(amount)
+    .bind(&amount)
     .bind(user_id)
-    .execute(&state.db)
+    .bind(&amount)
+    .execute(&mut *tx)
     .await;
-    if let Err(err) = update {
-        return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None);
+    match update {
+        Ok(result) if result.rows_affected() == 0 => {
+            return error(StatusCode::BAD_REQUEST, "返利余额不足", None)
+        }
+        Ok(_) => {}
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
     }
 
     let insert = sqlx::query(
@@ -162,29 +172,35 @@ async fn post_withdraw(
     "#,
     )
     .bind(user_id)
-    .bind(amount)
+    .bind(&amount)
     .bind(&method)
     .bind(account_payload.clone())
-    .bind(fee_rate)
-    .bind(fee_amount)
-    .execute(&state.db)
+    .bind(&fee_rate)
+    .bind(&fee_amount)
+    .execute(&mut *tx)
     .await;
     let withdrawal_id = match insert {
         Ok(result) => result.last_insert_id() as i64,
         Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
     };
 
-    let _ = insert_user_transaction(
-        &state,
+    if let Err(message) = insert_user_transaction(
+        &mut *tx,
         user_id,
-        -amount,
+        &-amount,
         "withdraw",
         "withdraw",
         Some(withdrawal_id),
         None,
         Some(&method),
     )
-    .await;
+    .await
+    {
+        return error(StatusCode::INTERNAL_SERVER_ERROR, &message, None);
+    }
+    if let Err(err) = tx.commit().await {
+        return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None);
+    }
 
     success(Value::Null, "提现申请已提交").into_response()
 }
@@ -234,11 +250,11 @@ async fn get_transfers(
     .into_iter()
     .map(|row| {
       json!({
-        "amount": parse_decimal(&row, "amount", 0.0),
-        "balance_before": parse_decimal(&row, "balance_before", 0.0),
-        "balance_after": parse_decimal(&row, "balance_after", 0.0),
-        "rebate_before": parse_decimal(&row, "rebate_before", 0.0),
-        "rebate_after": parse_decimal(&row, "rebate_after", 0.0),
+        "amount": Money::from_row_or_zero(&row, "amount"),
+        "balance_before": Money::from_row_or_zero(&row, "balance_before"),
+        "balance_after": Money::from_row_or_zero(&row, "balance_after"),
+        "rebate_before": Money::from_row_or_zero(&row, "rebate_before"),
+        "rebate_after": Money::from_row_or_zero(&row, "rebate_after"),
         "created_at": format_datetime(row.try_get::<Option<NaiveDateTime>, _>("created_at").ok().flatten())
       })
     })
@@ -316,13 +332,18 @@ async fn get_withdrawals(
         .and_then(|value| serde_json::from_str::<Value>(&value).ok());
       json!({
         "id": row.try_get::<i64, _>("id").unwrap_or(0),
-        "amount": parse_decimal(&row, "amount", 0.0),
+        "amount": Money::from_row_or_zero(&row, "amount"),
         "method": row.try_get::<Option<String>, _>("method").ok().flatten().unwrap_or_default(),
         "status": row.try_get::<Option<String>, _>("status").ok().flatten().unwrap_or_default(),
         "accountPayload": payload,
         "reviewNote": row.try_get::<Option<String>, _>("review_note").ok().flatten().unwrap_or_default(),
-        "feeRate": parse_decimal(&row, "fee_rate", 0.0),
-        "feeAmount": parse_decimal(&row, "fee_amount", 0.0),
+        "feeRate": row
+            .try_get::<Option<BigDecimal>, _>("fee_rate")
+            .ok()
+            .flatten()
+            .and_then(|value| value.to_f64())
+            .unwrap_or(0.0),
+        "feeAmount": Money::from_row_or_zero(&row, "fee_amount"),
         "createdAt": format_datetime(row.try_get::<Option<NaiveDateTime>, _>("created_at").ok().flatten()),
         "updatedAt": format_datetime(row.try_get::<Option<NaiveDateTime>, _>("updated_at").ok().flatten()),
         "processedAt": format_datetime(row.try_get::<Option<NaiveDateTime>, _>("processed_at").ok().flatten())
@@ -420,11 +441,12 @@ async fn get_transactions(
         "inviter_id": row.try_get::<i64, _>("inviter_id").unwrap_or(0),
         "referral_id": row.try_get::<Option<i64>, _>("referral_id").ok().flatten(),
         "invitee_id": row.try_get::<Option<i64>, _>("invitee_id").ok().flatten(),
+        "level": row.try_get::<Option<i64>, _>("level").ok().flatten().unwrap_or(1),
         "source_type": row.try_get::<Option<String>, _>("source_type").ok().flatten().unwrap_or_default(),
         "source_id": row.try_get::<Option<i64>, _>("source_id").ok().flatten(),
         "trade_no": row.try_get::<Option<String>, _>("trade_no").ok().flatten(),
         "event_type": row.try_get::<Option<String>, _>("event_type").ok().flatten().unwrap_or_default(),
-        "amount": parse_decimal(&row, "amount", 0.0),
+        "amount": Money::from_row_or_zero(&row, "amount"),
         "status": row.try_get::<Option<String>, _>("status").ok().flatten().unwrap_or_default(),
         "remark": row.try_get::<Option<String>, _>("remark").ok().flatten(),
         "created_at": format_datetime(row.try_get::<Option<NaiveDateTime>, _>("created_at").ok().flatten()),
@@ -508,7 +530,7 @@ async fn get_ledger(
     let data_sql = format!(
         r#"
     SELECT rt.id, rt.event_type, rt.amount, rt.source_type, rt.source_id,
-           rt.trade_no, rt.status, rt.created_at, rt.invitee_id,
+           rt.trade_no, rt.status, rt.created_at, rt.invitee_id, rt.level,
            u.email as invitee_email
     FROM rebate_transactions rt
     LEFT JOIN users u ON rt.invitee_id = u.id
@@ -535,9 +557,10 @@ async fn get_ledger(
       json!({
         "id": row.try_get::<i64, _>("id").unwrap_or(0),
         "eventType": row.try_get::<Option<String>, _>("event_type").ok().flatten().unwrap_or_default(),
-        "amount": parse_decimal(&row, "amount", 0.0),
+        "amount": Money::from_row_or_zero(&row, "amount"),
         "sourceType": row.try_get::<Option<String>, _>("source_type").ok().flatten().unwrap_or_default(),
         "sourceId": row.try_get::<Option<i64>, _>("source_id").ok().flatten(),
+        "level": row.try_get::<Option<i64>, _>("level").ok().flatten().unwrap_or(1),
         "tradeNo": row.try_get::<Option<String>, _>("trade_no").ok().flatten().unwrap_or_default(),
         "status": row.try_get::<Option<String>, _>("status").ok().flatten().unwrap_or_default(),
         "createdAt": format_datetime(row.try_get::<Option<NaiveDateTime>, _>("created_at").ok().flatten()),
@@ -581,8 +604,8 @@ async fn post_transfer(
         Err(resp) => return resp,
     };
 
-    let amount = fix_money_precision(body.amount.unwrap_or(0.0));
-    if amount <= 0.0 {
+    let amount = body.amount.and_then(Money::from_f64).unwrap_or_default();
+    if !amount.is_positive() {
         return error(StatusCode::BAD_REQUEST, "金额无效", None);
     }
 
@@ -598,8 +621,8 @@ async fn post_transfer(
         Some(value) => value,
         None => return error(StatusCode::NOT_FOUND, "用户不存在", None),
     };
-    let rebate_available = parse_decimal(&row, "rebate_available", 0.0);
-    if rebate_available + 1e-6 < amount {
+    let rebate_available = Money::from_row_or_zero(&row, "rebate_available");
+    if rebate_available < amount {
         return error(StatusCode::BAD_REQUEST, "返利余额不足", None);
     }
 
@@ -607,20 +630,25 @@ async fn post_transfer(
         r#"
     UPDATE users
     SET rebate_available = rebate_available - ?, money = money + ?, updated_at = CURRENT_TIMESTAMP
-    WHERE id = ?
+    WHERE id = ? AND rebate_available >= ?
     "#,
     )
-    .bind(amount)
-    .bind(amount)
+    .bind(&amount)
+    .bind(&amount)
     .bind(user_id)
+    .bind(&amount)
     .execute(&state.db)
     .await;
-    if let Err(err) = update {
-        return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None);
+    match update {
+        Ok(result) if result.rows_affected() == 0 => {
+            return error(StatusCode::BAD_REQUEST, "返利余额不足", None)
+        }
+        Ok(_) => {}
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
     }
 
     let _ = insert_user_transaction(
-        &state, user_id, -amount, "transfer", "balance", None, None, None,
+        &state.db, user_id, &-amount, "transfer", "balance", None, None, None,
     )
     .await;
 
@@ -636,8 +664,8 @@ async fn post_transfer(
         Some(value) => value,
         None => return error(StatusCode::NOT_FOUND, "用户不存在", None),
     };
-    let money = parse_decimal(&balance_row, "money", 0.0);
-    let rebate_available = parse_decimal(&balance_row, "rebate_available", 0.0);
+    let money = Money::from_row_or_zero(&balance_row, "money");
+    let rebate_available = Money::from_row_or_zero(&balance_row, "rebate_available");
 
     success(
         json!({
@@ -649,23 +677,6 @@ async fn post_transfer(
     .into_response()
 }
 
-fn fix_money_precision(amount: f64) -> f64 {
-    (amount * 100.0).round() / 100.0
-}
-
-fn parse_decimal(row: &sqlx::mysql::MySqlRow, column: &str, fallback: f64) -> f64 {
-    if let Ok(Some(value)) = row.try_get::<Option<f64>, _>(column) {
-        return value;
-    }
-    if let Ok(Some(value)) = row.try_get::<Option<sqlx::types::BigDecimal>, _>(column) {
-        return value.to_string().parse::<f64>().unwrap_or(fallback);
-    }
-    if let Ok(Some(value)) = row.try_get::<Option<String>, _>(column) {
-        return value.parse::<f64>().unwrap_or(fallback);
-    }
-    fallback
-}
-
 fn format_datetime(value: Option<NaiveDateTime>) -> Option<String> {
     value.map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
 }
@@ -8,7 +8,9 @@ use serde::Deserialize;
 use serde_json::{json, Value};
 use sqlx::Row;
 
+use crate::client_ip::client_ip;
 use crate::crypto::random_string;
+use crate::money::Money;
 use crate::payment::{self, PaymentOrder};
 use crate::response::{error, success};
 use crate::state::AppState;
@@ -55,7 +57,7 @@ struct PurchaseRequest {
 struct PackageSummary {
     id: i64,
     name: String,
-    price: f64,
+    price: Money,
 }
 
 #[derive(Clone)]
@@ -64,7 +66,7 @@ struct CouponRow {
     name: String,
     code: String,
     discount_type: String,
-    discount_value: f64,
+    discount_value: Money,
     start_at: i64,
     end_at: i64,
     max_usage: Option<i64>,
@@ -118,7 +120,7 @@ async fn get_packages(
 
     let list_sql = format!(
         r#"
-    SELECT id, name, CAST(price AS DOUBLE) as price, traffic_quota, validity_days, speed_limit, device_limit,
+    SELECT id, name, price, traffic_quota, validity_days, speed_limit, device_limit,
            level, is_recommended, sort_weight, created_at
     FROM packages
     {where_clause}
@@ -169,16 +171,16 @@ async fn get_package_detail(State(state): State<AppState>, Path(id): Path<i64>)
     }
 
     let row = sqlx::query(
-    r#"
-    SELECT id, name, CAST(price AS DOUBLE) as price, traffic_quota, validity_days, speed_limit, device_limit,
+        r#"
+    SELECT id, name, price, traffic_quota, validity_days, speed_limit, device_limit,
            level, is_recommended, sort_weight, created_at
     FROM packages
     WHERE id = ? AND status = 1
-    "#
-  )
-  .bind(id)
-  .fetch_optional(&state.db)
-  .await;
+    "#,
+    )
+    .bind(id)
+    .fetch_optional(&state.db)
+    .await;
     let row = match row {
         Ok(value) => value,
         Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
@@ -244,8 +246,8 @@ async fn post_coupon_preview(
     }
 
     let price = package.price;
-    let discount = compute_coupon_discount(&coupon, price);
-    let final_price = (price - discount).max(0.0);
+    let discount = compute_coupon_discount(&coupon, &price);
+    let final_price = (price.clone() - discount.clone()).non_negative();
 
     success(
         json!({
@@ -291,9 +293,9 @@ async fn post_purchase(
         Err(message) => return error(StatusCode::INTERNAL_SERVER_ERROR, &message, None),
     };
 
-    let original_price = package.price;
-    let mut price = original_price;
-    let mut discount = 0.0;
+    let original_price = package.price.clone();
+    let mut price = original_price.clone();
+    let mut discount = Money::zero();
     let mut coupon_id: Option<i64> = None;
     let mut coupon_code: Option<String> = None;
 
@@ -333,8 +335,8 @@ async fn post_purchase(
             }
         }
 
-        discount = compute_coupon_discount(&coupon, price);
-        price = (price - discount).max(0.0);
+        discount = compute_coupon_discount(&coupon, &price);
+        price = (price - discount.clone()).non_negative();
         coupon_id = Some(coupon.id);
         coupon_code = Some(coupon.code);
     }
@@ -351,34 +353,35 @@ async fn post_purchase(
         .unwrap_or_else(|| "balance".to_string())
         .to_lowercase();
 
+    let settings = payment::PaymentSettings::load(&state).await;
     let normalized_channel = payment::normalize_channel(body.payment_method.as_deref());
-    let default_channel = payment::active_channels(&state.env).first().copied();
+    let default_channel = payment::active_channels(&settings).first().copied();
 
     let mut actual_purchase_type = requested_type.as_str();
-    let mut payment_amount = price;
+    let mut payment_amount = price.clone();
 
-    if price <= 0.0 {
+    if !price.is_positive() {
         actual_purchase_type = "balance";
-        payment_amount = 0.0;
+        payment_amount = Money::zero();
     } else if requested_type == "balance" {
         if user_balance < price {
             actual_purchase_type = "smart_topup";
-            payment_amount = (price - user_balance).max(0.0);
+            payment_amount = (price.clone() - user_balance.clone()).non_negative();
         }
     } else if requested_type == "direct" {
-        if user_balance > 0.0 && user_balance < price {
+        if user_balance.is_positive() && user_balance < price {
             actual_purchase_type = "smart_topup";
-            payment_amount = (price - user_balance).max(0.0);
+            payment_amount = (price.clone() - user_balance.clone()).non_negative();
         }
     } else if requested_type == "smart_topup" {
         actual_purchase_type = "smart_topup";
-        payment_amount = (price - user_balance).max(0.0);
+        payment_amount = (price.clone() - user_balance.clone()).non_negative();
     } else {
         actual_purchase_type = "balance";
-        payment_amount = price;
+        payment_amount = price.clone();
     }
 
-    if payment_amount <= 0.0 {
+    if !payment_amount.is_positive() {
         actual_purchase_type = "balance";
     }
 
@@ -393,14 +396,14 @@ async fn post_purchase(
         channel_for_online.unwrap_or("online").to_string()
     };
 
-    if price <= 0.0 {
+    if !price.is_positive() {
         let insert = create_purchase_record(
             &state,
             user_id,
             package.id,
-            0.0,
-            original_price,
-            discount,
+            &Money::zero(),
+            &original_price,
+            &discount,
             "balance",
             &trade_no,
             coupon_id,
@@ -434,17 +437,14 @@ async fn post_purchase(
     }
 
     if actual_purchase_type == "balance" {
-        if let Err(message) = deduct_user_balance(&state, user_id, price).await {
-            return error(StatusCode::BAD_REQUEST, &message, None);
-        }
-
+        // 余额在入账事务中扣除，失败时整单回滚
         let insert = create_purchase_record(
             &state,
             user_id,
             package.id,
-            price,
-            original_price,
-            discount,
+            &price,
+            &original_price,
+            &discount,
             "balance",
             &trade_no,
             coupon_id,
@@ -452,15 +452,19 @@ async fn post_purchase(
         )
         .await;
         if let Err(message) = insert {
-            let _ = refund_user_balance(&state, user_id, price).await;
             return error(StatusCode::INTERNAL_SERVER_ERROR, &message, None);
         }
 
         let applied = match mark_purchase_paid(&state, &trade_no).await {
             Ok(value) => value.map(|value| value.applied).unwrap_or(false),
             Err(message) => {
-                let _ = refund_user_balance(&state, user_id, price).await;
-                return error(StatusCode::INTERNAL_SERVER_ERROR, &message, None);
+                let _ = sqlx::query(
+                    "UPDATE package_purchase_records SET status = 3 WHERE trade_no = ? AND status = 0",
+                )
+                .bind(&trade_no)
+                .execute(&state.db)
+                .await;
+                return error(StatusCode::BAD_REQUEST, &message, None);
             }
         };
 
@@ -486,7 +490,7 @@ async fn post_purchase(
         None => return error(StatusCode::BAD_REQUEST, "支付未配置，请联系管理员", None),
     };
 
-    if payment::active_channels(&state.env).is_empty() {
+    if payment::active_channels(&settings).is_empty() {
         return error(StatusCode::BAD_REQUEST, "支付未配置，请联系管理员", None);
     }
 
@@ -494,9 +498,9 @@ async fn post_purchase(
         &state,
         user_id,
         package.id,
-        payment_amount,
-        original_price,
-        discount,
+        &payment_amount,
+        &original_price,
+        &discount,
         &stored_purchase_type,
         &trade_no,
         coupon_id,
@@ -507,23 +511,23 @@ async fn post_purchase(
         return error(StatusCode::INTERNAL_SERVER_ERROR, &message, None);
     }
 
-    let notify_url = state
-        .env
-        .epay_notify_url
-        .clone()
-        .or(state.env.epusdt_notify_url.clone())
-        .unwrap_or_default();
+    let provider =
+        payment::get_channel_provider_type(&settings, channel_to_use).unwrap_or_default();
+    let notify_url = settings
+        .get(&format!("{provider}_notify_url"))
+        .unwrap_or_default()
+        .to_string();
     let return_url = resolve_return_url(&headers, &state.env, body.return_url.as_deref());
 
     let order = PaymentOrder {
         trade_no: trade_no.clone(),
-        amount: payment_amount,
+        amount: payment_amount.clone(),
         subject: package.name.clone(),
         notify_url,
         return_url: return_url.unwrap_or_default(),
-        clientip: get_client_ip(&headers),
+        clientip: client_ip(&headers),
     };
-    let pay = match payment::create_payment(&state.env, &order, Some(channel_to_use)).await {
+    let pay = match payment::create_payment(&settings, &order, Some(channel_to_use)).await {
         Ok(value) => value,
         Err(message) => return error(StatusCode::INTERNAL_SERVER_ERROR, &message, None),
     };
@@ -543,7 +547,7 @@ async fn post_purchase(
         "待支付"
     };
     let message = if is_mixed {
-        format!("需要补差额 ¥{:.2}，正在跳转到支付页面", payment_amount)
+        format!("需要补差额 ¥{payment_amount}，正在跳转到支付页面")
     } else {
         "购买订单创建成功，请完成支付".to_string()
     };
@@ -570,25 +574,6 @@ async fn post_purchase(
     .into_response()
 }
 
-fn get_client_ip(headers: &axum::http::HeaderMap) -> Option<String> {
-    let candidates = [
-        "x-client-ip",
-        "x-forwarded-for",
-        "cf-connecting-ip",
-        "true-client-ip",
-        "x-real-ip",
-    ];
-    for key in candidates {
-        if let Some(raw) = headers.get(key).and_then(|value| value.to_str().ok()) {
-            let first = raw.split(',').next().unwrap_or("").trim();
-            if !first.is_empty() {
-                return Some(first.to_string());
-            }
-        }
-    }
-    None
-}
-
 #[derive(Deserialize)]
 struct PurchaseRecordsQuery {
     page: Option<i64>,
@@ -619,9 +604,9 @@ async fn get_purchase_records(
         r#"
     SELECT
       ppr.id,
-      CAST(ppr.price AS DOUBLE) as price,
-      CAST(ppr.package_price AS DOUBLE) as package_price,
-      CAST(ppr.discount_amount AS DOUBLE) as discount_amount,
+      ppr.price,
+      ppr.package_price,
+      ppr.discount_amount,
       ppr.coupon_code,
       ppr.purchase_type,
       ppr.trade_no,
@@ -670,6 +655,8 @@ async fn get_purchase_records(
         1 => "已支付",
         2 => "已取消",
         3 => "支付失败",
+        4 => "已过期",
+        5 => "已退款",
         _ => "未知状态",
     };
     let purchase_type_text = |value: &str| {
@@ -699,14 +686,10 @@ async fn get_purchase_records(
     let records: Vec<Value> = rows
     .into_iter()
     .map(|row| {
-      let price = parse_decimal(&row, "price", 0.0);
-      let package_price = if row.try_get::<Option<f64>, _>("package_price").ok().flatten().is_some() {
-        parse_decimal(&row, "package_price", price)
-      } else {
-        price
-      };
-      let discount = parse_decimal(&row, "discount_amount", 0.0);
-      let final_price = (package_price - discount).max(0.0);
+      let price = Money::from_row_or_zero(&row, "price");
+      let package_price = Money::from_row(&row, "package_price").unwrap_or_else(|| price.clone());
+      let discount = Money::from_row_or_zero(&row, "discount_amount");
+      let final_price = (package_price.clone() - discount.clone()).non_negative();
       let purchase_type = row
         .try_get::<Option<String>, _>("purchase_type")
         .ok()
@@ -787,17 +770,17 @@ async fn get_package_by_id(
     package_id: i64,
 ) -> Result<Option<PackageSummary>, String> {
     let row = sqlx::query(
-    r#"
-    SELECT id, name, CAST(price AS DOUBLE) as price, traffic_quota, validity_days, speed_limit, device_limit,
+        r#"
+    SELECT id, name, price, traffic_quota, validity_days, speed_limit, device_limit,
            level, is_recommended, sort_weight
     FROM packages
     WHERE id = ? AND status = 1
-    "#
-  )
-  .bind(package_id)
-  .fetch_optional(&state.db)
-  .await
-  .map_err(|err| err.to_string())?;
+    "#,
+    )
+    .bind(package_id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|err| err.to_string())?;
 
     let row = match row {
         Some(value) => value,
@@ -811,7 +794,7 @@ async fn get_package_by_id(
             .ok()
             .flatten()
             .unwrap_or_default(),
-        price: parse_decimal(&row, "price", 0.0),
+        price: Money::from_row_or_zero(&row, "price"),
     }))
 }
 
@@ -851,7 +834,7 @@ async fn get_coupon_by_code(state: &AppState, code: &str) -> Result<Option<Coupo
             .ok()
             .flatten()
             .unwrap_or_else(|| "amount".to_string()),
-        discount_value: parse_decimal(&row, "discount_value", 0.0),
+        discount_value: Money::from_row_or_zero(&row, "discount_value"),
         start_at: row
             .try_get::<Option<i64>, _>("start_at")
             .unwrap_or(Some(0))
@@ -906,15 +889,16 @@ async fn count_coupon_usage(
     })
 }
 
-fn compute_coupon_discount(coupon: &CouponRow, price: f64) -> f64 {
-    match coupon.discount_type.as_str() {
-        "percentage" => (price * coupon.discount_value / 100.0).max(0.0),
-        "amount" => coupon.discount_value.max(0.0),
-        _ => 0.0,
-    }
+fn compute_coupon_discount(coupon: &CouponRow, price: &Money) -> Money {
+    let discount = match coupon.discount_type.as_str() {
+        "percentage" => price.percent(&coupon.discount_value),
+        "amount" => coupon.discount_value.clone(),
+        _ => Money::zero(),
+    };
+    discount.non_negative().min(price.clone())
 }
 
-async fn get_user_balance(state: &AppState, user_id: i64) -> Result<f64, String> {
+async fn get_user_balance(state: &AppState, user_id: i64) -> Result<Money, String> {
     let row = sqlx::query("SELECT money FROM users WHERE id = ?")
         .bind(user_id)
         .fetch_optional(&state.db)
@@ -922,9 +906,9 @@ async fn get_user_balance(state: &AppState, user_id: i64) -> Result<f64, String>
         .map_err(|err| err.to_string())?;
     let row = match row {
         Some(value) => value,
-        None => return Ok(0.0),
+        None => return Ok(Money::zero()),
     };
-    Ok(parse_decimal(&row, "money", 0.0))
+    Ok(Money::from_row_or_zero(&row, "money"))
 }
 
 fn build_trade_no() -> String {
@@ -941,9 +925,9 @@ async fn create_purchase_record(
     state: &AppState,
     user_id: i64,
     package_id: i64,
-    price: f64,
-    package_price: f64,
-    discount_amount: f64,
+    price: &Money,
+    package_price: &Money,
+    discount_amount: &Money,
     purchase_type: &str,
     trade_no: &str,
     coupon_id: Option<i64>,
@@ -972,48 +956,6 @@ async fn create_purchase_record(
     Ok(())
 }
 
-async fn deduct_user_balance(state: &AppState, user_id: i64, amount: f64) -> Result<(), String> {
-    if amount <= 0.0 {
-        return Ok(());
-    }
-    let result = sqlx::query(
-        r#"
-    UPDATE users
-    SET money = money - ?, updated_at = CURRENT_TIMESTAMP
-    WHERE id = ? AND money >= ?
-    "#,
-    )
-    .bind(amount)
-    .bind(user_id)
-    .bind(amount)
-    .execute(&state.db)
-    .await
-    .map_err(|err| err.to_string())?;
-    if result.rows_affected() == 0 {
-        return Err("余额不足".to_string());
-    }
-    Ok(())
-}
-
-async fn refund_user_balance(state: &AppState, user_id: i64, amount: f64) -> Result<(), String> {
-    if amount <= 0.0 {
-        return Ok(());
-    }
-    sqlx::query(
-        r#"
-    UPDATE users
-    SET money = money + ?, updated_at = CURRENT_TIMESTAMP
-    WHERE id = ?
-    "#,
-    )
-    .bind(amount)
-    .bind(user_id)
-    .execute(&state.db)
-    .await
-    .map_err(|err| err.to_string())?;
-    Ok(())
-}
-
 fn resolve_return_url(
     headers: &HeaderMap,
     env: &crate::config::AppEnv,
@@ -1083,7 +1025,7 @@ fn parse_base_url(value: &str) -> Option<String> {
 }
 
 fn map_package_row(row: &sqlx::mysql::MySqlRow) -> Value {
-    let price = parse_decimal(row, "price", 0.0);
+    let price = Money::from_row_or_zero(row, "price");
     let traffic_quota = row
         .try_get::<Option<i64>, _>("traffic_quota")
         .unwrap_or(Some(0))
@@ -1136,19 +1078,6 @@ fn bind_params<'a>(mut query: SqlxQuery<'a>, params: &'a [SqlParam]) -> SqlxQuer
     query
 }
 
-fn parse_decimal(row: &sqlx::mysql::MySqlRow, column: &str, fallback: f64) -> f64 {
-    if let Ok(Some(value)) = row.try_get::<Option<f64>, _>(column) {
-        return value;
-    }
-    if let Ok(Some(value)) = row.try_get::<Option<sqlx::types::BigDecimal>, _>(column) {
-        return value.to_string().parse::<f64>().unwrap_or(fallback);
-    }
-    if let Ok(Some(value)) = row.try_get::<Option<String>, _>(column) {
-        return value.parse::<f64>().unwrap_or(fallback);
-    }
-    fallback
-}
-
 fn format_datetime(value: Option<NaiveDateTime>) -> Option<String> {
     value.map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
 }
@@ -12,13 +12,19 @@ use sqlx::Row;
 use urlencoding::encode;
 
 use super::auth::list_system_configs;
+use crate::client_ip::client_ip;
+use crate::proxy_groups::{default_proxy_groups, parse_node_tags};
 use crate::response::error;
 use crate::state::AppState;
 use crate::subscription::{
     generate_clash_config, generate_quantumultx_config, generate_shadowrocket_config,
-    generate_singbox_config, generate_surge_config, generate_v2ray_config,
+    generate_singbox_config, generate_surge_config, generate_v2ray_config, generate_xray_config,
     subscription_expire_timestamp, SubscriptionNode, SubscriptionUser,
 };
+use crate::subscription_filter::{FilterParams, SubscriptionFilter};
+use crate::subscription_templates::load_active_templates;
+use crate::subscription_tokens::{resolve_subscription_token, touch_subscription_token};
+use crate::subscription_ua::{load_fallback_format, load_ua_rules, match_ua_rule};
 
 #[derive(Clone, Copy)]
 enum SubscriptionKind {
@@ -28,9 +34,23 @@ enum SubscriptionKind {
     Singbox,
     Shadowrocket,
     Surge,
+    Xray,
 }
 
 impl SubscriptionKind {
+    fn from_name(value: &str) -> Option<Self> {
+        match value {
+            "v2ray" => Some(SubscriptionKind::V2ray),
+            "clash" => Some(SubscriptionKind::Clash),
+            "quantumultx" => Some(SubscriptionKind::QuantumultX),
+            "singbox" => Some(SubscriptionKind::Singbox),
+            "shadowrocket" => Some(SubscriptionKind::Shadowrocket),
+            "surge" => Some(SubscriptionKind::Surge),
+            "xray" => Some(SubscriptionKind::Xray),
+            _ => None,
+        }
+    }
+
     fn as_str(&self) -> &'static str {
         match self {
             SubscriptionKind::V2ray => "v2ray",
@@ -39,6 +59,7 @@ impl SubscriptionKind {
             SubscriptionKind::Singbox => "singbox",
             SubscriptionKind::Shadowrocket => "shadowrocket",
             SubscriptionKind::Surge => "surge",
+            SubscriptionKind::Xray => "xray",
         }
     }
 
@@ -50,6 +71,7 @@ impl SubscriptionKind {
             SubscriptionKind::Singbox => "application/json",
             SubscriptionKind::Shadowrocket => "text/plain",
             SubscriptionKind::Surge => "text/plain",
+            SubscriptionKind::Xray => "application/json",
         }
     }
 
@@ -61,6 +83,7 @@ impl SubscriptionKind {
             SubscriptionKind::Singbox => "json",
             SubscriptionKind::Shadowrocket => "txt",
             SubscriptionKind::Surge => "conf",
+            SubscriptionKind::Xray => "json",
         }
     }
 }
@@ -68,6 +91,12 @@ impl SubscriptionKind {
 #[derive(Deserialize)]
 struct SubscriptionQuery {
     token: Option<String>,
+    include: Option<String>,
+    exclude: Option<String>,
+    #[serde(rename = "type")]
+    node_type: Option<String>,
+    region: Option<String>,
+    sort: Option<String>,
 }
 
 pub fn router() -> Router<AppState> {
@@ -78,6 +107,8 @@ pub fn router() -> Router<AppState> {
         .route("/singbox", get(get_singbox))
         .route("/shadowrocket", get(get_shadowrocket))
         .route("/surge", get(get_surge))
+        .route("/xray", get(get_xray))
+        .route("/auto", get(get_auto))
 }
 
 async fn get_v2ray(
@@ -128,6 +159,47 @@ async fn get_surge(
     handle_subscription(state, headers, query, SubscriptionKind::Surge).await
 }
 
+async fn get_xray(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<SubscriptionQuery>,
+) -> Response {
+    handle_subscription(state, headers, query, SubscriptionKind::Xray).await
+}
+
+async fn get_auto(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<SubscriptionQuery>,
+) -> Response {
+    let kind = detect_subscription_kind(&state, &headers).await;
+    let mut response = handle_subscription(state, headers, query, kind).await;
+    response.headers_mut().insert(
+        HeaderName::from_static("x-subscription-format"),
+        HeaderValue::from_static(kind.as_str()),
+    );
+    response
+}
+
+/// 按 User-Agent 映射表识别客户端，未命中时使用回退格式
+async fn detect_subscription_kind(state: &AppState, headers: &HeaderMap) -> SubscriptionKind {
+    let user_agent = headers
+        .get("user-agent")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let rules = load_ua_rules(state).await.unwrap_or_else(|err| {
+        tracing::warn!("[subscription] load ua rules failed: {err}");
+        Vec::new()
+    });
+    if let Some(kind) =
+        match_ua_rule(user_agent, &rules).and_then(|rule| SubscriptionKind::from_name(&rule.format))
+    {
+        return kind;
+    }
+    SubscriptionKind::from_name(&load_fallback_format(state).await)
+        .unwrap_or(SubscriptionKind::V2ray)
+}
+
 async fn handle_subscription(
     state: AppState,
     headers: HeaderMap,
@@ -144,13 +216,29 @@ async fn handle_subscription(
         None => return error(StatusCode::BAD_REQUEST, "缺少订阅 token", None),
     };
 
-    let user = match fetch_user_by_token(&state, token).await {
+    let access = match resolve_subscription_token(&state, token).await {
         Ok(Some(value)) => value,
         Ok(None) => return error(StatusCode::UNAUTHORIZED, "订阅 token 无效", None),
         Err(message) => return error(StatusCode::INTERNAL_SERVER_ERROR, &message, None),
     };
-
     let now = Local::now().naive_local();
+    if access.is_expired(now) {
+        return error(StatusCode::UNAUTHORIZED, "订阅 token 已过期", None);
+    }
+    if !access.allows(kind.as_str()) {
+        return error(
+            StatusCode::FORBIDDEN,
+            "该订阅 token 不允许使用此订阅格式",
+            None,
+        );
+    }
+
+    let user = match fetch_subscription_user(&state, access.user_id).await {
+        Ok(Some(value)) => value,
+        Ok(None) => return error(StatusCode::UNAUTHORIZED, "订阅 token 无效", None),
+        Err(message) => return error(StatusCode::INTERNAL_SERVER_ERROR, &message, None),
+    };
+
     if user.expire_time.map(|value| value <= now).unwrap_or(false) {
         return error(StatusCode::FORBIDDEN, "账号已过期", None);
     }
@@ -158,9 +246,32 @@ async fn handle_subscription(
         return error(StatusCode::FORBIDDEN, "流量已用完", None);
     }
 
-    if let Err(message) = insert_subscription_log(&state, user.id, kind.as_str(), &headers).await {
+    let filter = match SubscriptionFilter::parse(FilterParams {
+        include: query.include.as_deref(),
+        exclude: query.exclude.as_deref(),
+        node_type: query.node_type.as_deref(),
+        region: query.region.as_deref(),
+        sort: query.sort.as_deref(),
+    }) {
+        Ok(value) => value,
+        Err(message) => return error(StatusCode::BAD_REQUEST, &message, None),
+    };
+
+    if let Err(message) = insert_subscription_log(
+        &state,
+        user.id,
+        kind.as_str(),
+        access.token_id,
+        filter.summary().as_deref(),
+        &headers,
+    )
+    .await
+    {
         return error(StatusCode::INTERNAL_SERVER_ERROR, &message, None);
     }
+    if let Some(token_id) = access.token_id {
+        touch_subscription_token(&state, token_id, &get_client_ip(&headers)).await;
+    }
 
     let nodes = match fetch_accessible_nodes(&state, user.id).await {
         Ok(value) => value,
@@ -170,13 +281,22 @@ async fn handle_subscription(
         return error(StatusCode::NOT_FOUND, "暂无可用节点", None);
     }
 
+    let templates = load_active_templates(&state).await;
+    let nodes = match &templates.proxy_groups {
+        Some(groups) => filter.apply(nodes, groups),
+        None => filter.apply(nodes, &default_proxy_groups()),
+    };
+    if nodes.is_empty() {
+        return error(StatusCode::NOT_FOUND, "没有符合筛选条件的节点", None);
+    }
     let config = match kind {
         SubscriptionKind::V2ray => generate_v2ray_config(&nodes, &user),
-        SubscriptionKind::Clash => generate_clash_config(&nodes, &user),
+        SubscriptionKind::Clash => generate_clash_config(&nodes, &user, &templates),
         SubscriptionKind::QuantumultX => generate_quantumultx_config(&nodes, &user),
-        SubscriptionKind::Singbox => generate_singbox_config(&nodes, &user),
+        SubscriptionKind::Singbox => generate_singbox_config(&nodes, &user, &templates),
         SubscriptionKind::Shadowrocket => generate_shadowrocket_config(&nodes, &user),
-        SubscriptionKind::Surge => generate_surge_config(&nodes, &user),
+        SubscriptionKind::Surge => generate_surge_config(&nodes, &user, &templates),
+        SubscriptionKind::Xray => generate_xray_config(&nodes, &user, &templates),
     };
 
     let site_url = resolve_site_url(&state).await;
@@ -215,19 +335,19 @@ fn resolve_subscription_expire_time(user: &SubscriptionUser) -> Option<chrono::N
     user.class_expire_time.or(user.expire_time)
 }
 
-async fn fetch_user_by_token(
+async fn fetch_subscription_user(
     state: &AppState,
-    token: &str,
+    user_id: i64,
 ) -> Result<Option<SubscriptionUser>, String> {
     let row = sqlx::query(
         r#"
     SELECT id, uuid, passwd, transfer_enable, transfer_total, upload_traffic, download_traffic,
            class_expire_time, expire_time
     FROM users
-    WHERE token = ? AND status = 1
+    WHERE id = ? AND status = 1
     "#,
     )
-    .bind(token)
+    .bind(user_id)
     .fetch_optional(&state.db)
     .await
     .map_err(|err| err.to_string())?;
@@ -269,7 +389,9 @@ async fn fetch_accessible_nodes(
 ) -> Result<Vec<SubscriptionNode>, String> {
     let rows = sqlx::query(
         r#"
-    SELECT n.id, n.name, n.type, CAST(n.node_config AS CHAR) AS node_config
+    SELECT n.id, n.name, n.type, CAST(n.node_config AS CHAR) AS node_config,
+           CAST(n.tags AS CHAR) AS tags, n.node_class,
+           CAST(n.traffic_multiplier AS DOUBLE) AS traffic_multiplier
     FROM nodes n, users u
     WHERE u.id = ?
       AND u.status = 1
@@ -307,6 +429,22 @@ async fn fetch_accessible_nodes(
                     .flatten()
                     .unwrap_or_default(),
                 node_config: parsed_config,
+                tags: parse_node_tags(
+                    row.try_get::<Option<String>, _>("tags")
+                        .ok()
+                        .flatten()
+                        .as_deref(),
+                ),
+                node_class: row
+                    .try_get::<Option<i64>, _>("node_class")
+                    .ok()
+                    .flatten()
+                    .unwrap_or(0),
+                traffic_multiplier: row
+                    .try_get::<Option<f64>, _>("traffic_multiplier")
+                    .ok()
+                    .flatten()
+                    .unwrap_or(1.0),
             }
         })
         .collect();
@@ -317,6 +455,8 @@ async fn insert_subscription_log(
     state: &AppState,
     user_id: i64,
     subscription_type: &str,
+    token_id: Option<i64>,
+    filters: Option<&str>,
     headers: &HeaderMap,
 ) -> Result<(), String> {
     let request_ip = get_client_ip(headers);
@@ -327,12 +467,14 @@ async fn insert_subscription_log(
         .to_string();
     sqlx::query(
         r#"
-    INSERT INTO subscriptions (user_id, type, request_ip, request_user_agent)
-    VALUES (?, ?, ?, ?)
+    INSERT INTO subscriptions (user_id, token_id, type, filters, request_ip, request_user_agent)
+    VALUES (?, ?, ?, ?, ?, ?)
     "#,
     )
     .bind(user_id)
+    .bind(token_id)
     .bind(subscription_type)
+    .bind(filters)
     .bind(request_ip)
     .bind(user_agent)
     .execute(&state.db)
@@ -362,21 +504,7 @@ fn extract_ip(value: Option<&str>) -> String {
 }
 
 fn get_client_ip(headers: &HeaderMap) -> String {
-    let header_value = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
-    let candidates = [
-        header_value("x-client-ip"),
-        header_value("x-forwarded-for"),
-        header_value("cf-connecting-ip"),
-        header_value("true-client-ip"),
-        header_value("x-real-ip"),
-    ];
-    for candidate in candidates {
-        let ip = extract_ip(candidate);
-        if !ip.is_empty() {
-            return ip;
-        }
-    }
-    String::new()
+    extract_ip(client_ip(headers).as_deref())
 }
 
 async fn resolve_site_name(state: &AppState) -> String {
@@ -12,6 +12,8 @@ use std::collections::{HashMap, HashSet};
 use urlencoding::encode;
 
 use super::auth::list_system_configs;
+use crate::admin_permissions::load_admin_access;
+use crate::client_ip::client_ip;
 use crate::crypto::{
     generate_uuid, hash_password, random_base64, random_numeric_code, random_string, sha256_hex,
     verify_password,
@@ -1800,7 +1802,7 @@ async fn send_register_email_code(
     .execute(&state.db)
     .await;
 
-    let client_ip = get_client_ip(headers).unwrap_or_else(|| "unknown".to_string());
+    let client_ip = client_ip(headers).unwrap_or_else(|| "unknown".to_string());
     let user_agent = headers
         .get("user-agent")
         .and_then(|value| value.to_str().ok())
@@ -2108,20 +2110,13 @@ async fn register_by_email_code(
         &email,
         &username,
         password,
-        get_client_ip(headers),
+        client_ip(headers),
         inviter_id,
     )
     .await?;
 
     if let Some(inviter_id) = inviter_id {
-        save_referral_relation(
-            state,
-            inviter_id,
-            user_id,
-            &invite_code,
-            get_client_ip(headers),
-        )
-        .await;
+        save_referral_relation(state, inviter_id, user_id, &invite_code, client_ip(headers)).await;
         increment_invite_usage(state, inviter_id).await;
     }
 
@@ -2365,25 +2360,6 @@ fn to_int_config(value: Option<&String>, fallback: i64, min: i64, allow_zero: bo
     parsed
 }
 
-fn get_client_ip(headers: &HeaderMap) -> Option<String> {
-    let candidates = [
-        "x-client-ip",
-        "x-forwarded-for",
-        "cf-connecting-ip",
-        "true-client-ip",
-        "x-real-ip",
-    ];
-    for key in candidates {
-        if let Some(raw) = headers.get(key).and_then(|value| value.to_str().ok()) {
-            let first = raw.split(',').next().unwrap_or("").trim();
-            if !first.is_empty() {
-                return Some(first.to_string());
-            }
-        }
-    }
-    None
-}
-
 fn get_verification_attempt_limit(state: &AppState) -> i64 {
     state
         .env
@@ -2744,12 +2720,11 @@ async fn handle_ticket_topic_reply_message(
         ));
     }
 
-    let operator =
-        sqlx::query("SELECT id, is_admin, username FROM users WHERE telegram_id = ? LIMIT 1")
-            .bind(&sender_telegram_id)
-            .fetch_optional(&state.db)
-            .await
-            .map_err(|err| err.to_string())?;
+    let operator = sqlx::query("SELECT id, username FROM users WHERE telegram_id = ? LIMIT 1")
+        .bind(&sender_telegram_id)
+        .fetch_optional(&state.db)
+        .await
+        .map_err(|err| err.to_string())?;
     let operator = match operator {
         Some(value) => value,
         None => {
@@ -2762,16 +2737,18 @@ async fn handle_ticket_topic_reply_message(
         .try_get::<Option<i64>, _>("id")
         .unwrap_or(Some(0))
         .unwrap_or(0);
-    let operator_is_admin = operator
-        .try_get::<Option<i64>, _>("is_admin")
-        .unwrap_or(Some(0))
-        .unwrap_or(0)
-        == 1;
-    if operator_id <= 0 || !operator_is_admin {
+    if operator_id <= 0 {
         return Ok(Some(
             json!({ "ok": true, "skipped": "ticket_topic_sender_not_admin" }),
         ));
     }
+    // 与后台工单接口一致：角色需拥有 tickets 权限才能回复
+    let access = load_admin_access(state, operator_id).await?;
+    if !access.is_some_and(|access| access.allows("tickets")) {
+        return Ok(Some(
+            json!({ "ok": true, "skipped": "ticket_topic_sender_not_permitted" }),
+        ));
+    }
     let operator_name = operator
         .try_get::<Option<String>, _>("username")
         .ok()
@@ -3150,6 +3127,7 @@ fn parse_subscription_type(value: &str) -> Option<&'static str> {
         "singbox" => Some("singbox"),
         "shadowrocket" => Some("shadowrocket"),
         "surge" => Some("surge"),
+        "xray" => Some("xray"),
         _ => None,
     }
 }
@@ -3162,6 +3140,7 @@ fn subscription_label(value: &str) -> &'static str {
         "singbox" => "SingBox",
         "shadowrocket" => "Shadowrocket",
         "surge" => "Surge",
+        "xray" => "Xray",
         _ => "Unknown",
     }
 }
@@ -3261,6 +3240,9 @@ fn build_sublink_keyboard() -> Value {
         [
           { "text": "Shadowrocket", "callback_data": "link:shadowrocket" },
           { "text": "Surge", "callback_data": "link:surge" }
+        ],
+        [
+          { "text": "Xray", "callback_data": "link:xray" }
         ]
       ]
     })
@@ -18,11 +18,15 @@ use crate::shared_ids::{
     format_remote_account_id_for_response_text, parse_remote_account_id_list_text,
 };
 use crate::state::AppState;
+use crate::subscription_tokens::{
+    count_active_tokens, normalize_allowed_formats, parse_allowed_formats, MAX_ACTIVE_TOKENS,
+    MAX_TOKEN_LABEL_LENGTH, SUBSCRIPTION_TOKEN_LENGTH,
+};
 use crate::totp::verify_totp;
 
 use super::auth::{
     decrypt_two_factor_secret, encrypt_two_factor_secret, list_passkeys, list_system_configs,
-    normalize_backup_code, parse_backup_codes, require_user_id,
+    normalize_backup_code, parse_backup_codes, rehash_legacy_password, require_user_id,
 };
 
 const TELEGRAM_BIND_CODE_LEN: usize = 16;
@@ -41,6 +45,14 @@ pub fn router() -> Router<AppState> {
             "/reset-subscription-token",
             post(post_reset_subscription_token),
         )
+        .route(
+            "/subscription-tokens",
+            get(get_subscription_tokens).post(post_subscription_token),
+        )
+        .route(
+            "/subscription-tokens/{id}",
+            delete(delete_subscription_token),
+        )
         .route("/subscription-logs", get(get_subscription_logs))
         .route("/traffic-records", get(get_traffic_records))
         .route("/traffic/trends", get(get_traffic_trends))
@@ -695,12 +707,223 @@ async fn post_reset_subscription_token(
     }
 }
 
+#[derive(Deserialize)]
+struct CreateSubscriptionTokenRequest {
+    label: Option<String>,
+    allowed_formats: Option<Value>,
+    expires_at: Option<String>,
+}
+
+async fn get_subscription_tokens(
+    State(state): State<AppState>,
+    Extension(headers): Extension<axum::http::HeaderMap>,
+) -> Response {
+    let user_id = match require_user_id(&state, &headers, None).await {
+        Ok(value) => value,
+        Err(resp) => return resp,
+    };
+
+    let rows = match sqlx::query(
+        r#"
+    SELECT id, token, label, CAST(allowed_formats AS CHAR) AS allowed_formats, expires_at,
+           last_used_at, last_ip, revoked_at, created_at
+    FROM subscription_tokens
+    WHERE user_id = ?
+    ORDER BY revoked_at IS NOT NULL, id DESC
+    "#,
+    )
+    .bind(user_id)
+    .fetch_all(&state.db)
+    .await
+    {
+        Ok(value) => value,
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    };
+    let now = Local::now().naive_local();
+    let items = rows
+        .into_iter()
+        .map(|row| map_subscription_token_row(row, now))
+        .collect::<Vec<Value>>();
+
+    success(
+        json!({ "data": items, "max_tokens": MAX_ACTIVE_TOKENS }),
+        "Success",
+    )
+    .into_response()
+}
+
+async fn post_subscription_token(
+    State(state): State<AppState>,
+    Extension(headers): Extension<axum::http::HeaderMap>,
+    Json(body): Json<CreateSubscriptionTokenRequest>,
+) -> Response {
+    let user_id = match require_user_id(&state, &headers, None).await {
+        Ok(value) => value,
+        Err(resp) => return resp,
+    };
+
+    let label = body.label.unwrap_or_default().trim().to_string();
+    if label.is_empty() {
+        return error(StatusCode::BAD_REQUEST, "请填写设备名称", None);
+    }
+    if label.chars().count() > MAX_TOKEN_LABEL_LENGTH {
+        return error(
+            StatusCode::BAD_REQUEST,
+            &format!("设备名称不能超过 {MAX_TOKEN_LABEL_LENGTH} 个字符"),
+            None,
+        );
+    }
+    let allowed_formats =
+        match normalize_allowed_formats(body.allowed_formats.as_ref().unwrap_or(&Value::Null)) {
+            Ok(value) => value,
+            Err(message) => return error(StatusCode::BAD_REQUEST, &message, None),
+        };
+    let expires_at = match body
+        .expires_at
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+    {
+        Some(raw) => match parse_token_expiry(raw) {
+            Some(value) if value > Local::now().naive_local() => Some(value),
+            Some(_) => return error(StatusCode::BAD_REQUEST, "到期时间必须晚于当前时间", None),
+            None => return error(StatusCode::BAD_REQUEST, "到期时间格式无效", None),
+        },
+        None => None,
+    };
+
+    match count_active_tokens(&state, user_id).await {
+        Ok(total) if total >= MAX_ACTIVE_TOKENS => {
+            return error(
+                StatusCode::CONFLICT,
+                &format!("最多同时保留 {MAX_ACTIVE_TOKENS} 个订阅 Token，请先撤销不再使用的设备"),
+                None,
+            );
+        }
+        Ok(_) => {}
+        Err(message) => return error(StatusCode::INTERNAL_SERVER_ERROR, &message, None),
+    }
+
+    let token = random_string(SUBSCRIPTION_TOKEN_LENGTH);
+    let allowed_formats_json = if allowed_formats.is_empty() {
+        None
+    } else {
+        serde_json::to_string(&allowed_formats).ok()
+    };
+    let result = sqlx::query(
+        r#"
+    INSERT INTO subscription_tokens (user_id, token, label, allowed_formats, expires_at, created_at)
+    VALUES (?, ?, ?, ?, ?, CURRENT_TIMESTAMP)
+    "#,
+    )
+    .bind(user_id)
+    .bind(&token)
+    .bind(&label)
+    .bind(allowed_formats_json)
+    .bind(expires_at)
+    .execute(&state.db)
+    .await;
+    let id = match result {
+        Ok(value) => value.last_insert_id() as i64,
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    };
+
+    success(
+        json!({
+          "id": id,
+          "token": token,
+          "label": label,
+          "allowed_formats": allowed_formats,
+          "expires_at": format_datetime(expires_at)
+        }),
+        "订阅 Token 已创建",
+    )
+    .into_response()
+}
+
+async fn delete_subscription_token(
+    State(state): State<AppState>,
+    Extension(headers): Extension<axum::http::HeaderMap>,
+    Path(id): Path<i64>,
+) -> Response {
+    let user_id = match require_user_id(&state, &headers, None).await {
+        Ok(value) => value,
+        Err(resp) => return resp,
+    };
+    if id <= 0 {
+        return error(StatusCode::BAD_REQUEST, "ID 无效", None);
+    }
+
+    // 撤销而非删除，保留订阅日志中的 token 归属
+    match sqlx::query(
+        r#"
+    UPDATE subscription_tokens
+    SET revoked_at = CURRENT_TIMESTAMP
+    WHERE id = ? AND user_id = ? AND revoked_at IS NULL
+    "#,
+    )
+    .bind(id)
+    .bind(user_id)
+    .execute(&state.db)
+    .await
+    {
+        Ok(result) if result.rows_affected() == 0 => {
+            error(StatusCode::NOT_FOUND, "订阅 Token 不存在或已撤销", None)
+        }
+        Ok(_) => success(json!({ "id": id }), "订阅 Token 已撤销").into_response(),
+        Err(err) => error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
+    }
+}
+
+fn parse_token_expiry(raw: &str) -> Option<NaiveDateTime> {
+    if let Ok(value) = NaiveDateTime::parse_from_str(raw, "%Y-%m-%d %H:%M:%S") {
+        return Some(value);
+    }
+    if let Ok(value) = chrono::DateTime::parse_from_rfc3339(raw) {
+        return Some(value.with_timezone(&Local).naive_local());
+    }
+    chrono::NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(23, 59, 59))
+}
+
+fn map_subscription_token_row(row: sqlx::mysql::MySqlRow, now: NaiveDateTime) -> Value {
+    let expires_at = row
+        .try_get::<Option<NaiveDateTime>, _>("expires_at")
+        .ok()
+        .flatten();
+    let revoked_at = row
+        .try_get::<Option<NaiveDateTime>, _>("revoked_at")
+        .ok()
+        .flatten();
+    let status = if revoked_at.is_some() {
+        "revoked"
+    } else if expires_at.map(|value| value <= now).unwrap_or(false) {
+        "expired"
+    } else {
+        "active"
+    };
+    json!({
+      "id": row.try_get::<i64, _>("id").unwrap_or(0),
+      "token": row.try_get::<Option<String>, _>("token").ok().flatten().unwrap_or_default(),
+      "label": row.try_get::<Option<String>, _>("label").ok().flatten().unwrap_or_default(),
+      "allowed_formats": parse_allowed_formats(row.try_get::<Option<String>, _>("allowed_formats").ok().flatten().as_deref()),
+      "expires_at": format_datetime(expires_at),
+      "last_used_at": format_datetime(row.try_get::<Option<NaiveDateTime>, _>("last_used_at").ok().flatten()),
+      "last_ip": row.try_get::<Option<String>, _>("last_ip").ok().flatten(),
+      "revoked_at": format_datetime(revoked_at),
+      "created_at": format_datetime(row.try_get::<Option<NaiveDateTime>, _>("created_at").ok().flatten()),
+      "status": status
+    })
+}
+
 #[derive(Deserialize)]
 struct SubscriptionLogsQuery {
     page: Option<i64>,
     limit: Option<i64>,
     #[serde(rename = "type")]
     log_type: Option<String>,
+    token_id: Option<i64>,
 }
 
 async fn get_subscription_logs(
@@ -723,7 +946,7 @@ async fn get_subscription_logs(
     }
     let offset = (page - 1) * limit;
 
-    let mut filters = vec!["user_id = ?"];
+    let mut filters = vec!["s.user_id = ?"];
     let mut params = vec![SqlParam::I64(user_id)];
     if let Some(log_type) = query
         .log_type
@@ -731,17 +954,28 @@ async fn get_subscription_logs(
         .map(|value| value.trim())
         .filter(|value| !value.is_empty())
     {
-        filters.push("type = ?");
+        filters.push("s.type = ?");
         params.push(SqlParam::String(log_type.to_string()));
     }
+    // token_id=0 表示使用旧订阅链接（users.token）的请求
+    match query.token_id {
+        Some(0) => filters.push("s.token_id IS NULL"),
+        Some(token_id) => {
+            filters.push("s.token_id = ?");
+            params.push(SqlParam::I64(token_id));
+        }
+        None => {}
+    }
     let where_clause = format!("WHERE {}", filters.join(" AND "));
 
     let data_sql = format!(
         r#"
-    SELECT id, user_id, type, request_ip, request_time, request_user_agent
-    FROM subscriptions
+    SELECT s.id, s.user_id, s.type, s.filters, s.token_id, t.label AS token_label,
+           s.request_ip, s.request_time, s.request_user_agent
+    FROM subscriptions s
+    LEFT JOIN subscription_tokens t ON t.id = s.token_id
     {where_clause}
-    ORDER BY request_time DESC
+    ORDER BY s.request_time DESC
     LIMIT ? OFFSET ?
     "#
     );
@@ -758,7 +992,7 @@ async fn get_subscription_logs(
         Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
     };
 
-    let total_sql = format!("SELECT COUNT(*) as total FROM subscriptions {where_clause}");
+    let total_sql = format!("SELECT COUNT(*) as total FROM subscriptions s {where_clause}");
     let mut total_query = sqlx::query(&total_sql);
     total_query = bind_params(total_query, &params);
     let total_row = total_query
@@ -781,6 +1015,9 @@ async fn get_subscription_logs(
         "id": row.try_get::<i64, _>("id").unwrap_or(0),
         "user_id": row.try_get::<i64, _>("user_id").unwrap_or(0),
         "type": row.try_get::<String, _>("type").unwrap_or_default(),
+        "filters": row.try_get::<Option<String>, _>("filters").ok().flatten(),
+        "token_id": row.try_get::<Option<i64>, _>("token_id").ok().flatten(),
+        "token_label": row.try_get::<Option<String>, _>("token_label").ok().flatten(),
         "request_ip": row.try_get::<Option<String>, _>("request_ip").ok().flatten(),
         "request_time": format_datetime(row.try_get::<Option<NaiveDateTime>, _>("request_time").ok().flatten()),
         "request_user_agent": row.try_get::<Option<String>, _>("request_user_agent").ok().flatten()
@@ -2129,6 +2366,7 @@ async fn post_two_factor_disable(
     if !verify_password(&password, &user.password_hash) {
         return error(StatusCode::UNAUTHORIZED, "密码错误", None);
     }
+    rehash_legacy_password(&state, user_id, &password, &user.password_hash).await;
 
     let verification = match verify_user_two_factor_code(&state, &user, &code).await {
         Ok(value) => value,
@@ -9,12 +9,17 @@ use serde::Deserialize;
 use serde_json::{json, Value};
 use sqlx::Row;
 
-use crate::payment::{active_channels, create_payment, normalize_channel, PaymentOrder};
-use crate::referral::award_rebate;
+use crate::client_ip::client_ip;
+use crate::money::Money;
+use crate::payment::{
+    active_channels, create_payment, get_channel_provider_type, normalize_channel, PaymentOrder,
+    PaymentSettings,
+};
 use crate::response::{error, success};
 use crate::state::AppState;
 
 use super::auth::require_user_id;
+use super::payment_callback::mark_recharge_paid;
 
 pub fn router() -> Router<AppState> {
     Router::new()
@@ -23,8 +28,6 @@ pub fn router() -> Router<AppState> {
         .route("/recharge-records", get(get_recharge_records))
         .route("/stats", get(get_wallet_stats))
         .route("/recharge", post(post_recharge))
-        .route("/recharge/callback", post(post_recharge_callback))
-        .route("/recharge/callback", get(get_recharge_callback))
         .route("/gift-card/redeem", post(post_gift_card_redeem))
 }
 
@@ -55,32 +58,32 @@ async fn get_wallet(
         Some(value) => value,
         None => return error(StatusCode::NOT_FOUND, "用户不存在", None),
     };
-    let balance = parse_decimal(&row, "money", 0.0);
+    let balance = Money::from_row_or_zero(&row, "money");
 
     let recharge_stats = sqlx::query(
-    r#"
+        r#"
     SELECT
-      CAST(COALESCE(SUM(CASE WHEN status = 1 THEN amount ELSE 0 END), 0) AS DOUBLE) as total_recharged
+      COALESCE(SUM(CASE WHEN status = 1 THEN amount ELSE 0 END), 0) as total_recharged
     FROM recharge_records
     WHERE user_id = ?
-    "#
-  )
-  .bind(user_id)
-  .fetch_optional(&state.db)
-  .await;
+    "#,
+    )
+    .bind(user_id)
+    .fetch_optional(&state.db)
+    .await;
     let recharge_stats = match recharge_stats {
         Ok(value) => value,
         Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
     };
     let total_recharged = recharge_stats
         .as_ref()
-        .map(|row| parse_decimal(row, "total_recharged", 0.0))
-        .unwrap_or(0.0);
+        .map(|row| Money::from_row_or_zero(row, "total_recharged"))
+        .unwrap_or_default();
 
     let purchase_stats = sqlx::query(
         r#"
     SELECT
-      CAST(COALESCE(SUM(CASE WHEN status = 1 THEN price ELSE 0 END), 0) AS DOUBLE) as total_spent
+      COALESCE(SUM(CASE WHEN status = 1 THEN price ELSE 0 END), 0) as total_spent
     FROM package_purchase_records
     WHERE user_id = ?
     "#,
@@ -94,8 +97,8 @@ async fn get_wallet(
     };
     let total_consume = purchase_stats
         .as_ref()
-        .map(|row| parse_decimal(row, "total_spent", 0.0))
-        .unwrap_or(0.0);
+        .map(|row| Money::from_row_or_zero(row, "total_spent"))
+        .unwrap_or_default();
 
     success(
         json!({
@@ -186,6 +189,7 @@ async fn get_recharge_records(
         1 => "已支付",
         2 => "已取消",
         3 => "支付失败",
+        4 => "已过期",
         _ => "未知状态",
     };
 
@@ -284,22 +288,22 @@ async fn get_wallet_stats(
     };
     let balance = balance_row
         .as_ref()
-        .map(|row| parse_decimal(row, "money", 0.0))
-        .unwrap_or(0.0);
+        .map(|row| Money::from_row_or_zero(row, "money"))
+        .unwrap_or_default();
 
     let recharge_stats = sqlx::query(
-    r#"
+        r#"
     SELECT
       COUNT(*) as total_recharges,
-      CAST(COALESCE(SUM(CASE WHEN status = 1 THEN amount ELSE 0 END), 0) AS DOUBLE) as total_recharged,
-      CAST(COALESCE(SUM(CASE WHEN status = 0 THEN amount ELSE 0 END), 0) AS DOUBLE) as pending_amount
+      COALESCE(SUM(CASE WHEN status = 1 THEN amount ELSE 0 END), 0) as total_recharged,
+      COALESCE(SUM(CASE WHEN status = 0 THEN amount ELSE 0 END), 0) as pending_amount
     FROM recharge_records
     WHERE user_id = ?
-    "#
-  )
-  .bind(user_id)
-  .fetch_optional(&state.db)
-  .await;
+    "#,
+    )
+    .bind(user_id)
+    .fetch_optional(&state.db)
+    .await;
     let recharge_stats = match recharge_stats {
         Ok(value) => value,
         Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string(), None),
@@ -309,7 +313,7 @@ async fn get_wallet_stats(
         r#"
     SELECT
       COUNT(*) as total_purchases,
-      CAST(COALESCE(SUM(CASE WHEN status = 1 THEN price ELSE 0 END), 0) AS DOUBLE) as total_spent
+      COALESCE(SUM(CASE WHEN status = 1 THEN price ELSE 0 END), 0) as total_spent
     FROM package_purchase_records
     WHERE user_id = ?
     "#,
@@ -325,9 +329,9 @@ async fn get_wallet_stats(
     success(
     json!({
       "current_balance": balance,
-      "total_recharged": recharge_stats.as_ref().map(|row| parse_decimal(row, "total_recharged", 0.0)).unwrap_or(0.0),
-      "total_spent": purchase_stats.as_ref().map(|row| parse_decimal(row, "total_spent", 0.0)).unwrap_or(0.0),
-      "pending_recharge": recharge_stats.as_ref().map(|row| parse_decimal(row, "pending_amount", 0.0)).unwrap_or(0.0),
+      "total_recharged": recharge_stats.as_ref().map(|row| Money::from_row_or_zero(row, "total_recharged")).unwrap_or_default(),
+      "total_spent": purchase_stats.as_ref().map(|row| Money::from_row_or_zero(row, "total_spent")).unwrap_or_default(),
+      "pending_recharge": recharge_stats.as_ref().map(|row| Money::from_row_or_zero(row, "pending_amount")).unwrap_or_default(),
       "total_recharge_count": recharge_stats.as_ref().and_then(|row| row.try_get::<Option<i64>, _>("total_recharges").ok().flatten()).unwrap_or(0),
       "total_purchase_count": purchase_stats.as_ref().and_then(|row| row.try_get::<Option<i64>, _>("total_purchases").ok().flatten()).unwrap_or(0)
     }),
@@ -356,8 +360,8 @@ async fn post_recharge(
         Err(resp) => return resp,
     };
 
-    let amount = body.amount.unwrap_or(0.0);
-    if amount <= 0.0 {
+    let amount = body.amount.and_then(Money::from_f64).unwrap_or_default();
+    if !amount.is_positive() {
         return error(StatusCode::BAD_REQUEST, "金额无效", None);
     }
 
@@ -370,9 +374,10 @@ async fn post_recharge(
                 .as_deref()
                 .filter(|value| !value.trim().is_empty())
         });
+    let settings = PaymentSettings::load(&state).await;
     let preferred_channel = normalize_channel(raw_channel);
     let selected_channel = preferred_channel
-        .or_else(|| active_channels(&state.env).first().copied())
+        .or_else(|| active_channels(&settings).first().copied())
         .ok_or_else(|| "支付方式不可用".to_string());
     let selected_channel = match selected_channel {
         Ok(value) => value,
@@ -381,17 +386,17 @@ async fn post_recharge(
 
     let trade_no = generate_trade_no();
     if let Err(message) =
-        create_recharge_record(&state, user_id, amount, &trade_no, selected_channel).await
+        create_recharge_record(&state, user_id, &amount, &trade_no, selected_channel).await
     {
         return error(StatusCode::INTERNAL_SERVER_ERROR, &message, None);
     }
 
     let client_return_url = body.return_url.unwrap_or_default();
-    let env_return_url = if selected_channel == "crypto" {
-        state.env.epusdt_return_url.clone().unwrap_or_default()
-    } else {
-        state.env.epay_return_url.clone().unwrap_or_default()
-    };
+    let provider = get_channel_provider_type(&settings, selected_channel).unwrap_or_default();
+    let env_return_url = settings
+        .get(&format!("{provider}_return_url"))
+        .unwrap_or_default()
+        .to_string();
     let mut return_url = if !client_return_url.trim().is_empty() {
         client_return_url.trim().to_string()
     } else if !env_return_url.trim().is_empty() {
@@ -403,22 +408,21 @@ async fn post_recharge(
         return_url = build_return_url(&headers, state.env.site_url.clone());
     }
 
-    let notify_url = if selected_channel == "crypto" {
-        state.env.epusdt_notify_url.clone().unwrap_or_default()
-    } else {
-        state.env.epay_notify_url.clone().unwrap_or_default()
-    };
+    let notify_url = settings
+        .get(&format!("{provider}_notify_url"))
+        .unwrap_or_default()
+        .to_string();
 
     let order = PaymentOrder {
         trade_no: trade_no.clone(),
-        amount,
+        amount: amount.clone(),
         subject: "账户充值".to_string(),
         notify_url: notify_url.clone(),
         return_url: return_url.clone(),
-        clientip: get_client_ip(&headers),
+        clientip: client_ip(&headers),
     };
 
-    let result = match create_payment(&state.env, &order, Some(selected_channel)).await {
+    let result = match create_payment(&settings, &order, Some(selected_channel)).await {
         Ok(value) => value,
         Err(message) => return error(StatusCode::INTERNAL_SERVER_ERROR, &message, None),
     };
@@ -451,88 +455,6 @@ async fn post_recharge(
     .into_response()
 }
 
-fn get_client_ip(headers: &axum::http::HeaderMap) -> Option<String> {
-    let candidates = [
-        "x-client-ip",
-        "x-forwarded-for",
-        "cf-connecting-ip",
-        "true-client-ip",
-        "x-real-ip",
-    ];
-    for key in candidates {
-        if let Some(raw) = headers.get(key).and_then(|value| value.to_str().ok()) {
-            let first = raw.split(',').next().unwrap_or("").trim();
-            if !first.is_empty() {
-                return Some(first.to_string());
-            }
-        }
-    }
-    None
-}
-
-#[derive(Deserialize)]
-struct RechargeCallbackRequest {
-    trade_no: Option<String>,
-}
-
-async fn post_recharge_callback(
-    State(state): State<AppState>,
-    Json(body): Json<RechargeCallbackRequest>,
-) -> Response {
-    let trade_no = body.trade_no.unwrap_or_default();
-    if trade_no.trim().is_empty() {
-        return error(StatusCode::BAD_REQUEST, "缺少 trade_no", None);
-    }
-    handle_recharge_callback(&state, &trade_no).await
-}
-
-#[derive(Deserialize)]
-struct RechargeCallbackQuery {
-    trade_no: Option<String>,
-}
-
-async fn get_recharge_callback(
-    State(state): State<AppState>,
-    Query(query): Query<RechargeCallbackQuery>,
-) -> Response {
-    let trade_no = query.trade_no.unwrap_or_default();
-    if trade_no.trim().is_empty() {
-        return error(StatusCode::BAD_REQUEST, "缺少 trade_no", None);
-    }
-    handle_recharge_callback(&state, &trade_no).await
-}
-
-async fn handle_recharge_callback(state: &AppState, trade_no: &str) -> Response {
-    let result = match mark_recharge_paid(state, trade_no).await {
-        Ok(value) => value,
-        Err(message) => return error(StatusCode::INTERNAL_SERVER_ERROR, &message, None),
-    };
-    let result = match result {
-        Some(value) => value,
-        None => return error(StatusCode::NOT_FOUND, "订单不存在", None),
-    };
-
-    if result.applied {
-        let _ = award_rebate(
-            state,
-            result.user_id,
-            result.amount,
-            "recharge",
-            Some(result.record_id),
-            Some(trade_no),
-            Some("recharge_rebate"),
-        )
-        .await;
-        return success(json!({ "trade_no": trade_no }), "已入账").into_response();
-    }
-
-    if result.already_paid {
-        return success(json!({ "trade_no": trade_no }), "订单已是已支付").into_response();
-    }
-
-    error(StatusCode::BAD_REQUEST, "订单状态不可标记", None)
-}
-
 #[derive(Deserialize)]
 struct GiftCardRedeemRequest {
     code: Option<String>,
@@ -599,7 +521,7 @@ async fn post_gift_card_redeem(
     let usage_index = card.used_count + 1;
     let trade_no = build_gift_card_trade_no(&code, usage_index);
 
-    let mut change_amount: Option<f64> = None;
+    let mut change_amount: Option<Money> = None;
     let mut duration_days: Option<i64> = None;
     let mut traffic_value_gb: Option<i64> = None;
     let mut reset_traffic_gb: Option<i64> = None;
@@ -611,12 +533,12 @@ async fn post_gift_card_redeem(
 
     match card.card_type.as_str() {
         "balance" => {
-            let amount = card.balance_amount.unwrap_or(0.0);
-            if amount <= 0.0 {
+            let amount = card.balance_amount.clone().unwrap_or_default();
+            if !amount.is_positive() {
                 return error(StatusCode::BAD_REQUEST, "卡面值无效", None);
             }
             if let Err(message) =
-                create_recharge_record(&state, user_id, amount, &trade_no, "gift_card").await
+                create_recharge_record(&state, user_id, &amount, &trade_no, "gift_card").await
             {
                 return error(StatusCode::INTERNAL_SERVER_ERROR, &message, None);
             }
@@ -629,8 +551,8 @@ async fn post_gift_card_redeem(
                 None => return error(StatusCode::INTERNAL_SERVER_ERROR, "充值记录创建失败", None),
             };
             recharge_record_id = Some(paid_result.record_id);
+            message = format!("成功充值 ¥{}", amount);
             change_amount = Some(amount);
-            message = format!("成功充值 ¥{:.2}", amount);
         }
         "duration" => {
             let days = card.duration_days.unwrap_or(0);
@@ -714,8 +636,8 @@ async fn post_gift_card_redeem(
       )
       .bind(user_id)
       .bind(package.id)
-      .bind(package.price)
-      .bind(package.price)
+      .bind(&package.price)
+      .bind(&package.price)
       .bind(&trade_no)
       .bind(now)
       .bind(now)
@@ -768,7 +690,7 @@ async fn post_gift_card_redeem(
             user_id,
             code: code.clone(),
             card_type: card.card_type.clone(),
-            change_amount,
+            change_amount: change_amount.clone(),
             duration_days,
             traffic_value_gb,
             reset_traffic_gb,
@@ -808,28 +730,15 @@ async fn post_gift_card_redeem(
     .into_response()
 }
 
-fn parse_decimal(row: &sqlx::mysql::MySqlRow, column: &str, fallback: f64) -> f64 {
-    if let Ok(Some(value)) = row.try_get::<Option<f64>, _>(column) {
-        return value;
-    }
-    if let Ok(Some(value)) = row.try_get::<Option<sqlx::types::BigDecimal>, _>(column) {
-        return value.to_string().parse::<f64>().unwrap_or(fallback);
-    }
-    if let Ok(Some(value)) = row.try_get::<Option<String>, _>(column) {
-        return value.parse::<f64>().unwrap_or(fallback);
-    }
-    fallback
-}
-
-async fn get_user_balance(state: &AppState, user_id: i64) -> Result<f64, String> {
+async fn get_user_balance(state: &AppState, user_id: i64) -> Result<Money, String> {
     let row = sqlx::query("SELECT money FROM users WHERE id = ?")
         .bind(user_id)
         .fetch_optional(&state.db)
         .await
         .map_err(|err| err.to_string())?;
     Ok(row
-        .map(|value| parse_decimal(&value, "money", 0.0))
-        .unwrap_or(0.0))
+        .map(|value| Money::from_row_or_zero(&value, "money"))
+        .unwrap_or_default())
 }
 
 async fn list_recharge_records(
@@ -842,7 +751,7 @@ async fn list_recharge_records(
         r#"
     SELECT
       rr.id,
-      CAST(rr.amount AS DOUBLE) as amount,
+      rr.amount,
       rr.payment_method,
       rr.trade_no,
       rr.status,
@@ -868,7 +777,7 @@ async fn list_recharge_records(
     .map(|row| {
       json!({
         "id": row.try_get::<i64, _>("id").unwrap_or(0),
-        "amount": parse_decimal(&row, "amount", 0.0),
+        "amount": Money::from_row_or_zero(&row, "amount"),
         "payment_method": row.try_get::<Option<String>, _>("payment_method").ok().flatten().unwrap_or_default(),
         "trade_no": row.try_get::<Option<String>, _>("trade_no").ok().flatten().unwrap_or_default(),
         "status": row.try_get::<Option<i64>, _>("status").ok().flatten().unwrap_or(0),
@@ -885,7 +794,7 @@ async fn list_recharge_records(
 async fn create_recharge_record(
     state: &AppState,
     user_id: i64,
-    amount: f64,
+    amount: &Money,
     trade_no: &str,
     method: &str,
 ) -> Result<(), String> {
@@ -896,7 +805,7 @@ async fn create_recharge_record(
     "#,
     )
     .bind(user_id)
-    .bind(fix_money_precision(amount))
+    .bind(amount)
     .bind(method)
     .bind(trade_no)
     .execute(&state.db)
@@ -905,96 +814,6 @@ async fn create_recharge_record(
     Ok(())
 }
 
-struct RechargePaidResult {
-    record_id: i64,
-    user_id: i64,
-    amount: f64,
-    applied: bool,
-    already_paid: bool,
-}
-
-async fn mark_recharge_paid(
-    state: &AppState,
-    trade_no: &str,
-) -> Result<Option<RechargePaidResult>, String> {
-    let record = sqlx::query(
-        r#"
-    SELECT id, user_id, amount, status
-    FROM recharge_records
-    WHERE trade_no = ?
-    "#,
-    )
-    .bind(trade_no)
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|err| err.to_string())?;
-    let record = match record {
-        Some(value) => value,
-        None => return Ok(None),
-    };
-
-    let update = sqlx::query(
-        r#"
-    UPDATE recharge_records
-    SET status = 1, paid_at = CURRENT_TIMESTAMP
-    WHERE trade_no = ? AND status = 0
-    "#,
-    )
-    .bind(trade_no)
-    .execute(&state.db)
-    .await
-    .map_err(|err| err.to_string())?;
-
-    if update.rows_affected() == 0 {
-        let latest = sqlx::query("SELECT status FROM recharge_records WHERE trade_no = ?")
-            .bind(trade_no)
-            .fetch_optional(&state.db)
-            .await
-            .map_err(|err| err.to_string())?;
-        let is_paid = latest
-            .as_ref()
-            .and_then(|row| row.try_get::<Option<i64>, _>("status").ok().flatten())
-            .unwrap_or(
-                record
-                    .try_get::<Option<i64>, _>("status")
-                    .ok()
-                    .flatten()
-                    .unwrap_or(0),
-            )
-            == 1;
-        return Ok(Some(RechargePaidResult {
-            record_id: record.try_get::<i64, _>("id").unwrap_or(0),
-            user_id: record.try_get::<i64, _>("user_id").unwrap_or(0),
-            amount: parse_decimal(&record, "amount", 0.0),
-            applied: false,
-            already_paid: is_paid,
-        }));
-    }
-
-    let user_id = record.try_get::<i64, _>("user_id").unwrap_or(0);
-    let amount = parse_decimal(&record, "amount", 0.0);
-    sqlx::query(
-        r#"
-    UPDATE users
-    SET money = money + ?, updated_at = CURRENT_TIMESTAMP
-    WHERE id = ?
-    "#,
-    )
-    .bind(amount)
-    .bind(user_id)
-    .execute(&state.db)
-    .await
-    .map_err(|err| err.to_string())?;
-
-    Ok(Some(RechargePaidResult {
-        record_id: record.try_get::<i64, _>("id").unwrap_or(0),
-        user_id,
-        amount,
-        applied: true,
-        already_paid: false,
-    }))
-}
-
 fn build_return_url(headers: &axum::http::HeaderMap, site_url: Option<String>) -> String {
     let base = detect_base_url(headers, site_url);
     if base.is_empty() {
@@ -1100,10 +919,6 @@ fn random_upper_alnum(len: usize) -> String {
         .collect()
 }
 
-fn fix_money_precision(amount: f64) -> f64 {
-    (amount * 100.0).round() / 100.0
-}
-
 fn format_datetime(value: Option<NaiveDateTime>) -> Option<String> {
     value.map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
 }
@@ -1113,7 +928,7 @@ struct GiftCardRow {
     id: i64,
     card_type: String,
     status: i64,
-    balance_amount: Option<f64>,
+    balance_amount: Option<Money>,
     duration_days: Option<i64>,
     traffic_value_gb: Option<i64>,
     reset_traffic_gb: Option<i64>,
@@ -1130,17 +945,17 @@ async fn get_gift_card_by_code(
     code: &str,
 ) -> Result<Option<GiftCardRow>, String> {
     let row = sqlx::query(
-    r#"
-    SELECT id, card_type, status, CAST(balance_amount AS DOUBLE) as balance_amount, duration_days, traffic_value_gb,
+        r#"
+    SELECT id, card_type, status, balance_amount, duration_days, traffic_value_gb,
            reset_traffic_gb, package_id, max_usage, per_user_limit, used_count, start_at, end_at
     FROM gift_cards
     WHERE code = ? AND status = 1
-    "#
-  )
-  .bind(code)
-  .fetch_optional(&state.db)
-  .await
-  .map_err(|err| err.to_string())?;
+    "#,
+    )
+    .bind(code)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|err| err.to_string())?;
 
     Ok(row.map(|row| GiftCardRow {
         id: row.try_get::<i64, _>("id").unwrap_or(0),
@@ -1154,10 +969,7 @@ async fn get_gift_card_by_code(
             .ok()
             .flatten()
             .unwrap_or(0),
-        balance_amount: row
-            .try_get::<Option<f64>, _>("balance_amount")
-            .ok()
-            .flatten(),
+        balance_amount: Money::from_row(&row, "balance_amount"),
         duration_days: row
             .try_get::<Option<i64>, _>("duration_days")
             .ok()
@@ -1246,7 +1058,7 @@ struct GiftCardRedemption {
     user_id: i64,
     code: String,
     card_type: String,
-    change_amount: Option<f64>,
+    change_amount: Option<Money>,
     duration_days: Option<i64>,
     traffic_value_gb: Option<i64>,
     reset_traffic_gb: Option<i64>,
@@ -1292,7 +1104,7 @@ async fn insert_gift_card_redemption(
 struct PackageRow {
     id: i64,
     name: String,
-    price: f64,
+    price: Money,
     traffic_quota: Option<i64>,
     validity_days: Option<i64>,
     level: i64,
@@ -1324,7 +1136,7 @@ async fn get_package_by_id_any(
             .ok()
             .flatten()
             .unwrap_or_default(),
-        price: parse_decimal(&row, "price", 0.0),
+        price: Money::from_row_or_zero(&row, "price"),
         traffic_quota: row
             .try_get::<Option<i64>, _>("traffic_quota")
             .ok()
@@ -0,0 +1,423 @@
+use chrono::{Datelike, Duration, NaiveDateTime, Timelike, Utc};
+use sqlx::Row;
+use std::collections::HashMap;
+use std::time::Instant;
+
+use crate::cache::{redis_release_lock, redis_try_lock};
+use crate::crypto::generate_uuid;
+use crate::jobs::{run_job, JobKind};
+use crate::state::AppState;
+
+const JOB_LOCK_TTL_SECONDS: u64 = 3600;
+const JOB_RUNNING_STALE_MINUTES: i64 = 60;
+
+/// 启动进程内任务调度器：每分钟按北京时间（UTC+8）匹配各任务的 cron 表达式
+pub fn spawn_scheduler(state: AppState) {
+    let instance_id = generate_uuid();
+    println!("[scheduler] started (instance={instance_id})");
+    tokio::spawn(async move {
+        let mut invalid: HashMap<&'static str, String> = HashMap::new();
+        loop {
+            let now = Utc::now();
+            let wait_ms =
+                60_000 - (now.second() as u64 * 1000 + now.timestamp_subsec_millis() as u64);
+            tokio::time::sleep(std::time::Duration::from_millis(wait_ms.max(1))).await;
+
+            let slot = match beijing_now()
+                .with_second(0)
+                .and_then(|dt| dt.with_nanosecond(0))
+            {
+                Some(value) => value,
+                None => continue,
+            };
+
+            for (job, expr) in load_job_schedules(&state).await {
+                if expr.is_empty() {
+                    continue;
+                }
+                let schedule = match CronSchedule::parse(&expr) {
+                    Ok(value) => value,
+                    Err(message) => {
+                        if invalid.get(job.name()) != Some(&expr) {
+                            tracing::warn!(
+                                "[scheduler] invalid schedule for {}: {expr} ({message})",
+                                job.name()
+                            );
+                            invalid.insert(job.name(), expr);
+                        }
+                        continue;
+                    }
+                };
+                invalid.remove(job.name());
+                if schedule.matches(&slot) {
+                    let state = state.clone();
+                    let instance_id = instance_id.clone();
+                    tokio::spawn(async move {
+                        run_scheduled_job(&state, job, slot, &instance_id).await;
+                    });
+                }
+            }
+        }
+    });
+}
+
+/// 读取各任务的调度表达式；未配置时使用默认值，配置为空字符串表示禁用
+pub async fn load_job_schedules(state: &AppState) -> Vec<(JobKind, String)> {
+    let keys = JobKind::all()
+        .iter()
+        .map(|job| job.schedule_config_key())
+        .collect::<Vec<&str>>();
+    let placeholders = keys.iter().map(|_| "?").collect::<Vec<&str>>().join(",");
+    let sql = format!("SELECT `key`, `value` FROM system_configs WHERE `key` IN ({placeholders})");
+    let mut query = sqlx::query(&sql);
+    for key in &keys {
+        query = query.bind(*key);
+    }
+    let mut configured: HashMap<String, String> = HashMap::new();
+    if let Ok(rows) = query.fetch_all(&state.db).await {
+        for row in rows {
+            let key: String = row.try_get("key").unwrap_or_default();
+            let value: Option<String> = row.try_get("value").ok().flatten();
+            configured.insert(key, value.unwrap_or_default().trim().to_string());
+        }
+    }
+
+    JobKind::all()
+        .into_iter()
+        .map(|job| {
+            let expr = configured
+                .get(job.schedule_config_key())
+                .cloned()
+                .unwrap_or_else(|| job.default_schedule().to_string());
+            (job, expr)
+        })
+        .collect()
+}
+
+/// 手动/命令行触发的任务执行，同样写入 job_runs 记录（记录失败不影响任务本身）
+pub async fn run_tracked_job(state: &AppState, job: JobKind, trigger: &str) -> Result<(), String> {
+    let run_id = insert_job_run(state, job, trigger, None, "")
+        .await
+        .ok()
+        .flatten();
+    let started = Instant::now();
+    let result = run_job(state, job).await;
+    if let Some(id) = run_id {
+        finish_job_run(state, id, started, &result).await;
+    }
+    result
+}
+
+/// 后台启动一次手动任务，返回 job_runs 记录 ID；若任务正在其他实例上运行则拒绝
+pub async fn start_manual_job(state: &AppState, job: JobKind) -> Result<i64, String> {
+    let owner = generate_uuid();
+    let lock_key = job_lock_key(job);
+    if redis_try_lock(state, &lock_key, &owner, JOB_LOCK_TTL_SECONDS).await == Some(false) {
+        return Err("任务正在运行中，请稍后再试".to_string());
+    }
+    if has_running_job(state, job).await {
+        redis_release_lock(state, &lock_key, &owner).await;
+        return Err("任务正在运行中，请稍后再试".to_string());
+    }
+
+    let run_id = match insert_job_run(state, job, "manual", None, &owner).await {
+        Ok(Some(id)) => id,
+        Ok(None) => {
+            redis_release_lock(state, &lock_key, &owner).await;
+            return Err("创建任务记录失败".to_string());
+        }
+        Err(message) => {
+            redis_release_lock(state, &lock_key, &owner).await;
+            return Err(message);
+        }
+    };
+
+    let state = state.clone();
+    tokio::spawn(async move {
+        let started = Instant::now();
+        let result = run_job(&state, job).await;
+        finish_job_run(&state, run_id, started, &result).await;
+        redis_release_lock(&state, &lock_key, &owner).await;
+    });
+
+    Ok(run_id)
+}
+
+async fn run_scheduled_job(state: &AppState, job: JobKind, slot: NaiveDateTime, instance_id: &str) {
+    let lock_key = job_lock_key(job);
+    if redis_try_lock(state, &lock_key, instance_id, JOB_LOCK_TTL_SECONDS).await == Some(false) {
+        println!("[scheduler] {} skipped: locked by another run", job.name());
+        return;
+    }
+    if has_running_job(state, job).await {
+        redis_release_lock(state, &lock_key, instance_id).await;
+        println!(
+            "[scheduler] {} skipped: previous run still in progress",
+            job.name()
+        );
+        return;
+    }
+
+    // job_runs (job_name, scheduled_at) 唯一约束保证同一调度时间点只有一个实例能领取
+    let run_id = match insert_job_run(state, job, "schedule", Some(slot), instance_id).await {
+        Ok(Some(id)) => id,
+        Ok(None) => {
+            redis_release_lock(state, &lock_key, instance_id).await;
+            return;
+        }
+        Err(message) => {
+            tracing::warn!("[scheduler] claim {} failed: {message}", job.name());
+            redis_release_lock(state, &lock_key, instance_id).await;
+            return;
+        }
+    };
+
+    let started = Instant::now();
+    let result = run_job(state, job).await;
+    if let Err(message) = &result {
+        eprintln!("[scheduler] {} failed: {message}", job.name());
+    }
+    finish_job_run(state, run_id, started, &result).await;
+    redis_release_lock(state, &lock_key, instance_id).await;
+}
+
+async fn has_running_job(state: &AppState, job: JobKind) -> bool {
+    let row = sqlx::query(
+        r#"
+    SELECT id FROM job_runs
+    WHERE job_name = ?
+      AND status = 'running'
+      AND started_at >= DATE_SUB(CURRENT_TIMESTAMP, INTERVAL ? MINUTE)
+    LIMIT 1
+    "#,
+    )
+    .bind(job.name())
+    .bind(JOB_RUNNING_STALE_MINUTES)
+    .fetch_optional(&state.db)
+    .await;
+    matches!(row, Ok(Some(_)))
+}
+
+async fn insert_job_run(
+    state: &AppState,
+    job: JobKind,
+    trigger: &str,
+    scheduled_at: Option<NaiveDateTime>,
+    instance_id: &str,
+) -> Result<Option<i64>, String> {
+    let result = sqlx::query(
+        r#"
+    INSERT IGNORE INTO job_runs (job_name, trigger_type, scheduled_at, instance_id, status, started_at)
+    VALUES (?, ?, ?, ?, 'running', CURRENT_TIMESTAMP)
+    "#,
+    )
+    .bind(job.name())
+    .bind(trigger)
+    .bind(scheduled_at.map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string()))
+    .bind(instance_id)
+    .execute(&state.db)
+    .await
+    .map_err(|err| err.to_string())?;
+
+    if result.rows_affected() == 0 {
+        return Ok(None);
+    }
+    Ok(Some(result.last_insert_id() as i64))
+}
+
+async fn finish_job_run(
+    state: &AppState,
+    run_id: i64,
+    started: Instant,
+    result: &Result<(), String>,
+) {
+    let (status, message) = match result {
+        Ok(()) => ("success", None),
+        Err(message) => ("failed", Some(message.clone())),
+    };
+    let _ = sqlx::query(
+        r#"
+    UPDATE job_runs
+    SET status = ?, error_message = ?, finished_at = CURRENT_TIMESTAMP, duration_ms = ?
+    WHERE id = ?
+    "#,
+    )
+    .bind(status)
+    .bind(message)
+    .bind(started.elapsed().as_millis() as i64)
+    .bind(run_id)
+    .execute(&state.db)
+    .await;
+}
+
+fn job_lock_key(job: JobKind) -> String {
+    format!("job_lock_{}", job.name())
+}
+
+fn beijing_now() -> NaiveDateTime {
+    (Utc::now() + Duration::hours(8)).naive_utc()
+}
+
+/// 标准 5 段 cron 表达式：分 时 日 月 周（周日可写 0 或 7）
+pub struct CronSchedule {
+    minutes: Vec<bool>,
+    hours: Vec<bool>,
+    days: Vec<bool>,
+    months: Vec<bool>,
+    weekdays: Vec<bool>,
+    day_restricted: bool,
+    weekday_restricted: bool,
+}
+
+impl CronSchedule {
+    pub fn parse(expr: &str) -> Result<Self, String> {
+        let fields = expr.split_whitespace().collect::<Vec<&str>>();
+        if fields.len() != 5 {
+            return Err("cron 表达式需包含 5 段：分 时 日 月 周".to_string());
+        }
+        let mut weekdays = parse_cron_field(fields[4], 0, 7)?;
+        if weekdays[7] {
+            weekdays[0] = true;
+        }
+        weekdays.truncate(7);
+        Ok(Self {
+            minutes: parse_cron_field(fields[0], 0, 59)?,
+            hours: parse_cron_field(fields[1], 0, 23)?,
+            days: parse_cron_field(fields[2], 1, 31)?,
+            months: parse_cron_field(fields[3], 1, 12)?,
+            weekdays,
+            day_restricted: fields[2] != "*",
+            weekday_restricted: fields[4] != "*",
+        })
+    }
+
+    pub fn matches(&self, dt: &NaiveDateTime) -> bool {
+        self.minutes[dt.minute() as usize]
+            && self.hours[dt.hour() as usize]
+            && self.months[dt.month() as usize]
+            && self.matches_day(dt)
+    }
+
+    /// 查找严格晚于 after 的下一次触发时间（最多向后查找一年）
+    pub fn next_after(&self, after: &NaiveDateTime) -> Option<NaiveDateTime> {
+        let start = after.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let mut day = start.date();
+        for _ in 0..=366 {
+            let candidate_day = day.and_hms_opt(0, 0, 0)?;
+            if self.months[day.month() as usize] && self.matches_day(&candidate_day) {
+                for hour in 0..24u32 {
+                    if !self.hours[hour as usize] {
+                        continue;
+                    }
+                    for minute in 0..60u32 {
+                        if !self.minutes[minute as usize] {
+                            continue;
+                        }
+                        let candidate = day.and_hms_opt(hour, minute, 0)?;
+                        if candidate >= start {
+                            return Some(candidate);
+                        }
+                    }
+                }
+            }
+            day = day.succ_opt()?;
+        }
+        None
+    }
+
+    fn matches_day(&self, dt: &NaiveDateTime) -> bool {
+        let day_match = self.days[dt.day() as usize];
+        let weekday_match = self.weekdays[dt.weekday().num_days_from_sunday() as usize];
+        // 与 crontab 一致：日与周同时限定时任一满足即可
+        match (self.day_restricted, self.weekday_restricted) {
+            (true, true) => day_match || weekday_match,
+            (true, false) => day_match,
+            (false, true) => weekday_match,
+            (false, false) => true,
+        }
+    }
+}
+
+fn parse_cron_field(field: &str, min: u32, max: u32) -> Result<Vec<bool>, String> {
+    let mut allowed = vec![false; max as usize + 1];
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step = step
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|value| *value > 0)
+                    .ok_or_else(|| format!("无效步长: {part}"))?;
+                (range, step)
+            }
+            None => (part, 1),
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((left, right)) = range.split_once('-') {
+            (
+                parse_cron_value(left, min, max)?,
+                parse_cron_value(right, min, max)?,
+            )
+        } else {
+            let value = parse_cron_value(range, min, max)?;
+            if part.contains('/') {
+                (value, max)
+            } else {
+                (value, value)
+            }
+        };
+        if start > end {
+            return Err(format!("无效范围: {part}"));
+        }
+        let mut value = start;
+        while value <= end {
+            allowed[value as usize] = true;
+            value += step;
+        }
+    }
+    Ok(allowed)
+}
+
+fn parse_cron_value(raw: &str, min: u32, max: u32) -> Result<u32, String> {
+    raw.trim()
+        .parse::<u32>()
+        .ok()
+        .filter(|value| *value >= min && *value <= max)
+        .ok_or_else(|| format!("取值超出范围 {min}-{max}: {raw}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(y: i32, m: u32, d: u32, h: u32, min: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(y, m, d)
+            .and_then(|date| date.and_hms_opt(h, min, 0))
+            .expect("valid datetime")
+    }
+
+    #[test]
+    fn cron_fields_support_steps_ranges_and_lists() {
+        let schedule = CronSchedule::parse("*/15 9-18 * * 1-5").expect("parse");
+        assert!(schedule.matches(&at(2026, 10, 16, 9, 45)));
+        assert!(!schedule.matches(&at(2026, 10, 16, 9, 50)));
+        assert!(!schedule.matches(&at(2026, 10, 18, 10, 0)));
+
+        let schedule = CronSchedule::parse("0 3 1,15 * *").expect("parse");
+        assert!(schedule.matches(&at(2026, 10, 15, 3, 0)));
+        assert_eq!(
+            schedule.next_after(&at(2026, 10, 15, 3, 0)),
+            Some(at(2026, 11, 1, 3, 0))
+        );
+    }
+
+    #[test]
+    fn cron_rejects_malformed_expressions() {
+        assert!(CronSchedule::parse("* * * *").is_err());
+        assert!(CronSchedule::parse("60 * * * *").is_err());
+        assert!(CronSchedule::parse("*/0 * * * *").is_err());
+        assert!(CronSchedule::parse("5-1 * * * *").is_err());
+    }
+}
@@ -1,4 +1,5 @@
 use crate::config::AppEnv;
+use crate::node_hub::NodeHub;
 use redis::aio::ConnectionManager;
 use serde::{Deserialize, Serialize};
 use sqlx::MySqlPool;
@@ -14,6 +15,7 @@ pub struct AppState {
     pub redis_status: RedisStatus,
     pub oauth_pending: Arc<RwLock<HashMap<String, PendingOAuthCache>>>,
     pub passkey_challenges: Arc<RwLock<HashMap<String, PasskeyChallengeCache>>>,
+    pub node_hub: NodeHub,
 }
 
 #[derive(Clone)]
@@ -2,11 +2,15 @@ use base64::engine::general_purpose::STANDARD;
 use base64::Engine;
 use chrono::{NaiveDateTime, TimeZone, Utc};
 use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::collections::{HashMap, HashSet};
 use std::sync::OnceLock;
 use urlencoding::encode;
 
+use crate::proxy_groups::{default_proxy_groups, GroupMember, GroupPlan, ProxyGroup};
+use crate::subscription_templates::builtin_template;
+
 #[derive(Clone)]
 pub struct SubscriptionUser {
     pub id: i64,
@@ -20,23 +24,28 @@ pub struct SubscriptionUser {
     pub expire_time: Option<NaiveDateTime>,
 }
 
+/// 后台启用的订阅模板与节点分组；字段为空时使用编译内置的默认值
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct SubscriptionTemplates {
+    pub clash: Option<Value>,
+    pub clash_rules: Option<Vec<Value>>,
+    pub singbox: Option<Value>,
+    pub surge: Option<String>,
+    pub xray: Option<Value>,
+    pub proxy_groups: Option<Vec<ProxyGroup>>,
+}
+
 #[derive(Clone)]
 pub struct SubscriptionNode {
     pub id: i64,
     pub name: String,
     pub node_type: String,
     pub node_config: Value,
+    pub tags: Vec<String>,
+    pub node_class: i64,
+    pub traffic_multiplier: f64,
 }
 
-const REGION_TAGS: [&str; 7] = [
-    "🇭🇰 香港节点",
-    "🇨🇳 台湾节点",
-    "🇸🇬 狮城节点",
-    "🇯🇵 日本节点",
-    "🇺🇲 美国节点",
-    "🇰🇷 韩国节点",
-    "🎥 奈飞节点",
-];
 const VMESS_PROXY_KEYS: [&str; 23] = [
     "name",
     "type",
@@ -179,31 +188,56 @@ static CLASH_RULES: OnceLock<Vec<Value>> = OnceLock::new();
 static CLASH_TEMPLATE: OnceLock<Value> = OnceLock::new();
 static SINGBOX_TEMPLATE: OnceLock<Value> = OnceLock::new();
 static SURGE_TEMPLATE: OnceLock<String> = OnceLock::new();
+static XRAY_TEMPLATE: OnceLock<Value> = OnceLock::new();
 
-fn load_clash_rules() -> &'static Vec<Value> {
-    CLASH_RULES.get_or_init(|| {
-        let raw = include_str!("templates/clashRules.json");
-        serde_json::from_str::<Vec<Value>>(raw).unwrap_or_default()
-    })
+fn load_clash_rules(templates: &SubscriptionTemplates) -> Vec<Value> {
+    if let Some(rules) = &templates.clash_rules {
+        return rules.clone();
+    }
+    CLASH_RULES
+        .get_or_init(|| {
+            let raw = builtin_template("clash_rules").unwrap_or("[]");
+            serde_json::from_str::<Vec<Value>>(raw).unwrap_or_default()
+        })
+        .clone()
 }
 
-fn clone_clash_template() -> Value {
-    let raw = include_str!("templates/clashTemplate.json");
+fn clone_clash_template(templates: &SubscriptionTemplates) -> Value {
+    if let Some(template) = &templates.clash {
+        return template.clone();
+    }
+    let raw = builtin_template("clash").unwrap_or("{}");
     let template = CLASH_TEMPLATE
         .get_or_init(|| serde_json::from_str::<Value>(raw).unwrap_or_else(|_| json!({})));
     template.clone()
 }
 
-fn clone_singbox_template() -> Value {
-    let raw = include_str!("templates/singboxTemplate.json");
+fn clone_singbox_template(templates: &SubscriptionTemplates) -> Value {
+    if let Some(template) = &templates.singbox {
+        return template.clone();
+    }
+    let raw = builtin_template("singbox").unwrap_or("{}");
     let template = SINGBOX_TEMPLATE
         .get_or_init(|| serde_json::from_str::<Value>(raw).unwrap_or_else(|_| json!({})));
     template.clone()
 }
 
-fn clone_surge_template() -> String {
+fn clone_xray_template(templates: &SubscriptionTemplates) -> Value {
+    if let Some(template) = &templates.xray {
+        return template.clone();
+    }
+    let raw = builtin_template("xray").unwrap_or("{}");
+    let template = XRAY_TEMPLATE
+        .get_or_init(|| serde_json::from_str::<Value>(raw).unwrap_or_else(|_| json!({})));
+    template.clone()
+}
+
+fn clone_surge_template(templates: &SubscriptionTemplates) -> String {
+    if let Some(template) = &templates.surge {
+        return template.clone();
+    }
     SURGE_TEMPLATE
-        .get_or_init(|| include_str!("templates/surgeTemplate.conf").to_string())
+        .get_or_init(|| builtin_template("surge").unwrap_or_default().to_string())
         .clone()
 }
 
@@ -582,95 +616,6 @@ fn resolve_first_string(value: Option<&Value>) -> String {
     }
 }
 
-fn normalize_tokens(value: &str) -> Vec<String> {
-    value
-        .to_lowercase()
-        .split(|ch: char| !ch.is_ascii_alphanumeric())
-        .filter(|item| !item.is_empty())
-        .map(|item| item.to_string())
-        .collect()
-}
-
-fn contains_token(tokens: &[String], token: &str) -> bool {
-    tokens.iter().any(|item| item == token)
-}
-
-fn compact_lowercase(value: &str) -> String {
-    value
-        .to_lowercase()
-        .chars()
-        .filter(|ch| !ch.is_whitespace())
-        .collect()
-}
-
-fn contains_any(value: &str, needles: &[&str]) -> bool {
-    needles.iter().any(|needle| value.contains(needle))
-}
-
-fn match_region(tag: &str, name: &str) -> bool {
-    let lower = name.to_lowercase();
-    let compact = compact_lowercase(name);
-    let tokens = normalize_tokens(&lower);
-
-    match tag {
-        "🇭🇰 香港节点" => {
-            contains_any(name, &["香港", "🇭🇰"])
-                || compact.contains("hongkong")
-                || contains_token(&tokens, "hk")
-        }
-        "🇨🇳 台湾节点" => {
-            contains_any(name, &["台湾", "台北", "🇹🇼"])
-                || compact.contains("taiwan")
-                || compact.contains("taipei")
-                || contains_token(&tokens, "tw")
-        }
-        "🇸🇬 狮城节点" => {
-            contains_any(name, &["狮城", "新加坡", "🇸🇬"])
-                || compact.contains("singapore")
-                || contains_token(&tokens, "sg")
-        }
-        "🇯🇵 日本节点" => {
-            contains_any(name, &["日本", "东京", "大阪", "🇯🇵"])
-                || compact.contains("japan")
-                || contains_token(&tokens, "jp")
-        }
-        "🇺🇲 美国节点" => {
-            contains_any(name, &["美国", "洛杉矶", "纽约", "硅谷", "🇺🇸", "🇺🇲"])
-                || compact.contains("unitedstates")
-                || contains_token(&tokens, "usa")
-                || contains_token(&tokens, "us")
-        }
-        "🇰🇷 韩国节点" => {
-            contains_any(name, &["韩国", "首尔", "🇰🇷"])
-                || compact.contains("korea")
-                || contains_token(&tokens, "kr")
-        }
-        "🎥 奈飞节点" => {
-            contains_any(name, &["奈飞"])
-                || compact.contains("netflix")
-                || contains_token(&tokens, "nf")
-        }
-        _ => false,
-    }
-}
-
-fn collect_region_matches(proxy_names: &[String]) -> HashMap<String, Vec<String>> {
-    let mut matches: HashMap<String, Vec<String>> = HashMap::new();
-    for tag in REGION_TAGS.iter() {
-        matches.insert((*tag).to_string(), Vec::new());
-    }
-    for name in proxy_names {
-        for tag in REGION_TAGS.iter() {
-            if match_region(tag, name) {
-                if let Some(values) = matches.get_mut(*tag) {
-                    values.push(name.clone());
-                }
-            }
-        }
-    }
-    matches
-}
-
 fn unique_names(values: &[String]) -> Vec<String> {
     let mut result = Vec::new();
     let mut seen = HashSet::new();
@@ -685,11 +630,24 @@ fn unique_names(values: &[String]) -> Vec<String> {
     result
 }
 
-fn filter_region_tags(values: Vec<String>, available_region_set: &HashSet<String>) -> Vec<String> {
-    values
-        .into_iter()
-        .filter(|item| !REGION_TAGS.contains(&item.as_str()) || available_region_set.contains(item))
-        .collect()
+fn plan_proxy_groups(templates: &SubscriptionTemplates, members: &[GroupMember<'_>]) -> GroupPlan {
+    match &templates.proxy_groups {
+        Some(groups) => GroupPlan::new(groups, members),
+        None => GroupPlan::new(&default_proxy_groups(), members),
+    }
+}
+
+fn group_member<'a>(node: &'a SubscriptionNode, proxy_name: &str) -> GroupMember<'a> {
+    GroupMember {
+        node_id: node.id,
+        node_name: if node.name.is_empty() {
+            proxy_name.to_string()
+        } else {
+            node.name.clone()
+        },
+        tags: &node.tags,
+        proxy_name: proxy_name.to_string(),
+    }
 }
 
 fn with_fallback(values: Vec<String>, fallback: &[&str]) -> Vec<String> {
@@ -1386,9 +1344,14 @@ fn generate_anytls_link(
     )
 }
 
-pub fn generate_clash_config(nodes: &[SubscriptionNode], user: &SubscriptionUser) -> String {
+pub fn generate_clash_config(
+    nodes: &[SubscriptionNode],
+    user: &SubscriptionUser,
+    templates: &SubscriptionTemplates,
+) -> String {
     let mut proxies: Vec<Value> = Vec::new();
     let mut proxy_names: Vec<String> = Vec::new();
+    let mut members: Vec<GroupMember> = Vec::new();
 
     for node in nodes {
         let endpoint = resolve_node_endpoint(node);
@@ -1855,29 +1818,25 @@ pub fn generate_clash_config(nodes: &[SubscriptionNode], user: &SubscriptionUser
 
         if let Some(proxy_value) = proxy {
             proxies.push(Value::Object(proxy_value));
+            members.push(group_member(node, &name));
             proxy_names.push(name);
         }
     }
 
-    let clash = build_clash_template(&proxy_names, proxies);
+    let clash = build_clash_template(&proxy_names, &members, proxies, templates);
     dump_yaml(&clash)
 }
 
-fn build_clash_template(proxy_names: &[String], proxies: Vec<Value>) -> Value {
+fn build_clash_template(
+    proxy_names: &[String],
+    members: &[GroupMember<'_>],
+    proxies: Vec<Value>,
+    templates: &SubscriptionTemplates,
+) -> Value {
     let safe_proxy_names = unique_names(proxy_names);
     let manual_list = with_fallback(safe_proxy_names.clone(), &["DIRECT"]);
-    let region_matches = collect_region_matches(&safe_proxy_names);
-    let available_region_tags: Vec<String> = REGION_TAGS
-        .iter()
-        .filter(|tag| {
-            region_matches
-                .get(&tag.to_string())
-                .map(|v| !v.is_empty())
-                .unwrap_or(false)
-        })
-        .map(|tag| (*tag).to_string())
-        .collect();
-    let available_region_set: HashSet<String> = available_region_tags.iter().cloned().collect();
+    let plan = plan_proxy_groups(templates, members);
+    let available_region_tags = plan.available_names();
 
     let mut groups: Vec<Value> = Vec::new();
     let mut node_select = vec!["🚀 手动切换".to_string()];
@@ -1896,8 +1855,7 @@ fn build_clash_template(proxy_names: &[String], proxies: Vec<Value>) -> Value {
     groups.push(json!({
       "name": "📲 电报消息",
       "type": "select",
-      "proxies": filter_region_tags(
-        vec![
+      "proxies": plan.expand(&[
           "🚀 节点选择",
           "🇸🇬 狮城节点",
           "🇭🇰 香港节点",
@@ -1907,18 +1865,12 @@ fn build_clash_template(proxy_names: &[String], proxies: Vec<Value>) -> Value {
           "🇰🇷 韩国节点",
           "🚀 手动切换",
           "DIRECT"
-        ]
-        .iter()
-        .map(|item| item.to_string())
-        .collect(),
-        &available_region_set
-      )
+        ])
     }));
     groups.push(json!({
       "name": "💬 Ai平台",
       "type": "select",
-      "proxies": filter_region_tags(
-        vec![
+      "proxies": plan.expand(&[
           "🚀 节点选择",
           "🇸🇬 狮城节点",
           "🇭🇰 香港节点",
@@ -1928,18 +1880,12 @@ fn build_clash_template(proxy_names: &[String], proxies: Vec<Value>) -> Value {
           "🇰🇷 韩国节点",
           "🚀 手动切换",
           "DIRECT"
-        ]
-        .iter()
-        .map(|item| item.to_string())
-        .collect(),
-        &available_region_set
-      )
+        ])
     }));
     groups.push(json!({
       "name": "📹 油管视频",
       "type": "select",
-      "proxies": filter_region_tags(
-        vec![
+      "proxies": plan.expand(&[
           "🚀 节点选择",
           "🇸🇬 狮城节点",
           "🇭🇰 香港节点",
@@ -1949,18 +1895,12 @@ fn build_clash_template(proxy_names: &[String], proxies: Vec<Value>) -> Value {
           "🇰🇷 韩国节点",
           "🚀 手动切换",
           "DIRECT"
-        ]
-        .iter()
-        .map(|item| item.to_string())
-        .collect(),
-        &available_region_set
-      )
+        ])
     }));
     groups.push(json!({
       "name": "🎥 奈飞视频",
       "type": "select",
-      "proxies": filter_region_tags(
-        vec![
+      "proxies": plan.expand(&[
           "🎥 奈飞节点",
           "🚀 节点选择",
           "🇸🇬 狮城节点",
@@ -1971,40 +1911,22 @@ fn build_clash_template(proxy_names: &[String], proxies: Vec<Value>) -> Value {
           "🇰🇷 韩国节点",
           "🚀 手动切换",
           "DIRECT"
-        ]
-        .iter()
-        .map(|item| item.to_string())
-        .collect(),
-        &available_region_set
-      )
+        ])
     }));
     groups.push(json!({
       "name": "📺 巴哈姆特",
       "type": "select",
-      "proxies": filter_region_tags(
-        vec!["🇨🇳 台湾节点", "🚀 节点选择", "🚀 手动切换", "DIRECT"]
-          .iter()
-          .map(|item| item.to_string())
-          .collect(),
-        &available_region_set
-      )
+      "proxies": plan.expand(&["🇨🇳 台湾节点", "🚀 节点选择", "🚀 手动切换", "DIRECT"])
     }));
     groups.push(json!({
       "name": "📺 哔哩哔哩",
       "type": "select",
-      "proxies": filter_region_tags(
-        vec!["🎯 全球直连", "🇨🇳 台湾节点", "🇭🇰 香港节点"]
-          .iter()
-          .map(|item| item.to_string())
-          .collect(),
-        &available_region_set
-      )
+      "proxies": plan.expand(&["🎯 全球直连", "🇨🇳 台湾节点", "🇭🇰 香港节点"])
     }));
     groups.push(json!({
       "name": "🌍 国外媒体",
       "type": "select",
-      "proxies": filter_region_tags(
-        vec![
+      "proxies": plan.expand(&[
           "🚀 节点选择",
           "🇭🇰 香港节点",
           "🇨🇳 台湾节点",
@@ -2014,29 +1936,17 @@ fn build_clash_template(proxy_names: &[String], proxies: Vec<Value>) -> Value {
           "🇰🇷 韩国节点",
           "🚀 手动切换",
           "DIRECT"
-        ]
-        .iter()
-        .map(|item| item.to_string())
-        .collect(),
-        &available_region_set
-      )
+        ])
     }));
     groups.push(json!({
       "name": "🌏 国内媒体",
       "type": "select",
-      "proxies": filter_region_tags(
-        vec!["DIRECT", "🇭🇰 香港节点", "🇨🇳 台湾节点", "🇸🇬 狮城节点", "🇯🇵 日本节点", "🚀 手动切换"]
-          .iter()
-          .map(|item| item.to_string())
-          .collect(),
-        &available_region_set
-      )
+      "proxies": plan.expand(&["DIRECT", "🇭🇰 香港节点", "🇨🇳 台湾节点", "🇸🇬 狮城节点", "🇯🇵 日本节点", "🚀 手动切换"])
     }));
     groups.push(json!({
       "name": "📢 谷歌FCM",
       "type": "select",
-      "proxies": filter_region_tags(
-        vec![
+      "proxies": plan.expand(&[
           "DIRECT",
           "🚀 节点选择",
           "🇺🇲 美国节点",
@@ -2046,18 +1956,12 @@ fn build_clash_template(proxy_names: &[String], proxies: Vec<Value>) -> Value {
           "🇯🇵 日本节点",
           "🇰🇷 韩国节点",
           "🚀 手动切换"
-        ]
-        .iter()
-        .map(|item| item.to_string())
-        .collect(),
-        &available_region_set
-      )
+        ])
     }));
     groups.push(json!({
       "name": "Ⓜ️ 微软Bing",
       "type": "select",
-      "proxies": filter_region_tags(
-        vec![
+      "proxies": plan.expand(&[
           "DIRECT",
           "🚀 节点选择",
           "🇺🇲 美国节点",
@@ -2067,18 +1971,12 @@ fn build_clash_template(proxy_names: &[String], proxies: Vec<Value>) -> Value {
           "🇯🇵 日本节点",
           "🇰🇷 韩国节点",
           "🚀 手动切换"
-        ]
-        .iter()
-        .map(|item| item.to_string())
-        .collect(),
-        &available_region_set
-      )
+        ])
     }));
     groups.push(json!({
       "name": "Ⓜ️ 微软云盘",
       "type": "select",
-      "proxies": filter_region_tags(
-        vec![
+      "proxies": plan.expand(&[
           "DIRECT",
           "🚀 节点选择",
           "🇺🇲 美国节点",
@@ -2088,18 +1986,12 @@ fn build_clash_template(proxy_names: &[String], proxies: Vec<Value>) -> Value {
           "🇯🇵 日本节点",
           "🇰🇷 韩国节点",
           "🚀 手动切换"
-        ]
-        .iter()
-        .map(|item| item.to_string())
-        .collect(),
-        &available_region_set
-      )
+        ])
     }));
     groups.push(json!({
       "name": "Ⓜ️ 微软服务",
       "type": "select",
-      "proxies": filter_region_tags(
-        vec![
+      "proxies": plan.expand(&[
           "DIRECT",
           "🚀 节点选择",
           "🇺🇲 美国节点",
@@ -2109,18 +2001,12 @@ fn build_clash_template(proxy_names: &[String], proxies: Vec<Value>) -> Value {
           "🇯🇵 日本节点",
           "🇰🇷 韩国节点",
           "🚀 手动切换"
-        ]
-        .iter()
-        .map(|item| item.to_string())
-        .collect(),
-        &available_region_set
-      )
+        ])
     }));
     groups.push(json!({
       "name": "🍎 苹果服务",
       "type": "select",
-      "proxies": filter_region_tags(
-        vec![
+      "proxies": plan.expand(&[
           "DIRECT",
           "🚀 节点选择",
           "🇺🇲 美国节点",
@@ -2130,18 +2016,12 @@ fn build_clash_template(proxy_names: &[String], proxies: Vec<Value>) -> Value {
           "🇯🇵 日本节点",
           "🇰🇷 韩国节点",
           "🚀 手动切换"
-        ]
-        .iter()
-        .map(|item| item.to_string())
-        .collect(),
-        &available_region_set
-      )
+        ])
     }));
     groups.push(json!({
       "name": "🎮 游戏平台",
       "type": "select",
-      "proxies": filter_region_tags(
-        vec![
+      "proxies": plan.expand(&[
           "DIRECT",
           "🚀 节点选择",
           "🇺🇲 美国节点",
@@ -2151,12 +2031,7 @@ fn build_clash_template(proxy_names: &[String], proxies: Vec<Value>) -> Value {
           "🇯🇵 日本节点",
           "🇰🇷 韩国节点",
           "🚀 手动切换"
-        ]
-        .iter()
-        .map(|item| item.to_string())
-        .collect(),
-        &available_region_set
-      )
+        ])
     }));
     groups.push(json!({
       "name": "🎶 网易音乐",
@@ -2181,8 +2056,7 @@ fn build_clash_template(proxy_names: &[String], proxies: Vec<Value>) -> Value {
     groups.push(json!({
       "name": "🐟 漏网之鱼",
       "type": "select",
-      "proxies": filter_region_tags(
-        vec![
+      "proxies": plan.expand(&[
           "🚀 节点选择",
           "DIRECT",
           "🇭🇰 香港节点",
@@ -2192,29 +2066,15 @@ fn build_clash_template(proxy_names: &[String], proxies: Vec<Value>) -> Value {
           "🇺🇲 美国节点",
           "🇰🇷 韩国节点",
           "🚀 手动切换"
-        ]
-        .iter()
-        .map(|item| item.to_string())
-        .collect(),
-        &available_region_set
-      )
+        ])
     }));
 
-    for tag in available_region_tags {
-        let matched = region_matches.get(&tag).cloned().unwrap_or_default();
-        let matched = unique_names(&matched);
-        if matched.is_empty() {
-            continue;
-        }
-        groups.push(json!({
-          "name": tag,
-          "type": "select",
-          "proxies": matched
-        }));
+    for (group, matched) in plan.available() {
+        groups.push(build_clash_group(group, matched));
     }
 
-    let rules = load_clash_rules().clone();
-    let mut clash = match clone_clash_template() {
+    let rules = load_clash_rules(templates);
+    let mut clash = match clone_clash_template(templates) {
         Value::Object(map) => map,
         _ => serde_json::Map::new(),
     };
@@ -2224,6 +2084,19 @@ fn build_clash_template(proxy_names: &[String], proxies: Vec<Value>) -> Value {
     Value::Object(clash)
 }
 
+fn build_clash_group(group: &ProxyGroup, proxies: &[String]) -> Value {
+    let mut value = json!({
+      "name": group.name,
+      "type": group.group_type,
+      "proxies": unique_names(proxies)
+    });
+    if !group.is_select() {
+        value["url"] = json!(group.test_url);
+        value["interval"] = json!(group.interval);
+    }
+    value
+}
+
 fn resolve_outbound_tag(name: &str, used_tags: &mut HashSet<String>, fallback: &str) -> String {
     let base = if name.trim().is_empty() {
         fallback
@@ -2347,25 +2220,15 @@ fn apply_singbox_transport(
     }
 }
 
-fn collect_singbox_groups(name: &str, tag: &str, groups: &mut HashMap<String, Vec<String>>) {
-    for group in REGION_TAGS.iter() {
-        if match_region(group, name) {
-            groups
-                .entry((*group).to_string())
-                .or_default()
-                .push(tag.to_string());
-        }
-    }
-}
-
-pub fn generate_singbox_config(nodes: &[SubscriptionNode], user: &SubscriptionUser) -> String {
+pub fn generate_singbox_config(
+    nodes: &[SubscriptionNode],
+    user: &SubscriptionUser,
+    templates: &SubscriptionTemplates,
+) -> String {
     let mut node_outbounds: Vec<Value> = Vec::new();
     let mut node_tags: Vec<String> = Vec::new();
     let mut used_tags: HashSet<String> = HashSet::new();
-    let mut group_matches: HashMap<String, Vec<String>> = HashMap::new();
-    for tag in REGION_TAGS.iter() {
-        group_matches.insert((*tag).to_string(), Vec::new());
-    }
+    let mut members: Vec<GroupMember> = Vec::new();
 
     for node in nodes {
         let endpoint = resolve_node_endpoint(node);
@@ -2384,11 +2247,6 @@ pub fn generate_singbox_config(nodes: &[SubscriptionNode], user: &SubscriptionUs
             &mut used_tags,
             &format!("{}-{}", node.node_type, node.id),
         );
-        let match_name = if node.name.is_empty() {
-            tag.clone()
-        } else {
-            node.name.clone()
-        };
         let mut outbound: Option<serde_json::Map<String, Value>> = None;
 
         match node_type.as_str() {
@@ -2543,22 +2401,13 @@ pub fn generate_singbox_config(nodes: &[SubscriptionNode], user: &SubscriptionUs
 
         if let Some(outbound_value) = outbound {
             node_outbounds.push(Value::Object(outbound_value));
+            members.push(group_member(node, &tag));
             node_tags.push(tag.clone());
-            collect_singbox_groups(&match_name, &tag, &mut group_matches);
         }
     }
 
-    let all_region_tags: Vec<String> = REGION_TAGS.iter().map(|tag| (*tag).to_string()).collect();
-    let available_region_tags: Vec<String> = all_region_tags
-        .iter()
-        .filter(|tag| {
-            group_matches
-                .get(*tag)
-                .map(|v| !v.is_empty())
-                .unwrap_or(false)
-        })
-        .cloned()
-        .collect();
+    let plan = plan_proxy_groups(templates, &members);
+    let available_region_tags = plan.available_names();
     let mut group_overrides: HashMap<String, Option<Vec<String>>> = HashMap::new();
     group_overrides.insert(
         "🚀 节点选择".to_string(),
@@ -2578,43 +2427,37 @@ pub fn generate_singbox_config(nodes: &[SubscriptionNode], user: &SubscriptionUs
             list
         }),
     );
-    for tag in available_region_tags.iter() {
-        if let Some(list) = group_matches.get(tag) {
-            if !list.is_empty() {
-                group_overrides.insert(tag.clone(), Some(list.clone()));
-            }
-        }
+    for (group, matched) in plan.available() {
+        group_overrides.insert(group.name.clone(), Some(matched.clone()));
     }
 
-    let singbox = build_singbox_template(
-        node_outbounds,
-        &group_overrides,
-        &all_region_tags,
-        &available_region_tags,
-    );
+    let singbox = build_singbox_template(node_outbounds, &group_overrides, &plan, templates);
     serde_json::to_string_pretty(&singbox).unwrap_or_else(|_| "{}".to_string())
 }
 
 fn build_singbox_template(
     node_outbounds: Vec<Value>,
     group_overrides: &HashMap<String, Option<Vec<String>>>,
-    region_tags: &[String],
-    available_region_tags: &[String],
+    plan: &GroupPlan,
+    templates: &SubscriptionTemplates,
 ) -> Value {
-    let mut template = clone_singbox_template();
+    let mut template = clone_singbox_template(templates);
     let mut base_outbounds: Vec<Value> = Vec::new();
     let mut selector_outbounds: Vec<Value> = Vec::new();
     let mut existing_selector_tags: HashSet<String> = HashSet::new();
 
-    let region_tag_set: HashSet<String> = region_tags.iter().cloned().collect();
-    let available_region_set: HashSet<String> = available_region_tags.iter().cloned().collect();
+    let available_groups: HashMap<&str, &ProxyGroup> = plan
+        .available()
+        .iter()
+        .map(|(group, _)| (group.name.as_str(), group))
+        .collect();
 
     if let Some(outbounds) = template.get_mut("outbounds") {
         if let Value::Array(list) = outbounds {
             for outbound in list.iter() {
                 if let Value::Object(map) = outbound {
                     let outbound_type = ensure_string(map.get("type"));
-                    if outbound_type == "selector" {
+                    if outbound_type == "selector" || outbound_type == "urltest" {
                         selector_outbounds.push(outbound.clone());
                         let tag = ensure_string(map.get("tag"));
                         if !tag.is_empty() {
@@ -2628,7 +2471,8 @@ fn build_singbox_template(
         }
     }
 
-    for tag in available_region_tags.iter() {
+    for (group, _) in plan.available() {
+        let tag = &group.name;
         if existing_selector_tags.contains(tag) {
             continue;
         }
@@ -2654,7 +2498,7 @@ fn build_singbox_template(
             String::new()
         };
 
-        if region_tag_set.contains(&tag) && !available_region_set.contains(&tag) {
+        if plan.is_group_name(&tag) && !available_groups.contains_key(tag.as_str()) {
             continue;
         }
 
@@ -2683,11 +2527,8 @@ fn build_singbox_template(
             }
         }
 
-        if let Some(mut list) = outbounds_list {
-            if !region_tag_set.is_empty() {
-                list = filter_region_tags(list, &available_region_set);
-            }
-            let list = unique_names(&list);
+        if let Some(list) = outbounds_list {
+            let list = unique_names(&plan.expand(&list));
             if let Value::Object(map) = &mut outbound_value {
                 map.insert(
                     "outbounds".to_string(),
@@ -2696,6 +2537,11 @@ fn build_singbox_template(
             }
         }
 
+        if let Some(group) = available_groups.get(tag.as_str()) {
+            if let Value::Object(map) = &mut outbound_value {
+                apply_singbox_group_type(map, group);
+            }
+        }
         filtered_selectors.push(outbound_value);
     }
 
@@ -2709,6 +2555,326 @@ fn build_singbox_template(
     template
 }
 
+/// sing-box 只有 selector 与 urltest，测速类分组（url-test/fallback/load-balance）统一生成 urltest
+fn apply_singbox_group_type(map: &mut serde_json::Map<String, Value>, group: &ProxyGroup) {
+    if group.is_select() {
+        map.insert("type".to_string(), json!("selector"));
+        map.remove("url");
+        map.remove("interval");
+    } else {
+        map.insert("type".to_string(), json!("urltest"));
+        map.insert("url".to_string(), json!(group.test_url));
+        map.insert(
+            "interval".to_string(),
+            json!(format!("{}s", group.interval)),
+        );
+    }
+}
+
+fn build_xray_stream(
+    config: &Value,
+    client: &Value,
+    server: &str,
+    tls_host: &str,
+    mode: &str,
+) -> Value {
+    let stream_type = resolve_config_string_value(config, &["stream_type"], "tcp").to_lowercase();
+    let default_host = if !tls_host.is_empty() {
+        tls_host
+    } else {
+        server
+    };
+    let host = resolve_config_string_value(config, &["server"], default_host);
+    let mut stream = serde_json::Map::new();
+    match stream_type.as_str() {
+        "ws" => {
+            stream.insert("network".to_string(), json!("ws"));
+            stream.insert(
+                "wsSettings".to_string(),
+                json!({ "path": normalize_path(config.get("path")), "host": host }),
+            );
+        }
+        "grpc" => {
+            stream.insert("network".to_string(), json!("grpc"));
+            stream.insert(
+                "grpcSettings".to_string(),
+                json!({ "serviceName": resolve_config_string_value(config, &["service_name"], "grpc") }),
+            );
+        }
+        "xhttp" | "splithttp" => {
+            stream.insert("network".to_string(), json!("xhttp"));
+            stream.insert(
+                "xhttpSettings".to_string(),
+                json!({
+                    "path": normalize_path(config.get("path")),
+                    "host": host,
+                    "mode": resolve_config_string_value(config, &["mode", "xhttp_mode"], "auto")
+                }),
+            );
+        }
+        "httpupgrade" => {
+            stream.insert("network".to_string(), json!("httpupgrade"));
+            stream.insert(
+                "httpupgradeSettings".to_string(),
+                json!({ "path": normalize_path(config.get("path")), "host": host }),
+            );
+        }
+        _ => {
+            stream.insert("network".to_string(), json!("tcp"));
+        }
+    }
+
+    match mode {
+        "tls" => {
+            let mut tls = serde_json::Map::new();
+            tls.insert(
+                "serverName".to_string(),
+                json!(resolve_sni(config, tls_host, server)),
+            );
+            tls.insert(
+                "allowInsecure".to_string(),
+                json!(resolve_skip_cert_verify(config, client, false)),
+            );
+            if let Some(alpn) = normalize_alpn(config.get("alpn")) {
+                tls.insert("alpn".to_string(), json!(alpn));
+            }
+            let fingerprint = resolve_config_string(config, &["fingerprint"]);
+            if !fingerprint.is_empty() {
+                tls.insert("fingerprint".to_string(), json!(fingerprint));
+            }
+            let ech_config = resolve_ech_config(config, client);
+            if !ech_config.is_empty() {
+                tls.insert("echConfigList".to_string(), json!(ech_config));
+            }
+            stream.insert("security".to_string(), json!("tls"));
+            stream.insert("tlsSettings".to_string(), Value::Object(tls));
+        }
+        "reality" => {
+            let server_name = if !tls_host.is_empty() {
+                tls_host.to_string()
+            } else {
+                resolve_first_string(config.get("server_names"))
+            };
+            let server_name = if server_name.is_empty() {
+                server.to_string()
+            } else {
+                server_name
+            };
+            stream.insert("security".to_string(), json!("reality"));
+            stream.insert(
+                "realitySettings".to_string(),
+                json!({
+                    "serverName": server_name,
+                    "fingerprint": resolve_config_string_value(config, &["fingerprint"], "chrome"),
+                    "publicKey": resolve_reality_public_key(config, client),
+                    "shortId": pick_random_short_id(config.get("short_ids")),
+                    "spiderX": ""
+                }),
+            );
+        }
+        _ => {
+            stream.insert("security".to_string(), json!("none"));
+        }
+    }
+    Value::Object(stream)
+}
+
+fn resolve_xray_tls_mode(config: &Value, default_mode: &'static str) -> &'static str {
+    match ensure_string(config.get("tls_type")).as_str() {
+        "reality" => "reality",
+        "tls" => "tls",
+        _ => default_mode,
+    }
+}
+
+pub fn generate_xray_config(
+    nodes: &[SubscriptionNode],
+    user: &SubscriptionUser,
+    templates: &SubscriptionTemplates,
+) -> String {
+    let mut node_outbounds: Vec<Value> = Vec::new();
+    let mut node_tags: Vec<String> = Vec::new();
+    let mut used_tags: HashSet<String> = HashSet::from(["direct".to_string(), "block".to_string()]);
+
+    for node in nodes {
+        let endpoint = resolve_node_endpoint(node);
+        let node_type = node.node_type.to_lowercase();
+        let server = endpoint.server;
+        let port = endpoint.port;
+        let tls_host = endpoint.tls_host;
+        let config = endpoint.config;
+        let client = endpoint.client;
+
+        let outbound = match node_type.as_str() {
+            "v2ray" => Some(json!({
+                "protocol": "vmess",
+                "settings": {
+                    "vnext": [{
+                        "address": server,
+                        "port": port,
+                        "users": [{
+                            "id": user.uuid.clone().unwrap_or_default(),
+                            "alterId": ensure_i64(config.get("aid"), 0),
+                            "security": resolve_config_string_value(&config, &["security"], "auto")
+                        }]
+                    }]
+                },
+                "streamSettings": build_xray_stream(
+                    &config,
+                    &client,
+                    &server,
+                    &tls_host,
+                    resolve_xray_tls_mode(&config, "none"),
+                )
+            })),
+            "vless" => {
+                let mut vless_user = serde_json::Map::new();
+                vless_user.insert(
+                    "id".to_string(),
+                    json!(user.uuid.clone().unwrap_or_default()),
+                );
+                vless_user.insert(
+                    "encryption".to_string(),
+                    json!(resolve_vless_client_encryption(&config, &client)),
+                );
+                let flow = resolve_config_string(&config, &["flow"]);
+                if !flow.is_empty() {
+                    vless_user.insert("flow".to_string(), json!(flow));
+                }
+                Some(json!({
+                    "protocol": "vless",
+                    "settings": {
+                        "vnext": [{ "address": server, "port": port, "users": [vless_user] }]
+                    },
+                    "streamSettings": build_xray_stream(
+                        &config,
+                        &client,
+                        &server,
+                        &tls_host,
+                        resolve_xray_tls_mode(&config, "none"),
+                    )
+                }))
+            }
+            "trojan" => Some(json!({
+                "protocol": "trojan",
+                "settings": {
+                    "servers": [{
+                        "address": server,
+                        "port": port,
+                        "password": resolve_uuid_credential(user)
+                    }]
+                },
+                "streamSettings": build_xray_stream(
+                    &config,
+                    &client,
+                    &server,
+                    &tls_host,
+                    resolve_xray_tls_mode(&config, "tls"),
+                )
+            })),
+            "ss" => {
+                // Xray 不支持 simple-obfs 插件，开启混淆的节点不输出
+                let obfs = resolve_config_string(&config, &["obfs"]);
+                if !obfs.is_empty() && obfs != "plain" {
+                    None
+                } else {
+                    Some(json!({
+                        "protocol": "shadowsocks",
+                        "settings": {
+                            "servers": [{
+                                "address": server,
+                                "port": port,
+                                "method": resolve_config_string_value(&config, &["cipher"], "aes-128-gcm"),
+                                "password": build_ss2022_password(
+                                    &config,
+                                    &user.passwd.clone().unwrap_or_default()
+                                )
+                            }]
+                        }
+                    }))
+                }
+            }
+            "hysteria" => {
+                let mut stream = build_xray_stream(&config, &client, &server, &tls_host, "tls");
+                if let Value::Object(map) = &mut stream {
+                    map.insert("network".to_string(), json!("hysteria"));
+                    map.insert(
+                        "hysteriaSettings".to_string(),
+                        json!({
+                            "version": 2,
+                            "auth": resolve_uuid_credential(user),
+                            "up": format!("{} mbps", ensure_f64(config.get("up_mbps"), 100.0)),
+                            "down": format!("{} mbps", ensure_f64(config.get("down_mbps"), 100.0))
+                        }),
+                    );
+                    map.remove("wsSettings");
+                    map.remove("grpcSettings");
+                    map.remove("xhttpSettings");
+                    map.remove("httpupgradeSettings");
+                }
+                Some(json!({
+                    "protocol": "hysteria",
+                    "settings": { "version": 2, "address": server, "port": port },
+                    "streamSettings": stream
+                }))
+            }
+            _ => None,
+        };
+
+        if let Some(Value::Object(mut outbound)) = outbound {
+            let tag = resolve_outbound_tag(
+                &node.name,
+                &mut used_tags,
+                &format!("{}-{}", node.node_type, node.id),
+            );
+            outbound.insert("tag".to_string(), json!(tag));
+            node_outbounds.push(Value::Object(outbound));
+            node_tags.push(tag);
+        }
+    }
+
+    let xray = build_xray_template(node_outbounds, &node_tags, templates);
+    serde_json::to_string_pretty(&xray).unwrap_or_else(|_| "{}".to_string())
+}
+
+fn build_xray_template(
+    node_outbounds: Vec<Value>,
+    node_tags: &[String],
+    templates: &SubscriptionTemplates,
+) -> Value {
+    let mut template = clone_xray_template(templates);
+    let base_outbounds = template
+        .get("outbounds")
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+
+    // 第一个出站为默认出站，节点放在前面
+    let mut outbounds = node_outbounds;
+    outbounds.extend(base_outbounds);
+    let fallback_tag = node_tags
+        .first()
+        .cloned()
+        .unwrap_or_else(|| "direct".to_string());
+    let balancer = json!({
+        "tag": "proxy",
+        "selector": node_tags,
+        "strategy": { "type": "leastPing" },
+        "fallbackTag": fallback_tag
+    });
+
+    if let Value::Object(map) = &mut template {
+        map.insert("outbounds".to_string(), Value::Array(outbounds));
+        if let Some(Value::Object(routing)) = map.get_mut("routing") {
+            routing.insert("balancers".to_string(), json!([balancer]));
+        }
+        if let Some(Value::Object(observatory)) = map.get_mut("observatory") {
+            observatory.insert("subjectSelector".to_string(), json!(node_tags));
+        }
+    }
+    template
+}
+
 fn push_option(options: &mut Vec<String>, key: &str, value: &Value) {
     if value.is_null() {
         return;
@@ -3019,9 +3185,14 @@ pub fn generate_shadowrocket_config(nodes: &[SubscriptionNode], user: &Subscript
     lines.join("\n")
 }
 
-pub fn generate_surge_config(nodes: &[SubscriptionNode], user: &SubscriptionUser) -> String {
+pub fn generate_surge_config(
+    nodes: &[SubscriptionNode],
+    user: &SubscriptionUser,
+    templates: &SubscriptionTemplates,
+) -> String {
     let mut proxies: Vec<String> = Vec::new();
     let mut proxy_names: Vec<String> = Vec::new();
+    let mut members: Vec<GroupMember> = Vec::new();
     for node in nodes {
         let endpoint = resolve_node_endpoint(node);
         let node_type = node.node_type.to_lowercase();
@@ -3088,27 +3259,23 @@ pub fn generate_surge_config(nodes: &[SubscriptionNode], user: &SubscriptionUser
 
         if !proxy.is_empty() {
             proxies.push(proxy);
+            members.push(group_member(node, &name));
             proxy_names.push(name);
         }
     }
-    build_surge_template(&proxies, &proxy_names)
+    build_surge_template(&proxies, &proxy_names, &members, templates)
 }
 
-fn build_surge_template(proxies: &[String], proxy_names: &[String]) -> String {
+fn build_surge_template(
+    proxies: &[String],
+    proxy_names: &[String],
+    members: &[GroupMember<'_>],
+    templates: &SubscriptionTemplates,
+) -> String {
     let safe_proxy_names = unique_names(&proxy_names.to_vec());
     let manual_list = with_fallback(safe_proxy_names.clone(), &["DIRECT"]);
-    let region_matches = collect_region_matches(&safe_proxy_names);
-    let available_region_tags: Vec<String> = REGION_TAGS
-        .iter()
-        .filter(|tag| {
-            region_matches
-                .get(&tag.to_string())
-                .map(|v| !v.is_empty())
-                .unwrap_or(false)
-        })
-        .map(|tag| (*tag).to_string())
-        .collect();
-    let available_region_set: HashSet<String> = available_region_tags.iter().cloned().collect();
+    let plan = plan_proxy_groups(templates, members);
+    let available_region_tags = plan.available_names();
 
     let mut proxy_lines = vec!["DIRECT = direct".to_string()];
     proxy_lines.extend_from_slice(proxies);
@@ -3127,274 +3294,190 @@ fn build_surge_template(proxies: &[String], proxy_names: &[String]) -> String {
     groups.push(format!("🚀 手动切换 = select,{}", manual_list.join(",")));
     groups.push(format!(
         "📲 电报消息 = select,{}",
-        filter_region_tags(
-            vec![
-                "🚀 节点选择",
-                "🇸🇬 狮城节点",
-                "🇭🇰 香港节点",
-                "🇨🇳 台湾节点",
-                "🇯🇵 日本节点",
-                "🇺🇲 美国节点",
-                "🇰🇷 韩国节点",
-                "🚀 手动切换",
-                "DIRECT"
-            ]
-            .iter()
-            .map(|item| item.to_string())
-            .collect(),
-            &available_region_set
-        )
+        plan.expand(&[
+            "🚀 节点选择",
+            "🇸🇬 狮城节点",
+            "🇭🇰 香港节点",
+            "🇨🇳 台湾节点",
+            "🇯🇵 日本节点",
+            "🇺🇲 美国节点",
+            "🇰🇷 韩国节点",
+            "🚀 手动切换",
+            "DIRECT"
+        ])
         .join(",")
     ));
     groups.push(format!(
         "💬 Ai平台 = select,{}",
-        filter_region_tags(
-            vec![
-                "🚀 节点选择",
-                "🇸🇬 狮城节点",
-                "🇭🇰 香港节点",
-                "🇨🇳 台湾节点",
-                "🇯🇵 日本节点",
-                "🇺🇲 美国节点",
-                "🇰🇷 韩国节点",
-                "🚀 手动切换",
-                "DIRECT"
-            ]
-            .iter()
-            .map(|item| item.to_string())
-            .collect(),
-            &available_region_set
-        )
+        plan.expand(&[
+            "🚀 节点选择",
+            "🇸🇬 狮城节点",
+            "🇭🇰 香港节点",
+            "🇨🇳 台湾节点",
+            "🇯🇵 日本节点",
+            "🇺🇲 美国节点",
+            "🇰🇷 韩国节点",
+            "🚀 手动切换",
+            "DIRECT"
+        ])
         .join(",")
     ));
     groups.push(format!(
         "📹 油管视频 = select,{}",
-        filter_region_tags(
-            vec![
-                "🚀 节点选择",
-                "🇸🇬 狮城节点",
-                "🇭🇰 香港节点",
-                "🇨🇳 台湾节点",
-                "🇯🇵 日本节点",
-                "🇺🇲 美国节点",
-                "🇰🇷 韩国节点",
-                "🚀 手动切换",
-                "DIRECT"
-            ]
-            .iter()
-            .map(|item| item.to_string())
-            .collect(),
-            &available_region_set
-        )
+        plan.expand(&[
+            "🚀 节点选择",
+            "🇸🇬 狮城节点",
+            "🇭🇰 香港节点",
+            "🇨🇳 台湾节点",
+            "🇯🇵 日本节点",
+            "🇺🇲 美国节点",
+            "🇰🇷 韩国节点",
+            "🚀 手动切换",
+            "DIRECT"
+        ])
         .join(",")
     ));
     groups.push(format!(
         "🎥 奈飞视频 = select,{}",
-        filter_region_tags(
-            vec![
-                "🎥 奈飞节点",
-                "🚀 节点选择",
-                "🇸🇬 狮城节点",
-                "🇭🇰 香港节点",
-                "🇨🇳 台湾节点",
-                "🇯🇵 日本节点",
-                "🇺🇲 美国节点",
-                "🇰🇷 韩国节点",
-                "🚀 手动切换",
-                "DIRECT"
-            ]
-            .iter()
-            .map(|item| item.to_string())
-            .collect(),
-            &available_region_set
-        )
+        plan.expand(&[
+            "🎥 奈飞节点",
+            "🚀 节点选择",
+            "🇸🇬 狮城节点",
+            "🇭🇰 香港节点",
+            "🇨🇳 台湾节点",
+            "🇯🇵 日本节点",
+            "🇺🇲 美国节点",
+            "🇰🇷 韩国节点",
+            "🚀 手动切换",
+            "DIRECT"
+        ])
         .join(",")
     ));
     groups.push(format!(
         "📺 巴哈姆特 = select,{}",
-        filter_region_tags(
-            vec!["🇨🇳 台湾节点", "🚀 节点选择", "🚀 手动切换", "DIRECT"]
-                .iter()
-                .map(|item| item.to_string())
-                .collect(),
-            &available_region_set
-        )
-        .join(",")
+        plan.expand(&["🇨🇳 台湾节点", "🚀 节点选择", "🚀 手动切换", "DIRECT"])
+            .join(",")
     ));
     groups.push(format!(
         "📺 哔哩哔哩 = select,{}",
-        filter_region_tags(
-            vec!["🎯 全球直连", "🇨🇳 台湾节点", "🇭🇰 香港节点"]
-                .iter()
-                .map(|item| item.to_string())
-                .collect(),
-            &available_region_set
-        )
-        .join(",")
+        plan.expand(&["🎯 全球直连", "🇨🇳 台湾节点", "🇭🇰 香港节点"])
+            .join(",")
     ));
     groups.push(format!(
         "🌍 国外媒体 = select,{}",
-        filter_region_tags(
-            vec![
-                "🚀 节点选择",
-                "🇭🇰 香港节点",
-                "🇨🇳 台湾节点",
-                "🇸🇬 狮城节点",
-                "🇯🇵 日本节点",
-                "🇺🇲 美国节点",
-                "🇰🇷 韩国节点",
-                "🚀 手动切换",
-                "DIRECT"
-            ]
-            .iter()
-            .map(|item| item.to_string())
-            .collect(),
-            &available_region_set
-        )
+        plan.expand(&[
+            "🚀 节点选择",
+            "🇭🇰 香港节点",
+            "🇨🇳 台湾节点",
+            "🇸🇬 狮城节点",
+            "🇯🇵 日本节点",
+            "🇺🇲 美国节点",
+            "🇰🇷 韩国节点",
+            "🚀 手动切换",
+            "DIRECT"
+        ])
         .join(",")
     ));
     groups.push(format!(
         "🌏 国内媒体 = select,{}",
-        filter_region_tags(
-            vec![
-                "DIRECT",
-                "🇭🇰 香港节点",
-                "🇨🇳 台湾节点",
-                "🇸🇬 狮城节点",
-                "🇯🇵 日本节点",
-                "🚀 手动切换"
-            ]
-            .iter()
-            .map(|item| item.to_string())
-            .collect(),
-            &available_region_set
-        )
+        plan.expand(&[
+            "DIRECT",
+            "🇭🇰 香港节点",
+            "🇨🇳 台湾节点",
+            "🇸🇬 狮城节点",
+            "🇯🇵 日本节点",
+            "🚀 手动切换"
+        ])
         .join(",")
     ));
     groups.push(format!(
         "📢 谷歌FCM = select,{}",
-        filter_region_tags(
-            vec![
-                "DIRECT",
-                "🚀 节点选择",
-                "🇺🇲 美国节点",
-                "🇭🇰 香港节点",
-                "🇨🇳 台湾节点",
-                "🇸🇬 狮城节点",
-                "🇯🇵 日本节点",
-                "🇰🇷 韩国节点",
-                "🚀 手动切换"
-            ]
-            .iter()
-            .map(|item| item.to_string())
-            .collect(),
-            &available_region_set
-        )
+        plan.expand(&[
+            "DIRECT",
+            "🚀 节点选择",
+            "🇺🇲 美国节点",
+            "🇭🇰 香港节点",
+            "🇨🇳 台湾节点",
+            "🇸🇬 狮城节点",
+            "🇯🇵 日本节点",
+            "🇰🇷 韩国节点",
+            "🚀 手动切换"
+        ])
         .join(",")
     ));
     groups.push(format!(
         "Ⓜ️ 微软Bing = select,{}",
-        filter_region_tags(
-            vec![
-                "DIRECT",
-                "🚀 节点选择",
-                "🇺🇲 美国节点",
-                "🇭🇰 香港节点",
-                "🇨🇳 台湾节点",
-                "🇸🇬 狮城节点",
-                "🇯🇵 日本节点",
-                "🇰🇷 韩国节点",
-                "🚀 手动切换"
-            ]
-            .iter()
-            .map(|item| item.to_string())
-            .collect(),
-            &available_region_set
-        )
+        plan.expand(&[
+            "DIRECT",
+            "🚀 节点选择",
+            "🇺🇲 美国节点",
+            "🇭🇰 香港节点",
+            "🇨🇳 台湾节点",
+            "🇸🇬 狮城节点",
+            "🇯🇵 日本节点",
+            "🇰🇷 韩国节点",
+            "🚀 手动切换"
+        ])
         .join(",")
     ));
     groups.push(format!(
         "Ⓜ️ 微软云盘 = select,{}",
-        filter_region_tags(
-            vec![
-                "DIRECT",
-                "🚀 节点选择",
-                "🇺🇲 美国节点",
-                "🇭🇰 香港节点",
-                "🇨🇳 台湾节点",
-                "🇸🇬 狮城节点",
-                "🇯🇵 日本节点",
-                "🇰🇷 韩国节点",
-                "🚀 手动切换"
-            ]
-            .iter()
-            .map(|item| item.to_string())
-            .collect(),
-            &available_region_set
-        )
+        plan.expand(&[
+            "DIRECT",
+            "🚀 节点选择",
+            "🇺🇲 美国节点",
+            "🇭🇰 香港节点",
+            "🇨🇳 台湾节点",
+            "🇸🇬 狮城节点",
+            "🇯🇵 日本节点",
+            "🇰🇷 韩国节点",
+            "🚀 手动切换"
+        ])
         .join(",")
     ));
     groups.push(format!(
         "Ⓜ️ 微软服务 = select,{}",
-        filter_region_tags(
-            vec![
-                "DIRECT",
-                "🚀 节点选择",
-                "🇺🇲 美国节点",
-                "🇭🇰 香港节点",
-                "🇨🇳 台湾节点",
-                "🇸🇬 狮城节点",
-                "🇯🇵 日本节点",
-                "🇰🇷 韩国节点",
-                "🚀 手动切换"
-            ]
-            .iter()
-            .map(|item| item.to_string())
-            .collect(),
-            &available_region_set
-        )
+        plan.expand(&[
+            "DIRECT",
+            "🚀 节点选择",
+            "🇺🇲 美国节点",
+            "🇭🇰 香港节点",
+            "🇨🇳 台湾节点",
+            "🇸🇬 狮城节点",
+            "🇯🇵 日本节点",
+            "🇰🇷 韩国节点",
+            "🚀 手动切换"
+        ])
         .join(",")
     ));
     groups.push(format!(
         "🍎 苹果服务 = select,{}",
-        filter_region_tags(
-            vec![
-                "DIRECT",
-                "🚀 节点选择",
-                "🇺🇲 美国节点",
-                "🇭🇰 香港节点",
-                "🇨🇳 台湾节点",
-                "🇸🇬 狮城节点",
-                "🇯🇵 日本节点",
-                "🇰🇷 韩国节点",
-                "🚀 手动切换"
-            ]
-            .iter()
-            .map(|item| item.to_string())
-            .collect(),
-            &available_region_set
-        )
+        plan.expand(&[
+            "DIRECT",
+            "🚀 节点选择",
+            "🇺🇲 美国节点",
+            "🇭🇰 香港节点",
+            "🇨🇳 台湾节点",
+            "🇸🇬 狮城节点",
+            "🇯🇵 日本节点",
+            "🇰🇷 韩国节点",
+            "🚀 手动切换"
+        ])
         .join(",")
     ));
     groups.push(format!(
         "🎮 游戏平台 = select,{}",
-        filter_region_tags(
-            vec![
-                "DIRECT",
-                "🚀 节点选择",
-                "🇺🇲 美国节点",
-                "🇭🇰 香港节点",
-                "🇨🇳 台湾节点",
-                "🇸🇬 狮城节点",
-                "🇯🇵 日本节点",
-                "🇰🇷 韩国节点",
-                "🚀 手动切换"
-            ]
-            .iter()
-            .map(|item| item.to_string())
-            .collect(),
-            &available_region_set
-        )
+        plan.expand(&[
+            "DIRECT",
+            "🚀 节点选择",
+            "🇺🇲 美国节点",
+            "🇭🇰 香港节点",
+            "🇨🇳 台湾节点",
+            "🇸🇬 狮城节点",
+            "🇯🇵 日本节点",
+            "🇰🇷 韩国节点",
+            "🚀 手动切换"
+        ])
         .join(",")
     ));
     groups.push("🎶 网易音乐 = select,DIRECT,🚀 节点选择".to_string());
@@ -3403,41 +3486,46 @@ fn build_surge_template(proxies: &[String], proxy_names: &[String]) -> String {
     groups.push("🍃 应用净化 = select,REJECT,DIRECT".to_string());
     groups.push(format!(
         "🐟 漏网之鱼 = select,{}",
-        filter_region_tags(
-            vec![
-                "🚀 节点选择",
-                "DIRECT",
-                "🇭🇰 香港节点",
-                "🇨🇳 台湾节点",
-                "🇸🇬 狮城节点",
-                "🇯🇵 日本节点",
-                "🇺🇲 美国节点",
-                "🇰🇷 韩国节点",
-                "🚀 手动切换"
-            ]
-            .iter()
-            .map(|item| item.to_string())
-            .collect(),
-            &available_region_set
-        )
+        plan.expand(&[
+            "🚀 节点选择",
+            "DIRECT",
+            "🇭🇰 香港节点",
+            "🇨🇳 台湾节点",
+            "🇸🇬 狮城节点",
+            "🇯🇵 日本节点",
+            "🇺🇲 美国节点",
+            "🇰🇷 韩国节点",
+            "🚀 手动切换"
+        ])
         .join(",")
     ));
 
-    for tag in available_region_tags {
-        let matched = region_matches.get(&tag).cloned().unwrap_or_default();
-        let matched = unique_names(&matched);
-        if matched.is_empty() {
-            continue;
-        }
-        groups.push(format!("{tag} = select,{}", matched.join(",")));
+    for (group, matched) in plan.available() {
+        groups.push(build_surge_group(group, matched));
     }
 
     let groups_section = groups.join("\n");
-    clone_surge_template()
+    clone_surge_template(templates)
         .replace("{proxy_section}", &proxy_section)
         .replace("{groups}", &groups_section)
 }
 
+fn build_surge_group(group: &ProxyGroup, proxies: &[String]) -> String {
+    let mut line = format!(
+        "{} = {},{}",
+        group.name,
+        group.group_type,
+        unique_names(proxies).join(",")
+    );
+    if !group.is_select() {
+        line.push_str(&format!(
+            ",url={},interval={}",
+            group.test_url, group.interval
+        ));
+    }
+    line
+}
+
 pub fn subscription_expire_timestamp(expire_time: Option<NaiveDateTime>) -> i64 {
     expire_time
         .map(|value| Utc.from_utc_datetime(&value).timestamp())
@@ -0,0 +1,447 @@
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+
+use serde_json::json;
+use sqlx::Row;
+
+use crate::crypto::random_string;
+use crate::message_queue::enqueue_user_notice;
+use crate::state::AppState;
+use crate::subscription_tokens::SUBSCRIPTION_TOKEN_LENGTH;
+
+const DEFAULT_WINDOW_MINUTES: i64 = 24 * 60;
+const DEFAULT_MAX_IPS: i64 = 10;
+const DEFAULT_MAX_NETWORKS: i64 = 5;
+const DEFAULT_MAX_USER_AGENTS: i64 = 5;
+const DEFAULT_COOLDOWN_MINUTES: i64 = 24 * 60;
+/// 报告中保存的 IP / UA 样本上限
+const MAX_SAMPLE_IPS: usize = 50;
+const MAX_SAMPLE_USER_AGENTS: usize = 20;
+
+struct AbuseConfig {
+    enabled: bool,
+    window_minutes: i64,
+    max_ips: i64,
+    max_networks: i64,
+    max_user_agents: i64,
+    auto_reset: bool,
+    notify_enabled: bool,
+    cooldown_minutes: i64,
+}
+
+/// 窗口内某个订阅 token 的访问来源统计
+#[derive(Debug, Default)]
+struct TokenUsage {
+    ips: Vec<String>,
+    networks: HashSet<String>,
+    user_agents: Vec<String>,
+}
+
+impl TokenUsage {
+    fn record(&mut self, ip: &str, user_agent: &str) {
+        if !ip.is_empty() && !self.ips.iter().any(|item| item == ip) {
+            self.networks.insert(network_key(ip));
+            self.ips.push(ip.to_string());
+        }
+        if !user_agent.is_empty() && !self.user_agents.iter().any(|item| item == user_agent) {
+            self.user_agents.push(user_agent.to_string());
+        }
+    }
+
+    /// 超出的阈值名称（ips/networks/user_agents），为空表示正常
+    fn exceeded(&self, config: &AbuseConfig) -> Vec<&'static str> {
+        let mut reasons = Vec::new();
+        if self.ips.len() as i64 > config.max_ips {
+            reasons.push("ips");
+        }
+        if self.networks.len() as i64 > config.max_networks {
+            reasons.push("networks");
+        }
+        if self.user_agents.len() as i64 > config.max_user_agents {
+            reasons.push("user_agents");
+        }
+        reasons
+    }
+}
+
+pub struct SubscriptionAbuseResult {
+    pub checked_tokens: usize,
+    pub flagged_tokens: usize,
+    pub reset_tokens: usize,
+    pub notified_users: usize,
+}
+
+/// 统计窗口内各订阅 token 的来源 IP、网段与 UA 数量，超出阈值的记录报告并按配置重置 token
+pub async fn run_subscription_abuse_check(
+    state: &AppState,
+) -> Result<SubscriptionAbuseResult, String> {
+    let mut result = SubscriptionAbuseResult {
+        checked_tokens: 0,
+        flagged_tokens: 0,
+        reset_tokens: 0,
+        notified_users: 0,
+    };
+    let config = load_config(state).await?;
+    if !config.enabled {
+        return Ok(result);
+    }
+
+    // 网段数不超过 IP 数，先用 IP 与 UA 数筛出候选 token 再逐个统计
+    let candidates = sqlx::query(
+        r#"
+    SELECT s.user_id, s.token_id
+    FROM subscriptions s
+    JOIN users u ON u.id = s.user_id
+    WHERE s.request_time >= DATE_SUB(CURRENT_TIMESTAMP, INTERVAL ? MINUTE)
+      AND u.status = 1
+    GROUP BY s.user_id, s.token_id
+    HAVING COUNT(DISTINCT s.request_ip) > ? OR COUNT(DISTINCT s.request_user_agent) > ?
+    "#,
+    )
+    .bind(config.window_minutes)
+    .bind(config.max_ips.min(config.max_networks))
+    .bind(config.max_user_agents)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|err| err.to_string())?;
+
+    for row in candidates {
+        let user_id = row.try_get::<i64, _>("user_id").unwrap_or(0);
+        let token_id = row.try_get::<Option<i64>, _>("token_id").ok().flatten();
+        if user_id <= 0 {
+            continue;
+        }
+        result.checked_tokens += 1;
+
+        let usage = load_token_usage(state, user_id, token_id, config.window_minutes).await?;
+        let reasons = usage.exceeded(&config);
+        if reasons.is_empty() {
+            continue;
+        }
+        if reported_recently(state, user_id, token_id, config.cooldown_minutes).await? {
+            continue;
+        }
+        result.flagged_tokens += 1;
+
+        let reset = if config.auto_reset {
+            match reset_token(state, user_id, token_id).await {
+                Ok(value) => value,
+                Err(err) => {
+                    tracing::warn!(
+                        "[subscription_abuse] reset token for user {user_id} failed: {err}"
+                    );
+                    false
+                }
+            }
+        } else {
+            false
+        };
+        if reset {
+            result.reset_tokens += 1;
+        }
+
+        let mut notified = false;
+        if config.notify_enabled {
+            let content = build_notice(&usage, config.window_minutes, reset);
+            match enqueue_user_notice(state, user_id, "订阅链接异常提醒", &content).await {
+                Ok(queued) if queued > 0 => {
+                    notified = true;
+                    result.notified_users += 1;
+                }
+                Ok(_) => {}
+                Err(err) => {
+                    tracing::warn!("[subscription_abuse] notify user {user_id} failed: {err}")
+                }
+            }
+        }
+
+        sqlx::query(
+            r#"
+      INSERT INTO subscription_abuse_reports
+        (user_id, token_id, window_minutes, ip_count, network_count, user_agent_count, ips, user_agents, reasons, action, notified, created_at)
+      VALUES
+        (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP)
+      "#,
+        )
+        .bind(user_id)
+        .bind(token_id)
+        .bind(config.window_minutes)
+        .bind(usage.ips.len() as i64)
+        .bind(usage.networks.len() as i64)
+        .bind(usage.user_agents.len() as i64)
+        .bind(json!(usage.ips.iter().take(MAX_SAMPLE_IPS).collect::<Vec<_>>()).to_string())
+        .bind(
+            json!(usage
+                .user_agents
+                .iter()
+                .take(MAX_SAMPLE_USER_AGENTS)
+                .collect::<Vec<_>>())
+            .to_string(),
+        )
+        .bind(reasons.join(","))
+        .bind(if reset { "reset" } else { "record" })
+        .bind(if notified { 1 } else { 0 })
+        .execute(&state.db)
+        .await
+        .map_err(|err| err.to_string())?;
+    }
+
+    Ok(result)
+}
+
+async fn load_config(state: &AppState) -> Result<AbuseConfig, String> {
+    let rows = sqlx::query(
+        r#"
+    SELECT `key`, `value` FROM system_configs
+    WHERE `key` IN (
+      'subscription_abuse_enabled',
+      'subscription_abuse_window_minutes',
+      'subscription_abuse_max_ips',
+      'subscription_abuse_max_networks',
+      'subscription_abuse_max_user_agents',
+      'subscription_abuse_auto_reset',
+      'subscription_abuse_notify_enabled',
+      'subscription_abuse_cooldown_minutes'
+    )
+    "#,
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|err| err.to_string())?;
+
+    let mut map: HashMap<String, String> = HashMap::new();
+    for row in rows {
+        let key = row
+            .try_get::<Option<String>, _>("key")
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+        let value = row
+            .try_get::<Option<String>, _>("value")
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+        map.insert(key, value.trim().to_string());
+    }
+    let read_number = |key: &str, default: i64| {
+        map.get(key)
+            .and_then(|value| value.parse::<i64>().ok())
+            .filter(|value| *value > 0)
+            .unwrap_or(default)
+    };
+    let read_flag = |key: &str, default: bool| {
+        map.get(key)
+            .map(|value| value != "0" && value != "false")
+            .unwrap_or(default)
+    };
+
+    let window_minutes = read_number("subscription_abuse_window_minutes", DEFAULT_WINDOW_MINUTES);
+    Ok(AbuseConfig {
+        enabled: read_flag("subscription_abuse_enabled", true),
+        window_minutes,
+        max_ips: read_number("subscription_abuse_max_ips", DEFAULT_MAX_IPS),
+        max_networks: read_number("subscription_abuse_max_networks", DEFAULT_MAX_NETWORKS),
+        max_user_agents: read_number(
+            "subscription_abuse_max_user_agents",
+            DEFAULT_MAX_USER_AGENTS,
+        ),
+        auto_reset: read_flag("subscription_abuse_auto_reset", false),
+        notify_enabled: read_flag("subscription_abuse_notify_enabled", true),
+        // 冷却时间不短于统计窗口：旧 token 重置后的拉取记录仍归在 token_id = NULL 下，
+        // 窗口内再次检测会把新 token 误判并重复重置
+        cooldown_minutes: read_number(
+            "subscription_abuse_cooldown_minutes",
+            DEFAULT_COOLDOWN_MINUTES,
+        )
+        .max(window_minutes),
+    })
+}
+
+async fn load_token_usage(
+    state: &AppState,
+    user_id: i64,
+    token_id: Option<i64>,
+    window_minutes: i64,
+) -> Result<TokenUsage, String> {
+    let rows = sqlx::query(
+        r#"
+    SELECT request_ip, request_user_agent
+    FROM subscriptions
+    WHERE user_id = ?
+      AND token_id <=> ?
+      AND request_time >= DATE_SUB(CURRENT_TIMESTAMP, INTERVAL ? MINUTE)
+    ORDER BY request_time DESC
+    "#,
+    )
+    .bind(user_id)
+    .bind(token_id)
+    .bind(window_minutes)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|err| err.to_string())?;
+
+    let mut usage = TokenUsage::default();
+    for row in rows {
+        let ip = row
+            .try_get::<Option<String>, _>("request_ip")
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+        let user_agent = row
+            .try_get::<Option<String>, _>("request_user_agent")
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+        usage.record(ip.trim(), user_agent.trim());
+    }
+    Ok(usage)
+}
+
+async fn reported_recently(
+    state: &AppState,
+    user_id: i64,
+    token_id: Option<i64>,
+    cooldown_minutes: i64,
+) -> Result<bool, String> {
+    let row = sqlx::query(
+        r#"
+    SELECT id FROM subscription_abuse_reports
+    WHERE user_id = ?
+      AND token_id <=> ?
+      AND created_at >= DATE_SUB(CURRENT_TIMESTAMP, INTERVAL ? MINUTE)
+    LIMIT 1
+    "#,
+    )
+    .bind(user_id)
+    .bind(token_id)
+    .bind(cooldown_minutes)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|err| err.to_string())?;
+    Ok(row.is_some())
+}
+
+/// 重置被滥用的 token：旧链接直接更换 users.token；多设备 token 撤销后按原设置生成新 token
+async fn reset_token(
+    state: &AppState,
+    user_id: i64,
+    token_id: Option<i64>,
+) -> Result<bool, String> {
+    let new_token = random_string(SUBSCRIPTION_TOKEN_LENGTH);
+    let Some(token_id) = token_id else {
+        let result =
+            sqlx::query("UPDATE users SET token = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+                .bind(&new_token)
+                .bind(user_id)
+                .execute(&state.db)
+                .await
+                .map_err(|err| err.to_string())?;
+        return Ok(result.rows_affected() > 0);
+    };
+
+    let mut tx = state.db.begin().await.map_err(|err| err.to_string())?;
+    let revoked = sqlx::query(
+        r#"
+    UPDATE subscription_tokens
+    SET revoked_at = CURRENT_TIMESTAMP
+    WHERE id = ? AND user_id = ? AND revoked_at IS NULL
+    "#,
+    )
+    .bind(token_id)
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|err| err.to_string())?;
+    if revoked.rows_affected() == 0 {
+        // 用户已自行撤销，无需再生成
+        return Ok(false);
+    }
+    sqlx::query(
+        r#"
+    INSERT INTO subscription_tokens (user_id, token, label, allowed_formats, expires_at, created_at)
+    SELECT user_id, ?, label, allowed_formats, expires_at, CURRENT_TIMESTAMP
+    FROM subscription_tokens
+    WHERE id = ?
+    "#,
+    )
+    .bind(&new_token)
+    .bind(token_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|err| err.to_string())?;
+    tx.commit().await.map_err(|err| err.to_string())?;
+    Ok(true)
+}
+
+/// 无 ASN 数据时按网段近似：IPv4 取 /24，IPv6 取 /48
+fn network_key(ip: &str) -> String {
+    match ip.parse::<IpAddr>() {
+        Ok(IpAddr::V4(addr)) => {
+            let [a, b, c, _] = addr.octets();
+            format!("{a}.{b}.{c}.0/24")
+        }
+        Ok(IpAddr::V6(addr)) => {
+            let segments = addr.segments();
+            format!("{:x}:{:x}:{:x}::/48", segments[0], segments[1], segments[2])
+        }
+        Err(_) => ip.to_string(),
+    }
+}
+
+fn build_notice(usage: &TokenUsage, window_minutes: i64, reset: bool) -> String {
+    let action_text = if reset {
+        "为保护账号安全，系统已自动重置该订阅链接，请登录面板获取新的订阅地址并在自己的设备上重新导入。"
+    } else {
+        "如非本人操作，请尽快在面板中重置或撤销该订阅链接。"
+    };
+    format!(
+        "您的订阅链接在最近 {} 分钟内被 {} 个 IP（{} 个网段）、{} 种客户端拉取，疑似已泄露。\n{}",
+        window_minutes,
+        usage.ips.len(),
+        usage.networks.len(),
+        usage.user_agents.len(),
+        action_text
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> AbuseConfig {
+        AbuseConfig {
+            enabled: true,
+            window_minutes: 60,
+            max_ips: 3,
+            max_networks: 2,
+            max_user_agents: 2,
+            auto_reset: false,
+            notify_enabled: false,
+            cooldown_minutes: 60,
+        }
+    }
+
+    #[test]
+    fn groups_ips_into_networks() {
+        assert_eq!(network_key("203.0.113.45"), "203.0.113.0/24");
+        assert_eq!(network_key("2001:db8:1234:5678::1"), "2001:db8:1234::/48");
+        assert_eq!(network_key("unknown"), "unknown");
+    }
+
+    #[test]
+    fn flags_usage_over_thresholds() {
+        let mut usage = TokenUsage::default();
+        usage.record("203.0.113.1", "clash-verge/1.0");
+        usage.record("203.0.113.2", "clash-verge/1.0");
+        usage.record("203.0.113.3", "");
+        assert!(usage.exceeded(&config()).is_empty());
+
+        usage.record("198.51.100.7", "sing-box 1.9");
+        usage.record("192.0.2.9", "Shadowrocket/2");
+        assert_eq!(
+            usage.exceeded(&config()),
+            vec!["ips", "networks", "user_agents"]
+        );
+        assert_eq!(usage.ips.len(), 5);
+    }
+}
@@ -0,0 +1,308 @@
+use std::cmp::Ordering;
+
+use regex::{Regex, RegexBuilder};
+
+use crate::proxy_groups::{GroupMember, GroupPlan, ProxyGroup};
+use crate::subscription::SubscriptionNode;
+
+/// 正则参数的长度上限，避免客户端提交过大的表达式
+const MAX_PATTERN_LENGTH: usize = 200;
+/// 记录到订阅日志的筛选参数长度上限（与 subscriptions.filters 列一致）
+const MAX_SUMMARY_LENGTH: usize = 512;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum SortKey {
+    Name,
+    NodeClass,
+    Multiplier,
+}
+
+/// 订阅链接上的节点筛选参数（include/exclude/type/region/sort）
+#[derive(Debug, Default)]
+pub struct SubscriptionFilter {
+    include: Option<Regex>,
+    exclude: Option<Regex>,
+    types: Vec<String>,
+    regions: Vec<String>,
+    sort: Option<(SortKey, bool)>,
+    summary: Vec<String>,
+}
+
+/// 查询参数原值；空字符串视为未提供
+pub struct FilterParams<'a> {
+    pub include: Option<&'a str>,
+    pub exclude: Option<&'a str>,
+    pub node_type: Option<&'a str>,
+    pub region: Option<&'a str>,
+    pub sort: Option<&'a str>,
+}
+
+impl SubscriptionFilter {
+    pub fn parse(params: FilterParams<'_>) -> Result<Self, String> {
+        let mut filter = SubscriptionFilter::default();
+        if let Some(value) = non_empty(params.include) {
+            filter.include = Some(compile_pattern("include", value)?);
+            filter.summary.push(format!("include={value}"));
+        }
+        if let Some(value) = non_empty(params.exclude) {
+            filter.exclude = Some(compile_pattern("exclude", value)?);
+            filter.summary.push(format!("exclude={value}"));
+        }
+        if let Some(value) = non_empty(params.node_type) {
+            filter.types = split_list(value);
+            filter
+                .summary
+                .push(format!("type={}", filter.types.join(",")));
+        }
+        if let Some(value) = non_empty(params.region) {
+            filter.regions = split_list(value);
+            filter
+                .summary
+                .push(format!("region={}", filter.regions.join(",")));
+        }
+        if let Some(value) = non_empty(params.sort) {
+            let (name, descending) = match value.strip_prefix('-') {
+                Some(rest) => (rest, true),
+                None => (value, false),
+            };
+            let key = match name.to_lowercase().as_str() {
+                "name" => SortKey::Name,
+                "node_class" | "class" => SortKey::NodeClass,
+                "multiplier" | "traffic_multiplier" => SortKey::Multiplier,
+                _ => return Err("sort 仅支持 name、node_class、multiplier".to_string()),
+            };
+            filter.sort = Some((key, descending));
+            filter.summary.push(format!("sort={value}"));
+        }
+        Ok(filter)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.summary.is_empty()
+    }
+
+    /// 记录到订阅日志的筛选参数；未使用筛选时为 None
+    pub fn summary(&self) -> Option<String> {
+        if self.is_empty() {
+            return None;
+        }
+        Some(
+            self.summary
+                .join("&")
+                .chars()
+                .take(MAX_SUMMARY_LENGTH)
+                .collect(),
+        )
+    }
+
+    /// 按参数筛选并排序节点；`groups` 为订阅分组配置，`region` 按分组名称匹配
+    pub fn apply(
+        &self,
+        nodes: Vec<SubscriptionNode>,
+        groups: &[ProxyGroup],
+    ) -> Vec<SubscriptionNode> {
+        if self.is_empty() {
+            return nodes;
+        }
+        let region_nodes = if self.regions.is_empty() {
+            None
+        } else {
+            Some(self.region_node_ids(&nodes, groups))
+        };
+
+        let mut result = nodes
+            .into_iter()
+            .filter(|node| {
+                self.include
+                    .as_ref()
+                    .map(|regex| regex.is_match(&node.name))
+                    .unwrap_or(true)
+                    && !self
+                        .exclude
+                        .as_ref()
+                        .map(|regex| regex.is_match(&node.name))
+                        .unwrap_or(false)
+                    && (self.types.is_empty()
+                        || self
+                            .types
+                            .iter()
+                            .any(|value| value.eq_ignore_ascii_case(&node.node_type)))
+                    && region_nodes
+                        .as_ref()
+                        .map(|ids| ids.contains(&node.id))
+                        .unwrap_or(true)
+            })
+            .collect::<Vec<SubscriptionNode>>();
+
+        if let Some((key, descending)) = self.sort {
+            result.sort_by(|left, right| {
+                let ordering = match key {
+                    SortKey::Name => left.name.cmp(&right.name),
+                    SortKey::NodeClass => left.node_class.cmp(&right.node_class),
+                    SortKey::Multiplier => left
+                        .traffic_multiplier
+                        .partial_cmp(&right.traffic_multiplier)
+                        .unwrap_or(Ordering::Equal),
+                };
+                if descending {
+                    ordering.reverse()
+                } else {
+                    ordering
+                }
+            });
+        }
+        result
+    }
+
+    /// 名称包含任一 region 值的分组所匹配到的节点
+    fn region_node_ids(&self, nodes: &[SubscriptionNode], groups: &[ProxyGroup]) -> Vec<i64> {
+        let selected = groups
+            .iter()
+            .filter(|group| {
+                let name = group.name.to_lowercase();
+                self.regions
+                    .iter()
+                    .any(|value| name.contains(&value.to_lowercase()))
+            })
+            .cloned()
+            .collect::<Vec<ProxyGroup>>();
+        // 以节点 ID 作为成员名，避免同名节点被合并
+        let members = nodes
+            .iter()
+            .map(|node| GroupMember {
+                node_id: node.id,
+                node_name: node.name.clone(),
+                tags: &node.tags,
+                proxy_name: node.id.to_string(),
+            })
+            .collect::<Vec<GroupMember<'_>>>();
+        GroupPlan::new(&selected, &members)
+            .available()
+            .iter()
+            .flat_map(|(_, ids)| ids.iter().filter_map(|id| id.parse::<i64>().ok()))
+            .collect()
+    }
+}
+
+fn non_empty(value: Option<&str>) -> Option<&str> {
+    value.map(str::trim).filter(|value| !value.is_empty())
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    let mut items: Vec<String> = Vec::new();
+    for item in value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+    {
+        if !items
+            .iter()
+            .any(|existing| existing.eq_ignore_ascii_case(item))
+        {
+            items.push(item.to_string());
+        }
+    }
+    items
+}
+
+fn compile_pattern(label: &str, value: &str) -> Result<Regex, String> {
+    if value.chars().count() > MAX_PATTERN_LENGTH {
+        return Err(format!("{label} 不能超过 {MAX_PATTERN_LENGTH} 个字符"));
+    }
+    RegexBuilder::new(value)
+        .case_insensitive(true)
+        .size_limit(1 << 20)
+        .build()
+        .map_err(|_| format!("{label} 正则表达式无效"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy_groups::default_proxy_groups;
+    use serde_json::json;
+
+    fn node(id: i64, name: &str, node_type: &str, class: i64, multiplier: f64) -> SubscriptionNode {
+        SubscriptionNode {
+            id,
+            name: name.to_string(),
+            node_type: node_type.to_string(),
+            node_config: json!({}),
+            tags: Vec::new(),
+            node_class: class,
+            traffic_multiplier: multiplier,
+        }
+    }
+
+    fn sample_nodes() -> Vec<SubscriptionNode> {
+        vec![
+            node(1, "香港 IPLC 01", "vless", 2, 1.5),
+            node(2, "日本 Tokyo", "trojan", 1, 1.0),
+            node(3, "HK Backup", "vmess", 1, 0.5),
+            node(4, "美国 游戏", "vless", 3, 2.0),
+        ]
+    }
+
+    fn ids(nodes: &[SubscriptionNode]) -> Vec<i64> {
+        nodes.iter().map(|node| node.id).collect()
+    }
+
+    #[test]
+    fn filters_by_name_type_and_region() {
+        let groups = default_proxy_groups();
+        let filter = SubscriptionFilter::parse(FilterParams {
+            include: Some("iplc|backup"),
+            exclude: None,
+            node_type: Some("VLESS, vmess"),
+            region: None,
+            sort: Some("-multiplier"),
+        })
+        .unwrap();
+        assert_eq!(ids(&filter.apply(sample_nodes(), &groups)), vec![1, 3]);
+        assert_eq!(
+            filter.summary().as_deref(),
+            Some("include=iplc|backup&type=VLESS,vmess&sort=-multiplier")
+        );
+
+        let filter = SubscriptionFilter::parse(FilterParams {
+            include: None,
+            exclude: Some("游戏"),
+            node_type: None,
+            region: Some("香港,美国"),
+            sort: Some("name"),
+        })
+        .unwrap();
+        assert_eq!(ids(&filter.apply(sample_nodes(), &groups)), vec![3, 1]);
+    }
+
+    #[test]
+    fn rejects_invalid_parameters() {
+        let empty = FilterParams {
+            include: Some(" "),
+            exclude: None,
+            node_type: None,
+            region: None,
+            sort: None,
+        };
+        let filter = SubscriptionFilter::parse(empty).unwrap();
+        assert!(filter.is_empty() && filter.summary().is_none());
+        assert_eq!(ids(&filter.apply(sample_nodes(), &[])), vec![1, 2, 3, 4]);
+
+        assert!(SubscriptionFilter::parse(FilterParams {
+            include: Some("("),
+            exclude: None,
+            node_type: None,
+            region: None,
+            sort: None,
+        })
+        .is_err());
+        assert!(SubscriptionFilter::parse(FilterParams {
+            include: None,
+            exclude: None,
+            node_type: None,
+            region: None,
+            sort: Some("speed"),
+        })
+        .is_err());
+    }
+}
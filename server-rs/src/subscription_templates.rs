@@ -0,0 +1,241 @@
+use serde_json::Value;
+use sqlx::Row;
+
+use crate::cache::{cache_delete, cache_get, cache_set};
+use crate::proxy_groups::fetch_proxy_groups;
+use crate::state::AppState;
+use crate::subscription::SubscriptionTemplates;
+
+pub const SUBSCRIPTION_TEMPLATES_CACHE_KEY: &str = "subscription_templates";
+const SUBSCRIPTION_TEMPLATES_CACHE_TTL: u64 = 300;
+
+/// 可在后台编辑的模板类型；`clash_rules` 为 Clash 规则列表，与 Clash 基础配置分开维护
+pub const TEMPLATE_KINDS: [&str; 5] = ["clash", "clash_rules", "singbox", "surge", "xray"];
+
+/// Surge 模板必须包含的段落，节点与策略组分别替换 `{proxy_section}`、`{groups}` 占位符
+const SURGE_REQUIRED_SECTIONS: [&str; 4] = ["General", "Proxy", "Proxy Group", "Rule"];
+
+pub fn is_template_kind(value: &str) -> bool {
+    TEMPLATE_KINDS.contains(&value)
+}
+
+/// 编译内置的默认模板，未启用后台模板时使用
+pub fn builtin_template(kind: &str) -> Option<&'static str> {
+    match kind {
+        "clash" => Some(include_str!("templates/clashTemplate.json")),
+        "clash_rules" => Some(include_str!("templates/clashRules.json")),
+        "singbox" => Some(include_str!("templates/singboxTemplate.json")),
+        "surge" => Some(include_str!("templates/surgeTemplate.conf")),
+        "xray" => Some(include_str!("templates/xrayTemplate.json")),
+        _ => None,
+    }
+}
+
+/// 模板类型对应的订阅格式（预览时按该格式渲染）
+pub fn template_format(kind: &str) -> &'static str {
+    match kind {
+        "singbox" => "singbox",
+        "surge" => "surge",
+        "xray" => "xray",
+        _ => "clash",
+    }
+}
+
+/// 校验模板内容并写入对应字段，错误信息可直接返回给管理员
+pub fn apply_template(
+    templates: &mut SubscriptionTemplates,
+    kind: &str,
+    content: &str,
+) -> Result<(), String> {
+    if content.trim().is_empty() {
+        return Err("模板内容不能为空".to_string());
+    }
+    match kind {
+        "clash" => {
+            // YAML 是 JSON 的超集，两种写法都按 YAML 解析
+            let value = serde_yaml::from_str::<Value>(content)
+                .map_err(|err| format!("Clash 模板解析失败：{err}"))?;
+            if !value.is_object() {
+                return Err("Clash 模板必须是键值对象".to_string());
+            }
+            templates.clash = Some(value);
+        }
+        "clash_rules" => templates.clash_rules = Some(parse_clash_rules(content)?),
+        "singbox" => {
+            let value = parse_json_object(content, "sing-box")?;
+            if !value.get("outbounds").map(Value::is_array).unwrap_or(false) {
+                return Err("sing-box 模板缺少 outbounds 数组".to_string());
+            }
+            templates.singbox = Some(value);
+        }
+        "xray" => {
+            let value = parse_json_object(content, "Xray")?;
+            if value
+                .get("outbounds")
+                .map(|item| !item.is_array())
+                .unwrap_or(false)
+            {
+                return Err("Xray 模板的 outbounds 必须是数组".to_string());
+            }
+            templates.xray = Some(value);
+        }
+        "surge" => {
+            validate_surge_template(content)?;
+            templates.surge = Some(content.to_string());
+        }
+        _ => return Err("不支持的模板类型".to_string()),
+    }
+    Ok(())
+}
+
+fn parse_json_object(content: &str, label: &str) -> Result<Value, String> {
+    let value = serde_json::from_str::<Value>(content)
+        .map_err(|err| format!("{label} 模板 JSON 解析失败：{err}"))?;
+    if !value.is_object() {
+        return Err(format!("{label} 模板必须是 JSON 对象"));
+    }
+    Ok(value)
+}
+
+/// 规则列表支持 JSON/YAML 数组，每条规则至少包含类型与策略两段
+fn parse_clash_rules(content: &str) -> Result<Vec<Value>, String> {
+    let rules = serde_yaml::from_str::<Vec<String>>(content)
+        .map_err(|err| format!("Clash 规则必须是字符串数组：{err}"))?;
+    if rules.is_empty() {
+        return Err("Clash 规则不能为空".to_string());
+    }
+    for (index, rule) in rules.iter().enumerate() {
+        let parts = rule.split(',').map(str::trim).collect::<Vec<&str>>();
+        if parts.len() < 2 || parts.iter().take(2).any(|part| part.is_empty()) {
+            return Err(format!("第 {} 条规则格式无效：{rule}", index + 1));
+        }
+    }
+    Ok(rules.into_iter().map(Value::String).collect())
+}
+
+fn validate_surge_template(content: &str) -> Result<(), String> {
+    let mut sections: Vec<String> = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if !line.starts_with('[') {
+            continue;
+        }
+        if !line.ends_with(']') || line.len() < 3 {
+            return Err(format!("第 {} 行段落标题格式无效：{line}", index + 1));
+        }
+        let name = line[1..line.len() - 1].trim().to_string();
+        if sections.contains(&name) {
+            return Err(format!("段落 [{name}] 重复"));
+        }
+        sections.push(name);
+    }
+    for required in SURGE_REQUIRED_SECTIONS {
+        if !sections.iter().any(|name| name == required) {
+            return Err(format!("Surge 模板缺少 [{required}] 段落"));
+        }
+    }
+    for placeholder in ["{proxy_section}", "{groups}"] {
+        if !content.contains(placeholder) {
+            return Err(format!("Surge 模板缺少 {placeholder} 占位符"));
+        }
+    }
+    Ok(())
+}
+
+/// 读取各类型当前启用的模板及订阅分组；读取失败或内容无效时回退到内置模板/内置分组
+pub async fn load_active_templates(state: &AppState) -> SubscriptionTemplates {
+    if let Some(cached) = cache_get(state, SUBSCRIPTION_TEMPLATES_CACHE_KEY).await {
+        if let Ok(templates) = serde_json::from_str::<SubscriptionTemplates>(&cached) {
+            return templates;
+        }
+    }
+
+    let rows = match sqlx::query(
+        "SELECT id, kind, content FROM subscription_templates WHERE is_active = 1",
+    )
+    .fetch_all(&state.db)
+    .await
+    {
+        Ok(rows) => rows,
+        Err(err) => {
+            tracing::warn!("[subscription] load templates failed: {err}");
+            return SubscriptionTemplates::default();
+        }
+    };
+
+    let mut templates = SubscriptionTemplates::default();
+    for row in rows {
+        let id = row.try_get::<i64, _>("id").unwrap_or(0);
+        let kind = row
+            .try_get::<Option<String>, _>("kind")
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+        let content = row
+            .try_get::<Option<String>, _>("content")
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+        if let Err(message) = apply_template(&mut templates, &kind, &content) {
+            tracing::warn!("[subscription] template #{id} ({kind}) ignored: {message}");
+        }
+    }
+    match fetch_proxy_groups(state).await {
+        Ok(groups) => templates.proxy_groups = Some(groups),
+        Err(err) => tracing::warn!("[subscription] load proxy groups failed: {err}"),
+    }
+
+    cache_set(
+        state,
+        SUBSCRIPTION_TEMPLATES_CACHE_KEY,
+        &serde_json::to_string(&templates).unwrap_or_default(),
+        SUBSCRIPTION_TEMPLATES_CACHE_TTL,
+    )
+    .await;
+    templates
+}
+
+pub async fn invalidate_templates(state: &AppState) {
+    cache_delete(state, SUBSCRIPTION_TEMPLATES_CACHE_KEY).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_templates_pass_validation() {
+        let mut templates = SubscriptionTemplates::default();
+        for kind in TEMPLATE_KINDS {
+            let content = builtin_template(kind).unwrap();
+            assert!(
+                apply_template(&mut templates, kind, content).is_ok(),
+                "{kind}"
+            );
+        }
+        assert!(templates.clash.is_some() && templates.surge.is_some());
+    }
+
+    #[test]
+    fn invalid_templates_are_rejected() {
+        let mut templates = SubscriptionTemplates::default();
+        assert!(apply_template(
+            &mut templates,
+            "clash",
+            "mixed-port: 7890\ndns:\n  enable: true\n"
+        )
+        .is_ok());
+        assert!(apply_template(&mut templates, "clash", "- a\n- b\n").is_err());
+        assert!(apply_template(&mut templates, "clash_rules", "- MATCH,🐟 漏网之鱼\n").is_ok());
+        assert!(apply_template(&mut templates, "clash_rules", "[\"MATCH\"]").is_err());
+        assert!(apply_template(&mut templates, "singbox", "{\"dns\": {}}").is_err());
+        assert!(apply_template(&mut templates, "xray", "{\"outbounds\": {}}").is_err());
+        assert!(apply_template(
+            &mut templates,
+            "surge",
+            "[General]\n[Proxy]\n{proxy_section}\n[Rule]\n"
+        )
+        .is_err());
+        assert!(apply_template(&mut templates, "unknown", "{}").is_err());
+    }
+}
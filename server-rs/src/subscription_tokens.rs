@@ -0,0 +1,168 @@
+use chrono::{Local, NaiveDateTime};
+use serde_json::Value;
+use sqlx::Row;
+
+use crate::state::AppState;
+use crate::subscription_ua::is_supported_format;
+
+/// 每个用户同时有效的订阅 token 上限（不含 users.token 旧链接）
+pub const MAX_ACTIVE_TOKENS: i64 = 10;
+pub const SUBSCRIPTION_TOKEN_LENGTH: usize = 32;
+pub const MAX_TOKEN_LABEL_LENGTH: usize = 64;
+
+/// 订阅请求所用 token 的解析结果；`token_id` 为空表示使用 users.token 旧链接
+pub struct TokenAccess {
+    pub user_id: i64,
+    pub token_id: Option<i64>,
+    pub allowed_formats: Vec<String>,
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+impl TokenAccess {
+    pub fn is_expired(&self, now: NaiveDateTime) -> bool {
+        self.expires_at.map(|value| value <= now).unwrap_or(false)
+    }
+
+    /// 未限制格式时允许全部格式
+    pub fn allows(&self, format: &str) -> bool {
+        self.allowed_formats.is_empty() || self.allowed_formats.iter().any(|item| item == format)
+    }
+}
+
+/// 读取 `allowed_formats` 列（JSON 字符串数组），为空表示不限制
+pub fn parse_allowed_formats(raw: Option<&str>) -> Vec<String> {
+    raw.and_then(|value| serde_json::from_str::<Vec<String>>(value).ok())
+        .unwrap_or_default()
+}
+
+/// 规范化用户提交的允许格式：支持数组或逗号分隔字符串，必须是已支持的订阅格式
+pub fn normalize_allowed_formats(value: &Value) -> Result<Vec<String>, String> {
+    let raw: Vec<String> = match value {
+        Value::Null => Vec::new(),
+        Value::String(text) => text.split(',').map(str::to_string).collect(),
+        Value::Array(items) => items
+            .iter()
+            .map(|item| {
+                item.as_str()
+                    .map(str::to_string)
+                    .ok_or_else(|| "订阅格式必须是字符串".to_string())
+            })
+            .collect::<Result<Vec<String>, String>>()?,
+        _ => return Err("订阅格式列表格式无效".to_string()),
+    };
+    let mut formats: Vec<String> = Vec::new();
+    for item in raw {
+        let format = item.trim().to_lowercase();
+        if format.is_empty() || formats.contains(&format) {
+            continue;
+        }
+        if !is_supported_format(&format) {
+            return Err(format!("不支持的订阅格式：{format}"));
+        }
+        formats.push(format);
+    }
+    Ok(formats)
+}
+
+/// 按 token 查找订阅所属用户：优先匹配未撤销的多 token 记录，其次兼容 users.token
+pub async fn resolve_subscription_token(
+    state: &AppState,
+    token: &str,
+) -> Result<Option<TokenAccess>, String> {
+    let row = sqlx::query(
+        r#"
+    SELECT id, user_id, CAST(allowed_formats AS CHAR) AS allowed_formats, expires_at
+    FROM subscription_tokens
+    WHERE token = ? AND revoked_at IS NULL
+    "#,
+    )
+    .bind(token)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|err| err.to_string())?;
+    if let Some(row) = row {
+        return Ok(Some(TokenAccess {
+            user_id: row.try_get::<i64, _>("user_id").unwrap_or(0),
+            token_id: Some(row.try_get::<i64, _>("id").unwrap_or(0)),
+            allowed_formats: parse_allowed_formats(
+                row.try_get::<Option<String>, _>("allowed_formats")
+                    .ok()
+                    .flatten()
+                    .as_deref(),
+            ),
+            expires_at: row
+                .try_get::<Option<NaiveDateTime>, _>("expires_at")
+                .ok()
+                .flatten(),
+        }));
+    }
+
+    let row = sqlx::query("SELECT id FROM users WHERE token = ?")
+        .bind(token)
+        .fetch_optional(&state.db)
+        .await
+        .map_err(|err| err.to_string())?;
+    Ok(row.map(|row| TokenAccess {
+        user_id: row.try_get::<i64, _>("id").unwrap_or(0),
+        token_id: None,
+        allowed_formats: Vec::new(),
+        expires_at: None,
+    }))
+}
+
+/// 记录 token 最近一次使用的时间与 IP
+pub async fn touch_subscription_token(state: &AppState, token_id: i64, ip: &str) {
+    let result =
+        sqlx::query("UPDATE subscription_tokens SET last_used_at = ?, last_ip = ? WHERE id = ?")
+            .bind(Local::now().naive_local())
+            .bind(ip)
+            .bind(token_id)
+            .execute(&state.db)
+            .await;
+    if let Err(err) = result {
+        tracing::warn!("[subscription] touch token #{token_id} failed: {err}");
+    }
+}
+
+pub async fn count_active_tokens(state: &AppState, user_id: i64) -> Result<i64, String> {
+    let row = sqlx::query(
+        r#"
+    SELECT COUNT(*) AS total
+    FROM subscription_tokens
+    WHERE user_id = ? AND revoked_at IS NULL
+      AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)
+    "#,
+    )
+    .bind(user_id)
+    .fetch_one(&state.db)
+    .await
+    .map_err(|err| err.to_string())?;
+    Ok(row.try_get::<i64, _>("total").unwrap_or(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn normalizes_allowed_formats() {
+        assert_eq!(
+            normalize_allowed_formats(&json!("Clash, singbox,clash")).unwrap(),
+            vec!["clash", "singbox"]
+        );
+        assert!(normalize_allowed_formats(&json!(null)).unwrap().is_empty());
+        assert!(normalize_allowed_formats(&json!(["clash", "auto"])).is_err());
+        assert!(normalize_allowed_formats(&json!([1])).is_err());
+
+        let now = Local::now().naive_local();
+        let access = TokenAccess {
+            user_id: 1,
+            token_id: Some(2),
+            allowed_formats: vec!["clash".to_string()],
+            expires_at: Some(now - chrono::Duration::minutes(1)),
+        };
+        assert!(access.allows("clash") && !access.allows("surge"));
+        assert!(access.is_expired(now));
+    }
+}
@@ -0,0 +1,146 @@
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+
+use crate::cache::{cache_delete, cache_get, cache_set};
+use crate::state::AppState;
+
+pub const SUBSCRIPTION_UA_RULES_CACHE_KEY: &str = "subscription_ua_rules";
+const SUBSCRIPTION_UA_RULES_CACHE_TTL: u64 = 300;
+/// 未配置回退格式时使用通用分享链接
+const DEFAULT_FALLBACK_FORMAT: &str = "v2ray";
+
+/// 可被自动识别分发的订阅格式
+pub const SUBSCRIPTION_FORMATS: [&str; 7] = [
+    "v2ray",
+    "clash",
+    "quantumultx",
+    "singbox",
+    "shadowrocket",
+    "surge",
+    "xray",
+];
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UaRule {
+    pub id: i64,
+    pub pattern: String,
+    pub format: String,
+}
+
+pub fn is_supported_format(value: &str) -> bool {
+    SUBSCRIPTION_FORMATS.contains(&value)
+}
+
+/// 读取启用的 UA 映射规则，按优先级从高到低排列
+pub async fn load_ua_rules(state: &AppState) -> Result<Vec<UaRule>, String> {
+    if let Some(cached) = cache_get(state, SUBSCRIPTION_UA_RULES_CACHE_KEY).await {
+        if let Ok(rules) = serde_json::from_str::<Vec<UaRule>>(&cached) {
+            return Ok(rules);
+        }
+    }
+
+    let rows = sqlx::query(
+        r#"
+    SELECT id, pattern, format
+    FROM subscription_ua_rules
+    WHERE enabled = 1
+    ORDER BY priority DESC, id ASC
+    "#,
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|err| err.to_string())?;
+
+    let rules = rows
+        .into_iter()
+        .map(|row| UaRule {
+            id: row.try_get::<i64, _>("id").unwrap_or(0),
+            pattern: row
+                .try_get::<Option<String>, _>("pattern")
+                .ok()
+                .flatten()
+                .unwrap_or_default()
+                .trim()
+                .to_lowercase(),
+            format: row
+                .try_get::<Option<String>, _>("format")
+                .ok()
+                .flatten()
+                .unwrap_or_default(),
+        })
+        .filter(|rule| !rule.pattern.is_empty() && is_supported_format(&rule.format))
+        .collect::<Vec<UaRule>>();
+
+    cache_set(
+        state,
+        SUBSCRIPTION_UA_RULES_CACHE_KEY,
+        &serde_json::to_string(&rules).unwrap_or_default(),
+        SUBSCRIPTION_UA_RULES_CACHE_TTL,
+    )
+    .await;
+    Ok(rules)
+}
+
+pub async fn invalidate_ua_rules(state: &AppState) {
+    cache_delete(state, SUBSCRIPTION_UA_RULES_CACHE_KEY).await;
+}
+
+/// 读取 `subscription_auto_fallback` 配置的回退格式
+pub async fn load_fallback_format(state: &AppState) -> String {
+    let value = sqlx::query(
+        "SELECT `value` FROM system_configs WHERE `key` = 'subscription_auto_fallback' LIMIT 1",
+    )
+    .fetch_optional(&state.db)
+    .await
+    .ok()
+    .flatten()
+    .and_then(|row| row.try_get::<Option<String>, _>("value").ok().flatten())
+    .map(|value| value.trim().to_lowercase())
+    .unwrap_or_default();
+    if is_supported_format(&value) {
+        value
+    } else {
+        DEFAULT_FALLBACK_FORMAT.to_string()
+    }
+}
+
+/// 按 UA 关键字（不区分大小写）匹配第一条规则
+pub fn match_ua_rule<'a>(user_agent: &str, rules: &'a [UaRule]) -> Option<&'a UaRule> {
+    let user_agent = user_agent.trim().to_lowercase();
+    if user_agent.is_empty() {
+        return None;
+    }
+    rules
+        .iter()
+        .find(|rule| user_agent.contains(rule.pattern.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ua_rules_match_in_priority_order() {
+        let rule = |id, pattern: &str, format: &str| UaRule {
+            id,
+            pattern: pattern.to_string(),
+            format: format.to_string(),
+        };
+        let rules = vec![
+            rule(1, "clash-verge", "clash"),
+            rule(2, "v2rayng", "v2ray"),
+            rule(3, "sfa/", "singbox"),
+            rule(4, "clash", "clash"),
+        ];
+        assert_eq!(
+            match_ua_rule("clash-verge/v2.0.3", &rules).map(|r| r.id),
+            Some(1)
+        );
+        assert_eq!(
+            match_ua_rule("SFA/1.11.4 (Android)", &rules).map(|r| r.format.as_str()),
+            Some("singbox")
+        );
+        assert_eq!(match_ua_rule("curl/8.0", &rules).map(|r| r.id), None);
+        assert_eq!(match_ua_rule("", &rules).map(|r| r.id), None);
+    }
+}
@@ -0,0 +1,314 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+use sqlx::{MySqlConnection, Row};
+
+use crate::state::AppState;
+
+const DEFAULT_MAX_MBPS: i64 = 10_000;
+const DEFAULT_GRACE_MINUTES: i64 = 10;
+/// 计算吞吐上限时的最短统计间隔，避免上报过于频繁时误判
+const MIN_INTERVAL_SECONDS: i64 = 60;
+/// 节点首次上报（无上次上报时间）时假定的统计间隔
+const DEFAULT_INTERVAL_SECONDS: i64 = 300;
+
+/// 流量上报被隔离的原因
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnomalyReason {
+    /// 单次上报流量超过节点带宽在统计间隔内的理论上限
+    Throughput,
+    /// 用户不存在、已停用或等级不足以使用该节点
+    NotEligible,
+    /// 节点已禁用仍在上报
+    NodeDisabled,
+}
+
+impl AnomalyReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Throughput => "throughput",
+            Self::NotEligible => "not_eligible",
+            Self::NodeDisabled => "node_disabled",
+        }
+    }
+}
+
+pub struct AnomalyConfig {
+    pub enabled: bool,
+    pub max_mbps: i64,
+    pub grace_minutes: i64,
+}
+
+/// 本次上报所属节点的状态，用于逐条判断
+pub struct ReportContext {
+    pub node_enabled: bool,
+    pub node_class: i64,
+    pub interval_seconds: i64,
+}
+
+/// 上报用户的当前状态；`recently_changed` 表示账户或等级在宽限期内刚到期
+pub struct ReportedUser {
+    pub status: i64,
+    pub class_level: i64,
+    pub recently_changed: bool,
+}
+
+pub async fn load_config(state: &AppState) -> Result<AnomalyConfig, String> {
+    let rows = sqlx::query(
+        r#"
+    SELECT `key`, `value` FROM system_configs
+    WHERE `key` IN (
+      'traffic_anomaly_enabled',
+      'traffic_anomaly_max_mbps',
+      'traffic_anomaly_grace_minutes'
+    )
+    "#,
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|err| err.to_string())?;
+
+    let mut map: HashMap<String, String> = HashMap::new();
+    for row in rows {
+        let key = row
+            .try_get::<Option<String>, _>("key")
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+        let value = row
+            .try_get::<Option<String>, _>("value")
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+        map.insert(key, value.trim().to_string());
+    }
+    let read_positive = |key: &str, default: i64| {
+        map.get(key)
+            .and_then(|value| value.parse::<i64>().ok())
+            .filter(|value| *value > 0)
+            .unwrap_or(default)
+    };
+
+    Ok(AnomalyConfig {
+        enabled: map
+            .get("traffic_anomaly_enabled")
+            .map(|value| value != "0")
+            .unwrap_or(true),
+        max_mbps: read_positive("traffic_anomaly_max_mbps", DEFAULT_MAX_MBPS),
+        grace_minutes: read_positive("traffic_anomaly_grace_minutes", DEFAULT_GRACE_MINUTES),
+    })
+}
+
+/// 距上次上报的秒数换算为统计间隔：首次上报取默认值，过短时取最小值
+pub fn report_interval_seconds(seconds_since_last: Option<i64>) -> i64 {
+    seconds_since_last
+        .unwrap_or(DEFAULT_INTERVAL_SECONDS)
+        .max(MIN_INTERVAL_SECONDS)
+}
+
+/// 统计间隔内按 `max_mbps` 满速传输可产生的最大字节数
+pub fn max_bytes_for_interval(max_mbps: i64, interval_seconds: i64) -> i64 {
+    // 1 Mbps = 125,000 字节/秒
+    max_mbps
+        .max(0)
+        .saturating_mul(125_000)
+        .saturating_mul(interval_seconds.max(0))
+}
+
+/// 判断单条用户流量是否可疑；`None` 表示正常计费
+pub fn classify_report(
+    config: &AnomalyConfig,
+    context: &ReportContext,
+    user: Option<&ReportedUser>,
+    total_bytes: i64,
+) -> Option<AnomalyReason> {
+    if !config.enabled {
+        return None;
+    }
+    if !context.node_enabled {
+        return Some(AnomalyReason::NodeDisabled);
+    }
+    if total_bytes > max_bytes_for_interval(config.max_mbps, context.interval_seconds) {
+        return Some(AnomalyReason::Throughput);
+    }
+    match user {
+        None => Some(AnomalyReason::NotEligible),
+        Some(user) if user.recently_changed => None,
+        Some(user) if user.status != 1 || user.class_level < context.node_class => {
+            Some(AnomalyReason::NotEligible)
+        }
+        Some(_) => None,
+    }
+}
+
+/// 一条待计费的用户流量（上传/下载为节点上报的原始值）
+pub struct TrafficCharge {
+    pub user_id: i64,
+    pub node_id: i64,
+    pub upload: i64,
+    pub download: i64,
+    pub multiplier: f64,
+    pub date: NaiveDate,
+}
+
+/// 累加用户流量并写入 traffic_logs，返回按倍率计算后的扣费流量
+pub async fn charge_user_traffic(
+    conn: &mut MySqlConnection,
+    charge: &TrafficCharge,
+) -> Result<i64, String> {
+    let actual_upload = ((charge.upload as f64) * charge.multiplier)
+        .round()
+        .max(0.0) as i64;
+    let actual_download = ((charge.download as f64) * charge.multiplier)
+        .round()
+        .max(0.0) as i64;
+    let deducted_total = actual_upload.saturating_add(actual_download);
+
+    sqlx::query(
+        r#"
+      UPDATE users
+      SET upload_traffic = upload_traffic + ?,
+          download_traffic = download_traffic + ?,
+          upload_today = upload_today + ?,
+          download_today = download_today + ?,
+          transfer_total = transfer_total + ?,
+          updated_at = CURRENT_TIMESTAMP
+      WHERE id = ?
+      "#,
+    )
+    .bind(charge.upload)
+    .bind(charge.download)
+    .bind(charge.upload)
+    .bind(charge.download)
+    .bind(deducted_total)
+    .bind(charge.user_id)
+    .execute(&mut *conn)
+    .await
+    .map_err(|err| err.to_string())?;
+
+    sqlx::query(
+      r#"
+      INSERT INTO traffic_logs
+      (user_id, node_id, upload_traffic, download_traffic, actual_upload_traffic, actual_download_traffic, actual_traffic, deduction_multiplier, date, created_at)
+      VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP)
+      "#
+    )
+    .bind(charge.user_id)
+    .bind(charge.node_id)
+    .bind(charge.upload)
+    .bind(charge.download)
+    .bind(actual_upload)
+    .bind(actual_download)
+    .bind(deducted_total)
+    .bind(charge.multiplier)
+    .bind(charge.date)
+    .execute(&mut *conn)
+    .await
+    .map_err(|err| err.to_string())?;
+
+    Ok(deducted_total)
+}
+
+/// 可疑流量写入待审核表，不计入用户与节点流量
+pub async fn quarantine_traffic(
+    conn: &mut MySqlConnection,
+    charge: &TrafficCharge,
+    reason: AnomalyReason,
+    interval_seconds: i64,
+) -> Result<(), String> {
+    sqlx::query(
+        r#"
+    INSERT INTO traffic_anomalies
+      (node_id, user_id, upload_traffic, download_traffic, deduction_multiplier, reason,
+       interval_seconds, date, status, created_at)
+    VALUES (?, ?, ?, ?, ?, ?, ?, ?, 'pending', CURRENT_TIMESTAMP)
+    "#,
+    )
+    .bind(charge.node_id)
+    .bind(charge.user_id)
+    .bind(charge.upload)
+    .bind(charge.download)
+    .bind(charge.multiplier)
+    .bind(reason.as_str())
+    .bind(interval_seconds)
+    .bind(charge.date)
+    .execute(&mut *conn)
+    .await
+    .map_err(|err| err.to_string())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_are_classified_by_throughput_and_eligibility() {
+        let config = AnomalyConfig {
+            enabled: true,
+            max_mbps: 1000,
+            grace_minutes: 10,
+        };
+        let context = ReportContext {
+            node_enabled: true,
+            node_class: 2,
+            interval_seconds: report_interval_seconds(Some(10)),
+        };
+        assert_eq!(context.interval_seconds, MIN_INTERVAL_SECONDS);
+        assert_eq!(report_interval_seconds(None), DEFAULT_INTERVAL_SECONDS);
+        // 1000 Mbps × 60 s = 7.5 GB
+        let limit = max_bytes_for_interval(1000, 60);
+        assert_eq!(limit, 7_500_000_000);
+
+        let user = ReportedUser {
+            status: 1,
+            class_level: 2,
+            recently_changed: false,
+        };
+        assert_eq!(classify_report(&config, &context, Some(&user), limit), None);
+        assert_eq!(
+            classify_report(&config, &context, Some(&user), limit + 1),
+            Some(AnomalyReason::Throughput)
+        );
+        assert_eq!(
+            classify_report(&config, &context, None, 1024),
+            Some(AnomalyReason::NotEligible)
+        );
+
+        let low_class = ReportedUser {
+            status: 1,
+            class_level: 1,
+            recently_changed: false,
+        };
+        assert_eq!(
+            classify_report(&config, &context, Some(&low_class), 1024),
+            Some(AnomalyReason::NotEligible)
+        );
+        let just_expired = ReportedUser {
+            status: 0,
+            class_level: 0,
+            recently_changed: true,
+        };
+        assert_eq!(
+            classify_report(&config, &context, Some(&just_expired), 1024),
+            None
+        );
+
+        let disabled_node = ReportContext {
+            node_enabled: false,
+            ..context
+        };
+        assert_eq!(
+            classify_report(&config, &disabled_node, Some(&user), 1024),
+            Some(AnomalyReason::NodeDisabled)
+        );
+        let disabled = AnomalyConfig {
+            enabled: false,
+            ..config
+        };
+        assert_eq!(
+            classify_report(&disabled, &disabled_node, None, i64::MAX),
+            None
+        );
+    }
+}